@@ -0,0 +1,116 @@
+-- Users, role permissions, grants, sessions, MFA, matter access lists and ethical walls.
+-- Created by AccessController when it first touches the database.
+
+-- Legal access control: users, role permissions, expiring grants and hashed session tokens
+CREATE TABLE IF NOT EXISTS access_users (
+  user_id TEXT PRIMARY KEY,
+  email TEXT NOT NULL,
+  role TEXT NOT NULL,
+  bar_number TEXT,
+  jurisdiction TEXT,
+  supervisor_id TEXT,
+  permissions TEXT NOT NULL DEFAULT '[]', -- JSON array of operation names
+  created_at DATETIME NOT NULL,
+  last_login DATETIME,
+  active BOOLEAN NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS access_role_permissions (
+  role TEXT NOT NULL,
+  operation_type TEXT NOT NULL,
+  PRIMARY KEY (role, operation_type)
+);
+
+CREATE TABLE IF NOT EXISTS access_grants (
+  grant_id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  operation_type TEXT NOT NULL,
+  granted_by TEXT NOT NULL,
+  granted_at DATETIME NOT NULL,
+  expires_at DATETIME NOT NULL,
+  revoked_at DATETIME,
+  revoked_by TEXT,
+  expired_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_grants_user ON access_grants(user_id, operation_type);
+
+CREATE TABLE IF NOT EXISTS access_sessions (
+  token_hash TEXT PRIMARY KEY, -- SHA-256 of the bearer token
+  user_id TEXT NOT NULL,
+  created_at DATETIME NOT NULL,
+  expires_at DATETIME NOT NULL,
+  revoked_at DATETIME
+);
+
+-- Single sign-on identities linked to access users
+CREATE TABLE IF NOT EXISTS access_identities (
+  issuer TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  linked_at DATETIME NOT NULL,
+  PRIMARY KEY (issuer, subject)
+);
+
+-- TOTP enrollment per user; the secret itself is kept in the KMS
+CREATE TABLE IF NOT EXISTS access_mfa (
+  user_id TEXT PRIMARY KEY,
+  enrolled_at DATETIME NOT NULL,
+  confirmed_at DATETIME,
+  last_step INTEGER NOT NULL DEFAULT 0,
+  failed_attempts INTEGER NOT NULL DEFAULT 0,
+  locked_until DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS access_recovery_codes (
+  user_id TEXT NOT NULL,
+  code_hash TEXT NOT NULL, -- SHA-256 of the normalized code
+  created_at DATETIME NOT NULL,
+  used_at DATETIME,
+  PRIMARY KEY (user_id, code_hash)
+);
+
+-- Sessions that passed MFA, and when (for step-up)
+CREATE TABLE IF NOT EXISTS access_mfa_sessions (
+  token_hash TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  verified_at DATETIME NOT NULL
+);
+
+-- Matter access lists and ethical walls screening users from matters
+CREATE TABLE IF NOT EXISTS matter_access (
+  case_id INTEGER NOT NULL,
+  user_id TEXT NOT NULL,
+  granted_by TEXT NOT NULL,
+  granted_at DATETIME NOT NULL,
+  PRIMARY KEY (case_id, user_id)
+);
+
+-- Matters that have ever had an access list; emptying the list doesn't reopen them
+CREATE TABLE IF NOT EXISTS matter_restrictions (
+  case_id INTEGER PRIMARY KEY,
+  restricted_by TEXT NOT NULL,
+  restricted_at DATETIME NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ethical_walls (
+  wall_id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  reason TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  created_at DATETIME NOT NULL,
+  lifted_at DATETIME,
+  lifted_by TEXT
+);
+
+CREATE TABLE IF NOT EXISTS ethical_wall_members (
+  wall_id TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  PRIMARY KEY (wall_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS ethical_wall_matters (
+  wall_id TEXT NOT NULL,
+  case_id INTEGER NOT NULL,
+  PRIMARY KEY (wall_id, case_id)
+);
@@ -0,0 +1,22 @@
+-- Parties to each matter and the conflict-of-interest reports screened against them.
+-- Created by ConflictChecker when it first touches the database.
+
+CREATE TABLE IF NOT EXISTS matter_parties (
+  id INTEGER PRIMARY KEY,
+  case_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  role TEXT NOT NULL, -- Client, AdverseParty, OpposingCounsel, RelatedParty, Witness
+  created_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_matter_parties_case ON matter_parties(case_id);
+
+CREATE TABLE IF NOT EXISTS conflict_reports (
+  report_id TEXT PRIMARY KEY,
+  case_title TEXT NOT NULL,
+  requested_by TEXT NOT NULL,
+  status TEXT NOT NULL, -- NoConflicts, PendingReview, Cleared, Waived, Declined
+  report TEXT NOT NULL, -- JSON ConflictReport
+  created_at DATETIME NOT NULL,
+  reviewed_at DATETIME
+);
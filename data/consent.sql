@@ -0,0 +1,32 @@
+-- Published consent form versions and the append-only ledger of consents given against them.
+-- Created by ConsentManager when it first touches the database.
+
+CREATE TABLE IF NOT EXISTS consent_forms (
+  operation_type TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  consent_text TEXT NOT NULL,
+  disclaimer_text TEXT NOT NULL,
+  acknowledgements TEXT NOT NULL, -- JSON array
+  disclaimer_hash TEXT NOT NULL,
+  form_hash TEXT NOT NULL,
+  published_at DATETIME NOT NULL,
+  PRIMARY KEY (operation_type, version)
+);
+
+CREATE TABLE IF NOT EXISTS consent_ledger (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id TEXT NOT NULL,
+  operation_type TEXT NOT NULL,
+  action TEXT NOT NULL, -- 'granted', 'declined' or 'withdrawn'
+  form_version INTEGER NOT NULL,
+  form_hash TEXT NOT NULL,
+  disclaimer_hash TEXT NOT NULL,
+  attorney_supervised INTEGER NOT NULL,
+  disclaimer_acknowledged INTEGER NOT NULL,
+  ip_address TEXT,
+  user_agent TEXT,
+  reason TEXT,
+  recorded_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_consent_ledger_user ON consent_ledger(user_id, operation_type, id);
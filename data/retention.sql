@@ -0,0 +1,56 @@
+-- Retention schedule, legal holds and disposition certificates.
+-- Created by RetentionScheduler when it opens the database.
+
+CREATE TABLE IF NOT EXISTS retention_schedule (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  record_kind TEXT NOT NULL, -- file, exhibit
+  record_id TEXT NOT NULL,
+  case_id INTEGER,
+  data_classification TEXT NOT NULL,
+  retention_days INTEGER NOT NULL,
+  retain_from DATETIME NOT NULL,
+  disposition_date DATETIME NOT NULL,
+  status TEXT NOT NULL, -- scheduled, pending_review, approved, destroyed
+  queued_at DATETIME,
+  reviewed_by TEXT,
+  reviewed_at DATETIME,
+  review_notes TEXT,
+  UNIQUE (record_kind, record_id)
+);
+
+CREATE TABLE IF NOT EXISTS legal_holds (
+  hold_id TEXT PRIMARY KEY,
+  case_id INTEGER NOT NULL,
+  reason TEXT NOT NULL,
+  placed_by TEXT NOT NULL,
+  placed_at DATETIME NOT NULL,
+  released_by TEXT,
+  released_at DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS disposition_certificates (
+  certificate_id TEXT PRIMARY KEY,
+  schedule_id INTEGER NOT NULL,
+  record_kind TEXT NOT NULL,
+  record_id TEXT NOT NULL,
+  case_id INTEGER,
+  data_classification TEXT NOT NULL,
+  retention_days INTEGER NOT NULL,
+  disposition_date DATETIME NOT NULL,
+  approved_by TEXT NOT NULL,
+  approved_at DATETIME NOT NULL,
+  destroyed_by TEXT NOT NULL,
+  destroyed_at DATETIME NOT NULL,
+  method TEXT NOT NULL,
+  content_hash TEXT,
+  certificate_hash TEXT NOT NULL -- SHA-256 over the other fields
+);
+
+CREATE TABLE IF NOT EXISTS matter_files (
+  file_path TEXT PRIMARY KEY, -- canonical path under the import root
+  case_id INTEGER NOT NULL,
+  imported_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_schedule_due ON retention_schedule(status, disposition_date);
+CREATE INDEX IF NOT EXISTS idx_legal_holds_case ON legal_holds(case_id, released_at);
@@ -114,24 +114,149 @@ CREATE TABLE IF NOT EXISTS legal_patterns (
   created_at TEXT DEFAULT CURRENT_TIMESTAMP
 );
 
--- Add AI metadata columns to existing tables
-ALTER TABLE placement_denials ADD COLUMN ai_risk_score REAL DEFAULT 0.0;
-ALTER TABLE placement_denials ADD COLUMN ai_analysis TEXT; -- JSON AI analysis
-ALTER TABLE timeline_events ADD COLUMN ai_generated BOOLEAN DEFAULT 0;
-ALTER TABLE timeline_events ADD COLUMN ai_confidence REAL DEFAULT 0.0;
-ALTER TABLE communications ADD COLUMN sentiment_score REAL DEFAULT 0.0;
-ALTER TABLE communications ADD COLUMN ai_summary TEXT;
-ALTER TABLE exhibits ADD COLUMN ai_extracted_text TEXT;
-ALTER TABLE exhibits ADD COLUMN ai_content_type TEXT;
-ALTER TABLE exhibits ADD COLUMN processing_status TEXT DEFAULT 'pending';
+-- Labelled outcomes and trained models for placement-denial risk scoring
+CREATE TABLE IF NOT EXISTS risk_outcomes (
+  id INTEGER PRIMARY KEY,
+  placement_denial_id INTEGER NOT NULL REFERENCES placement_denials(id),
+  outcome BOOLEAN NOT NULL, -- 1 = adverse outcome (e.g. court finding of interference)
+  labeled_by TEXT,
+  notes TEXT,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS risk_models (
+  id INTEGER PRIMARY KEY,
+  version TEXT UNIQUE NOT NULL,
+  model_json TEXT NOT NULL, -- serialized weights, feature scaling and metrics
+  training_examples INTEGER NOT NULL,
+  log_loss REAL,
+  accuracy REAL,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Per-message tone scores for communications
+CREATE TABLE IF NOT EXISTS communication_tone_scores (
+  id INTEGER PRIMARY KEY,
+  communication_id INTEGER UNIQUE NOT NULL REFERENCES communications(id),
+  sentiment REAL NOT NULL, -- -1.0 (negative) to 1.0 (positive)
+  hostility REAL NOT NULL, -- 0.0 to 1.0
+  escalation REAL NOT NULL, -- 0.0 to 1.0
+  method TEXT NOT NULL DEFAULT 'lexicon', -- 'lexicon' or 'lexicon+llm'
+  scored_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Timed transcript segments linking a recording (exhibit) to its transcript (communication)
+CREATE TABLE IF NOT EXISTS transcript_segments (
+  id INTEGER PRIMARY KEY,
+  exhibit_id INTEGER NOT NULL REFERENCES exhibits(id),
+  communication_id INTEGER NOT NULL REFERENCES communications(id),
+  segment_index INTEGER NOT NULL,
+  start_ms INTEGER NOT NULL,
+  end_ms INTEGER NOT NULL,
+  text TEXT NOT NULL,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Reviewer approval queue for AI-extracted timeline events, with source provenance
+CREATE TABLE IF NOT EXISTS timeline_event_candidates (
+  id INTEGER PRIMARY KEY,
+  event_date TEXT NOT NULL,
+  event_type TEXT NOT NULL,
+  event_title TEXT NOT NULL,
+  event_description TEXT NOT NULL,
+  importance_level INTEGER DEFAULT 3,
+  confidence REAL DEFAULT 0.0,
+  source_type TEXT NOT NULL, -- 'exhibit' or 'communication'
+  source_id INTEGER NOT NULL,
+  source_field TEXT NOT NULL, -- column of the source row the passage came from
+  source_passage TEXT NOT NULL,
+  passage_start INTEGER NOT NULL, -- byte offsets of the passage in that column
+  passage_end INTEGER NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending', -- pending, approved, rejected
+  reviewer TEXT,
+  reviewed_at TEXT,
+  timeline_event_id INTEGER REFERENCES timeline_events(id),
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Persistent full-text index over exhibits and communications
+CREATE TABLE IF NOT EXISTS search_documents (
+  id INTEGER PRIMARY KEY,
+  source_type TEXT NOT NULL, -- 'exhibit' or 'communication'
+  source_id INTEGER NOT NULL,
+  title TEXT NOT NULL,
+  content TEXT NOT NULL, -- left empty: no copy of the text is kept, snippets are cut from the source row
+  content_hash TEXT NOT NULL, -- SHA-256 of the source text, unchanged documents are skipped on sync
+  token_count INTEGER NOT NULL,
+  indexed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE(source_type, source_id)
+);
+
+CREATE TABLE IF NOT EXISTS search_terms (
+  term TEXT PRIMARY KEY,
+  doc_freq INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS search_postings (
+  term TEXT NOT NULL,
+  document_id INTEGER NOT NULL REFERENCES search_documents(id),
+  term_freq INTEGER NOT NULL,
+  positions TEXT NOT NULL, -- space-separated token positions
+  PRIMARY KEY (term, document_id)
+);
+
+-- Source rows changed since the last search sync, logged by the search_log_* triggers
+CREATE TABLE IF NOT EXISTS search_changes (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  source_type TEXT NOT NULL,
+  source_id INTEGER NOT NULL
+);
+
+-- Highest search_changes.seq already applied to the index
+CREATE TABLE IF NOT EXISTS search_sync_state (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  last_change_seq INTEGER NOT NULL
+);
+
+-- Serialized probabilistic sketches rolled up by case, metric and day
+CREATE TABLE IF NOT EXISTS analytics_sketches (
+  case_id INTEGER NOT NULL,
+  metric TEXT NOT NULL, -- e.g. 'unique_senders', 'term_frequency'
+  bucket_date TEXT NOT NULL, -- UTC day, YYYY-MM-DD
+  sketch_kind TEXT NOT NULL,
+  format_version INTEGER NOT NULL,
+  data BLOB NOT NULL,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (case_id, metric, bucket_date)
+);
+
+-- Encrypted-at-rest records and index state for crypto_db (values are ciphertext)
+CREATE TABLE IF NOT EXISTS crypto_store (
+  key TEXT PRIMARY KEY, -- '{table}:{uuid}', or '__'-prefixed internal state
+  value BLOB NOT NULL,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Access control, matter access and ethical wall tables are in access_control.sql
+
+-- Consent tables are in consent.sql
+
+-- Matter parties and conflict reports are in conflicts.sql
+
+-- Retention tables are in retention.sql
+
+-- AI metadata and matter columns on the tables above are added by db::run_migrations,
+-- which skips any a database already has; their indexes are in schema_columns.sql
 
 -- Indexes for performance
 CREATE INDEX IF NOT EXISTS idx_ai_insights_entity ON ai_insights(entity_type, entity_id);
 CREATE INDEX IF NOT EXISTS idx_ai_insights_type ON ai_insights(insight_type);
 CREATE INDEX IF NOT EXISTS idx_document_vectors_doc ON document_vectors(document_id);
 CREATE INDEX IF NOT EXISTS idx_legal_patterns_type ON legal_patterns(pattern_type);
-CREATE INDEX IF NOT EXISTS idx_placement_denials_risk ON placement_denials(ai_risk_score);
-CREATE INDEX IF NOT EXISTS idx_timeline_events_ai ON timeline_events(ai_generated);
+CREATE INDEX IF NOT EXISTS idx_risk_outcomes_denial ON risk_outcomes(placement_denial_id);
+CREATE INDEX IF NOT EXISTS idx_timeline_candidates_status ON timeline_event_candidates(status, event_date);
+CREATE INDEX IF NOT EXISTS idx_transcript_segments_exhibit ON transcript_segments(exhibit_id, segment_index);
+CREATE INDEX IF NOT EXISTS idx_search_postings_document ON search_postings(document_id);
 
 -- Prepopulate initial data
 INSERT OR IGNORE INTO case_info (docket_number, case_title, court, status)
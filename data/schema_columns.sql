@@ -0,0 +1,41 @@
+-- Indexes and triggers over the columns db::run_migrations adds to the base tables
+
+CREATE INDEX IF NOT EXISTS idx_placement_denials_risk ON placement_denials(ai_risk_score);
+CREATE INDEX IF NOT EXISTS idx_timeline_events_ai ON timeline_events(ai_generated);
+
+-- Log changes to searchable columns for the incremental search sync
+CREATE TRIGGER IF NOT EXISTS search_log_exhibit_insert
+  AFTER INSERT ON exhibits
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('exhibit', NEW.id);
+  END;
+
+CREATE TRIGGER IF NOT EXISTS search_log_exhibit_update
+  AFTER UPDATE OF exhibit_label, document_name, description, ai_extracted_text ON exhibits
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('exhibit', NEW.id);
+  END;
+
+CREATE TRIGGER IF NOT EXISTS search_log_exhibit_delete
+  AFTER DELETE ON exhibits
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('exhibit', OLD.id);
+  END;
+
+CREATE TRIGGER IF NOT EXISTS search_log_communication_insert
+  AFTER INSERT ON communications
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('communication', NEW.id);
+  END;
+
+CREATE TRIGGER IF NOT EXISTS search_log_communication_update
+  AFTER UPDATE OF communication_date, sender, subject, message_content ON communications
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('communication', NEW.id);
+  END;
+
+CREATE TRIGGER IF NOT EXISTS search_log_communication_delete
+  AFTER DELETE ON communications
+  BEGIN
+    INSERT INTO search_changes (source_type, source_id) VALUES ('communication', OLD.id);
+  END;
@@ -1,4 +1,7 @@
+use crate::ai::risk_model::{LogisticRiskModel, RiskExplanation, RiskFeatures};
+use crate::ai::transcription::{Transcriber, Transcript};
 use crate::ai::{AiConfig, AiError, AiInsight, AnalysisResponse, InsightType};
+use crate::legal::pii::{detector, Pseudonymizer};
 use chrono::Utc;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -13,6 +16,7 @@ pub struct AiCoreEngine {
     context_memory: Arc<Mutex<VecDeque<ConversationContext>>>,
     session_state: Arc<Mutex<SessionState>>,
     predictive_models: PredictiveModels,
+    transcriber: Transcriber,
 }
 
 /// Conversation context for memory and learning
@@ -170,6 +174,7 @@ impl AiCoreEngine {
         )));
         let session_state = Arc::new(Mutex::new(SessionState::default()));
         let predictive_models = PredictiveModels::new();
+        let transcriber = Transcriber::new(config.transcription.clone());
 
         Self {
             client,
@@ -177,9 +182,16 @@ impl AiCoreEngine {
             context_memory,
             session_state,
             predictive_models,
+            transcriber,
         }
     }
 
+    /// Use a trained risk model for risk alerts and proactive suggestions
+    pub fn with_risk_model(mut self, model: LogisticRiskModel) -> Self {
+        self.predictive_models.risk_predictor = RiskPredictor::with_model(model);
+        self
+    }
+
     /// Process advanced AI prompt with multi-modal capabilities
     pub async fn process_advanced_prompt(
         &self,
@@ -321,18 +333,28 @@ impl AiCoreEngine {
         &self,
         audio_data: &[u8],
     ) -> Result<AdvancedAiResponse, AiError> {
+        let transcript = self.transcribe(audio_data).await?;
+        self.process_voice_transcript(&transcript).await
+    }
+
+    /// Transcribe WAV/OGG/WebM audio with the configured speech-to-text backend
+    pub async fn transcribe(&self, audio_data: &[u8]) -> Result<Transcript, AiError> {
         if !self.config.enable_voice_processing {
             return Err(AiError::ConfigError(
                 "Voice processing not enabled".to_string(),
             ));
         }
 
-        // Placeholder for voice-to-text processing
-        // In a real implementation, this would use a speech recognition service
-        let transcribed_text = self.transcribe_audio(audio_data).await?;
+        self.transcriber.transcribe(audio_data).await
+    }
 
+    /// Answer an already-transcribed voice request
+    pub async fn process_voice_transcript(
+        &self,
+        transcript: &Transcript,
+    ) -> Result<AdvancedAiResponse, AiError> {
         let request = AdvancedPromptRequest {
-            input: transcribed_text,
+            input: transcript.text.clone(),
             input_type: InputType::Voice,
             context: None,
             intent_hints: vec!["voice_command".to_string()],
@@ -504,8 +526,32 @@ impl AiCoreEngine {
         &self,
         context: &HashMap<String, serde_json::Value>,
     ) -> Result<Vec<RiskAlert>, AiError> {
-        // Placeholder for advanced risk analysis
-        Ok(Vec::new())
+        let alerts = self
+            .predictive_models
+            .risk_predictor
+            .high_risk_explanations(context)
+            .into_iter()
+            .map(|explanation| RiskAlert {
+                alert_type: "placement_denial_risk".to_string(),
+                severity: if explanation.risk_score >= 0.85 {
+                    RiskSeverity::Critical
+                } else {
+                    RiskSeverity::High
+                },
+                description: format!(
+                    "Denial {} scored {:.2} by model {} (drivers: {})",
+                    explanation.denial_id,
+                    explanation.risk_score,
+                    explanation.model_version,
+                    RiskPredictor::describe_drivers(&explanation)
+                ),
+                recommended_action: "Review the denial and supporting evidence with counsel"
+                    .to_string(),
+                deadline: None,
+            })
+            .collect();
+
+        Ok(alerts)
     }
 
     async fn generate_contextual_insights(
@@ -628,11 +674,6 @@ impl AiCoreEngine {
         Ok(())
     }
 
-    async fn transcribe_audio(&self, _audio_data: &[u8]) -> Result<String, AiError> {
-        // Placeholder for audio transcription
-        Ok("Transcribed audio content".to_string())
-    }
-
     async fn call_llm(&self, prompt: &str, model: &str) -> Result<String, AiError> {
         let api_key = self
             .config
@@ -640,10 +681,17 @@ impl AiCoreEngine {
             .as_ref()
             .ok_or_else(|| AiError::ConfigError("OpenAI API key not configured".to_string()))?;
 
+        // Same protection as OpenAiService: pseudonymise, then re-identify the reply
+        let mut pseudonyms = Pseudonymizer::new();
+        let protected = detector().pseudonymize(prompt, &mut pseudonyms);
+        if protected.total() > 0 {
+            tracing::info!("Pseudonymised {} PII spans before LLM request", protected.total());
+        }
+
         let request_body = serde_json::json!({
             "model": model,
             "messages": [
-                {"role": "user", "content": prompt}
+                {"role": "user", "content": protected.text}
             ],
             "temperature": 0.3,
             "max_tokens": 1500
@@ -671,10 +719,11 @@ impl AiCoreEngine {
 
         let response_json: serde_json::Value = response.json().await.map_err(AiError::ApiError)?;
 
-        Ok(response_json["choices"][0]["message"]["content"]
-            .as_str()
-            .unwrap_or("No response")
-            .to_string())
+        Ok(pseudonyms.reidentify(
+            response_json["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap_or("No response"),
+        ))
     }
 }
 
@@ -700,21 +749,105 @@ impl PredictiveModels {
     }
 }
 
+/// Risk predictor backed by a trained `LogisticRiskModel`.
+/// Without a model it stays silent rather than guessing.
+pub struct RiskPredictor {
+    model: Option<LogisticRiskModel>,
+    alert_threshold: f64,
+}
+
 // Placeholder implementations for predictive models
-pub struct RiskPredictor;
 pub struct TrendAnalyzer;
 pub struct RecommendationEngine;
 
 impl RiskPredictor {
     fn new() -> Self {
-        Self
+        Self {
+            model: None,
+            alert_threshold: 0.7,
+        }
+    }
+
+    pub fn with_model(model: LogisticRiskModel) -> Self {
+        Self {
+            model: Some(model),
+            ..Self::new()
+        }
+    }
+
+    /// Explanations for the `risk_features` in the context that exceed the alert threshold
+    fn high_risk_explanations(
+        &self,
+        context: &HashMap<String, serde_json::Value>,
+    ) -> Vec<RiskExplanation> {
+        let (Some(model), Some(features)) = (&self.model, context.get("risk_features")) else {
+            return Vec::new();
+        };
+
+        serde_json::from_value::<Vec<RiskFeatures>>(features.clone())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|f| match model.explain(f) {
+                Ok(explanation) => Some(explanation),
+                Err(e) => {
+                    tracing::warn!("Skipping risk features: {}", e);
+                    None
+                }
+            })
+            .filter(|e| e.risk_score >= self.alert_threshold)
+            .collect()
+    }
+
+    fn describe_drivers(explanation: &RiskExplanation) -> String {
+        explanation
+            .contributions
+            .iter()
+            .filter(|c| c.contribution > 0.0)
+            .take(3)
+            .map(|c| format!("{}={} (+{:.2})", c.feature, c.value, c.contribution))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
     async fn analyze_current_state(
         &self,
-        _context: &HashMap<String, serde_json::Value>,
+        context: &HashMap<String, serde_json::Value>,
     ) -> Result<Option<Vec<SuggestedAction>>, AiError> {
-        Ok(None)
+        let explanations = self.high_risk_explanations(context);
+        if explanations.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            explanations
+                .iter()
+                .map(|explanation| SuggestedAction {
+                    action_type: "review_high_risk_denial".to_string(),
+                    description: format!(
+                        "Denial {} has risk {:.2}; main drivers: {}",
+                        explanation.denial_id,
+                        explanation.risk_score,
+                        Self::describe_drivers(explanation)
+                    ),
+                    priority: if explanation.risk_score >= 0.85 {
+                        ActionPriority::Critical
+                    } else {
+                        ActionPriority::High
+                    },
+                    estimated_impact: explanation.risk_score,
+                    required_data: vec![
+                        "placement_denials".to_string(),
+                        "communications".to_string(),
+                        "violations".to_string(),
+                    ],
+                    execution_steps: vec![
+                        "Open the denial record and its feature breakdown".to_string(),
+                        "Verify supporting evidence for the top drivers".to_string(),
+                        "Record the outcome in risk_outcomes for future training".to_string(),
+                    ],
+                })
+                .collect(),
+        ))
     }
 }
 
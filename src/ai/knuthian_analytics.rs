@@ -9,7 +9,9 @@ use crate::algorithms::{
     AlgorithmMetrics, 
     suffix_tree::StringMatcher, 
     cache_oblivious::CacheObliviousAlgorithms,
-    probabilistic::{ProbabilisticStructures, BloomFilter, HyperLogLog, CountMinSketch}
+    minhash::{LshConfig, NearDuplicateDetector},
+    probabilistic::{ProbabilisticStructures, BloomFilter, HyperLogLog},
+    top_k::{CmsTopK, HeavyHitter, SpaceSaving},
 };
 use crate::ai::{AiError, AiInsight, InsightType};
 
@@ -19,10 +21,19 @@ pub struct KnuthianAnalytics {
     string_matcher: StringMatcher,
     document_bloom_filter: BloomFilter,
     cardinality_estimator: HyperLogLog,
-    frequency_sketch: CountMinSketch,
+    frequency_sketch: CmsTopK<String>,
+    correspondents: SpaceSaving<String>,
+    near_duplicate_config: LshConfig,
     performance_metrics: Vec<AlgorithmMetrics>,
 }
 
+/// Words too common to be useful in a frequent-term report
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "that", "this", "with", "are", "was", "were", "have", "has", "had",
+    "not", "but", "from", "you", "your", "our", "will", "would", "can", "all", "any", "its",
+    "they", "them", "their", "there", "been", "which", "who", "what", "when", "into", "than",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnalyticsPerformanceReport {
     pub total_operations: u64,
@@ -40,12 +51,24 @@ impl KnuthianAnalytics {
             string_matcher: StringMatcher::new(),
             document_bloom_filter: BloomFilter::new(10000, 0.01), // 1% false positive rate
             cardinality_estimator: HyperLogLog::new(12), // 4096 buckets for high precision
-            frequency_sketch: CountMinSketch::new(0.001, 0.01), // 0.1% error, 99% confidence
+            frequency_sketch: CmsTopK::new(200, 0.001, 0.01), // 0.1% error, 99% confidence, top 200 terms named
+            correspondents: SpaceSaving::new(100),
+            near_duplicate_config: LshConfig::default(), // Jaccard ≥ 0.8 over 5-word shingles
             performance_metrics: Vec::new(),
         }
     }
 
-    /// Advanced document similarity analysis using suffix trees
+    /// Set the estimated Jaccard similarity above which documents count as near-duplicates
+    pub fn with_similarity_threshold(mut self, threshold: f64) -> Self {
+        let config = &self.near_duplicate_config;
+        self.near_duplicate_config = LshConfig::for_threshold(threshold, config.num_perm, config.shingle_size);
+        self
+    }
+
+    /// Document similarity analysis using MinHash/LSH near-duplicate detection
+    ///
+    /// The documents are also indexed in the suffix-tree matcher that
+    /// `search_legal_patterns` searches.
     pub fn analyze_document_similarity(&mut self, documents: &[String]) -> Result<AiInsight, AiError> {
         let start_time = std::time::Instant::now();
         
@@ -66,19 +89,18 @@ impl KnuthianAnalytics {
             }
         }
 
-        // Find similar document pairs using longest common substrings
+        // Find near-duplicate pairs with MinHash/LSH; only documents sharing a band are compared
+        let mut detector = NearDuplicateDetector::new(self.near_duplicate_config.clone());
+        for (i, doc) in documents.iter().enumerate() {
+            detector.add_document(&i.to_string(), doc);
+        }
+
         let mut similarity_scores = HashMap::new();
-        for i in 0..documents.len() {
-            for j in (i + 1)..documents.len() {
-                let doc1_id = format!("doc_{}", i);
-                let doc2_id = format!("doc_{}", j);
-                
-                if let Some(lcs) = self.string_matcher.find_lcs(&doc1_id, &doc2_id) {
-                    let similarity_ratio = lcs.len() as f64 / documents[i].len().max(documents[j].len()) as f64;
-                    similarity_scores.insert(format!("{}_{}", i, j), similarity_ratio);
-                }
-            }
+        for (a, b, similarity) in detector.index().candidate_pairs() {
+            let (i, j): (usize, usize) = (a.parse().unwrap_or_default(), b.parse().unwrap_or_default());
+            similarity_scores.insert(format!("{}_{}", i.min(j), i.max(j)), similarity);
         }
+        let near_duplicate_clusters = detector.index().clusters();
 
         let processing_time = start_time.elapsed();
         
@@ -91,12 +113,14 @@ impl KnuthianAnalytics {
             "total_documents": documents.len(),
             "estimated_unique_documents": estimated_unique_docs,
             "similarity_scores": similarity_scores,
+            "similarity_threshold": self.near_duplicate_config.threshold,
+            "near_duplicate_clusters": near_duplicate_clusters,
             "bloom_filter_fp_rate": false_positive_rate,
             "processing_time_ms": processing_time.as_millis(),
             "algorithmic_complexity": {
                 "suffix_tree_construction": "O(n)",
                 "pattern_matching": "O(m)",
-                "similarity_computation": "O(k log k)"
+                "similarity_computation": "O(n·b) MinHash/LSH"
             },
             "performance_metrics": {
                 "cache_efficiency": "cache-oblivious",
@@ -266,6 +290,73 @@ impl KnuthianAnalytics {
         })
     }
 
+    /// Record one message sender for the frequent-correspondent report
+    pub fn record_correspondent(&mut self, sender: &str) {
+        let sender = sender.trim().to_lowercase();
+        if !sender.is_empty() {
+            self.correspondents.add(sender.as_str(), 1);
+        }
+    }
+
+    /// Most frequent terms seen so far, named rather than as sketch buckets
+    pub fn frequent_terms_report(&self, k: usize) -> Result<AiInsight, AiError> {
+        let terms: Vec<HeavyHitter<String>> = self
+            .frequency_sketch
+            .top(usize::MAX)
+            .into_iter()
+            .filter(|hitter| hitter.item.chars().count() > 2 && !STOP_WORDS.contains(&hitter.item.as_str()))
+            .take(k)
+            .collect();
+
+        let insight_data = serde_json::json!({
+            "analysis_type": "frequent_terms",
+            "total_terms_observed": self.frequency_sketch.total_count(),
+            "frequent_terms": terms.iter().map(|hitter| serde_json::json!({
+                "term": hitter.item,
+                "estimated_count": hitter.count,
+                "max_overestimate": hitter.error
+            })).collect::<Vec<_>>(),
+            "algorithm": "Count-Min Sketch with top-k candidate tracking"
+        });
+
+        Ok(AiInsight {
+            insight_type: InsightType::Pattern,
+            confidence_score: 0.9,
+            data: insight_data,
+            generated_by: "knuthian_heavy_hitters".to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Most frequent correspondents across the given communications and any recorded earlier
+    pub fn frequent_correspondents_report(&mut self, communications: &[Value], k: usize) -> Result<AiInsight, AiError> {
+        for communication in communications {
+            if let Some(sender) = communication.get("sender").and_then(|s| s.as_str()) {
+                self.record_correspondent(sender);
+            }
+        }
+
+        let correspondents = self.correspondents.top(k);
+        let insight_data = serde_json::json!({
+            "analysis_type": "frequent_correspondents",
+            "total_messages_observed": self.correspondents.total_count(),
+            "frequent_correspondents": correspondents.iter().map(|hitter| serde_json::json!({
+                "correspondent": hitter.item,
+                "message_count": hitter.count,
+                "guaranteed_count": hitter.guaranteed_count()
+            })).collect::<Vec<_>>(),
+            "algorithm": "Space-Saving top-k"
+        });
+
+        Ok(AiInsight {
+            insight_type: InsightType::Pattern,
+            confidence_score: 0.9,
+            data: insight_data,
+            generated_by: "knuthian_heavy_hitters".to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
     /// Generate comprehensive performance report of algorithmic optimizations
     pub fn generate_performance_report(&self) -> AnalyticsPerformanceReport {
         let mut total_operations = 0;
@@ -303,6 +394,13 @@ impl KnuthianAnalytics {
         complexity_map.insert("count_min_sketch".to_string(), cms_metrics.complexity.time_complexity.clone());
         data_structures.push("CountMinSketch".to_string());
 
+        let space_saving_metrics = self.correspondents.get_metrics();
+        total_operations += space_saving_metrics.complexity.operations_count;
+        total_cache_misses += space_saving_metrics.complexity.cache_misses;
+        total_execution_time += space_saving_metrics.execution_time_ns;
+        complexity_map.insert("space_saving".to_string(), space_saving_metrics.complexity.time_complexity.clone());
+        data_structures.push("SpaceSaving".to_string());
+
         let cache_hit_ratio = if total_operations > 0 {
             1.0 - (total_cache_misses as f64 / total_operations as f64)
         } else {
@@ -427,6 +525,45 @@ mod tests {
         assert!(insight.confidence_score > 0.9);
     }
 
+    #[test]
+    fn test_near_duplicate_clusters() {
+        let mut analytics = KnuthianAnalytics::new().with_similarity_threshold(0.5);
+        let email = "The visit scheduled for Saturday is cancelled because of a prior family commitment, we will propose new dates next week";
+        let documents = vec![
+            email.to_string(),
+            format!("FW: {}", email),
+            "Litigation case study analysis of unrelated custody proceedings".to_string(),
+        ];
+
+        let insight = analytics.analyze_document_similarity(&documents).unwrap();
+        assert_eq!(insight.data["near_duplicate_clusters"], serde_json::json!([["0", "1"]]));
+        assert!(insight.data["similarity_scores"].get("0_1").is_some());
+        assert!(insight.data["similarity_scores"].get("0_2").is_none());
+    }
+
+    #[test]
+    fn test_heavy_hitter_reports_name_items() {
+        let mut analytics = KnuthianAnalytics::new();
+        let documents = vec![
+            "The custody schedule and the custody order".to_string(),
+            "Custody exchange was missed and custody was disputed".to_string(),
+        ];
+        analytics.analyze_document_similarity(&documents).unwrap();
+
+        let terms = analytics.frequent_terms_report(1).unwrap();
+        assert_eq!(terms.data["frequent_terms"][0]["term"], "custody");
+        assert!(terms.data["frequent_terms"][0]["estimated_count"].as_u64().unwrap() >= 4);
+
+        let communications = vec![
+            serde_json::json!({"sender": "Agency"}),
+            serde_json::json!({"sender": "agency "}),
+            serde_json::json!({"sender": "Parent"}),
+        ];
+        let senders = analytics.frequent_correspondents_report(&communications, 2).unwrap();
+        assert_eq!(senders.data["frequent_correspondents"][0]["correspondent"], "agency");
+        assert_eq!(senders.data["frequent_correspondents"][0]["message_count"], 2);
+    }
+
     #[test]
     fn test_cache_oblivious_pattern_search() {
         let mut analytics = KnuthianAnalytics::new();
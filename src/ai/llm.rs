@@ -1,21 +1,46 @@
+use crate::ai::risk_model::{LogisticRiskModel, RiskFeatures};
 use crate::ai::{
     AiConfig, AiError, AiInsight, AiService, AnalysisRequest, AnalysisResponse, InsightType,
 };
+use crate::legal::pii::{detector, PiiMode, Pseudonymizer};
 use chrono::Utc;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
 /// OpenAI API service implementation
+///
+/// Personal data is removed from every message before it leaves the process:
+/// pseudonymised by default, so replies can be re-identified locally, or
+/// redacted outright with `with_pii_mode(PiiMode::Redact)`.
 pub struct OpenAiService {
     client: Client,
     config: AiConfig,
+    risk_model: Option<LogisticRiskModel>,
+    pii_mode: PiiMode,
 }
 
 impl OpenAiService {
     pub fn new(config: AiConfig) -> Self {
         let client = Client::new();
-        Self { client, config }
+        Self {
+            client,
+            config,
+            risk_model: None,
+            pii_mode: PiiMode::Pseudonymize,
+        }
+    }
+
+    /// Score risk with a trained model instead of asking the LLM
+    pub fn with_risk_model(mut self, model: LogisticRiskModel) -> Self {
+        self.risk_model = Some(model);
+        self
+    }
+
+    /// How personal data is removed from prompts
+    pub fn with_pii_mode(mut self, mode: PiiMode) -> Self {
+        self.pii_mode = mode;
+        self
     }
 
     /// Create a completion request to OpenAI API
@@ -26,6 +51,23 @@ impl OpenAiService {
             .as_ref()
             .ok_or_else(|| AiError::ConfigError("OpenAI API key not configured".to_string()))?;
 
+        // Only tokens or redaction markers are sent; the mapping stays here
+        let mut pseudonyms = Pseudonymizer::new();
+        let contents: Vec<&str> = messages.iter().map(|message| message.content.as_str()).collect();
+        let protected = detector().protect_all(&contents, self.pii_mode, &mut pseudonyms);
+        let removed: usize = protected.iter().map(|text| text.total()).sum();
+        let messages = messages
+            .iter()
+            .zip(protected)
+            .map(|(message, protected)| OpenAiMessage {
+                role: message.role.clone(),
+                content: protected.text,
+            })
+            .collect();
+        if removed > 0 {
+            tracing::info!("Removed {} PII spans ({:?}) before OpenAI request", removed, self.pii_mode);
+        }
+
         let request = OpenAiRequest {
             model: self.config.default_model.clone(),
             messages,
@@ -52,13 +94,13 @@ impl OpenAiService {
 
         let openai_response: OpenAiResponse = response.json().await?;
 
-        Ok(openai_response
+        let content = &openai_response
             .choices
             .first()
             .ok_or_else(|| AiError::ModelError("No response from model".to_string()))?
             .message
-            .content
-            .clone())
+            .content;
+        Ok(pseudonyms.reidentify(content))
     }
 }
 
@@ -178,6 +220,13 @@ impl AiService for OpenAiService {
     }
 
     async fn assess_risk(&self, placement_denial: &serde_json::Value) -> Result<f64, AiError> {
+        // A trained model gives a reproducible, explainable score when features are supplied
+        if let Some(model) = &self.risk_model {
+            if let Ok(features) = serde_json::from_value::<RiskFeatures>(placement_denial.clone()) {
+                return model.score(&features);
+            }
+        }
+
         let system_message = OpenAiMessage {
             role: "system".to_string(),
             content: "You are a legal risk assessment expert. Analyze the provided placement denial data and return a risk score between 0.0 and 1.0, where 1.0 represents highest legal risk. Consider factors like denial reasons, timing patterns, and potential violations.".to_string(),
@@ -3,6 +3,11 @@ pub mod core_engine;
 pub mod fabric_integration;
 pub mod llm;
 pub mod patterns;
+pub mod risk_model;
+pub mod sentiment;
+pub mod timeline_extraction;
+pub mod transcription;
+mod util;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -88,6 +93,8 @@ pub enum AiError {
     ConfigError(String),
     #[error("Model error: {0}")]
     ModelError(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
     #[error("Timeout error")]
     TimeoutError,
 }
@@ -110,6 +117,7 @@ pub struct AiConfig {
     pub timeout_seconds: u64,
     pub context_memory_size: usize,
     pub learning_rate: f64,
+    pub transcription: transcription::TranscriptionConfig,
 }
 
 impl Default for AiConfig {
@@ -130,6 +138,7 @@ impl Default for AiConfig {
             timeout_seconds: 30,
             context_memory_size: 50,
             learning_rate: 0.01,
+            transcription: transcription::TranscriptionConfig::default(),
         }
     }
 }
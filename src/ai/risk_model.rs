@@ -0,0 +1,608 @@
+// Trainable placement-denial risk model
+// Logistic regression over features engineered from denial history,
+// communications and violations, with per-feature explanations.
+
+use crate::ai::util::{db_error, parse_date};
+use crate::ai::AiError;
+use crate::db::DbPool;
+use crate::legal::ethical_walls::MatterScope;
+use crate::models::{Communication, PlacementDenial, Violation};
+use chrono::{Datelike, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tokio::time::{sleep, Duration};
+
+/// Names of the engineered features, in the order they appear in `RiskFeatures::values`
+pub const FEATURE_NAMES: [&str; 9] = [
+    "duration_hours",
+    "prior_denials_30d",
+    "prior_denials_90d",
+    "days_since_last_denial",
+    "holiday_related",
+    "weekend",
+    "placement_communications_7d",
+    "linked_violation_impact",
+    "evidence_attached",
+];
+
+/// Cap used for `days_since_last_denial` when there is no earlier denial
+const MAX_GAP_DAYS: f64 = 365.0;
+
+/// Engineered feature vector for a single placement denial
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskFeatures {
+    pub denial_id: i64,
+    pub values: Vec<f64>,
+}
+
+/// Case history the features are derived from
+#[derive(Debug, Default)]
+pub struct CaseHistory {
+    pub denials: Vec<PlacementDenial>,
+    pub communications: Vec<Communication>,
+    pub violations: Vec<Violation>,
+}
+
+impl CaseHistory {
+    /// Load the denial, communication and violation history of the matters `scope` allows
+    ///
+    /// Violations follow the denial they're linked to.
+    pub async fn load(pool: &DbPool, scope: &MatterScope) -> Result<Self, sqlx::Error> {
+        let in_scope = scope.sql_filter("case_id");
+
+        let denials = sqlx::query_as::<_, PlacementDenial>(&format!(
+            "SELECT id, denied_date, requested_start_time, requested_end_time, duration_hours,
+                    denial_reason, violation_category, evidence_attached, created_at
+             FROM placement_denials WHERE {} ORDER BY denied_date",
+            in_scope
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let communications = sqlx::query_as::<_, Communication>(&format!(
+            "SELECT id, communication_date, sender, recipient, medium, subject,
+                    message_content, related_to_placement, created_at
+             FROM communications WHERE {} ORDER BY communication_date",
+            in_scope
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let violations = sqlx::query_as::<_, Violation>(&format!(
+            "SELECT id, violation_date, violation_type, description, stipulation_reference,
+                    impact_score, placement_denial_id, created_at
+             FROM violations
+             WHERE placement_denial_id IS NULL
+                OR placement_denial_id IN (SELECT id FROM placement_denials WHERE {})
+             ORDER BY violation_date",
+            in_scope
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Self {
+            denials,
+            communications,
+            violations,
+        })
+    }
+
+    /// Engineer the feature vector for one denial using only history up to its date
+    pub fn features_for(&self, denial: &PlacementDenial) -> RiskFeatures {
+        let date = parse_date(&denial.denied_date);
+
+        let prior_dates: Vec<NaiveDate> = self
+            .denials
+            .iter()
+            .filter(|d| d.id != denial.id)
+            .filter_map(|d| parse_date(&d.denied_date))
+            .filter(|d| date.map(|date| *d < date).unwrap_or(false))
+            .collect();
+
+        let (prior_30, prior_90, gap) = match date {
+            Some(date) => {
+                let within = |days: i64| {
+                    prior_dates
+                        .iter()
+                        .filter(|d| (date - **d).num_days() <= days)
+                        .count() as f64
+                };
+                let gap = prior_dates
+                    .iter()
+                    .map(|d| (date - *d).num_days() as f64)
+                    .fold(MAX_GAP_DAYS, f64::min);
+                (within(30), within(90), gap)
+            }
+            None => (0.0, 0.0, MAX_GAP_DAYS),
+        };
+
+        let text = format!(
+            "{} {}",
+            denial.denial_reason.as_deref().unwrap_or(""),
+            denial.violation_category.as_deref().unwrap_or("")
+        )
+        .to_lowercase();
+        let holiday_related = if text.contains("holiday") || text.contains("day denied") {
+            1.0
+        } else {
+            0.0
+        };
+
+        let weekend = match date.map(|d| d.weekday()) {
+            Some(Weekday::Sat) | Some(Weekday::Sun) => 1.0,
+            _ => 0.0,
+        };
+
+        let communications_7d = match date {
+            Some(date) => self
+                .communications
+                .iter()
+                .filter(|c| c.related_to_placement.unwrap_or(false))
+                .filter_map(|c| parse_date(&c.communication_date))
+                .filter(|d| *d <= date && (date - *d).num_days() <= 7)
+                .count() as f64,
+            None => 0.0,
+        };
+
+        let violation_impact: f64 = self
+            .violations
+            .iter()
+            .filter(|v| {
+                v.placement_denial_id == Some(denial.id)
+                    || (v.placement_denial_id.is_none() && v.violation_date == denial.denied_date)
+            })
+            .map(|v| v.impact_score.unwrap_or(1) as f64)
+            .sum();
+
+        let evidence = match denial.evidence_attached.as_deref() {
+            Some(e) if !e.trim().is_empty() => 1.0,
+            _ => 0.0,
+        };
+
+        RiskFeatures {
+            denial_id: denial.id,
+            values: vec![
+                denial.duration_hours.unwrap_or(0.0),
+                prior_30,
+                prior_90,
+                gap,
+                holiday_related,
+                weekend,
+                communications_7d,
+                violation_impact,
+                evidence,
+            ],
+        }
+    }
+}
+
+/// A labelled training example: features plus the observed outcome
+#[derive(Debug, Clone)]
+pub struct LabelledExample {
+    pub features: RiskFeatures,
+    pub outcome: bool,
+}
+
+/// Hyper-parameters for gradient-descent training
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingConfig {
+    pub learning_rate: f64,
+    pub epochs: usize,
+    pub l2_penalty: f64,
+    pub min_examples: usize,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.1,
+            epochs: 1000,
+            l2_penalty: 0.01,
+            min_examples: 10,
+        }
+    }
+}
+
+/// Summary statistics recorded alongside a trained model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingMetrics {
+    pub examples: usize,
+    pub positive_rate: f64,
+    pub log_loss: f64,
+    pub accuracy: f64,
+}
+
+/// Logistic regression risk model over standardised features
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticRiskModel {
+    pub version: String,
+    pub feature_names: Vec<String>,
+    pub weights: Vec<f64>,
+    pub bias: f64,
+    pub feature_means: Vec<f64>,
+    pub feature_stds: Vec<f64>,
+    pub metrics: TrainingMetrics,
+    pub trained_at: chrono::DateTime<Utc>,
+}
+
+/// Contribution of one feature to a score, in log-odds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureContribution {
+    pub feature: String,
+    pub value: f64,
+    pub contribution: f64,
+}
+
+/// Scored denial with its explanation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskExplanation {
+    pub denial_id: i64,
+    pub risk_score: f64,
+    pub baseline_log_odds: f64,
+    pub contributions: Vec<FeatureContribution>,
+    pub model_version: String,
+}
+
+impl LogisticRiskModel {
+    /// Fit the model to labelled outcomes with L2-regularised batch gradient descent
+    pub fn train(examples: &[LabelledExample], config: &TrainingConfig) -> Result<Self, AiError> {
+        if examples.len() < config.min_examples {
+            return Err(AiError::InvalidInput(format!(
+                "At least {} labelled outcomes are required, found {}",
+                config.min_examples,
+                examples.len()
+            )));
+        }
+
+        let positives = examples.iter().filter(|e| e.outcome).count();
+        if positives == 0 || positives == examples.len() {
+            return Err(AiError::InvalidInput(
+                "Labelled outcomes must include both positive and negative cases".to_string(),
+            ));
+        }
+
+        let dims = FEATURE_NAMES.len();
+        for example in examples {
+            check_dimensions(dims, &example.features)?;
+        }
+        let n = examples.len() as f64;
+
+        let mut means = vec![0.0; dims];
+        for example in examples {
+            for (mean, value) in means.iter_mut().zip(&example.features.values) {
+                *mean += value / n;
+            }
+        }
+        let mut stds = vec![0.0; dims];
+        for example in examples {
+            for ((std, mean), value) in stds.iter_mut().zip(&means).zip(&example.features.values) {
+                *std += (value - mean).powi(2) / n;
+            }
+        }
+        for std in stds.iter_mut() {
+            *std = if *std > 0.0 { std.sqrt() } else { 1.0 };
+        }
+
+        let standardised: Vec<(Vec<f64>, f64)> = examples
+            .iter()
+            .map(|e| {
+                let z = standardise(&e.features.values, &means, &stds);
+                (z, if e.outcome { 1.0 } else { 0.0 })
+            })
+            .collect();
+
+        let mut weights = vec![0.0; dims];
+        let mut bias = 0.0;
+        for _ in 0..config.epochs {
+            let mut grad_w = vec![0.0; dims];
+            let mut grad_b = 0.0;
+            for (z, y) in &standardised {
+                let error = sigmoid(dot(&weights, z) + bias) - y;
+                for (g, x) in grad_w.iter_mut().zip(z) {
+                    *g += error * x / n;
+                }
+                grad_b += error / n;
+            }
+            for (w, g) in weights.iter_mut().zip(&grad_w) {
+                *w -= config.learning_rate * (g + config.l2_penalty * *w);
+            }
+            bias -= config.learning_rate * grad_b;
+        }
+
+        let mut log_loss = 0.0;
+        let mut correct = 0;
+        for (z, y) in &standardised {
+            let p = sigmoid(dot(&weights, z) + bias).clamp(1e-12, 1.0 - 1e-12);
+            log_loss -= (y * p.ln() + (1.0 - y) * (1.0 - p).ln()) / n;
+            if (p >= 0.5) == (*y >= 0.5) {
+                correct += 1;
+            }
+        }
+
+        let trained_at = Utc::now();
+        Ok(Self {
+            version: format!("logreg-{}", trained_at.format("%Y%m%d%H%M%S")),
+            feature_names: FEATURE_NAMES.iter().map(|s| s.to_string()).collect(),
+            weights,
+            bias,
+            feature_means: means,
+            feature_stds: stds,
+            metrics: TrainingMetrics {
+                examples: examples.len(),
+                positive_rate: positives as f64 / n,
+                log_loss,
+                accuracy: correct as f64 / n,
+            },
+            trained_at,
+        })
+    }
+
+    /// Probability of an adverse outcome for the given features
+    pub fn score(&self, features: &RiskFeatures) -> Result<f64, AiError> {
+        check_dimensions(self.weights.len(), features)?;
+        let z = standardise(&features.values, &self.feature_means, &self.feature_stds);
+        Ok(sigmoid(dot(&self.weights, &z) + self.bias))
+    }
+
+    /// Score and break the log-odds down into per-feature contributions
+    pub fn explain(&self, features: &RiskFeatures) -> Result<RiskExplanation, AiError> {
+        check_dimensions(self.weights.len(), features)?;
+        let z = standardise(&features.values, &self.feature_means, &self.feature_stds);
+
+        let mut contributions: Vec<FeatureContribution> = self
+            .feature_names
+            .iter()
+            .zip(&features.values)
+            .zip(self.weights.iter().zip(&z))
+            .map(|((name, value), (w, x))| FeatureContribution {
+                feature: name.clone(),
+                value: *value,
+                contribution: w * x,
+            })
+            .collect();
+        contributions.sort_by(|a, b| {
+            b.contribution
+                .abs()
+                .partial_cmp(&a.contribution.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(RiskExplanation {
+            denial_id: features.denial_id,
+            risk_score: sigmoid(dot(&self.weights, &z) + self.bias),
+            baseline_log_odds: self.bias,
+            contributions,
+            model_version: self.version.clone(),
+        })
+    }
+
+    /// Train from the `risk_outcomes` table and store the resulting model
+    pub async fn train_from_database(
+        pool: &DbPool,
+        config: &TrainingConfig,
+    ) -> Result<Self, AiError> {
+        // The model is trained for the whole firm
+        let history = CaseHistory::load(pool, &MatterScope::unrestricted())
+            .await
+            .map_err(db_error)?;
+
+        let labels = sqlx::query(
+            "SELECT placement_denial_id, outcome FROM risk_outcomes
+             WHERE id IN (SELECT MAX(id) FROM risk_outcomes GROUP BY placement_denial_id)",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+        let examples: Vec<LabelledExample> = labels
+            .iter()
+            .filter_map(|row| {
+                let denial_id: i64 = row.get("placement_denial_id");
+                let outcome: bool = row.get("outcome");
+                history
+                    .denials
+                    .iter()
+                    .find(|d| d.id == denial_id)
+                    .map(|denial| LabelledExample {
+                        features: history.features_for(denial),
+                        outcome,
+                    })
+            })
+            .collect();
+
+        let model = Self::train(&examples, config)?;
+        model.save(pool).await?;
+        Ok(model)
+    }
+
+    /// Persist the model so scoring runs use a reproducible version
+    pub async fn save(&self, pool: &DbPool) -> Result<(), AiError> {
+        sqlx::query(
+            "INSERT INTO risk_models (version, model_json, training_examples, log_loss, accuracy)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&self.version)
+        .bind(serde_json::to_string(self)?)
+        .bind(self.metrics.examples as i64)
+        .bind(self.metrics.log_loss)
+        .bind(self.metrics.accuracy)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Load the most recently trained model, if any
+    pub async fn load_latest(pool: &DbPool) -> Result<Option<Self>, AiError> {
+        let row = sqlx::query("SELECT model_json FROM risk_models ORDER BY id DESC LIMIT 1")
+            .fetch_optional(pool)
+            .await
+            .map_err(db_error)?;
+
+        match row {
+            Some(row) => Ok(Some(serde_json::from_str(&row.get::<String, _>("model_json"))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Score every placement denial in `scope` and write `ai_risk_score` plus the explanation
+pub async fn score_all_denials(
+    pool: &DbPool,
+    model: &LogisticRiskModel,
+    scope: &MatterScope,
+) -> Result<usize, AiError> {
+    let history = CaseHistory::load(pool, scope).await.map_err(db_error)?;
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    for denial in &history.denials {
+        let explanation = model.explain(&history.features_for(denial))?;
+        sqlx::query("UPDATE placement_denials SET ai_risk_score = ?, ai_analysis = ? WHERE id = ?")
+            .bind(explanation.risk_score)
+            .bind(serde_json::to_string(&explanation)?)
+            .bind(denial.id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(history.denials.len())
+}
+
+/// Run `score_all_denials` every night at the given UTC hour using the latest model
+pub fn spawn_nightly_scoring(pool: DbPool, hour_utc: u32) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            sleep(duration_until_hour(hour_utc)).await;
+
+            match LogisticRiskModel::load_latest(&pool).await {
+                Ok(Some(model)) => {
+                    match score_all_denials(&pool, &model, &MatterScope::unrestricted()).await {
+                        Ok(count) => tracing::info!(
+                            "Nightly risk scoring updated {} denials with model {}",
+                            count,
+                            model.version
+                        ),
+                        Err(e) => tracing::error!("Nightly risk scoring failed: {}", e),
+                    }
+                }
+                Ok(None) => tracing::warn!("Nightly risk scoring skipped: no trained model"),
+                Err(e) => tracing::error!("Failed to load risk model: {}", e),
+            }
+        }
+    })
+}
+
+fn duration_until_hour(hour_utc: u32) -> Duration {
+    let now = Utc::now();
+    let today = now
+        .date_naive()
+        .and_hms_opt(hour_utc.min(23), 0, 0)
+        .expect("valid hour")
+        .and_utc();
+    let next = if today > now {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+    (next - now).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+/// Reject feature vectors that do not line up with the model's weights
+fn check_dimensions(expected: usize, features: &RiskFeatures) -> Result<(), AiError> {
+    if features.values.len() != expected {
+        return Err(AiError::InvalidInput(format!(
+            "Denial {} has {} features, model expects {}",
+            features.denial_id,
+            features.values.len(),
+            expected
+        )));
+    }
+    Ok(())
+}
+
+fn standardise(values: &[f64], means: &[f64], stds: &[f64]) -> Vec<f64> {
+    values
+        .iter()
+        .zip(means.iter().zip(stds))
+        .map(|(v, (m, s))| (v - m) / s)
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(id: i64, duration: f64, prior: f64, outcome: bool) -> LabelledExample {
+        LabelledExample {
+            features: RiskFeatures {
+                denial_id: id,
+                values: vec![duration, prior, prior, 30.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            },
+            outcome,
+        }
+    }
+
+    fn training_set() -> Vec<LabelledExample> {
+        (0..20)
+            .map(|i| {
+                let high = i % 2 == 0;
+                let duration = if high { 10.0 + i as f64 * 0.1 } else { 3.0 + i as f64 * 0.1 };
+                example(i, duration, if high { 3.0 } else { 0.0 }, high)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_training_separates_outcomes() {
+        let model = LogisticRiskModel::train(&training_set(), &TrainingConfig::default()).unwrap();
+
+        assert!(model.metrics.accuracy > 0.9);
+        assert!(model.score(&example(99, 12.0, 3.0, true).features).unwrap() > 0.5);
+        assert!(model.score(&example(99, 3.0, 0.0, false).features).unwrap() < 0.5);
+    }
+
+    #[test]
+    fn test_contributions_sum_to_log_odds() {
+        let model = LogisticRiskModel::train(&training_set(), &TrainingConfig::default()).unwrap();
+        let explanation = model
+            .explain(&example(99, 12.0, 3.0, true).features)
+            .unwrap();
+
+        let log_odds: f64 = explanation.baseline_log_odds
+            + explanation
+                .contributions
+                .iter()
+                .map(|c| c.contribution)
+                .sum::<f64>();
+        assert!((sigmoid(log_odds) - explanation.risk_score).abs() < 1e-9);
+        assert_eq!(explanation.contributions.len(), FEATURE_NAMES.len());
+    }
+
+    #[test]
+    fn test_training_requires_both_classes() {
+        let examples: Vec<_> = (0..20).map(|i| example(i, 5.0, 0.0, true)).collect();
+        assert!(LogisticRiskModel::train(&examples, &TrainingConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_feature_length_mismatch_is_rejected() {
+        let model = LogisticRiskModel::train(&training_set(), &TrainingConfig::default()).unwrap();
+        let short = RiskFeatures {
+            denial_id: 99,
+            values: vec![12.0, 3.0],
+        };
+
+        assert!(matches!(model.score(&short), Err(AiError::InvalidInput(_))));
+        assert!(model.explain(&short).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+// Lexicon-based tone analysis for co-parenting communications
+// Scores sentiment, hostility and escalation locally; an `AiService` can optionally refine sentiment.
+
+use crate::ai::util::{db_error, parse_date};
+use crate::ai::{AiError, AiService};
+use crate::db::DbPool;
+use crate::legal::ethical_walls::MatterScope;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::HashMap;
+
+/// Positive and negative sentiment terms with weights in [-1, 1]
+const SENTIMENT_LEXICON: &[(&str, f64)] = &[
+    ("thank", 0.6),
+    ("thanks", 0.6),
+    ("appreciate", 0.7),
+    ("glad", 0.5),
+    ("happy", 0.6),
+    ("great", 0.5),
+    ("good", 0.4),
+    ("agree", 0.4),
+    ("sure", 0.3),
+    ("fine", 0.2),
+    ("please", 0.2),
+    ("sorry", 0.3),
+    ("flexible", 0.5),
+    ("welcome", 0.5),
+    ("love", 0.6),
+    ("bad", -0.4),
+    ("no", -0.2),
+    ("not", -0.1),
+    ("never", -0.5),
+    ("refuse", -0.6),
+    ("denied", -0.5),
+    ("deny", -0.5),
+    ("cancel", -0.4),
+    ("cancelled", -0.4),
+    ("unacceptable", -0.7),
+    ("ridiculous", -0.7),
+    ("angry", -0.6),
+    ("upset", -0.5),
+    ("disappointed", -0.5),
+    ("problem", -0.3),
+    ("fault", -0.5),
+    ("blame", -0.6),
+    ("worst", -0.8),
+    ("hate", -0.9),
+];
+
+/// Hostile terms (insults, threats, coercion) with weights in [0, 1]
+const HOSTILITY_LEXICON: &[(&str, f64)] = &[
+    ("stupid", 0.8),
+    ("idiot", 0.9),
+    ("liar", 0.8),
+    ("pathetic", 0.8),
+    ("useless", 0.7),
+    ("shut", 0.5),
+    ("threat", 0.7),
+    ("lawyer", 0.3),
+    ("court", 0.2),
+    ("police", 0.5),
+    ("regret", 0.5),
+    ("warned", 0.6),
+    ("never see", 0.9),
+    ("or else", 0.9),
+    ("last time", 0.5),
+    ("you always", 0.5),
+    ("you never", 0.5),
+    ("sick of", 0.6),
+    ("damn", 0.6),
+    ("hell", 0.5),
+];
+
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "don't", "doesn't", "didn't", "won't", "isn't", "can't",
+];
+const INTENSIFIERS: &[&str] = &[
+    "very",
+    "really",
+    "extremely",
+    "so",
+    "totally",
+    "completely",
+    "absolutely",
+];
+
+/// Scores for a single message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToneScore {
+    /// -1.0 (very negative) to 1.0 (very positive)
+    pub sentiment: f64,
+    /// 0.0 (civil) to 1.0 (openly hostile)
+    pub hostility: f64,
+    /// 0.0 to 1.0; shouting, exclamation runs and ultimatums
+    pub escalation: f64,
+    pub method: String,
+}
+
+/// Local lexicon scorer
+#[derive(Debug, Clone)]
+pub struct LexiconToneScorer {
+    sentiment: HashMap<&'static str, f64>,
+    hostility_words: HashMap<&'static str, f64>,
+    hostility_phrases: Vec<(&'static str, f64)>,
+}
+
+impl LexiconToneScorer {
+    pub fn new() -> Self {
+        let (phrases, words): (Vec<_>, Vec<_>) = HOSTILITY_LEXICON
+            .iter()
+            .partition(|(term, _)| term.contains(' '));
+
+        Self {
+            sentiment: SENTIMENT_LEXICON.iter().copied().collect(),
+            hostility_words: words.into_iter().copied().collect(),
+            hostility_phrases: phrases.into_iter().copied().collect(),
+        }
+    }
+
+    /// Score a message without any network calls
+    pub fn score(&self, message: &str) -> ToneScore {
+        let lower = message.to_lowercase();
+        let tokens: Vec<String> = lower
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string())
+            .collect();
+
+        let mut sentiment_sum = 0.0;
+        let mut sentiment_hits = 0usize;
+        let mut hostility_sum = 0.0;
+
+        for (i, token) in tokens.iter().enumerate() {
+            let previous = |back: usize| {
+                i.checked_sub(back)
+                    .and_then(|j| tokens.get(j))
+                    .map(|t| t.as_str())
+            };
+            let negated =
+                (1..=3).any(|b| previous(b).map(|t| NEGATIONS.contains(&t)).unwrap_or(false));
+            let intensity = if previous(1)
+                .map(|t| INTENSIFIERS.contains(&t))
+                .unwrap_or(false)
+            {
+                1.5
+            } else {
+                1.0
+            };
+
+            if let Some(weight) = self.sentiment.get(token.as_str()) {
+                // Negators are also lexicon entries; don't let them flip themselves
+                let weight = if negated && !NEGATIONS.contains(&token.as_str()) {
+                    -weight * 0.7
+                } else {
+                    *weight
+                };
+                sentiment_sum += weight * intensity;
+                sentiment_hits += 1;
+            }
+
+            if let Some(weight) = self.hostility_words.get(token.as_str()) {
+                hostility_sum += weight * intensity;
+            }
+        }
+
+        for (phrase, weight) in &self.hostility_phrases {
+            hostility_sum += phrase_count(&tokens, phrase) as f64 * weight;
+        }
+
+        let sentiment = if sentiment_hits == 0 {
+            0.0
+        } else {
+            // Normalise so a few strong words saturate without long messages dominating
+            (sentiment_sum / (sentiment_hits as f64).sqrt() / 2.0).tanh()
+        };
+        let hostility = (hostility_sum / 2.0).tanh();
+
+        let letters: Vec<char> = message.chars().filter(|c| c.is_alphabetic()).collect();
+        let caps_ratio = if letters.len() >= 10 {
+            letters.iter().filter(|c| c.is_uppercase()).count() as f64 / letters.len() as f64
+        } else {
+            0.0
+        };
+        let exclamations = message.matches('!').count() as f64;
+        let ultimatums = [
+            "or else",
+            "last chance",
+            "last time",
+            "final warning",
+            "i will take",
+        ]
+        .iter()
+        .filter(|p| phrase_count(&tokens, p) > 0)
+        .count() as f64;
+
+        let escalation =
+            (caps_ratio * 1.5 + exclamations * 0.15 + ultimatums * 0.4 + hostility * 0.5)
+                .tanh()
+                .clamp(0.0, 1.0);
+
+        ToneScore {
+            sentiment,
+            hostility,
+            escalation,
+            method: "lexicon".to_string(),
+        }
+    }
+
+    /// Blend the lexicon sentiment with an LLM estimate, keeping the local hostility and escalation
+    pub async fn score_with_refinement(
+        &self,
+        message: &str,
+        service: &(dyn AiService + Sync),
+    ) -> Result<ToneScore, AiError> {
+        let mut score = self.score(message);
+        let refined = service.analyze_communication_sentiment(message).await?;
+        score.sentiment = ((score.sentiment + refined) / 2.0).clamp(-1.0, 1.0);
+        score.method = "lexicon+llm".to_string();
+        Ok(score)
+    }
+}
+
+impl Default for LexiconToneScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One scored message in a sender's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TonePoint {
+    pub communication_id: i64,
+    pub communication_date: String,
+    pub sentiment: f64,
+    pub hostility: f64,
+    pub escalation: f64,
+}
+
+/// Alert raised when a sender's tone worsens ahead of a placement denial
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToneEscalationAlert {
+    pub placement_denial_id: i64,
+    pub denied_date: String,
+    pub sender: String,
+    pub baseline_hostility: f64,
+    pub pre_denial_hostility: f64,
+    pub pre_denial_escalation: f64,
+    pub communication_ids: Vec<i64>,
+}
+
+/// Occurrences of a space-separated phrase as whole consecutive tokens
+fn phrase_count(tokens: &[String], phrase: &str) -> usize {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    tokens
+        .windows(words.len())
+        .filter(|window| window.iter().zip(&words).all(|(token, word)| token == word))
+        .count()
+}
+
+/// Score communications added since the last run and persist the results;
+/// returns the number scored
+pub async fn score_communications(
+    pool: &DbPool,
+    scorer: &LexiconToneScorer,
+    refine_with: Option<&(dyn AiService + Sync)>,
+) -> Result<usize, AiError> {
+    let rows = sqlx::query(
+        "SELECT c.id, c.message_content FROM communications c
+         WHERE NOT EXISTS (SELECT 1 FROM communication_tone_scores t WHERE t.communication_id = c.id)
+         ORDER BY c.id",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    for row in &rows {
+        let id: i64 = row.get("id");
+        let content: Option<String> = row.get("message_content");
+        let content = content.unwrap_or_default();
+
+        let score = match refine_with {
+            Some(service) => match scorer.score_with_refinement(&content, service).await {
+                Ok(score) => score,
+                Err(e) => {
+                    tracing::warn!("LLM tone refinement failed for communication {}: {}", id, e);
+                    scorer.score(&content)
+                }
+            },
+            None => scorer.score(&content),
+        };
+
+        sqlx::query(
+            "INSERT INTO communication_tone_scores
+                (communication_id, sentiment, hostility, escalation, method)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(communication_id) DO UPDATE SET
+                sentiment = excluded.sentiment,
+                hostility = excluded.hostility,
+                escalation = excluded.escalation,
+                method = excluded.method,
+                scored_at = CURRENT_TIMESTAMP",
+        )
+        .bind(id)
+        .bind(score.sentiment)
+        .bind(score.hostility)
+        .bind(score.escalation)
+        .bind(&score.method)
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+
+        sqlx::query("UPDATE communications SET sentiment_score = ? WHERE id = ?")
+            .bind(score.sentiment)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(db_error)?;
+    }
+
+    Ok(rows.len())
+}
+
+/// Scored messages in the matters `scope` allows, grouped by sender, in date order
+pub async fn tone_timelines(
+    pool: &DbPool,
+    scope: &MatterScope,
+) -> Result<HashMap<String, Vec<TonePoint>>, AiError> {
+    let rows = sqlx::query(&format!(
+        "SELECT c.id, c.communication_date, COALESCE(c.sender, 'unknown') AS sender,
+                t.sentiment, t.hostility, t.escalation
+         FROM communications c
+         JOIN communication_tone_scores t ON t.communication_id = c.id
+         WHERE {}
+         ORDER BY c.communication_date, c.id",
+        scope.sql_filter("c.case_id")
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut timelines: HashMap<String, Vec<TonePoint>> = HashMap::new();
+    for row in rows {
+        timelines
+            .entry(row.get("sender"))
+            .or_default()
+            .push(TonePoint {
+                communication_id: row.get("id"),
+                communication_date: row.get("communication_date"),
+                sentiment: row.get("sentiment"),
+                hostility: row.get("hostility"),
+                escalation: row.get("escalation"),
+            });
+    }
+
+    Ok(timelines)
+}
+
+/// Flag senders whose hostility in the `window_days` before a denial rose by at least `min_increase`
+pub fn detect_pre_denial_escalation(
+    timelines: &HashMap<String, Vec<TonePoint>>,
+    denials: &[(i64, String)],
+    window_days: i64,
+    min_increase: f64,
+) -> Vec<ToneEscalationAlert> {
+    let mut alerts = Vec::new();
+
+    for (denial_id, denied_date) in denials {
+        let Some(denial_day) = parse_date(denied_date) else {
+            continue;
+        };
+
+        for (sender, points) in timelines {
+            let (window, baseline): (Vec<&TonePoint>, Vec<&TonePoint>) = points
+                .iter()
+                .filter_map(|p| parse_date(&p.communication_date).map(|d| (d, p)))
+                .filter(|(d, _)| *d <= denial_day)
+                .map(|(d, p)| ((denial_day - d).num_days() <= window_days, p))
+                .fold(
+                    (Vec::new(), Vec::new()),
+                    |(mut w, mut b), (in_window, p)| {
+                        if in_window {
+                            w.push(p);
+                        } else {
+                            b.push(p);
+                        }
+                        (w, b)
+                    },
+                );
+
+            // Without earlier messages there is nothing to have risen from
+            if window.is_empty() || baseline.is_empty() {
+                continue;
+            }
+
+            let mean = |points: &[&TonePoint], f: fn(&TonePoint) -> f64| {
+                points.iter().map(|p| f(p)).sum::<f64>() / points.len().max(1) as f64
+            };
+            let baseline_hostility = mean(&baseline, |p| p.hostility);
+            let pre_denial_hostility = mean(&window, |p| p.hostility);
+
+            if pre_denial_hostility - baseline_hostility >= min_increase {
+                alerts.push(ToneEscalationAlert {
+                    placement_denial_id: *denial_id,
+                    denied_date: denied_date.clone(),
+                    sender: sender.clone(),
+                    baseline_hostility,
+                    pre_denial_hostility,
+                    pre_denial_escalation: mean(&window, |p| p.escalation),
+                    communication_ids: window.iter().map(|p| p.communication_id).collect(),
+                });
+            }
+        }
+    }
+
+    alerts
+}
+
+/// Load timelines and denials in `scope` and run `detect_pre_denial_escalation`
+pub async fn pre_denial_alerts(
+    pool: &DbPool,
+    scope: &MatterScope,
+    window_days: i64,
+    min_increase: f64,
+) -> Result<Vec<ToneEscalationAlert>, AiError> {
+    let timelines = tone_timelines(pool, scope).await?;
+    let denials: Vec<(i64, String)> = sqlx::query_as(&format!(
+        "SELECT id, denied_date FROM placement_denials WHERE {} ORDER BY denied_date",
+        scope.sql_filter("case_id")
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(detect_pre_denial_escalation(
+        &timelines,
+        &denials,
+        window_days,
+        min_increase,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polarity() {
+        let scorer = LexiconToneScorer::new();
+        let positive = scorer.score("Thanks so much, I really appreciate you being flexible.");
+        let negative = scorer.score("This is ridiculous and unacceptable. I refuse.");
+
+        assert!(positive.sentiment > 0.3);
+        assert!(negative.sentiment < -0.3);
+        assert!(positive.hostility < 0.1);
+    }
+
+    #[test]
+    fn test_negation_flips_sentiment() {
+        let scorer = LexiconToneScorer::new();
+        assert!(scorer.score("That is not good").sentiment < 0.0);
+    }
+
+    #[test]
+    fn test_hostility_and_escalation() {
+        let scorer = LexiconToneScorer::new();
+        let calm = scorer.score("Can we move pickup to 5pm on Friday?");
+        let hostile =
+            scorer.score("YOU ARE A LIAR!!! Do it or else you will never see them again!");
+
+        assert!(hostile.hostility > 0.7);
+        assert!(hostile.escalation > calm.escalation + 0.5);
+    }
+
+    #[test]
+    fn test_phrases_match_whole_words() {
+        let scorer = LexiconToneScorer::new();
+        let innocuous = scorer.score("Wait by the door elsewhere, the shell path is closed");
+
+        assert_eq!(innocuous.hostility, 0.0);
+        assert!(scorer.score("Do it or else").hostility > 0.0);
+    }
+
+    #[test]
+    fn test_pre_denial_escalation_alert() {
+        let point = |id: i64, date: &str, hostility: f64| TonePoint {
+            communication_id: id,
+            communication_date: date.to_string(),
+            sentiment: 0.0,
+            hostility,
+            escalation: hostility,
+        };
+        let mut timelines = HashMap::new();
+        timelines.insert(
+            "parent_a".to_string(),
+            vec![
+                point(1, "2024-05-01", 0.0),
+                point(2, "2024-05-02", 0.1),
+                point(3, "2024-05-10", 0.8),
+                point(4, "2024-05-11", 0.9),
+            ],
+        );
+
+        let alerts =
+            detect_pre_denial_escalation(&timelines, &[(7, "2024-05-12".to_string())], 3, 0.3);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].communication_ids, vec![3, 4]);
+
+        // A sender with no messages before the window has no baseline to compare with
+        timelines.insert("parent_b".to_string(), vec![point(5, "2024-05-11", 0.9)]);
+        let alerts =
+            detect_pre_denial_escalation(&timelines, &[(7, "2024-05-12".to_string())], 3, 0.3);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].sender, "parent_a");
+    }
+
+    #[tokio::test]
+    async fn test_only_new_communications_are_scored() {
+        let pool = crate::db::create_pool("sqlite::memory:").await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let add = |content: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("INSERT INTO communications (communication_date, sender, message_content) VALUES ('2024-05-01', 'Parent A', ?)")
+                    .bind(content)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+        };
+        let scorer = LexiconToneScorer::new();
+
+        add("Thanks, that works for us.").await;
+        add("This is unacceptable and I will call my lawyer.").await;
+        assert_eq!(score_communications(&pool, &scorer, None).await.unwrap(), 2);
+        assert_eq!(score_communications(&pool, &scorer, None).await.unwrap(), 0);
+
+        add("See you Friday.").await;
+        assert_eq!(score_communications(&pool, &scorer, None).await.unwrap(), 1);
+    }
+}
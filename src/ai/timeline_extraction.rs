@@ -0,0 +1,706 @@
+// Automatic chronology building
+// Extracts dated events from exhibits and communications, normalises dates,
+// deduplicates against the existing timeline and queues candidates for reviewer approval.
+
+use crate::ai::util::db_error;
+use crate::ai::{AiError, AiService};
+use crate::db::DbPool;
+use crate::legal::ethical_walls::{MatterScope, SOURCE_MATTER_SQL};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::HashSet;
+
+/// Minimum word overlap for two events on the same date to count as duplicates
+const DUPLICATE_SIMILARITY: f64 = 0.6;
+
+/// Where a candidate event was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    Exhibit,
+    Communication,
+}
+
+impl EventSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exhibit => "exhibit",
+            Self::Communication => "communication",
+        }
+    }
+}
+
+/// A dated event extracted from a source passage, awaiting review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateEvent {
+    pub event_date: String,
+    pub event_type: String,
+    pub event_title: String,
+    pub event_description: String,
+    pub importance_level: i32,
+    pub confidence: f64,
+    pub source_type: EventSource,
+    pub source_id: i64,
+    /// Column of the source row the passage was found in
+    pub source_field: String,
+    pub source_passage: String,
+    /// Byte offsets of `source_passage` in that column
+    pub passage_start: usize,
+    pub passage_end: usize,
+}
+
+/// Review state of a queued candidate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedCandidate {
+    pub id: i64,
+    pub status: String,
+    pub timeline_event_id: Option<i64>,
+    #[serde(flatten)]
+    pub candidate: CandidateEvent,
+}
+
+/// Outcome of an extraction run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractionSummary {
+    pub sources_scanned: usize,
+    pub events_found: usize,
+    pub duplicates_skipped: usize,
+    pub queued: usize,
+}
+
+/// Regex-based date and event extractor
+pub struct TimelineExtractor {
+    iso: Regex,
+    numeric: Regex,
+    month_first: Regex,
+    day_first: Regex,
+}
+
+const MONTHS: &str = "jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sep(?:t(?:ember)?)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?";
+
+impl TimelineExtractor {
+    pub fn new() -> Self {
+        Self {
+            iso: Regex::new(r"\b(\d{4})-(\d{1,2})-(\d{1,2})\b").unwrap(),
+            numeric: Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{2}|\d{4})\b").unwrap(),
+            month_first: Regex::new(&format!(
+                r"(?i)\b({})\.?\s+(\d{{1,2}})(?:st|nd|rd|th)?,?\s+(\d{{4}})\b",
+                MONTHS
+            ))
+            .unwrap(),
+            day_first: Regex::new(&format!(
+                r"(?i)\b(\d{{1,2}})(?:st|nd|rd|th)?\s+(?:of\s+)?({})\.?,?\s+(\d{{4}})\b",
+                MONTHS
+            ))
+            .unwrap(),
+        }
+    }
+
+    /// Find every date in the text, normalised, with its byte span
+    pub fn find_dates(&self, text: &str) -> Vec<(NaiveDate, usize, usize)> {
+        let mut found: Vec<(NaiveDate, usize, usize)> = Vec::new();
+
+        for caps in self.iso.captures_iter(text) {
+            let m = caps.get(0).unwrap();
+            if let Some(date) = ymd(&caps[1], &caps[2], &caps[3]) {
+                found.push((date, m.start(), m.end()));
+            }
+        }
+        for caps in self.numeric.captures_iter(text) {
+            let m = caps.get(0).unwrap();
+            // US court documents use month/day/year
+            let year = if caps[3].len() == 2 {
+                format!("20{}", &caps[3])
+            } else {
+                caps[3].to_string()
+            };
+            if let Some(date) = ymd(&year, &caps[1], &caps[2]) {
+                found.push((date, m.start(), m.end()));
+            }
+        }
+        for caps in self.month_first.captures_iter(text) {
+            let m = caps.get(0).unwrap();
+            if let Some(date) = month_name(&caps[1]).and_then(|mo| ymd(&caps[3], &mo, &caps[2])) {
+                found.push((date, m.start(), m.end()));
+            }
+        }
+        for caps in self.day_first.captures_iter(text) {
+            let m = caps.get(0).unwrap();
+            if let Some(date) = month_name(&caps[2]).and_then(|mo| ymd(&caps[3], &mo, &caps[1])) {
+                found.push((date, m.start(), m.end()));
+            }
+        }
+
+        found.sort_by_key(|(_, start, _)| *start);
+        // Patterns can overlap (e.g. "5 March 2024" inside a longer match); keep the first
+        let mut last_end = 0;
+        found.retain(|(_, start, end)| {
+            let keep = *start >= last_end;
+            if keep {
+                last_end = *end;
+            }
+            keep
+        });
+        found
+    }
+
+    /// Extract one candidate per dated sentence of `text`, the `source_field`
+    /// column of the source row
+    pub fn extract(
+        &self,
+        text: &str,
+        source_type: EventSource,
+        source_id: i64,
+        source_field: &str,
+    ) -> Vec<CandidateEvent> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (date, date_start, date_end) in self.find_dates(text) {
+            let (start, end) = sentence_bounds(text, date_start, date_end);
+            if !seen.insert((date, start)) {
+                continue;
+            }
+            // Point the offsets at exactly the text that is kept
+            let start = end - text[start..end].trim_start().len();
+            let end = start + text[start..end].trim_end().len();
+
+            let passage = text[start..end].to_string();
+            let (event_type, importance) = classify(&passage);
+            events.push(CandidateEvent {
+                event_date: date.format("%Y-%m-%d").to_string(),
+                event_type: event_type.to_string(),
+                event_title: title_from(&passage),
+                event_description: passage.clone(),
+                importance_level: importance,
+                // Explicit dates in a single sentence are reliable; long passages less so
+                confidence: if passage.len() < 300 { 0.8 } else { 0.6 },
+                source_type,
+                source_id,
+                source_field: source_field.to_string(),
+                source_passage: passage,
+                passage_start: start,
+                passage_end: end,
+            });
+        }
+
+        events
+    }
+
+    /// Ask the LLM for events in a passage and keep only those with parseable dates
+    pub async fn refine_with_llm(
+        &self,
+        candidate: &CandidateEvent,
+        service: &(dyn AiService + Sync),
+    ) -> Result<Vec<CandidateEvent>, AiError> {
+        let events = service
+            .generate_timeline_events(&candidate.source_passage)
+            .await?;
+
+        Ok(events
+            .iter()
+            .filter_map(|event| {
+                let date = event
+                    .get("event_date")
+                    .or_else(|| event.get("date"))
+                    .and_then(|d| d.as_str())
+                    .and_then(|d| self.find_dates(d).first().map(|(date, _, _)| *date))?;
+                let title = event
+                    .get("event_title")
+                    .or_else(|| event.get("title"))
+                    .and_then(|t| t.as_str())
+                    .unwrap_or(&candidate.event_title);
+
+                Some(CandidateEvent {
+                    event_date: date.format("%Y-%m-%d").to_string(),
+                    event_type: event
+                        .get("event_type")
+                        .or_else(|| event.get("type"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or(&candidate.event_type)
+                        .to_string(),
+                    event_title: title.to_string(),
+                    event_description: event
+                        .get("event_description")
+                        .or_else(|| event.get("description"))
+                        .and_then(|d| d.as_str())
+                        .unwrap_or(&candidate.event_description)
+                        .to_string(),
+                    importance_level: event
+                        .get("importance_level")
+                        .and_then(|i| i.as_i64())
+                        .map(|i| i.clamp(1, 5) as i32)
+                        .unwrap_or(candidate.importance_level),
+                    confidence: candidate.confidence.max(0.85),
+                    ..candidate.clone()
+                })
+            })
+            .collect())
+    }
+}
+
+impl Default for TimelineExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn ymd(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+}
+
+fn month_name(name: &str) -> Option<String> {
+    let month = match &name.to_lowercase()[..3] {
+        "jan" => 1,
+        "feb" => 2,
+        "mar" => 3,
+        "apr" => 4,
+        "may" => 5,
+        "jun" => 6,
+        "jul" => 7,
+        "aug" => 8,
+        "sep" => 9,
+        "oct" => 10,
+        "nov" => 11,
+        "dec" => 12,
+        _ => return None,
+    };
+    Some(month.to_string())
+}
+
+/// Byte range of the sentence containing the date at `date_start..date_end`
+fn sentence_bounds(text: &str, date_start: usize, date_end: usize) -> (usize, usize) {
+    // Abbreviations like "Mar. 5" put periods inside sentences, so a break needs trailing space
+    let is_break = |i: usize, c: char| {
+        matches!(c, '.' | '!' | '?' | '\n')
+            && text[i + c.len_utf8()..]
+                .chars()
+                .next()
+                .map(|n| n.is_whitespace())
+                .unwrap_or(true)
+    };
+
+    let start = text[..date_start]
+        .char_indices()
+        .rev()
+        .find(|(i, c)| is_break(*i, *c))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0);
+    let end = text[date_end..]
+        .char_indices()
+        .find(|(i, c)| is_break(date_end + i, *c))
+        .map(|(i, c)| date_end + i + c.len_utf8())
+        .unwrap_or(text.len());
+
+    (start, end)
+}
+
+fn classify(passage: &str) -> (&'static str, i32) {
+    let lower = passage.to_lowercase();
+    let rules: [(&[&str], &str, i32); 6] = [
+        (
+            &["order", "court", "hearing", "judge", "motion", "filed"],
+            "court",
+            5,
+        ),
+        (
+            &["denied", "refused", "cancelled", "withheld", "violation"],
+            "violation",
+            4,
+        ),
+        (&["mediation", "mediator"], "mediation", 4),
+        (&["agreement", "stipulation", "agreed"], "agreement", 4),
+        (
+            &["doctor", "medical", "school", "appointment"],
+            "appointment",
+            2,
+        ),
+        (&["email", "text", "message", "call"], "communication", 2),
+    ];
+
+    rules
+        .iter()
+        .find(|(keywords, _, _)| keywords.iter().any(|k| lower.contains(k)))
+        .map(|(_, event_type, importance)| (*event_type, *importance))
+        .unwrap_or(("event", 3))
+}
+
+fn title_from(passage: &str) -> String {
+    let words: Vec<&str> = passage.split_whitespace().take(10).collect();
+    let mut title = words.join(" ");
+    if passage.split_whitespace().count() > 10 {
+        title.push('…');
+    }
+    title
+}
+
+fn word_set(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2 && !w.chars().all(|c| c.is_ascii_digit()))
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Overlap coefficient of the significant words in two strings.
+/// Titles are much shorter than descriptions, so this beats Jaccard here.
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (word_set(a), word_set(b));
+    let smaller = a.len().min(b.len());
+    if smaller == 0 {
+        return if a.is_empty() && b.is_empty() {
+            1.0
+        } else {
+            0.0
+        };
+    }
+    a.intersection(&b).count() as f64 / smaller as f64
+}
+
+/// Drop candidates that match an existing event (same date, similar text) or each other
+pub fn deduplicate(
+    candidates: Vec<CandidateEvent>,
+    existing: &[(String, String)],
+) -> (Vec<CandidateEvent>, usize) {
+    let mut kept: Vec<CandidateEvent> = Vec::new();
+    let mut skipped = 0;
+
+    for candidate in candidates {
+        let text = format!("{} {}", candidate.event_title, candidate.event_description);
+        let duplicate = existing.iter().any(|(date, existing_text)| {
+            *date == candidate.event_date
+                && text_similarity(&text, existing_text) >= DUPLICATE_SIMILARITY
+        }) || kept.iter().any(|k| {
+            k.event_date == candidate.event_date
+                && text_similarity(&text, &format!("{} {}", k.event_title, k.event_description))
+                    >= DUPLICATE_SIMILARITY
+        });
+
+        if duplicate {
+            skipped += 1;
+        } else {
+            kept.push(candidate);
+        }
+    }
+
+    (kept, skipped)
+}
+
+/// Scan exhibits and communications and queue new candidate events for review
+pub async fn extract_and_queue(
+    pool: &DbPool,
+    extractor: &TimelineExtractor,
+    refine_with: Option<&(dyn AiService + Sync)>,
+) -> Result<ExtractionSummary, AiError> {
+    let mut summary = ExtractionSummary::default();
+    let mut candidates = Vec::new();
+
+    // Each field is scanned on its own so offsets point into a stored column
+    let mut fields: Vec<(EventSource, i64, &str, String)> = Vec::new();
+    let exhibits = sqlx::query("SELECT id, ai_extracted_text, description FROM exhibits")
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    for row in &exhibits {
+        summary.sources_scanned += 1;
+        let extracted: Option<String> = row.get("ai_extracted_text");
+        let (field, text) = match extracted {
+            Some(text) => ("ai_extracted_text", text),
+            None => ("description", row.get::<Option<String>, _>("description").unwrap_or_default()),
+        };
+        fields.push((EventSource::Exhibit, row.get("id"), field, text));
+    }
+    let communications = sqlx::query("SELECT id, subject, message_content FROM communications")
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+    for row in &communications {
+        summary.sources_scanned += 1;
+        for field in ["subject", "message_content"] {
+            if let Some(text) = row.get::<Option<String>, _>(field) {
+                fields.push((EventSource::Communication, row.get("id"), field, text));
+            }
+        }
+    }
+
+    for (source_type, source_id, field, text) in &fields {
+        for candidate in extractor.extract(text, *source_type, *source_id, field) {
+            match refine_with {
+                Some(service) => match extractor.refine_with_llm(&candidate, service).await {
+                    Ok(refined) if !refined.is_empty() => candidates.extend(refined),
+                    Ok(_) => candidates.push(candidate),
+                    Err(e) => {
+                        tracing::warn!("LLM timeline refinement failed: {}", e);
+                        candidates.push(candidate);
+                    }
+                },
+                None => candidates.push(candidate),
+            }
+        }
+    }
+    summary.events_found = candidates.len();
+
+    // Compare against the timeline and anything already queued or rejected
+    let existing: Vec<(String, String)> = sqlx::query_as(
+        "SELECT event_date, event_title || ' ' || COALESCE(event_description, '') FROM timeline_events
+         UNION ALL
+         SELECT event_date, event_title || ' ' || event_description FROM timeline_event_candidates",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    let (new_candidates, skipped) = deduplicate(candidates, &existing);
+    summary.duplicates_skipped = skipped;
+
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    for candidate in &new_candidates {
+        sqlx::query(
+            "INSERT INTO timeline_event_candidates
+                (event_date, event_type, event_title, event_description, importance_level,
+                 confidence, source_type, source_id, source_field, source_passage,
+                 passage_start, passage_end)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&candidate.event_date)
+        .bind(&candidate.event_type)
+        .bind(&candidate.event_title)
+        .bind(&candidate.event_description)
+        .bind(candidate.importance_level)
+        .bind(candidate.confidence)
+        .bind(candidate.source_type.as_str())
+        .bind(candidate.source_id)
+        .bind(&candidate.source_field)
+        .bind(&candidate.source_passage)
+        .bind(candidate.passage_start as i64)
+        .bind(candidate.passage_end as i64)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+    summary.queued = new_candidates.len();
+
+    Ok(summary)
+}
+
+/// List queued candidates from matters in `scope`, optionally filtered by status
+pub async fn list_candidates(
+    pool: &DbPool,
+    status: Option<&str>,
+    scope: &MatterScope,
+) -> Result<Vec<QueuedCandidate>, AiError> {
+    let rows = sqlx::query(&format!(
+        "SELECT * FROM timeline_event_candidates
+         WHERE (? IS NULL OR status = ?) AND {}
+         ORDER BY event_date, id",
+        scope.sql_filter(SOURCE_MATTER_SQL)
+    ))
+    .bind(status)
+    .bind(status)
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(rows
+        .iter()
+        .map(|row| QueuedCandidate {
+            id: row.get("id"),
+            status: row.get("status"),
+            timeline_event_id: row.get("timeline_event_id"),
+            candidate: CandidateEvent {
+                event_date: row.get("event_date"),
+                event_type: row.get("event_type"),
+                event_title: row.get("event_title"),
+                event_description: row.get("event_description"),
+                importance_level: row.get("importance_level"),
+                confidence: row.get("confidence"),
+                source_type: if row.get::<String, _>("source_type") == "exhibit" {
+                    EventSource::Exhibit
+                } else {
+                    EventSource::Communication
+                },
+                source_id: row.get("source_id"),
+                source_field: row.get("source_field"),
+                source_passage: row.get("source_passage"),
+                passage_start: row.get::<i64, _>("passage_start") as usize,
+                passage_end: row.get::<i64, _>("passage_end") as usize,
+            },
+        })
+        .collect())
+}
+
+/// Approve a pending candidate, inserting it into the timeline with `ai_generated = 1`
+///
+/// The event is filed under the matter of the candidate's source. Candidates
+/// from matters outside `scope` are treated as missing.
+pub async fn approve_candidate(
+    pool: &DbPool,
+    id: i64,
+    reviewer: &str,
+    scope: &MatterScope,
+) -> Result<i64, AiError> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let row = sqlx::query(&format!(
+        "SELECT event_date, event_type, event_title, event_description, importance_level, confidence,
+                {matter} AS case_id
+         FROM timeline_event_candidates WHERE id = ? AND status = 'pending' AND {filter}",
+        matter = SOURCE_MATTER_SQL,
+        filter = scope.sql_filter(SOURCE_MATTER_SQL)
+    ))
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| AiError::ModelError(format!("No pending timeline candidate {}", id)))?;
+
+    let event_id = sqlx::query(
+        "INSERT INTO timeline_events
+            (event_date, event_type, event_title, event_description, importance_level,
+             ai_generated, ai_confidence, case_id)
+         VALUES (?, ?, ?, ?, ?, 1, ?, ?)",
+    )
+    .bind(row.get::<String, _>("event_date"))
+    .bind(row.get::<String, _>("event_type"))
+    .bind(row.get::<String, _>("event_title"))
+    .bind(row.get::<String, _>("event_description"))
+    .bind(row.get::<i32, _>("importance_level"))
+    .bind(row.get::<f64, _>("confidence"))
+    .bind(row.get::<Option<i64>, _>("case_id"))
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    sqlx::query(
+        "UPDATE timeline_event_candidates
+         SET status = 'approved', reviewer = ?, reviewed_at = CURRENT_TIMESTAMP, timeline_event_id = ?
+         WHERE id = ?",
+    )
+    .bind(reviewer)
+    .bind(event_id)
+    .bind(id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(event_id)
+}
+
+/// Reject a pending candidate; it stays queued so it isn't proposed again
+pub async fn reject_candidate(
+    pool: &DbPool,
+    id: i64,
+    reviewer: &str,
+    scope: &MatterScope,
+) -> Result<(), AiError> {
+    let result = sqlx::query(&format!(
+        "UPDATE timeline_event_candidates
+         SET status = 'rejected', reviewer = ?, reviewed_at = CURRENT_TIMESTAMP
+         WHERE id = ? AND status = 'pending' AND {}",
+        scope.sql_filter(SOURCE_MATTER_SQL)
+    ))
+    .bind(reviewer)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(AiError::ModelError(format!(
+            "No pending timeline candidate {}",
+            id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_formats_normalise() {
+        let extractor = TimelineExtractor::new();
+        let text = "Filed 2024-02-11. Hearing on 03/05/2024. Order entered March 7th, 2024. \
+                    Mediation held 12 April 2024.";
+        let dates: Vec<String> = extractor
+            .find_dates(text)
+            .iter()
+            .map(|(d, _, _)| d.format("%Y-%m-%d").to_string())
+            .collect();
+
+        assert_eq!(
+            dates,
+            vec!["2024-02-11", "2024-03-05", "2024-03-07", "2024-04-12"]
+        );
+    }
+
+    #[test]
+    fn test_extract_keeps_passage_provenance() {
+        let extractor = TimelineExtractor::new();
+        let text = "Background notes. On Jun. 16, 2024 the Father's Day placement was denied by text. Later.";
+        let events = extractor.extract(text, EventSource::Communication, 42, "message_content");
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.event_date, "2024-06-16");
+        assert_eq!(event.event_type, "violation");
+        assert_eq!(&text[event.passage_start..event.passage_end], event.source_passage);
+        assert_eq!((event.source_id, event.source_field.as_str()), (42, "message_content"));
+    }
+
+    #[test]
+    fn test_deduplicates_against_existing_timeline() {
+        let extractor = TimelineExtractor::new();
+        let events = extractor.extract(
+            "Holiday placement completely denied on 2024-06-14.",
+            EventSource::Exhibit,
+            1,
+            "description",
+        );
+        let existing = vec![(
+            "2024-06-14".to_string(),
+            "Holiday Placement Denied Complete denial of holiday placement".to_string(),
+        )];
+
+        let (kept, skipped) = deduplicate(events, &existing);
+        assert!(kept.is_empty());
+        assert_eq!(skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_queued_provenance_slices_the_stored_field() {
+        let pool = crate::db::create_pool("sqlite::memory:").await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let message = "Thanks for the call.  On 2024-07-02 the exchange at the library was cancelled. See you.";
+        sqlx::query(
+            "INSERT INTO communications (communication_date, sender, subject, message_content)
+             VALUES ('2024-07-03', 'Parent B', 'Hearing set for 08/14/2024', ?)",
+        )
+        .bind(message)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let summary = extract_and_queue(&pool, &TimelineExtractor::new(), None).await.unwrap();
+        assert_eq!(summary.queued, 2);
+
+        for queued in list_candidates(&pool, None, &MatterScope::unrestricted()).await.unwrap() {
+            let event = queued.candidate;
+            let field: String = sqlx::query_scalar(&format!(
+                "SELECT {} FROM communications WHERE id = ?",
+                event.source_field
+            ))
+            .bind(event.source_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!(&field[event.passage_start..event.passage_end], event.source_passage);
+        }
+    }
+}
@@ -0,0 +1,963 @@
+// Speech-to-text for voice input and recorded calls
+// Decodes WAV natively (OGG/WebM via ffmpeg), chunks long recordings and
+// transcribes them through a Whisper-compatible HTTP API or a local whisper.cpp binary.
+
+use crate::ai::util::db_error;
+use crate::ai::AiError;
+use crate::db::DbPool;
+use reqwest::{multipart, Client};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Sample rate expected by Whisper models
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Which transcription backend to use
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TranscriptionBackendKind {
+    /// OpenAI `/audio/transcriptions` or any server exposing the same API
+    WhisperHttp,
+    /// Local whisper.cpp command-line binary
+    WhisperCpp,
+}
+
+/// Transcription settings, read from the environment by default
+#[derive(Debug, Clone)]
+pub struct TranscriptionConfig {
+    pub backend: TranscriptionBackendKind,
+    pub api_base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub whisper_cpp_binary: String,
+    pub whisper_cpp_model: String,
+    pub ffmpeg_binary: String,
+    pub chunk_seconds: u32,
+    pub overlap_seconds: u32,
+    pub language: Option<String>,
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        let backend = match std::env::var("TRANSCRIPTION_BACKEND").as_deref() {
+            Ok("whisper_cpp") | Ok("whisper.cpp") => TranscriptionBackendKind::WhisperCpp,
+            _ => TranscriptionBackendKind::WhisperHttp,
+        };
+
+        Self {
+            backend,
+            api_base_url: std::env::var("WHISPER_API_URL")
+                .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            api_key: std::env::var("WHISPER_API_KEY")
+                .or_else(|_| std::env::var("OPENAI_API_KEY"))
+                .ok(),
+            model: std::env::var("WHISPER_MODEL").unwrap_or_else(|_| "whisper-1".to_string()),
+            whisper_cpp_binary: std::env::var("WHISPER_CPP_BIN")
+                .unwrap_or_else(|_| "whisper-cli".to_string()),
+            whisper_cpp_model: std::env::var("WHISPER_CPP_MODEL")
+                .unwrap_or_else(|_| "models/ggml-base.en.bin".to_string()),
+            ffmpeg_binary: std::env::var("FFMPEG_BIN").unwrap_or_else(|_| "ffmpeg".to_string()),
+            chunk_seconds: 300,
+            overlap_seconds: 2,
+            language: std::env::var("WHISPER_LANGUAGE").ok(),
+        }
+    }
+}
+
+/// Container format detected from the leading bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioFormat {
+    Wav,
+    Ogg,
+    WebM,
+}
+
+impl AudioFormat {
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+            Some(Self::Wav)
+        } else if data.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            Some(Self::WebM)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Ogg => "ogg",
+            Self::WebM => "webm",
+        }
+    }
+
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::Ogg => "audio/ogg",
+            Self::WebM => "audio/webm",
+        }
+    }
+}
+
+/// Mono PCM audio with samples in [-1.0, 1.0]
+#[derive(Debug, Clone)]
+pub struct PcmAudio {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl PcmAudio {
+    pub fn duration_ms(&self) -> u64 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        self.samples.len() as u64 * 1000 / self.sample_rate as u64
+    }
+
+    /// Linear-interpolation resample, good enough for speech recognition input
+    pub fn resample(&self, target_rate: u32) -> Result<PcmAudio, AiError> {
+        if self.sample_rate == 0 || target_rate == 0 {
+            return Err(AiError::InvalidInput(
+                "Cannot resample audio with a zero sample rate".to_string(),
+            ));
+        }
+        if self.sample_rate == target_rate || self.samples.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let ratio = self.sample_rate as f64 / target_rate as f64;
+        let out_len = (self.samples.len() as f64 / ratio).floor() as usize;
+        let samples = (0..out_len)
+            .map(|i| {
+                let pos = i as f64 * ratio;
+                let idx = pos.floor() as usize;
+                let frac = (pos - idx as f64) as f32;
+                let a = self.samples[idx];
+                let b = *self.samples.get(idx + 1).unwrap_or(&a);
+                a + (b - a) * frac
+            })
+            .collect();
+
+        Ok(PcmAudio {
+            sample_rate: target_rate,
+            samples,
+        })
+    }
+
+    /// Encode as a 16-bit mono WAV file
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = (self.samples.len() * 2) as u32;
+        let mut out = Vec::with_capacity(44 + data_len as usize);
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    /// Split into chunks of `chunk_seconds` that overlap by `overlap_seconds`
+    pub fn chunks(&self, chunk_seconds: u32, overlap_seconds: u32) -> Vec<AudioChunk> {
+        let chunk_len = (chunk_seconds.max(1) * self.sample_rate) as usize;
+        let overlap = (overlap_seconds * self.sample_rate) as usize;
+        let step = chunk_len.saturating_sub(overlap).max(1);
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < self.samples.len() {
+            let end = (start + chunk_len).min(self.samples.len());
+            chunks.push(AudioChunk {
+                offset_ms: start as u64 * 1000 / self.sample_rate as u64,
+                audio: PcmAudio {
+                    sample_rate: self.sample_rate,
+                    samples: self.samples[start..end].to_vec(),
+                },
+            });
+            if end == self.samples.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+}
+
+/// A slice of a longer recording and where it starts
+#[derive(Debug, Clone)]
+pub struct AudioChunk {
+    pub offset_ms: u64,
+    pub audio: PcmAudio,
+}
+
+/// Decode a RIFF/WAVE file (integer PCM or 32-bit float) to mono
+pub fn decode_wav(data: &[u8]) -> Result<PcmAudio, AiError> {
+    if AudioFormat::detect(data) != Some(AudioFormat::Wav) {
+        return Err(AiError::InvalidInput("Not a RIFF/WAVE file".to_string()));
+    }
+
+    let read_u16 = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+    let read_u32 =
+        |pos: usize| u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = read_u32(pos + 4) as usize;
+        let body = pos + 8;
+        // Streamed WAV (e.g. ffmpeg writing to a pipe) leaves the data size unset
+        let body_end = if size == 0 || body + size > data.len() {
+            data.len()
+        } else {
+            body + size
+        };
+
+        if id == b"fmt " && size >= 16 && body + 16 <= data.len() {
+            let mut tag = read_u16(body);
+            let channels = read_u16(body + 2);
+            let rate = read_u32(body + 4);
+            let bits = read_u16(body + 14);
+            // WAVE_FORMAT_EXTENSIBLE stores the real format in the sub-format GUID
+            if tag == 0xFFFE && size >= 26 && body + 26 <= data.len() {
+                tag = read_u16(body + 24);
+            }
+            if channels == 0 || rate == 0 {
+                return Err(AiError::InvalidInput(format!(
+                    "WAV declares {} channels at {} Hz",
+                    channels, rate
+                )));
+            }
+            format = Some((tag, channels, rate, bits));
+        } else if id == b"data" {
+            let (tag, channels, rate, bits) = format
+                .ok_or_else(|| AiError::InvalidInput("WAV data before fmt chunk".to_string()))?;
+            return decode_pcm(&data[body..body_end], tag, channels, rate, bits);
+        }
+
+        // Chunks are padded to an even length
+        pos = body + size + (size & 1);
+    }
+
+    Err(AiError::InvalidInput(
+        "WAV file has no data chunk".to_string(),
+    ))
+}
+
+fn decode_pcm(
+    bytes: &[u8],
+    tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+) -> Result<PcmAudio, AiError> {
+    let width = (bits as usize).div_ceil(8);
+    if width == 0 {
+        return Err(AiError::InvalidInput(
+            "WAV has zero bits per sample".to_string(),
+        ));
+    }
+
+    let sample_at = |b: &[u8]| -> Option<f32> {
+        match (tag, bits) {
+            (1, 8) => Some((b[0] as f32 - 128.0) / 128.0),
+            (1, 16) => Some(i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0),
+            (1, 24) => Some((i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0),
+            (1, 32) => Some(i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0),
+            (3, 32) => Some(f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            _ => None,
+        }
+    };
+
+    let frame = width * channels as usize;
+    let mut samples = Vec::with_capacity(bytes.len() / frame.max(1));
+    for frame_bytes in bytes.chunks_exact(frame) {
+        let mut sum = 0.0;
+        for channel in frame_bytes.chunks_exact(width) {
+            sum += sample_at(channel).ok_or_else(|| {
+                AiError::InvalidInput(format!(
+                    "Unsupported WAV encoding: format {} with {} bits",
+                    tag, bits
+                ))
+            })?;
+        }
+        samples.push(sum / channels as f32);
+    }
+
+    Ok(PcmAudio {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Decode any supported container to mono PCM; OGG and WebM go through ffmpeg
+pub async fn decode_audio(data: &[u8], ffmpeg_binary: &str) -> Result<PcmAudio, AiError> {
+    match AudioFormat::detect(data) {
+        Some(AudioFormat::Wav) => decode_wav(data),
+        Some(AudioFormat::Ogg) | Some(AudioFormat::WebM) => {
+            let wav = transcode_to_wav(data, ffmpeg_binary).await?;
+            decode_wav(&wav)
+        }
+        None => Err(AiError::InvalidInput(
+            "Unrecognised audio format; expected WAV, OGG or WebM".to_string(),
+        )),
+    }
+}
+
+async fn transcode_to_wav(data: &[u8], ffmpeg_binary: &str) -> Result<Vec<u8>, AiError> {
+    let mut child = Command::new(ffmpeg_binary)
+        .args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"])
+        .args([
+            "-f",
+            "wav",
+            "-ac",
+            "1",
+            "-ar",
+            &WHISPER_SAMPLE_RATE.to_string(),
+            "pipe:1",
+        ])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| AiError::ConfigError(format!("Failed to start {}: {}", ffmpeg_binary, e)))?;
+
+    // Feed stdin from a separate task so a full stdout pipe can't deadlock us
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| AiError::ModelError("ffmpeg stdin unavailable".to_string()))?;
+    let input = data.to_vec();
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&input).await;
+    });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| AiError::ModelError(format!("ffmpeg failed: {}", e)))?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        return Err(AiError::ModelError(format!(
+            "ffmpeg could not decode audio: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// A timed piece of transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Full transcript of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    pub duration_ms: u64,
+    pub backend: String,
+    pub language: Option<String>,
+}
+
+/// A speech recognition engine that transcribes one chunk of 16 kHz mono WAV
+#[async_trait::async_trait]
+pub trait TranscriptionBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Segment timestamps are relative to the start of the chunk
+    async fn transcribe_chunk(&self, wav: &[u8]) -> Result<Vec<TranscriptSegment>, AiError>;
+}
+
+/// Whisper-compatible HTTP API (`POST {base}/audio/transcriptions`)
+pub struct WhisperHttpBackend {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    language: Option<String>,
+}
+
+impl WhisperHttpBackend {
+    pub fn new(config: &TranscriptionConfig) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: config.api_base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            language: config.language.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VerboseTranscription {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerboseSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+#[async_trait::async_trait]
+impl TranscriptionBackend for WhisperHttpBackend {
+    fn name(&self) -> &str {
+        "whisper_http"
+    }
+
+    async fn transcribe_chunk(&self, wav: &[u8]) -> Result<Vec<TranscriptSegment>, AiError> {
+        let file = multipart::Part::bytes(wav.to_vec())
+            .file_name("chunk.wav")
+            .mime_str("audio/wav")?;
+        let mut form = multipart::Form::new()
+            .part("file", file)
+            .text("model", self.model.clone())
+            .text("response_format", "verbose_json")
+            .text("timestamp_granularities[]", "segment");
+        if let Some(language) = &self.language {
+            form = form.text("language", language.clone());
+        }
+
+        let mut request = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .multipart(form);
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AiError::ModelError(format!(
+                "Transcription API error {}: {}",
+                status, body
+            )));
+        }
+
+        let result: VerboseTranscription = response.json().await?;
+        if result.segments.is_empty() {
+            // Servers without segment support still return the plain text
+            return whole_clip(wav, &result.text);
+        }
+
+        Ok(result
+            .segments
+            .into_iter()
+            .map(|s| TranscriptSegment {
+                start_ms: (s.start * 1000.0) as u64,
+                end_ms: (s.end * 1000.0) as u64,
+                text: s.text.trim().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// One segment covering the whole clip, for text that came back without timings
+fn whole_clip(wav: &[u8], text: &str) -> Result<Vec<TranscriptSegment>, AiError> {
+    Ok(vec![TranscriptSegment {
+        start_ms: 0,
+        end_ms: decode_wav(wav)?.duration_ms(),
+        text: text.trim().to_string(),
+    }])
+}
+
+/// Local whisper.cpp process writing JSON output (`-oj`)
+pub struct WhisperCppBackend {
+    binary: String,
+    model_path: String,
+    language: Option<String>,
+}
+
+impl WhisperCppBackend {
+    pub fn new(config: &TranscriptionConfig) -> Self {
+        Self {
+            binary: config.whisper_cpp_binary.clone(),
+            model_path: config.whisper_cpp_model.clone(),
+            language: config.language.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperCppOutput {
+    transcription: Vec<WhisperCppSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperCppSegment {
+    offsets: WhisperCppOffsets,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperCppOffsets {
+    from: u64,
+    to: u64,
+}
+
+#[async_trait::async_trait]
+impl TranscriptionBackend for WhisperCppBackend {
+    fn name(&self) -> &str {
+        "whisper_cpp"
+    }
+
+    async fn transcribe_chunk(&self, wav: &[u8]) -> Result<Vec<TranscriptSegment>, AiError> {
+        let stem = std::env::temp_dir().join(format!("moodbridge-{}", uuid::Uuid::new_v4()));
+        let wav_path = stem.with_extension("wav");
+        let json_path = stem.with_extension("json");
+
+        tokio::fs::write(&wav_path, wav)
+            .await
+            .map_err(|e| AiError::ModelError(format!("Failed to write audio chunk: {}", e)))?;
+
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("-m")
+            .arg(&self.model_path)
+            .arg("-f")
+            .arg(&wav_path)
+            .arg("-oj")
+            .arg("-of")
+            .arg(&stem)
+            .arg("-np");
+        if let Some(language) = &self.language {
+            command.arg("-l").arg(language);
+        }
+
+        let result = command.output().await;
+        let _ = tokio::fs::remove_file(&wav_path).await;
+        let output = result
+            .map_err(|e| AiError::ConfigError(format!("Failed to start {}: {}", self.binary, e)))?;
+
+        if !output.status.success() {
+            let _ = tokio::fs::remove_file(&json_path).await;
+            return Err(AiError::ModelError(format!(
+                "whisper.cpp failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let json = tokio::fs::read_to_string(&json_path)
+            .await
+            .map_err(|e| AiError::ModelError(format!("whisper.cpp produced no output: {}", e)));
+        let _ = tokio::fs::remove_file(&json_path).await;
+        let parsed: WhisperCppOutput = serde_json::from_str(&json?)?;
+
+        Ok(parsed
+            .transcription
+            .into_iter()
+            .map(|s| TranscriptSegment {
+                start_ms: s.offsets.from,
+                end_ms: s.offsets.to,
+                text: s.text.trim().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Decodes, chunks and transcribes recordings with the configured backend
+#[derive(Clone)]
+pub struct Transcriber {
+    backend: Arc<dyn TranscriptionBackend>,
+    config: TranscriptionConfig,
+}
+
+impl Transcriber {
+    pub fn new(config: TranscriptionConfig) -> Self {
+        let backend: Arc<dyn TranscriptionBackend> = match config.backend {
+            TranscriptionBackendKind::WhisperHttp => Arc::new(WhisperHttpBackend::new(&config)),
+            TranscriptionBackendKind::WhisperCpp => Arc::new(WhisperCppBackend::new(&config)),
+        };
+        Self { backend, config }
+    }
+
+    /// Use a custom backend (e.g. a mock in tests)
+    pub fn with_backend(
+        config: TranscriptionConfig,
+        backend: Arc<dyn TranscriptionBackend>,
+    ) -> Self {
+        Self { backend, config }
+    }
+
+    pub async fn transcribe(&self, audio_data: &[u8]) -> Result<Transcript, AiError> {
+        let pcm = decode_audio(audio_data, &self.config.ffmpeg_binary)
+            .await?
+            .resample(WHISPER_SAMPLE_RATE)?;
+
+        let mut segments: Vec<TranscriptSegment> = Vec::new();
+        for chunk in pcm.chunks(self.config.chunk_seconds, self.config.overlap_seconds) {
+            let chunk_segments = self.backend.transcribe_chunk(&chunk.audio.to_wav()).await?;
+            merge_segments(&mut segments, chunk.offset_ms, chunk_segments);
+        }
+
+        let text = segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(Transcript {
+            text,
+            segments,
+            duration_ms: pcm.duration_ms(),
+            backend: self.backend.name().to_string(),
+            language: self.config.language.clone(),
+        })
+    }
+}
+
+/// Shift chunk-relative segments to absolute time, dropping what the previous chunk already covered
+///
+/// A segment that starts inside the overlap keeps the words after it. Backends give no
+/// word timings, so words are assumed to be spread evenly over their segment.
+fn merge_segments(
+    merged: &mut Vec<TranscriptSegment>,
+    offset_ms: u64,
+    chunk: Vec<TranscriptSegment>,
+) {
+    let covered_until = merged.last().map(|s| s.end_ms).unwrap_or(0);
+
+    for segment in chunk {
+        let mut start_ms = segment.start_ms + offset_ms;
+        let end_ms = (segment.end_ms + offset_ms).max(start_ms);
+        let mut text = segment.text;
+
+        if start_ms < covered_until {
+            if end_ms <= covered_until {
+                continue;
+            }
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let overlapped = (covered_until - start_ms) as f64 / (end_ms - start_ms) as f64;
+            let skip = ((words.len() as f64 * overlapped).round() as usize).min(words.len());
+            text = words[skip..].join(" ");
+            start_ms = covered_until;
+            if text.is_empty() {
+                continue;
+            }
+        }
+
+        merged.push(TranscriptSegment {
+            start_ms,
+            end_ms,
+            text,
+        });
+    }
+}
+
+/// Where a stored transcript and its source audio ended up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTranscript {
+    pub exhibit_id: i64,
+    pub communication_id: i64,
+    pub audio_path: String,
+}
+
+/// Save the audio as an exhibit and the transcript as a communication with timed segments
+///
+/// Both rows are filed under `case_id`, so matter access lists and walls cover the recording.
+pub async fn store_transcript(
+    pool: &DbPool,
+    audio_data: &[u8],
+    transcript: &Transcript,
+    sender: Option<&str>,
+    case_id: Option<i64>,
+    audio_dir: &str,
+) -> Result<StoredTranscript, AiError> {
+    let format = AudioFormat::detect(audio_data);
+    let hash = hex::encode(Sha256::digest(audio_data));
+    let extension = format.map(|f| f.extension()).unwrap_or("bin");
+
+    let mut path = PathBuf::from(audio_dir);
+    tokio::fs::create_dir_all(&path)
+        .await
+        .map_err(|e| AiError::ModelError(format!("Failed to create audio directory: {}", e)))?;
+    path.push(format!("{}.{}", hash, extension));
+    tokio::fs::write(&path, audio_data)
+        .await
+        .map_err(|e| AiError::ModelError(format!("Failed to save audio: {}", e)))?;
+    let audio_path = path.to_string_lossy().to_string();
+
+    let now = chrono::Utc::now();
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let exhibit_id = sqlx::query(
+        "INSERT INTO exhibits
+            (document_name, file_path, file_size_bytes, media_type, hash_sha256,
+             description, category, ai_extracted_text, ai_content_type, processing_status, case_id)
+         VALUES (?, ?, ?, ?, ?, ?, 'audio', ?, 'transcript', 'transcribed', ?)",
+    )
+    .bind(format!("Recording {}", now.format("%Y-%m-%d %H:%M:%S")))
+    .bind(&audio_path)
+    .bind(audio_data.len() as i64)
+    .bind(
+        format
+            .map(|f| f.media_type())
+            .unwrap_or("application/octet-stream"),
+    )
+    .bind(&hash)
+    .bind(format!(
+        "Audio recording, {:.1} minutes, transcribed by {}",
+        transcript.duration_ms as f64 / 60_000.0,
+        transcript.backend
+    ))
+    .bind(&transcript.text)
+    .bind(case_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    let communication_id = sqlx::query(
+        "INSERT INTO communications
+            (communication_date, sender, medium, subject, message_content, case_id)
+         VALUES (?, ?, 'voice_transcript', ?, ?, ?)",
+    )
+    .bind(now.format("%Y-%m-%d").to_string())
+    .bind(sender)
+    .bind(format!("Transcript of exhibit {}", exhibit_id))
+    .bind(&transcript.text)
+    .bind(case_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?
+    .last_insert_rowid();
+
+    for (index, segment) in transcript.segments.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO transcript_segments
+                (exhibit_id, communication_id, segment_index, start_ms, end_ms, text)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(exhibit_id)
+        .bind(communication_id)
+        .bind(index as i64)
+        .bind(segment.start_ms as i64)
+        .bind(segment.end_ms as i64)
+        .bind(&segment.text)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(StoredTranscript {
+        exhibit_id,
+        communication_id,
+        audio_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(seconds: u32, rate: u32) -> PcmAudio {
+        PcmAudio {
+            sample_rate: rate,
+            samples: (0..seconds * rate)
+                .map(|i| (i as f32 * 0.05).sin() * 0.5)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_wav_roundtrip() {
+        let audio = tone(1, 8_000);
+        let wav = audio.to_wav();
+
+        assert_eq!(AudioFormat::detect(&wav), Some(AudioFormat::Wav));
+        let decoded = decode_wav(&wav).unwrap();
+        assert_eq!(decoded.sample_rate, 8_000);
+        assert_eq!(decoded.samples.len(), audio.samples.len());
+        assert!((decoded.samples[100] - audio.samples[100]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_resample_and_chunk_offsets() {
+        let audio = tone(10, 8_000).resample(WHISPER_SAMPLE_RATE).unwrap();
+        assert_eq!(audio.duration_ms(), 10_000);
+
+        let chunks = audio.chunks(4, 1);
+        let offsets: Vec<u64> = chunks.iter().map(|c| c.offset_ms).collect();
+        assert_eq!(offsets, vec![0, 3_000, 6_000]);
+        assert_eq!(chunks.last().unwrap().audio.duration_ms(), 4_000);
+    }
+
+    #[test]
+    fn test_zero_rate_or_channels_is_invalid_input() {
+        let mut wav = tone(1, 8_000).to_wav();
+        wav[22..24].copy_from_slice(&0u16.to_le_bytes());
+        assert!(matches!(decode_wav(&wav), Err(AiError::InvalidInput(_))));
+
+        let mut wav = tone(1, 8_000).to_wav();
+        wav[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert!(matches!(decode_wav(&wav), Err(AiError::InvalidInput(_))));
+
+        assert!(matches!(tone(1, 0).resample(WHISPER_SAMPLE_RATE), Err(AiError::InvalidInput(_))));
+        assert!(matches!(tone(1, 8_000).resample(0), Err(AiError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_untimed_text_spans_the_whole_clip() {
+        let segments = whole_clip(&tone(3, 8_000).to_wav(), " Hello there ").unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (0, 3_000));
+        assert_eq!(segments[0].text, "Hello there");
+    }
+
+    #[test]
+    fn test_detects_ogg_and_webm() {
+        assert_eq!(AudioFormat::detect(b"OggS\0\x02"), Some(AudioFormat::Ogg));
+        assert_eq!(
+            AudioFormat::detect(&[0x1A, 0x45, 0xDF, 0xA3, 0x01]),
+            Some(AudioFormat::WebM)
+        );
+        assert_eq!(AudioFormat::detect(b"ID3"), None);
+    }
+
+    #[test]
+    fn test_merge_drops_overlap() {
+        let segment = |start_ms, end_ms, text: &str| TranscriptSegment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        };
+        let mut merged = Vec::new();
+        merge_segments(
+            &mut merged,
+            0,
+            vec![segment(0, 2_000, "a"), segment(2_000, 4_000, "b")],
+        );
+        merge_segments(
+            &mut merged,
+            3_000,
+            vec![segment(0, 1_000, "b"), segment(1_000, 3_000, "c")],
+        );
+
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "b", "c"]);
+        assert_eq!(merged[2].start_ms, 4_000);
+    }
+
+    #[test]
+    fn test_merge_keeps_words_after_the_overlap() {
+        let mut merged = Vec::new();
+        merge_segments(
+            &mut merged,
+            0,
+            vec![TranscriptSegment { start_ms: 0, end_ms: 4_000, text: "one two three four".to_string() }],
+        );
+        // Starts one second into the previous chunk, so its first word is a repeat
+        merge_segments(
+            &mut merged,
+            3_000,
+            vec![TranscriptSegment { start_ms: 0, end_ms: 4_000, text: "four five six seven".to_string() }],
+        );
+
+        let texts: Vec<&str> = merged.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["one two three four", "five six seven"]);
+        assert_eq!((merged[1].start_ms, merged[1].end_ms), (4_000, 7_000));
+    }
+
+    /// Answers like a server without segment support: one untimed text per chunk,
+    /// with a word for each second of the chunk
+    struct UntimedBackend;
+
+    #[async_trait::async_trait]
+    impl TranscriptionBackend for UntimedBackend {
+        fn name(&self) -> &str {
+            "untimed"
+        }
+
+        async fn transcribe_chunk(&self, wav: &[u8]) -> Result<Vec<TranscriptSegment>, AiError> {
+            let seconds = decode_wav(wav)?.duration_ms() / 1_000;
+            let words: Vec<String> = (0..seconds).map(|s| format!("w{}", s)).collect();
+            whole_clip(wav, &words.join(" "))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_untimed_chunks_all_reach_the_transcript() {
+        let config = TranscriptionConfig {
+            chunk_seconds: 4,
+            overlap_seconds: 1,
+            ..TranscriptionConfig::default()
+        };
+        let transcriber = Transcriber::with_backend(config, Arc::new(UntimedBackend));
+        let transcript = transcriber.transcribe(&tone(10, 8_000).to_wav()).await.unwrap();
+
+        // Chunks start at 0, 3 and 6 seconds; only the word for each overlapped second is dropped
+        assert_eq!(transcript.segments.len(), 3);
+        assert_eq!(transcript.text, "w0 w1 w2 w3 w1 w2 w3 w1 w2 w3");
+        assert_eq!(transcript.segments.last().unwrap().end_ms, 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_stored_transcript_is_filed_under_its_matter() {
+        let pool = crate::db::create_pool("sqlite::memory:").await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let transcript = Transcript {
+            text: "Pickup is at five".to_string(),
+            segments: Vec::new(),
+            duration_ms: 1_000,
+            backend: "test".to_string(),
+            language: None,
+        };
+
+        let stored = store_transcript(
+            &pool,
+            &tone(1, 8_000).to_wav(),
+            &transcript,
+            Some("Parent A"),
+            Some(1),
+            dir.path().to_str().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let exhibit: Option<i64> = sqlx::query_scalar("SELECT case_id FROM exhibits WHERE id = ?")
+            .bind(stored.exhibit_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let communication: Option<i64> = sqlx::query_scalar("SELECT case_id FROM communications WHERE id = ?")
+            .bind(stored.communication_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((exhibit, communication), (Some(1), Some(1)));
+    }
+}
@@ -0,0 +1,14 @@
+//! Helpers shared by the AI modules that read and write the case database
+
+use crate::ai::AiError;
+use chrono::NaiveDate;
+
+/// Wrap a database failure as an `AiError`
+pub(crate) fn db_error(e: sqlx::Error) -> AiError {
+    AiError::ModelError(format!("Database error: {}", e))
+}
+
+/// Date part of a stored `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` value
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d").ok()
+}
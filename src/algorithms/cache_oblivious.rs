@@ -1,9 +1,13 @@
 // Cache-Oblivious Algorithms Implementation
 // Knuthian Optimization Step 8: Cache-Oblivious Data Structures and Algorithms
 
+use std::borrow::Borrow;
 use std::cmp::{min, max};
 use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
 use crate::algorithms::{ComplexityTracker, AlgorithmMetrics};
+use crate::algorithms::paged_map::{write_pages, PageCodec, PagedMap, DEFAULT_PAGE_SIZE};
 
 /// Cache-oblivious matrix multiplication using recursive blocking
 #[derive(Debug)]
@@ -261,7 +265,11 @@ impl<T: Clone + Ord> CacheObliviousSorter<T> {
     }
 }
 
-/// Cache-oblivious B-tree implementation
+/// Ordered map stored as a B-tree of small, contiguous nodes
+///
+/// Every node other than the root holds between `MIN_DEGREE - 1` and `MAX_KEYS` keys,
+/// so a lookup touches O(log_B n) nodes. Maps can be saved to and loaded from the
+/// page format in `paged_map`, or queried on disk through `PagedMap`.
 #[derive(Debug)]
 pub struct CacheObliviousBTree<K, V> {
     root: Option<Box<BTreeNode<K, V>>>,
@@ -270,12 +278,14 @@ pub struct CacheObliviousBTree<K, V> {
     metrics: AlgorithmMetrics,
 }
 
+const MIN_DEGREE: usize = 6;
+const MAX_KEYS: usize = 2 * MIN_DEGREE - 1;
+
 #[derive(Debug)]
 struct BTreeNode<K, V> {
     keys: Vec<K>,
     values: Vec<V>,
-    children: Vec<Box<BTreeNode<K, V>>>,
-    is_leaf: bool,
+    children: Vec<BTreeNode<K, V>>,
 }
 
 impl<K: Ord + Clone, V: Clone> CacheObliviousBTree<K, V> {
@@ -288,101 +298,134 @@ impl<K: Ord + Clone, V: Clone> CacheObliviousBTree<K, V> {
         }
     }
 
-    pub fn insert(&mut self, key: K, value: V) {
+    /// Build a tree from unsorted entries in O(n log n); later duplicates win
+    pub fn bulk_load<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut entries: Vec<(K, V)> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        // Keep the last value for each key, matching repeated inserts
+        let mut deduped: Vec<(K, V)> = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match deduped.last_mut() {
+                Some(last) if last.0 == key => last.1 = value,
+                _ => deduped.push((key, value)),
+            }
+        }
+
+        let mut tree = Self::new();
+        let n = deduped.len();
+        if n == 0 {
+            return tree;
+        }
+        let mut height = 1;
+        while subtree_capacity(height) < n {
+            height += 1;
+        }
+        let mut entries = deduped.into_iter();
+        tree.root = Some(Box::new(BTreeNode::build(&mut entries, n, height, true)));
+        tree.height = height;
+        tree.size = n;
+        tree.metrics.complexity.record_operation();
+        tree
+    }
+
+    /// Insert a key, returning the previous value if it was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         self.metrics.complexity.record_operation();
-        
+
         if self.root.is_none() {
-            let mut node = BTreeNode::new_leaf();
-            node.keys.push(key);
-            node.values.push(value);
-            self.root = Some(Box::new(node));
+            self.root = Some(Box::new(BTreeNode::new()));
             self.height = 1;
-        } else {
-            if let Some(new_root) = self.insert_into_node(&mut self.root.as_mut().unwrap(), key, value) {
-                let mut new_root_node = BTreeNode::new_internal();
-                new_root_node.children.push(self.root.take().unwrap());
-                new_root_node.children.push(new_root);
-                // In practice, you'd need to handle key promotion
-                self.root = Some(Box::new(new_root_node));
-                self.height += 1;
-            }
         }
-        self.size += 1;
-    }
+        let root = self.root.as_mut().unwrap();
+        if root.keys.len() == MAX_KEYS {
+            let old_root = mem::replace(root.as_mut(), BTreeNode::new());
+            root.children.push(old_root);
+            root.split_child(0);
+            self.height += 1;
+        }
 
-    fn insert_into_node(&mut self, node: &mut BTreeNode<K, V>, key: K, value: V) 
-        -> Option<Box<BTreeNode<K, V>>> {
-        
-        if node.is_leaf {
-            // Insert into leaf
-            let pos = node.keys.binary_search(&key).unwrap_or_else(|e| e);
-            node.keys.insert(pos, key);
-            node.values.insert(pos, value);
-            
-            // Check if split is needed (simplified - using fixed threshold)
-            if node.keys.len() > 4 {
-                return Some(self.split_leaf(node));
-            }
-        } else {
-            // Find child to insert into
-            let child_index = node.keys.binary_search(&key).unwrap_or_else(|e| e);
-            if let Some(new_child) = self.insert_into_node(&mut node.children[child_index], key, value) {
-                // Handle child split
-                node.children.insert(child_index + 1, new_child);
-                // In practice, you'd promote the median key
-                
-                if node.children.len() > 5 {
-                    return Some(self.split_internal(node));
-                }
-            }
+        let previous = root.insert_non_full(key, value);
+        if previous.is_none() {
+            self.size += 1;
         }
-        
-        None
+        previous
     }
 
-    fn split_leaf(&self, node: &mut BTreeNode<K, V>) -> Box<BTreeNode<K, V>> {
-        let mid = node.keys.len() / 2;
-        let mut new_node = BTreeNode::new_leaf();
-        
-        new_node.keys = node.keys.split_off(mid);
-        new_node.values = node.values.split_off(mid);
-        
-        Box::new(new_node)
-    }
+    /// Remove a key, returning its value if it was present
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.metrics.complexity.record_operation();
 
-    fn split_internal(&self, node: &mut BTreeNode<K, V>) -> Box<BTreeNode<K, V>> {
-        let mid = node.children.len() / 2;
-        let mut new_node = BTreeNode::new_internal();
-        
-        new_node.children = node.children.split_off(mid);
-        
-        Box::new(new_node)
+        let root = self.root.as_mut()?;
+        let removed = root.remove(key);
+        if root.keys.is_empty() {
+            if root.children.is_empty() {
+                self.root = None;
+                self.height = 0;
+            } else {
+                let child = root.children.pop().unwrap();
+                self.root = Some(Box::new(child));
+                self.height -= 1;
+            }
+        }
+
+        removed.map(|(_, value)| {
+            self.size -= 1;
+            value
+        })
     }
 
     pub fn search(&mut self, key: &K) -> Option<&V> {
         self.metrics.complexity.record_operation();
-        self.search_in_node(self.root.as_ref()?, key)
+        self.root.as_ref()?.get(key)
     }
 
-    fn search_in_node(&self, node: &BTreeNode<K, V>, key: &K) -> Option<&V> {
-        match node.keys.binary_search(key) {
-            Ok(index) => {
-                if node.is_leaf {
-                    Some(&node.values[index])
-                } else {
-                    self.search_in_node(&node.children[index], key)
-                }
-            }
-            Err(index) => {
-                if node.is_leaf {
-                    None
-                } else {
-                    self.search_in_node(&node.children[index], key)
-                }
-            }
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.root.as_ref()?.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// In-order iterator over the entries whose keys fall in `range`
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> BTreeRange<'_, K, V> {
+        let mut stack = Vec::with_capacity(self.height);
+        let mut node = self.root.as_deref();
+        while let Some(current) = node {
+            let index = match range.start_bound() {
+                Bound::Included(start) => current.keys.partition_point(|k| k < start),
+                Bound::Excluded(start) => current.keys.partition_point(|k| k <= start),
+                Bound::Unbounded => 0,
+            };
+            stack.push((current, index));
+            node = current.children.get(index);
+        }
+        BTreeRange {
+            stack,
+            end: range.end_bound().cloned(),
         }
     }
 
+    pub fn iter(&self) -> BTreeRange<'_, K, V> {
+        self.range(..)
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
     pub fn get_metrics(&self) -> &AlgorithmMetrics {
         &self.metrics
     }
@@ -394,24 +437,311 @@ impl<K: Ord + Clone, V: Clone> CacheObliviousBTree<K, V> {
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Write the tree to `path` in the paged on-disk format
+    pub fn save_pages(&self, path: &Path) -> Result<(), String>
+    where
+        K: PageCodec,
+        V: PageCodec,
+    {
+        write_pages(path, self.iter(), DEFAULT_PAGE_SIZE)
+    }
+
+    /// Load a tree previously written with `save_pages`
+    pub fn load_pages(path: &Path) -> Result<Self, String>
+    where
+        K: PageCodec,
+        V: PageCodec,
+    {
+        let pages: PagedMap<K, V> = PagedMap::open(path)?;
+        let entries = pages.iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::bulk_load(entries))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for CacheObliviousBTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for CacheObliviousBTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::bulk_load(iter)
+    }
+}
+
+/// Most entries a subtree of the given height can hold
+fn subtree_capacity(height: usize) -> usize {
+    let mut capacity = MAX_KEYS;
+    for _ in 1..height {
+        capacity = capacity
+            .saturating_mul(MAX_KEYS + 1)
+            .saturating_add(MAX_KEYS);
+    }
+    capacity
 }
 
-impl<K, V> BTreeNode<K, V> {
-    fn new_leaf() -> Self {
+impl<K: Ord, V> BTreeNode<K, V> {
+    fn new() -> Self {
         Self {
-            keys: Vec::new(),
-            values: Vec::new(),
+            keys: Vec::with_capacity(MAX_KEYS),
+            values: Vec::with_capacity(MAX_KEYS),
             children: Vec::new(),
-            is_leaf: true,
         }
     }
 
-    fn new_internal() -> Self {
-        Self {
-            keys: Vec::new(),
-            values: Vec::new(),
-            children: Vec::new(),
-            is_leaf: false,
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Build a balanced subtree of `height` holding the next `n` sorted entries
+    fn build(
+        entries: &mut impl Iterator<Item = (K, V)>,
+        n: usize,
+        height: usize,
+        is_root: bool,
+    ) -> Self {
+        let mut node = Self::new();
+        if height == 1 {
+            for (key, value) in entries.take(n) {
+                node.keys.push(key);
+                node.values.push(value);
+            }
+            return node;
+        }
+
+        // Fewest children whose subtrees can hold the entries, but never fewer than
+        // a non-root node needs, spreading entries evenly keeps every child legal
+        let child_capacity = subtree_capacity(height - 1);
+        let mut children = if is_root { 2 } else { MIN_DEGREE };
+        while n - (children - 1) > children * child_capacity {
+            children += 1;
+        }
+        let in_children = n - (children - 1);
+        let (base, extra) = (in_children / children, in_children % children);
+
+        for i in 0..children {
+            let size = base + usize::from(i < extra);
+            node.children.push(Self::build(entries, size, height - 1, false));
+            if i + 1 < children {
+                let (key, value) = entries.next().expect("entry count was precomputed");
+                node.keys.push(key);
+                node.values.push(value);
+            }
+        }
+        node
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = self;
+        loop {
+            match node.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+                Ok(index) => return Some(&node.values[index]),
+                Err(index) => node = node.children.get(index)?,
+            }
+        }
+    }
+
+    /// Split the full child at `index`, lifting its median into this node
+    fn split_child(&mut self, index: usize) {
+        let child = &mut self.children[index];
+        let mut right = Self::new();
+        right.keys = child.keys.split_off(MIN_DEGREE);
+        right.values = child.values.split_off(MIN_DEGREE);
+        if !child.is_leaf() {
+            right.children = child.children.split_off(MIN_DEGREE);
+        }
+        let median_key = child.keys.pop().unwrap();
+        let median_value = child.values.pop().unwrap();
+
+        self.keys.insert(index, median_key);
+        self.values.insert(index, median_value);
+        self.children.insert(index + 1, right);
+    }
+
+    fn insert_non_full(&mut self, key: K, value: V) -> Option<V> {
+        let mut node = self;
+        loop {
+            let mut index = match node.keys.binary_search(&key) {
+                Ok(index) => return Some(mem::replace(&mut node.values[index], value)),
+                Err(index) => index,
+            };
+            if node.is_leaf() {
+                node.keys.insert(index, key);
+                node.values.insert(index, value);
+                return None;
+            }
+            if node.children[index].keys.len() == MAX_KEYS {
+                node.split_child(index);
+                match key.cmp(&node.keys[index]) {
+                    std::cmp::Ordering::Equal => {
+                        return Some(mem::replace(&mut node.values[index], value))
+                    }
+                    std::cmp::Ordering::Greater => index += 1,
+                    std::cmp::Ordering::Less => {}
+                }
+            }
+            node = &mut node.children[index];
+        }
+    }
+
+    /// Remove `key` from this subtree; callers guarantee this node can spare a key
+    fn remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+            Ok(index) if self.is_leaf() => {
+                Some((self.keys.remove(index), self.values.remove(index)))
+            }
+            Ok(index) => {
+                if self.children[index].keys.len() >= MIN_DEGREE {
+                    let (key, value) = self.children[index].pop_last();
+                    Some(self.replace_entry(index, key, value))
+                } else if self.children[index + 1].keys.len() >= MIN_DEGREE {
+                    let (key, value) = self.children[index + 1].pop_first();
+                    Some(self.replace_entry(index, key, value))
+                } else {
+                    self.merge_children(index);
+                    self.children[index].remove(key)
+                }
+            }
+            Err(_) if self.is_leaf() => None,
+            Err(index) => {
+                let index = self.ensure_child_can_spare(index);
+                self.children[index].remove(key)
+            }
+        }
+    }
+
+    fn replace_entry(&mut self, index: usize, key: K, value: V) -> (K, V) {
+        (
+            mem::replace(&mut self.keys[index], key),
+            mem::replace(&mut self.values[index], value),
+        )
+    }
+
+    fn pop_first(&mut self) -> (K, V) {
+        if self.is_leaf() {
+            return (self.keys.remove(0), self.values.remove(0));
+        }
+        let index = self.ensure_child_can_spare(0);
+        self.children[index].pop_first()
+    }
+
+    fn pop_last(&mut self) -> (K, V) {
+        if self.is_leaf() {
+            return (self.keys.pop().unwrap(), self.values.pop().unwrap());
+        }
+        let index = self.ensure_child_can_spare(self.children.len() - 1);
+        self.children[index].pop_last()
+    }
+
+    /// Top up the child at `index` to at least `MIN_DEGREE` keys before descending
+    ///
+    /// Borrows a key through this node from a sibling when one can spare it and merges
+    /// with a sibling otherwise. Returns the index of the child now covering the range.
+    fn ensure_child_can_spare(&mut self, index: usize) -> usize {
+        if self.children[index].keys.len() >= MIN_DEGREE {
+            return index;
+        }
+
+        if index > 0 && self.children[index - 1].keys.len() >= MIN_DEGREE {
+            let (left, right) = self.children.split_at_mut(index);
+            let (left, child) = (&mut left[index - 1], &mut right[0]);
+            let key = mem::replace(&mut self.keys[index - 1], left.keys.pop().unwrap());
+            let value = mem::replace(&mut self.values[index - 1], left.values.pop().unwrap());
+            child.keys.insert(0, key);
+            child.values.insert(0, value);
+            if let Some(grandchild) = left.children.pop() {
+                child.children.insert(0, grandchild);
+            }
+            index
+        } else if index + 1 < self.children.len()
+            && self.children[index + 1].keys.len() >= MIN_DEGREE
+        {
+            let (left, right) = self.children.split_at_mut(index + 1);
+            let (child, right) = (&mut left[index], &mut right[0]);
+            let key = mem::replace(&mut self.keys[index], right.keys.remove(0));
+            let value = mem::replace(&mut self.values[index], right.values.remove(0));
+            child.keys.push(key);
+            child.values.push(value);
+            if !right.is_leaf() {
+                child.children.push(right.children.remove(0));
+            }
+            index
+        } else if index + 1 < self.children.len() {
+            self.merge_children(index);
+            index
+        } else {
+            self.merge_children(index - 1);
+            index - 1
+        }
+    }
+
+    /// Merge the child at `index + 1` and the separating key into the child at `index`
+    fn merge_children(&mut self, index: usize) {
+        let right = self.children.remove(index + 1);
+        let key = self.keys.remove(index);
+        let value = self.values.remove(index);
+
+        let left = &mut self.children[index];
+        left.keys.push(key);
+        left.values.push(value);
+        left.keys.extend(right.keys);
+        left.values.extend(right.values);
+        left.children.extend(right.children);
+    }
+}
+
+/// Iterator over a key range of a `CacheObliviousBTree`
+///
+/// Each stack frame is a node and the index of the next key to yield from it.
+pub struct BTreeRange<'a, K, V> {
+    stack: Vec<(&'a BTreeNode<K, V>, usize)>,
+    end: Bound<K>,
+}
+
+impl<'a, K: Ord, V> Iterator for BTreeRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, index) = *self.stack.last()?;
+            if index >= node.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+
+            let key = &node.keys[index];
+            let in_range = match &self.end {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.stack.clear();
+                return None;
+            }
+
+            self.stack.last_mut().unwrap().1 += 1;
+            // Everything in the child right of this key comes before the node's next key
+            let mut child = node.children.get(index + 1);
+            while let Some(current) = child {
+                self.stack.push((current, 0));
+                child = current.children.first();
+            }
+            return Some((key, &node.values[index]));
         }
     }
 }
@@ -515,6 +845,79 @@ mod tests {
         assert_eq!(tree.search(&4), None);
     }
 
+    #[test]
+    fn test_btree_matches_std_btreemap() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        use std::collections::BTreeMap;
+
+        for seed in 0..8u64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let initial: Vec<(u32, u64)> = (0..rng.gen_range(0..300))
+                .map(|i| (rng.gen_range(0..500), i))
+                .collect();
+            // Half the runs start from a bulk-loaded tree rather than an empty one
+            let (mut tree, mut model) = if seed % 2 == 0 {
+                (CacheObliviousBTree::new(), BTreeMap::new())
+            } else {
+                (
+                    CacheObliviousBTree::bulk_load(initial.clone()),
+                    initial.into_iter().collect(),
+                )
+            };
+
+            for step in 0..4000u64 {
+                let key: u32 = rng.gen_range(0..500);
+                match rng.gen_range(0..10) {
+                    0..=4 => assert_eq!(tree.insert(key, step), model.insert(key, step)),
+                    5..=7 => assert_eq!(tree.remove(&key), model.remove(&key)),
+                    8 => assert_eq!(tree.get(&key), model.get(&key)),
+                    _ => {
+                        let end = key + rng.gen_range(0..60);
+                        assert!(tree.range(key..end).eq(model.range(key..end)));
+                        let bounds = (Bound::Excluded(key), Bound::Included(end));
+                        assert!(tree.range(bounds).eq(model.range(bounds)));
+                    }
+                }
+                assert_eq!(tree.len(), model.len());
+            }
+            assert!(tree.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn test_btree_bulk_load_and_pages() {
+        let entries: Vec<(i64, String)> = (0..5000)
+            .rev()
+            .map(|i| (i * 2, format!("event {}", i)))
+            .collect();
+        let mut tree: CacheObliviousBTree<i64, String> = entries.into_iter().collect();
+        assert_eq!(tree.len(), 5000);
+        assert_eq!(tree.height(), 4);
+        assert_eq!(tree.first_key_value(), Some((&0, &"event 0".to_string())));
+
+        // A bulk-loaded tree keeps working under updates
+        for i in (0..5000).step_by(3) {
+            assert!(tree.remove(&(i * 2)).is_some());
+        }
+        tree.insert(101, "odd".to_string());
+
+        let path = std::env::temp_dir().join(format!("btree-{}.pages", uuid::Uuid::new_v4()));
+        tree.save_pages(&path).unwrap();
+        let pages: PagedMap<i64, String> = PagedMap::open(&path).unwrap();
+        assert_eq!(pages.len(), tree.len());
+        let on_disk: Vec<(i64, String)> = pages.range(90..=130).map(Result::unwrap).collect();
+        let in_memory: Vec<(i64, String)> = tree
+            .range(90..=130)
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+        assert_eq!(on_disk, in_memory);
+        assert_eq!(pages.get(&101).unwrap(), Some("odd".to_string()));
+
+        let reloaded = CacheObliviousBTree::<i64, String>::load_pages(&path).unwrap();
+        assert!(reloaded.iter().eq(tree.iter()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_distribution_sort() {
         let mut data = vec![3, 1, 4, 1, 5, 9, 2, 6, 5];
@@ -0,0 +1,457 @@
+// MinHash and Locality-Sensitive Hashing for Near-Duplicate Detection
+// Replaces pairwise longest-common-substring comparison with sub-quadratic candidate lookup
+
+use crate::algorithms::AlgorithmMetrics;
+use std::collections::{HashMap, HashSet};
+
+/// Fixed seed so signatures stay comparable across processes and releases
+const SIGNATURE_SEED: u64 = 0x4d69_6e48_6173_6831;
+
+/// Stable 64-bit FNV-1a hash; `DefaultHasher` may change between Rust versions,
+/// which would silently invalidate persisted signatures
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// SplitMix64 finaliser, used both to derive seeds and as the per-permutation hash
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Hashed word k-shingles of a document, normalised to lowercase alphanumerics
+pub fn shingles(text: &str, shingle_size: usize) -> HashSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    let k = shingle_size.max(1);
+    if words.len() <= k {
+        return if words.is_empty() {
+            HashSet::new()
+        } else {
+            HashSet::from([fnv1a(words.join(" ").as_bytes())])
+        };
+    }
+
+    words
+        .windows(k)
+        .map(|window| fnv1a(window.join(" ").as_bytes()))
+        .collect()
+}
+
+/// Parameters for signatures and banding
+#[derive(Debug, Clone, PartialEq)]
+pub struct LshConfig {
+    pub num_perm: usize,
+    pub bands: usize,
+    pub rows: usize,
+    pub shingle_size: usize,
+    /// Minimum estimated Jaccard similarity reported as a near-duplicate
+    pub threshold: f64,
+}
+
+impl LshConfig {
+    /// Choose the band/row split whose S-curve inflection sits closest to `threshold`
+    pub fn for_threshold(threshold: f64, num_perm: usize, shingle_size: usize) -> Self {
+        let threshold = threshold.clamp(0.01, 1.0);
+        let (bands, rows) = (1..=num_perm)
+            .filter(|b| num_perm.is_multiple_of(*b))
+            .map(|b| (b, num_perm / b))
+            .min_by(|a, b| {
+                let inflection = |(b, r): (usize, usize)| (1.0 / b as f64).powf(1.0 / r as f64);
+                (inflection(*a) - threshold)
+                    .abs()
+                    .total_cmp(&(inflection(*b) - threshold).abs())
+            })
+            .unwrap_or((num_perm, 1));
+
+        Self {
+            num_perm,
+            bands,
+            rows,
+            shingle_size,
+            threshold,
+        }
+    }
+}
+
+impl Default for LshConfig {
+    fn default() -> Self {
+        Self::for_threshold(0.8, 128, 5)
+    }
+}
+
+/// A MinHash signature; the fraction of equal slots estimates Jaccard similarity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinHashSignature {
+    pub values: Vec<u64>,
+}
+
+impl MinHashSignature {
+    pub fn jaccard(&self, other: &MinHashSignature) -> f64 {
+        if self.values.is_empty() || self.values.len() != other.values.len() {
+            return 0.0;
+        }
+        let equal = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .filter(|(a, b)| a == b)
+            .count();
+        equal as f64 / self.values.len() as f64
+    }
+
+    /// Little-endian encoding for storage
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(8) {
+            return Err("MinHash signature length must be a multiple of 8 bytes".to_string());
+        }
+        let values = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("8-byte chunk")))
+            .collect();
+        Ok(Self { values })
+    }
+}
+
+/// Computes MinHash signatures with a fixed family of hash permutations
+#[derive(Debug, Clone)]
+pub struct MinHasher {
+    seeds: Vec<u64>,
+    shingle_size: usize,
+}
+
+impl MinHasher {
+    pub fn new(num_perm: usize, shingle_size: usize) -> Self {
+        let seeds = (0..num_perm as u64)
+            .map(|i| splitmix64(SIGNATURE_SEED ^ i))
+            .collect();
+        Self {
+            seeds,
+            shingle_size,
+        }
+    }
+
+    pub fn signature(&self, text: &str) -> MinHashSignature {
+        self.signature_of(&shingles(text, self.shingle_size))
+    }
+
+    pub fn signature_of(&self, shingles: &HashSet<u64>) -> MinHashSignature {
+        let values = self
+            .seeds
+            .iter()
+            .map(|&seed| {
+                shingles
+                    .iter()
+                    .map(|&s| splitmix64(s ^ seed))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+        MinHashSignature { values }
+    }
+}
+
+/// Banded LSH index: documents sharing any band hash become candidate pairs
+#[derive(Debug)]
+pub struct LshIndex {
+    config: LshConfig,
+    buckets: HashMap<(usize, u64), Vec<String>>,
+    signatures: HashMap<String, MinHashSignature>,
+    metrics: AlgorithmMetrics,
+}
+
+impl LshIndex {
+    pub fn new(config: LshConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+            signatures: HashMap::new(),
+            metrics: AlgorithmMetrics::new("MinHashLSH", "O(b) per lookup", "O(n·k)"),
+        }
+    }
+
+    fn band_keys<'a>(
+        &'a self,
+        signature: &'a MinHashSignature,
+    ) -> impl Iterator<Item = (usize, u64)> + 'a {
+        signature
+            .values
+            .chunks(self.config.rows)
+            .take(self.config.bands)
+            .enumerate()
+            .map(|(band, rows)| {
+                let bytes: Vec<u8> = rows.iter().flat_map(|v| v.to_le_bytes()).collect();
+                (band, fnv1a(&bytes))
+            })
+    }
+
+    pub fn insert(&mut self, id: &str, signature: MinHashSignature) -> Result<(), String> {
+        if signature.values.len() != self.config.num_perm {
+            return Err(format!(
+                "Signature has {} values, index expects {}",
+                signature.values.len(),
+                self.config.num_perm
+            ));
+        }
+        self.metrics.complexity.record_operation();
+
+        if self.signatures.contains_key(id) {
+            self.remove(id);
+        }
+        let keys: Vec<(usize, u64)> = self.band_keys(&signature).collect();
+        for key in keys {
+            self.buckets.entry(key).or_default().push(id.to_string());
+        }
+        self.signatures.insert(id.to_string(), signature);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(signature) = self.signatures.remove(id) {
+            let keys: Vec<(usize, u64)> = self.band_keys(&signature).collect();
+            for key in keys {
+                if let Some(bucket) = self.buckets.get_mut(&key) {
+                    bucket.retain(|existing| existing != id);
+                    if bucket.is_empty() {
+                        self.buckets.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Indexed documents whose estimated similarity meets the threshold, best first
+    pub fn query(&mut self, signature: &MinHashSignature) -> Vec<(String, f64)> {
+        self.metrics.complexity.record_operation();
+
+        let mut candidates = HashSet::new();
+        for key in self.band_keys(signature) {
+            if let Some(bucket) = self.buckets.get(&key) {
+                candidates.extend(bucket.iter().cloned());
+            }
+        }
+
+        let mut matches: Vec<(String, f64)> = candidates
+            .into_iter()
+            .filter_map(|id| {
+                let similarity = self.signatures.get(&id)?.jaccard(signature);
+                (similarity >= self.config.threshold).then_some((id, similarity))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        matches
+    }
+
+    /// Near-duplicate pairs above the threshold, found through shared buckets only
+    pub fn candidate_pairs(&self) -> Vec<(String, String, f64)> {
+        let mut seen = HashSet::new();
+        let mut pairs = Vec::new();
+
+        for bucket in self.buckets.values() {
+            for (i, a) in bucket.iter().enumerate() {
+                for b in &bucket[i + 1..] {
+                    let key = if a < b { (a, b) } else { (b, a) };
+                    if !seen.insert(key) {
+                        continue;
+                    }
+                    let similarity = self.signatures[a].jaccard(&self.signatures[b]);
+                    if similarity >= self.config.threshold {
+                        pairs.push((key.0.clone(), key.1.clone(), similarity));
+                    }
+                }
+            }
+        }
+
+        pairs.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        pairs
+    }
+
+    /// Group near-duplicates transitively; singletons are omitted
+    pub fn clusters(&self) -> Vec<Vec<String>> {
+        let pairs = self.candidate_pairs();
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+
+        fn find<'a>(parent: &mut HashMap<&'a str, &'a str>, id: &'a str) -> &'a str {
+            let mut root = id;
+            while let Some(&next) = parent.get(root) {
+                if next == root {
+                    break;
+                }
+                root = next;
+            }
+            parent.insert(id, root);
+            root
+        }
+
+        for (a, b, _) in &pairs {
+            let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+            if root_a != root_b {
+                let (low, high) = if root_a < root_b {
+                    (root_a, root_b)
+                } else {
+                    (root_b, root_a)
+                };
+                parent.insert(high, low);
+                parent.insert(low, low);
+            }
+        }
+
+        let members: Vec<&str> = parent.keys().copied().collect();
+        let mut groups: HashMap<&str, Vec<String>> = HashMap::new();
+        for id in members {
+            let root = find(&mut parent, id);
+            groups.entry(root).or_default().push(id.to_string());
+        }
+
+        let mut clusters: Vec<Vec<String>> = groups
+            .into_values()
+            .filter(|g| g.len() > 1)
+            .map(|mut g| {
+                g.sort();
+                g
+            })
+            .collect();
+        clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        clusters
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    pub fn config(&self) -> &LshConfig {
+        &self.config
+    }
+
+    pub fn get_metrics(&self) -> &AlgorithmMetrics {
+        &self.metrics
+    }
+}
+
+/// Shingling, signatures and LSH lookup behind one incremental interface
+#[derive(Debug)]
+pub struct NearDuplicateDetector {
+    hasher: MinHasher,
+    index: LshIndex,
+}
+
+impl NearDuplicateDetector {
+    pub fn new(config: LshConfig) -> Self {
+        Self {
+            hasher: MinHasher::new(config.num_perm, config.shingle_size),
+            index: LshIndex::new(config),
+        }
+    }
+
+    /// Return existing near-duplicates of `text`, then index it under `id`
+    pub fn add_document(&mut self, id: &str, text: &str) -> Vec<(String, f64)> {
+        let signature = self.hasher.signature(text);
+        let matches = self
+            .index
+            .query(&signature)
+            .into_iter()
+            .filter(|(existing, _)| existing != id)
+            .collect();
+        self.index
+            .insert(id, signature)
+            .expect("signature built with the index's own parameters");
+        matches
+    }
+
+    pub fn signature(&self, text: &str) -> MinHashSignature {
+        self.hasher.signature(text)
+    }
+
+    pub fn index(&self) -> &LshIndex {
+        &self.index
+    }
+
+    pub fn index_mut(&mut self) -> &mut LshIndex {
+        &mut self.index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMAIL: &str = "Please be advised that the scheduled visitation for this weekend \
+        cannot proceed because of a prior commitment. We will propose alternative dates \
+        next week once the calendar has been confirmed with the agency.";
+
+    #[test]
+    fn test_signature_estimates_jaccard() {
+        let hasher = MinHasher::new(256, 3);
+        let edited = EMAIL.replace("this weekend", "this coming weekend");
+        let unrelated = "The court reviewed the financial affidavit and scheduled a hearing \
+            on the motion to modify support for the second week of March.";
+
+        let base = hasher.signature(EMAIL);
+        assert!(base.jaccard(&hasher.signature(&edited)) > 0.6);
+        assert!(base.jaccard(&hasher.signature(unrelated)) < 0.1);
+        assert_eq!(base.jaccard(&hasher.signature(EMAIL)), 1.0);
+    }
+
+    #[test]
+    fn test_signature_bytes_round_trip() {
+        let signature = MinHasher::new(16, 2).signature(EMAIL);
+        let decoded = MinHashSignature::from_bytes(&signature.to_bytes()).unwrap();
+        assert_eq!(decoded, signature);
+        assert!(MinHashSignature::from_bytes(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_config_for_threshold() {
+        let config = LshConfig::for_threshold(0.8, 128, 5);
+        assert_eq!(config.bands * config.rows, 128);
+        let inflection = (1.0 / config.bands as f64).powf(1.0 / config.rows as f64);
+        assert!((inflection - 0.8).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_detector_clusters_near_duplicates() {
+        let mut detector = NearDuplicateDetector::new(LshConfig::for_threshold(0.5, 128, 3));
+        assert!(detector.add_document("email-1", EMAIL).is_empty());
+
+        let forwarded = format!("FW: {}", EMAIL);
+        let matches = detector.add_document("email-2", &forwarded);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, "email-1");
+
+        detector.add_document("email-3", &EMAIL.replace("agency", "caseworker"));
+        detector.add_document(
+            "order-1",
+            "It is hereby ordered that the respondent shall file an updated financial \
+             statement within thirty days of the date of this order.",
+        );
+
+        let clusters = detector.index().clusters();
+        assert_eq!(
+            clusters,
+            vec![vec![
+                "email-1".to_string(),
+                "email-2".to_string(),
+                "email-3".to_string()
+            ]]
+        );
+    }
+}
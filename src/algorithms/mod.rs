@@ -4,6 +4,10 @@
 pub mod suffix_tree;
 pub mod cache_oblivious;
 pub mod probabilistic;
+pub mod minhash;
+pub mod sketch_store;
+pub mod top_k;
+pub mod paged_map;
 
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
@@ -11,8 +15,12 @@ use std::sync::Arc;
 
 // Re-export key structures
 pub use suffix_tree::{SuffixTree, SuffixTreeNode};
-pub use cache_oblivious::{CacheObliviousAlgorithms, CacheOptimalMatrix};
-pub use probabilistic::{BloomFilter, HyperLogLog, CountMinSketch};
+pub use cache_oblivious::{CacheObliviousAlgorithms, CacheObliviousBTree, CacheOptimalMatrix};
+pub use probabilistic::{BloomFilter, HyperLogLog, CountMinSketch, SkipList};
+pub use minhash::{LshConfig, LshIndex, MinHasher, NearDuplicateDetector};
+pub use sketch_store::{SketchStore, StoredSketch};
+pub use top_k::{CmsTopK, HeavyHitter, SpaceSaving};
+pub use paged_map::{PageCodec, PagedMap};
 
 /// Algorithmic complexity tracker for performance analysis
 #[derive(Debug, Clone)]
@@ -0,0 +1,452 @@
+// On-disk page format for sorted key-value maps with a memory-mapped read path
+// Written by CacheObliviousBTree and SkipList, readable without loading the whole map
+
+use std::borrow::Borrow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use memmap2::Mmap;
+
+pub const PAGE_FORMAT_VERSION: u8 = 1;
+pub const DEFAULT_PAGE_SIZE: usize = 4096;
+
+const PAGE_MAGIC: &[u8; 4] = b"KPGS";
+const FILE_HEADER_LEN: usize = 40;
+const PAGE_HEADER_LEN: usize = 4;
+const MIN_PAGE_SIZE: usize = 256;
+const MAX_PAGE_SIZE: usize = 65536;
+
+/// Binary encoding for keys and values stored in pages
+///
+/// Encodings must be self-delimiting so entries can be packed back to back.
+pub trait PageCodec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Result<Self, String>;
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if input.len() < len {
+        return Err("Page entry is truncated".to_string());
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! impl_page_codec_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl PageCodec for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> Result<Self, String> {
+                    let bytes = take(input, std::mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_page_codec_for_int!(u8, u16, u32, u64, i32, i64);
+
+impl PageCodec for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, String> {
+        let len = u32::decode(input)? as usize;
+        Ok(take(input, len)?.to_vec())
+    }
+}
+
+impl PageCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, String> {
+        let len = u32::decode(input)? as usize;
+        String::from_utf8(take(input, len)?.to_vec())
+            .map_err(|_| "Page entry is not valid UTF-8".to_string())
+    }
+}
+
+impl PageCodec for NaiveDate {
+    fn encode(&self, out: &mut Vec<u8>) {
+        chrono::Datelike::num_days_from_ce(self).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, String> {
+        let days = i32::decode(input)?;
+        NaiveDate::from_num_days_from_ce_opt(days)
+            .ok_or_else(|| format!("Invalid date in page entry: {}", days))
+    }
+}
+
+impl<A: PageCodec, B: PageCodec> PageCodec for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, String> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+/// Write entries, which must be in strictly increasing key order, as a page file
+///
+/// The file is written beside `path` and renamed into place, so readers that already
+/// have the old file mapped keep a consistent view.
+pub fn write_pages<'a, K, V, I>(path: &Path, entries: I, page_size: usize) -> Result<(), String>
+where
+    K: PageCodec + Ord + 'a,
+    V: PageCodec + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    if !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err(format!(
+            "Page size must be between {} and {} bytes",
+            MIN_PAGE_SIZE, MAX_PAGE_SIZE
+        ));
+    }
+
+    let tmp_path = path.with_extension("pages.tmp");
+    let file = File::create(&tmp_path).map_err(|e| e.to_string())?;
+    let mut writer = BufWriter::new(file);
+    let io = |e: std::io::Error| e.to_string();
+
+    // Header page is written last, once the counts are known
+    writer.write_all(&vec![0u8; page_size]).map_err(io)?;
+
+    let mut fences = Vec::new();
+    let mut page = Vec::with_capacity(page_size);
+    let mut page_entries: u16 = 0;
+    let mut data_pages: u32 = 0;
+    let mut entry_count: u64 = 0;
+    let mut entry = Vec::new();
+    let mut previous: Option<&K> = None;
+
+    for (key, value) in entries {
+        if previous.is_some_and(|p| p >= key) {
+            return Err("Entries must be written in strictly increasing key order".to_string());
+        }
+        previous = Some(key);
+
+        entry.clear();
+        key.encode(&mut entry);
+        value.encode(&mut entry);
+        if PAGE_HEADER_LEN + entry.len() > page_size {
+            return Err(format!(
+                "Entry of {} bytes does not fit in a {} byte page",
+                entry.len(),
+                page_size
+            ));
+        }
+
+        if page_entries == u16::MAX || PAGE_HEADER_LEN + page.len() + entry.len() > page_size {
+            flush_page(&mut writer, &page, page_entries, page_size).map_err(io)?;
+            data_pages += 1;
+            page.clear();
+            page_entries = 0;
+        }
+        if page_entries == 0 {
+            key.encode(&mut fences);
+        }
+        page.extend_from_slice(&entry);
+        page_entries += 1;
+        entry_count += 1;
+    }
+    if page_entries > 0 {
+        flush_page(&mut writer, &page, page_entries, page_size).map_err(io)?;
+        data_pages += 1;
+    }
+
+    let fence_offset = (1 + data_pages as u64) * page_size as u64;
+    writer.write_all(&fences).map_err(io)?;
+
+    let mut header = Vec::with_capacity(FILE_HEADER_LEN);
+    header.extend_from_slice(PAGE_MAGIC);
+    header.push(PAGE_FORMAT_VERSION);
+    header.extend_from_slice(&[0u8; 3]);
+    header.extend_from_slice(&(page_size as u32).to_le_bytes());
+    header.extend_from_slice(&entry_count.to_le_bytes());
+    header.extend_from_slice(&data_pages.to_le_bytes());
+    header.extend_from_slice(&fence_offset.to_le_bytes());
+    header.extend_from_slice(&(fences.len() as u64).to_le_bytes());
+
+    let mut file = writer.into_inner().map_err(|e| e.to_string())?;
+    std::io::Seek::rewind(&mut file).map_err(io)?;
+    file.write_all(&header).map_err(io)?;
+    file.sync_all().map_err(io)?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).map_err(io)
+}
+
+fn flush_page(
+    writer: &mut impl Write,
+    body: &[u8],
+    entries: u16,
+    page_size: usize,
+) -> std::io::Result<()> {
+    writer.write_all(&entries.to_le_bytes())?;
+    writer.write_all(&(body.len() as u16).to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&vec![0u8; page_size - PAGE_HEADER_LEN - body.len()])
+}
+
+/// Read-only view of a page file through a memory map
+///
+/// Only the first key of every page is held in memory. Lookups decode a single page
+/// and range scans decode pages as the iterator reaches them. The file must not be
+/// modified in place while mapped; `write_pages` replaces files by rename.
+pub struct PagedMap<K, V> {
+    mmap: Mmap,
+    page_size: usize,
+    len: usize,
+    fences: Vec<K>,
+    _values: PhantomData<V>,
+}
+
+impl<K: PageCodec + Ord, V: PageCodec> PagedMap<K, V> {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        // SAFETY: page files are only ever replaced by rename, never written in place
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+
+        if mmap.len() < FILE_HEADER_LEN || &mmap[..4] != PAGE_MAGIC {
+            return Err("Not a page file".to_string());
+        }
+        if mmap[4] != PAGE_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported page format version {} (expected {})",
+                mmap[4], PAGE_FORMAT_VERSION
+            ));
+        }
+
+        let read_u32 = |at: usize| u32::from_le_bytes(mmap[at..at + 4].try_into().unwrap());
+        let read_u64 = |at: usize| u64::from_le_bytes(mmap[at..at + 8].try_into().unwrap());
+        let page_size = read_u32(8) as usize;
+        let len = read_u64(12) as usize;
+        let data_pages = read_u32(20) as usize;
+        let fence_offset = read_u64(24) as usize;
+        let fence_len = read_u64(32) as usize;
+
+        if !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size)
+            || fence_offset != (1 + data_pages) * page_size
+            || mmap.len() != fence_offset + fence_len
+        {
+            return Err("Page file header does not match the file layout".to_string());
+        }
+
+        let mut input = &mmap[fence_offset..];
+        let mut fences = Vec::with_capacity(data_pages);
+        for _ in 0..data_pages {
+            let key = K::decode(&mut input)?;
+            if fences.last().is_some_and(|last| last >= &key) {
+                return Err("Page fence keys are out of order".to_string());
+            }
+            fences.push(key);
+        }
+
+        Ok(Self {
+            mmap,
+            page_size,
+            len,
+            fences,
+            _values: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.fences.len()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>, String>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let page = self.fences.partition_point(|fence| fence.borrow() <= key);
+        if page == 0 {
+            return Ok(None);
+        }
+        for (candidate, value) in self.decode_page(page - 1)? {
+            match candidate.borrow().cmp(key) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => return Ok(Some(value)),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Entries with keys in `range`, decoded one page at a time
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> PagedRange<'_, K, V>
+    where
+        K: Clone,
+    {
+        let page = match range.start_bound() {
+            Bound::Included(start) | Bound::Excluded(start) => self
+                .fences
+                .partition_point(|fence| fence <= start)
+                .saturating_sub(1),
+            Bound::Unbounded => 0,
+        };
+        PagedRange {
+            map: self,
+            next_page: page,
+            buffered: Vec::new().into_iter(),
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            done: false,
+        }
+    }
+
+    pub fn iter(&self) -> PagedRange<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.range(..)
+    }
+
+    fn decode_page(&self, page: usize) -> Result<Vec<(K, V)>, String> {
+        let offset = (1 + page) * self.page_size;
+        let bytes = &self.mmap[offset..offset + self.page_size];
+        let count = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+        let used = u16::from_le_bytes([bytes[2], bytes[3]]) as usize;
+        if PAGE_HEADER_LEN + used > self.page_size {
+            return Err(format!("Page {} is corrupt", page));
+        }
+
+        let mut input = &bytes[PAGE_HEADER_LEN..PAGE_HEADER_LEN + used];
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let key = K::decode(&mut input)?;
+            let value = V::decode(&mut input)?;
+            entries.push((key, value));
+        }
+        Ok(entries)
+    }
+}
+
+pub struct PagedRange<'a, K, V> {
+    map: &'a PagedMap<K, V>,
+    next_page: usize,
+    buffered: std::vec::IntoIter<(K, V)>,
+    start: Bound<K>,
+    end: Bound<K>,
+    done: bool,
+}
+
+impl<'a, K: PageCodec + Ord + Clone, V: PageCodec> Iterator for PagedRange<'a, K, V> {
+    type Item = Result<(K, V), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if let Some((key, value)) = self.buffered.next() {
+                let after_start = match &self.start {
+                    Bound::Included(start) => &key >= start,
+                    Bound::Excluded(start) => &key > start,
+                    Bound::Unbounded => true,
+                };
+                if !after_start {
+                    continue;
+                }
+                let before_end = match &self.end {
+                    Bound::Included(end) => &key <= end,
+                    Bound::Excluded(end) => &key < end,
+                    Bound::Unbounded => true,
+                };
+                if !before_end {
+                    self.done = true;
+                    return None;
+                }
+                return Some(Ok((key, value)));
+            }
+
+            if self.next_page >= self.map.page_count() {
+                self.done = true;
+                return None;
+            }
+            match self.map.decode_page(self.next_page) {
+                Ok(entries) => {
+                    self.buffered = entries.into_iter();
+                    self.next_page += 1;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("paged-map-{}.pages", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_round_trip_through_mmap() {
+        let entries: Vec<(i64, String)> = (0..2000)
+            .map(|i| (i * 3, format!("event {}", i)))
+            .collect();
+        let path = temp_path();
+        write_pages(&path, entries.iter().map(|(k, v)| (k, v)), 512).unwrap();
+
+        let map: PagedMap<i64, String> = PagedMap::open(&path).unwrap();
+        assert_eq!(map.len(), 2000);
+        assert!(map.page_count() > 1);
+        assert_eq!(map.get(&300).unwrap(), Some("event 100".to_string()));
+        assert_eq!(map.get(&301).unwrap(), None);
+        assert_eq!(map.get(&-1).unwrap(), None);
+
+        let scanned: Vec<i64> = map.range(299..=330).map(|e| e.unwrap().0).collect();
+        assert_eq!(scanned, vec![300, 303, 306, 309, 312, 315, 318, 321, 324, 327, 330]);
+        assert_eq!(map.iter().count(), 2000);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_unsorted_and_corrupt_files() {
+        let path = temp_path();
+        let unsorted = [(2u32, 0u8), (1, 0)];
+        assert!(write_pages(&path, unsorted.iter().map(|(k, v)| (k, v)), 4096).is_err());
+
+        let key = (NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 7i64);
+        write_pages(&path, [(&key, &1u8)], 4096).unwrap();
+        let map: PagedMap<(NaiveDate, i64), u8> = PagedMap::open(&path).unwrap();
+        assert_eq!(map.get(&key).unwrap(), Some(1));
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4] = 99;
+        fs::write(&path, &bytes).unwrap();
+        assert!(PagedMap::<(NaiveDate, i64), u8>::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,19 +1,256 @@
 // Probabilistic Data Structures Implementation
 // Knuthian Optimization Step 9: Bloom Filters, HyperLogLog, and Count-Min Sketch
 
-use std::collections::hash_map::DefaultHasher;
+use std::borrow::Borrow;
 use std::hash::{Hash, Hasher};
 use std::f64::consts::LN_2;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
 use crate::algorithms::{ComplexityTracker, AlgorithmMetrics};
+use crate::algorithms::paged_map::{write_pages, PageCodec, PagedMap, DEFAULT_PAGE_SIZE};
+
+/// Version written into every encoded sketch; bump when the layout or hashing changes
+pub const SKETCH_FORMAT_VERSION: u8 = 1;
+const SKETCH_MAGIC: &[u8; 2] = b"KS";
+
+/// Sketch type tag in the binary header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SketchKind {
+    BloomFilter = 1,
+    HyperLogLog = 2,
+    CountMinSketch = 3,
+    SpaceSaving = 4,
+    CmsTopK = 5,
+}
 
-/// Bloom Filter for membership testing with false positives
+/// Deterministic FNV-1a hasher with a SplitMix64 finaliser. Encoded sketches are only
+/// mergeable if every process hashes items identically, which `DefaultHasher` does not promise.
+/// Integers are fed in as little-endian bytes and `usize` (including slice and string length
+/// prefixes) as a `u64`, so the hash doesn't depend on the platform's endianness or word size.
 #[derive(Debug, Clone)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(i as u8);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut x = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+}
+
+pub(crate) fn stable_hash<T: Hash + ?Sized>(item: &T, seed: u64) -> u64 {
+    let mut hasher = StableHasher::new();
+    item.hash(&mut hasher);
+    hasher.write_u64(seed);
+    hasher.finish()
+}
+
+/// Read counter kept outside sketch state so query methods only need `&self`
+#[derive(Debug, Default)]
+pub struct QueryCounter(AtomicU64);
+
+impl QueryCounter {
+    pub fn record(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Metrics snapshot combining write operations with counted reads
+    pub fn snapshot(&self, metrics: &AlgorithmMetrics) -> AlgorithmMetrics {
+        let mut snapshot = metrics.clone();
+        snapshot.complexity.operations_count += self.count();
+        snapshot
+    }
+}
+
+impl Clone for QueryCounter {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.count()))
+    }
+}
+
+/// Little-endian writer for the versioned sketch layout: magic, version, kind, payload
+pub(crate) struct SketchEncoder(Vec<u8>);
+
+impl SketchEncoder {
+    pub(crate) fn new(kind: SketchKind) -> Self {
+        let mut bytes = SKETCH_MAGIC.to_vec();
+        bytes.push(SKETCH_FORMAT_VERSION);
+        bytes.push(kind as u8);
+        Self(bytes)
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value);
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+pub(crate) struct SketchDecoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SketchDecoder<'a> {
+    pub(crate) fn new(bytes: &'a [u8], kind: SketchKind) -> Result<Self, String> {
+        if bytes.len() < 4 || &bytes[..2] != SKETCH_MAGIC {
+            return Err("Not an encoded sketch".to_string());
+        }
+        check_version(bytes[2])?;
+        if bytes[3] != kind as u8 {
+            return Err(format!("Expected {:?} sketch, found kind {}", kind, bytes[3]));
+        }
+        Ok(Self { bytes, pos: 4 })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| "Encoded sketch is truncated".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub(crate) fn finish(self) -> Result<(), String> {
+        if self.pos == self.bytes.len() {
+            Ok(())
+        } else {
+            Err("Trailing bytes after encoded sketch".to_string())
+        }
+    }
+}
+
+pub(crate) fn check_version(version: u8) -> Result<(), String> {
+    if version == 0 || version > SKETCH_FORMAT_VERSION {
+        Err(format!("Unsupported sketch format version {}", version))
+    } else {
+        Ok(())
+    }
+}
+
+/// Bloom Filter for membership testing with false positives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "BloomFilterRepr", into = "BloomFilterRepr")]
 pub struct BloomFilter {
     bit_array: Vec<bool>,
     size: usize,
     hash_functions: usize,
     items_count: usize,
     metrics: AlgorithmMetrics,
+    queries: QueryCounter,
+}
+
+/// Serde form of a Bloom filter with the bit array packed into bytes
+#[derive(Debug, Serialize, Deserialize)]
+struct BloomFilterRepr {
+    version: u8,
+    size: usize,
+    hash_functions: usize,
+    items_count: usize,
+    bits: Vec<u8>,
 }
 
 impl BloomFilter {
@@ -22,12 +259,17 @@ impl BloomFilter {
         let size = Self::optimal_size(expected_items, false_positive_rate);
         let hash_functions = Self::optimal_hash_functions(size, expected_items);
         
+        Self::with_parameters(size, hash_functions, vec![false; size], 0)
+    }
+
+    fn with_parameters(size: usize, hash_functions: usize, bit_array: Vec<bool>, items_count: usize) -> Self {
         Self {
-            bit_array: vec![false; size],
+            bit_array,
             size,
             hash_functions,
-            items_count: 0,
+            items_count,
             metrics: AlgorithmMetrics::new("BloomFilter", "O(k)", "O(m)"),
+            queries: QueryCounter::default(),
         }
     }
 
@@ -44,7 +286,7 @@ impl BloomFilter {
     }
 
     /// Add item to the filter
-    pub fn insert<T: Hash>(&mut self, item: &T) {
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
         self.metrics.complexity.record_operation();
         
         for i in 0..self.hash_functions {
@@ -57,8 +299,8 @@ impl BloomFilter {
     }
 
     /// Test if item might be in the set (no false negatives)
-    pub fn contains<T: Hash>(&mut self, item: &T) -> bool {
-        self.metrics.complexity.record_operation();
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.queries.record();
         
         for i in 0..self.hash_functions {
             let hash = self.hash_with_seed(item, i);
@@ -72,11 +314,8 @@ impl BloomFilter {
     }
 
     /// Hash with seed for multiple hash functions
-    fn hash_with_seed<T: Hash>(&self, item: &T, seed: usize) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        item.hash(&mut hasher);
-        seed.hash(&mut hasher);
-        hasher.finish()
+    fn hash_with_seed<T: Hash + ?Sized>(&self, item: &T, seed: usize) -> u64 {
+        stable_hash(item, seed as u64)
     }
 
     /// Estimate current false positive probability
@@ -113,8 +352,44 @@ impl BloomFilter {
         Ok(())
     }
 
-    pub fn get_metrics(&self) -> &AlgorithmMetrics {
-        &self.metrics
+    fn packed_bits(&self) -> Vec<u8> {
+        self.bit_array
+            .chunks(8)
+            .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << i)))
+            .collect()
+    }
+
+    fn from_parts(size: usize, hash_functions: usize, bits: &[u8], items_count: usize) -> Result<Self, String> {
+        if size == 0 || hash_functions == 0 || bits.len() != size.div_ceil(8) {
+            return Err("Bloom filter bit array does not match its parameters".to_string());
+        }
+        let bit_array = (0..size).map(|i| bits[i / 8] & (1 << (i % 8)) != 0).collect();
+        Ok(Self::with_parameters(size, hash_functions, bit_array, items_count))
+    }
+
+    /// Versioned binary encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = SketchEncoder::new(SketchKind::BloomFilter);
+        encoder.u64(self.size as u64);
+        encoder.u32(self.hash_functions as u32);
+        encoder.u64(self.items_count as u64);
+        encoder.bytes(&self.packed_bits());
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = SketchDecoder::new(bytes, SketchKind::BloomFilter)?;
+        let size = decoder.u64()? as usize;
+        let hash_functions = decoder.u32()? as usize;
+        let items_count = decoder.u64()? as usize;
+        let bits = decoder.bytes()?;
+        decoder.finish()?;
+        Self::from_parts(size, hash_functions, bits, items_count)
+    }
+
+    /// Write operations plus `&self` membership queries
+    pub fn get_metrics(&self) -> AlgorithmMetrics {
+        self.queries.snapshot(&self.metrics)
     }
 
     pub fn len(&self) -> usize {
@@ -126,26 +401,64 @@ impl BloomFilter {
     }
 }
 
+impl From<BloomFilter> for BloomFilterRepr {
+    fn from(filter: BloomFilter) -> Self {
+        Self {
+            version: SKETCH_FORMAT_VERSION,
+            size: filter.size,
+            hash_functions: filter.hash_functions,
+            items_count: filter.items_count,
+            bits: filter.packed_bits(),
+        }
+    }
+}
+
+impl TryFrom<BloomFilterRepr> for BloomFilter {
+    type Error = String;
+
+    fn try_from(repr: BloomFilterRepr) -> Result<Self, Self::Error> {
+        check_version(repr.version)?;
+        Self::from_parts(repr.size, repr.hash_functions, &repr.bits, repr.items_count)
+    }
+}
+
 /// HyperLogLog for cardinality estimation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "HyperLogLogRepr", into = "HyperLogLogRepr")]
 pub struct HyperLogLog {
     buckets: Vec<u8>,
     bucket_count: usize,
+    precision: u8,
     alpha: f64,
     metrics: AlgorithmMetrics,
+    queries: QueryCounter,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HyperLogLogRepr {
+    version: u8,
+    precision: u8,
+    buckets: Vec<u8>,
 }
 
 impl HyperLogLog {
     /// Create new HyperLogLog with 2^precision buckets
     pub fn new(precision: u8) -> Self {
         let bucket_count = 1 << precision;
+        Self::with_buckets(precision, vec![0; bucket_count])
+    }
+
+    fn with_buckets(precision: u8, buckets: Vec<u8>) -> Self {
+        let bucket_count = buckets.len();
         let alpha = Self::calculate_alpha(bucket_count);
         
         Self {
-            buckets: vec![0; bucket_count],
+            buckets,
             bucket_count,
+            precision,
             alpha,
             metrics: AlgorithmMetrics::new("HyperLogLog", "O(1)", "O(2^p)"),
+            queries: QueryCounter::default(),
         }
     }
 
@@ -159,25 +472,25 @@ impl HyperLogLog {
     }
 
     /// Add item to the estimator
-    pub fn add<T: Hash>(&mut self, item: &T) {
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T) {
         self.metrics.complexity.record_operation();
         
         let hash = self.hash_item(item);
         let bucket_index = (hash & (self.bucket_count - 1) as u64) as usize;
-        let leading_zeros = (hash >> (64 - 6)).leading_zeros() as u8 + 1;
+        // Rank of the first set bit in the hash bits not used for the bucket index
+        let remaining = hash >> self.precision;
+        let leading_zeros = (remaining.leading_zeros() - self.precision as u32 + 1) as u8;
         
         self.buckets[bucket_index] = self.buckets[bucket_index].max(leading_zeros);
     }
 
-    fn hash_item<T: Hash>(&self, item: &T) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        item.hash(&mut hasher);
-        hasher.finish()
+    fn hash_item<T: Hash + ?Sized>(&self, item: &T) -> u64 {
+        stable_hash(item, 0)
     }
 
     /// Estimate cardinality
-    pub fn estimate(&mut self) -> f64 {
-        self.metrics.complexity.record_operation();
+    pub fn estimate(&self) -> f64 {
+        self.queries.record();
         
         let raw_estimate = self.alpha * (self.bucket_count as f64).powi(2) / 
             self.buckets.iter().map(|&b| 2.0_f64.powi(-(b as i32))).sum::<f64>();
@@ -212,19 +525,73 @@ impl HyperLogLog {
         Ok(())
     }
 
-    pub fn get_metrics(&self) -> &AlgorithmMetrics {
-        &self.metrics
+    /// Versioned binary encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = SketchEncoder::new(SketchKind::HyperLogLog);
+        encoder.u32(self.precision as u32);
+        encoder.bytes(&self.buckets);
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = SketchDecoder::new(bytes, SketchKind::HyperLogLog)?;
+        let precision = decoder.u32()?;
+        let buckets = decoder.bytes()?.to_vec();
+        decoder.finish()?;
+        Self::from_parts(precision, buckets)
+    }
+
+    fn from_parts(precision: u32, buckets: Vec<u8>) -> Result<Self, String> {
+        if !(4..=18).contains(&precision) || buckets.len() != 1 << precision {
+            return Err("HyperLogLog buckets do not match its precision".to_string());
+        }
+        Ok(Self::with_buckets(precision as u8, buckets))
+    }
+
+    /// Write operations plus `&self` estimates
+    pub fn get_metrics(&self) -> AlgorithmMetrics {
+        self.queries.snapshot(&self.metrics)
+    }
+}
+
+impl From<HyperLogLog> for HyperLogLogRepr {
+    fn from(hll: HyperLogLog) -> Self {
+        Self {
+            version: SKETCH_FORMAT_VERSION,
+            precision: hll.precision,
+            buckets: hll.buckets,
+        }
+    }
+}
+
+impl TryFrom<HyperLogLogRepr> for HyperLogLog {
+    type Error = String;
+
+    fn try_from(repr: HyperLogLogRepr) -> Result<Self, Self::Error> {
+        check_version(repr.version)?;
+        Self::from_parts(repr.precision as u32, repr.buckets)
     }
 }
 
 /// Count-Min Sketch for frequency estimation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "CountMinSketchRepr", into = "CountMinSketchRepr")]
 pub struct CountMinSketch {
     table: Vec<Vec<u32>>,
     width: usize,
     depth: usize,
     total_count: u64,
     metrics: AlgorithmMetrics,
+    queries: QueryCounter,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CountMinSketchRepr {
+    version: u8,
+    width: usize,
+    depth: usize,
+    total_count: u64,
+    table: Vec<Vec<u32>>,
 }
 
 impl CountMinSketch {
@@ -233,31 +600,36 @@ impl CountMinSketch {
         let width = (std::f64::consts::E / epsilon).ceil() as usize;
         let depth = (1.0 / delta).ln().ceil() as usize;
         
+        Self::with_table(vec![vec![0; width]; depth], 0)
+    }
+
+    fn with_table(table: Vec<Vec<u32>>, total_count: u64) -> Self {
         Self {
-            table: vec![vec![0; width]; depth],
-            width,
-            depth,
-            total_count: 0,
+            width: table.first().map_or(0, |row| row.len()),
+            depth: table.len(),
+            table,
+            total_count,
             metrics: AlgorithmMetrics::new("CountMinSketch", "O(log(1/δ))", "O(log(1/δ)/ε)"),
+            queries: QueryCounter::default(),
         }
     }
 
     /// Add item with given count
-    pub fn add<T: Hash>(&mut self, item: &T, count: u32) {
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T, count: u32) {
         self.metrics.complexity.record_operation();
         
         for i in 0..self.depth {
             let hash = self.hash_with_seed(item, i);
             let col = (hash % self.width as u64) as usize;
-            self.table[i][col] += count;
+            self.table[i][col] = self.table[i][col].saturating_add(count);
         }
         
         self.total_count += count as u64;
     }
 
     /// Estimate frequency of item
-    pub fn estimate<T: Hash>(&mut self, item: &T) -> u32 {
-        self.metrics.complexity.record_operation();
+    pub fn estimate<T: Hash + ?Sized>(&self, item: &T) -> u32 {
+        self.queries.record();
         
         let mut min_count = u32::MAX;
         
@@ -270,11 +642,8 @@ impl CountMinSketch {
         min_count
     }
 
-    fn hash_with_seed<T: Hash>(&self, item: &T, seed: usize) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        item.hash(&mut hasher);
-        seed.hash(&mut hasher);
-        hasher.finish()
+    fn hash_with_seed<T: Hash + ?Sized>(&self, item: &T, seed: usize) -> u64 {
+        stable_hash(item, seed as u64)
     }
 
     /// Merge with another Count-Min Sketch
@@ -285,7 +654,7 @@ impl CountMinSketch {
 
         for i in 0..self.depth {
             for j in 0..self.width {
-                self.table[i][j] += other.table[i][j];
+                self.table[i][j] = self.table[i][j].saturating_add(other.table[i][j]);
             }
         }
 
@@ -298,6 +667,11 @@ impl CountMinSketch {
         self.total_count
     }
 
+    /// Overestimate bound ε·N that holds with probability 1 - δ
+    pub fn error_bound(&self) -> u64 {
+        (std::f64::consts::E / self.width as f64 * self.total_count as f64).ceil() as u64
+    }
+
     /// Get heavy hitters (items with frequency > threshold)
     pub fn heavy_hitters(&self, threshold: f64) -> Vec<(usize, u32)> {
         let mut heavy_hitters = Vec::new();
@@ -317,38 +691,104 @@ impl CountMinSketch {
         heavy_hitters
     }
 
-    pub fn get_metrics(&self) -> &AlgorithmMetrics {
-        &self.metrics
+    /// Versioned binary encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = SketchEncoder::new(SketchKind::CountMinSketch);
+        encoder.u32(self.width as u32);
+        encoder.u32(self.depth as u32);
+        encoder.u64(self.total_count);
+        for counter in self.table.iter().flatten() {
+            encoder.u32(*counter);
+        }
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = SketchDecoder::new(bytes, SketchKind::CountMinSketch)?;
+        let width = decoder.u32()? as usize;
+        let depth = decoder.u32()? as usize;
+        let total_count = decoder.u64()?;
+        if width.checked_mul(depth).and_then(|cells| cells.checked_mul(4)) != Some(decoder.remaining()) {
+            return Err("Count-Min Sketch table does not match its dimensions".to_string());
+        }
+        let mut table = Vec::with_capacity(depth);
+        for _ in 0..depth {
+            let row = (0..width).map(|_| decoder.u32()).collect::<Result<Vec<u32>, String>>()?;
+            table.push(row);
+        }
+        decoder.finish()?;
+        Self::from_parts(width, depth, table, total_count)
+    }
+
+    fn from_parts(width: usize, depth: usize, table: Vec<Vec<u32>>, total_count: u64) -> Result<Self, String> {
+        if width == 0 || table.len() != depth || table.iter().any(|row| row.len() != width) {
+            return Err("Count-Min Sketch table does not match its dimensions".to_string());
+        }
+        Ok(Self::with_table(table, total_count))
+    }
+
+    /// Write operations plus `&self` estimates
+    pub fn get_metrics(&self) -> AlgorithmMetrics {
+        self.queries.snapshot(&self.metrics)
+    }
+}
+
+impl From<CountMinSketch> for CountMinSketchRepr {
+    fn from(cms: CountMinSketch) -> Self {
+        Self {
+            version: SKETCH_FORMAT_VERSION,
+            width: cms.width,
+            depth: cms.depth,
+            total_count: cms.total_count,
+            table: cms.table,
+        }
+    }
+}
+
+impl TryFrom<CountMinSketchRepr> for CountMinSketch {
+    type Error = String;
+
+    fn try_from(repr: CountMinSketchRepr) -> Result<Self, Self::Error> {
+        check_version(repr.version)?;
+        Self::from_parts(repr.width, repr.depth, repr.table, repr.total_count)
     }
 }
 
 /// Skip List with probabilistic balancing
+///
+/// Nodes live in an arena and link to each other by index, slot 0 being the head.
+/// Removed slots are reused by later inserts.
 #[derive(Debug)]
 pub struct SkipList<K, V> {
-    head: Box<SkipNode<K, V>>,
+    nodes: Vec<SkipNode<K, V>>,
+    free: Vec<usize>,
     max_level: usize,
     level: usize,
     size: usize,
     metrics: AlgorithmMetrics,
 }
 
+const HEAD: usize = 0;
+const NIL: usize = usize::MAX;
+
 #[derive(Debug)]
 struct SkipNode<K, V> {
     key: Option<K>,
     value: Option<V>,
-    forward: Vec<Option<Box<SkipNode<K, V>>>>,
+    forward: Vec<usize>,
 }
 
 impl<K: Ord + Clone, V: Clone> SkipList<K, V> {
     pub fn new(max_level: usize) -> Self {
-        let head = Box::new(SkipNode {
+        let head = SkipNode {
             key: None,
             value: None,
-            forward: vec![None; max_level + 1],
-        });
+            forward: vec![NIL; max_level + 1],
+        };
 
         Self {
-            head,
+            nodes: vec![head],
+            free: Vec::new(),
             max_level,
             level: 0,
             size: 0,
@@ -356,6 +796,41 @@ impl<K: Ord + Clone, V: Clone> SkipList<K, V> {
         }
     }
 
+    /// Build a list from unsorted entries; later duplicates win
+    ///
+    /// Levels are assigned deterministically so every level skips every other node of
+    /// the level below, the shape random insertion only reaches on average.
+    pub fn bulk_load<I: IntoIterator<Item = (K, V)>>(max_level: usize, entries: I) -> Self {
+        let mut entries: Vec<(K, V)> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut list = Self::new(max_level);
+        let mut last_at_level = vec![HEAD; max_level + 1];
+        for (key, value) in entries {
+            let last = last_at_level[0];
+            if last != HEAD && list.key_at(last) == &key {
+                list.nodes[last].value = Some(value);
+                continue;
+            }
+
+            let level = ((list.size + 1).trailing_zeros() as usize).min(max_level);
+            let index = list.nodes.len();
+            list.nodes.push(SkipNode {
+                key: Some(key),
+                value: Some(value),
+                forward: vec![NIL; level + 1],
+            });
+            for (l, last) in last_at_level.iter_mut().enumerate().take(level + 1) {
+                list.nodes[*last].forward[l] = index;
+                *last = index;
+            }
+            list.level = list.level.max(level);
+            list.size += 1;
+        }
+        list.metrics.complexity.record_operation();
+        list
+    }
+
     /// Generate random level using geometric distribution
     fn random_level(&self) -> usize {
         let mut level = 0;
@@ -365,80 +840,153 @@ impl<K: Ord + Clone, V: Clone> SkipList<K, V> {
         level
     }
 
-    /// Insert key-value pair
-    pub fn insert(&mut self, key: K, value: V) {
-        self.metrics.complexity.record_operation();
-        
-        let mut update = vec![None; self.max_level + 1];
-        let mut current = &mut self.head;
-
-        // Find insertion position
-        for i in (0..=self.level).rev() {
-            while let Some(ref next) = current.forward[i] {
-                if let Some(ref next_key) = next.key {
-                    if *next_key < key {
-                        current = current.forward[i].as_mut().unwrap();
-                        continue;
-                    }
+    fn key_at(&self, index: usize) -> &K {
+        self.nodes[index]
+            .key
+            .as_ref()
+            .expect("only the head node has no key")
+    }
+
+    /// The last node before `key` on every level, from level 0 up to `max_level`
+    fn predecessors<Q>(&self, key: &Q) -> Vec<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update = vec![HEAD; self.max_level + 1];
+        let mut current = HEAD;
+        for level in (0..=self.level).rev() {
+            loop {
+                let next = self.nodes[current].forward[level];
+                if next != NIL && self.key_at(next).borrow() < key {
+                    current = next;
+                } else {
+                    break;
                 }
-                break;
             }
-            update[i] = Some(current as *mut SkipNode<K, V>);
+            update[level] = current;
         }
+        update
+    }
 
-        let new_level = self.random_level();
-        if new_level > self.level {
-            for i in (self.level + 1)..=new_level {
-                update[i] = Some(&mut *self.head as *mut SkipNode<K, V>);
-            }
-            self.level = new_level;
+    /// Insert key-value pair, returning the previous value if the key was present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.metrics.complexity.record_operation();
+
+        let update = self.predecessors(&key);
+        let candidate = self.nodes[update[0]].forward[0];
+        if candidate != NIL && self.key_at(candidate) == &key {
+            return self.nodes[candidate].value.replace(value);
         }
 
-        let mut new_node = Box::new(SkipNode {
+        let new_level = self.random_level();
+        self.level = self.level.max(new_level);
+
+        let node = SkipNode {
             key: Some(key),
             value: Some(value),
-            forward: vec![None; self.max_level + 1],
-        });
+            forward: (0..=new_level)
+                .map(|level| self.nodes[update[level]].forward[level])
+                .collect(),
+        };
+        let index = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
 
         // Update forward pointers
-        for i in 0..=new_level {
-            if let Some(update_ptr) = update[i] {
-                unsafe {
-                    new_node.forward[i] = (*update_ptr).forward[i].take();
-                    (*update_ptr).forward[i] = Some(new_node);
-                    new_node = (*update_ptr).forward[i].as_mut().unwrap();
-                }
-            }
+        for (level, &previous) in update.iter().enumerate().take(new_level + 1) {
+            self.nodes[previous].forward[level] = index;
         }
 
         self.size += 1;
+        None
+    }
+
+    /// Remove a key, returning its value if it was present
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.metrics.complexity.record_operation();
+
+        let update = self.predecessors(key);
+        let target = self.nodes[update[0]].forward[0];
+        if target == NIL || self.key_at(target).borrow() != key {
+            return None;
+        }
+
+        let forward = std::mem::take(&mut self.nodes[target].forward);
+        for (level, next) in forward.into_iter().enumerate() {
+            self.nodes[update[level]].forward[level] = next;
+        }
+        while self.level > 0 && self.nodes[HEAD].forward[self.level] == NIL {
+            self.level -= 1;
+        }
+
+        self.nodes[target].key = None;
+        self.free.push(target);
+        self.size -= 1;
+        self.nodes[target].value.take()
     }
 
     /// Search for key
     pub fn search(&mut self, key: &K) -> Option<&V> {
         self.metrics.complexity.record_operation();
-        
-        let mut current = &self.head;
-
-        for i in (0..=self.level).rev() {
-            while let Some(ref next) = current.forward[i] {
-                if let Some(ref next_key) = next.key {
-                    match next_key.cmp(key) {
-                        std::cmp::Ordering::Less => {
-                            current = next;
-                            continue;
-                        }
-                        std::cmp::Ordering::Equal => {
-                            return next.value.as_ref();
-                        }
-                        std::cmp::Ordering::Greater => break,
-                    }
+        self.get(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let candidate = self.nodes[self.predecessors(key)[0]].forward[0];
+        if candidate != NIL && self.key_at(candidate).borrow() == key {
+            self.nodes[candidate].value.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// In-order iterator over the entries whose keys fall in `range`
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> SkipListRange<'_, K, V> {
+        let next = match range.start_bound() {
+            Bound::Included(start) => self.nodes[self.predecessors(start)[0]].forward[0],
+            Bound::Excluded(start) => {
+                let candidate = self.nodes[self.predecessors(start)[0]].forward[0];
+                if candidate != NIL && self.key_at(candidate) == start {
+                    self.nodes[candidate].forward[0]
+                } else {
+                    candidate
                 }
-                break;
             }
+            Bound::Unbounded => self.nodes[HEAD].forward[0],
+        };
+        SkipListRange {
+            list: self,
+            next,
+            end: range.end_bound().cloned(),
         }
+    }
 
-        None
+    pub fn iter(&self) -> SkipListRange<'_, K, V> {
+        self.range(..)
     }
 
     pub fn len(&self) -> usize {
@@ -452,6 +1000,56 @@ impl<K: Ord + Clone, V: Clone> SkipList<K, V> {
     pub fn get_metrics(&self) -> &AlgorithmMetrics {
         &self.metrics
     }
+
+    /// Write the list to `path` in the paged on-disk format
+    pub fn save_pages(&self, path: &Path) -> Result<(), String>
+    where
+        K: PageCodec,
+        V: PageCodec,
+    {
+        write_pages(path, self.iter(), DEFAULT_PAGE_SIZE)
+    }
+
+    /// Load a list previously written with `save_pages`
+    pub fn load_pages(max_level: usize, path: &Path) -> Result<Self, String>
+    where
+        K: PageCodec,
+        V: PageCodec,
+    {
+        let pages: PagedMap<K, V> = PagedMap::open(path)?;
+        let entries = pages.iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::bulk_load(max_level, entries))
+    }
+}
+
+/// Iterator over a key range of a `SkipList`
+pub struct SkipListRange<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    next: usize,
+    end: Bound<K>,
+}
+
+impl<'a, K: Ord, V> Iterator for SkipListRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == NIL {
+            return None;
+        }
+        let node = &self.list.nodes[self.next];
+        let key = node.key.as_ref()?;
+        let in_range = match &self.end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+        if !in_range {
+            self.next = NIL;
+            return None;
+        }
+        self.next = node.forward[0];
+        Some((key, node.value.as_ref()?))
+    }
 }
 
 /// Probabilistic data structure collection
@@ -541,6 +1139,19 @@ mod rand {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_stable_hash_is_platform_independent() {
+        // Lengths hash as u64 and integers as little-endian bytes, whatever the target
+        let mut by_usize = StableHasher::new();
+        by_usize.write_usize(3);
+        let mut by_u64 = StableHasher::new();
+        by_u64.write(&3u64.to_le_bytes());
+        assert_eq!(by_usize.finish(), by_u64.finish());
+
+        assert_eq!(stable_hash(&[1u32, 2, 3][..], 7), stable_hash(&vec![1u32, 2, 3], 7));
+        assert_eq!(stable_hash("custody", 0), 10617221552661208640);
+    }
+
     #[test]
     fn test_bloom_filter() {
         let mut filter = BloomFilter::new(1000, 0.01);
@@ -574,6 +1185,70 @@ mod tests {
         assert!(cms.estimate(&"world") >= 3);
     }
 
+    #[test]
+    fn test_queries_take_shared_reference() {
+        let mut filter = BloomFilter::new(100, 0.01);
+        filter.insert("sender@example.com");
+        let before = filter.get_metrics().complexity.operations_count;
+
+        let shared = &filter;
+        assert!(shared.contains("sender@example.com"));
+        assert!(!shared.contains("other@example.com"));
+        assert_eq!(filter.get_metrics().complexity.operations_count, before + 2);
+    }
+
+    #[test]
+    fn test_sketch_binary_round_trip() {
+        let mut filter = BloomFilter::new(500, 0.01);
+        let mut hll = HyperLogLog::new(10);
+        let mut cms = CountMinSketch::new(0.01, 0.01);
+        for i in 0..300 {
+            filter.insert(&i);
+            hll.add(&i);
+            cms.add(&(i % 7), 1);
+        }
+
+        let filter_copy = BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert!((0..300).all(|i| filter_copy.contains(&i)));
+        assert_eq!(filter_copy.len(), 300);
+
+        let hll_copy = HyperLogLog::from_bytes(&hll.to_bytes()).unwrap();
+        assert_eq!(hll_copy.estimate(), hll.estimate());
+
+        let cms_copy = CountMinSketch::from_bytes(&cms.to_bytes()).unwrap();
+        assert_eq!(cms_copy.estimate(&3), cms.estimate(&3));
+        assert_eq!(cms_copy.total_count(), 300);
+
+        // Wrong kind, future version and truncation are all rejected
+        assert!(HyperLogLog::from_bytes(&cms.to_bytes()).is_err());
+        let mut future = hll.to_bytes();
+        future[2] = SKETCH_FORMAT_VERSION + 1;
+        assert!(HyperLogLog::from_bytes(&future).is_err());
+        let encoded = cms.to_bytes();
+        assert!(CountMinSketch::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_sketch_serde_round_trip_and_merge() {
+        let mut monday = HyperLogLog::new(12);
+        let mut tuesday = HyperLogLog::new(12);
+        for i in 0..600 {
+            monday.add(&format!("sender-{}", i));
+            tuesday.add(&format!("sender-{}", i + 400));
+        }
+
+        let json = serde_json::to_string(&monday).unwrap();
+        assert!(json.contains("\"version\":1"));
+        let mut restored: HyperLogLog = serde_json::from_str(&json).unwrap();
+        restored.merge(&tuesday).unwrap();
+
+        let estimate = restored.estimate();
+        assert!(estimate > 900.0 && estimate < 1100.0, "estimate was {}", estimate);
+
+        let bad = json.replace("\"version\":1", "\"version\":99");
+        assert!(serde_json::from_str::<HyperLogLog>(&bad).is_err());
+    }
+
     #[test]
     fn test_skip_list() {
         let mut skip_list = SkipList::new(10);
@@ -585,6 +1260,70 @@ mod tests {
         assert_eq!(skip_list.search(&4), None);
     }
 
+    #[test]
+    fn test_skip_list_matches_std_btreemap() {
+        use ::rand::{rngs::StdRng, Rng, SeedableRng};
+        use std::collections::BTreeMap;
+
+        for seed in 0..8u64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let initial: Vec<(u32, u64)> = (0..rng.gen_range(0..300))
+                .map(|i| (rng.gen_range(0..500), i))
+                .collect();
+            let (mut list, mut model) = if seed % 2 == 0 {
+                (SkipList::new(12), BTreeMap::new())
+            } else {
+                (
+                    SkipList::bulk_load(12, initial.clone()),
+                    initial.into_iter().collect(),
+                )
+            };
+
+            for step in 0..4000u64 {
+                let key: u32 = rng.gen_range(0..500);
+                match rng.gen_range(0..10) {
+                    0..=4 => assert_eq!(list.insert(key, step), model.insert(key, step)),
+                    5..=7 => assert_eq!(list.remove(&key), model.remove(&key)),
+                    8 => assert_eq!(list.get(&key), model.get(&key)),
+                    _ => {
+                        let end = key + rng.gen_range(0..60);
+                        assert!(list.range(key..end).eq(model.range(key..end)));
+                        let bounds = (Bound::Excluded(key), Bound::Included(end));
+                        assert!(list.range(bounds).eq(model.range(bounds)));
+                    }
+                }
+                assert_eq!(list.len(), model.len());
+            }
+            assert!(list.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn test_skip_list_pages_by_date() {
+        use chrono::NaiveDate;
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let list = SkipList::bulk_load(
+            8,
+            (1..=28).flat_map(|d| (0..3i64).map(move |id| ((day(d), id), format!("{}-{}", d, id)))),
+        );
+
+        let path = std::env::temp_dir().join(format!("skiplist-{}.pages", uuid::Uuid::new_v4()));
+        list.save_pages(&path).unwrap();
+        let pages: PagedMap<(NaiveDate, i64), String> = PagedMap::open(&path).unwrap();
+        let week: Vec<String> = pages
+            .range((day(8), i64::MIN)..(day(15), i64::MIN))
+            .map(|entry| entry.unwrap().1)
+            .collect();
+        assert_eq!(week.len(), 21);
+        assert_eq!(week.first().map(String::as_str), Some("8-0"));
+        assert_eq!(week.last().map(String::as_str), Some("14-2"));
+
+        let reloaded = SkipList::<(NaiveDate, i64), String>::load_pages(8, &path).unwrap();
+        assert!(reloaded.iter().eq(list.iter()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_probabilistic_set_similarity() {
         let set1 = vec!["a".to_string(), "b".to_string(), "c".to_string()];
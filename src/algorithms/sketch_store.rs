@@ -0,0 +1,286 @@
+// SQLite store for probabilistic sketches keyed by case, metric and day
+// Daily sketches merge into multi-day rollups without rescanning raw communications
+
+use chrono::NaiveDate;
+use sqlx::Row;
+
+use crate::algorithms::probabilistic::{
+    BloomFilter, CountMinSketch, HyperLogLog, SKETCH_FORMAT_VERSION,
+};
+use crate::algorithms::top_k::{CmsTopK, SpaceSaving};
+use crate::db::DbPool;
+
+/// Distinct senders per day
+pub const UNIQUE_SENDERS: &str = "unique_senders";
+/// Word frequencies across message subjects and bodies per day, with the top terms named
+pub const TERM_FREQUENCY: &str = "term_frequency";
+/// Most active correspondents per day
+pub const FREQUENT_SENDERS: &str = "frequent_senders";
+
+/// Parameters every stored sketch of a metric must share to stay mergeable
+pub const SENDER_HLL_PRECISION: u8 = 12;
+pub const TERM_CMS_EPSILON: f64 = 0.001;
+pub const TERM_CMS_DELTA: f64 = 0.01;
+pub const TERM_TOP_K_CAPACITY: usize = 100;
+pub const SENDER_TOP_K_CAPACITY: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SketchStoreError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Sketch encoding error: {0}")]
+    Encoding(String),
+}
+
+/// A sketch that can be persisted and merged with others of the same shape
+pub trait StoredSketch: Sized {
+    const KIND: &'static str;
+
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Result<Self, String>;
+    fn merge_with(&mut self, other: &Self) -> Result<(), String>;
+}
+
+impl StoredSketch for BloomFilter {
+    const KIND: &'static str = "bloom_filter";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_bytes(bytes)
+    }
+
+    fn merge_with(&mut self, other: &Self) -> Result<(), String> {
+        self.union(other)
+    }
+}
+
+impl StoredSketch for HyperLogLog {
+    const KIND: &'static str = "hyperloglog";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_bytes(bytes)
+    }
+
+    fn merge_with(&mut self, other: &Self) -> Result<(), String> {
+        self.merge(other)
+    }
+}
+
+impl StoredSketch for CountMinSketch {
+    const KIND: &'static str = "count_min_sketch";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_bytes(bytes)
+    }
+
+    fn merge_with(&mut self, other: &Self) -> Result<(), String> {
+        self.merge(other)
+    }
+}
+
+impl StoredSketch for SpaceSaving<String> {
+    const KIND: &'static str = "space_saving";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_bytes(bytes)
+    }
+
+    fn merge_with(&mut self, other: &Self) -> Result<(), String> {
+        self.merge(other)
+    }
+}
+
+impl StoredSketch for CmsTopK<String> {
+    const KIND: &'static str = "cms_top_k";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_bytes(bytes)
+    }
+
+    fn merge_with(&mut self, other: &Self) -> Result<(), String> {
+        self.merge(other)
+    }
+}
+
+pub struct SketchStore {
+    pool: DbPool,
+}
+
+impl SketchStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a sketch for one day, replacing any existing one
+    pub async fn save<S: StoredSketch>(
+        &self,
+        case_id: i64,
+        metric: &str,
+        day: NaiveDate,
+        sketch: &S,
+    ) -> Result<(), SketchStoreError> {
+        sqlx::query(
+            "INSERT INTO analytics_sketches
+             (case_id, metric, bucket_date, sketch_kind, format_version, data, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(case_id, metric, bucket_date) DO UPDATE SET
+               sketch_kind = excluded.sketch_kind,
+               format_version = excluded.format_version,
+               data = excluded.data,
+               updated_at = excluded.updated_at",
+        )
+        .bind(case_id)
+        .bind(metric)
+        .bind(day.to_string())
+        .bind(S::KIND)
+        .bind(SKETCH_FORMAT_VERSION as i64)
+        .bind(sketch.encode())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn load<S: StoredSketch>(
+        &self,
+        case_id: i64,
+        metric: &str,
+        day: NaiveDate,
+    ) -> Result<Option<S>, SketchStoreError> {
+        let row = sqlx::query(
+            "SELECT sketch_kind, data FROM analytics_sketches
+             WHERE case_id = ? AND metric = ? AND bucket_date = ?",
+        )
+        .bind(case_id)
+        .bind(metric)
+        .bind(day.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| decode_row::<S>(&row)).transpose()
+    }
+
+    /// Merge `sketch` into whatever is already stored for the day
+    pub async fn accumulate<S: StoredSketch>(
+        &self,
+        case_id: i64,
+        metric: &str,
+        day: NaiveDate,
+        sketch: &S,
+    ) -> Result<(), SketchStoreError> {
+        match self.load::<S>(case_id, metric, day).await? {
+            Some(mut existing) => {
+                existing
+                    .merge_with(sketch)
+                    .map_err(SketchStoreError::Encoding)?;
+                self.save(case_id, metric, day, &existing).await
+            }
+            None => self.save(case_id, metric, day, sketch).await,
+        }
+    }
+
+    /// Merge every daily sketch in `[from, to]`; `None` when no day has data
+    pub async fn rollup<S: StoredSketch>(
+        &self,
+        case_id: i64,
+        metric: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Option<S>, SketchStoreError> {
+        let rows = sqlx::query(
+            "SELECT sketch_kind, data FROM analytics_sketches
+             WHERE case_id = ? AND metric = ? AND bucket_date BETWEEN ? AND ?
+             ORDER BY bucket_date",
+        )
+        .bind(case_id)
+        .bind(metric)
+        .bind(from.to_string())
+        .bind(to.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut merged: Option<S> = None;
+        for row in rows {
+            let sketch = decode_row::<S>(&row)?;
+            match merged.as_mut() {
+                Some(total) => total
+                    .merge_with(&sketch)
+                    .map_err(SketchStoreError::Encoding)?,
+                None => merged = Some(sketch),
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Rebuild the sender and term sketches for one day from its communications
+    pub async fn refresh_communication_sketches(
+        &self,
+        case_id: i64,
+        day: NaiveDate,
+    ) -> Result<usize, SketchStoreError> {
+        let rows: Vec<(Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT sender, subject, message_content FROM communications
+             WHERE date(communication_date) = ?",
+        )
+        .bind(day.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut senders = HyperLogLog::new(SENDER_HLL_PRECISION);
+        let mut frequent_senders = SpaceSaving::new(SENDER_TOP_K_CAPACITY);
+        let mut terms = CmsTopK::new(TERM_TOP_K_CAPACITY, TERM_CMS_EPSILON, TERM_CMS_DELTA);
+
+        for (sender, subject, content) in &rows {
+            if let Some(sender) = sender {
+                let sender = sender.trim().to_lowercase();
+                senders.add(sender.as_str());
+                frequent_senders.add(sender.as_str(), 1);
+            }
+            for text in [subject, content].into_iter().flatten() {
+                for term in text
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|w| w.len() > 2)
+                {
+                    terms.add(term.to_lowercase().as_str(), 1);
+                }
+            }
+        }
+
+        self.save(case_id, UNIQUE_SENDERS, day, &senders).await?;
+        self.save(case_id, FREQUENT_SENDERS, day, &frequent_senders)
+            .await?;
+        self.save(case_id, TERM_FREQUENCY, day, &terms).await?;
+        Ok(rows.len())
+    }
+}
+
+fn decode_row<S: StoredSketch>(row: &sqlx::sqlite::SqliteRow) -> Result<S, SketchStoreError> {
+    let kind: String = row.get("sketch_kind");
+    if kind != S::KIND {
+        return Err(SketchStoreError::Encoding(format!(
+            "Stored sketch is a {}, expected {}",
+            kind,
+            S::KIND
+        )));
+    }
+    let data: Vec<u8> = row.get("data");
+    S::decode(&data).map_err(SketchStoreError::Encoding)
+}
@@ -0,0 +1,542 @@
+// Top-k Heavy Hitters: Space-Saving and Count-Min backed candidate tracking
+// Unlike `CountMinSketch::heavy_hitters`, both structures report the actual items
+
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use crate::algorithms::probabilistic::{
+    check_version, CountMinSketch, QueryCounter, SketchDecoder, SketchEncoder, SketchKind,
+    SKETCH_FORMAT_VERSION,
+};
+use crate::algorithms::AlgorithmMetrics;
+
+/// A reported item with its estimated count and the maximum overestimate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeavyHitter<K> {
+    pub item: K,
+    pub count: u64,
+    pub error: u64,
+}
+
+impl<K> HeavyHitter<K> {
+    /// Lower bound on the true count
+    pub fn guaranteed_count(&self) -> u64 {
+        self.count.saturating_sub(self.error)
+    }
+}
+
+/// Space-Saving summary (Metwally et al.): monitors at most `capacity` items and
+/// replaces the smallest counter when a new item arrives, inheriting its count as error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    try_from = "SpaceSavingRepr<K>",
+    into = "SpaceSavingRepr<K>",
+    bound(
+        serialize = "K: Serialize + Clone + Ord + Hash",
+        deserialize = "K: Deserialize<'de> + Clone + Ord + Hash"
+    )
+)]
+pub struct SpaceSaving<K> {
+    capacity: usize,
+    counters: HashMap<K, (u64, u64)>,
+    /// (count, item) ordered so the minimum counter is found in O(log k)
+    order: BTreeSet<(u64, K)>,
+    total: u64,
+    metrics: AlgorithmMetrics,
+    queries: QueryCounter,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SpaceSavingRepr<K> {
+    version: u8,
+    capacity: usize,
+    total: u64,
+    counters: Vec<HeavyHitter<K>>,
+}
+
+impl<K: Clone + Ord + Hash> SpaceSaving<K> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counters: HashMap::new(),
+            order: BTreeSet::new(),
+            total: 0,
+            metrics: AlgorithmMetrics::new("SpaceSaving", "O(log k)", "O(k)"),
+            queries: QueryCounter::default(),
+        }
+    }
+
+    /// Streaming update with a weight
+    pub fn add<Q>(&mut self, item: &Q, weight: u64)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized + ToOwned<Owned = K>,
+    {
+        self.metrics.complexity.record_operation();
+        self.total += weight;
+
+        if let Some(entry) = self.counters.get_mut(item) {
+            let key = item.to_owned();
+            self.order.remove(&(entry.0, key.clone()));
+            entry.0 += weight;
+            self.order.insert((entry.0, key));
+            return;
+        }
+
+        let key = item.to_owned();
+        if self.counters.len() < self.capacity {
+            self.counters.insert(key.clone(), (weight, 0));
+            self.order.insert((weight, key));
+        } else if let Some((min_count, evicted)) = self.order.pop_first() {
+            self.counters.remove::<K>(&evicted);
+            self.counters
+                .insert(key.clone(), (min_count + weight, min_count));
+            self.order.insert((min_count + weight, key));
+        }
+    }
+
+    /// The `n` largest counters, highest first
+    pub fn top(&self, n: usize) -> Vec<HeavyHitter<K>> {
+        self.queries.record();
+        self.order
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(count, item)| HeavyHitter {
+                item: item.clone(),
+                count: *count,
+                error: self.counters[item].1,
+            })
+            .collect()
+    }
+
+    /// Items whose estimated share of the stream is at least `phi`
+    pub fn heavy_hitters(&self, phi: f64) -> Vec<HeavyHitter<K>> {
+        let threshold = (phi * self.total as f64).ceil() as u64;
+        self.top(self.capacity)
+            .into_iter()
+            .take_while(|hitter| hitter.count >= threshold)
+            .collect()
+    }
+
+    /// Estimated count; unmonitored items may have occurred up to the minimum counter
+    pub fn estimate<Q>(&self, item: &Q) -> u64
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.queries.record();
+        match self.counters.get(item) {
+            Some((count, _)) => *count,
+            None => self.min_counter(),
+        }
+    }
+
+    fn min_counter(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.order.first().map_or(0, |(count, _)| *count)
+        }
+    }
+
+    /// Merge another summary (Agarwal et al. mergeable summaries); capacity of `self` is kept
+    pub fn merge(&mut self, other: &SpaceSaving<K>) -> Result<(), String> {
+        let (self_min, other_min) = (self.min_counter(), other.min_counter());
+
+        let mut combined: HashMap<K, (u64, u64)> = HashMap::new();
+        for (item, (count, error)) in &self.counters {
+            let (other_count, other_error) = other
+                .counters
+                .get(item)
+                .copied()
+                .unwrap_or((other_min, other_min));
+            combined.insert(item.clone(), (count + other_count, error + other_error));
+        }
+        for (item, (count, error)) in &other.counters {
+            combined
+                .entry(item.clone())
+                .or_insert((count + self_min, error + self_min));
+        }
+
+        let mut entries: Vec<(K, (u64, u64))> = combined.into_iter().collect();
+        entries.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(self.capacity);
+
+        self.order = entries.iter().map(|(k, (c, _))| (*c, k.clone())).collect();
+        self.counters = entries.into_iter().collect();
+        self.total += other.total;
+        Ok(())
+    }
+
+    pub fn total_count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+
+    pub fn get_metrics(&self) -> AlgorithmMetrics {
+        self.queries.snapshot(&self.metrics)
+    }
+
+    fn from_parts(
+        capacity: usize,
+        total: u64,
+        hitters: Vec<HeavyHitter<K>>,
+    ) -> Result<Self, String> {
+        if capacity == 0 || hitters.len() > capacity {
+            return Err("Space-Saving counters exceed its capacity".to_string());
+        }
+        let mut summary = Self::new(capacity);
+        summary.total = total;
+        for hitter in hitters {
+            summary.order.insert((hitter.count, hitter.item.clone()));
+            if summary
+                .counters
+                .insert(hitter.item, (hitter.count, hitter.error))
+                .is_some()
+            {
+                return Err("Duplicate item in Space-Saving counters".to_string());
+            }
+        }
+        Ok(summary)
+    }
+}
+
+impl SpaceSaving<String> {
+    /// Versioned binary encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = SketchEncoder::new(SketchKind::SpaceSaving);
+        encoder.u32(self.capacity as u32);
+        encoder.u64(self.total);
+        encoder.u32(self.counters.len() as u32);
+        for (count, item) in &self.order {
+            encoder.bytes(item.as_bytes());
+            encoder.u64(*count);
+            encoder.u64(self.counters[item].1);
+        }
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = SketchDecoder::new(bytes, SketchKind::SpaceSaving)?;
+        let capacity = decoder.u32()? as usize;
+        let total = decoder.u64()?;
+        let len = decoder.u32()? as usize;
+        if len > capacity {
+            return Err("Space-Saving counters exceed its capacity".to_string());
+        }
+        let mut hitters = Vec::with_capacity(len);
+        for _ in 0..len {
+            let item = String::from_utf8(decoder.bytes()?.to_vec())
+                .map_err(|_| "Space-Saving item is not valid UTF-8".to_string())?;
+            hitters.push(HeavyHitter {
+                item,
+                count: decoder.u64()?,
+                error: decoder.u64()?,
+            });
+        }
+        decoder.finish()?;
+        Self::from_parts(capacity, total, hitters)
+    }
+}
+
+impl<K: Clone + Ord + Hash> From<SpaceSaving<K>> for SpaceSavingRepr<K> {
+    fn from(summary: SpaceSaving<K>) -> Self {
+        Self {
+            version: SKETCH_FORMAT_VERSION,
+            capacity: summary.capacity,
+            total: summary.total,
+            counters: summary.top(summary.capacity),
+        }
+    }
+}
+
+impl<K: Clone + Ord + Hash> TryFrom<SpaceSavingRepr<K>> for SpaceSaving<K> {
+    type Error = String;
+
+    fn try_from(repr: SpaceSavingRepr<K>) -> Result<Self, Self::Error> {
+        check_version(repr.version)?;
+        Self::from_parts(repr.capacity, repr.total, repr.counters)
+    }
+}
+
+/// Count-Min Sketch that also keeps the `capacity` items with the highest estimates,
+/// so frequency reports can name them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    try_from = "CmsTopKRepr<K>",
+    into = "CmsTopKRepr<K>",
+    bound(
+        serialize = "K: Serialize + Clone + Ord + Hash",
+        deserialize = "K: Deserialize<'de> + Clone + Ord + Hash"
+    )
+)]
+pub struct CmsTopK<K> {
+    sketch: CountMinSketch,
+    capacity: usize,
+    candidates: HashMap<K, u64>,
+    order: BTreeSet<(u64, K)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CmsTopKRepr<K> {
+    version: u8,
+    capacity: usize,
+    sketch: CountMinSketch,
+    items: Vec<K>,
+}
+
+impl<K: Clone + Ord + Hash> CmsTopK<K> {
+    pub fn new(capacity: usize, epsilon: f64, delta: f64) -> Self {
+        Self::with_sketch(capacity, CountMinSketch::new(epsilon, delta))
+    }
+
+    fn with_sketch(capacity: usize, sketch: CountMinSketch) -> Self {
+        Self {
+            sketch,
+            capacity: capacity.max(1),
+            candidates: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    /// Streaming update; the item becomes a candidate if its estimate beats the weakest one
+    pub fn add<Q>(&mut self, item: &Q, count: u32)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized + ToOwned<Owned = K>,
+    {
+        self.sketch.add(item, count);
+        let estimate = self.sketch.estimate(item) as u64;
+        self.offer(item.to_owned(), estimate);
+    }
+
+    fn offer(&mut self, item: K, estimate: u64) {
+        if let Some(current) = self.candidates.get_mut(&item) {
+            self.order.remove(&(*current, item.clone()));
+            *current = estimate;
+            self.order.insert((estimate, item));
+            return;
+        }
+
+        if self.candidates.len() >= self.capacity {
+            match self.order.first() {
+                Some((min, _)) if *min < estimate => {
+                    let (_, evicted) = self.order.pop_first().expect("non-empty");
+                    self.candidates.remove(&evicted);
+                }
+                _ => return,
+            }
+        }
+        self.candidates.insert(item.clone(), estimate);
+        self.order.insert((estimate, item));
+    }
+
+    /// Estimated frequency of any item, tracked or not
+    pub fn estimate<Q>(&self, item: &Q) -> u32
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.sketch.estimate(item)
+    }
+
+    /// The `n` most frequent tracked items; `error` is the sketch's ε·N overestimate bound
+    pub fn top(&self, n: usize) -> Vec<HeavyHitter<K>> {
+        let error = self.sketch.error_bound();
+        self.order
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(count, item)| HeavyHitter {
+                item: item.clone(),
+                count: *count,
+                error,
+            })
+            .collect()
+    }
+
+    /// Merge the underlying sketches and re-rank the union of both candidate sets
+    pub fn merge(&mut self, other: &CmsTopK<K>) -> Result<(), String> {
+        self.sketch.merge(&other.sketch)?;
+
+        let items: Vec<K> = self
+            .candidates
+            .keys()
+            .chain(other.candidates.keys())
+            .cloned()
+            .collect();
+        self.candidates.clear();
+        self.order.clear();
+        for item in items {
+            let estimate = self.sketch.estimate(&item) as u64;
+            self.offer(item, estimate);
+        }
+        Ok(())
+    }
+
+    pub fn total_count(&self) -> u64 {
+        self.sketch.total_count()
+    }
+
+    pub fn sketch(&self) -> &CountMinSketch {
+        &self.sketch
+    }
+
+    pub fn get_metrics(&self) -> AlgorithmMetrics {
+        self.sketch.get_metrics()
+    }
+
+    fn from_parts(capacity: usize, sketch: CountMinSketch, items: Vec<K>) -> Result<Self, String> {
+        if capacity == 0 || items.len() > capacity {
+            return Err("Top-k candidates exceed its capacity".to_string());
+        }
+        let mut top_k = Self::with_sketch(capacity, sketch);
+        for item in items {
+            let estimate = top_k.sketch.estimate(&item) as u64;
+            top_k.offer(item, estimate);
+        }
+        Ok(top_k)
+    }
+}
+
+impl CmsTopK<String> {
+    /// Versioned binary encoding; candidate counts are re-derived from the sketch
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = SketchEncoder::new(SketchKind::CmsTopK);
+        encoder.u32(self.capacity as u32);
+        encoder.bytes(&self.sketch.to_bytes());
+        encoder.u32(self.candidates.len() as u32);
+        for (_, item) in &self.order {
+            encoder.bytes(item.as_bytes());
+        }
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = SketchDecoder::new(bytes, SketchKind::CmsTopK)?;
+        let capacity = decoder.u32()? as usize;
+        let sketch = CountMinSketch::from_bytes(decoder.bytes()?)?;
+        let len = decoder.u32()? as usize;
+        if len > capacity {
+            return Err("Top-k candidates exceed its capacity".to_string());
+        }
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(
+                String::from_utf8(decoder.bytes()?.to_vec())
+                    .map_err(|_| "Top-k item is not valid UTF-8".to_string())?,
+            );
+        }
+        decoder.finish()?;
+        Self::from_parts(capacity, sketch, items)
+    }
+}
+
+impl<K: Clone + Ord + Hash> From<CmsTopK<K>> for CmsTopKRepr<K> {
+    fn from(top_k: CmsTopK<K>) -> Self {
+        Self {
+            version: SKETCH_FORMAT_VERSION,
+            capacity: top_k.capacity,
+            items: top_k.order.into_iter().map(|(_, item)| item).collect(),
+            sketch: top_k.sketch,
+        }
+    }
+}
+
+impl<K: Clone + Ord + Hash> TryFrom<CmsTopKRepr<K>> for CmsTopK<K> {
+    type Error = String;
+
+    fn try_from(repr: CmsTopKRepr<K>) -> Result<Self, Self::Error> {
+        check_version(repr.version)?;
+        Self::from_parts(repr.capacity, repr.sketch, repr.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zipf_stream() -> Vec<String> {
+        // "custody" x50, "visitation" x30, "motion" x20, plus 200 singletons
+        let mut stream = Vec::new();
+        for (term, count) in [("custody", 50), ("visitation", 30), ("motion", 20)] {
+            stream.extend((0..count).map(|_| term.to_string()));
+        }
+        stream.extend((0..200).map(|i| format!("rare{}", i)));
+        // Interleave so frequent items are not all seen first
+        let mut interleaved = Vec::new();
+        for i in 0..stream.len() {
+            interleaved.push(stream[(i * 37) % stream.len()].clone());
+        }
+        interleaved
+    }
+
+    #[test]
+    fn test_space_saving_finds_heavy_hitters() {
+        let mut summary = SpaceSaving::new(20);
+        for item in zipf_stream() {
+            summary.add(item.as_str(), 1);
+        }
+
+        let top: Vec<String> = summary.top(3).into_iter().map(|h| h.item).collect();
+        assert_eq!(top, vec!["custody", "visitation", "motion"]);
+
+        let custody = &summary.top(1)[0];
+        assert!(custody.count >= 50 && custody.guaranteed_count() <= 50);
+        assert_eq!(summary.total_count(), 300);
+        assert_eq!(summary.heavy_hitters(0.15).len(), 1);
+    }
+
+    #[test]
+    fn test_space_saving_merge_and_encoding() {
+        let stream = zipf_stream();
+        let (first, second) = stream.split_at(stream.len() / 2);
+        let mut left = SpaceSaving::new(20);
+        let mut right = SpaceSaving::new(20);
+        first.iter().for_each(|item| left.add(item.as_str(), 1));
+        second.iter().for_each(|item| right.add(item.as_str(), 1));
+
+        let mut right = SpaceSaving::from_bytes(&right.to_bytes()).unwrap();
+        right.merge(&left).unwrap();
+        assert_eq!(right.total_count(), 300);
+        assert_eq!(right.top(1)[0].item, "custody");
+
+        let json = serde_json::to_string(&right).unwrap();
+        let restored: SpaceSaving<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.top(3), right.top(3));
+    }
+
+    #[test]
+    fn test_cms_top_k_tracks_real_items() {
+        let stream = zipf_stream();
+        let (first, second) = stream.split_at(stream.len() / 2);
+        let mut monday = CmsTopK::new(5, 0.01, 0.01);
+        let mut tuesday = CmsTopK::new(5, 0.01, 0.01);
+        first.iter().for_each(|item| monday.add(item.as_str(), 1));
+        second.iter().for_each(|item| tuesday.add(item.as_str(), 1));
+
+        monday.merge(&tuesday).unwrap();
+        let top: Vec<String> = monday.top(3).into_iter().map(|h| h.item).collect();
+        assert_eq!(top, vec!["custody", "visitation", "motion"]);
+        assert!(monday.estimate("custody") >= 50);
+
+        let decoded = CmsTopK::from_bytes(&monday.to_bytes()).unwrap();
+        assert_eq!(decoded.top(3), monday.top(3));
+        assert!(SpaceSaving::from_bytes(&monday.to_bytes()).is_err());
+    }
+}
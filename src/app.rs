@@ -0,0 +1,146 @@
+//! The MoodBridge router, shared by the server binary and the integration tests
+
+use crate::{
+    auth::AuthManager,
+    crypto_db::CryptoDatabase,
+    handlers,
+    legal::access_control::AccessController,
+    legal::access_middleware::require_access,
+    legal::audit_log::AuditLogger,
+    legal::compliance_layer::LegalComplianceLayer,
+    legal::consent::ConsentManager,
+    legal::retention::RetentionScheduler,
+    legal::LegalComplianceEngine,
+};
+use axum::routing::{any, delete, get, post};
+use axum::{middleware, Extension, Router};
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+use tower_http::services::ServeDir;
+
+/// The application's routes, access and compliance layers and shared state.
+/// `main` adds the HTTP transport layers (tracing, CORS, timeouts, body limit).
+pub async fn create_app(
+    pool: Pool<Sqlite>,
+    secure: Option<Arc<CryptoDatabase>>,
+    access: AccessController,
+    consent: ConsentManager,
+    audit: AuditLogger,
+    retention: RetentionScheduler,
+    auth: Option<Arc<AuthManager>>,
+) -> Router {
+    let sso = match auth {
+        Some(auth) => handlers::sso::router(auth, access.clone()),
+        None => Router::new(),
+    };
+    let secure_routes = match secure {
+        Some(secure) => Router::new()
+            .route(
+                "/api/secure/communications",
+                get(handlers::secure::list_communications).post(handlers::secure::create_communication),
+            )
+            .route(
+                "/api/secure/communications/:id",
+                get(handlers::secure::get_communication)
+                    .put(handlers::secure::update_communication)
+                    .delete(handlers::secure::delete_communication),
+            )
+            .layer(Extension(secure)),
+        None => Router::new()
+            .route("/api/secure/communications", any(handlers::secure::unavailable))
+            .route("/api/secure/communications/:id", any(handlers::secure::unavailable)),
+    };
+
+    // AI, search, document, privileged-communication and records routes also pass the
+    // legal compliance checks, which record an audit entry for every call
+    let engine = LegalComplianceEngine::new()
+        .with_access_controller(access.clone())
+        .with_consent_manager(consent.clone())
+        .with_audit_logger(audit.clone());
+    let compliance_routes = Router::new()
+        .route("/api/ai/prompt", post(handlers::ai_prompt))
+        .route("/api/ai/voice", post(handlers::ai_voice))
+        .route("/api/ai/risk/outcomes", post(handlers::risk::record_outcome))
+        .route("/api/ai/risk/train", post(handlers::risk::train_model))
+        .route("/api/ai/risk/score", post(handlers::risk::score_denials))
+        .route("/api/ai/risk/:id/explain", get(handlers::risk::explain_denial))
+        .route("/api/communications/tone/score", post(handlers::tone::score_tone))
+        .route("/api/communications/tone/timeline", get(handlers::tone::tone_timeline))
+        .route("/api/communications/tone/alerts", get(handlers::tone::tone_alerts))
+        .route("/api/timeline/extract", post(handlers::timeline::extract_timeline))
+        .route("/api/timeline/candidates", get(handlers::timeline::timeline_candidates))
+        .route(
+            "/api/timeline/candidates/:id/approve",
+            post(handlers::timeline::approve_timeline_candidate),
+        )
+        .route(
+            "/api/timeline/candidates/:id/reject",
+            post(handlers::timeline::reject_timeline_candidate),
+        )
+        .route("/api/search", get(handlers::search::search))
+        .route("/api/search/reindex", post(handlers::search::reindex))
+        .route("/api/data/diff", get(handlers::diff_data))
+        .route("/api/data/commit", post(handlers::commit_changes))
+        .route("/api/exhibits/:id/export", get(handlers::exhibits::export_exhibit))
+        .route("/api/exhibits/:id", delete(handlers::exhibits::delete_exhibit))
+        .route("/api/audit/export", get(handlers::audit::export_audit_log))
+        .route("/api/retention/files", post(handlers::retention::schedule_file))
+        .route("/api/retention/records", post(handlers::retention::schedule_record))
+        .route("/api/retention/reviews", get(handlers::retention::review_queue))
+        .route("/api/retention/reviews/:id", post(handlers::retention::review_item))
+        .route("/api/retention/dispose", post(handlers::retention::dispose_due))
+        .route("/api/retention/certificates", get(handlers::retention::certificates))
+        .route("/api/retention/holds/:hold_id/release", post(handlers::retention::release_hold))
+        .route(
+            "/api/matters/:case_id/holds",
+            get(handlers::retention::matter_holds).post(handlers::retention::place_hold),
+        )
+        .merge(secure_routes)
+        .route_layer(LegalComplianceLayer::new(Arc::new(engine)));
+
+    Router::new()
+        .route("/api/health", get(handlers::health_check))
+        .route("/api/dashboard", get(handlers::dashboard_data))
+        .merge(compliance_routes)
+        .route("/api/matters", post(handlers::matters::open_matter))
+        .route("/api/matters/conflict-check", post(handlers::matters::conflict_check))
+        .route("/api/matters/conflicts/:report_id", get(handlers::matters::get_conflict_report))
+        .route(
+            "/api/matters/conflicts/:report_id/review",
+            post(handlers::matters::review_conflict_report),
+        )
+        .route("/api/matters/:case_id/parties", get(handlers::matters::matter_parties))
+        .route(
+            "/api/matters/:case_id/access",
+            get(handlers::matters::matter_access).post(handlers::matters::grant_matter_access),
+        )
+        .route(
+            "/api/matters/:case_id/access/:user_id",
+            delete(handlers::matters::revoke_matter_access),
+        )
+        .route("/api/walls", post(handlers::matters::create_wall))
+        .route("/api/walls/:wall_id/members", post(handlers::matters::add_wall_member))
+        .route("/api/walls/:wall_id/lift", post(handlers::matters::lift_wall))
+        .route("/api/mfa/enroll", post(handlers::mfa::begin_enrollment))
+        .route("/api/mfa/enroll/confirm", post(handlers::mfa::confirm_enrollment))
+        .route("/api/mfa/verify", post(handlers::mfa::verify))
+        .route("/api/mfa/status", get(handlers::mfa::status))
+        .route("/api/mfa/recovery-codes", post(handlers::mfa::regenerate_recovery_codes))
+        .route("/api/mfa/reset/:user_id", post(handlers::mfa::reset))
+        .route("/api/consent", post(handlers::consent::record_consent))
+        .route("/api/consent/withdraw", post(handlers::consent::withdraw_consent))
+        .route("/api/consent/forms/:operation_type", get(handlers::consent::consent_form))
+        .route("/api/consent/status/:operation_type", get(handlers::consent::consent_status))
+        .route("/api/consent/report", get(handlers::consent::consent_report))
+        // Only matched API routes; static files and the fallback stay public
+        .route_layer(middleware::from_fn_with_state(access.clone(), require_access))
+        // Sign-in routes are added after the access layer and stay public
+        .merge(sso)
+        .nest_service("/", ServeDir::new("frontend/dist"))
+        .fallback(handlers::handle_fallback)
+        .with_state(pool)
+        .layer(Extension(access))
+        .layer(Extension(consent))
+        .layer(Extension(audit))
+        .layer(Extension(retention))
+}
@@ -0,0 +1,844 @@
+//! Passphrase-sealed local keystore
+//!
+//! Secrets are sealed with AES-256-GCM under random, versioned data keys. The data
+//! keys are wrapped by a key-encryption key derived with Argon2id from a passphrase
+//! or key file, so changing the passphrase rewrites only the header while rotating
+//! the data key re-encrypts entries one file at a time.
+//!
+//! Layout of the keystore directory:
+//! - `keystore.json` - KDF parameters, salt and the wrapped data keys
+//! - `<base64url name>.sealed` - one JSON file per secret
+//! - `*.enc` - secrets from the old XOR provider, until `migrate_legacy` runs
+//! - `*.enc.migrated` - the same files after migration, kept for rollback
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use ring::aead;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+use zeroize::Zeroizing;
+
+use super::kms::{KmsError, KmsProvider};
+
+const HEADER_FILE: &str = "keystore.json";
+const ENTRY_EXTENSION: &str = "sealed";
+const LEGACY_EXTENSION: &str = "enc";
+const MIGRATED_SUFFIX: &str = "migrated";
+/// Migrated legacy secrets are held under this prefix plus their old file stem
+/// until first read under their real name
+const LEGACY_ALIAS_PREFIX: &str = "legacy-xor/";
+const FORMAT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Passphrase for the keystore
+pub const PASSPHRASE_ENV: &str = "LOCAL_KMS_PASSPHRASE";
+/// Path of a key file, used instead of a passphrase
+pub const KEY_FILE_ENV: &str = "LOCAL_KMS_KEY_FILE";
+/// XOR key of the old provider, needed only to migrate `.enc` files
+///
+/// Deployments that never set it used `default-dev-key-change-in-production`
+/// and must now set that explicitly.
+pub const LEGACY_KEY_ENV: &str = "LOCAL_KMS_KEY";
+/// Set to `true` to migrate `.enc` files on startup; requires `LOCAL_KMS_KEY`
+pub const MIGRATE_LEGACY_ENV: &str = "LOCAL_KMS_MIGRATE_LEGACY";
+
+/// Secret the key-encryption key is derived from
+#[derive(Clone)]
+pub enum KeystoreSecret {
+    Passphrase(Zeroizing<String>),
+    KeyFile(PathBuf),
+}
+
+impl KeystoreSecret {
+    pub fn passphrase(passphrase: impl Into<String>) -> Self {
+        Self::Passphrase(Zeroizing::new(passphrase.into()))
+    }
+
+    /// Read `LOCAL_KMS_KEY_FILE`, falling back to `LOCAL_KMS_PASSPHRASE`
+    pub fn from_env() -> Result<Self, KmsError> {
+        if let Ok(path) = std::env::var(KEY_FILE_ENV) {
+            return Ok(Self::KeyFile(PathBuf::from(path)));
+        }
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(Self::passphrase(passphrase)),
+            _ => Err(KmsError::ConfigError(format!(
+                "Local keystore needs {} or {}",
+                PASSPHRASE_ENV, KEY_FILE_ENV
+            ))),
+        }
+    }
+
+    async fn material(&self) -> Result<Zeroizing<Vec<u8>>, KmsError> {
+        let material = match self {
+            Self::Passphrase(passphrase) => Zeroizing::new(passphrase.as_bytes().to_vec()),
+            Self::KeyFile(path) => Zeroizing::new(tokio::fs::read(path).await.map_err(|e| {
+                KmsError::ConfigError(format!("Failed to read key file {}: {}", path.display(), e))
+            })?),
+        };
+        if material.is_empty() {
+            return Err(KmsError::ConfigError("Keystore secret is empty".to_string()));
+        }
+        Ok(material)
+    }
+}
+
+/// Argon2id cost parameters, stored in the header so they can be raised later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeystoreHeader {
+    format_version: u32,
+    kdf: KdfParams,
+    salt: String,
+    current_version: u32,
+    keys: Vec<WrappedKey>,
+}
+
+/// A data key sealed under the key-encryption key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WrappedKey {
+    version: u32,
+    created_at: DateTime<Utc>,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// One secret on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedEntry {
+    format_version: u32,
+    name: String,
+    key_version: u32,
+    nonce: String,
+    ciphertext: String,
+    updated_at: DateTime<Utc>,
+}
+
+struct KeyState {
+    header: KeystoreHeader,
+    kek: Zeroizing<[u8; KEY_LEN]>,
+    keys: BTreeMap<u32, Zeroizing<[u8; KEY_LEN]>>,
+}
+
+/// Local file-based KMS sealed with AES-256-GCM
+pub struct LocalKmsProvider {
+    storage_path: PathBuf,
+    rng: SystemRandom,
+    state: RwLock<KeyState>,
+}
+
+impl LocalKmsProvider {
+    /// Open the keystore in `storage_path`, creating it on first use
+    pub async fn open(storage_path: PathBuf, secret: KeystoreSecret) -> Result<Self, KmsError> {
+        Self::open_with_kdf(storage_path, secret, KdfParams::default()).await
+    }
+
+    /// Like `open`; `kdf` applies only when the keystore is created
+    pub async fn open_with_kdf(
+        storage_path: PathBuf,
+        secret: KeystoreSecret,
+        kdf: KdfParams,
+    ) -> Result<Self, KmsError> {
+        tokio::fs::create_dir_all(&storage_path)
+            .await
+            .map_err(|e| KmsError::ConfigError(format!("Failed to create {}: {}", storage_path.display(), e)))?;
+
+        let rng = SystemRandom::new();
+        let material = secret.material().await?;
+        let header_path = storage_path.join(HEADER_FILE);
+
+        let state = match tokio::fs::read(&header_path).await {
+            Ok(bytes) => {
+                let header: KeystoreHeader = serde_json::from_slice(&bytes)
+                    .map_err(|e| KmsError::ConfigError(format!("Corrupt keystore header: {}", e)))?;
+                if header.format_version != FORMAT_VERSION {
+                    return Err(KmsError::ConfigError(format!(
+                        "Unsupported keystore format {}",
+                        header.format_version
+                    )));
+                }
+                let kek = derive_kek(material, decode(&header.salt)?, header.kdf).await?;
+                let mut keys = BTreeMap::new();
+                for wrapped in &header.keys {
+                    let key = unwrap_key(&kek, wrapped).map_err(|_| {
+                        KmsError::DecryptionFailed("Wrong keystore passphrase or key file".to_string())
+                    })?;
+                    keys.insert(wrapped.version, key);
+                }
+                if !keys.contains_key(&header.current_version) {
+                    return Err(KmsError::ConfigError(format!(
+                        "Keystore is missing data key version {}",
+                        header.current_version
+                    )));
+                }
+                KeyState { header, kek, keys }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let salt = random_bytes::<SALT_LEN>(&rng)?;
+                let kek = derive_kek(material, salt.to_vec(), kdf).await?;
+                let key = Zeroizing::new(random_bytes::<KEY_LEN>(&rng)?);
+                let header = KeystoreHeader {
+                    format_version: FORMAT_VERSION,
+                    kdf,
+                    salt: encode(&salt),
+                    current_version: 1,
+                    keys: vec![wrap_key(&rng, &kek, 1, &key)?],
+                };
+                write_atomic(&header_path, &to_json(&header)?).await?;
+                KeyState { header, kek, keys: BTreeMap::from([(1, key)]) }
+            }
+            Err(e) => {
+                return Err(KmsError::ConfigError(format!("Failed to read keystore header: {}", e)));
+            }
+        };
+
+        Ok(Self {
+            storage_path,
+            rng,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Data key version used for new writes
+    pub async fn current_key_version(&self) -> u32 {
+        self.state.read().await.header.current_version
+    }
+
+    /// Re-wrap the data keys under a new passphrase or key file
+    ///
+    /// Entries are untouched; only the header is rewritten.
+    pub async fn change_secret(&self, secret: KeystoreSecret) -> Result<(), KmsError> {
+        let material = secret.material().await?;
+        let mut state = self.state.write().await;
+        let salt = random_bytes::<SALT_LEN>(&self.rng)?;
+        let kek = derive_kek(material, salt.to_vec(), state.header.kdf).await?;
+
+        let mut header = state.header.clone();
+        header.salt = encode(&salt);
+        header.keys = state
+            .keys
+            .iter()
+            .map(|(version, key)| wrap_key(&self.rng, &kek, *version, key))
+            .collect::<Result<_, _>>()?;
+        self.write_header(&header).await?;
+
+        state.header = header;
+        state.kek = kek;
+        Ok(())
+    }
+
+    /// Start a new data key version and re-encrypt every entry under it
+    ///
+    /// Returns the number of entries re-encrypted. If interrupted, entries keep
+    /// working under their old version and `reencrypt_stale` finishes the job.
+    pub async fn rotate_key(&self) -> Result<usize, KmsError> {
+        {
+            let mut state = self.state.write().await;
+            let version = state.keys.keys().next_back().copied().unwrap_or(0) + 1;
+            let key = Zeroizing::new(random_bytes::<KEY_LEN>(&self.rng)?);
+
+            let mut header = state.header.clone();
+            header.keys.push(wrap_key(&self.rng, &state.kek, version, &key)?);
+            header.current_version = version;
+            self.write_header(&header).await?;
+
+            state.header = header;
+            state.keys.insert(version, key);
+        }
+        self.reencrypt_stale().await
+    }
+
+    /// Re-encrypt entries sealed under old data keys, then retire those keys
+    pub async fn reencrypt_stale(&self) -> Result<usize, KmsError> {
+        let mut reencrypted = 0;
+        for path in self.files_with_extension(ENTRY_EXTENSION).await? {
+            let entry = match self.read_entry(&path).await {
+                Ok(entry) => entry,
+                // Deleted since the directory was listed
+                Err(KmsError::KeyNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            if entry.key_version == self.current_key_version().await {
+                continue;
+            }
+            let plaintext = self.open_entry(&entry).await?;
+            self.seal_entry(&entry.name, &plaintext).await?;
+            reencrypted += 1;
+        }
+
+        let mut state = self.state.write().await;
+        let current = state.header.current_version;
+        if state.keys.len() > 1 {
+            let mut header = state.header.clone();
+            header.keys.retain(|wrapped| wrapped.version == current);
+            self.write_header(&header).await?;
+            state.header = header;
+            state.keys.retain(|version, _| *version == current);
+        }
+        Ok(reencrypted)
+    }
+
+    /// Seal secrets left by the old XOR provider and rename their files to `.enc.migrated`
+    ///
+    /// `legacy_key` must be the key they were written with. XOR has no integrity
+    /// check, so every file is decrypted and checked to be printable text before
+    /// anything is written; one failure aborts the whole migration.
+    ///
+    /// The old provider stored `a/b` as `a_b.enc`, which can't be reversed, so each
+    /// secret is sealed under an alias of its file stem and moved to its real name
+    /// the first time it's read (see `claim_legacy`).
+    pub async fn migrate_legacy(&self, legacy_key: &str) -> Result<usize, KmsError> {
+        if legacy_key.is_empty() {
+            return Err(KmsError::ConfigError("Legacy key is empty".to_string()));
+        }
+
+        let mut decrypted = Vec::new();
+        for path in self.legacy_files().await? {
+            let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            };
+            let data = tokio::fs::read(&path)
+                .await
+                .map_err(|e| KmsError::DecryptionFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+            let plaintext = legacy_plaintext(legacy_xor(&data, legacy_key)).ok_or_else(|| {
+                KmsError::DecryptionFailed(format!(
+                    "{} does not decrypt with the legacy key; nothing was migrated",
+                    path.display()
+                ))
+            })?;
+            decrypted.push((path, stem, plaintext));
+        }
+
+        let mut migrated = 0;
+        for (path, stem, plaintext) in decrypted {
+            let alias = format!("{}{}", LEGACY_ALIAS_PREFIX, stem);
+            if !self.key_exists(&alias).await? {
+                self.seal_entry(&alias, &plaintext).await?;
+                migrated += 1;
+            }
+            let mut renamed = path.clone().into_os_string();
+            renamed.push(format!(".{}", MIGRATED_SUFFIX));
+            tokio::fs::rename(&path, &renamed)
+                .await
+                .map_err(|e| KmsError::ConfigError(format!("Failed to rename {}: {}", path.display(), e)))?;
+        }
+        Ok(migrated)
+    }
+
+    /// `.enc` files still waiting for `migrate_legacy`
+    pub async fn legacy_files(&self) -> Result<Vec<PathBuf>, KmsError> {
+        self.files_with_extension(LEGACY_EXTENSION).await
+    }
+
+    /// Move a migrated legacy secret to `name`, the name the old provider would
+    /// have stored it under
+    async fn claim_legacy(&self, name: &str) -> Result<Option<Zeroizing<String>>, KmsError> {
+        let alias = legacy_alias(name);
+        let entry = match self.read_entry(&self.entry_path(&alias)).await {
+            Ok(entry) => entry,
+            Err(KmsError::KeyNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let plaintext = self.open_entry(&entry).await?;
+        match self.create_if_absent(name, &plaintext).await {
+            // Claimed concurrently; the real entry wins either way
+            Ok(()) | Err(KmsError::VersionConflict(_)) => {}
+            Err(e) => return Err(e),
+        }
+        tokio::fs::remove_file(self.entry_path(&alias)).await.ok();
+        Ok(Some(plaintext))
+    }
+
+    async fn seal_entry(&self, name: &str, plaintext: &str) -> Result<(), KmsError> {
+        let entry = self.sealed_entry(name, plaintext).await?;
+        write_atomic(&self.entry_path(name), &entry).await
+    }
+
+    /// Serialized entry for `plaintext` sealed under the current data key
+    async fn sealed_entry(&self, name: &str, plaintext: &str) -> Result<Vec<u8>, KmsError> {
+        let state = self.state.read().await;
+        let version = state.header.current_version;
+        let key = &state.keys[&version];
+
+        let nonce = random_bytes::<{ aead::NONCE_LEN }>(&self.rng)?;
+        let mut ciphertext = plaintext.as_bytes().to_vec();
+        aead_key(key)?
+            .seal_in_place_append_tag(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(entry_aad(version, name)),
+                &mut ciphertext,
+            )
+            .map_err(|_| KmsError::EncryptionFailed(format!("Failed to seal {}", name)))?;
+
+        let entry = SealedEntry {
+            format_version: FORMAT_VERSION,
+            name: name.to_string(),
+            key_version: version,
+            nonce: encode(&nonce),
+            ciphertext: encode(&ciphertext),
+            updated_at: Utc::now(),
+        };
+        to_json(&entry)
+    }
+
+    async fn open_entry(&self, entry: &SealedEntry) -> Result<Zeroizing<String>, KmsError> {
+        let state = self.state.read().await;
+        let key = state.keys.get(&entry.key_version).ok_or_else(|| {
+            KmsError::DecryptionFailed(format!("{} uses unknown key version {}", entry.name, entry.key_version))
+        })?;
+
+        let nonce: [u8; aead::NONCE_LEN] = decode(&entry.nonce)?
+            .try_into()
+            .map_err(|_| KmsError::DecryptionFailed(format!("{} has a malformed nonce", entry.name)))?;
+        let mut ciphertext = decode(&entry.ciphertext)?;
+        let plaintext = aead_key(key)?
+            .open_in_place(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(entry_aad(entry.key_version, &entry.name)),
+                &mut ciphertext,
+            )
+            .map_err(|_| KmsError::DecryptionFailed(format!("{} failed authentication", entry.name)))?;
+
+        let plaintext = String::from_utf8(plaintext.to_vec())
+            .map(Zeroizing::new)
+            .map_err(|_| KmsError::DecryptionFailed(format!("{} is not valid UTF-8", entry.name)));
+        // The buffer holds the decrypted bytes after open_in_place
+        ciphertext.fill(0);
+        plaintext
+    }
+
+    async fn read_entry(&self, path: &Path) -> Result<SealedEntry, KmsError> {
+        let bytes = tokio::fs::read(path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                KmsError::KeyNotFound(path.display().to_string())
+            } else {
+                KmsError::DecryptionFailed(format!("Failed to read {}: {}", path.display(), e))
+            }
+        })?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| KmsError::DecryptionFailed(format!("Corrupt entry {}: {}", path.display(), e)))
+    }
+
+    async fn write_header(&self, header: &KeystoreHeader) -> Result<(), KmsError> {
+        write_atomic(&self.storage_path.join(HEADER_FILE), &to_json(header)?).await
+    }
+
+    /// File names are the base64url key name, so `/` and `_` stay distinct
+    fn entry_path(&self, name: &str) -> PathBuf {
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(name);
+        self.storage_path.join(format!("{}.{}", encoded, ENTRY_EXTENSION))
+    }
+
+    async fn files_with_extension(&self, extension: &str) -> Result<Vec<PathBuf>, KmsError> {
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.storage_path)
+            .await
+            .map_err(|e| KmsError::ConfigError(format!("Failed to read directory: {}", e)))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| KmsError::ConfigError(format!("Failed to read directory entry: {}", e)))?
+        {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && path.extension().is_some_and(|ext| ext == extension) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[async_trait]
+impl KmsProvider for LocalKmsProvider {
+    async fn encrypt_and_store(&self, key: &str, data: &str) -> Result<(), KmsError> {
+        self.seal_entry(key, data).await
+    }
+
+    async fn create_if_absent(&self, key: &str, data: &str) -> Result<(), KmsError> {
+        let entry = self.sealed_entry(key, data).await?;
+        write_new(&self.entry_path(key), &entry).await.map_err(|e| match e {
+            KmsError::VersionConflict(_) => KmsError::VersionConflict(format!("{} already exists", key)),
+            e => e,
+        })
+    }
+
+    async fn retrieve_and_decrypt(&self, key: &str) -> Result<String, KmsError> {
+        let entry = match self.read_entry(&self.entry_path(key)).await {
+            Ok(entry) => entry,
+            Err(KmsError::KeyNotFound(_)) => {
+                return match self.claim_legacy(key).await? {
+                    Some(plaintext) => Ok(plaintext.to_string()),
+                    None => Err(KmsError::KeyNotFound(key.to_string())),
+                };
+            }
+            Err(e) => return Err(e),
+        };
+        if entry.name != key {
+            return Err(KmsError::DecryptionFailed(format!("Entry for {} holds {}", key, entry.name)));
+        }
+        Ok(self.open_entry(&entry).await?.to_string())
+    }
+
+    async fn delete_key(&self, key: &str) -> Result<(), KmsError> {
+        tokio::fs::remove_file(self.entry_path(key)).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                KmsError::KeyNotFound(key.to_string())
+            } else {
+                KmsError::ConfigError(format!("Failed to delete file: {}", e))
+            }
+        })
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, KmsError> {
+        let mut keys = vec![];
+        for path in self.files_with_extension(ENTRY_EXTENSION).await? {
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(stem).ok())
+                .and_then(|bytes| String::from_utf8(bytes).ok());
+            let Some(name) = name else { continue };
+            let name = match name.strip_prefix(LEGACY_ALIAS_PREFIX) {
+                // An unclaimed legacy stem is only listable when it has no `_`,
+                // because then it is exactly its real name
+                Some(stem) if stem.contains('_') => continue,
+                Some(stem) => stem.to_string(),
+                None => name,
+            };
+            if name.starts_with(prefix) && !keys.contains(&name) {
+                keys.push(name);
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn key_exists(&self, key: &str) -> Result<bool, KmsError> {
+        for path in [self.entry_path(key), self.entry_path(&legacy_alias(key))] {
+            let exists = tokio::fs::try_exists(path)
+                .await
+                .map_err(|e| KmsError::ConfigError(format!("Failed to check {}: {}", key, e)))?;
+            if exists {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Argon2id is deliberately slow, so it runs off the async executor
+async fn derive_kek(
+    material: Zeroizing<Vec<u8>>,
+    salt: Vec<u8>,
+    kdf: KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LEN]>, KmsError> {
+    tokio::task::spawn_blocking(move || {
+        let params = argon2::Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+            .map_err(|e| KmsError::ConfigError(format!("Invalid Argon2 parameters: {}", e)))?;
+        let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut kek = Zeroizing::new([0u8; KEY_LEN]);
+        argon2
+            .hash_password_into(&material, &salt, kek.as_mut())
+            .map_err(|e| KmsError::EncryptionFailed(format!("Key derivation failed: {}", e)))?;
+        Ok(kek)
+    })
+    .await
+    .map_err(|e| KmsError::EncryptionFailed(format!("Key derivation task failed: {}", e)))?
+}
+
+fn wrap_key(
+    rng: &SystemRandom,
+    kek: &[u8; KEY_LEN],
+    version: u32,
+    key: &[u8; KEY_LEN],
+) -> Result<WrappedKey, KmsError> {
+    let nonce = random_bytes::<{ aead::NONCE_LEN }>(rng)?;
+    let mut ciphertext = key.to_vec();
+    aead_key(kek)?
+        .seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(key_aad(version)),
+            &mut ciphertext,
+        )
+        .map_err(|_| KmsError::EncryptionFailed(format!("Failed to wrap key version {}", version)))?;
+    Ok(WrappedKey {
+        version,
+        created_at: Utc::now(),
+        nonce: encode(&nonce),
+        ciphertext: encode(&ciphertext),
+    })
+}
+
+fn unwrap_key(kek: &[u8; KEY_LEN], wrapped: &WrappedKey) -> Result<Zeroizing<[u8; KEY_LEN]>, KmsError> {
+    let nonce: [u8; aead::NONCE_LEN] = decode(&wrapped.nonce)?
+        .try_into()
+        .map_err(|_| KmsError::DecryptionFailed("Malformed key nonce".to_string()))?;
+    let mut ciphertext = Zeroizing::new(decode(&wrapped.ciphertext)?);
+    let plaintext = aead_key(kek)?
+        .open_in_place(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(key_aad(wrapped.version)),
+            &mut ciphertext,
+        )
+        .map_err(|_| KmsError::DecryptionFailed(format!("Failed to unwrap key version {}", wrapped.version)))?;
+    let key: [u8; KEY_LEN] = plaintext
+        .try_into()
+        .map_err(|_| KmsError::DecryptionFailed("Wrapped key has the wrong length".to_string()))?;
+    Ok(Zeroizing::new(key))
+}
+
+fn aead_key(key: &[u8; KEY_LEN]) -> Result<aead::LessSafeKey, KmsError> {
+    aead::UnboundKey::new(&aead::AES_256_GCM, key)
+        .map(aead::LessSafeKey::new)
+        .map_err(|_| KmsError::EncryptionFailed("Invalid AES-256-GCM key".to_string()))
+}
+
+/// Binds a wrapped key to its version so versions can't be swapped
+fn key_aad(version: u32) -> Vec<u8> {
+    format!("moodbridge-keystore:key:{}", version).into_bytes()
+}
+
+/// Binds ciphertext to its name so entry files can't be swapped
+fn entry_aad(version: u32, name: &str) -> Vec<u8> {
+    format!("moodbridge-keystore:entry:{}:{}", version, name).into_bytes()
+}
+
+fn random_bytes<const N: usize>(rng: &SystemRandom) -> Result<[u8; N], KmsError> {
+    let mut bytes = [0u8; N];
+    rng.fill(&mut bytes)
+        .map_err(|_| KmsError::EncryptionFailed("System random number generator failed".to_string()))?;
+    Ok(bytes)
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, KmsError> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| KmsError::DecryptionFailed(format!("Invalid base64 in keystore: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, KmsError> {
+    serde_json::to_vec_pretty(value).map_err(|e| KmsError::EncryptionFailed(format!("Failed to serialize: {}", e)))
+}
+
+/// Replace `path` through a synced temporary file and rename
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), KmsError> {
+    use tokio::io::AsyncWriteExt;
+
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("entry");
+    // Concurrent writers each get their own temp file; the last rename wins
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+    let write_error = |e: std::io::Error| KmsError::EncryptionFailed(format!("Failed to write {}: {}", path.display(), e));
+
+    let mut file = tokio::fs::File::create(&tmp_path).await.map_err(write_error)?;
+    let written = async {
+        file.write_all(contents).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, path).await
+    }
+    .await;
+    if let Err(e) = written {
+        tokio::fs::remove_file(&tmp_path).await.ok();
+        return Err(write_error(e));
+    }
+
+    // Persist the rename itself
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = tokio::fs::File::open(dir).await {
+            dir.sync_all().await.ok();
+        }
+    }
+    Ok(())
+}
+
+/// Create `path` with `contents`, failing with `VersionConflict` if it already exists
+///
+/// The hard link is the atomic step: it never replaces an existing file.
+async fn write_new(path: &Path, contents: &[u8]) -> Result<(), KmsError> {
+    use tokio::io::AsyncWriteExt;
+
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("entry");
+    let tmp_path = path.with_file_name(format!(".{}.{}.new", file_name, uuid::Uuid::new_v4()));
+    let write_error = |e: std::io::Error| KmsError::EncryptionFailed(format!("Failed to write {}: {}", path.display(), e));
+
+    let mut file = tokio::fs::File::create(&tmp_path).await.map_err(write_error)?;
+    let written = async {
+        file.write_all(contents).await?;
+        file.sync_all().await?;
+        tokio::fs::hard_link(&tmp_path, path).await
+    }
+    .await;
+    tokio::fs::remove_file(&tmp_path).await.ok();
+
+    match written {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            Err(KmsError::VersionConflict(path.display().to_string()))
+        }
+        Err(e) => Err(write_error(e)),
+    }
+}
+
+/// Alias a migrated legacy secret is sealed under, from the old provider's file name
+fn legacy_alias(name: &str) -> String {
+    format!("{}{}", LEGACY_ALIAS_PREFIX, name.replace('/', "_"))
+}
+
+/// Decrypted legacy bytes, if they look like a secret rather than XOR noise
+fn legacy_plaintext(bytes: Vec<u8>) -> Option<Zeroizing<String>> {
+    let text = Zeroizing::new(String::from_utf8(bytes).ok()?);
+    let printable = !text.is_empty()
+        && text.chars().all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'));
+    printable.then_some(text)
+}
+
+/// Inverse of the old provider's repeating-key XOR
+fn legacy_xor(data: &[u8], key: &str) -> Vec<u8> {
+    let key_bytes = key.as_bytes();
+    data.iter()
+        .enumerate()
+        .map(|(i, &b)| b ^ key_bytes[i % key_bytes.len()])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Cheap parameters so tests don't spend seconds in Argon2
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    async fn open(dir: &TempDir, passphrase: &str) -> Result<LocalKmsProvider, KmsError> {
+        LocalKmsProvider::open_with_kdf(dir.path().to_path_buf(), KeystoreSecret::passphrase(passphrase), TEST_KDF).await
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_and_wrong_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let kms = open(&dir, "correct horse").await.unwrap();
+        kms.encrypt_and_store("oauth/google_token", "secret-value").await.unwrap();
+        kms.encrypt_and_store("oauth_google/token", "other").await.unwrap();
+
+        let raw = std::fs::read_to_string(kms.entry_path("oauth/google_token")).unwrap();
+        assert!(!raw.contains("secret-value"));
+
+        let reopened = open(&dir, "correct horse").await.unwrap();
+        assert_eq!(reopened.retrieve_and_decrypt("oauth/google_token").await.unwrap(), "secret-value");
+        assert_eq!(reopened.retrieve_and_decrypt("oauth_google/token").await.unwrap(), "other");
+        let mut keys = reopened.list_keys("oauth").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["oauth/google_token", "oauth_google/token"]);
+
+        assert!(matches!(open(&dir, "wrong").await, Err(KmsError::DecryptionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_swapped_entries_fail_authentication() {
+        let dir = TempDir::new().unwrap();
+        let kms = open(&dir, "pass").await.unwrap();
+        kms.encrypt_and_store("a", "alpha").await.unwrap();
+        kms.encrypt_and_store("b", "bravo").await.unwrap();
+
+        // Pretend b's ciphertext belongs to a
+        let mut entry: SealedEntry =
+            serde_json::from_slice(&std::fs::read(kms.entry_path("b")).unwrap()).unwrap();
+        entry.name = "a".to_string();
+        std::fs::write(kms.entry_path("a"), serde_json::to_vec(&entry).unwrap()).unwrap();
+        assert!(matches!(kms.retrieve_and_decrypt("a").await, Err(KmsError::DecryptionFailed(_))));
+
+        let mut entry: SealedEntry =
+            serde_json::from_slice(&std::fs::read(kms.entry_path("b")).unwrap()).unwrap();
+        let mut ciphertext = decode(&entry.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        entry.ciphertext = encode(&ciphertext);
+        std::fs::write(kms.entry_path("b"), serde_json::to_vec(&entry).unwrap()).unwrap();
+        assert!(matches!(kms.retrieve_and_decrypt("b").await, Err(KmsError::DecryptionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rotation_and_passphrase_change() {
+        let dir = TempDir::new().unwrap();
+        let kms = open(&dir, "old pass").await.unwrap();
+        kms.encrypt_and_store("one", "1").await.unwrap();
+        kms.encrypt_and_store("two", "2").await.unwrap();
+
+        assert_eq!(kms.rotate_key().await.unwrap(), 2);
+        assert_eq!(kms.current_key_version().await, 2);
+        let entry: SealedEntry = serde_json::from_slice(&std::fs::read(kms.entry_path("one")).unwrap()).unwrap();
+        assert_eq!(entry.key_version, 2);
+
+        kms.change_secret(KeystoreSecret::passphrase("new pass")).await.unwrap();
+        assert!(open(&dir, "old pass").await.is_err());
+        let reopened = open(&dir, "new pass").await.unwrap();
+        assert_eq!(reopened.retrieve_and_decrypt("two").await.unwrap(), "2");
+        assert_eq!(reopened.state.read().await.header.keys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_migrates_legacy_xor_files() {
+        let dir = TempDir::new().unwrap();
+        let legacy_key = "legacy-key";
+        // The old provider stored `oauth/token` and `api_key` both with `_` in the file name
+        std::fs::write(dir.path().join("oauth_token.enc"), legacy_xor(b"hunter2", legacy_key)).unwrap();
+        std::fs::write(dir.path().join("api_key.enc"), legacy_xor(b"sk-test-123", legacy_key)).unwrap();
+
+        let kms = open(&dir, "pass").await.unwrap();
+        assert_eq!(kms.migrate_legacy(legacy_key).await.unwrap(), 2);
+        assert!(!dir.path().join("oauth_token.enc").exists());
+        assert!(dir.path().join("oauth_token.enc.migrated").exists());
+        assert_eq!(kms.migrate_legacy(legacy_key).await.unwrap(), 0);
+
+        assert!(kms.key_exists("oauth/token").await.unwrap());
+        assert_eq!(kms.retrieve_and_decrypt("oauth/token").await.unwrap(), "hunter2");
+        assert_eq!(kms.retrieve_and_decrypt("api_key").await.unwrap(), "sk-test-123");
+        // Claimed under the real name, so the name survives listing and a reopen
+        assert_eq!(kms.list_keys("oauth/").await.unwrap(), vec!["oauth/token".to_string()]);
+        let reopened = open(&dir, "pass").await.unwrap();
+        assert_eq!(reopened.retrieve_and_decrypt("oauth/token").await.unwrap(), "hunter2");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_legacy_key_migrates_nothing() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("good.enc"), legacy_xor(b"hunter2", "legacy-key")).unwrap();
+        std::fs::write(dir.path().join("other.enc"), legacy_xor(b"correct horse", "production-secret")).unwrap();
+
+        let kms = open(&dir, "pass").await.unwrap();
+        assert!(matches!(
+            kms.migrate_legacy("legacy-key").await,
+            Err(KmsError::DecryptionFailed(_))
+        ));
+        assert_eq!(kms.legacy_files().await.unwrap().len(), 2);
+        assert!(!kms.key_exists("good").await.unwrap());
+    }
+}
@@ -2,6 +2,10 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub use super::keystore::{KdfParams, KeystoreSecret, LocalKmsProvider};
+pub use super::vault::{HashiCorpVaultProvider, VaultAuth, VaultConfig};
+use super::keystore::{LEGACY_KEY_ENV, MIGRATE_LEGACY_ENV};
+
 /// KMS provider trait for secure credential storage
 #[async_trait]
 pub trait KmsProvider: Send + Sync {
@@ -10,6 +14,18 @@ pub trait KmsProvider: Send + Sync {
     async fn delete_key(&self, key: &str) -> Result<(), KmsError>;
     async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, KmsError>;
     async fn key_exists(&self, key: &str) -> Result<bool, KmsError>;
+
+    /// Store `data` under `key` only if nothing is stored there yet
+    ///
+    /// Fails with `KmsError::VersionConflict` when the key exists. This default checks
+    /// and then writes, which is only safe within one process; providers that can
+    /// create atomically override it.
+    async fn create_if_absent(&self, key: &str, data: &str) -> Result<(), KmsError> {
+        if self.key_exists(key).await? {
+            return Err(KmsError::VersionConflict(format!("{} already exists", key)));
+        }
+        self.encrypt_and_store(key, data).await
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -26,6 +42,8 @@ pub enum KmsError {
     NetworkError(String),
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    #[error("Version conflict: {0}")]
+    VersionConflict(String),
 }
 
 /// AWS KMS Provider
@@ -37,8 +55,9 @@ pub struct AwsKmsProvider {
 
 impl AwsKmsProvider {
     pub async fn new(key_id: String, region: Option<String>) -> Result<Self, KmsError> {
+        let region = region.unwrap_or_else(|| "us-east-1".to_string());
         let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .region(region.clone().unwrap_or_else(|| "us-east-1".to_string()))
+            .region(aws_config::Region::new(region.clone()))
             .load()
             .await;
             
@@ -47,7 +66,7 @@ impl AwsKmsProvider {
         Ok(Self {
             client,
             key_id,
-            region: region.unwrap_or_else(|| "us-east-1".to_string()),
+            region,
         })
     }
 }
@@ -171,239 +190,15 @@ impl KmsProvider for GcpKmsProvider {
     }
 }
 
-/// HashiCorp Vault Provider
-pub struct HashiCorpVaultProvider {
-    vault_addr: String,
-    vault_token: String,
-    mount_path: String,
-}
-
-impl HashiCorpVaultProvider {
-    pub fn new(vault_addr: String, vault_token: String, mount_path: String) -> Self {
-        Self {
-            vault_addr,
-            vault_token,
-            mount_path,
-        }
-    }
-}
-
-#[async_trait]
-impl KmsProvider for HashiCorpVaultProvider {
-    async fn encrypt_and_store(&self, key: &str, data: &str) -> Result<(), KmsError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/{}/data/{}", self.vault_addr, self.mount_path, key);
-        
-        let payload = serde_json::json!({
-            "data": {
-                "value": data
-            }
-        });
-        
-        let response = client
-            .post(&url)
-            .header("X-Vault-Token", &self.vault_token)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| KmsError::NetworkError(format!("Vault request failed: {}", e)))?;
-            
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(KmsError::EncryptionFailed(format!("Vault returned status: {}", response.status())))
-        }
-    }
-    
-    async fn retrieve_and_decrypt(&self, key: &str) -> Result<String, KmsError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/{}/data/{}", self.vault_addr, self.mount_path, key);
-        
-        let response = client
-            .get(&url)
-            .header("X-Vault-Token", &self.vault_token)
-            .send()
-            .await
-            .map_err(|e| KmsError::NetworkError(format!("Vault request failed: {}", e)))?;
-            
-        if response.status().is_success() {
-            let json: serde_json::Value = response.json().await
-                .map_err(|e| KmsError::DecryptionFailed(format!("Failed to parse response: {}", e)))?;
-                
-            let value = json["data"]["data"]["value"].as_str()
-                .ok_or_else(|| KmsError::DecryptionFailed("Value not found in response".to_string()))?;
-                
-            Ok(value.to_string())
-        } else if response.status() == 404 {
-            Err(KmsError::KeyNotFound(key.to_string()))
-        } else {
-            Err(KmsError::DecryptionFailed(format!("Vault returned status: {}", response.status())))
-        }
-    }
-    
-    async fn delete_key(&self, key: &str) -> Result<(), KmsError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/{}/metadata/{}", self.vault_addr, self.mount_path, key);
-        
-        let response = client
-            .delete(&url)
-            .header("X-Vault-Token", &self.vault_token)
-            .send()
-            .await
-            .map_err(|e| KmsError::NetworkError(format!("Vault request failed: {}", e)))?;
-            
-        if response.status().is_success() || response.status() == 404 {
-            Ok(())
-        } else {
-            Err(KmsError::ConfigError(format!("Failed to delete key: {}", response.status())))
-        }
-    }
-    
-    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, KmsError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/{}/metadata", self.vault_addr, self.mount_path);
-        
-        let response = client
-            .get(&url)
-            .header("X-Vault-Token", &self.vault_token)
-            .query(&[("list", "true")])
-            .send()
-            .await
-            .map_err(|e| KmsError::NetworkError(format!("Vault request failed: {}", e)))?;
-            
-        if response.status().is_success() {
-            let json: serde_json::Value = response.json().await
-                .map_err(|e| KmsError::DecryptionFailed(format!("Failed to parse response: {}", e)))?;
-                
-            let keys = json["data"]["keys"].as_array()
-                .unwrap_or(&vec![])
-                .iter()
-                .filter_map(|v| v.as_str())
-                .filter(|k| k.starts_with(prefix))
-                .map(|s| s.to_string())
-                .collect();
-                
-            Ok(keys)
-        } else {
-            Ok(vec![])
-        }
-    }
-    
-    async fn key_exists(&self, key: &str) -> Result<bool, KmsError> {
-        match self.retrieve_and_decrypt(key).await {
-            Ok(_) => Ok(true),
-            Err(KmsError::KeyNotFound(_)) => Ok(false),
-            Err(e) => Err(e),
-        }
-    }
-}
-
-/// Local file-based KMS (for development/testing)
-pub struct LocalKmsProvider {
-    storage_path: std::path::PathBuf,
-    encryption_key: String,
-}
-
-impl LocalKmsProvider {
-    pub fn new(storage_path: std::path::PathBuf, encryption_key: String) -> Self {
-        std::fs::create_dir_all(&storage_path).ok();
-        Self {
-            storage_path,
-            encryption_key,
-        }
-    }
-}
-
-#[async_trait]
-impl KmsProvider for LocalKmsProvider {
-    async fn encrypt_and_store(&self, key: &str, data: &str) -> Result<(), KmsError> {
-        // Simple XOR encryption for demo (use proper encryption in production)
-        let encrypted_data = simple_encrypt(data, &self.encryption_key);
-        let file_path = self.storage_path.join(format!("{}.enc", key.replace('/', "_")));
-        
-        tokio::fs::write(&file_path, encrypted_data).await
-            .map_err(|e| KmsError::EncryptionFailed(format!("Failed to write file: {}", e)))?;
-            
-        Ok(())
-    }
-    
-    async fn retrieve_and_decrypt(&self, key: &str) -> Result<String, KmsError> {
-        let file_path = self.storage_path.join(format!("{}.enc", key.replace('/', "_")));
-        
-        let encrypted_data = tokio::fs::read(&file_path).await
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    KmsError::KeyNotFound(key.to_string())
-                } else {
-                    KmsError::DecryptionFailed(format!("Failed to read file: {}", e))
-                }
-            })?;
-            
-        let decrypted_data = simple_decrypt(&encrypted_data, &self.encryption_key);
-        Ok(decrypted_data)
-    }
-    
-    async fn delete_key(&self, key: &str) -> Result<(), KmsError> {
-        let file_path = self.storage_path.join(format!("{}.enc", key.replace('/', "_")));
-        tokio::fs::remove_file(&file_path).await
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    KmsError::KeyNotFound(key.to_string())
-                } else {
-                    KmsError::ConfigError(format!("Failed to delete file: {}", e))
-                }
-            })?;
-        Ok(())
-    }
-    
-    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, KmsError> {
-        let mut keys = vec![];
-        let mut entries = tokio::fs::read_dir(&self.storage_path).await
-            .map_err(|e| KmsError::ConfigError(format!("Failed to read directory: {}", e)))?;
-            
-        while let Some(entry) = entries.next_entry().await
-            .map_err(|e| KmsError::ConfigError(format!("Failed to read directory entry: {}", e)))? {
-            
-            if let Some(name) = entry.file_name().to_str() {
-                if name.ends_with(".enc") {
-                    let key = name.trim_end_matches(".enc").replace('_', "/");
-                    if key.starts_with(prefix) {
-                        keys.push(key);
-                    }
-                }
-            }
-        }
-        
-        Ok(keys)
-    }
-    
-    async fn key_exists(&self, key: &str) -> Result<bool, KmsError> {
-        let file_path = self.storage_path.join(format!("{}.enc", key.replace('/', "_")));
-        Ok(file_path.exists())
-    }
-}
-
-// Simple encryption/decryption for demo purposes
-fn simple_encrypt(data: &str, key: &str) -> Vec<u8> {
-    let key_bytes = key.as_bytes();
-    data.bytes()
-        .enumerate()
-        .map(|(i, b)| b ^ key_bytes[i % key_bytes.len()])
-        .collect()
-}
-
-fn simple_decrypt(data: &[u8], key: &str) -> String {
-    let key_bytes = key.as_bytes();
-    let decrypted: Vec<u8> = data.iter()
-        .enumerate()
-        .map(|(i, &b)| b ^ key_bytes[i % key_bytes.len()])
-        .collect();
-    String::from_utf8_lossy(&decrypted).to_string()
-}
-
 /// Create KMS provider from environment variables
 pub async fn create_kms_from_env() -> Result<Box<dyn KmsProvider>, KmsError> {
-    // Try AWS KMS first
+    // Vault is the standard backend, so it wins when configured
+    if let Some(config) = VaultConfig::from_env()? {
+        let provider = HashiCorpVaultProvider::new(config)?;
+        return Ok(Box::new(provider));
+    }
+
+    // Try AWS KMS
     if let (Ok(key_id), Ok(region)) = (
         std::env::var("AWS_KMS_KEY_ID"),
         std::env::var("AWS_REGION")
@@ -412,16 +207,6 @@ pub async fn create_kms_from_env() -> Result<Box<dyn KmsProvider>, KmsError> {
         return Ok(Box::new(provider));
     }
     
-    // Try HashiCorp Vault
-    if let (Ok(vault_addr), Ok(vault_token)) = (
-        std::env::var("VAULT_ADDR"),
-        std::env::var("VAULT_TOKEN")
-    ) {
-        let mount_path = std::env::var("VAULT_MOUNT_PATH").unwrap_or_else(|_| "secret".to_string());
-        let provider = HashiCorpVaultProvider::new(vault_addr, vault_token, mount_path);
-        return Ok(Box::new(provider));
-    }
-    
     // Try Azure Key Vault
     if let (Ok(vault_url), Ok(client_id), Ok(client_secret), Ok(tenant_id)) = (
         std::env::var("AZURE_VAULT_URL"),
@@ -444,12 +229,28 @@ pub async fn create_kms_from_env() -> Result<Box<dyn KmsProvider>, KmsError> {
         return Ok(Box::new(provider));
     }
     
-    // Fallback to local KMS
+    // Fallback to the local keystore
     let storage_path = std::env::var("LOCAL_KMS_PATH")
         .unwrap_or_else(|_| format!("{}/.moodbridge/kms", std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string())));
-    let encryption_key = std::env::var("LOCAL_KMS_KEY")
-        .unwrap_or_else(|_| "default-dev-key-change-in-production".to_string());
-        
-    let provider = LocalKmsProvider::new(std::path::PathBuf::from(storage_path), encryption_key);
+    let provider = LocalKmsProvider::open(std::path::PathBuf::from(storage_path), KeystoreSecret::from_env()?).await?;
+
+    // Secrets written by the old XOR provider are re-sealed only when asked, with their key
+    if matches!(std::env::var(MIGRATE_LEGACY_ENV).as_deref(), Ok("true") | Ok("1")) {
+        let legacy_key = std::env::var(LEGACY_KEY_ENV).map_err(|_| {
+            KmsError::ConfigError(format!("{} requires {}", MIGRATE_LEGACY_ENV, LEGACY_KEY_ENV))
+        })?;
+        let migrated = provider.migrate_legacy(&legacy_key).await?;
+        tracing::info!("Migrated {} legacy secrets into the local keystore", migrated);
+    } else {
+        let pending = provider.legacy_files().await?.len();
+        if pending > 0 {
+            tracing::warn!(
+                "{} legacy .enc secrets are not migrated; set {} and {} to migrate them",
+                pending,
+                MIGRATE_LEGACY_ENV,
+                LEGACY_KEY_ENV
+            );
+        }
+    }
     Ok(Box::new(provider))
 }
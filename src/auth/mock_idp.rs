@@ -0,0 +1,458 @@
+//! In-process OpenID Connect identity provider for tests
+//!
+//! Serves discovery, authorize, token, JWKS, userinfo and revocation endpoints
+//! on a loopback port so the real `OidcProvider` can be exercised end to end
+//! without network access. `/authorize` signs in whoever `sign_in_as` named
+//! without a login page, the token endpoint enforces PKCE, redirect URI and
+//! single-use codes, and `inject_fault` makes it mint bad ID tokens so the
+//! client's rejections can be tested.
+
+use super::oidc::{Audience, IdTokenClaims, Jwk, JwkSet, ProviderMetadata};
+use super::providers::with_query;
+use super::random_token;
+use axum::{
+    extract::{Form, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::Engine as _;
+use chrono::Utc;
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, KeyPair, RsaKeyPair};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Lifetime of the ID and access tokens it issues
+const TOKEN_LIFETIME_SECONDS: i64 = 300;
+
+/// Someone who can sign in at the mock provider
+#[derive(Debug, Clone)]
+pub struct MockUser {
+    pub sub: String,
+    pub email: String,
+    pub name: String,
+}
+
+/// Ways to corrupt the next ID tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockIdpFault {
+    WrongNonce,
+    WrongAudience,
+    WrongIssuer,
+    Expired,
+    BadSignature,
+}
+
+enum SigningKey {
+    Es256 { kid: String, pair: EcdsaKeyPair },
+    Rs256 { kid: String, pair: RsaKeyPair },
+}
+
+impl SigningKey {
+    fn generate_es256() -> io::Result<Self> {
+        let rng = SystemRandom::new();
+        let alg = &signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(alg, &rng).map_err(|_| key_error("EC key generation failed"))?;
+        let pair = EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng).map_err(|_| key_error("Invalid EC key"))?;
+        Ok(SigningKey::Es256 { kid: new_kid()?, pair })
+    }
+
+    fn alg(&self) -> &'static str {
+        match self {
+            SigningKey::Es256 { .. } => "ES256",
+            SigningKey::Rs256 { .. } => "RS256",
+        }
+    }
+
+    fn kid(&self) -> &str {
+        match self {
+            SigningKey::Es256 { kid, .. } | SigningKey::Rs256 { kid, .. } => kid,
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let rng = SystemRandom::new();
+        match self {
+            SigningKey::Es256 { pair, .. } => pair.sign(&rng, message).map(|s| s.as_ref().to_vec()).unwrap_or_default(),
+            SigningKey::Rs256 { pair, .. } => {
+                let mut signature = vec![0u8; pair.public().modulus_len()];
+                match pair.sign(&signature::RSA_PKCS1_SHA256, &rng, message, &mut signature) {
+                    Ok(()) => signature,
+                    Err(_) => Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn jwk(&self) -> Jwk {
+        let mut jwk = Jwk {
+            kty: String::new(),
+            kid: Some(self.kid().to_string()),
+            alg: Some(self.alg().to_string()),
+            key_use: Some("sig".to_string()),
+            n: None,
+            e: None,
+            crv: None,
+            x: None,
+            y: None,
+        };
+        match self {
+            SigningKey::Es256 { pair, .. } => {
+                // Uncompressed point: 0x04 || x || y
+                let point = pair.public_key().as_ref();
+                jwk.kty = "EC".to_string();
+                jwk.crv = Some("P-256".to_string());
+                jwk.x = Some(b64(&point[1..33]));
+                jwk.y = Some(b64(&point[33..65]));
+            }
+            SigningKey::Rs256 { pair, .. } => {
+                let (n, e) = rsa_public_components(pair.public().as_ref()).unwrap_or_default();
+                jwk.kty = "RSA".to_string();
+                jwk.n = Some(b64(n));
+                jwk.e = Some(b64(e));
+            }
+        }
+        jwk
+    }
+}
+
+/// An authorization code waiting to be redeemed
+struct IssuedCode {
+    redirect_uri: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    sub: String,
+}
+
+struct MockState {
+    issuer: String,
+    client_id: String,
+    key: Mutex<SigningKey>,
+    users: Mutex<HashMap<String, MockUser>>,
+    signed_in: Mutex<Option<String>>,
+    codes: Mutex<HashMap<String, IssuedCode>>,
+    access_tokens: Mutex<HashMap<String, String>>,
+    refresh_tokens: Mutex<HashMap<String, String>>,
+    fault: Mutex<Option<MockIdpFault>>,
+}
+
+/// A running mock provider; stops when the test runtime shuts down
+pub struct MockIdp {
+    state: Arc<MockState>,
+}
+
+impl MockIdp {
+    /// Start a provider signing ES256 ID tokens for `client_id`
+    pub async fn start(client_id: &str) -> io::Result<Self> {
+        Self::serve(client_id, SigningKey::generate_es256()?).await
+    }
+
+    /// Start a provider signing RS256 ID tokens with a PKCS#8 RSA key
+    pub async fn start_with_rsa(client_id: &str, pkcs8_der: &[u8]) -> io::Result<Self> {
+        let pair = RsaKeyPair::from_pkcs8(pkcs8_der).map_err(|e| key_error(&format!("Invalid RSA key: {}", e)))?;
+        Self::serve(client_id, SigningKey::Rs256 { kid: new_kid()?, pair }).await
+    }
+
+    async fn serve(client_id: &str, key: SigningKey) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let issuer = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(MockState {
+            issuer,
+            client_id: client_id.to_string(),
+            key: Mutex::new(key),
+            users: Mutex::new(HashMap::new()),
+            signed_in: Mutex::new(None),
+            codes: Mutex::new(HashMap::new()),
+            access_tokens: Mutex::new(HashMap::new()),
+            refresh_tokens: Mutex::new(HashMap::new()),
+            fault: Mutex::new(None),
+        });
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .route("/jwks", get(jwks))
+            .route("/userinfo", get(userinfo))
+            .route("/revoke", post(revoke))
+            .with_state(state.clone());
+
+        let server = axum::Server::from_tcp(listener).map_err(io::Error::other)?;
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(app.into_make_service()).await {
+                tracing::error!("Mock identity provider stopped: {}", e);
+            }
+        });
+        Ok(Self { state })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.state.issuer
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.state.client_id
+    }
+
+    pub fn add_user(&self, user: MockUser) {
+        self.state.users.lock().unwrap().insert(user.sub.clone(), user);
+    }
+
+    /// The user `/authorize` will sign in; `None` answers `login_required`
+    pub fn sign_in_as(&self, sub: Option<&str>) {
+        *self.state.signed_in.lock().unwrap() = sub.map(str::to_string);
+    }
+
+    pub fn inject_fault(&self, fault: Option<MockIdpFault>) {
+        *self.state.fault.lock().unwrap() = fault;
+    }
+
+    /// Replace the signing key with a new ES256 key under a new key ID; the old
+    /// key disappears from the JWKS
+    pub fn rotate_key(&self) -> io::Result<()> {
+        *self.state.key.lock().unwrap() = SigningKey::generate_es256()?;
+        Ok(())
+    }
+
+    /// Sign arbitrary claims with the current key, for tests that need a token
+    /// the endpoints wouldn't mint
+    pub fn sign_claims(&self, claims: &IdTokenClaims) -> String {
+        sign_jwt(&self.state.key.lock().unwrap(), &json!(claims))
+    }
+}
+
+async fn discovery(State(state): State<Arc<MockState>>) -> Json<ProviderMetadata> {
+    let issuer = &state.issuer;
+    Json(ProviderMetadata {
+        issuer: issuer.clone(),
+        authorization_endpoint: format!("{}/authorize", issuer),
+        token_endpoint: format!("{}/token", issuer),
+        jwks_uri: format!("{}/jwks", issuer),
+        userinfo_endpoint: Some(format!("{}/userinfo", issuer)),
+        revocation_endpoint: Some(format!("{}/revoke", issuer)),
+        id_token_signing_alg_values_supported: vec!["RS256".to_string(), "ES256".to_string()],
+    })
+}
+
+async fn authorize(State(state): State<Arc<MockState>>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let param = |name: &str| params.get(name).cloned().unwrap_or_default();
+    if param("client_id") != state.client_id {
+        return oauth_error(StatusCode::BAD_REQUEST, "unauthorized_client", "Unknown client_id");
+    }
+    let redirect_uri = param("redirect_uri");
+    if redirect_uri.is_empty() || param("response_type") != "code" {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "Expected response_type=code and a redirect_uri");
+    }
+    if param("code_challenge_method") != "S256" || param("code_challenge").is_empty() {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_request", "PKCE with S256 is required");
+    }
+
+    let state_param = param("state");
+    let signed_in = state.signed_in.lock().unwrap().clone();
+    let Some(sub) = signed_in.filter(|sub| state.users.lock().unwrap().contains_key(sub)) else {
+        let location = with_query(&redirect_uri, &[("error", "login_required"), ("state", &state_param)]);
+        return Redirect::to(&location).into_response();
+    };
+
+    let code = match random_token() {
+        Ok(code) => code,
+        Err(_) => return oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Random generator failed"),
+    };
+    state.codes.lock().unwrap().insert(
+        code.clone(),
+        IssuedCode {
+            redirect_uri: redirect_uri.clone(),
+            code_challenge: param("code_challenge"),
+            nonce: params.get("nonce").cloned(),
+            sub,
+        },
+    );
+    Redirect::to(&with_query(&redirect_uri, &[("code", &code), ("state", &state_param)])).into_response()
+}
+
+async fn token(State(state): State<Arc<MockState>>, Form(form): Form<HashMap<String, String>>) -> Response {
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    if field("client_id") != state.client_id {
+        return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Unknown client_id");
+    }
+
+    let (sub, nonce) = match field("grant_type") {
+        "authorization_code" => {
+            // Codes are single use even when the exchange fails
+            let Some(issued) = state.codes.lock().unwrap().remove(field("code")) else {
+                return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", "Unknown or used code");
+            };
+            if issued.redirect_uri != field("redirect_uri") {
+                return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", "redirect_uri does not match");
+            }
+            let challenge = b64(&Sha256::digest(field("code_verifier").as_bytes()));
+            if challenge != issued.code_challenge {
+                return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", "PKCE verification failed");
+            }
+            (issued.sub, issued.nonce)
+        }
+        "refresh_token" => match state.refresh_tokens.lock().unwrap().get(field("refresh_token")) {
+            Some(sub) => (sub.clone(), None),
+            None => return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", "Unknown refresh token"),
+        },
+        _ => return oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type", "Unsupported grant_type"),
+    };
+
+    let Some(user) = state.users.lock().unwrap().get(&sub).cloned() else {
+        return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", "User no longer exists");
+    };
+    let (Ok(access_token), Ok(refresh_token)) = (random_token(), random_token()) else {
+        return oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Random generator failed");
+    };
+    state.access_tokens.lock().unwrap().insert(access_token.clone(), sub.clone());
+    state.refresh_tokens.lock().unwrap().insert(refresh_token.clone(), sub);
+
+    let id_token = id_token(&state, &user, nonce);
+    Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": TOKEN_LIFETIME_SECONDS,
+        "refresh_token": refresh_token,
+        "id_token": id_token,
+        "scope": "openid email profile"
+    }))
+    .into_response()
+}
+
+fn id_token(state: &MockState, user: &MockUser, nonce: Option<String>) -> String {
+    let now = Utc::now().timestamp();
+    let mut claims = IdTokenClaims {
+        iss: state.issuer.clone(),
+        sub: user.sub.clone(),
+        aud: Audience::One(state.client_id.clone()),
+        azp: None,
+        exp: now + TOKEN_LIFETIME_SECONDS,
+        iat: now,
+        nbf: None,
+        nonce,
+        email: Some(user.email.clone()),
+        email_verified: Some(true),
+        preferred_username: Some(user.email.clone()),
+        upn: None,
+        name: Some(user.name.clone()),
+        tid: None,
+        oid: None,
+    };
+
+    let fault = *state.fault.lock().unwrap();
+    match fault {
+        Some(MockIdpFault::WrongNonce) => claims.nonce = Some("replayed-nonce".to_string()),
+        Some(MockIdpFault::WrongAudience) => claims.aud = Audience::One("another-client".to_string()),
+        Some(MockIdpFault::WrongIssuer) => claims.iss = "https://attacker.example".to_string(),
+        Some(MockIdpFault::Expired) => {
+            claims.iat = now - 2 * 3600;
+            claims.exp = now - 3600;
+        }
+        Some(MockIdpFault::BadSignature) | None => {}
+    }
+
+    let token = sign_jwt(&state.key.lock().unwrap(), &json!(claims));
+    if fault == Some(MockIdpFault::BadSignature) {
+        // A genuine signature, but over a different user's claims
+        let (signed, _) = token.rsplit_once('.').unwrap_or_default();
+        let mut other = claims.clone();
+        other.sub = format!("{}-other", claims.sub);
+        let forged = sign_jwt(&state.key.lock().unwrap(), &json!(other));
+        let (_, signature) = forged.rsplit_once('.').unwrap_or_default();
+        return format!("{}.{}", signed, signature);
+    }
+    token
+}
+
+async fn jwks(State(state): State<Arc<MockState>>) -> Json<JwkSet> {
+    Json(JwkSet { keys: vec![state.key.lock().unwrap().jwk()] })
+}
+
+async fn userinfo(State(state): State<Arc<MockState>>, headers: HeaderMap) -> Response {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let sub = state.access_tokens.lock().unwrap().get(token).cloned();
+    let user = sub.and_then(|sub| state.users.lock().unwrap().get(&sub).cloned());
+    match user {
+        Some(user) => Json(json!({
+            "sub": user.sub,
+            "email": user.email,
+            "email_verified": true,
+            "name": user.name
+        }))
+        .into_response(),
+        None => oauth_error(StatusCode::UNAUTHORIZED, "invalid_token", "Unknown access token"),
+    }
+}
+
+async fn revoke(State(state): State<Arc<MockState>>, Form(form): Form<HashMap<String, String>>) -> StatusCode {
+    if let Some(token) = form.get("token") {
+        state.access_tokens.lock().unwrap().remove(token);
+        state.refresh_tokens.lock().unwrap().remove(token);
+    }
+    StatusCode::OK
+}
+
+fn sign_jwt(key: &SigningKey, claims: &serde_json::Value) -> String {
+    let header = json!({ "alg": key.alg(), "kid": key.kid(), "typ": "JWT" });
+    let signing_input = format!("{}.{}", b64(header.to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+    let signature = key.sign(signing_input.as_bytes());
+    format!("{}.{}", signing_input, b64(&signature))
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> Response {
+    (status, Json(json!({ "error": error, "error_description": description }))).into_response()
+}
+
+/// Modulus and exponent from a DER `RSAPublicKey ::= SEQUENCE { n INTEGER, e INTEGER }`
+fn rsa_public_components(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (sequence, _) = der_element(der, 0x30)?;
+    let (n, rest) = der_element(sequence, 0x02)?;
+    let (e, _) = der_element(rest, 0x02)?;
+    // Drop the sign byte DER adds when the high bit is set
+    let n = n.strip_prefix(&[0u8]).unwrap_or(n);
+    Some((n, e))
+}
+
+/// Contents of the DER element with `tag` at the start of `input`, and what follows it
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&first, rest) = input.split_first()?;
+    if first != tag {
+        return None;
+    }
+    let (&length_byte, mut rest) = rest.split_first()?;
+    let length = if length_byte < 0x80 {
+        length_byte as usize
+    } else {
+        let count = (length_byte & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        rest = &rest[count..];
+        length
+    };
+    (rest.len() >= length).then(|| (&rest[..length], &rest[length..]))
+}
+
+fn b64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn new_kid() -> io::Result<String> {
+    random_token()
+        .map(|token| token[..12].to_string())
+        .map_err(|_| key_error("Random generator failed"))
+}
+
+fn key_error(message: &str) -> io::Error {
+    io::Error::other(message.to_string())
+}
@@ -1,12 +1,21 @@
 pub mod providers;
 pub mod kms;
-pub mod tokens;
-pub mod middleware;
+pub mod keystore;
+pub mod vault;
+pub mod oidc;
+pub mod mock_idp;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Mutex;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use base64::Engine as _;
+use chrono::{DateTime, Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+
+/// How long a user has to finish signing in at the identity provider
+pub const LOGIN_TIMEOUT_MINUTES: i64 = 10;
 
 /// Unified authentication result
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,7 +23,7 @@ pub struct AuthResult {
     pub user_id: String,
     pub email: String,
     pub display_name: String,
-    pub provider: AuthProvider,
+    pub provider: AuthProviderKind,
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
@@ -23,8 +32,8 @@ pub struct AuthResult {
 }
 
 /// Supported authentication providers
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AuthProvider {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthProviderKind {
     Google,
     Microsoft,
     Salesforce,
@@ -35,6 +44,73 @@ pub enum AuthProvider {
     Custom(String),
 }
 
+impl AuthProviderKind {
+    /// Stable lowercase name, used in storage keys
+    pub fn key(&self) -> String {
+        match self {
+            Self::Custom(name) => name.to_lowercase(),
+            other => format!("{:?}", other).to_lowercase(),
+        }
+    }
+
+    /// Position in the enum, which earlier releases used in storage keys
+    /// instead of the name; custom providers never had one
+    fn legacy_key(&self) -> Option<u8> {
+        match self {
+            Self::Google => Some(0),
+            Self::Microsoft => Some(1),
+            Self::Salesforce => Some(2),
+            Self::Snowflake => Some(3),
+            Self::GitHub => Some(4),
+            Self::Auth0 => Some(5),
+            Self::Okta => Some(6),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+/// Per-login secrets: `state` identifies the login in the callback (the SSO
+/// handlers also set it in a cookie, tying the callback to the browser that
+/// started the login), `nonce` ties the ID token to the login and the PKCE
+/// verifier ties the code exchange to it
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    pub state: String,
+    pub nonce: String,
+    pub pkce_verifier: String,
+}
+
+impl AuthorizationRequest {
+    pub fn new() -> Result<Self, AuthError> {
+        Ok(Self {
+            state: random_token()?,
+            nonce: random_token()?,
+            pkce_verifier: random_token()?,
+        })
+    }
+
+    /// S256 code challenge sent with the authorization request (RFC 7636)
+    pub fn pkce_challenge(&self) -> String {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(self.pkce_verifier.as_bytes()))
+    }
+}
+
+/// 32 random bytes, base64url encoded
+fn random_token() -> Result<String, AuthError> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| AuthError::AuthenticationFailed("System random number generator failed".to_string()))?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// A login waiting for the provider to redirect back
+struct PendingLogin {
+    provider: String,
+    request: AuthorizationRequest,
+    started_at: DateTime<Utc>,
+}
+
 /// OAuth2 configuration for any provider
 #[derive(Debug, Clone)]
 pub struct OAuth2Config {
@@ -64,11 +140,17 @@ pub enum AuthError {
     JsonError(#[from] serde_json::Error),
 }
 
+impl From<kms::KmsError> for AuthError {
+    fn from(e: kms::KmsError) -> Self {
+        AuthError::KmsError(e.to_string())
+    }
+}
+
 /// Main authentication trait - implemented by all providers
 #[async_trait]
 pub trait AuthProvider: Send + Sync {
-    async fn get_auth_url(&self, state: &str) -> Result<String, AuthError>;
-    async fn exchange_code(&self, code: &str, state: &str) -> Result<AuthResult, AuthError>;
+    async fn get_auth_url(&self, request: &AuthorizationRequest) -> Result<String, AuthError>;
+    async fn exchange_code(&self, code: &str, request: &AuthorizationRequest) -> Result<AuthResult, AuthError>;
     async fn refresh_token(&self, refresh_token: &str) -> Result<AuthResult, AuthError>;
     async fn validate_token(&self, token: &str) -> Result<AuthResult, AuthError>;
     async fn revoke_token(&self, token: &str) -> Result<(), AuthError>;
@@ -79,6 +161,7 @@ pub struct AuthManager {
     providers: HashMap<String, Box<dyn AuthProvider>>,
     kms: Box<dyn kms::KmsProvider>,
     default_provider: Option<String>,
+    pending: Mutex<HashMap<String, PendingLogin>>,
 }
 
 impl AuthManager {
@@ -87,6 +170,7 @@ impl AuthManager {
             providers: HashMap::new(),
             kms,
             default_provider: None,
+            pending: Mutex::new(HashMap::new()),
         }
     }
 
@@ -100,20 +184,51 @@ impl AuthManager {
         self.default_provider = Some(name);
     }
 
-    /// Get authentication URL for a specific provider
-    pub async fn get_auth_url(&self, provider: &str, state: &str) -> Result<String, AuthError> {
-        let provider = self.providers.get(provider)
-            .ok_or_else(|| AuthError::ProviderNotConfigured(provider.to_string()))?;
-        
-        provider.get_auth_url(state).await
+    pub fn default_provider(&self) -> Option<&str> {
+        self.default_provider.as_deref()
     }
 
-    /// Exchange authorization code for tokens
-    pub async fn exchange_code(&self, provider: &str, code: &str, state: &str) -> Result<AuthResult, AuthError> {
-        let provider = self.providers.get(provider)
-            .ok_or_else(|| AuthError::ProviderNotConfigured(provider.to_string()))?;
-        
-        provider.exchange_code(code, state).await
+    /// Start a login: returns the provider URL to send the browser to and the
+    /// login's `state`, which the caller binds to the browser
+    pub async fn begin_login(&self, provider_name: &str) -> Result<(String, String), AuthError> {
+        let provider = self.providers.get(provider_name)
+            .ok_or_else(|| AuthError::ProviderNotConfigured(provider_name.to_string()))?;
+
+        let request = AuthorizationRequest::new()?;
+        let url = provider.get_auth_url(&request).await?;
+        let state = request.state.clone();
+
+        let mut pending = self.pending.lock().unwrap();
+        let cutoff = Utc::now() - Duration::minutes(LOGIN_TIMEOUT_MINUTES);
+        pending.retain(|_, login| login.started_at > cutoff);
+        pending.insert(request.state.clone(), PendingLogin {
+            provider: provider_name.to_string(),
+            request,
+            started_at: Utc::now(),
+        });
+        Ok((url, state))
+    }
+
+    /// Finish a login from the provider's callback and keep its tokens in the KMS
+    ///
+    /// Each `state` can be used once, only with the provider that issued it and
+    /// only within the login timeout.
+    pub async fn complete_login(&self, provider_name: &str, code: &str, state: &str) -> Result<AuthResult, AuthError> {
+        let login = self.pending.lock().unwrap().remove(state)
+            .ok_or_else(|| AuthError::TokenValidationFailed("Unknown or already used login state".to_string()))?;
+        if login.provider != provider_name {
+            return Err(AuthError::TokenValidationFailed("Login state belongs to another provider".to_string()));
+        }
+        if Utc::now() - login.started_at > Duration::minutes(LOGIN_TIMEOUT_MINUTES) {
+            return Err(AuthError::TokenValidationFailed("Login timed out".to_string()));
+        }
+
+        let provider = self.providers.get(provider_name)
+            .ok_or_else(|| AuthError::ProviderNotConfigured(provider_name.to_string()))?;
+        let result = provider.exchange_code(code, &login.request).await?;
+
+        self.store_credentials(&result.user_id, &result).await?;
+        Ok(result)
     }
 
     /// Validate and refresh tokens as needed
@@ -142,7 +257,7 @@ impl AuthManager {
 
     /// Securely store credentials using KMS
     pub async fn store_credentials(&self, user_id: &str, auth_result: &AuthResult) -> Result<(), AuthError> {
-        let key = format!("auth:{}:{}", auth_result.provider as u8, user_id);
+        let key = format!("auth:{}:{}", auth_result.provider.key(), user_id);
         let data = serde_json::to_string(auth_result)?;
         
         self.kms.encrypt_and_store(&key, &data).await
@@ -150,62 +265,84 @@ impl AuthManager {
     }
 
     /// Retrieve credentials from KMS
-    pub async fn retrieve_credentials(&self, user_id: &str, provider: AuthProvider) -> Result<AuthResult, AuthError> {
-        let key = format!("auth:{}:{}", provider as u8, user_id);
-        
-        let data = self.kms.retrieve_and_decrypt(&key).await
-            .map_err(|e| AuthError::KmsError(e.to_string()))?;
-        
+    ///
+    /// Credentials stored under the old numeric key are moved to the named key
+    /// the first time they are read.
+    pub async fn retrieve_credentials(&self, user_id: &str, provider: AuthProviderKind) -> Result<AuthResult, AuthError> {
+        let key = format!("auth:{}:{}", provider.key(), user_id);
+
+        let data = match self.kms.retrieve_and_decrypt(&key).await {
+            Ok(data) => data,
+            Err(kms::KmsError::KeyNotFound(_)) if provider.legacy_key().is_some() => {
+                self.claim_legacy_credentials(&key, user_id, &provider).await?
+            }
+            Err(e) => return Err(AuthError::KmsError(e.to_string())),
+        };
+
         serde_json::from_str(&data).map_err(AuthError::JsonError)
     }
+
+    async fn claim_legacy_credentials(&self, key: &str, user_id: &str, provider: &AuthProviderKind) -> Result<String, AuthError> {
+        let legacy = format!("auth:{}:{}", provider.legacy_key().unwrap_or_default(), user_id);
+        let data = self.kms.retrieve_and_decrypt(&legacy).await
+            .map_err(|e| AuthError::KmsError(e.to_string()))?;
+
+        self.kms.encrypt_and_store(key, &data).await
+            .map_err(|e| AuthError::KmsError(e.to_string()))?;
+        if let Err(e) = self.kms.delete_key(&legacy).await {
+            tracing::warn!("Failed to remove credentials under old key {}: {}", legacy, e);
+        }
+        tracing::info!("Moved stored {} credentials for {} to {}", provider.key(), user_id, key);
+        Ok(data)
+    }
 }
 
 /// Configuration builder for easy setup
 pub struct AuthConfigBuilder {
     manager: AuthManager,
+    redirect_base: String,
 }
 
 impl AuthConfigBuilder {
     pub fn new(kms: Box<dyn kms::KmsProvider>) -> Self {
         Self {
             manager: AuthManager::new(kms),
+            redirect_base: "http://localhost:8000".to_string(),
         }
     }
 
-    /// Add Google OAuth2 provider
-    pub fn with_google(mut self, client_id: String, client_secret: String) -> Self {
-        let config = OAuth2Config {
-            client_id,
-            client_secret,
-            auth_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
-            token_url: "https://oauth2.googleapis.com/token".to_string(),
-            redirect_uri: "http://localhost:8000/auth/google/callback".to_string(),
-            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
-            additional_params: HashMap::new(),
-        };
-        
-        let provider = Box::new(providers::GoogleProvider::new(config));
-        self.manager.register_provider("google".to_string(), provider);
+    /// Public base URL of this server; providers redirect to `{base}/auth/{name}/callback`
+    pub fn with_redirect_base(mut self, base: &str) -> Self {
+        self.redirect_base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    fn redirect_uri(&self, name: &str) -> String {
+        format!("{}/auth/{}/callback", self.redirect_base, name)
+    }
+
+    /// Add any OpenID Connect provider; its redirect URI is taken from the builder
+    pub fn with_oidc(mut self, name: &str, config: oidc::OidcConfig) -> Self {
+        let config = config.with_redirect_uri(self.redirect_uri(name));
+        self.manager.register_provider(name.to_string(), Box::new(oidc::OidcProvider::new(config)));
         self
     }
 
+    /// Add Google OAuth2 provider
+    pub fn with_google(self, client_id: String, client_secret: String) -> Self {
+        let config = oidc::OidcConfig::new("https://accounts.google.com", &client_id)
+            .with_client_secret(client_secret)
+            .with_kind(AuthProviderKind::Google);
+        self.with_oidc("google", config)
+    }
+
     /// Add Salesforce OAuth2 provider
-    pub fn with_salesforce(mut self, client_id: String, client_secret: String, domain: Option<String>) -> Self {
+    pub fn with_salesforce(self, client_id: String, client_secret: String, domain: Option<String>) -> Self {
         let base_url = domain.unwrap_or_else(|| "https://login.salesforce.com".to_string());
-        
-        let config = OAuth2Config {
-            client_id,
-            client_secret,
-            auth_url: format!("{}/services/oauth2/authorize", base_url),
-            token_url: format!("{}/services/oauth2/token", base_url),
-            redirect_uri: "http://localhost:8000/auth/salesforce/callback".to_string(),
-            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
-            additional_params: HashMap::new(),
-        };
-        
-        let provider = Box::new(providers::SalesforceProvider::new(config));
-        self.manager.register_provider("salesforce".to_string(), provider);
-        self
+        let config = oidc::OidcConfig::new(&base_url, &client_id)
+            .with_client_secret(client_secret)
+            .with_kind(AuthProviderKind::Salesforce);
+        self.with_oidc("salesforce", config)
     }
 
     /// Add Snowflake OAuth2 provider
@@ -215,33 +352,32 @@ impl AuthConfigBuilder {
             client_secret,
             auth_url: format!("{}/oauth/authorize", account_url),
             token_url: format!("{}/oauth/token-request", account_url),
-            redirect_uri: "http://localhost:8000/auth/snowflake/callback".to_string(),
+            redirect_uri: self.redirect_uri("snowflake"),
             scopes: vec!["session:role-any".to_string()],
             additional_params: HashMap::new(),
         };
-        
-        let provider = Box::new(providers::SnowflakeProvider::new(config));
+
+        let provider = Box::new(providers::OAuth2Provider::new(AuthProviderKind::Snowflake, config));
         self.manager.register_provider("snowflake".to_string(), provider);
         self
     }
 
-    /// Add Microsoft/Azure AD provider
-    pub fn with_microsoft(mut self, client_id: String, client_secret: String, tenant_id: Option<String>) -> Self {
+    /// Add Microsoft Entra ID (Azure AD) single sign-on
+    ///
+    /// Pass the firm's tenant ID. Without one the `common` authority is used,
+    /// which only admits sign-ins from the tenants in `allowed_tenants`.
+    pub fn with_microsoft(
+        self,
+        client_id: String,
+        client_secret: String,
+        tenant_id: Option<String>,
+        allowed_tenants: Vec<String>,
+    ) -> Self {
         let tenant = tenant_id.unwrap_or_else(|| "common".to_string());
-        
-        let config = OAuth2Config {
-            client_id,
-            client_secret,
-            auth_url: format!("https://login.microsoftonline.com/{}/oauth2/v2.0/authorize", tenant),
-            token_url: format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", tenant),
-            redirect_uri: "http://localhost:8000/auth/microsoft/callback".to_string(),
-            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string(), "User.Read".to_string()],
-            additional_params: HashMap::new(),
-        };
-        
-        let provider = Box::new(providers::MicrosoftProvider::new(config));
-        self.manager.register_provider("microsoft".to_string(), provider);
-        self
+        let config = oidc::OidcConfig::entra_id(&tenant, &client_id)
+            .with_client_secret(client_secret)
+            .with_allowed_tenants(allowed_tenants);
+        self.with_oidc("microsoft", config)
     }
 
     /// Add GitHub OAuth2 provider
@@ -251,12 +387,15 @@ impl AuthConfigBuilder {
             client_secret,
             auth_url: "https://github.com/login/oauth/authorize".to_string(),
             token_url: "https://github.com/login/oauth/access_token".to_string(),
-            redirect_uri: "http://localhost:8000/auth/github/callback".to_string(),
+            redirect_uri: self.redirect_uri("github"),
             scopes: vec!["user:email".to_string(), "read:user".to_string()],
             additional_params: HashMap::new(),
         };
-        
-        let provider = Box::new(providers::GitHubProvider::new(config));
+
+        let provider = Box::new(
+            providers::OAuth2Provider::new(AuthProviderKind::GitHub, config)
+                .with_user_info_url("https://api.github.com/user"),
+        );
         self.manager.register_provider("github".to_string(), provider);
         self
     }
@@ -278,6 +417,10 @@ pub async fn from_env() -> Result<AuthManager, AuthError> {
     let kms = kms::create_kms_from_env().await?;
     let mut builder = AuthConfigBuilder::new(kms);
 
+    if let Ok(base) = std::env::var("AUTH_REDIRECT_BASE") {
+        builder = builder.with_redirect_base(&base);
+    }
+
     // Google OAuth2
     if let (Ok(client_id), Ok(client_secret)) = (
         std::env::var("GOOGLE_CLIENT_ID"),
@@ -304,13 +447,16 @@ pub async fn from_env() -> Result<AuthManager, AuthError> {
         builder = builder.with_snowflake(client_id, client_secret, account_url);
     }
 
-    // Microsoft OAuth2
+    // Microsoft Entra ID
     if let (Ok(client_id), Ok(client_secret)) = (
         std::env::var("MICROSOFT_CLIENT_ID"),
         std::env::var("MICROSOFT_CLIENT_SECRET")
     ) {
         let tenant_id = std::env::var("MICROSOFT_TENANT_ID").ok();
-        builder = builder.with_microsoft(client_id, client_secret, tenant_id);
+        let allowed_tenants = std::env::var("MICROSOFT_ALLOWED_TENANTS")
+            .map(|tenants| tenants.split(',').map(|tenant| tenant.trim().to_string()).filter(|t| !t.is_empty()).collect())
+            .unwrap_or_default();
+        builder = builder.with_microsoft(client_id, client_secret, tenant_id, allowed_tenants);
     }
 
     // GitHub OAuth2
@@ -321,6 +467,11 @@ pub async fn from_env() -> Result<AuthManager, AuthError> {
         builder = builder.with_github(client_id, client_secret);
     }
 
+    // Any other OpenID Connect provider
+    if let Some((name, config)) = oidc::OidcConfig::from_env() {
+        builder = builder.with_oidc(&name, config);
+    }
+
     // Set default provider
     if let Ok(default) = std::env::var("DEFAULT_AUTH_PROVIDER") {
         builder = builder.with_default_provider(&default);
@@ -328,3 +479,56 @@ pub async fn from_env() -> Result<AuthManager, AuthError> {
 
     Ok(builder.build())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::kms::KmsProvider;
+    use super::*;
+
+    #[test]
+    fn test_pkce_challenge_matches_rfc_7636_example() {
+        let request = AuthorizationRequest {
+            state: String::new(),
+            nonce: String::new(),
+            pkce_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+        };
+        assert_eq!(request.pkce_challenge(), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[test]
+    fn test_authorization_requests_are_unguessable() {
+        let a = AuthorizationRequest::new().unwrap();
+        let b = AuthorizationRequest::new().unwrap();
+        assert_ne!(a.state, b.state);
+        assert_ne!(a.nonce, a.state);
+        assert_eq!(a.pkce_verifier.len(), 43);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_under_the_numeric_key_are_moved_on_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let kdf = kms::KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let kms = kms::LocalKmsProvider::open_with_kdf(dir.path().to_path_buf(), kms::KeystoreSecret::passphrase("test"), kdf)
+            .await
+            .unwrap();
+        let stored = AuthResult {
+            user_id: "jane".to_string(),
+            email: "jane@firm.example".to_string(),
+            display_name: "Jane".to_string(),
+            provider: AuthProviderKind::Microsoft,
+            access_token: "access".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: None,
+            scopes: Vec::new(),
+            metadata: HashMap::new(),
+        };
+        kms.encrypt_and_store("auth:1:jane", &serde_json::to_string(&stored).unwrap()).await.unwrap();
+        let manager = AuthManager::new(Box::new(kms));
+
+        let found = manager.retrieve_credentials("jane", AuthProviderKind::Microsoft).await.unwrap();
+        assert_eq!(found.refresh_token.as_deref(), Some("refresh"));
+        assert!(manager.kms.key_exists("auth:microsoft:jane").await.unwrap());
+        assert!(!manager.kms.key_exists("auth:1:jane").await.unwrap());
+        assert!(manager.retrieve_credentials("jane", AuthProviderKind::Google).await.is_err());
+    }
+}
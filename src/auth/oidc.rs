@@ -0,0 +1,693 @@
+//! OpenID Connect sign-in for any compliant identity provider
+//!
+//! The provider is configured with just an issuer and client ID; endpoints come
+//! from the issuer's discovery document. Logins use the authorization-code flow
+//! with PKCE, and the returned ID token is only trusted once its signature checks
+//! out against the issuer's JWKS (RS256 or ES256) and its issuer, audience,
+//! lifetime and nonce match what this login asked for. Microsoft Entra ID is the
+//! same flow with a tenant-specific issuer, see `OidcConfig::entra_id`.
+//!
+//! Only the `email` claim is ever used to match an account, and only when the
+//! provider marks it verified; `preferred_username` and `upn` are display names
+//! that users or other tenants can set to anything.
+
+use super::providers::{revoke, with_query, TokenResponse};
+use super::{AuthError, AuthProvider, AuthProviderKind, AuthResult, AuthorizationRequest};
+use async_trait::async_trait;
+use base64::Engine as _;
+use chrono::Utc;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+
+/// Placeholder Entra ID puts in the issuer of its multi-tenant metadata
+const TENANT_PLACEHOLDER: &str = "{tenantid}";
+
+/// Multi-tenant Entra ID authorities that publish a templated issuer
+const MULTI_TENANT_AUTHORITIES: &[&str] = &["common", "organizations", "consumers"];
+
+/// Settings for one OpenID Connect client registration
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Confidential clients only; public clients rely on PKCE alone
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub kind: AuthProviderKind,
+    /// Clock skew tolerated when checking `exp`, `iat` and `nbf`
+    pub leeway: chrono::Duration,
+    /// Minimum time between JWKS fetches triggered by an unknown key ID
+    pub jwks_refresh_interval: Duration,
+    /// Tenants (`tid`) admitted through a multi-tenant issuer; with none, the
+    /// issuer admits nobody
+    pub allowed_tenants: Vec<String>,
+}
+
+impl OidcConfig {
+    pub fn new(issuer: &str, client_id: &str) -> Self {
+        Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            redirect_uri: String::new(),
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+            kind: AuthProviderKind::Custom("oidc".to_string()),
+            leeway: chrono::Duration::seconds(60),
+            jwks_refresh_interval: Duration::from_secs(60),
+            allowed_tenants: Vec::new(),
+        }
+    }
+
+    /// Microsoft Entra ID for one tenant (directory ID or verified domain)
+    pub fn entra_id(tenant: &str, client_id: &str) -> Self {
+        let mut config = Self::new(&format!("https://login.microsoftonline.com/{}/v2.0", tenant), client_id)
+            .with_kind(AuthProviderKind::Microsoft);
+        config.scopes.push("offline_access".to_string());
+        config
+    }
+
+    /// `OIDC_ISSUER` and `OIDC_CLIENT_ID` plus optional `OIDC_CLIENT_SECRET`,
+    /// `OIDC_SCOPES` and `OIDC_PROVIDER_NAME` (default `oidc`)
+    pub fn from_env() -> Option<(String, Self)> {
+        let issuer = std::env::var("OIDC_ISSUER").ok()?;
+        let client_id = std::env::var("OIDC_CLIENT_ID").ok()?;
+        let name = std::env::var("OIDC_PROVIDER_NAME").unwrap_or_else(|_| "oidc".to_string());
+
+        let mut config = Self::new(&issuer, &client_id).with_kind(AuthProviderKind::Custom(name.clone()));
+        if let Ok(secret) = std::env::var("OIDC_CLIENT_SECRET") {
+            config = config.with_client_secret(secret);
+        }
+        if let Ok(scopes) = std::env::var("OIDC_SCOPES") {
+            config = config.with_scopes(scopes.split_whitespace().map(str::to_string).collect());
+        }
+        Some((name, config))
+    }
+
+    pub fn with_client_secret(mut self, secret: String) -> Self {
+        self.client_secret = Some(secret);
+        self
+    }
+
+    pub fn with_redirect_uri(mut self, redirect_uri: String) -> Self {
+        self.redirect_uri = redirect_uri;
+        self
+    }
+
+    /// Requested scopes; `openid` is always included
+    pub fn with_scopes(mut self, mut scopes: Vec<String>) -> Self {
+        if !scopes.iter().any(|scope| scope == "openid") {
+            scopes.insert(0, "openid".to_string());
+        }
+        self.scopes = scopes;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: AuthProviderKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_jwks_refresh_interval(mut self, interval: Duration) -> Self {
+        self.jwks_refresh_interval = interval;
+        self
+    }
+
+    pub fn with_allowed_tenants(mut self, tenants: Vec<String>) -> Self {
+        self.allowed_tenants = tenants;
+        self
+    }
+
+    /// Whether the discovered `issuer` belongs to this configuration
+    fn accepts_metadata_issuer(&self, issuer: &str) -> bool {
+        let issuer = issuer.trim_end_matches('/');
+        issuer == self.issuer
+            || (issuer.contains(TENANT_PLACEHOLDER)
+                && MULTI_TENANT_AUTHORITIES
+                    .iter()
+                    .any(|authority| issuer.replace(TENANT_PLACEHOLDER, authority) == self.issuer))
+    }
+}
+
+/// The parts of the discovery document this client uses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub userinfo_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+}
+
+/// A public key from the issuer's JWKS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+
+    fn is_multiple(&self) -> bool {
+        matches!(self, Audience::Many(auds) if auds.len() > 1)
+    }
+}
+
+/// ID token claims, including the Entra ID tenant (`tid`) and object (`oid`) IDs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azp: Option<String>,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Default)]
+struct JwksCache {
+    keys: Vec<Jwk>,
+    fetched_at: Option<Instant>,
+}
+
+/// OpenID Connect relying party for one issuer
+pub struct OidcProvider {
+    config: OidcConfig,
+    client: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: Mutex<JwksCache>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+            jwks: Mutex::new(JwksCache::default()),
+        }
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// The issuer's discovery document, fetched on first use
+    pub async fn metadata(&self) -> Result<&ProviderMetadata, AuthError> {
+        self.metadata.get_or_try_init(|| self.discover()).await
+    }
+
+    async fn discover(&self) -> Result<ProviderMetadata, AuthError> {
+        if !is_secure_url(&self.config.issuer) {
+            return Err(AuthError::AuthenticationFailed(format!(
+                "Issuer {} must use https",
+                self.config.issuer
+            )));
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(AuthError::AuthenticationFailed(format!("Discovery at {} returned {}", url, response.status())));
+        }
+        let metadata: ProviderMetadata = response.json().await?;
+
+        if !self.config.accepts_metadata_issuer(&metadata.issuer) {
+            return Err(AuthError::AuthenticationFailed(format!(
+                "Discovery document names issuer {} instead of {}",
+                metadata.issuer, self.config.issuer
+            )));
+        }
+        for endpoint in [&metadata.authorization_endpoint, &metadata.token_endpoint, &metadata.jwks_uri] {
+            if !is_secure_url(endpoint) {
+                return Err(AuthError::AuthenticationFailed(format!("Endpoint {} must use https", endpoint)));
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Check an ID token's signature and claims; `nonce` is required for tokens
+    /// from a login and absent for tokens from a refresh
+    pub async fn verify_id_token(&self, token: &str, nonce: Option<&str>) -> Result<IdTokenClaims, AuthError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let [header, payload, signature] = parts[..] else {
+            return Err(invalid("ID token is not a signed JWT"));
+        };
+
+        let header: JwtHeader = serde_json::from_slice(&decode_segment(header)?)?;
+        if header.alg != "RS256" && header.alg != "ES256" {
+            return Err(invalid(&format!("ID token algorithm {} is not accepted", header.alg)));
+        }
+
+        let key = self.signing_key(&header).await?;
+        let message = &token.as_bytes()[..token.len() - signature.len() - 1];
+        verify_signature(&header.alg, &key, message, &decode_segment(signature)?)?;
+
+        let claims: IdTokenClaims = serde_json::from_slice(&decode_segment(payload)?)?;
+        let metadata = self.metadata().await?;
+        self.check_claims(&claims, &metadata.issuer, nonce)?;
+        Ok(claims)
+    }
+
+    fn check_claims(&self, claims: &IdTokenClaims, metadata_issuer: &str, nonce: Option<&str>) -> Result<(), AuthError> {
+        let expected_issuer = if metadata_issuer.contains(TENANT_PLACEHOLDER) {
+            // Any Entra ID tenant can mint tokens for a multi-tenant issuer
+            let tid = claims.tid.as_deref().ok_or_else(|| invalid("ID token has no tenant ID"))?;
+            if !self.config.allowed_tenants.iter().any(|allowed| allowed.eq_ignore_ascii_case(tid)) {
+                return Err(invalid(&format!("Tenant {} is not allowed to sign in", tid)));
+            }
+            metadata_issuer.replace(TENANT_PLACEHOLDER, tid)
+        } else {
+            metadata_issuer.to_string()
+        };
+        if claims.iss.trim_end_matches('/') != expected_issuer.trim_end_matches('/') {
+            return Err(invalid(&format!("ID token issuer {} is not {}", claims.iss, expected_issuer)));
+        }
+
+        if !claims.aud.contains(&self.config.client_id) {
+            return Err(invalid("ID token was issued to another client"));
+        }
+        if (claims.aud.is_multiple() || claims.azp.is_some())
+            && claims.azp.as_deref() != Some(self.config.client_id.as_str())
+        {
+            return Err(invalid("ID token authorized party is not this client"));
+        }
+
+        let now = Utc::now().timestamp();
+        let leeway = self.config.leeway.num_seconds();
+        if claims.exp + leeway < now {
+            return Err(invalid("ID token has expired"));
+        }
+        if claims.iat - leeway > now || claims.nbf.is_some_and(|nbf| nbf - leeway > now) {
+            return Err(invalid("ID token is not valid yet"));
+        }
+
+        if let Some(expected) = nonce {
+            if claims.nonce.as_deref() != Some(expected) {
+                return Err(invalid("ID token nonce does not match this login"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The JWKS key for a token header, refetching the set once when the key ID
+    /// is unknown (the issuer may have rotated keys) but no more often than
+    /// `jwks_refresh_interval`
+    async fn signing_key(&self, header: &JwtHeader) -> Result<Jwk, AuthError> {
+        let mut cache = self.jwks.lock().await;
+        if let Some(key) = find_key(&cache.keys, header) {
+            return Ok(key);
+        }
+
+        let stale = cache
+            .fetched_at
+            .is_none_or(|fetched_at| fetched_at.elapsed() >= self.config.jwks_refresh_interval);
+        if stale {
+            let metadata = self.metadata().await?;
+            let response = self.client.get(&metadata.jwks_uri).send().await?;
+            if !response.status().is_success() {
+                return Err(invalid(&format!("JWKS fetch returned {}", response.status())));
+            }
+            let set: JwkSet = response.json().await?;
+            cache.keys = set.keys;
+            cache.fetched_at = Some(Instant::now());
+        }
+
+        find_key(&cache.keys, header).ok_or_else(|| invalid("ID token was signed with an unknown key"))
+    }
+
+    async fn request_tokens(&self, params: &[(&str, &str)]) -> Result<TokenResponse, AuthError> {
+        let metadata = self.metadata().await?;
+        let mut form: Vec<(&str, &str)> = params.to_vec();
+        form.push(("client_id", &self.config.client_id));
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response = self
+            .client
+            .post(&metadata.token_endpoint)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await?;
+        TokenResponse::from_response(response).await
+    }
+
+    async fn user_info(&self, access_token: &str) -> Result<Value, AuthError> {
+        let metadata = self.metadata().await?;
+        let url = metadata
+            .userinfo_endpoint
+            .as_ref()
+            .ok_or_else(|| AuthError::TokenValidationFailed("Issuer has no userinfo endpoint".to_string()))?;
+        let response = self.client.get(url).bearer_auth(access_token).send().await?;
+        if !response.status().is_success() {
+            return Err(AuthError::TokenValidationFailed(format!("Userinfo returned {}", response.status())));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Build the result from ID token claims or a userinfo response
+    fn auth_result(&self, issuer: &str, profile: Value, tokens: &TokenResponse) -> Result<AuthResult, AuthError> {
+        let claim = |name: &str| profile.get(name).and_then(Value::as_str).map(str::to_string);
+        let subject = claim("sub").ok_or_else(|| invalid("Identity has no subject"))?;
+        let email = claim("email").unwrap_or_default();
+        let display_name = claim("name")
+            .or_else(|| claim("preferred_username"))
+            .or_else(|| claim("upn"))
+            .unwrap_or_else(|| email.clone());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("issuer".to_string(), Value::String(issuer.to_string()));
+        metadata.insert("subject".to_string(), Value::String(subject.clone()));
+        for name in ["tid", "oid", "email_verified"] {
+            if let Some(value) = profile.get(name) {
+                metadata.insert(name.to_string(), value.clone());
+            }
+        }
+
+        Ok(AuthResult {
+            user_id: subject,
+            email,
+            display_name,
+            provider: self.config.kind.clone(),
+            access_token: tokens.access_token.clone().unwrap_or_default(),
+            refresh_token: tokens.refresh_token.clone(),
+            expires_at: tokens.expires_at(),
+            scopes: tokens.scopes(&self.config.scopes),
+            metadata,
+        })
+    }
+
+    async fn id_token_result(&self, tokens: &TokenResponse, nonce: Option<&str>) -> Result<AuthResult, AuthError> {
+        let id_token = tokens
+            .id_token
+            .as_deref()
+            .ok_or_else(|| AuthError::AuthenticationFailed("Token response has no ID token".to_string()))?;
+        let claims = self.verify_id_token(id_token, nonce).await?;
+        let issuer = claims.iss.clone();
+        self.auth_result(&issuer, serde_json::to_value(claims)?, tokens)
+    }
+}
+
+#[async_trait]
+impl AuthProvider for OidcProvider {
+    async fn get_auth_url(&self, request: &AuthorizationRequest) -> Result<String, AuthError> {
+        let metadata = self.metadata().await?;
+        let challenge = request.pkce_challenge();
+        let scope = self.config.scopes.join(" ");
+        Ok(with_query(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.config.client_id),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("scope", &scope),
+                ("state", &request.state),
+                ("nonce", &request.nonce),
+                ("code_challenge", &challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        ))
+    }
+
+    async fn exchange_code(&self, code: &str, request: &AuthorizationRequest) -> Result<AuthResult, AuthError> {
+        let tokens = self
+            .request_tokens(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("code_verifier", &request.pkce_verifier),
+            ])
+            .await?;
+        self.id_token_result(&tokens, Some(&request.nonce)).await
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<AuthResult, AuthError> {
+        let tokens = self
+            .request_tokens(&[("grant_type", "refresh_token"), ("refresh_token", refresh_token)])
+            .await?;
+        if tokens.id_token.is_some() {
+            return self.id_token_result(&tokens, None).await;
+        }
+        let profile = self.user_info(tokens.access_token.as_deref().unwrap_or_default()).await?;
+        let issuer = self.metadata().await?.issuer.clone();
+        self.auth_result(&issuer, profile, &tokens)
+    }
+
+    async fn validate_token(&self, token: &str) -> Result<AuthResult, AuthError> {
+        let profile = self.user_info(token).await?;
+        let issuer = self.metadata().await?.issuer.clone();
+        let tokens = TokenResponse {
+            access_token: Some(token.to_string()),
+            refresh_token: None,
+            id_token: None,
+            expires_in: None,
+            scope: None,
+            username: None,
+            error: None,
+            error_description: None,
+        };
+        self.auth_result(&issuer, profile, &tokens)
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), AuthError> {
+        let metadata = self.metadata().await?;
+        let url = metadata.revocation_endpoint.as_ref().ok_or_else(|| {
+            AuthError::AuthenticationFailed(format!("{} does not support token revocation", self.config.issuer))
+        })?;
+        revoke(&self.client, url, token, &self.config.client_id, self.config.client_secret.as_deref()).await
+    }
+}
+
+/// https, or plain http to this machine for development and tests
+fn is_secure_url(url: &str) -> bool {
+    if url.starts_with("https://") {
+        return true;
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let host = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+fn find_key(keys: &[Jwk], header: &JwtHeader) -> Option<Jwk> {
+    let kty = if header.alg == "RS256" { "RSA" } else { "EC" };
+    let mut candidates = keys.iter().filter(|key| {
+        key.kty == kty
+            && key.alg.as_deref().is_none_or(|alg| alg == header.alg)
+            && key.key_use.as_deref().is_none_or(|key_use| key_use == "sig")
+    });
+    match &header.kid {
+        Some(kid) => candidates.find(|key| key.kid.as_deref() == Some(kid.as_str())).cloned(),
+        // Without a key ID the choice has to be unambiguous
+        None => match (candidates.next(), candidates.next()) {
+            (Some(key), None) => Some(key.clone()),
+            _ => None,
+        },
+    }
+}
+
+fn verify_signature(alg: &str, key: &Jwk, message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+    let verified = match alg {
+        "RS256" => {
+            let n = decode_segment(key.n.as_deref().ok_or_else(|| invalid("RSA key has no modulus"))?)?;
+            let e = decode_segment(key.e.as_deref().ok_or_else(|| invalid("RSA key has no exponent"))?)?;
+            RsaPublicKeyComponents { n: &n, e: &e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok()
+        }
+        "ES256" => {
+            if key.crv.as_deref() != Some("P-256") {
+                return Err(invalid("EC key is not on P-256"));
+            }
+            let x = decode_segment(key.x.as_deref().ok_or_else(|| invalid("EC key has no x"))?)?;
+            let y = decode_segment(key.y.as_deref().ok_or_else(|| invalid("EC key has no y"))?)?;
+            let point = [&[0x04][..], &x, &y].concat();
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                .verify(message, signature)
+                .is_ok()
+        }
+        _ => false,
+    };
+    if verified {
+        Ok(())
+    } else {
+        Err(invalid("ID token signature is invalid"))
+    }
+}
+
+fn decode_segment(segment: &str) -> Result<Vec<u8>, AuthError> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|_| invalid("Malformed base64url in token"))
+}
+
+fn invalid(reason: &str) -> AuthError {
+    AuthError::TokenValidationFailed(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_https_or_loopback_issuers_are_trusted() {
+        assert!(is_secure_url("https://login.microsoftonline.com/contoso.onmicrosoft.com/v2.0"));
+        assert!(is_secure_url("http://127.0.0.1:8123"));
+        assert!(is_secure_url("http://localhost/realms/firm"));
+        assert!(!is_secure_url("http://idp.example.com"));
+        assert!(!is_secure_url("http://localhost.evil.example"));
+    }
+
+    #[test]
+    fn test_entra_multi_tenant_metadata_matches_common_authority() {
+        let common = OidcConfig::entra_id("common", "client");
+        assert!(common.accepts_metadata_issuer("https://login.microsoftonline.com/{tenantid}/v2.0"));
+        assert!(!common.accepts_metadata_issuer("https://login.microsoftonline.com/other-tenant/v2.0"));
+
+        let firm = OidcConfig::entra_id("9188040d-6c67-4c5b-b112-36a304b66dad", "client");
+        assert!(!firm.accepts_metadata_issuer("https://login.microsoftonline.com/{tenantid}/v2.0"));
+        assert!(firm.scopes.contains(&"offline_access".to_string()));
+    }
+
+    fn entra_claims(tid: &str) -> IdTokenClaims {
+        let now = Utc::now().timestamp();
+        IdTokenClaims {
+            iss: format!("https://login.microsoftonline.com/{}/v2.0", tid),
+            sub: "subject".to_string(),
+            aud: Audience::One("client".to_string()),
+            azp: None,
+            exp: now + 600,
+            iat: now,
+            nbf: None,
+            nonce: None,
+            email: None,
+            email_verified: None,
+            preferred_username: Some("jane@firm.example".to_string()),
+            upn: None,
+            name: None,
+            tid: Some(tid.to_string()),
+            oid: None,
+        }
+    }
+
+    #[test]
+    fn test_multi_tenant_issuer_admits_only_allowed_tenants() {
+        let metadata_issuer = "https://login.microsoftonline.com/{tenantid}/v2.0";
+        let firm = "9188040d-6c67-4c5b-b112-36a304b66dad";
+        let open = OidcProvider::new(OidcConfig::entra_id("common", "client"));
+        assert!(open.check_claims(&entra_claims(firm), metadata_issuer, None).is_err());
+
+        let config = OidcConfig::entra_id("common", "client").with_allowed_tenants(vec![firm.to_string()]);
+        let allowlisted = OidcProvider::new(config);
+        assert!(allowlisted.check_claims(&entra_claims(firm), metadata_issuer, None).is_ok());
+        assert!(allowlisted.check_claims(&entra_claims("attacker-tenant"), metadata_issuer, None).is_err());
+    }
+
+    #[test]
+    fn test_preferred_username_is_never_the_email() {
+        let provider = OidcProvider::new(OidcConfig::entra_id("common", "client"));
+        let tokens: TokenResponse = serde_json::from_value(serde_json::json!({})).unwrap();
+        let claims = serde_json::to_value(entra_claims("tenant")).unwrap();
+        let result = provider.auth_result("https://login.microsoftonline.com/tenant/v2.0", claims, &tokens).unwrap();
+
+        assert_eq!(result.email, "");
+        assert_eq!(result.display_name, "jane@firm.example");
+        assert!(!result.metadata.contains_key("email_verified"));
+    }
+
+    #[test]
+    fn test_keys_are_chosen_by_kid_and_algorithm() {
+        let key = |kid: &str, kty: &str| Jwk {
+            kty: kty.to_string(),
+            kid: Some(kid.to_string()),
+            alg: None,
+            key_use: Some("sig".to_string()),
+            n: None,
+            e: None,
+            crv: None,
+            x: None,
+            y: None,
+        };
+        let keys = vec![key("a", "RSA"), key("b", "EC")];
+        let header = |alg: &str, kid: Option<&str>| JwtHeader { alg: alg.to_string(), kid: kid.map(str::to_string) };
+
+        assert_eq!(find_key(&keys, &header("ES256", Some("b"))).unwrap().kid.as_deref(), Some("b"));
+        assert!(find_key(&keys, &header("ES256", Some("a"))).is_none());
+        assert_eq!(find_key(&keys, &header("RS256", None)).unwrap().kid.as_deref(), Some("a"));
+        assert!(find_key(&[key("a", "EC"), key("b", "EC")], &header("ES256", None)).is_none());
+    }
+}
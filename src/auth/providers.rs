@@ -0,0 +1,271 @@
+//! Plain OAuth2 providers for services without OpenID Connect
+//!
+//! GitHub and Snowflake issue access tokens but no ID token, so the user is
+//! identified from a user-info endpoint or from the token response itself.
+//! Providers that speak OpenID Connect go through `super::oidc` instead.
+
+use super::{AuthError, AuthProvider, AuthProviderKind, AuthResult, AuthorizationRequest, OAuth2Config};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Some providers (GitHub) reject API calls without a User-Agent
+const USER_AGENT: &str = "moodbridge-rust";
+
+/// Token endpoint response shared by OAuth2 and OpenID Connect providers
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenResponse {
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+    pub expires_in: Option<i64>,
+    pub scope: Option<String>,
+    /// Snowflake names the signed-in user here
+    pub username: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl TokenResponse {
+    /// Read a token endpoint reply, turning OAuth2 error bodies into `AuthError`s
+    pub(crate) async fn from_response(response: reqwest::Response) -> Result<Self, AuthError> {
+        let status = response.status();
+        let body: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| AuthError::AuthenticationFailed(format!("Unreadable token response ({}): {}", status, e)))?;
+
+        if let Some(error) = &body.error {
+            let description = body.error_description.as_deref().unwrap_or_default();
+            return Err(AuthError::AuthenticationFailed(format!("{} {}", error, description).trim().to_string()));
+        }
+        if !status.is_success() || body.access_token.is_none() {
+            return Err(AuthError::AuthenticationFailed(format!("Token endpoint returned {}", status)));
+        }
+        Ok(body)
+    }
+
+    pub(crate) fn expires_at(&self) -> Option<chrono::DateTime<Utc>> {
+        self.expires_in.map(|seconds| Utc::now() + Duration::seconds(seconds))
+    }
+
+    pub(crate) fn scopes(&self, requested: &[String]) -> Vec<String> {
+        match &self.scope {
+            Some(scope) => scope.split([' ', ',']).filter(|s| !s.is_empty()).map(str::to_string).collect(),
+            None => requested.to_vec(),
+        }
+    }
+}
+
+/// Authorization-code provider with PKCE and an optional user-info endpoint
+pub struct OAuth2Provider {
+    kind: AuthProviderKind,
+    config: OAuth2Config,
+    user_info_url: Option<String>,
+    revocation_url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OAuth2Provider {
+    pub fn new(kind: AuthProviderKind, config: OAuth2Config) -> Self {
+        Self {
+            kind,
+            config,
+            user_info_url: None,
+            revocation_url: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Endpoint returning the signed-in user for an access token
+    pub fn with_user_info_url(mut self, url: &str) -> Self {
+        self.user_info_url = Some(url.to_string());
+        self
+    }
+
+    /// RFC 7009 token revocation endpoint
+    pub fn with_revocation_url(mut self, url: &str) -> Self {
+        self.revocation_url = Some(url.to_string());
+        self
+    }
+
+    async fn request_tokens(&self, params: &[(&str, &str)]) -> Result<TokenResponse, AuthError> {
+        let mut form: Vec<(&str, &str)> = params.to_vec();
+        form.push(("client_id", &self.config.client_id));
+        form.push(("client_secret", &self.config.client_secret));
+
+        let response = self
+            .client
+            .post(&self.config.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await?;
+        TokenResponse::from_response(response).await
+    }
+
+    async fn user_info(&self, access_token: &str) -> Result<Option<Value>, AuthError> {
+        let Some(url) = &self.user_info_url else {
+            return Ok(None);
+        };
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(access_token)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(AuthError::TokenValidationFailed(format!("User info returned {}", response.status())));
+        }
+        Ok(Some(response.json().await?))
+    }
+
+    async fn to_auth_result(&self, tokens: TokenResponse) -> Result<AuthResult, AuthError> {
+        let access_token = tokens.access_token.clone().unwrap_or_default();
+        let profile = self.user_info(&access_token).await?;
+
+        let (user_id, email, display_name) = match &profile {
+            Some(profile) => {
+                let user_id = match &profile["id"] {
+                    Value::Number(id) => id.to_string(),
+                    Value::String(id) => id.clone(),
+                    _ => profile["login"].as_str().unwrap_or_default().to_string(),
+                };
+                let email = profile["email"].as_str().unwrap_or_default().to_string();
+                let name = profile["name"].as_str().or(profile["login"].as_str()).unwrap_or(&email).to_string();
+                (user_id, email, name)
+            }
+            None => {
+                let username = tokens.username.clone().unwrap_or_default();
+                (username.clone(), String::new(), username)
+            }
+        };
+        if user_id.is_empty() {
+            return Err(AuthError::AuthenticationFailed("Provider did not identify the user".to_string()));
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("issuer".to_string(), Value::String(origin(&self.config.auth_url).to_string()));
+        metadata.insert("subject".to_string(), Value::String(user_id.clone()));
+        if let Some(profile) = profile {
+            metadata.insert("profile".to_string(), profile);
+        }
+
+        Ok(AuthResult {
+            user_id,
+            email,
+            display_name,
+            provider: self.kind.clone(),
+            access_token,
+            refresh_token: tokens.refresh_token.clone(),
+            expires_at: tokens.expires_at(),
+            scopes: tokens.scopes(&self.config.scopes),
+            metadata,
+        })
+    }
+}
+
+#[async_trait]
+impl AuthProvider for OAuth2Provider {
+    async fn get_auth_url(&self, request: &AuthorizationRequest) -> Result<String, AuthError> {
+        let challenge = request.pkce_challenge();
+        let scope = self.config.scopes.join(" ");
+        let mut params: Vec<(&str, &str)> = vec![
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", &self.config.redirect_uri),
+            ("scope", &scope),
+            ("state", &request.state),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        params.extend(self.config.additional_params.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        Ok(with_query(&self.config.auth_url, &params))
+    }
+
+    async fn exchange_code(&self, code: &str, request: &AuthorizationRequest) -> Result<AuthResult, AuthError> {
+        let tokens = self
+            .request_tokens(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("code_verifier", &request.pkce_verifier),
+            ])
+            .await?;
+        self.to_auth_result(tokens).await
+    }
+
+    async fn refresh_token(&self, refresh_token: &str) -> Result<AuthResult, AuthError> {
+        let tokens = self
+            .request_tokens(&[("grant_type", "refresh_token"), ("refresh_token", refresh_token)])
+            .await?;
+        self.to_auth_result(tokens).await
+    }
+
+    async fn validate_token(&self, token: &str) -> Result<AuthResult, AuthError> {
+        if self.user_info_url.is_none() {
+            return Err(AuthError::TokenValidationFailed(format!("{:?} tokens cannot be validated", self.kind)));
+        }
+        self.to_auth_result(TokenResponse {
+            access_token: Some(token.to_string()),
+            refresh_token: None,
+            id_token: None,
+            expires_in: None,
+            scope: None,
+            username: None,
+            error: None,
+            error_description: None,
+        })
+        .await
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<(), AuthError> {
+        let url = self.revocation_url.as_ref().ok_or_else(|| {
+            AuthError::AuthenticationFailed(format!("{:?} does not support token revocation", self.kind))
+        })?;
+        revoke(&self.client, url, token, &self.config.client_id, Some(&self.config.client_secret)).await
+    }
+}
+
+/// Scheme and host of a URL, which identifies the provider for account linking
+fn origin(url: &str) -> &str {
+    let host_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    match url[host_start..].find('/') {
+        Some(end) => &url[..host_start + end],
+        None => url,
+    }
+}
+
+/// `base?key=value&...` with each value percent-encoded
+pub(crate) fn with_query(base: &str, params: &[(&str, &str)]) -> String {
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect();
+    let separator = if base.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", base, separator, query.join("&"))
+}
+
+/// RFC 7009 revocation request
+pub(crate) async fn revoke(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+) -> Result<(), AuthError> {
+    let mut form = vec![("token", token), ("client_id", client_id)];
+    if let Some(secret) = client_secret {
+        form.push(("client_secret", secret));
+    }
+    let response = client.post(url).form(&form).send().await?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(AuthError::AuthenticationFailed(format!("Token revocation returned {}", response.status())))
+    }
+}
@@ -0,0 +1,589 @@
+//! HashiCorp Vault KMS provider
+//!
+//! Secrets live in a KV v2 engine with versioned reads and check-and-set writes.
+//! When a Transit key is configured, values are encrypted by Transit before they
+//! reach KV, so data keys never sit in KV in the clear. Authentication is a static
+//! token or AppRole; tokens are renewed before their lease runs out and AppRole
+//! logs in again when renewal is no longer possible.
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use super::kms::{KmsError, KmsProvider};
+
+/// Field of a KV secret holding a plain value
+const VALUE_FIELD: &str = "value";
+/// Field of a KV secret holding a Transit ciphertext
+const CIPHERTEXT_FIELD: &str = "ciphertext";
+
+/// How to authenticate to Vault
+#[derive(Debug, Clone)]
+pub enum VaultAuth {
+    Token(String),
+    AppRole {
+        role_id: String,
+        secret_id: String,
+        mount: String,
+    },
+}
+
+/// Connection settings for `HashiCorpVaultProvider`
+#[derive(Debug, Clone)]
+pub struct VaultConfig {
+    pub addr: String,
+    pub auth: VaultAuth,
+    pub namespace: Option<String>,
+    pub kv_mount: String,
+    pub transit_mount: String,
+    /// Transit key used to encrypt values before they are written to KV
+    pub transit_key: Option<String>,
+    pub timeout: Duration,
+}
+
+impl VaultConfig {
+    pub fn new(addr: impl Into<String>, auth: VaultAuth) -> Self {
+        Self {
+            addr: addr.into().trim_end_matches('/').to_string(),
+            auth,
+            namespace: None,
+            kv_mount: "secret".to_string(),
+            transit_mount: "transit".to_string(),
+            transit_key: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn with_kv_mount(mut self, mount: impl Into<String>) -> Self {
+        self.kv_mount = mount.into();
+        self
+    }
+
+    pub fn with_transit(mut self, mount: impl Into<String>, key: impl Into<String>) -> Self {
+        self.transit_mount = mount.into();
+        self.transit_key = Some(key.into());
+        self
+    }
+
+    /// Read settings from the environment; `None` when `VAULT_ADDR` is unset
+    ///
+    /// Uses `VAULT_TOKEN`, or AppRole via `VAULT_ROLE_ID`, `VAULT_SECRET_ID` and
+    /// `VAULT_APPROLE_MOUNT`. `VAULT_NAMESPACE`, `VAULT_MOUNT_PATH`,
+    /// `VAULT_TRANSIT_MOUNT` and `VAULT_TRANSIT_KEY` are optional.
+    pub fn from_env() -> Result<Option<Self>, KmsError> {
+        let addr = match std::env::var("VAULT_ADDR") {
+            Ok(addr) => addr,
+            Err(_) => return Ok(None),
+        };
+
+        let auth = match (
+            std::env::var("VAULT_ROLE_ID"),
+            std::env::var("VAULT_SECRET_ID"),
+            std::env::var("VAULT_TOKEN"),
+        ) {
+            (Ok(role_id), Ok(secret_id), _) => VaultAuth::AppRole {
+                role_id,
+                secret_id,
+                mount: std::env::var("VAULT_APPROLE_MOUNT").unwrap_or_else(|_| "approle".to_string()),
+            },
+            (_, _, Ok(token)) => VaultAuth::Token(token),
+            _ => {
+                return Err(KmsError::ConfigError(
+                    "VAULT_ADDR is set but neither VAULT_TOKEN nor VAULT_ROLE_ID/VAULT_SECRET_ID are".to_string(),
+                ))
+            }
+        };
+
+        let mut config = Self::new(addr, auth);
+        if let Ok(namespace) = std::env::var("VAULT_NAMESPACE") {
+            config = config.with_namespace(namespace);
+        }
+        if let Ok(mount) = std::env::var("VAULT_MOUNT_PATH") {
+            config = config.with_kv_mount(mount);
+        }
+        if let Ok(key) = std::env::var("VAULT_TRANSIT_KEY") {
+            let mount = std::env::var("VAULT_TRANSIT_MOUNT").unwrap_or_else(|_| "transit".to_string());
+            config = config.with_transit(mount, key);
+        }
+        Ok(Some(config))
+    }
+}
+
+/// A version of a KV v2 secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KvSecret {
+    pub data: Map<String, Value>,
+    pub version: u64,
+    pub created_time: Option<String>,
+}
+
+/// A data key from Transit: use `plaintext` locally, store `ciphertext`
+#[derive(Debug, Clone)]
+pub struct TransitDataKey {
+    pub plaintext: Vec<u8>,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    token: Option<String>,
+    renewable: bool,
+    lease: Duration,
+    /// `None` for tokens that never expire, such as a dev-mode root token
+    expires_at: Option<Instant>,
+    checked: bool,
+}
+
+impl TokenState {
+    /// Renew once less than a third of the lease is left
+    fn needs_refresh(&self) -> bool {
+        if self.token.is_none() || !self.checked {
+            return true;
+        }
+        match self.expires_at {
+            Some(expires_at) => expires_at.saturating_duration_since(Instant::now()) <= self.lease / 3,
+            None => false,
+        }
+    }
+
+    fn update(&mut self, auth: &Value) {
+        if let Some(token) = auth["client_token"].as_str() {
+            self.token = Some(token.to_string());
+        }
+        let lease = auth["lease_duration"].as_u64().or_else(|| auth["ttl"].as_u64()).unwrap_or(0);
+        self.renewable = auth["renewable"].as_bool().unwrap_or(false);
+        self.lease = Duration::from_secs(lease);
+        self.expires_at = (lease > 0).then(|| Instant::now() + self.lease);
+        self.checked = true;
+    }
+}
+
+/// HashiCorp Vault Provider
+pub struct HashiCorpVaultProvider {
+    config: VaultConfig,
+    client: reqwest::Client,
+    token: RwLock<TokenState>,
+}
+
+impl HashiCorpVaultProvider {
+    pub fn new(config: VaultConfig) -> Result<Self, KmsError> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| KmsError::ConfigError(format!("Failed to build Vault client: {}", e)))?;
+
+        let token = match &config.auth {
+            VaultAuth::Token(token) => TokenState {
+                token: Some(token.clone()),
+                ..TokenState::default()
+            },
+            VaultAuth::AppRole { .. } => TokenState::default(),
+        };
+
+        Ok(Self {
+            config,
+            client,
+            token: RwLock::new(token),
+        })
+    }
+
+    pub fn config(&self) -> &VaultConfig {
+        &self.config
+    }
+
+    /// Read a KV v2 secret, optionally at a specific version
+    pub async fn read_secret(&self, path: &str, version: Option<u64>) -> Result<KvSecret, KmsError> {
+        let mut api_path = format!("{}/data/{}", self.config.kv_mount, path);
+        if let Some(version) = version {
+            api_path.push_str(&format!("?version={}", version));
+        }
+
+        let (status, body) = self.request(Method::GET, &api_path, None).await?;
+        if status == StatusCode::NOT_FOUND {
+            return Err(KmsError::KeyNotFound(path.to_string()));
+        }
+        let body = expect_success(status, body, "read")?;
+
+        let data = &body["data"];
+        // Deleted and destroyed versions come back with null data
+        let secret_data = data["data"]
+            .as_object()
+            .cloned()
+            .ok_or_else(|| KmsError::KeyNotFound(path.to_string()))?;
+        Ok(KvSecret {
+            data: secret_data,
+            version: data["metadata"]["version"].as_u64().unwrap_or(0),
+            created_time: data["metadata"]["created_time"].as_str().map(str::to_string),
+        })
+    }
+
+    /// Write a KV v2 secret and return its new version
+    ///
+    /// With `cas`, the write only succeeds if the current version matches; `Some(0)`
+    /// only creates. A mismatch is reported as `KmsError::VersionConflict`.
+    pub async fn write_secret(
+        &self,
+        path: &str,
+        data: Map<String, Value>,
+        cas: Option<u64>,
+    ) -> Result<u64, KmsError> {
+        let mut payload = json!({ "data": data });
+        if let Some(cas) = cas {
+            payload["options"] = json!({ "cas": cas });
+        }
+
+        let api_path = format!("{}/data/{}", self.config.kv_mount, path);
+        let (status, body) = self.request(Method::POST, &api_path, Some(payload)).await?;
+        if status == StatusCode::BAD_REQUEST && vault_errors(&body).contains("check-and-set") {
+            return Err(KmsError::VersionConflict(format!(
+                "{} changed since version {}",
+                path,
+                cas.unwrap_or(0)
+            )));
+        }
+        let body = expect_success(status, body, "write")?;
+        Ok(body["data"]["version"].as_u64().unwrap_or(0))
+    }
+
+    /// Encrypt with the configured Transit key, returning a `vault:v…` ciphertext
+    pub async fn transit_encrypt(&self, plaintext: &[u8]) -> Result<String, KmsError> {
+        let api_path = format!("{}/encrypt/{}", self.config.transit_mount, self.transit_key()?);
+        let payload = json!({ "plaintext": base64::engine::general_purpose::STANDARD.encode(plaintext) });
+        let (status, body) = self.request(Method::POST, &api_path, Some(payload)).await?;
+        let body = expect_success(status, body, "encrypt").map_err(as_encryption_error)?;
+        body["data"]["ciphertext"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| KmsError::EncryptionFailed("Transit returned no ciphertext".to_string()))
+    }
+
+    pub async fn transit_decrypt(&self, ciphertext: &str) -> Result<Vec<u8>, KmsError> {
+        let api_path = format!("{}/decrypt/{}", self.config.transit_mount, self.transit_key()?);
+        let payload = json!({ "ciphertext": ciphertext });
+        let (status, body) = self.request(Method::POST, &api_path, Some(payload)).await?;
+        let body = expect_success(status, body, "decrypt").map_err(as_decryption_error)?;
+        let plaintext = body["data"]["plaintext"]
+            .as_str()
+            .ok_or_else(|| KmsError::DecryptionFailed("Transit returned no plaintext".to_string()))?;
+        base64::engine::general_purpose::STANDARD
+            .decode(plaintext)
+            .map_err(|e| KmsError::DecryptionFailed(format!("Transit returned invalid base64: {}", e)))
+    }
+
+    /// Have Transit generate a 256-bit data key
+    pub async fn generate_data_key(&self) -> Result<TransitDataKey, KmsError> {
+        let api_path = format!("{}/datakey/plaintext/{}", self.config.transit_mount, self.transit_key()?);
+        let payload = json!({ "bits": 256 });
+        let (status, body) = self.request(Method::POST, &api_path, Some(payload)).await?;
+        let body = expect_success(status, body, "generate data key").map_err(as_encryption_error)?;
+
+        let plaintext = body["data"]["plaintext"]
+            .as_str()
+            .and_then(|p| base64::engine::general_purpose::STANDARD.decode(p).ok())
+            .ok_or_else(|| KmsError::EncryptionFailed("Transit returned no data key".to_string()))?;
+        let ciphertext = body["data"]["ciphertext"]
+            .as_str()
+            .ok_or_else(|| KmsError::EncryptionFailed("Transit returned no wrapped data key".to_string()))?;
+        Ok(TransitDataKey {
+            plaintext,
+            ciphertext: ciphertext.to_string(),
+        })
+    }
+
+    /// Re-encrypt a ciphertext under the latest version of the Transit key
+    pub async fn transit_rewrap(&self, ciphertext: &str) -> Result<String, KmsError> {
+        let api_path = format!("{}/rewrap/{}", self.config.transit_mount, self.transit_key()?);
+        let payload = json!({ "ciphertext": ciphertext });
+        let (status, body) = self.request(Method::POST, &api_path, Some(payload)).await?;
+        let body = expect_success(status, body, "rewrap").map_err(as_encryption_error)?;
+        body["data"]["ciphertext"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| KmsError::EncryptionFailed("Transit returned no ciphertext".to_string()))
+    }
+
+    /// Renew the current token, logging in again with AppRole if renewal fails
+    pub async fn renew_token(&self) -> Result<(), KmsError> {
+        let mut state = self.token.write().await;
+        self.refresh(&mut state, true).await
+    }
+
+    /// KV fields for a stored value, Transit-encrypted when a Transit key is configured
+    async fn secret_fields(&self, data: &str) -> Result<Map<String, Value>, KmsError> {
+        let mut secret = Map::new();
+        if self.config.transit_key.is_some() {
+            let ciphertext = self.transit_encrypt(data.as_bytes()).await?;
+            secret.insert(CIPHERTEXT_FIELD.to_string(), Value::String(ciphertext));
+        } else {
+            secret.insert(VALUE_FIELD.to_string(), Value::String(data.to_string()));
+        }
+        Ok(secret)
+    }
+
+    fn transit_key(&self) -> Result<&str, KmsError> {
+        self.config
+            .transit_key
+            .as_deref()
+            .ok_or_else(|| KmsError::ConfigError("No Vault Transit key configured".to_string()))
+    }
+
+    async fn current_token(&self) -> Result<String, KmsError> {
+        {
+            let state = self.token.read().await;
+            if !state.needs_refresh() {
+                if let Some(token) = &state.token {
+                    return Ok(token.clone());
+                }
+            }
+        }
+
+        let mut state = self.token.write().await;
+        // Another task may have refreshed while we waited for the lock
+        if state.needs_refresh() {
+            self.refresh(&mut state, false).await?;
+        }
+        state
+            .token
+            .clone()
+            .ok_or_else(|| KmsError::ConfigError("No Vault token available".to_string()))
+    }
+
+    async fn refresh(&self, state: &mut TokenState, force: bool) -> Result<(), KmsError> {
+        if let Some(token) = state.token.clone() {
+            if !state.checked {
+                // Learn the lease of a token we were handed
+                let (status, body) = self.send(Method::GET, "auth/token/lookup-self", None, &token).await?;
+                if status.is_success() {
+                    state.update(&body["data"]);
+                    if !force && !state.needs_refresh() {
+                        return Ok(());
+                    }
+                }
+            }
+            if state.renewable || force {
+                let (status, body) = self
+                    .send(Method::POST, "auth/token/renew-self", Some(json!({})), &token)
+                    .await?;
+                if status.is_success() {
+                    state.update(&body["auth"]);
+                    return Ok(());
+                }
+                tracing::warn!("Vault token renewal failed with status {}", status);
+            }
+        }
+
+        match &self.config.auth {
+            VaultAuth::AppRole { role_id, secret_id, mount } => {
+                let payload = json!({ "role_id": role_id, "secret_id": secret_id });
+                let url = self.url(&format!("auth/{}/login", mount));
+                let mut request = self.client.post(&url).json(&payload);
+                if let Some(namespace) = &self.config.namespace {
+                    request = request.header("X-Vault-Namespace", namespace);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| KmsError::NetworkError(format!("Vault login failed: {}", e)))?;
+                let status = response.status();
+                let body: Value = response.json().await.unwrap_or(Value::Null);
+                let body = expect_success(status, body, "AppRole login")?;
+                state.update(&body["auth"]);
+                Ok(())
+            }
+            VaultAuth::Token(_) => {
+                let expired = state.expires_at.is_some_and(|expires_at| expires_at <= Instant::now());
+                if expired {
+                    Err(KmsError::ConfigError("Vault token expired and cannot be renewed".to_string()))
+                } else {
+                    // Keep using the token until it actually expires
+                    state.checked = true;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Send an authenticated request, logging in again once if Vault rejects the token
+    async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<(StatusCode, Value), KmsError> {
+        let token = self.current_token().await?;
+        let (status, response) = self.send(method.clone(), path, body.clone(), &token).await?;
+        if status != StatusCode::FORBIDDEN || !matches!(self.config.auth, VaultAuth::AppRole { .. }) {
+            return Ok((status, response));
+        }
+
+        {
+            let mut state = self.token.write().await;
+            // Skip the login if another task already replaced the rejected token
+            if state.token.as_deref() == Some(token.as_str()) {
+                state.token = None;
+                state.renewable = false;
+                self.refresh(&mut state, false).await?;
+            }
+        }
+        let token = self.current_token().await?;
+        self.send(method, path, body, &token).await
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+        token: &str,
+    ) -> Result<(StatusCode, Value), KmsError> {
+        let mut request = self
+            .client
+            .request(method, self.url(path))
+            .header("X-Vault-Token", token);
+        if let Some(namespace) = &self.config.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| KmsError::NetworkError(format!("Vault request failed: {}", e)))?;
+        let status = response.status();
+        // 204 responses have no body
+        let body = response.json::<Value>().await.unwrap_or(Value::Null);
+        Ok((status, body))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1/{}", self.config.addr, path)
+    }
+
+    /// Keys under `dir` in KV metadata, descending into sub-folders
+    async fn list_dir(&self, dir: &str, keys: &mut Vec<String>) -> Result<(), KmsError> {
+        let api_path = format!("{}/metadata/{}?list=true", self.config.kv_mount, dir);
+        let (status, body) = self.request(Method::GET, &api_path, None).await?;
+        if status == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        let body = expect_success(status, body, "list")?;
+
+        let entries: Vec<String> = body["data"]["keys"]
+            .as_array()
+            .map(|entries| entries.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        for entry in entries {
+            let full = format!("{}{}", dir, entry);
+            if full.ends_with('/') {
+                Box::pin(self.list_dir(&full, keys)).await?;
+            } else {
+                keys.push(full);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl KmsProvider for HashiCorpVaultProvider {
+    async fn encrypt_and_store(&self, key: &str, data: &str) -> Result<(), KmsError> {
+        let secret = self.secret_fields(data).await?;
+        self.write_secret(key, secret, None).await.map(|_| ())
+    }
+
+    /// Written with `cas: 0`, so Vault refuses it if any version exists
+    async fn create_if_absent(&self, key: &str, data: &str) -> Result<(), KmsError> {
+        let secret = self.secret_fields(data).await?;
+        self.write_secret(key, secret, Some(0)).await.map(|_| ())
+    }
+
+    async fn retrieve_and_decrypt(&self, key: &str) -> Result<String, KmsError> {
+        let secret = self.read_secret(key, None).await?;
+        if let Some(ciphertext) = secret.data.get(CIPHERTEXT_FIELD).and_then(Value::as_str) {
+            let plaintext = self.transit_decrypt(ciphertext).await?;
+            return String::from_utf8(plaintext)
+                .map_err(|_| KmsError::DecryptionFailed(format!("{} is not valid UTF-8", key)));
+        }
+        secret
+            .data
+            .get(VALUE_FIELD)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| KmsError::DecryptionFailed("Value not found in response".to_string()))
+    }
+
+    /// Removes every version and the metadata of the secret
+    async fn delete_key(&self, key: &str) -> Result<(), KmsError> {
+        let api_path = format!("{}/metadata/{}", self.config.kv_mount, key);
+        let (status, body) = self.request(Method::DELETE, &api_path, None).await?;
+        if status.is_success() || status == StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(KmsError::ConfigError(format!(
+                "Failed to delete key: {} {}",
+                status,
+                vault_errors(&body)
+            )))
+        }
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, KmsError> {
+        // Start listing from the deepest folder the prefix names
+        let dir = match prefix.rfind('/') {
+            Some(index) => &prefix[..=index],
+            None => "",
+        };
+        let mut keys = Vec::new();
+        self.list_dir(dir, &mut keys).await?;
+        keys.retain(|key| key.starts_with(prefix));
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn key_exists(&self, key: &str) -> Result<bool, KmsError> {
+        match self.read_secret(key, None).await {
+            Ok(_) => Ok(true),
+            Err(KmsError::KeyNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn vault_errors(body: &Value) -> String {
+    body["errors"]
+        .as_array()
+        .map(|errors| errors.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("; "))
+        .unwrap_or_default()
+}
+
+fn expect_success(status: StatusCode, body: Value, operation: &str) -> Result<Value, KmsError> {
+    if status.is_success() {
+        return Ok(body);
+    }
+    let message = format!("Vault {} returned {}: {}", operation, status, vault_errors(&body));
+    Err(match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => KmsError::ConfigError(message),
+        StatusCode::SERVICE_UNAVAILABLE => KmsError::NotAvailable(message),
+        _ => KmsError::NetworkError(message),
+    })
+}
+
+fn as_encryption_error(error: KmsError) -> KmsError {
+    match error {
+        KmsError::NetworkError(message) => KmsError::EncryptionFailed(message),
+        e => e,
+    }
+}
+
+fn as_decryption_error(error: KmsError) -> KmsError {
+    match error {
+        KmsError::NetworkError(message) => KmsError::DecryptionFailed(message),
+        e => e,
+    }
+}
@@ -0,0 +1,166 @@
+//! # MoodBridge Access Admin
+//!
+//! Manages the users, temporary grants and API tokens enforced by the access
+//! control middleware.
+//!
+//! Usage:
+//! - `cargo run --bin access_admin add-user <user_id> <email> <role> [--bar-number N] [--jurisdiction J] [--supervisor ID]`
+//! - `cargo run --bin access_admin token <user_id>` - Issue a bearer token
+//! - `cargo run --bin access_admin grant <user_id> <operation> <granted_by> <hours>`
+//! - `cargo run --bin access_admin revoke <user_id> <operation> <revoked_by>`
+//! - `cargo run --bin access_admin report` - Print the access control audit report
+//!
+//! The database is read from `DATABASE_URL` (default `data/main.db`).
+
+use chrono::Utc;
+use clap::{Arg, ArgMatches, Command};
+use moodbridge_rust::db::create_pool;
+use moodbridge_rust::legal::access_control::{AccessController, User, UserRole};
+use moodbridge_rust::legal::LegalOperationType;
+use std::collections::HashSet;
+use std::env;
+
+#[tokio::main]
+async fn main() {
+    let matches = Command::new("MoodBridge Access Admin")
+        .version("1.0")
+        .about("Manages users, temporary grants and API tokens")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add-user")
+                .about("Create or update a user")
+                .arg(Arg::new("user_id").required(true).index(1))
+                .arg(Arg::new("email").required(true).index(2))
+                .arg(Arg::new("role")
+                    .help("Attorney, Paralegal, LegalAssistant, Administrator, User or Guest")
+                    .required(true)
+                    .index(3))
+                .arg(Arg::new("bar-number").long("bar-number"))
+                .arg(Arg::new("jurisdiction").long("jurisdiction"))
+                .arg(Arg::new("supervisor").long("supervisor").help("Supervising attorney's user ID"))
+        )
+        .subcommand(
+            Command::new("token")
+                .about("Issue a bearer token for a user")
+                .arg(Arg::new("user_id").required(true).index(1))
+        )
+        .subcommand(
+            Command::new("grant")
+                .about("Temporarily grant an operation to a user")
+                .arg(Arg::new("user_id").required(true).index(1))
+                .arg(Arg::new("operation").help("e.g. AILegalAdvice").required(true).index(2))
+                .arg(Arg::new("granted_by").required(true).index(3))
+                .arg(Arg::new("hours").help("How long the grant lasts").required(true).index(4))
+        )
+        .subcommand(
+            Command::new("revoke")
+                .about("Revoke an operation and any grants for it")
+                .arg(Arg::new("user_id").required(true).index(1))
+                .arg(Arg::new("operation").required(true).index(2))
+                .arg(Arg::new("revoked_by").required(true).index(3))
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Print the access control audit report")
+        )
+        .get_matches();
+
+    let controller = match open_controller().await {
+        Ok(controller) => controller,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match matches.subcommand() {
+        Some(("add-user", sub_matches)) => add_user(&controller, sub_matches).await,
+        Some(("token", sub_matches)) => token(&controller, arg(sub_matches, "user_id")).await,
+        Some(("grant", sub_matches)) => grant(&controller, sub_matches).await,
+        Some(("revoke", sub_matches)) => revoke(&controller, sub_matches).await,
+        _ => controller.generate_access_report().await.map(|report| println!("{}", report)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn open_controller() -> Result<AccessController, String> {
+    let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data/main.db".to_string());
+    let pool = create_pool(&database_url)
+        .await
+        .map_err(|e| format!("Failed to open database {}: {}", database_url, e))?;
+    AccessController::open(pool).await
+}
+
+fn arg<'a>(matches: &'a ArgMatches, name: &str) -> &'a str {
+    matches.get_one::<String>(name).map(String::as_str).unwrap_or_default()
+}
+
+fn parse_name<T: serde::de::DeserializeOwned>(kind: &str, name: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| format!("Unknown {}: {}", kind, name))
+}
+
+async fn add_user(controller: &AccessController, matches: &ArgMatches) -> Result<(), String> {
+    let user_id = arg(matches, "user_id");
+    let role: UserRole = parse_name("role", arg(matches, "role"))?;
+    let existing = controller.get_user(user_id).await?;
+
+    controller
+        .add_user(User {
+            user_id: user_id.to_string(),
+            email: arg(matches, "email").to_string(),
+            role,
+            bar_number: matches.get_one::<String>("bar-number").cloned(),
+            jurisdiction: matches.get_one::<String>("jurisdiction").cloned(),
+            supervisor_id: matches.get_one::<String>("supervisor").cloned(),
+            permissions: existing.as_ref().map(|u| u.permissions.clone()).unwrap_or_else(HashSet::new),
+            created_at: existing.as_ref().map(|u| u.created_at).unwrap_or_else(Utc::now),
+            last_login: existing.as_ref().and_then(|u| u.last_login),
+            active: true,
+        })
+        .await?;
+    println!("✅ Saved user {}", user_id);
+    Ok(())
+}
+
+async fn token(controller: &AccessController, user_id: &str) -> Result<(), String> {
+    let token = controller.create_auth_token(user_id).await?;
+    println!("{}", token);
+    Ok(())
+}
+
+async fn grant(controller: &AccessController, matches: &ArgMatches) -> Result<(), String> {
+    let operation: LegalOperationType = parse_name("operation", arg(matches, "operation"))?;
+    let hours = arg(matches, "hours");
+    let hours: i64 = hours.parse().map_err(|_| format!("Invalid number of hours: {}", hours))?;
+
+    let grant = controller
+        .grant_temporary_permission(
+            arg(matches, "user_id"),
+            operation,
+            arg(matches, "granted_by"),
+            chrono::Duration::hours(hours),
+        )
+        .await?;
+    println!(
+        "✅ Granted {:?} to {} until {}",
+        grant.operation_type,
+        grant.user_id,
+        grant.expires_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    Ok(())
+}
+
+async fn revoke(controller: &AccessController, matches: &ArgMatches) -> Result<(), String> {
+    let operation: LegalOperationType = parse_name("operation", arg(matches, "operation"))?;
+    let user_id = arg(matches, "user_id");
+    controller
+        .revoke_permission(user_id, operation.clone(), arg(matches, "revoked_by"))
+        .await?;
+    println!("✅ Revoked {:?} from {}", operation, user_id);
+    Ok(())
+}
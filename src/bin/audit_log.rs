@@ -0,0 +1,105 @@
+//! # MoodBridge Audit Log Tool
+//!
+//! Verifies and maintains the tamper-evident legal audit trail.
+//!
+//! Usage:
+//! - `cargo run --bin audit_log verify` - Check the whole chain, exiting non-zero on tampering
+//! - `cargo run --bin audit_log checkpoint` - Sign the current chain head
+//! - `cargo run --bin audit_log archive <days>` - Seal entries older than `days` into segments
+//!
+//! The store is read from `AUDIT_LOG_DIR` (default `data/audit`) and signed with the
+//! hex key in `AUDIT_SIGNING_KEY`.
+
+use chrono::Utc;
+use clap::{Arg, Command};
+use moodbridge_rust::legal::audit_store::{audit_dir_from_env, signing_key_from_env, AuditStore};
+
+#[tokio::main]
+async fn main() {
+    let matches = Command::new("MoodBridge Audit Log")
+        .version("1.0")
+        .about("Verifies and maintains the hash-chained audit log")
+        .subcommand(
+            Command::new("verify")
+                .about("Detect modified, deleted or truncated audit entries")
+        )
+        .subcommand(
+            Command::new("checkpoint")
+                .about("Sign the current head of the chain")
+        )
+        .subcommand(
+            Command::new("archive")
+                .about("Seal entries older than the given number of days")
+                .arg(Arg::new("days")
+                    .help("Age in days of the newest entry to archive")
+                    .required(true)
+                    .index(1))
+        )
+        .get_matches();
+
+    let store = match open_store().await {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match matches.subcommand() {
+        Some(("verify", _)) => verify(&store).await,
+        Some(("checkpoint", _)) => checkpoint(&store).await,
+        Some(("archive", sub_matches)) => {
+            let days = sub_matches.get_one::<String>("days").unwrap();
+            match days.parse::<i64>() {
+                Ok(days) => archive(&store, days).await,
+                Err(_) => Err(format!("Invalid number of days: {}", days)),
+            }
+        }
+        _ => verify(&store).await,
+    };
+
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn open_store() -> Result<AuditStore, String> {
+    let key = signing_key_from_env()?;
+    AuditStore::open(audit_dir_from_env(), &key).await
+}
+
+async fn verify(store: &AuditStore) -> Result<(), String> {
+    let report = store.verify().await?;
+
+    println!("🔎 Audit log at {}", store.dir().display());
+    println!("   Entries checked:     {}", report.entries_checked);
+    println!("   Segments checked:    {}", report.segments_checked);
+    println!("   Checkpoints checked: {}", report.checkpoints_checked);
+    println!("   Chain head:          #{} {}", report.last_sequence, report.last_hash);
+
+    if report.is_intact() {
+        println!("✅ Audit trail is intact");
+        return Ok(());
+    }
+
+    for issue in &report.issues {
+        println!("   🚨 {}", issue);
+    }
+    Err(format!("{} integrity problem(s) found", report.issues.len()))
+}
+
+async fn checkpoint(store: &AuditStore) -> Result<(), String> {
+    match store.checkpoint().await? {
+        Some(checkpoint) => println!("✅ Checkpoint signed at #{} {}", checkpoint.sequence, checkpoint.hash),
+        None => println!("ℹ️  Audit log is empty; nothing to checkpoint"),
+    }
+    Ok(())
+}
+
+async fn archive(store: &AuditStore, days: i64) -> Result<(), String> {
+    let before = Utc::now() - chrono::Duration::days(days);
+    let archived = store.archive_before(before).await?;
+    println!("✅ Sealed {} entries logged before {}", archived, before.format("%Y-%m-%d"));
+    Ok(())
+}
@@ -402,6 +402,18 @@ impl LegalEthicsComplianceBot {
                 "Collaboration Metrics analyze team performance, which may raise employment law \
                 and privacy concerns for team members."
             }
+            LegalOperationType::AuditLogExport => {
+                "Audit Log Export releases the record of who did what with client data. Exports may \
+                be produced in discovery or to regulators and must stay complete and verifiable."
+            }
+            LegalOperationType::ExhibitDeletion => {
+                "Exhibit Deletion permanently removes evidence. Deleting material subject to a \
+                preservation duty can amount to spoliation."
+            }
+            LegalOperationType::RecordDisposition => {
+                "Record Disposition destroys files and records at the end of their retention period. \
+                Anything under a legal hold or otherwise relevant to pending litigation must be kept."
+            }
         };
 
         if matches!(profile.experience_level, ExperienceLevel::Novice) {
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use super::{AuditLevel, DatabaseConfig, DatabaseError, DbResult, WriteOp};
+
+/// Most recent events kept in memory
+const MAX_RECENT_EVENTS: usize = 10_000;
+
+/// Storage key prefix of persisted write events; `list_tables` skips keys starting "__"
+pub(super) const AUDIT_ENTRY_PREFIX: &str = "__audit:";
+
+/// Record access events for the encrypted store
+///
+/// Events go to the tracing log and a bounded in-memory buffer. `AuditLevel::Basic`
+/// records writes only; `AuditLevel::Full` also records reads and queries. Write
+/// events are also persisted through `entry_op`, in the same transaction as the write.
+pub struct AuditLogger {
+    level: AuditLevel,
+    recent: RwLock<VecDeque<AuditEvent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub table: String,
+    pub record_id: Option<Uuid>,
+    pub result_count: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditAction {
+    Insert,
+    Read,
+    Update,
+    Delete,
+    Query,
+}
+
+impl AuditAction {
+    fn is_write(self) -> bool {
+        matches!(self, Self::Insert | Self::Update | Self::Delete)
+    }
+}
+
+impl AuditLogger {
+    pub async fn new(config: &DatabaseConfig) -> DbResult<Self> {
+        Ok(Self {
+            level: config.audit_level,
+            recent: RwLock::new(VecDeque::new()),
+        })
+    }
+
+    pub async fn log_insert(&self, table: &str, id: &Uuid) -> DbResult<()> {
+        self.record(AuditAction::Insert, table, Some(*id), None).await
+    }
+
+    pub async fn log_read(&self, table: &str, id: &Uuid) -> DbResult<()> {
+        self.record(AuditAction::Read, table, Some(*id), None).await
+    }
+
+    pub async fn log_update(&self, table: &str, id: &Uuid) -> DbResult<()> {
+        self.record(AuditAction::Update, table, Some(*id), None).await
+    }
+
+    pub async fn log_delete(&self, table: &str, id: &Uuid) -> DbResult<()> {
+        self.record(AuditAction::Delete, table, Some(*id), None).await
+    }
+
+    pub async fn log_query(&self, table: &str, result_count: usize) -> DbResult<()> {
+        self.record(AuditAction::Query, table, None, Some(result_count)).await
+    }
+
+    /// Persisted entry for a write event, or `None` when the level doesn't record it
+    ///
+    /// The caller commits it with the write, so the store never holds a change
+    /// without its audit entry or an entry for a change that was rolled back.
+    pub fn entry_op(&self, action: AuditAction, table: &str, record_id: &Uuid) -> DbResult<Option<WriteOp>> {
+        if !action.is_write() || !self.enabled(action) {
+            return Ok(None);
+        }
+        let event = AuditEvent {
+            timestamp: Utc::now(),
+            action,
+            table: table.to_string(),
+            record_id: Some(*record_id),
+            result_count: None,
+        };
+        let key = format!(
+            "{}{}:{}",
+            AUDIT_ENTRY_PREFIX,
+            event.timestamp.format("%Y%m%dT%H%M%S%.9fZ"),
+            Uuid::new_v4()
+        );
+        let value = serde_json::to_vec(&event)
+            .map_err(|e| DatabaseError::Storage(format!("Audit serialization failed: {}", e)))?;
+        Ok(Some(WriteOp::Put { key, value }))
+    }
+
+    /// Recorded events, oldest first
+    pub async fn recent_events(&self) -> Vec<AuditEvent> {
+        self.recent.read().await.iter().cloned().collect()
+    }
+
+    async fn record(
+        &self,
+        action: AuditAction,
+        table: &str,
+        record_id: Option<Uuid>,
+        result_count: Option<usize>,
+    ) -> DbResult<()> {
+        if !self.enabled(action) {
+            return Ok(());
+        }
+
+        tracing::info!(
+            target: "crypto_db::audit",
+            ?action,
+            table,
+            record_id = ?record_id,
+            result_count = ?result_count,
+            "encrypted store access"
+        );
+
+        let mut recent = self.recent.write().await;
+        if recent.len() >= MAX_RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(AuditEvent {
+            timestamp: Utc::now(),
+            action,
+            table: table.to_string(),
+            record_id,
+            result_count,
+        });
+        Ok(())
+    }
+
+    fn enabled(&self, action: AuditAction) -> bool {
+        match self.level {
+            AuditLevel::None => false,
+            AuditLevel::Basic => action.is_write(),
+            AuditLevel::Full => true,
+        }
+    }
+}
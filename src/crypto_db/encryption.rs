@@ -1,18 +1,23 @@
 use ring::{aead, digest, hmac, pbkdf2, rand};
-use ring::rand::SecureRandom;
+// Imported anonymously: the `SecureRandom` helper below shares the trait's name
+use ring::rand::SecureRandom as _;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use zeroize::{Zeroize, ZeroizeOnDrop};
-use crate::auth::kms::KmsProvider;
+use crate::auth::kms::{KmsError, KmsProvider};
 use super::{DatabaseConfig, DatabaseError, DbResult, EncryptedData, EncryptionAlgorithm};
 
 /// Encryption manager for handling all cryptographic operations
 pub struct EncryptionManager {
     keys: Arc<RwLock<HashMap<String, DerivedKey>>>,
+    index_key: Arc<RwLock<Option<BlindIndexKey>>>,
+    master_version: Arc<RwLock<Option<u32>>>,
     kms: Arc<dyn KmsProvider>,
     config: DatabaseConfig,
-    rng: Arc<dyn SecureRandom>,
+    rng: Arc<dyn rand::SecureRandom + Send + Sync>,
 }
 
 /// A derived encryption key with metadata
@@ -27,8 +32,44 @@ struct DerivedKey {
     version: u32,
 }
 
-/// Key derivation parameters
+/// Key for blind (HMAC) indexes, held apart from the data encryption keys
+#[derive(Clone, ZeroizeOnDrop)]
+pub struct BlindIndexKey {
+    key: Vec<u8>,
+}
+
+/// Data encrypted under its own one-off data key, which is wrapped by a master key version
 #[derive(Debug, Clone)]
+pub struct SealedData {
+    pub data: EncryptedData,
+    pub wrapped_key: EncryptedData,
+    pub key_version: u32,
+}
+
+/// A master key version as held in KMS
+#[derive(Serialize, Deserialize)]
+struct StoredMasterKey {
+    key: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Outcome of creating a master key version that didn't succeed
+enum MasterKeyWrite {
+    /// The version was already in KMS
+    Exists,
+    Failed(DatabaseError),
+}
+
+impl From<DatabaseError> for MasterKeyWrite {
+    fn from(e: DatabaseError) -> Self {
+        Self::Failed(e)
+    }
+}
+
+/// Key derivation parameters
+///
+/// Not `Debug`: `pbkdf2::Algorithm` doesn't implement it.
+#[derive(Clone)]
 pub struct KeyDerivationParams {
     pub salt: Vec<u8>,
     pub iterations: u32,
@@ -39,43 +80,249 @@ impl EncryptionManager {
     pub async fn new(config: &DatabaseConfig) -> DbResult<Self> {
         let kms = crate::auth::kms::create_kms_from_env().await
             .map_err(|e| DatabaseError::Encryption(format!("KMS initialization failed: {}", e)))?;
+        Ok(Self::with_kms(config, Arc::from(kms)))
+    }
 
-        let rng = Arc::new(rand::SystemRandom::new());
-        
-        Ok(Self {
+    /// Build a manager around an already-opened KMS
+    pub fn with_kms(config: &DatabaseConfig, kms: Arc<dyn KmsProvider>) -> Self {
+        Self {
             keys: Arc::new(RwLock::new(HashMap::new())),
+            index_key: Arc::new(RwLock::new(None)),
+            master_version: Arc::new(RwLock::new(None)),
             kms,
             config: config.clone(),
-            rng,
-        })
+            rng: Arc::new(rand::SystemRandom::new()),
+        }
     }
 
     /// Encrypt data using the configured algorithm
+    ///
+    /// The key is derived from the `master_key_id` entry in KMS, created on first use.
+    /// Prefer `seal`, which supports master key rotation.
     pub async fn encrypt(&self, plaintext: &[u8]) -> DbResult<EncryptedData> {
+        self.ensure_key(&self.config.master_key_id).await?;
         let key = self.get_or_derive_key(&self.config.master_key_id).await?;
-        
+        self.encrypt_with(plaintext, &key.key).await
+    }
+
+    /// Decrypt data using the configured algorithm
+    pub async fn decrypt(&self, encrypted: &EncryptedData) -> DbResult<Vec<u8>> {
+        let key = self.get_or_derive_key(&self.config.master_key_id).await?;
+        self.decrypt_with(encrypted, &key.key).await
+    }
+
+    async fn encrypt_with(&self, plaintext: &[u8], key: &[u8]) -> DbResult<EncryptedData> {
         match self.config.encryption_algorithm {
             EncryptionAlgorithm::ChaCha20Poly1305 => {
-                self.encrypt_chacha20_poly1305(plaintext, &key.key).await
+                self.encrypt_chacha20_poly1305(plaintext, key).await
             }
             EncryptionAlgorithm::Aes256Gcm => {
-                self.encrypt_aes256_gcm(plaintext, &key.key).await
+                self.encrypt_aes256_gcm(plaintext, key).await
             }
         }
     }
 
-    /// Decrypt data using the configured algorithm
-    pub async fn decrypt(&self, encrypted: &EncryptedData) -> DbResult<Vec<u8>> {
-        let key = self.get_or_derive_key(&self.config.master_key_id).await?;
-        
+    async fn decrypt_with(&self, encrypted: &EncryptedData, key: &[u8]) -> DbResult<Vec<u8>> {
         match self.config.encryption_algorithm {
             EncryptionAlgorithm::ChaCha20Poly1305 => {
-                self.decrypt_chacha20_poly1305(encrypted, &key.key).await
+                self.decrypt_chacha20_poly1305(encrypted, key).await
             }
             EncryptionAlgorithm::Aes256Gcm => {
-                self.decrypt_aes256_gcm(encrypted, &key.key).await
+                self.decrypt_aes256_gcm(encrypted, key).await
+            }
+        }
+    }
+
+    /// Envelope-encrypt data under a fresh data key wrapped by the current master key version
+    pub async fn seal(&self, plaintext: &[u8]) -> DbResult<SealedData> {
+        let key_version = self.current_key_version().await?;
+        let master = self.master_key(key_version).await?;
+
+        let mut data_key = vec![0u8; 32];
+        self.rng.fill(&mut data_key)
+            .map_err(|e| DatabaseError::Encryption(format!("Key generation failed: {:?}", e)))?;
+        let data = self.encrypt_with(plaintext, &data_key).await;
+        let wrapped_key = self.encrypt_with(&data_key, &master.key).await;
+        data_key.zeroize();
+
+        Ok(SealedData {
+            data: data?,
+            wrapped_key: wrapped_key?,
+            key_version,
+        })
+    }
+
+    /// Decrypt envelope-encrypted data with whichever master key version wrapped it
+    pub async fn open(&self, sealed: &SealedData) -> DbResult<Vec<u8>> {
+        let mut data_key = self.unwrap_data_key(&sealed.wrapped_key, sealed.key_version).await?;
+        let plaintext = self.decrypt_with(&sealed.data, &data_key).await;
+        data_key.zeroize();
+        plaintext
+    }
+
+    /// Wrap a data key again under the current master key version
+    ///
+    /// The data it protects is left as is, so re-wrapping costs the same for any record size.
+    pub async fn rewrap(&self, wrapped_key: &EncryptedData, key_version: u32) -> DbResult<(EncryptedData, u32)> {
+        let current = self.current_key_version().await?;
+        if key_version == current {
+            return Ok((wrapped_key.clone(), current));
+        }
+
+        let mut data_key = self.unwrap_data_key(wrapped_key, key_version).await?;
+        let master = self.master_key(current).await?;
+        let rewrapped = self.encrypt_with(&data_key, &master.key).await;
+        data_key.zeroize();
+        Ok((rewrapped?, current))
+    }
+
+    async fn unwrap_data_key(&self, wrapped_key: &EncryptedData, key_version: u32) -> DbResult<Vec<u8>> {
+        let master = self.master_key(key_version).await?;
+        self.decrypt_with(wrapped_key, &master.key).await
+    }
+
+    /// Version of the master key new data keys are wrapped with
+    ///
+    /// Without a stored current version, the highest version in KMS is used, and
+    /// version 1 is created only when there is none.
+    pub async fn current_key_version(&self) -> DbResult<u32> {
+        if let Some(version) = *self.master_version.read().await {
+            return Ok(version);
+        }
+
+        let mut current = self.master_version.write().await;
+        if let Some(version) = *current {
+            return Ok(version);
+        }
+        let version = match self.stored_key_version().await? {
+            Some(version) => version,
+            None => {
+                let version = match self.latest_master_key_version().await? {
+                    Some(version) => version,
+                    None => self.create_next_master_key().await?,
+                };
+                self.store_key_version(version).await?;
+                version
             }
+        };
+        *current = Some(version);
+        Ok(version)
+    }
+
+    /// Add a master key version and make it current
+    ///
+    /// Earlier versions stay in KMS so data keys they wrapped can still be opened
+    /// until they are re-wrapped. Rotations in this process are serialised by the
+    /// `master_version` lock; the new version is chosen from what KMS holds and
+    /// created only if absent, so a concurrent rotation elsewhere can't be overwritten.
+    pub async fn rotate_master_key(&self) -> DbResult<u32> {
+        let mut current = self.master_version.write().await;
+
+        let next = self.create_next_master_key().await?;
+        // Another process may have rotated past us; never move the pointer backwards
+        if self.stored_key_version().await?.is_none_or(|stored| stored < next) {
+            self.store_key_version(next).await?;
         }
+        *current = Some(next);
+        Ok(next)
+    }
+
+    /// When the current master key version was created
+    pub async fn key_created_at(&self) -> DbResult<chrono::DateTime<chrono::Utc>> {
+        let version = self.current_key_version().await?;
+        Ok(self.master_key(version).await?.created_at)
+    }
+
+    fn master_key_name(&self, version: u32) -> String {
+        format!("{}.v{}", self.config.master_key_id, version)
+    }
+
+    fn key_version_name(&self) -> String {
+        format!("{}.current", self.config.master_key_id)
+    }
+
+    async fn stored_key_version(&self) -> DbResult<Option<u32>> {
+        match self.kms.retrieve_and_decrypt(&self.key_version_name()).await {
+            Ok(version) => version.trim().parse().map(Some)
+                .map_err(|e| DatabaseError::Encryption(format!("Corrupt master key version: {}", e))),
+            Err(KmsError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(DatabaseError::Encryption(format!("KMS retrieval failed: {}", e))),
+        }
+    }
+
+    async fn store_key_version(&self, version: u32) -> DbResult<()> {
+        self.kms.encrypt_and_store(&self.key_version_name(), &version.to_string()).await
+            .map_err(|e| DatabaseError::Encryption(format!("KMS storage failed: {}", e)))
+    }
+
+    /// Highest master key version held in KMS
+    async fn latest_master_key_version(&self) -> DbResult<Option<u32>> {
+        let prefix = format!("{}.v", self.config.master_key_id);
+        let names = self.kms.list_keys(&prefix).await
+            .map_err(|e| DatabaseError::Encryption(format!("KMS listing failed: {}", e)))?;
+        Ok(names
+            .iter()
+            .filter_map(|name| name.strip_prefix(&prefix)?.parse::<u32>().ok())
+            .max())
+    }
+
+    /// Create the version after the highest one in KMS, retrying if another writer
+    /// claims it first
+    async fn create_next_master_key(&self) -> DbResult<u32> {
+        const MAX_ATTEMPTS: usize = 5;
+        for _ in 0..MAX_ATTEMPTS {
+            let next = self.latest_master_key_version().await?.unwrap_or(0) + 1;
+            match self.store_master_key(next).await {
+                Ok(()) => return Ok(next),
+                Err(MasterKeyWrite::Exists) => continue,
+                Err(MasterKeyWrite::Failed(e)) => return Err(e),
+            }
+        }
+        Err(DatabaseError::Conflict(
+            "Master key versions kept changing during rotation".to_string(),
+        ))
+    }
+
+    async fn store_master_key(&self, version: u32) -> Result<(), MasterKeyWrite> {
+        let mut key_bytes = vec![0u8; 32];
+        self.rng.fill(&mut key_bytes)
+            .map_err(|e| DatabaseError::Encryption(format!("Key generation failed: {:?}", e)))?;
+        let stored = StoredMasterKey {
+            key: base64::engine::general_purpose::STANDARD.encode(&key_bytes),
+            created_at: chrono::Utc::now(),
+        };
+        key_bytes.zeroize();
+
+        let serialized = serde_json::to_string(&stored)
+            .map_err(|e| DatabaseError::Encryption(format!("Key serialization failed: {}", e)))?;
+        match self.kms.create_if_absent(&self.master_key_name(version), &serialized).await {
+            Ok(()) => Ok(()),
+            Err(KmsError::VersionConflict(_)) => Err(MasterKeyWrite::Exists),
+            Err(e) => Err(DatabaseError::Encryption(format!("KMS storage failed: {}", e)).into()),
+        }
+    }
+
+    /// A master key version, cached after the first KMS lookup
+    async fn master_key(&self, version: u32) -> DbResult<DerivedKey> {
+        let name = self.master_key_name(version);
+        if let Some(key) = self.keys.read().await.get(&name) {
+            return Ok(key.clone());
+        }
+
+        let serialized = self.kms.retrieve_and_decrypt(&name).await
+            .map_err(|e| DatabaseError::Encryption(format!("KMS retrieval failed: {}", e)))?;
+        let stored: StoredMasterKey = serde_json::from_str(&serialized)
+            .map_err(|e| DatabaseError::Encryption(format!("Corrupt master key {}: {}", name, e)))?;
+        let key = DerivedKey {
+            algorithm: self.config.encryption_algorithm,
+            key: base64::engine::general_purpose::STANDARD.decode(&stored.key)
+                .map_err(|e| DatabaseError::Encryption(format!("Base64 decode failed: {}", e)))?,
+            created_at: stored.created_at,
+            version,
+        };
+
+        self.keys.write().await.insert(name, key.clone());
+        Ok(key)
     }
 
     /// Generate a new encryption key and store it in KMS
@@ -95,8 +342,32 @@ impl EncryptionManager {
         Ok(())
     }
 
+    /// Create `key_id` in KMS unless it already exists
+    async fn ensure_key(&self, key_id: &str) -> DbResult<()> {
+        if self.keys.read().await.contains_key(key_id) {
+            return Ok(());
+        }
+        let mut key_bytes = vec![0u8; 32];
+        self.rng.fill(&mut key_bytes)
+            .map_err(|e| DatabaseError::Encryption(format!("Key generation failed: {:?}", e)))?;
+        let key_b64 = base64::engine::general_purpose::STANDARD.encode(&key_bytes);
+        key_bytes.zeroize();
+
+        match self.kms.create_if_absent(key_id, &key_b64).await {
+            Ok(()) | Err(KmsError::VersionConflict(_)) => Ok(()),
+            Err(e) => Err(DatabaseError::Encryption(format!("KMS storage failed: {}", e))),
+        }
+    }
+
     /// Rotate an existing key
+    ///
+    /// The master key gets a new version rather than being replaced; see `rotate_master_key`.
     pub async fn rotate_key(&self, key_id: &str) -> DbResult<()> {
+        if key_id == self.config.master_key_id {
+            self.rotate_master_key().await?;
+            return Ok(());
+        }
+
         // Generate new key
         self.generate_key(key_id).await?;
         
@@ -107,6 +378,59 @@ impl EncryptionManager {
         Ok(())
     }
 
+    /// Key for blind indexes, created in KMS under `index_key_id` on first use
+    ///
+    /// The raw KMS key is used rather than a derived one so tokens stay stable across restarts.
+    pub async fn blind_index_key(&self) -> DbResult<BlindIndexKey> {
+        if let Some(key) = self.index_key.read().await.as_ref() {
+            return Ok(key.clone());
+        }
+
+        let mut cached = self.index_key.write().await;
+        if let Some(key) = cached.as_ref() {
+            return Ok(key.clone());
+        }
+
+        let key_id = &self.config.index_key_id;
+        if key_id == &self.config.master_key_id {
+            return Err(DatabaseError::Encryption(
+                "Blind index key must be separate from the master key".to_string(),
+            ));
+        }
+        let exists = self.kms.key_exists(key_id).await
+            .map_err(|e| DatabaseError::Encryption(format!("KMS lookup failed: {}", e)))?;
+        if !exists {
+            self.generate_key(key_id).await?;
+        }
+
+        let key_b64 = self.kms.retrieve_and_decrypt(key_id).await
+            .map_err(|e| DatabaseError::Encryption(format!("KMS retrieval failed: {}", e)))?;
+        let key = BlindIndexKey {
+            key: base64::engine::general_purpose::STANDARD.decode(&key_b64)
+                .map_err(|e| DatabaseError::Encryption(format!("Base64 decode failed: {}", e)))?,
+        };
+
+        *cached = Some(key.clone());
+        Ok(key)
+    }
+
+    /// Replace the blind index key; existing blind indexes must be rebuilt afterwards
+    pub async fn rotate_index_key(&self) -> DbResult<()> {
+        let mut cached = self.index_key.write().await;
+        self.generate_key(&self.config.index_key_id).await?;
+        *cached = None;
+        Ok(())
+    }
+
+    /// Blind index token for `value`
+    ///
+    /// Each `scope` (table and field) gets its own HMAC key, so equal values in
+    /// different fields produce unrelated tokens.
+    pub fn blind_index(&self, key: &BlindIndexKey, scope: &str, value: &[u8]) -> DbResult<Vec<u8>> {
+        let scope_key = self.generate_hmac(scope.as_bytes(), &key.key)?;
+        self.generate_hmac(value, &scope_key)
+    }
+
     /// Get or derive a key for encryption/decryption
     async fn get_or_derive_key(&self, key_id: &str) -> DbResult<DerivedKey> {
         // Check cache first
@@ -138,17 +462,16 @@ impl EncryptionManager {
         let master_key = base64::engine::general_purpose::STANDARD.decode(&master_key_b64)
             .map_err(|e| DatabaseError::Encryption(format!("Base64 decode failed: {}", e)))?;
 
-        // Generate salt for key derivation
-        let mut salt = vec![0u8; 32];
-        self.rng.fill(&mut salt)
-            .map_err(|e| DatabaseError::Encryption(format!("Salt generation failed: {:?}", e)))?;
+        // The salt must be reproducible for data to decrypt after a restart; the input
+        // is a random 256-bit key, so a salt fixed per key id costs nothing
+        let salt = digest::digest(&digest::SHA256, format!("moodbridge-crypto-db:{}", key_id).as_bytes());
 
         // Derive encryption key using PBKDF2
         let mut derived = vec![0u8; 32];
         pbkdf2::derive(
             pbkdf2::PBKDF2_HMAC_SHA256,
             std::num::NonZeroU32::new(self.config.key_derivation_rounds).unwrap(),
-            &salt,
+            salt.as_ref(),
             &master_key,
             &mut derived,
         );
@@ -170,13 +493,13 @@ impl EncryptionManager {
         self.rng.fill(&mut nonce_bytes)
             .map_err(|e| DatabaseError::Encryption(format!("Nonce generation failed: {:?}", e)))?;
 
-        let nonce = aead::Nonce::try_assume_unique_for_key(nonce_bytes.clone())
+        let nonce = aead::Nonce::try_assume_unique_for_key(&nonce_bytes)
             .map_err(|e| DatabaseError::Encryption(format!("Nonce creation failed: {:?}", e)))?;
 
         let sealing_key = aead::LessSafeKey::new(unbound_key);
         
         let mut ciphertext = plaintext.to_vec();
-        let tag = sealing_key.seal_in_place_detached(nonce, aead::Aad::empty(), &mut ciphertext)
+        let tag = sealing_key.seal_in_place_separate_tag(nonce, aead::Aad::empty(), &mut ciphertext)
             .map_err(|e| DatabaseError::Encryption(format!("Encryption failed: {:?}", e)))?;
 
         Ok(EncryptedData {
@@ -191,7 +514,7 @@ impl EncryptionManager {
         let unbound_key = aead::UnboundKey::new(&aead::CHACHA20_POLY1305, key)
             .map_err(|e| DatabaseError::Decryption(format!("Key creation failed: {:?}", e)))?;
 
-        let nonce = aead::Nonce::try_assume_unique_for_key(encrypted.nonce.clone())
+        let nonce = aead::Nonce::try_assume_unique_for_key(&encrypted.nonce)
             .map_err(|e| DatabaseError::Decryption(format!("Nonce creation failed: {:?}", e)))?;
 
         let opening_key = aead::LessSafeKey::new(unbound_key);
@@ -214,13 +537,13 @@ impl EncryptionManager {
         self.rng.fill(&mut nonce_bytes)
             .map_err(|e| DatabaseError::Encryption(format!("Nonce generation failed: {:?}", e)))?;
 
-        let nonce = aead::Nonce::try_assume_unique_for_key(nonce_bytes.clone())
+        let nonce = aead::Nonce::try_assume_unique_for_key(&nonce_bytes)
             .map_err(|e| DatabaseError::Encryption(format!("Nonce creation failed: {:?}", e)))?;
 
         let sealing_key = aead::LessSafeKey::new(unbound_key);
         
         let mut ciphertext = plaintext.to_vec();
-        let tag = sealing_key.seal_in_place_detached(nonce, aead::Aad::empty(), &mut ciphertext)
+        let tag = sealing_key.seal_in_place_separate_tag(nonce, aead::Aad::empty(), &mut ciphertext)
             .map_err(|e| DatabaseError::Encryption(format!("Encryption failed: {:?}", e)))?;
 
         Ok(EncryptedData {
@@ -235,7 +558,7 @@ impl EncryptionManager {
         let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, key)
             .map_err(|e| DatabaseError::Decryption(format!("Key creation failed: {:?}", e)))?;
 
-        let nonce = aead::Nonce::try_assume_unique_for_key(encrypted.nonce.clone())
+        let nonce = aead::Nonce::try_assume_unique_for_key(&encrypted.nonce)
             .map_err(|e| DatabaseError::Decryption(format!("Nonce creation failed: {:?}", e)))?;
 
         let opening_key = aead::LessSafeKey::new(unbound_key);
@@ -286,6 +609,7 @@ impl EncryptionManager {
     pub async fn clear_cache(&self) {
         let mut keys = self.keys.write().await;
         keys.clear();
+        *self.index_key.write().await = None;
     }
 }
 
@@ -321,15 +645,38 @@ impl SecureRandom {
     }
 }
 
+/// Throwaway keystores, so tests never touch the real KMS
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use crate::auth::kms::{KdfParams, KeystoreSecret, LocalKmsProvider};
+    use tempfile::TempDir;
+
+    pub(crate) async fn test_kms() -> (Arc<dyn KmsProvider>, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let kdf = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let kms = LocalKmsProvider::open_with_kdf(dir.path().to_path_buf(), KeystoreSecret::passphrase("test"), kdf)
+            .await
+            .unwrap();
+        (Arc::new(kms), dir)
+    }
+
+    pub(crate) async fn test_manager(config: &DatabaseConfig) -> (EncryptionManager, TempDir) {
+        let (kms, dir) = test_kms().await;
+        (EncryptionManager::with_kms(config, kms), dir)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::test_support::{test_kms, test_manager};
     use super::*;
     use crate::crypto_db::DatabaseConfig;
 
     #[tokio::test]
     async fn test_encryption_roundtrip() {
         let config = DatabaseConfig::default();
-        let manager = EncryptionManager::new(&config).await.unwrap();
+        let (manager, _dir) = test_manager(&config).await;
         
         // Generate a test key
         manager.generate_key("test_key").await.unwrap();
@@ -344,7 +691,7 @@ mod tests {
     #[tokio::test]
     async fn test_hmac_verification() {
         let config = DatabaseConfig::default();
-        let manager = EncryptionManager::new(&config).await.unwrap();
+        let (manager, _dir) = test_manager(&config).await;
         
         let data = b"test data";
         let key = SecureRandom::generate_bytes(32).unwrap();
@@ -357,6 +704,36 @@ mod tests {
         assert!(!manager.verify_hmac(wrong_data, &mac, &key).unwrap());
     }
 
+    #[tokio::test]
+    async fn test_envelope_survives_rotation() {
+        let (manager, _dir) = test_manager(&DatabaseConfig::default()).await;
+
+        let sealed = manager.seal(b"privileged memo").await.unwrap();
+        let rotated = manager.rotate_master_key().await.unwrap();
+        assert!(rotated > sealed.key_version);
+
+        // Data keys wrapped by the old version still open, before and after re-wrapping
+        assert_eq!(manager.open(&sealed).await.unwrap(), b"privileged memo");
+        let (wrapped_key, key_version) = manager.rewrap(&sealed.wrapped_key, sealed.key_version).await.unwrap();
+        assert_eq!(key_version, rotated);
+        let rewrapped = SealedData { wrapped_key, key_version, ..sealed };
+        assert_eq!(manager.open(&rewrapped).await.unwrap(), b"privileged memo");
+    }
+
+    #[tokio::test]
+    async fn test_blind_index_tokens() {
+        let (manager, _dir) = test_manager(&DatabaseConfig::default()).await;
+        let key = manager.blind_index_key().await.unwrap();
+
+        let token = manager.blind_index(&key, "clients.name", b"Alice").unwrap();
+        assert_eq!(token, manager.blind_index(&key, "clients.name", b"Alice").unwrap());
+        assert_ne!(token, manager.blind_index(&key, "matters.name", b"Alice").unwrap());
+
+        manager.rotate_index_key().await.unwrap();
+        let rotated = manager.blind_index_key().await.unwrap();
+        assert_ne!(token, manager.blind_index(&rotated, "clients.name", b"Alice").unwrap());
+    }
+
     #[test]
     fn test_secure_random() {
         let uuid = SecureRandom::generate_uuid();
@@ -368,4 +745,41 @@ mod tests {
         let string = SecureRandom::generate_string(32).unwrap();
         assert!(!string.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_concurrent_rotations_get_distinct_versions() {
+        let config = DatabaseConfig::default();
+        let (kms, _dir) = test_kms().await;
+        let first = EncryptionManager::with_kms(&config, kms.clone());
+        let second = EncryptionManager::with_kms(&config, kms.clone());
+        let sealed = first.seal(b"engagement letter").await.unwrap();
+        assert_eq!(second.current_key_version().await.unwrap(), sealed.key_version);
+
+        let (a, b) = tokio::join!(first.rotate_master_key(), second.rotate_master_key());
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_ne!(a, b);
+
+        // Neither rotation overwrote the other's key, and the pointer went to the newest
+        let restarted = EncryptionManager::with_kms(&config, kms);
+        assert_eq!(restarted.current_key_version().await.unwrap(), a.max(b));
+        assert!(restarted.master_key(a).await.is_ok() && restarted.master_key(b).await.is_ok());
+        let (wrapped_key, key_version) = restarted.rewrap(&sealed.wrapped_key, sealed.key_version).await.unwrap();
+        let resealed = SealedData { wrapped_key, key_version, ..sealed };
+        assert_eq!(first.open(&resealed).await.unwrap(), b"engagement letter");
+    }
+
+    #[tokio::test]
+    async fn test_missing_version_pointer_uses_highest_key() {
+        let config = DatabaseConfig::default();
+        let (kms, _dir) = test_kms().await;
+        let manager = EncryptionManager::with_kms(&config, kms.clone());
+        manager.current_key_version().await.unwrap();
+        let rotated = manager.rotate_master_key().await.unwrap();
+        let sealed = manager.seal(b"settlement draft").await.unwrap();
+
+        kms.delete_key(&manager.key_version_name()).await.unwrap();
+        let restarted = EncryptionManager::with_kms(&config, kms);
+        assert_eq!(restarted.current_key_version().await.unwrap(), rotated);
+        assert_eq!(restarted.open(&sealed).await.unwrap(), b"settlement draft");
+    }
 }
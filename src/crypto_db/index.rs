@@ -1,22 +1,62 @@
-use std::collections::{HashMap, BTreeMap, HashSet};
+use std::collections::{HashMap, BTreeMap, BTreeSet, HashSet};
+use std::ops::Bound;
 use std::sync::Arc;
+use chrono::{DateTime, NaiveDate, Utc};
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
-use super::{DatabaseConfig, DatabaseError, DbResult, QueryBuilder, QueryCondition, QueryOperator};
+use super::encryption::{BlindIndexKey, EncryptionManager};
+use super::{
+    DatabaseConfig, DatabaseError, DbResult, EncryptedRecord, QueryBuilder, QueryCondition,
+    QueryFilter, QueryOperator, StorageEngine, WriteOp,
+};
+
+/// Storage key prefixes for persisted index state; `list_tables` skips keys starting "__"
+pub(super) const INDEX_ENTRY_PREFIX: &str = "__index:";
+const INDEX_META_PREFIX: &str = "__index_meta:";
+
+/// Longest prefix, in characters, answerable on an encrypted field
+///
+/// Encrypted fields store one HMAC token per lowercased prefix up to this length.
+const MAX_BLIND_PREFIX_CHARS: usize = 24;
 
 /// Index manager for efficient querying of encrypted data
 pub struct IndexManager {
     indexes: Arc<RwLock<HashMap<String, TableIndex>>>,
     config: DatabaseConfig,
+    storage: Option<Arc<dyn StorageEngine>>,
+    encryption: Option<Arc<EncryptionManager>>,
 }
 
 /// Index for a specific table
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct TableIndex {
-    primary_index: BTreeMap<String, HashSet<Uuid>>, // field_name -> value -> record_ids
-    composite_indexes: HashMap<String, BTreeMap<String, HashSet<Uuid>>>, // index_name -> value -> record_ids
+    fields: HashMap<String, IndexField>, // declared field definitions
+    values: HashMap<String, BTreeMap<IndexKey, BTreeSet<Uuid>>>, // field_name -> typed value -> record_ids
+    composite_indexes: HashMap<String, CompositeIndex>, // index_name -> index
     bloom_filters: HashMap<String, BloomFilter>, // field_name -> bloom_filter
+    blind_prefixes: HashMap<String, HashMap<String, BTreeSet<Uuid>>>, // field_name -> prefix token -> record_ids
+    records: HashMap<Uuid, IndexedRecord>,
+    blind_key_fingerprint: Option<String>, // blind index key the tokens were made with
+}
+
+/// Everything indexed for one record, also the persisted form of an index entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexedRecord {
+    keys: HashMap<String, IndexKey>, // field_name -> typed value or blind token
+    #[serde(default)]
+    prefixes: HashMap<String, Vec<String>>, // encrypted field_name -> prefix tokens
+}
+
+/// Multi-field index keyed by the values of its fields in order
+///
+/// Records missing a field are indexed with `IndexKey::Null` in that position so that
+/// prefix lookups still find them.
+#[derive(Debug, Clone)]
+struct CompositeIndex {
+    fields: Vec<String>,
+    entries: BTreeMap<Vec<IndexKey>, BTreeSet<Uuid>>,
 }
 
 /// Simple bloom filter implementation for existence checks
@@ -37,7 +77,7 @@ pub struct IndexField {
     pub enable_bloom_filter: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IndexFieldType {
     String,
     Number,
@@ -47,6 +87,180 @@ pub enum IndexFieldType {
     Json,
 }
 
+/// A field value converted to its index type
+///
+/// Keys of one type sort naturally (numbers numerically, dates chronologically).
+/// Range and prefix scans never cross into keys of another type.
+/// Fields declared `is_encrypted` are indexed only by a `Blind` HMAC token of their key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum IndexKey {
+    Null,
+    Boolean(bool),
+    Number(IndexNumber),
+    Date(DateTime<Utc>),
+    String(String),
+    Uuid(Uuid),
+    Json(String),
+    Blind(String),
+}
+
+/// `f64` with a total order so it can key a `BTreeMap`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IndexNumber(pub f64);
+
+impl PartialEq for IndexNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for IndexNumber {}
+
+impl PartialOrd for IndexNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexNumber {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl std::hash::Hash for IndexNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl IndexKey {
+    /// Convert a JSON value to a key of `field_type`, or infer the type when undeclared
+    pub fn from_value(value: &Value, field_type: Option<&IndexFieldType>) -> Option<Self> {
+        let Some(field_type) = field_type else {
+            return Some(match value {
+                Value::Null => Self::Null,
+                Value::Bool(b) => Self::Boolean(*b),
+                Value::Number(n) => Self::number(n.as_f64()?),
+                Value::String(s) => Self::String(s.clone()),
+                Value::Array(_) | Value::Object(_) => Self::Json(value.to_string()),
+            });
+        };
+
+        match (field_type, value) {
+            (_, Value::Null) => Some(Self::Null),
+            (IndexFieldType::String, Value::String(s)) => Some(Self::String(s.clone())),
+            (IndexFieldType::String, Value::Number(n)) => Some(Self::String(n.to_string())),
+            (IndexFieldType::Number, Value::Number(n)) => Some(Self::number(n.as_f64()?)),
+            (IndexFieldType::Number, Value::String(s)) => Some(Self::number(s.trim().parse().ok()?)),
+            (IndexFieldType::Boolean, Value::Bool(b)) => Some(Self::Boolean(*b)),
+            (IndexFieldType::Boolean, Value::String(s)) => Some(Self::Boolean(s.parse().ok()?)),
+            (IndexFieldType::Date, Value::String(s)) => parse_date(s).map(Self::Date),
+            (IndexFieldType::Date, Value::Number(n)) => {
+                DateTime::from_timestamp_millis(n.as_i64()?).map(Self::Date)
+            }
+            (IndexFieldType::Uuid, Value::String(s)) => Uuid::parse_str(s).ok().map(Self::Uuid),
+            (IndexFieldType::Json, value) => Some(Self::Json(value.to_string())),
+            _ => None,
+        }
+    }
+
+    fn number(n: f64) -> Self {
+        // -0.0 and 0.0 are the same value to a query
+        Self::Number(IndexNumber(if n == 0.0 { 0.0 } else { n }))
+    }
+
+    /// Back to JSON, used when a field's declared type changes
+    fn to_value(&self) -> Value {
+        match self {
+            Self::Null => Value::Null,
+            Self::Boolean(b) => Value::Bool(*b),
+            Self::Number(n) => serde_json::Number::from_f64(n.0)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Self::Date(d) => Value::String(d.to_rfc3339()),
+            Self::String(s) | Self::Json(s) => serde_json::from_str(s)
+                .ok()
+                .filter(|_| matches!(self, Self::Json(_)))
+                .unwrap_or_else(|| Value::String(s.clone())),
+            Self::Uuid(u) => Value::String(u.to_string()),
+            Self::Blind(token) => Value::String(token.clone()),
+        }
+    }
+
+    fn same_type(&self, other: &IndexKey) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn bloom_repr(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+/// Index definitions for a table, persisted in plain JSON
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedDefinitions {
+    fields: Vec<IndexField>,
+    composite_indexes: Vec<(String, Vec<String>)>,
+    #[serde(default)]
+    blind_key_fingerprint: Option<String>,
+}
+
+/// Turns values of a table's encrypted fields into blind index tokens
+struct Blinder<'a> {
+    encryption: &'a EncryptionManager,
+    key: BlindIndexKey,
+    table: &'a str,
+}
+
+impl Blinder<'_> {
+    /// Identifies the key without revealing it, to detect indexes built with an old key
+    fn fingerprint(&self) -> DbResult<String> {
+        let mac = self.encryption.blind_index(&self.key, "fingerprint", &[])?;
+        Ok(hex::encode(&mac[..8]))
+    }
+
+    /// Token for an equality match on `field`
+    fn token(&self, field: &str, key: &IndexKey) -> DbResult<IndexKey> {
+        let plain = serde_json::to_vec(key)
+            .map_err(|e| DatabaseError::Index(format!("Serialization failed: {}", e)))?;
+        let mac = self
+            .encryption
+            .blind_index(&self.key, &format!("{}.{}", self.table, field), &plain)?;
+        Ok(IndexKey::Blind(hex::encode(mac)))
+    }
+
+    /// Token for a case-insensitive prefix match on `field`
+    fn prefix_token(&self, field: &str, prefix: &str) -> DbResult<String> {
+        let mac = self.encryption.blind_index(
+            &self.key,
+            &format!("{}.{}#prefix", self.table, field),
+            prefix.to_lowercase().as_bytes(),
+        )?;
+        Ok(hex::encode(mac))
+    }
+
+    fn prefix_tokens(&self, field: &str, value: &str) -> DbResult<Vec<String>> {
+        let lowered = value.to_lowercase();
+        lowered
+            .char_indices()
+            .map(|(start, c)| start + c.len_utf8())
+            .take(MAX_BLIND_PREFIX_CHARS)
+            .map(|end| self.prefix_token(field, &lowered[..end]))
+            .collect()
+    }
+}
+
 /// Search result with relevance scoring
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -60,11 +274,186 @@ impl IndexManager {
         Ok(Self {
             indexes: Arc::new(RwLock::new(HashMap::new())),
             config: config.clone(),
+            storage: None,
+            encryption: None,
         })
     }
 
+    /// Enable blind indexes for fields declared `is_encrypted`
+    pub fn with_encryption(mut self, encryption: Arc<EncryptionManager>) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Persist index state through `storage`, sealing per-record entries like records
+    pub fn with_storage(
+        mut self,
+        storage: Arc<dyn StorageEngine>,
+        encryption: Arc<EncryptionManager>,
+    ) -> Self {
+        self.storage = Some(storage);
+        self.with_encryption(encryption)
+    }
+
+    /// Restore persisted index state, returning the number of records loaded
+    pub async fn load(&self) -> DbResult<usize> {
+        let (Some(storage), Some(encryption)) = (&self.storage, &self.encryption) else {
+            return Ok(0);
+        };
+        let mut indexes = self.indexes.write().await;
+
+        for key in storage.list_keys(INDEX_META_PREFIX).await? {
+            let table = &key[INDEX_META_PREFIX.len()..];
+            let bytes = storage.retrieve(&key).await?;
+            let definitions: PersistedDefinitions = serde_json::from_slice(&bytes)
+                .map_err(|e| DatabaseError::Index(format!("Corrupt index definitions: {}", e)))?;
+
+            let table_index = indexes.entry(table.to_string()).or_default();
+            for field in definitions.fields {
+                table_index.fields.insert(field.name.clone(), field);
+            }
+            for (name, fields) in definitions.composite_indexes {
+                table_index.composite_indexes.insert(
+                    name,
+                    CompositeIndex {
+                        fields,
+                        entries: BTreeMap::new(),
+                    },
+                );
+            }
+            table_index.blind_key_fingerprint = definitions.blind_key_fingerprint;
+        }
+
+        let mut loaded = 0;
+        for key in storage.list_keys(INDEX_ENTRY_PREFIX).await? {
+            let Some((table, id)) = key[INDEX_ENTRY_PREFIX.len()..].rsplit_once(':') else {
+                continue;
+            };
+            let id = Uuid::parse_str(id)
+                .map_err(|e| DatabaseError::Index(format!("Corrupt index key {}: {}", key, e)))?;
+
+            let sealed = storage.retrieve(&key).await?;
+            let sealed: EncryptedRecord = serde_json::from_slice(&sealed)
+                .map_err(|e| DatabaseError::Index(format!("Corrupt index entry: {}", e)))?;
+            let plain = sealed.decrypt(encryption).await?;
+            let record: IndexedRecord = serde_json::from_slice(&plain)
+                .map_err(|e| DatabaseError::Index(format!("Corrupt index entry: {}", e)))?;
+
+            indexes
+                .entry(table.to_string())
+                .or_default()
+                .insert(id, record);
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Tables whose blind indexes were built with a key other than the current one
+    ///
+    /// These refuse reads and writes until rebuilt from the stored records.
+    pub async fn stale_tables(&self) -> DbResult<Vec<String>> {
+        let indexes = self.indexes.read().await;
+        let Some(encryption) = &self.encryption else {
+            return Ok(Vec::new());
+        };
+        let mut stale = Vec::new();
+        let mut current = None;
+        for (table, table_index) in indexes.iter() {
+            if !table_index.has_encrypted_fields() {
+                continue;
+            }
+            if current.is_none() {
+                let blinder = Blinder {
+                    encryption: encryption.as_ref(),
+                    key: encryption.blind_index_key().await?,
+                    table,
+                };
+                current = Some(blinder.fingerprint()?);
+            }
+            if table_index.blind_key_fingerprint != current {
+                stale.push(table.clone());
+            }
+        }
+        stale.sort();
+        Ok(stale)
+    }
+
+    /// Blinder for `table`, or `None` when it has no encrypted fields
+    async fn blinder<'a>(&'a self, table: &'a str, table_index: &TableIndex) -> DbResult<Option<Blinder<'a>>> {
+        if !table_index.has_encrypted_fields() {
+            return Ok(None);
+        }
+        let encryption = self.encryption.as_ref().ok_or_else(|| {
+            DatabaseError::Encryption(format!(
+                "Encrypted fields of {} need an encryption manager for blind indexes",
+                table
+            ))
+        })?;
+        let blinder = Blinder {
+            encryption: encryption.as_ref(),
+            key: encryption.blind_index_key().await?,
+            table,
+        };
+
+        let fingerprint = blinder.fingerprint()?;
+        match &table_index.blind_key_fingerprint {
+            Some(built_with) if *built_with != fingerprint => Err(DatabaseError::Index(format!(
+                "Blind indexes of {} were built with a rotated key and must be rebuilt",
+                table
+            ))),
+            _ => Ok(Some(blinder)),
+        }
+    }
+
+    /// Declare a field's type and options, re-keying records already indexed
+    pub async fn define_field(&self, table: &str, field: IndexField) -> DbResult<()> {
+        let mut indexes = self.indexes.write().await;
+        let table_index = indexes.entry(table.to_string()).or_default();
+
+        let previous = table_index.fields.insert(field.name.clone(), field.clone());
+        let rekeyed = self.blinder(table, table_index).await.and_then(|blinder| {
+            let rekeyed = table_index.rekey_field(table, &field, previous.as_ref(), blinder.as_ref())?;
+            Ok((blinder, rekeyed))
+        });
+        let (blinder, rekeyed) = match rekeyed {
+            Ok(rekeyed) => rekeyed,
+            Err(e) => {
+                table_index.restore_field(&field.name, previous);
+                return Err(e);
+            }
+        };
+
+        for (id, record) in &rekeyed {
+            table_index.insert(*id, record.clone());
+        }
+        table_index.rebuild_bloom(&field.name);
+        if let Some(blinder) = &blinder {
+            table_index.blind_key_fingerprint = Some(blinder.fingerprint()?);
+        }
+
+        let mut ops: Vec<WriteOp> = self.definitions_op(table, table_index)?.into_iter().collect();
+        for (id, record) in &rekeyed {
+            ops.extend(self.entry_op(table, id, record).await?);
+        }
+        self.commit(ops).await
+    }
+
     /// Add a record to indexes
     pub async fn add_record<T>(&self, table: &str, id: &Uuid, data: &T) -> DbResult<()>
+    where
+        T: Serialize + Send + Sync,
+    {
+        self.add_record_with(table, id, data, Vec::new()).await
+    }
+
+    /// Add a record to indexes, committing `ops` in the same storage transaction as its
+    /// index entry
+    ///
+    /// The in-memory index changes only once the transaction has committed, and
+    /// uniqueness is checked under the same lock, so a failed write leaves both the
+    /// store and the index as they were.
+    pub async fn add_record_with<T>(&self, table: &str, id: &Uuid, data: &T, mut ops: Vec<WriteOp>) -> DbResult<()>
     where
         T: Serialize + Send + Sync,
     {
@@ -72,30 +461,15 @@ impl IndexManager {
             .map_err(|e| DatabaseError::Index(format!("Serialization failed: {}", e)))?;
 
         let mut indexes = self.indexes.write().await;
-        let table_index = indexes.entry(table.to_string()).or_insert_with(TableIndex::new);
+        let table_index = indexes.entry(table.to_string()).or_default();
 
-        // Extract indexable fields from the data
-        if let serde_json::Value::Object(obj) = serialized {
-            for (field_name, value) in obj {
-                let value_str = self.value_to_string(&value);
-                
-                // Add to primary index
-                table_index.primary_index
-                    .entry(field_name.clone())
-                    .or_insert_with(HashSet::new)
-                    .insert(*id);
-
-                // Add to bloom filter
-                if let Some(bloom_filter) = table_index.bloom_filters.get_mut(&field_name) {
-                    bloom_filter.add(&value_str);
-                } else {
-                    let mut bloom_filter = BloomFilter::new(10000, 3);
-                    bloom_filter.add(&value_str);
-                    table_index.bloom_filters.insert(field_name, bloom_filter);
-                }
-            }
-        }
+        let blinder = self.blinder(table, table_index).await?;
+        let record = table_index.extract_record(&serialized, blinder.as_ref())?;
+        table_index.check_unique(id, &record.keys)?;
 
+        ops.extend(self.entry_op(table, id, &record).await?);
+        self.commit(ops).await?;
+        table_index.insert(*id, record);
         Ok(())
     }
 
@@ -104,28 +478,29 @@ impl IndexManager {
     where
         T: Serialize + Send + Sync,
     {
-        // For simplicity, we'll remove the old record and add the new one
-        // In a production system, you'd want to be more efficient
-        self.remove_record(table, id).await?;
-        self.add_record(table, id, data).await?;
-        Ok(())
+        // `add_record` checks uniqueness against other records before replacing this one
+        self.add_record(table, id, data).await
     }
 
     /// Remove a record from indexes
     pub async fn remove_record(&self, table: &str, id: &Uuid) -> DbResult<()> {
+        self.remove_record_with(table, id, Vec::new()).await
+    }
+
+    /// Remove a record from indexes, committing `ops` in the same storage transaction
+    /// as the removal of its index entry
+    pub async fn remove_record_with(&self, table: &str, id: &Uuid, mut ops: Vec<WriteOp>) -> DbResult<()> {
         let mut indexes = self.indexes.write().await;
-        if let Some(table_index) = indexes.get_mut(table) {
-            // Remove from primary index
-            for (_, record_set) in table_index.primary_index.iter_mut() {
-                record_set.remove(id);
-            }
+        let indexed = indexes
+            .get(table)
+            .is_some_and(|table_index| table_index.records.contains_key(id));
+        if indexed && self.storage.is_some() {
+            ops.push(WriteOp::Delete { key: entry_key(table, id) });
+        }
 
-            // Remove from composite indexes
-            for (_, index) in table_index.composite_indexes.iter_mut() {
-                for (_, record_set) in index.iter_mut() {
-                    record_set.remove(id);
-                }
-            }
+        self.commit(ops).await?;
+        if let Some(table_index) = indexes.get_mut(table) {
+            table_index.remove(id);
         }
         Ok(())
     }
@@ -136,37 +511,25 @@ impl IndexManager {
         let table_index = indexes.get(&query.table)
             .ok_or_else(|| DatabaseError::Index(format!("No index found for table: {}", query.table)))?;
 
-        let mut result_sets: Vec<HashSet<Uuid>> = Vec::new();
+        let blinder = self.blinder(&query.table, table_index).await?;
+        let matched = table_index.evaluate(&query.to_filter(), blinder.as_ref())?;
 
-        // Process each condition
-        for condition in &query.conditions {
-            let matching_ids = self.search_condition(table_index, condition).await?;
-            result_sets.push(matching_ids);
-        }
-
-        // Intersect all result sets (AND operation)
-        let mut final_results = if let Some(first_set) = result_sets.first() {
-            first_set.clone()
-        } else {
-            HashSet::new()
-        };
-
-        for result_set in result_sets.iter().skip(1) {
-            final_results = final_results.intersection(result_set).cloned().collect();
-        }
-
-        // Convert to Vec and apply ordering/limiting
-        let mut results: Vec<Uuid> = final_results.into_iter().collect();
-
-        // Apply ordering (simplified - in practice you'd need the actual data for complex sorting)
-        if let Some(order_field) = &query.order_by {
-            // For now, just sort by UUID as a placeholder
-            // In a real implementation, you'd need to access the actual field values
-            results.sort();
-            if query.order_desc {
-                results.reverse();
+        // Only the first offset + limit results need to be put in order
+        let needed = query.limit.map(|limit| limit + query.offset.unwrap_or(0));
+        let mut results = match &query.order_by {
+            Some(field) if table_index.is_encrypted(field) => {
+                return Err(DatabaseError::Query(format!(
+                    "Cannot order by encrypted field {}",
+                    field
+                )));
             }
-        }
+            Some(field) => table_index.ordered(field, query.order_desc, &matched, needed),
+            None => {
+                let mut ids: Vec<Uuid> = matched.into_iter().collect();
+                ids.sort();
+                ids
+            }
+        };
 
         // Apply pagination
         if let Some(offset) = query.offset {
@@ -184,169 +547,695 @@ impl IndexManager {
         Ok(results)
     }
 
-    /// Search for a specific condition
-    async fn search_condition(&self, table_index: &TableIndex, condition: &QueryCondition) -> DbResult<HashSet<Uuid>> {
-        let field_name = &condition.field;
-        let value_str = self.value_to_string(&condition.value);
+    /// Create a composite index for multiple fields, indexing existing records
+    pub async fn create_composite_index(&self, table: &str, index_name: &str, fields: &[String]) -> DbResult<()> {
+        if fields.len() < 2 {
+            return Err(DatabaseError::Index(
+                "A composite index needs at least two fields".to_string(),
+            ));
+        }
 
-        // Check bloom filter first for existence queries
-        if let Some(bloom_filter) = table_index.bloom_filters.get(field_name) {
-            if !bloom_filter.might_contain(&value_str) {
-                // Definitely not present
-                return Ok(HashSet::new());
-            }
+        let mut indexes = self.indexes.write().await;
+        let table_index = indexes.entry(table.to_string()).or_default();
+
+        let mut composite = CompositeIndex {
+            fields: fields.to_vec(),
+            entries: BTreeMap::new(),
+        };
+        for (id, record) in &table_index.records {
+            composite
+                .entries
+                .entry(composite.key_for(&record.keys))
+                .or_default()
+                .insert(*id);
         }
+        table_index.composite_indexes.insert(index_name.to_string(), composite);
 
-        // Get field index
-        let field_index = table_index.primary_index.get(field_name)
-            .ok_or_else(|| DatabaseError::Index(format!("No index found for field: {}", field_name)))?;
+        self.commit(self.definitions_op(table, table_index)?.into_iter().collect()).await
+    }
 
-        match condition.operator {
-            QueryOperator::Equals => {
-                Ok(field_index.get(&value_str).cloned().unwrap_or_default())
-            }
-            QueryOperator::NotEquals => {
-                let mut all_ids: HashSet<Uuid> = HashSet::new();
-                for (_, ids) in field_index.iter() {
-                    all_ids.extend(ids);
+    /// Get index statistics
+    pub async fn get_stats(&self, table: &str) -> DbResult<IndexStats> {
+        let indexes = self.indexes.read().await;
+        let table_index = indexes.get(table)
+            .ok_or_else(|| DatabaseError::Index(format!("No index found for table: {}", table)))?;
+
+        let field_counts = table_index
+            .values
+            .iter()
+            .map(|(field_name, index)| (field_name.clone(), index.len()))
+            .collect();
+
+        Ok(IndexStats {
+            table: table.to_string(),
+            total_records: table_index.records.len(),
+            field_counts,
+            composite_indexes: table_index.composite_indexes.keys().cloned().collect(),
+        })
+    }
+
+    /// Drop every indexed record of a table, keeping field and composite definitions
+    ///
+    /// Blind indexes are re-keyed to the current index key, so records added afterwards
+    /// use it.
+    pub async fn rebuild_index(&self, table: &str) -> DbResult<()> {
+        let mut indexes = self.indexes.write().await;
+        let Some(table_index) = indexes.get_mut(table) else {
+            return Ok(());
+        };
+
+        let ids: Vec<Uuid> = table_index.records.keys().copied().collect();
+        *table_index = TableIndex {
+            fields: std::mem::take(&mut table_index.fields),
+            composite_indexes: std::mem::take(&mut table_index.composite_indexes)
+                .into_iter()
+                .map(|(name, composite)| {
+                    let fields = composite.fields;
+                    (name, CompositeIndex { fields, entries: BTreeMap::new() })
+                })
+                .collect(),
+            ..TableIndex::default()
+        };
+        let mut ops = Vec::new();
+        if let Some(blinder) = self.blinder(table, table_index).await? {
+            table_index.blind_key_fingerprint = Some(blinder.fingerprint()?);
+            ops.extend(self.definitions_op(table, table_index)?);
+        }
+        if self.storage.is_some() {
+            ops.extend(ids.iter().map(|id| WriteOp::Delete { key: entry_key(table, id) }));
+        }
+        self.commit(ops).await
+    }
+
+    /// Apply `ops` as one storage transaction
+    async fn commit(&self, ops: Vec<WriteOp>) -> DbResult<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        let storage = self.storage.as_ref().ok_or_else(|| {
+            DatabaseError::Storage("Index manager has no storage to write to".to_string())
+        })?;
+        storage.transaction(ops).await
+    }
+
+    /// Write of a record's index entry, sealed under its own data key like the records
+    /// themselves so master key rotation re-wraps it too
+    async fn entry_op(&self, table: &str, id: &Uuid, record: &IndexedRecord) -> DbResult<Option<WriteOp>> {
+        let (Some(_), Some(encryption)) = (&self.storage, &self.encryption) else {
+            return Ok(None);
+        };
+        let plain = serde_json::to_vec(record)
+            .map_err(|e| DatabaseError::Index(format!("Serialization failed: {}", e)))?;
+        let now = Utc::now();
+        let mut entry = EncryptedRecord {
+            id: *id,
+            table_name: table.to_string(),
+            encrypted_data: Vec::new(),
+            nonce: Vec::new(),
+            mac: Vec::new(),
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            metadata: HashMap::new(),
+            wrapped_key: None,
+            key_version: None,
+        };
+        entry.set_sealed(encryption.seal(&plain).await?);
+        let value = serde_json::to_vec(&entry)
+            .map_err(|e| DatabaseError::Index(format!("Serialization failed: {}", e)))?;
+        Ok(Some(WriteOp::Put { key: entry_key(table, id), value }))
+    }
+
+    fn definitions_op(&self, table: &str, table_index: &TableIndex) -> DbResult<Option<WriteOp>> {
+        if self.storage.is_none() {
+            return Ok(None);
+        }
+        let definitions = PersistedDefinitions {
+            fields: table_index.fields.values().cloned().collect(),
+            composite_indexes: table_index
+                .composite_indexes
+                .iter()
+                .map(|(name, composite)| (name.clone(), composite.fields.clone()))
+                .collect(),
+            blind_key_fingerprint: table_index.blind_key_fingerprint.clone(),
+        };
+        let value = serde_json::to_vec(&definitions)
+            .map_err(|e| DatabaseError::Index(format!("Serialization failed: {}", e)))?;
+        Ok(Some(WriteOp::Put { key: format!("{}{}", INDEX_META_PREFIX, table), value }))
+    }
+}
+
+fn entry_key(table: &str, id: &Uuid) -> String {
+    format!("{}{}:{}", INDEX_ENTRY_PREFIX, table, id)
+}
+
+impl TableIndex {
+    fn has_encrypted_fields(&self) -> bool {
+        self.fields.values().any(|f| f.is_encrypted)
+    }
+
+    fn is_encrypted(&self, field: &str) -> bool {
+        self.fields.get(field).is_some_and(|f| f.is_encrypted)
+    }
+
+    /// Put back the definition `define_field` replaced after it fails
+    fn restore_field(&mut self, name: &str, previous: Option<IndexField>) {
+        match previous {
+            Some(previous) => self.fields.insert(name.to_string(), previous),
+            None => self.fields.remove(name),
+        };
+    }
+
+    /// Records re-keyed for the new definition of `field`, checked for uniqueness
+    fn rekey_field(
+        &self,
+        table: &str,
+        field: &IndexField,
+        previous: Option<&IndexField>,
+        blinder: Option<&Blinder>,
+    ) -> DbResult<Vec<(Uuid, IndexedRecord)>> {
+        let unchanged = previous.is_some_and(|previous| {
+            previous.is_encrypted == field.is_encrypted && previous.field_type == field.field_type
+        });
+
+        let mut rekeyed = Vec::new();
+        for (id, record) in &self.records {
+            let Some(key) = record.keys.get(&field.name) else {
+                continue;
+            };
+            if let IndexKey::Blind(_) = key {
+                if unchanged {
+                    continue;
                 }
-                let equal_ids = field_index.get(&value_str).cloned().unwrap_or_default();
-                Ok(all_ids.difference(&equal_ids).cloned().collect())
-            }
-            QueryOperator::Contains => {
-                let mut matching_ids = HashSet::new();
-                for (indexed_value, ids) in field_index.iter() {
-                    if indexed_value.contains(&value_str) {
-                        matching_ids.extend(ids);
+                return Err(DatabaseError::Index(format!(
+                    "{} only holds blind tokens; rebuild the indexes of {} after redefining it",
+                    field.name, table
+                )));
+            }
+
+            let converted = IndexKey::from_value(&key.to_value(), Some(&field.field_type))
+                .ok_or_else(|| {
+                    DatabaseError::Validation(format!(
+                        "Existing value of {} in record {} is not a {:?}",
+                        field.name, id, field.field_type
+                    ))
+                })?;
+            let mut record = record.clone();
+            record.prefixes.remove(&field.name);
+            match blinder.filter(|_| field.is_encrypted) {
+                Some(blinder) => {
+                    if let IndexKey::String(text) = &converted {
+                        record
+                            .prefixes
+                            .insert(field.name.clone(), blinder.prefix_tokens(&field.name, text)?);
                     }
+                    record.keys.insert(field.name.clone(), blinder.token(&field.name, &converted)?);
+                }
+                None => {
+                    record.keys.insert(field.name.clone(), converted);
                 }
-                Ok(matching_ids)
             }
-            QueryOperator::StartsWith => {
-                let mut matching_ids = HashSet::new();
-                for (indexed_value, ids) in field_index.iter() {
-                    if indexed_value.starts_with(&value_str) {
-                        matching_ids.extend(ids);
+            rekeyed.push((*id, record));
+        }
+
+        for (id, record) in &rekeyed {
+            self.check_unique(id, &record.keys)?;
+        }
+        Ok(rekeyed)
+    }
+
+    /// Typed keys for the top-level fields of a record, blinding encrypted fields
+    fn extract_record(&self, data: &Value, blinder: Option<&Blinder>) -> DbResult<IndexedRecord> {
+        let mut record = IndexedRecord::default();
+        if let Value::Object(obj) = data {
+            for (field_name, value) in obj {
+                let field_type = self.fields.get(field_name).map(|f| &f.field_type);
+                let key = IndexKey::from_value(value, field_type).ok_or_else(|| {
+                    DatabaseError::Validation(format!(
+                        "Field {} is not a valid {:?}",
+                        field_name,
+                        field_type.unwrap()
+                    ))
+                })?;
+
+                match blinder.filter(|_| self.is_encrypted(field_name)) {
+                    Some(blinder) => {
+                        if let IndexKey::String(text) = &key {
+                            record
+                                .prefixes
+                                .insert(field_name.clone(), blinder.prefix_tokens(field_name, text)?);
+                        }
+                        record.keys.insert(field_name.clone(), blinder.token(field_name, &key)?);
+                    }
+                    None => {
+                        record.keys.insert(field_name.clone(), key);
                     }
                 }
-                Ok(matching_ids)
             }
-            QueryOperator::EndsWith => {
-                let mut matching_ids = HashSet::new();
-                for (indexed_value, ids) in field_index.iter() {
-                    if indexed_value.ends_with(&value_str) {
-                        matching_ids.extend(ids);
+        }
+        Ok(record)
+    }
+
+    fn check_unique(&self, id: &Uuid, keys: &HashMap<String, IndexKey>) -> DbResult<()> {
+        for field in self.fields.values().filter(|f| f.is_unique) {
+            let Some(key) = keys.get(&field.name) else {
+                continue;
+            };
+            let taken = self
+                .values
+                .get(&field.name)
+                .and_then(|index| index.get(key))
+                .is_some_and(|ids| ids.iter().any(|other| other != id));
+            if taken {
+                return Err(DatabaseError::Conflict(format!(
+                    "Duplicate value for unique field {}",
+                    field.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, id: Uuid, record: IndexedRecord) {
+        self.remove(&id);
+
+        for (field_name, key) in &record.keys {
+            let bloom_enabled = self
+                .fields
+                .get(field_name)
+                .is_none_or(|f| f.enable_bloom_filter);
+            if bloom_enabled {
+                self.bloom_filters
+                    .entry(field_name.clone())
+                    .or_insert_with(|| BloomFilter::new(10000, 3))
+                    .add(&key.bloom_repr());
+            }
+            self.values
+                .entry(field_name.clone())
+                .or_default()
+                .entry(key.clone())
+                .or_default()
+                .insert(id);
+        }
+        for composite in self.composite_indexes.values_mut() {
+            composite
+                .entries
+                .entry(composite.key_for(&record.keys))
+                .or_default()
+                .insert(id);
+        }
+        for (field_name, tokens) in &record.prefixes {
+            let prefixes = self.blind_prefixes.entry(field_name.clone()).or_default();
+            for token in tokens {
+                prefixes.entry(token.clone()).or_default().insert(id);
+            }
+        }
+        self.records.insert(id, record);
+    }
+
+    fn remove(&mut self, id: &Uuid) -> bool {
+        let Some(record) = self.records.remove(id) else {
+            return false;
+        };
+
+        for (field_name, key) in &record.keys {
+            if let Some(index) = self.values.get_mut(field_name) {
+                if let Some(ids) = index.get_mut(key) {
+                    ids.remove(id);
+                    if ids.is_empty() {
+                        index.remove(key);
                     }
                 }
-                Ok(matching_ids)
-            }
-            QueryOperator::GreaterThan | QueryOperator::LessThan | 
-            QueryOperator::GreaterThanOrEqual | QueryOperator::LessThanOrEqual => {
-                // For range queries, we'd need a more sophisticated index structure
-                // For now, return empty set
-                Ok(HashSet::new())
-            }
-            QueryOperator::In => {
-                if let serde_json::Value::Array(values) = &condition.value {
-                    let mut matching_ids = HashSet::new();
-                    for value in values {
-                        let value_str = self.value_to_string(value);
-                        if let Some(ids) = field_index.get(&value_str) {
-                            matching_ids.extend(ids);
-                        }
-                    }
-                    Ok(matching_ids)
-                } else {
-                    Err(DatabaseError::Query("IN operator requires array value".to_string()))
+                if index.is_empty() {
+                    self.values.remove(field_name);
                 }
             }
-            QueryOperator::NotIn => {
-                if let serde_json::Value::Array(values) = &condition.value {
-                    let mut all_ids: HashSet<Uuid> = HashSet::new();
-                    for (_, ids) in field_index.iter() {
-                        all_ids.extend(ids);
-                    }
-                    
-                    let mut excluded_ids = HashSet::new();
-                    for value in values {
-                        let value_str = self.value_to_string(value);
-                        if let Some(ids) = field_index.get(&value_str) {
-                            excluded_ids.extend(ids);
+        }
+        for composite in self.composite_indexes.values_mut() {
+            let key = composite.key_for(&record.keys);
+            if let Some(ids) = composite.entries.get_mut(&key) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    composite.entries.remove(&key);
+                }
+            }
+        }
+        for (field_name, tokens) in &record.prefixes {
+            if let Some(prefixes) = self.blind_prefixes.get_mut(field_name) {
+                for token in tokens {
+                    if let Some(ids) = prefixes.get_mut(token) {
+                        ids.remove(id);
+                        if ids.is_empty() {
+                            prefixes.remove(token);
                         }
                     }
-                    
-                    Ok(all_ids.difference(&excluded_ids).cloned().collect())
-                } else {
-                    Err(DatabaseError::Query("NOT IN operator requires array value".to_string()))
+                }
+                if prefixes.is_empty() {
+                    self.blind_prefixes.remove(field_name);
                 }
             }
         }
+        true
     }
 
-    /// Convert JSON value to string for indexing
-    fn value_to_string(&self, value: &serde_json::Value) -> String {
-        match value {
-            serde_json::Value::String(s) => s.clone(),
-            serde_json::Value::Number(n) => n.to_string(),
-            serde_json::Value::Bool(b) => b.to_string(),
-            serde_json::Value::Null => "null".to_string(),
-            _ => value.to_string(),
+    /// Refill the bloom filter of `field` from its current keys
+    fn rebuild_bloom(&mut self, field: &str) {
+        self.bloom_filters.remove(field);
+        let enabled = self.fields.get(field).is_none_or(|f| f.enable_bloom_filter);
+        if let (true, Some(index)) = (enabled, self.values.get(field)) {
+            let mut bloom = BloomFilter::new(10000, 3);
+            for key in index.keys() {
+                bloom.add(&key.bloom_repr());
+            }
+            self.bloom_filters.insert(field.to_string(), bloom);
         }
     }
 
-    /// Create a composite index for multiple fields
-    pub async fn create_composite_index(&self, table: &str, index_name: &str, fields: &[String]) -> DbResult<()> {
-        let mut indexes = self.indexes.write().await;
-        let table_index = indexes.entry(table.to_string()).or_insert_with(TableIndex::new);
-        
-        // Create empty composite index
-        table_index.composite_indexes.insert(
-            index_name.to_string(),
-            BTreeMap::new()
-        );
-        
-        Ok(())
+    fn all_ids(&self) -> HashSet<Uuid> {
+        self.records.keys().copied().collect()
     }
 
-    /// Get index statistics
-    pub async fn get_stats(&self, table: &str) -> DbResult<IndexStats> {
-        let indexes = self.indexes.read().await;
-        let table_index = indexes.get(table)
-            .ok_or_else(|| DatabaseError::Index(format!("No index found for table: {}", table)))?;
+    fn evaluate(&self, filter: &QueryFilter, blinder: Option<&Blinder>) -> DbResult<HashSet<Uuid>> {
+        match filter {
+            QueryFilter::Condition(condition) => self.search_condition(condition, blinder),
+            QueryFilter::And(filters) => self.evaluate_and(filters, blinder),
+            QueryFilter::Or(filters) => {
+                let mut matched = HashSet::new();
+                for filter in filters {
+                    matched.extend(self.evaluate(filter, blinder)?);
+                }
+                Ok(matched)
+            }
+            QueryFilter::Not(filter) => {
+                let excluded = self.evaluate(filter, blinder)?;
+                Ok(self.all_ids().difference(&excluded).copied().collect())
+            }
+        }
+    }
+
+    /// Intersect the filters, answering leading equalities from a composite index when one fits
+    fn evaluate_and(&self, filters: &[QueryFilter], blinder: Option<&Blinder>) -> DbResult<HashSet<Uuid>> {
+        if filters.is_empty() {
+            return Ok(self.all_ids());
+        }
 
-        let mut total_records = 0;
-        let mut field_counts = HashMap::new();
+        let equalities: Vec<&QueryCondition> = filters
+            .iter()
+            .filter_map(|filter| match filter {
+                QueryFilter::Condition(c) if matches!(c.operator, QueryOperator::Equals) => Some(c),
+                _ => None,
+            })
+            .collect();
 
-        for (field_name, record_set) in &table_index.primary_index {
-            let unique_values = record_set.len();
-            field_counts.insert(field_name.clone(), unique_values);
-            total_records = total_records.max(unique_values);
+        let (mut matched, covered) = match self.composite_lookup(&equalities, blinder)? {
+            Some((ids, covered)) => (Some(ids), covered),
+            None => (None, Vec::new()),
+        };
+
+        for filter in filters {
+            if let QueryFilter::Condition(c) = filter {
+                if covered.iter().any(|used| std::ptr::eq(*used, c)) {
+                    continue;
+                }
+            }
+            if matched.as_ref().is_some_and(|ids| ids.is_empty()) {
+                break;
+            }
+            let ids = self.evaluate(filter, blinder)?;
+            matched = Some(match matched {
+                Some(previous) => previous.intersection(&ids).copied().collect(),
+                None => ids,
+            });
         }
 
-        Ok(IndexStats {
-            table: table.to_string(),
-            total_records,
-            field_counts,
-            composite_indexes: table_index.composite_indexes.keys().cloned().collect(),
-        })
+        Ok(matched.unwrap_or_default())
     }
 
-    /// Rebuild indexes for a table
-    pub async fn rebuild_index(&self, table: &str) -> DbResult<()> {
-        let mut indexes = self.indexes.write().await;
-        indexes.insert(table.to_string(), TableIndex::new());
-        Ok(())
+    /// Use the composite index whose leading fields are covered by the most equalities
+    fn composite_lookup<'a>(
+        &self,
+        equalities: &[&'a QueryCondition],
+        blinder: Option<&Blinder>,
+    ) -> DbResult<Option<(HashSet<Uuid>, Vec<&'a QueryCondition>)>> {
+        let best = self
+            .composite_indexes
+            .values()
+            .map(|composite| {
+                let covered: Vec<&QueryCondition> = composite
+                    .fields
+                    .iter()
+                    .map_while(|field| equalities.iter().copied().find(|c| &c.field == field))
+                    .collect();
+                (composite, covered)
+            })
+            .filter(|(_, covered)| covered.len() >= 2)
+            .max_by_key(|(_, covered)| covered.len());
+
+        let Some((composite, covered)) = best else {
+            return Ok(None);
+        };
+
+        let prefix = covered
+            .iter()
+            .map(|c| self.condition_key(&c.field, &c.value, blinder))
+            .collect::<DbResult<Vec<_>>>()?;
+        let ids = composite
+            .entries
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+        Ok(Some((ids, covered)))
     }
-}
 
-impl TableIndex {
-    fn new() -> Self {
-        Self {
-            primary_index: BTreeMap::new(),
-            composite_indexes: HashMap::new(),
-            bloom_filters: HashMap::new(),
+    /// Convert a query value to the key type of `field`, or its blind token if encrypted
+    fn condition_key(&self, field: &str, value: &Value, blinder: Option<&Blinder>) -> DbResult<IndexKey> {
+        let field_type = self.fields.get(field).map(|f| &f.field_type);
+        let key = IndexKey::from_value(value, field_type).ok_or_else(|| {
+            DatabaseError::Query(format!("{} is not a valid value for field {}", value, field))
+        })?;
+        match blinder.filter(|_| self.is_encrypted(field)) {
+            Some(blinder) => blinder.token(field, &key),
+            None => Ok(key),
+        }
+    }
+
+    /// Search for a specific condition
+    fn search_condition(&self, condition: &QueryCondition, blinder: Option<&Blinder>) -> DbResult<HashSet<Uuid>> {
+        let field_name = &condition.field;
+        // A field no record has matches nothing, including for NOT-style operators
+        let Some(field_index) = self.values.get(field_name) else {
+            return Ok(HashSet::new());
+        };
+        let all_in_field = || -> HashSet<Uuid> { collect_ids(field_index.iter()) };
+
+        if self.is_encrypted(field_name) {
+            match condition.operator {
+                QueryOperator::Equals
+                | QueryOperator::NotEquals
+                | QueryOperator::In
+                | QueryOperator::NotIn => {}
+                QueryOperator::StartsWith => {
+                    return self.search_blind_prefix(condition, blinder, all_in_field);
+                }
+                _ => {
+                    return Err(DatabaseError::Query(format!(
+                        "{:?} is not supported on encrypted field {}; use equality or prefix queries",
+                        condition.operator, field_name
+                    )));
+                }
+            }
+        }
+
+        match condition.operator {
+            QueryOperator::Equals => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                // Check bloom filter first for existence queries
+                if let Some(bloom_filter) = self.bloom_filters.get(field_name) {
+                    if !bloom_filter.might_contain(&key.bloom_repr()) {
+                        // Definitely not present
+                        return Ok(HashSet::new());
+                    }
+                }
+                Ok(field_index.get(&key).map(|ids| ids.iter().copied().collect()).unwrap_or_default())
+            }
+            QueryOperator::NotEquals => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                let mut ids = all_in_field();
+                for id in field_index.get(&key).into_iter().flatten() {
+                    ids.remove(id);
+                }
+                Ok(ids)
+            }
+            QueryOperator::GreaterThan => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                Ok(range_ids(field_index, Bound::Excluded(&key), Bound::Unbounded, &key))
+            }
+            QueryOperator::GreaterThanOrEqual => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                Ok(range_ids(field_index, Bound::Included(&key), Bound::Unbounded, &key))
+            }
+            QueryOperator::LessThan => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                Ok(range_ids(field_index, Bound::Unbounded, Bound::Excluded(&key), &key))
+            }
+            QueryOperator::LessThanOrEqual => {
+                let key = self.condition_key(field_name, &condition.value, blinder)?;
+                Ok(range_ids(field_index, Bound::Unbounded, Bound::Included(&key), &key))
+            }
+            QueryOperator::StartsWith => {
+                let prefix = text_operand(condition)?;
+                let start = IndexKey::String(prefix.to_string());
+                Ok(collect_ids(
+                    field_index
+                        .range(start..)
+                        .take_while(|(key, _)| matches!(key, IndexKey::String(s) if s.starts_with(prefix))),
+                ))
+            }
+            QueryOperator::Contains | QueryOperator::EndsWith => {
+                let needle = text_operand(condition)?;
+                let ends_with = matches!(condition.operator, QueryOperator::EndsWith);
+                Ok(collect_ids(field_index.iter().filter(|(key, _)| match key {
+                    IndexKey::String(s) if ends_with => s.ends_with(needle),
+                    IndexKey::String(s) => s.contains(needle),
+                    _ => false,
+                })))
+            }
+            QueryOperator::In | QueryOperator::NotIn => {
+                let Value::Array(values) = &condition.value else {
+                    return Err(DatabaseError::Query(format!(
+                        "{:?} operator requires array value",
+                        condition.operator
+                    )));
+                };
+                let mut listed = HashSet::new();
+                for value in values {
+                    let key = self.condition_key(field_name, value, blinder)?;
+                    listed.extend(field_index.get(&key).into_iter().flatten().copied());
+                }
+                if matches!(condition.operator, QueryOperator::In) {
+                    Ok(listed)
+                } else {
+                    Ok(all_in_field().difference(&listed).copied().collect())
+                }
+            }
         }
     }
+
+    /// Prefix match on an encrypted field through its stored prefix tokens
+    fn search_blind_prefix(
+        &self,
+        condition: &QueryCondition,
+        blinder: Option<&Blinder>,
+        all_in_field: impl Fn() -> HashSet<Uuid>,
+    ) -> DbResult<HashSet<Uuid>> {
+        let prefix = text_operand(condition)?;
+        let Some(blinder) = blinder else {
+            return Err(DatabaseError::Encryption(format!(
+                "Encrypted field {} needs an encryption manager for blind indexes",
+                condition.field
+            )));
+        };
+        match prefix.chars().count() {
+            0 => Ok(all_in_field()),
+            n if n > MAX_BLIND_PREFIX_CHARS => Err(DatabaseError::Query(format!(
+                "Prefixes on encrypted field {} are limited to {} characters",
+                condition.field, MAX_BLIND_PREFIX_CHARS
+            ))),
+            _ => {
+                let token = blinder.prefix_token(&condition.field, prefix)?;
+                Ok(self
+                    .blind_prefixes
+                    .get(&condition.field)
+                    .and_then(|prefixes| prefixes.get(&token))
+                    .map(|ids| ids.iter().copied().collect())
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    /// Matched ids ordered by the typed value of `field`, records without it last
+    fn ordered(&self, field: &str, desc: bool, matched: &HashSet<Uuid>, needed: Option<usize>) -> Vec<Uuid> {
+        let field_index = self.values.get(field);
+        let index_len = field_index.map_or(0, |index| index.len());
+
+        // Walking the field index in order is cheapest unless the match set is small
+        let mut ordered: Vec<Uuid> = match field_index {
+            Some(index) if matched.len().saturating_mul(8) >= index_len => {
+                let groups: Box<dyn Iterator<Item = &BTreeSet<Uuid>>> = if desc {
+                    Box::new(index.values().rev())
+                } else {
+                    Box::new(index.values())
+                };
+                let mut ordered = Vec::new();
+                for id in groups.flatten().filter(|id| matched.contains(id)) {
+                    ordered.push(*id);
+                    if needed == Some(ordered.len()) {
+                        return ordered;
+                    }
+                }
+                ordered
+            }
+            _ => {
+                let value_of = |id: &Uuid| self.records.get(id).and_then(|record| record.keys.get(field));
+                let mut ids: Vec<Uuid> = matched
+                    .iter()
+                    .filter(|id| value_of(id).is_some())
+                    .copied()
+                    .collect();
+                ids.sort_by(|a, b| {
+                    let by_value = value_of(a).cmp(&value_of(b));
+                    let by_value = if desc { by_value.reverse() } else { by_value };
+                    by_value.then_with(|| a.cmp(b))
+                });
+                ids
+            }
+        };
+
+        let mut missing: Vec<Uuid> = matched
+            .iter()
+            .filter(|id| !self.records.get(id).is_some_and(|record| record.keys.contains_key(field)))
+            .copied()
+            .collect();
+        missing.sort();
+        ordered.extend(missing);
+        ordered
+    }
+}
+
+impl CompositeIndex {
+    fn key_for(&self, keys: &HashMap<String, IndexKey>) -> Vec<IndexKey> {
+        self.fields
+            .iter()
+            .map(|field| keys.get(field).cloned().unwrap_or(IndexKey::Null))
+            .collect()
+    }
+}
+
+fn collect_ids<'a>(entries: impl Iterator<Item = (&'a IndexKey, &'a BTreeSet<Uuid>)>) -> HashSet<Uuid> {
+    entries.flat_map(|(_, ids)| ids.iter().copied()).collect()
+}
+
+/// Ids whose keys fall between the bounds and share the type of `like`
+fn range_ids(
+    index: &BTreeMap<IndexKey, BTreeSet<Uuid>>,
+    lower: Bound<&IndexKey>,
+    upper: Bound<&IndexKey>,
+    like: &IndexKey,
+) -> HashSet<Uuid> {
+    let same_type = |(key, _): &(&IndexKey, &BTreeSet<Uuid>)| key.same_type(like);
+    let range = index.range::<IndexKey, _>((lower, upper));
+    // Keys of one type are contiguous, so stop at the first key of another type
+    match upper {
+        Bound::Unbounded => collect_ids(range.take_while(same_type)),
+        _ => collect_ids(range.rev().take_while(same_type)),
+    }
+}
+
+fn text_operand(condition: &QueryCondition) -> DbResult<&str> {
+    condition.value.as_str().ok_or_else(|| {
+        DatabaseError::Query(format!("{:?} operator requires a string value", condition.operator))
+    })
 }
 
 impl BloomFilter {
@@ -400,6 +1289,7 @@ pub struct IndexStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto_db::encryption::test_support::test_manager;
     use crate::crypto_db::DatabaseConfig;
     use serde_json::json;
 
@@ -431,15 +1321,15 @@ mod tests {
         // Search by name
         let query = QueryBuilder::new(table)
             .where_eq("name", json!("Alice"));
-        
+
         let results = manager.search(&query).await.unwrap();
         assert_eq!(results.len(), 1);
         assert!(results.contains(&id1));
 
-        // Search by age range (should return empty for now as range queries aren't fully implemented)
+        // Search by age
         let query = QueryBuilder::new(table)
             .where_eq("age", json!(30));
-        
+
         let results = manager.search(&query).await.unwrap();
         assert_eq!(results.len(), 1);
         assert!(results.contains(&id1));
@@ -448,13 +1338,13 @@ mod tests {
     #[tokio::test]
     async fn test_bloom_filter() {
         let mut bloom = BloomFilter::new(1000, 3);
-        
+
         bloom.add("test1");
         bloom.add("test2");
-        
+
         assert!(bloom.might_contain("test1"));
         assert!(bloom.might_contain("test2"));
-        
+
         // This might give a false positive, but should not give a false negative
         let non_existent = bloom.might_contain("test3");
         // We can't assert false here due to the nature of bloom filters
@@ -491,5 +1381,240 @@ mod tests {
         assert_eq!(results.len(), 2);
         assert!(results.contains(&id1));
         assert!(results.contains(&id2));
+
+        // A composite index answers the two-field equality and keeps later inserts
+        manager
+            .create_composite_index(table, "category_price", &["category".to_string(), "price".to_string()])
+            .await
+            .unwrap();
+        let id4 = Uuid::new_v4();
+        manager
+            .add_record(table, &id4, &json!({"category": "electronics", "price": 100}))
+            .await
+            .unwrap();
+        let query = QueryBuilder::new(table)
+            .where_eq("category", json!("electronics"))
+            .where_eq("price", json!(100));
+        let mut results = manager.search(&query).await.unwrap();
+        results.sort();
+        let mut expected = vec![id1, id4];
+        expected.sort();
+        assert_eq!(results, expected);
+    }
+
+    #[tokio::test]
+    async fn test_typed_ranges_and_ordering() {
+        let manager = IndexManager::new(&DatabaseConfig::default()).await.unwrap();
+        let table = "hearings";
+        manager
+            .define_field(table, IndexField {
+                name: "date".to_string(),
+                field_type: IndexFieldType::Date,
+                is_unique: false,
+                is_encrypted: false,
+                enable_bloom_filter: false,
+            })
+            .await
+            .unwrap();
+
+        let mut ids = Vec::new();
+        for (date, fee) in [("2024-03-01", 900), ("2024-01-15", 80), ("2024-02-10", 1000), ("2023-12-31", 5)] {
+            let id = Uuid::new_v4();
+            manager
+                .add_record(table, &id, &json!({"date": date, "fee": fee, "judge": "Hale"}))
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let untyped = Uuid::new_v4();
+        manager.add_record(table, &untyped, &json!({"judge": "Ortiz"})).await.unwrap();
+
+        // Numbers compare numerically, not as strings ("900" > "1000")
+        let query = QueryBuilder::new(table)
+            .where_op("fee", QueryOperator::GreaterThanOrEqual, json!(100))
+            .order_by("fee", true);
+        assert_eq!(manager.search(&query).await.unwrap(), vec![ids[2], ids[0]]);
+
+        let query = QueryBuilder::new(table)
+            .where_op("date", QueryOperator::GreaterThanOrEqual, json!("2024-01-01"))
+            .where_op("date", QueryOperator::LessThan, json!("2024-03-01T00:00:00Z"))
+            .order_by("date", false);
+        assert_eq!(manager.search(&query).await.unwrap(), vec![ids[1], ids[2]]);
+
+        // Records without the order field sort last, and limit applies after ordering
+        let query = QueryBuilder::new(table).order_by("date", false).limit(5);
+        assert_eq!(
+            manager.search(&query).await.unwrap(),
+            vec![ids[3], ids[1], ids[2], ids[0], untyped]
+        );
+
+        let query = QueryBuilder::new(table).where_op("judge", QueryOperator::StartsWith, json!("Ha"));
+        assert_eq!(manager.search(&query).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_or_not_filters() {
+        let manager = IndexManager::new(&DatabaseConfig::default()).await.unwrap();
+        let table = "cases";
+        let open = Uuid::new_v4();
+        let closed = Uuid::new_v4();
+        let appeal = Uuid::new_v4();
+        manager.add_record(table, &open, &json!({"status": "open", "county": "King"})).await.unwrap();
+        manager.add_record(table, &closed, &json!({"status": "closed", "county": "King"})).await.unwrap();
+        manager.add_record(table, &appeal, &json!({"status": "appeal", "county": "Pierce"})).await.unwrap();
+
+        let query = QueryBuilder::new(table)
+            .where_any(vec![
+                QueryFilter::eq("status", json!("open")),
+                QueryFilter::eq("county", json!("Pierce")),
+            ])
+            .order_by("status", false);
+        assert_eq!(manager.search(&query).await.unwrap(), vec![appeal, open]);
+
+        let query = QueryBuilder::new(table)
+            .where_eq("county", json!("King"))
+            .where_not(QueryFilter::eq("status", json!("closed")));
+        assert_eq!(manager.search(&query).await.unwrap(), vec![open]);
+    }
+
+    #[tokio::test]
+    async fn test_indexes_survive_restart() {
+        use crate::crypto_db::storage::MemoryStorageEngine;
+
+        let config = DatabaseConfig::default();
+        let storage: Arc<dyn StorageEngine> = Arc::new(MemoryStorageEngine::new());
+        let (encryption, _dir) = test_manager(&config).await;
+        let encryption = Arc::new(encryption);
+
+        let manager = IndexManager::new(&config)
+            .await
+            .unwrap()
+            .with_storage(storage.clone(), encryption.clone());
+        manager
+            .define_field("clients", IndexField {
+                name: "email".to_string(),
+                field_type: IndexFieldType::String,
+                is_unique: true,
+                is_encrypted: true,
+                enable_bloom_filter: true,
+            })
+            .await
+            .unwrap();
+        let kept = Uuid::new_v4();
+        let removed = Uuid::new_v4();
+        manager.add_record("clients", &kept, &json!({"email": "a@firm.test", "matters": 3})).await.unwrap();
+        manager.add_record("clients", &removed, &json!({"email": "b@firm.test", "matters": 1})).await.unwrap();
+        manager.remove_record("clients", &removed).await.unwrap();
+
+        let restarted = IndexManager::new(&config).await.unwrap().with_storage(storage, encryption);
+        assert_eq!(restarted.load().await.unwrap(), 1);
+
+        let query = QueryBuilder::new("clients").where_op("matters", QueryOperator::GreaterThan, json!(2));
+        assert_eq!(restarted.search(&query).await.unwrap(), vec![kept]);
+
+        // The unique definition was restored along with the entries
+        let duplicate = restarted
+            .add_record("clients", &Uuid::new_v4(), &json!({"email": "a@firm.test"}))
+            .await;
+        assert!(matches!(duplicate, Err(DatabaseError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_index_write_drops_record_write() {
+        use crate::crypto_db::storage::MemoryStorageEngine;
+
+        let config = DatabaseConfig::default();
+        let storage: Arc<dyn StorageEngine> = Arc::new(MemoryStorageEngine::new());
+        let (encryption, _dir) = test_manager(&config).await;
+        let manager = IndexManager::new(&config)
+            .await
+            .unwrap()
+            .with_storage(storage.clone(), Arc::new(encryption));
+        let mut unique = encrypted_field("bar_number");
+        unique.is_unique = true;
+        manager.define_field("attorneys", unique).await.unwrap();
+
+        let first = Uuid::new_v4();
+        let put = |id: &Uuid| vec![WriteOp::Put { key: format!("attorneys:{}", id), value: b"record".to_vec() }];
+        manager
+            .add_record_with("attorneys", &first, &json!({"bar_number": "WSBA-1"}), put(&first))
+            .await
+            .unwrap();
+
+        let second = Uuid::new_v4();
+        let duplicate = manager
+            .add_record_with("attorneys", &second, &json!({"bar_number": "WSBA-1"}), put(&second))
+            .await;
+        assert!(matches!(duplicate, Err(DatabaseError::Conflict(_))));
+        assert!(storage.retrieve(&format!("attorneys:{}", first)).await.is_ok());
+        assert!(storage.retrieve(&format!("attorneys:{}", second)).await.is_err());
+        assert!(storage.retrieve(&entry_key("attorneys", &second)).await.is_err());
+    }
+
+    fn encrypted_field(name: &str) -> IndexField {
+        IndexField {
+            name: name.to_string(),
+            field_type: IndexFieldType::String,
+            is_unique: false,
+            is_encrypted: true,
+            enable_bloom_filter: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blind_index_hides_values() {
+        let config = DatabaseConfig::default();
+        let (encryption, _dir) = test_manager(&config).await;
+        let encryption = Arc::new(encryption);
+        let manager = IndexManager::new(&config).await.unwrap().with_encryption(encryption);
+        manager.define_field("clients", encrypted_field("name")).await.unwrap();
+
+        let alice = Uuid::new_v4();
+        let alicia = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        for (id, name) in [(alice, "Alice Moreau"), (alicia, "alicia Chen"), (bob, "Bob Alison")] {
+            manager.add_record("clients", &id, &json!({"name": name, "tier": 1})).await.unwrap();
+        }
+
+        let query = QueryBuilder::new("clients").where_eq("name", json!("Alice Moreau"));
+        assert_eq!(manager.search(&query).await.unwrap(), vec![alice]);
+
+        let mut expected = vec![alice, alicia];
+        expected.sort();
+        let query = QueryBuilder::new("clients").where_op("name", QueryOperator::StartsWith, json!("ALI"));
+        assert_eq!(manager.search(&query).await.unwrap(), expected);
+
+        let query = QueryBuilder::new("clients").where_op("name", QueryOperator::Contains, json!("Ali"));
+        assert!(matches!(manager.search(&query).await, Err(DatabaseError::Query(_))));
+
+        // Nothing derived from the names is held in the clear
+        let indexes = manager.indexes.read().await;
+        let table_index = &indexes["clients"];
+        assert!(table_index.values["name"].keys().all(|key| matches!(key, IndexKey::Blind(_))));
+        let held = format!("{:?}", table_index.records);
+        assert!(!held.contains("Alice") && !held.to_lowercase().contains("ali"));
+    }
+
+    #[tokio::test]
+    async fn test_blind_index_key_rotation() {
+        let config = DatabaseConfig::default();
+        let (encryption, _dir) = test_manager(&config).await;
+        let encryption = Arc::new(encryption);
+        let manager = IndexManager::new(&config).await.unwrap().with_encryption(encryption.clone());
+        manager.define_field("clients", encrypted_field("name")).await.unwrap();
+
+        let id = Uuid::new_v4();
+        let record = json!({"name": "Ines Duarte"});
+        manager.add_record("clients", &id, &record).await.unwrap();
+
+        encryption.rotate_index_key().await.unwrap();
+        assert_eq!(manager.stale_tables().await.unwrap(), vec!["clients".to_string()]);
+        let query = QueryBuilder::new("clients").where_eq("name", json!("Ines Duarte"));
+        assert!(matches!(manager.search(&query).await, Err(DatabaseError::Index(_))));
+
+        manager.rebuild_index("clients").await.unwrap();
+        manager.add_record("clients", &id, &record).await.unwrap();
+        assert!(manager.stale_tables().await.unwrap().is_empty());
+        assert_eq!(manager.search(&query).await.unwrap(), vec![id]);
     }
 }
@@ -2,19 +2,19 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use ring::{aead, digest, hmac, pbkdf2, rand};
-use ring::rand::SecureRandom;
-use base64::{Engine as _, engine::general_purpose};
 
 pub mod encryption;
-pub mod schema;
 pub mod storage;
 pub mod index;
-pub mod query;
 pub mod audit;
+pub mod rotation;
+
+use audit::{AuditAction, AuditLogger};
+use encryption::{EncryptionManager, SealedData};
+use index::{IndexField, IndexManager};
+use rotation::{RewrapProgress, RotationManager};
 
 /// Core cryptographic database engine
 pub struct CryptoDatabase {
@@ -22,6 +22,7 @@ pub struct CryptoDatabase {
     encryption: Arc<EncryptionManager>,
     indexing: Arc<IndexManager>,
     audit: Arc<AuditLogger>,
+    rotation: Arc<RotationManager>,
     config: DatabaseConfig,
 }
 
@@ -29,6 +30,8 @@ pub struct CryptoDatabase {
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
     pub master_key_id: String,
+    /// KMS key for blind indexes; must differ from `master_key_id`
+    pub index_key_id: String,
     pub encryption_algorithm: EncryptionAlgorithm,
     pub hash_algorithm: HashAlgorithm,
     pub key_derivation_rounds: u32,
@@ -37,12 +40,15 @@ pub struct DatabaseConfig {
     pub audit_level: AuditLevel,
     pub max_record_size: usize,
     pub cache_size: usize,
+    /// Age after which the master key is due for rotation
+    pub key_rotation_interval_days: u32,
 }
 
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             master_key_id: "default".to_string(),
+            index_key_id: "default-index".to_string(),
             encryption_algorithm: EncryptionAlgorithm::ChaCha20Poly1305,
             hash_algorithm: HashAlgorithm::Sha256,
             key_derivation_rounds: 100_000,
@@ -51,6 +57,7 @@ impl Default for DatabaseConfig {
             audit_level: AuditLevel::Full,
             max_record_size: 10 * 1024 * 1024, // 10MB
             cache_size: 100 * 1024 * 1024,      // 100MB
+            key_rotation_interval_days: 365,
         }
     }
 }
@@ -87,6 +94,45 @@ pub struct EncryptedRecord {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: HashMap<String, String>,
+    /// Data key for `encrypted_data`, wrapped by master key version `key_version`
+    ///
+    /// Both are `None` for records written before envelope encryption.
+    #[serde(default)]
+    pub wrapped_key: Option<EncryptedData>,
+    #[serde(default)]
+    pub key_version: Option<u32>,
+}
+
+impl EncryptedRecord {
+    /// Decrypt the record payload, whichever scheme it was written with
+    pub async fn decrypt(&self, encryption: &EncryptionManager) -> DbResult<Vec<u8>> {
+        let data = EncryptedData {
+            ciphertext: self.encrypted_data.clone(),
+            nonce: self.nonce.clone(),
+            mac: self.mac.clone(),
+        };
+        match (&self.wrapped_key, self.key_version) {
+            (Some(wrapped_key), Some(key_version)) => {
+                encryption
+                    .open(&SealedData {
+                        data,
+                        wrapped_key: wrapped_key.clone(),
+                        key_version,
+                    })
+                    .await
+            }
+            _ => encryption.decrypt(&data).await,
+        }
+    }
+
+    /// Replace the payload with newly sealed data
+    pub fn set_sealed(&mut self, sealed: SealedData) {
+        self.encrypted_data = sealed.data.ciphertext;
+        self.nonce = sealed.data.nonce;
+        self.mac = sealed.data.mac;
+        self.wrapped_key = Some(sealed.wrapped_key);
+        self.key_version = Some(sealed.key_version);
+    }
 }
 
 /// Database operation result
@@ -116,7 +162,16 @@ pub enum DatabaseError {
     Conflict(String),
 }
 
+/// A single write within a `StorageEngine::transaction`
+#[derive(Debug, Clone)]
+pub enum WriteOp {
+    Put { key: String, value: Vec<u8> },
+    Delete { key: String },
+}
+
 /// Storage engine trait for different backends
+///
+/// Object-safe so engines can be shared as `Arc<dyn StorageEngine>`.
 #[async_trait]
 pub trait StorageEngine: Send + Sync {
     async fn store(&self, key: &str, value: &[u8]) -> DbResult<()>;
@@ -124,10 +179,8 @@ pub trait StorageEngine: Send + Sync {
     async fn delete(&self, key: &str) -> DbResult<()>;
     async fn list_keys(&self, prefix: &str) -> DbResult<Vec<String>>;
     async fn batch_store(&self, entries: Vec<(String, Vec<u8>)>) -> DbResult<()>;
-    async fn transaction<F, R>(&self, op: F) -> DbResult<R>
-    where
-        F: FnOnce() -> DbResult<R> + Send,
-        R: Send;
+    /// Apply every write or none of them
+    async fn transaction(&self, ops: Vec<WriteOp>) -> DbResult<()>;
 }
 
 impl CryptoDatabase {
@@ -136,16 +189,30 @@ impl CryptoDatabase {
         config: DatabaseConfig,
     ) -> DbResult<Self> {
         let encryption = Arc::new(EncryptionManager::new(&config).await?);
-        let indexing = Arc::new(IndexManager::new(&config).await?);
+        let indexing = Arc::new(
+            IndexManager::new(&config)
+                .await?
+                .with_storage(storage.clone(), encryption.clone()),
+        );
+        indexing.load().await?;
         let audit = Arc::new(AuditLogger::new(&config).await?);
+        let rotation = Arc::new(RotationManager::new(storage.clone(), encryption.clone(), &config));
 
-        Ok(Self {
+        let db = Self {
             storage,
             encryption,
             indexing,
             audit,
+            rotation,
             config,
-        })
+        };
+
+        // Blind indexes made with a since-rotated key can't be queried, so rebuild them
+        for table in db.indexing.stale_tables().await? {
+            db.rebuild_indexes(&table).await?;
+        }
+
+        Ok(db)
     }
 
     /// Insert a new record
@@ -157,31 +224,31 @@ impl CryptoDatabase {
         let serialized = serde_json::to_vec(data)
             .map_err(|e| DatabaseError::Validation(e.to_string()))?;
 
-        // Encrypt the data
-        let encrypted = self.encryption.encrypt(&serialized).await?;
+        // Encrypt the data under its own data key
+        let sealed = self.encryption.seal(&serialized).await?;
         
         // Create record
         let record = EncryptedRecord {
             id,
             table_name: table.to_string(),
-            encrypted_data: encrypted.ciphertext,
-            nonce: encrypted.nonce,
-            mac: encrypted.mac,
+            encrypted_data: sealed.data.ciphertext,
+            nonce: sealed.data.nonce,
+            mac: sealed.data.mac,
             version: 1,
             created_at: Utc::now(),
             updated_at: Utc::now(),
             metadata: HashMap::new(),
+            wrapped_key: Some(sealed.wrapped_key),
+            key_version: Some(sealed.key_version),
         };
 
-        // Store record
+        // Store the record with its index and audit entries in one transaction
         let key = format!("{}:{}", table, id);
         let record_bytes = serde_json::to_vec(&record)
             .map_err(|e| DatabaseError::Storage(e.to_string()))?;
-        
-        self.storage.store(&key, &record_bytes).await?;
-
-        // Update indexes
-        self.indexing.add_record(table, &id, data).await?;
+        let mut ops = vec![WriteOp::Put { key, value: record_bytes }];
+        ops.extend(self.audit.entry_op(AuditAction::Insert, table, &id)?);
+        self.indexing.add_record_with(table, &id, data, ops).await?;
 
         // Audit log
         self.audit.log_insert(table, &id).await?;
@@ -201,13 +268,7 @@ impl CryptoDatabase {
             .map_err(|e| DatabaseError::Storage(e.to_string()))?;
 
         // Decrypt the data
-        let encrypted = EncryptedData {
-            ciphertext: record.encrypted_data,
-            nonce: record.nonce,
-            mac: record.mac,
-        };
-        
-        let decrypted = self.encryption.decrypt(&encrypted).await?;
+        let decrypted = record.decrypt(&self.encryption).await?;
         
         let data: T = serde_json::from_slice(&decrypted)
             .map_err(|e| DatabaseError::Decryption(e.to_string()))?;
@@ -233,23 +294,19 @@ impl CryptoDatabase {
         // Serialize and encrypt new data
         let serialized = serde_json::to_vec(data)
             .map_err(|e| DatabaseError::Validation(e.to_string()))?;
-        let encrypted = self.encryption.encrypt(&serialized).await?;
+        let sealed = self.encryption.seal(&serialized).await?;
 
         // Update record
-        record.encrypted_data = encrypted.ciphertext;
-        record.nonce = encrypted.nonce;
-        record.mac = encrypted.mac;
+        record.set_sealed(sealed);
         record.version += 1;
         record.updated_at = Utc::now();
 
-        // Store updated record
+        // Store the updated record with its index and audit entries in one transaction
         let record_bytes = serde_json::to_vec(&record)
             .map_err(|e| DatabaseError::Storage(e.to_string()))?;
-        
-        self.storage.store(&key, &record_bytes).await?;
-
-        // Update indexes
-        self.indexing.update_record(table, id, data).await?;
+        let mut ops = vec![WriteOp::Put { key, value: record_bytes }];
+        ops.extend(self.audit.entry_op(AuditAction::Update, table, id)?);
+        self.indexing.add_record_with(table, id, data, ops).await?;
 
         // Audit log
         self.audit.log_update(table, id).await?;
@@ -260,12 +317,11 @@ impl CryptoDatabase {
     /// Delete a record
     pub async fn delete(&self, table: &str, id: &Uuid) -> DbResult<()> {
         let key = format!("{}:{}", table, id);
-        
-        // Remove from storage
-        self.storage.delete(&key).await?;
 
-        // Remove from indexes
-        self.indexing.remove_record(table, id).await?;
+        // Remove the record with its index entry, auditing it in the same transaction
+        let mut ops = vec![WriteOp::Delete { key }];
+        ops.extend(self.audit.entry_op(AuditAction::Delete, table, id)?);
+        self.indexing.remove_record_with(table, id, ops).await?;
 
         // Audit log
         self.audit.log_delete(table, id).await?;
@@ -275,6 +331,15 @@ impl CryptoDatabase {
 
     /// Query records with encrypted search
     pub async fn query<T>(&self, query: &QueryBuilder) -> DbResult<Vec<T>>
+    where
+        T: for<'de> Deserialize<'de> + Send + Sync,
+    {
+        let results = self.query_with_ids(query).await?;
+        Ok(results.into_iter().map(|(_, record)| record).collect())
+    }
+
+    /// Query records with encrypted search, keeping each record's ID
+    pub async fn query_with_ids<T>(&self, query: &QueryBuilder) -> DbResult<Vec<(Uuid, T)>>
     where
         T: for<'de> Deserialize<'de> + Send + Sync,
     {
@@ -283,7 +348,7 @@ impl CryptoDatabase {
 
         for id in record_ids {
             match self.get::<T>(&query.table, &id).await {
-                Ok(record) => results.push(record),
+                Ok(record) => results.push((id, record)),
                 Err(DatabaseError::NotFound(_)) => continue, // Skip deleted records
                 Err(e) => return Err(e),
             }
@@ -295,6 +360,69 @@ impl CryptoDatabase {
         Ok(results)
     }
 
+    /// Declare an indexed field, e.g. to keep it searchable only through blind tokens
+    pub async fn define_index(&self, table: &str, field: IndexField) -> DbResult<()> {
+        self.indexing.define_field(table, field).await
+    }
+
+    /// Rebuild a table's indexes from its stored records, keeping index definitions
+    pub async fn rebuild_indexes(&self, table: &str) -> DbResult<usize> {
+        self.indexing.rebuild_index(table).await?;
+
+        let keys = self.storage.list_keys(&format!("{}:", table)).await?;
+        let mut rebuilt = 0;
+        for key in keys {
+            let Some(id) = key.rsplit(':').next().and_then(|id| Uuid::parse_str(id).ok()) else {
+                continue;
+            };
+            let data: serde_json::Value = self.get(table, &id).await?;
+            self.indexing.add_record(table, &id, &data).await?;
+            rebuilt += 1;
+        }
+        Ok(rebuilt)
+    }
+
+    /// Rotate the blind index key and rebuild every blind index, returning records reindexed
+    pub async fn rotate_index_key(&self) -> DbResult<usize> {
+        self.encryption.rotate_index_key().await?;
+
+        let mut reindexed = 0;
+        for table in self.indexing.stale_tables().await? {
+            reindexed += self.rebuild_indexes(&table).await?;
+        }
+        Ok(reindexed)
+    }
+
+    /// Start a new master key version; existing records stay readable and are re-wrapped
+    /// by `rewrap_batch` or `spawn_rewrap`
+    pub async fn rotate_master_key(&self) -> DbResult<RewrapProgress> {
+        self.rotation.rotate().await
+    }
+
+    /// Whether the master key is older than `key_rotation_interval_days`
+    pub async fn key_rotation_due(&self) -> DbResult<bool> {
+        self.rotation.rotation_due().await
+    }
+
+    /// Progress of the latest re-wrap, kept in storage so it survives restarts
+    pub async fn rewrap_progress(&self) -> DbResult<Option<RewrapProgress>> {
+        self.rotation.progress().await
+    }
+
+    /// Re-wrap up to `batch_size` more records, resuming where the last batch stopped
+    pub async fn rewrap_batch(&self, batch_size: usize) -> DbResult<RewrapProgress> {
+        self.rotation.run_batch(batch_size).await
+    }
+
+    /// Re-wrap the remaining records in the background, pausing between batches
+    pub fn spawn_rewrap(
+        &self,
+        batch_size: usize,
+        pause: std::time::Duration,
+    ) -> tokio::task::JoinHandle<DbResult<RewrapProgress>> {
+        self.rotation.clone().spawn(batch_size, pause)
+    }
+
     /// Get database statistics
     pub async fn stats(&self) -> DbResult<DatabaseStats> {
         let tables = self.list_tables().await?;
@@ -314,7 +442,8 @@ impl CryptoDatabase {
         let all_keys = self.storage.list_keys("").await?;
         let mut tables = std::collections::HashSet::new();
 
-        for key in all_keys {
+        // Keys starting with "__" hold index and key-management state, not tables
+        for key in all_keys.iter().filter(|key| !key.starts_with("__")) {
             if let Some(table) = key.split(':').next() {
                 tables.insert(table.to_string());
             }
@@ -331,7 +460,7 @@ pub struct DatabaseStats {
 }
 
 /// Encrypted data container
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedData {
     pub ciphertext: Vec<u8>,
     pub nonce: Vec<u8>,
@@ -339,10 +468,13 @@ pub struct EncryptedData {
 }
 
 /// Query builder for encrypted searches
+///
+/// `conditions` and `filters` are ANDed together, `filters` adding OR and NOT.
 #[derive(Debug, Clone)]
 pub struct QueryBuilder {
     pub table: String,
     pub conditions: Vec<QueryCondition>,
+    pub filters: Vec<QueryFilter>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub order_by: Option<String>,
@@ -356,6 +488,53 @@ pub struct QueryCondition {
     pub value: serde_json::Value,
 }
 
+/// Boolean combination of query conditions
+#[derive(Debug, Clone)]
+pub enum QueryFilter {
+    Condition(QueryCondition),
+    And(Vec<QueryFilter>),
+    Or(Vec<QueryFilter>),
+    Not(Box<QueryFilter>),
+}
+
+impl QueryFilter {
+    pub fn condition(field: &str, operator: QueryOperator, value: serde_json::Value) -> Self {
+        Self::Condition(QueryCondition {
+            field: field.to_string(),
+            operator,
+            value,
+        })
+    }
+
+    pub fn eq(field: &str, value: serde_json::Value) -> Self {
+        Self::condition(field, QueryOperator::Equals, value)
+    }
+
+    pub fn and(self, other: QueryFilter) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
+
+    pub fn or(self, other: QueryFilter) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            filter => Self::Or(vec![filter, other]),
+        }
+    }
+
+    pub fn negate(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum QueryOperator {
     Equals,
@@ -376,6 +555,7 @@ impl QueryBuilder {
         Self {
             table: table.to_string(),
             conditions: Vec::new(),
+            filters: Vec::new(),
             limit: None,
             offset: None,
             order_by: None,
@@ -392,6 +572,41 @@ impl QueryBuilder {
         self
     }
 
+    pub fn where_op(mut self, field: &str, operator: QueryOperator, value: serde_json::Value) -> Self {
+        self.conditions.push(QueryCondition {
+            field: field.to_string(),
+            operator,
+            value,
+        });
+        self
+    }
+
+    pub fn filter(mut self, filter: QueryFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Match records satisfying at least one of `filters`
+    pub fn where_any(self, filters: Vec<QueryFilter>) -> Self {
+        self.filter(QueryFilter::Or(filters))
+    }
+
+    pub fn where_not(self, filter: QueryFilter) -> Self {
+        self.filter(filter.negate())
+    }
+
+    /// The whole WHERE clause as a single filter
+    pub fn to_filter(&self) -> QueryFilter {
+        QueryFilter::And(
+            self.conditions
+                .iter()
+                .cloned()
+                .map(QueryFilter::Condition)
+                .chain(self.filters.iter().cloned())
+                .collect(),
+        )
+    }
+
     pub fn limit(mut self, limit: usize) -> Self {
         self.limit = Some(limit);
         self
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use super::encryption::EncryptionManager;
+use super::index::INDEX_ENTRY_PREFIX;
+use super::{DatabaseConfig, DatabaseError, DbResult, EncryptedRecord, StorageEngine};
+
+/// Storage key of the re-wrap progress; `list_tables` skips keys starting "__"
+const REWRAP_PROGRESS_KEY: &str = "__rewrap_progress";
+
+/// Master key rotation and re-wrapping of record data keys
+///
+/// Records keep their ciphertext; only the wrapped data key is replaced, so reads and
+/// writes carry on against old and new key versions while a re-wrap runs.
+pub struct RotationManager {
+    storage: Arc<dyn StorageEngine>,
+    encryption: Arc<EncryptionManager>,
+    rotation_interval: chrono::Duration,
+    running: Mutex<()>,
+}
+
+/// How far re-wrapping has got after the latest rotation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewrapProgress {
+    pub target_version: u32,
+    pub total_records: usize,
+    pub rewrapped: usize,
+    pub already_current: usize,
+    /// Last storage key processed; the next batch starts after it
+    pub cursor: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl RewrapProgress {
+    pub fn is_complete(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// Share of records processed, from 0.0 to 1.0
+    pub fn fraction_done(&self) -> f64 {
+        if self.is_complete() || self.total_records == 0 {
+            return 1.0;
+        }
+        let processed = self.rewrapped + self.already_current;
+        (processed as f64 / self.total_records as f64).min(1.0)
+    }
+}
+
+impl RotationManager {
+    pub fn new(
+        storage: Arc<dyn StorageEngine>,
+        encryption: Arc<EncryptionManager>,
+        config: &DatabaseConfig,
+    ) -> Self {
+        Self {
+            storage,
+            encryption,
+            rotation_interval: chrono::Duration::days(config.key_rotation_interval_days as i64),
+            running: Mutex::new(()),
+        }
+    }
+
+    /// Add a master key version and start a re-wrap towards it
+    ///
+    /// An unfinished re-wrap is restarted against the new version.
+    pub async fn rotate(&self) -> DbResult<RewrapProgress> {
+        let _running = self.running.lock().await;
+
+        let target_version = self.encryption.rotate_master_key().await?;
+        let now = Utc::now();
+        let progress = RewrapProgress {
+            target_version,
+            total_records: self.record_keys().await?.len(),
+            rewrapped: 0,
+            already_current: 0,
+            cursor: None,
+            started_at: now,
+            updated_at: now,
+            completed_at: None,
+        };
+        self.save_progress(&progress).await?;
+        Ok(progress)
+    }
+
+    /// Whether the current master key version is older than the rotation interval
+    pub async fn rotation_due(&self) -> DbResult<bool> {
+        let created_at = self.encryption.key_created_at().await?;
+        Ok(created_at + self.rotation_interval <= Utc::now())
+    }
+
+    pub async fn progress(&self) -> DbResult<Option<RewrapProgress>> {
+        match self.storage.retrieve(REWRAP_PROGRESS_KEY).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| DatabaseError::Storage(format!("Corrupt re-wrap progress: {}", e))),
+            Err(DatabaseError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Re-wrap the next `batch_size` records after the saved cursor
+    pub async fn run_batch(&self, batch_size: usize) -> DbResult<RewrapProgress> {
+        let _running = self.running.lock().await;
+
+        let mut progress = self
+            .progress()
+            .await?
+            .ok_or_else(|| DatabaseError::NotFound("No key rotation has been started".to_string()))?;
+        if progress.is_complete() {
+            return Ok(progress);
+        }
+
+        let keys = self.record_keys().await?;
+        let batch: Vec<&String> = keys
+            .iter()
+            .filter(|key| progress.cursor.as_ref().is_none_or(|cursor| *key > cursor))
+            .take(batch_size.max(1))
+            .collect();
+
+        for key in &batch {
+            if self.rewrap_record(key, progress.target_version).await? {
+                progress.rewrapped += 1;
+            } else {
+                progress.already_current += 1;
+            }
+            progress.cursor = Some((*key).clone());
+        }
+
+        progress.updated_at = Utc::now();
+        if batch.len() < batch_size.max(1) {
+            progress.completed_at = Some(progress.updated_at);
+        }
+        self.save_progress(&progress).await?;
+        Ok(progress)
+    }
+
+    /// Run batches until the re-wrap completes, pausing between them
+    ///
+    /// Progress is saved after every batch, so a restarted process can spawn this again
+    /// to resume.
+    pub fn spawn(
+        self: Arc<Self>,
+        batch_size: usize,
+        pause: Duration,
+    ) -> tokio::task::JoinHandle<DbResult<RewrapProgress>> {
+        tokio::spawn(async move {
+            loop {
+                let progress = self.run_batch(batch_size).await?;
+                if progress.is_complete() {
+                    return Ok(progress);
+                }
+                tokio::time::sleep(pause).await;
+            }
+        })
+    }
+
+    /// Re-wrap one record, returning false if it was already at `target_version` or is gone
+    async fn rewrap_record(&self, key: &str, target_version: u32) -> DbResult<bool> {
+        let Some(mut record) = self.load_record(key).await? else {
+            return Ok(false);
+        };
+
+        match (&record.wrapped_key, record.key_version) {
+            (Some(_), Some(version)) if version >= target_version => return Ok(false),
+            (Some(wrapped_key), Some(version)) => {
+                let (wrapped_key, version) = self.encryption.rewrap(wrapped_key, version).await?;
+                record.wrapped_key = Some(wrapped_key);
+                record.key_version = Some(version);
+            }
+            // Records from before envelope encryption are moved onto it
+            _ => {
+                let plaintext = record.decrypt(&self.encryption).await?;
+                record.set_sealed(self.encryption.seal(&plaintext).await?);
+            }
+        }
+
+        // Skip records updated meanwhile; the update already used the current key version
+        let Some(latest) = self.load_record(key).await? else {
+            return Ok(false);
+        };
+        if latest.version != record.version || latest.updated_at != record.updated_at {
+            return Ok(false);
+        }
+
+        let bytes = serde_json::to_vec(&record)
+            .map_err(|e| DatabaseError::Storage(e.to_string()))?;
+        self.storage.store(key, &bytes).await?;
+        Ok(true)
+    }
+
+    async fn load_record(&self, key: &str) -> DbResult<Option<EncryptedRecord>> {
+        match self.storage.retrieve(key).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| DatabaseError::Storage(e.to_string())),
+            Err(DatabaseError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Storage keys of every record and index entry, in a stable order for the cursor
+    async fn record_keys(&self) -> DbResult<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .storage
+            .list_keys("")
+            .await?
+            .into_iter()
+            .filter(|key| !key.starts_with("__") || key.starts_with(INDEX_ENTRY_PREFIX))
+            .filter(|key| {
+                key.rsplit_once(':')
+                    .is_some_and(|(_, id)| Uuid::parse_str(id).is_ok())
+            })
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn save_progress(&self, progress: &RewrapProgress) -> DbResult<()> {
+        let bytes = serde_json::to_vec(progress)
+            .map_err(|e| DatabaseError::Storage(e.to_string()))?;
+        self.storage.store(REWRAP_PROGRESS_KEY, &bytes).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto_db::encryption::test_support::test_manager;
+    use crate::crypto_db::storage::MemoryStorageEngine;
+    use std::collections::HashMap;
+
+    async fn store_record(
+        storage: &dyn StorageEngine,
+        encryption: &EncryptionManager,
+        table: &str,
+        body: &[u8],
+    ) -> String {
+        let id = Uuid::new_v4();
+        let mut record = EncryptedRecord {
+            id,
+            table_name: table.to_string(),
+            encrypted_data: Vec::new(),
+            nonce: Vec::new(),
+            mac: Vec::new(),
+            version: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            metadata: HashMap::new(),
+            wrapped_key: None,
+            key_version: None,
+        };
+        record.set_sealed(encryption.seal(body).await.unwrap());
+
+        let key = format!("{}:{}", table, id);
+        storage.store(&key, &serde_json::to_vec(&record).unwrap()).await.unwrap();
+        key
+    }
+
+    #[tokio::test]
+    async fn test_rewrap_resumes_after_restart() {
+        let config = DatabaseConfig::default();
+        let storage: Arc<dyn StorageEngine> = Arc::new(MemoryStorageEngine::new());
+        let (encryption, _dir) = test_manager(&config).await;
+        let encryption = Arc::new(encryption);
+
+        let mut keys = Vec::new();
+        for n in 0..5 {
+            let body = format!("filing {}", n);
+            keys.push((store_record(storage.as_ref(), &encryption, "filings", body.as_bytes()).await, body));
+        }
+
+        let rotation = RotationManager::new(storage.clone(), encryption.clone(), &config);
+        let started = rotation.rotate().await.unwrap();
+        assert_eq!(started.total_records, 5);
+
+        let first = rotation.run_batch(2).await.unwrap();
+        assert_eq!(first.rewrapped, 2);
+        assert!(!first.is_complete());
+
+        // A fresh manager picks up the saved cursor
+        let resumed = Arc::new(RotationManager::new(storage.clone(), encryption.clone(), &config));
+        let done = resumed.spawn(2, Duration::from_millis(1)).await.unwrap().unwrap();
+        assert!(done.is_complete());
+        assert_eq!(done.rewrapped, 5);
+        assert_eq!(done.fraction_done(), 1.0);
+
+        for (key, body) in keys {
+            let record: EncryptedRecord =
+                serde_json::from_slice(&storage.retrieve(&key).await.unwrap()).unwrap();
+            assert_eq!(record.key_version, Some(started.target_version));
+            assert_eq!(record.decrypt(&encryption).await.unwrap(), body.as_bytes());
+        }
+    }
+}
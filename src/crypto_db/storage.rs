@@ -5,7 +5,9 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::fs;
 use serde::{Deserialize, Serialize};
-use super::{StorageEngine, DatabaseError, DbResult};
+use sha2::Digest;
+use crate::db::DbPool;
+use super::{StorageEngine, DatabaseError, DbResult, WriteOp};
 
 /// File-based storage engine with compression and backup support
 pub struct FileStorageEngine {
@@ -171,6 +173,10 @@ impl StorageEngine for FileStorageEngine {
         fs::write(&file_path, &compressed).await
             .map_err(|e| DatabaseError::Storage(format!("Failed to write file: {}", e)))?;
 
+        // Record the original key beside the data file so list_keys can recover it
+        fs::write(file_path.with_extension("key"), key.as_bytes()).await
+            .map_err(|e| DatabaseError::Storage(format!("Failed to write key file: {}", e)))?;
+
         // Create backup
         self.backup_file(key, value).await?;
 
@@ -231,6 +237,11 @@ impl StorageEngine for FileStorageEngine {
             fs::remove_file(&file_path).await
                 .map_err(|e| DatabaseError::Storage(format!("Failed to delete file: {}", e)))?;
         }
+        let key_path = file_path.with_extension("key");
+        if key_path.exists() {
+            fs::remove_file(&key_path).await
+                .map_err(|e| DatabaseError::Storage(format!("Failed to delete key file: {}", e)))?;
+        }
 
         Ok(())
     }
@@ -252,16 +263,10 @@ impl StorageEngine for FileStorageEngine {
                 let path = entry.path();
                 if path.is_dir() {
                     stack.push(path);
-                } else if let Some(file_name) = path.file_stem() {
-                    if let Some(file_str) = file_name.to_str() {
-                        if file_str.ends_with(".dat") {
-                            // Extract key from filename (this is a simplified approach)
-                            // In practice, you'd maintain a separate index
-                            if let Some(key) = self.extract_key_from_file(&path).await? {
-                                if key.starts_with(prefix) {
-                                    keys.push(key);
-                                }
-                            }
+                } else if path.extension().is_some_and(|ext| ext == "dat") {
+                    if let Some(key) = self.extract_key_from_file(&path).await? {
+                        if key.starts_with(prefix) {
+                            keys.push(key);
                         }
                     }
                 }
@@ -275,38 +280,72 @@ impl StorageEngine for FileStorageEngine {
         // Process entries in parallel for better performance
         use futures::future::try_join_all;
 
-        let futures = entries.into_iter().map(|(key, value)| {
-            self.store(&key, &value)
+        let futures = entries.iter().map(|(key, value)| {
+            self.store(key, value)
         });
 
         try_join_all(futures).await?;
         Ok(())
     }
 
-    async fn transaction<F, R>(&self, op: F) -> DbResult<R>
-    where
-        F: FnOnce() -> DbResult<R> + Send,
-        R: Send,
-    {
-        // For file-based storage, we implement a simple transaction using temporary files
-        // In a production system, you'd want proper ACID transactions
-        
-        // Execute the operation
-        let result = op()?;
-        
-        // For now, we just return the result
-        // In a full implementation, you'd maintain transaction logs
-        Ok(result)
+    async fn transaction(&self, ops: Vec<WriteOp>) -> DbResult<()> {
+        // Files can't be replaced as a group, so remember what each write overwrote
+        // and put it back if a later write fails
+        let mut applied: Vec<(String, Option<Vec<u8>>)> = Vec::new();
+
+        for op in ops {
+            let key = match &op {
+                WriteOp::Put { key, .. } | WriteOp::Delete { key } => key.clone(),
+            };
+            let previous = match self.retrieve(&key).await {
+                Ok(previous) => Some(previous),
+                Err(DatabaseError::NotFound(_)) => None,
+                Err(e) => {
+                    self.undo(applied).await;
+                    return Err(e);
+                }
+            };
+            applied.push((key, previous));
+
+            let result = match op {
+                WriteOp::Put { key, value } => self.store(&key, &value).await,
+                WriteOp::Delete { key } => self.delete(&key).await,
+            };
+            if let Err(e) = result {
+                self.undo(applied).await;
+                return Err(e);
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl FileStorageEngine {
-    /// Extract the original key from a file (simplified implementation)
-    async fn extract_key_from_file(&self, _path: &std::path::Path) -> DbResult<Option<String>> {
-        // This is a simplified implementation
-        // In practice, you'd maintain a separate index file that maps file hashes to keys
-        // For now, return None to indicate we can't extract the key
-        Ok(None)
+    /// Restore the values overwritten by a failed transaction, newest first
+    async fn undo(&self, applied: Vec<(String, Option<Vec<u8>>)>) {
+        for (key, previous) in applied.into_iter().rev() {
+            let restored = match previous {
+                Some(value) => self.store(&key, &value).await,
+                None => self.delete(&key).await,
+            };
+            if let Err(e) = restored {
+                tracing::error!("Failed to roll back {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Extract the original key of a data file from its `.key` sidecar
+    ///
+    /// Files written before sidecars existed have none and are skipped.
+    async fn extract_key_from_file(&self, path: &std::path::Path) -> DbResult<Option<String>> {
+        match fs::read(path.with_extension("key")).await {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|e| DatabaseError::Storage(format!("Corrupt key file: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(DatabaseError::Storage(format!("Failed to read key file: {}", e))),
+        }
     }
 }
 
@@ -367,13 +406,127 @@ impl StorageEngine for MemoryStorageEngine {
         Ok(())
     }
 
-    async fn transaction<F, R>(&self, op: F) -> DbResult<R>
-    where
-        F: FnOnce() -> DbResult<R> + Send,
-        R: Send,
-    {
-        // For in-memory storage, transactions are atomic by nature
-        op()
+    async fn transaction(&self, ops: Vec<WriteOp>) -> DbResult<()> {
+        // Holding the write lock for every op makes the group atomic to readers
+        let mut data = self.data.write().await;
+        for op in ops {
+            match op {
+                WriteOp::Put { key, value } => {
+                    data.insert(key, value);
+                }
+                WriteOp::Delete { key } => {
+                    data.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// SQLite storage engine sharing the application's connection pool
+///
+/// Values live in the `crypto_store` table, so encrypted records sit in the same
+/// database file as the rest of the case data. Batches and transactions run inside a
+/// single SQLite transaction.
+pub struct SqliteStorageEngine {
+    pool: DbPool,
+}
+
+impl SqliteStorageEngine {
+    /// Use `pool`, creating the `crypto_store` table if migrations haven't
+    pub async fn new(pool: DbPool) -> DbResult<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS crypto_store (
+                key TEXT PRIMARY KEY,
+                value BLOB NOT NULL,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(sqlite_error)?;
+
+        Ok(Self { pool })
+    }
+}
+
+const SQLITE_UPSERT: &str = "INSERT INTO crypto_store (key, value, updated_at)
+    VALUES (?, ?, CURRENT_TIMESTAMP)
+    ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at";
+
+const SQLITE_DELETE: &str = "DELETE FROM crypto_store WHERE key = ?";
+
+fn sqlite_error(e: sqlx::Error) -> DatabaseError {
+    DatabaseError::Storage(format!("SQLite error: {}", e))
+}
+
+#[async_trait]
+impl StorageEngine for SqliteStorageEngine {
+    async fn store(&self, key: &str, value: &[u8]) -> DbResult<()> {
+        sqlx::query(SQLITE_UPSERT)
+            .bind(key)
+            .bind(value)
+            .execute(&self.pool)
+            .await
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    async fn retrieve(&self, key: &str) -> DbResult<Vec<u8>> {
+        sqlx::query_scalar::<_, Vec<u8>>("SELECT value FROM crypto_store WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(sqlite_error)?
+            .ok_or_else(|| DatabaseError::NotFound(key.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> DbResult<()> {
+        sqlx::query(SQLITE_DELETE)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    async fn list_keys(&self, prefix: &str) -> DbResult<Vec<String>> {
+        // substr rather than LIKE, which is case-insensitive and treats % and _ as wildcards
+        sqlx::query_scalar::<_, String>(
+            "SELECT key FROM crypto_store WHERE substr(key, 1, ?) = ? ORDER BY key",
+        )
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(sqlite_error)
+    }
+
+    async fn batch_store(&self, entries: Vec<(String, Vec<u8>)>) -> DbResult<()> {
+        self.transaction(
+            entries
+                .into_iter()
+                .map(|(key, value)| WriteOp::Put { key, value })
+                .collect(),
+        )
+        .await
+    }
+
+    async fn transaction(&self, ops: Vec<WriteOp>) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(sqlite_error)?;
+
+        // Returning early drops `tx`, which rolls every write back
+        for op in ops {
+            match op {
+                WriteOp::Put { key, value } => {
+                    sqlx::query(SQLITE_UPSERT).bind(key).bind(value).execute(&mut tx).await
+                }
+                WriteOp::Delete { key } => sqlx::query(SQLITE_DELETE).bind(key).execute(&mut tx).await,
+            }
+            .map_err(sqlite_error)?;
+        }
+
+        tx.commit().await.map_err(sqlite_error)
     }
 }
 
@@ -395,10 +548,12 @@ mod tests {
         engine.store(key, value).await.unwrap();
         let retrieved = engine.retrieve(key).await.unwrap();
         assert_eq!(value, retrieved.as_slice());
+        assert_eq!(engine.list_keys("test_").await.unwrap(), vec![key.to_string()]);
 
         // Test delete
         engine.delete(key).await.unwrap();
         assert!(engine.retrieve(key).await.is_err());
+        assert!(engine.list_keys("test_").await.unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -439,6 +594,68 @@ mod tests {
         assert_eq!(b"value3", value3.as_slice());
     }
 
+    #[tokio::test]
+    async fn test_sqlite_storage_engine() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let engine = SqliteStorageEngine::new(pool).await.unwrap();
+
+        engine.store("communications:1", b"first").await.unwrap();
+        engine.store("communications:1", b"second").await.unwrap();
+        engine.store("Communications_x", b"other").await.unwrap();
+        assert_eq!(engine.retrieve("communications:1").await.unwrap(), b"second");
+        assert_eq!(
+            engine.list_keys("communications:").await.unwrap(),
+            vec!["communications:1".to_string()]
+        );
+
+        engine.batch_store(vec![
+            ("communications:2".to_string(), b"two".to_vec()),
+            ("communications:3".to_string(), b"three".to_vec()),
+        ]).await.unwrap();
+        assert_eq!(engine.list_keys("communications:").await.unwrap().len(), 3);
+
+        engine.delete("communications:1").await.unwrap();
+        assert!(matches!(
+            engine.retrieve("communications:1").await,
+            Err(DatabaseError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_transaction_rolls_back() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let engine = SqliteStorageEngine::new(pool.clone()).await.unwrap();
+        engine.store("record:1", b"original").await.unwrap();
+
+        // A trigger makes the last write fail after the earlier ones were applied
+        sqlx::query(
+            "CREATE TRIGGER reject_poison BEFORE INSERT ON crypto_store
+             WHEN NEW.key = 'poison' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let result = engine.transaction(vec![
+            WriteOp::Put { key: "record:1".to_string(), value: b"changed".to_vec() },
+            WriteOp::Put { key: "record:2".to_string(), value: b"new".to_vec() },
+            WriteOp::Delete { key: "record:1".to_string() },
+            WriteOp::Put { key: "poison".to_string(), value: Vec::new() },
+        ]).await;
+        assert!(result.is_err());
+
+        assert_eq!(engine.retrieve("record:1").await.unwrap(), b"original");
+        assert!(engine.retrieve("record:2").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_list_keys() {
         let engine = MemoryStorageEngine::new();
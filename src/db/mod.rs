@@ -1,7 +1,33 @@
 use sqlx::{sqlite::SqlitePool, Pool, Sqlite};
 use std::path::Path;
+use std::sync::Arc;
 use tracing::info;
 
+use crate::crypto_db::index::{IndexField, IndexFieldType};
+use crate::crypto_db::storage::SqliteStorageEngine;
+use crate::crypto_db::{CryptoDatabase, DatabaseConfig, DbResult};
+
+/// Fields of secure communications searchable only through blind index tokens
+const SECURE_COMMUNICATION_FIELDS: &[&str] = &["sender", "recipient", "subject", "message_content"];
+
+/// Columns added to the base tables after they were first created, as
+/// (table, column, definition)
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("placement_denials", "ai_risk_score", "REAL DEFAULT 0.0"),
+    ("placement_denials", "ai_analysis", "TEXT"), // JSON AI analysis
+    ("timeline_events", "ai_generated", "BOOLEAN DEFAULT 0"),
+    ("timeline_events", "ai_confidence", "REAL DEFAULT 0.0"),
+    ("communications", "sentiment_score", "REAL DEFAULT 0.0"),
+    ("communications", "ai_summary", "TEXT"),
+    ("exhibits", "ai_extracted_text", "TEXT"),
+    ("exhibits", "ai_content_type", "TEXT"),
+    ("exhibits", "processing_status", "TEXT DEFAULT 'pending'"),
+    ("exhibits", "case_id", "INTEGER REFERENCES case_info(id)"),
+    ("communications", "case_id", "INTEGER REFERENCES case_info(id)"),
+    ("placement_denials", "case_id", "INTEGER REFERENCES case_info(id)"),
+    ("timeline_events", "case_id", "INTEGER REFERENCES case_info(id)"),
+];
+
 pub type DbPool = Pool<Sqlite>;
 
 pub async fn create_pool(database_url: &str) -> Result<DbPool, sqlx::Error> {
@@ -37,10 +63,52 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
 
     sqlx::query(schema).execute(pool).await?;
 
+    // SQLite has no ADD COLUMN IF NOT EXISTS, so only add what is missing
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    sqlx::query(include_str!("../../data/schema_columns.sql")).execute(pool).await?;
+
     info!("Database schema initialized");
     Ok(())
 }
 
+/// Open the encrypted record store in the same database as `pool`
+///
+/// Records are sealed under the KMS master key and kept in the `crypto_store` table.
+pub async fn create_secure_store(pool: &DbPool) -> DbResult<Arc<CryptoDatabase>> {
+    let storage = Arc::new(SqliteStorageEngine::new(pool.clone()).await?);
+    let secure = CryptoDatabase::new(storage, DatabaseConfig::default()).await?;
+
+    for name in SECURE_COMMUNICATION_FIELDS {
+        secure
+            .define_index(
+                "communications",
+                IndexField {
+                    name: name.to_string(),
+                    field_type: IndexFieldType::String,
+                    is_unique: false,
+                    is_encrypted: true,
+                    enable_bloom_filter: false,
+                },
+            )
+            .await?;
+    }
+
+    info!("Encrypted record store ready");
+    Ok(Arc::new(secure))
+}
+
 pub async fn seed_sample_data(pool: &DbPool) -> Result<(), sqlx::Error> {
     info!("Seeding sample placement incident data");
 
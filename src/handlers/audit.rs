@@ -0,0 +1,73 @@
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::legal::audit_log::{AuditLogger, AuditSearchCriteria, ExportFormat};
+use crate::legal::{AuditLogEntry, ComplianceStatus, DataClassification, LegalOperationType};
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+}
+
+// Download the audit trail; the export itself is recorded before it is sent
+pub async fn export_audit_log(
+    Extension(audit): Extension<AuditLogger>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(criteria): Query<AuditSearchCriteria>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let format = params.format.unwrap_or(ExportFormat::JSON);
+
+    let mut details = HashMap::new();
+    details.insert("format".to_string(), json!(format));
+    details.insert("criteria".to_string(), json!(criteria));
+    let entry = AuditLogEntry {
+        entry_id: uuid::Uuid::new_v4().to_string(),
+        user_id: user.user_id.clone(),
+        operation_type: LegalOperationType::AuditLogExport,
+        timestamp: Utc::now(),
+        operation_details: details,
+        compliance_status: ComplianceStatus::Compliant,
+        attorney_review_required: false,
+        data_processed: DataClassification::Confidential,
+    };
+    if let Err(e) = audit.log_operation(&entry).await {
+        tracing::error!("Refusing audit export for {}: {}", user.user_id, e);
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let (content_type, extension) = match format {
+        ExportFormat::JSON => ("application/json", "json"),
+        ExportFormat::CSV => ("text/csv", "csv"),
+        ExportFormat::PDF => ("application/pdf", "pdf"),
+        ExportFormat::XML => ("application/xml", "xml"),
+    };
+    match audit.export_audit_log(&criteria, format).await {
+        Ok(body) => {
+            tracing::warn!("User {} exported the audit log", user.user_id);
+            let disposition = format!(
+                "attachment; filename=\"audit-log-{}.{}\"",
+                Utc::now().format("%Y%m%dT%H%M%SZ"),
+                extension
+            );
+            Ok((
+                [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+                body,
+            )
+                .into_response())
+        }
+        Err(e) => {
+            tracing::error!("Audit export failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
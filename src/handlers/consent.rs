@@ -0,0 +1,192 @@
+use axum::{
+    extract::{ConnectInfo, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::legal::access_control::UserRole;
+use crate::legal::access_middleware::AuthenticatedSession;
+use crate::legal::audit_log::ExportFormat;
+use crate::legal::consent::{ConsentManager, ConsentSignature};
+use crate::legal::{ConsentRecord, LegalOperationType};
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentRequest {
+    pub operation_type: LegalOperationType,
+    pub form_version: u32,
+    pub consent_given: bool,
+    pub disclaimer_acknowledged: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WithdrawRequest {
+    pub operation_type: LegalOperationType,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    pub user_id: Option<String>,
+    pub format: Option<ExportFormat>,
+}
+
+// Current consent form for an operation
+pub async fn consent_form(
+    Extension(consent): Extension<ConsentManager>,
+    Path(operation_type): Path<String>,
+) -> Result<Html<String>, StatusCode> {
+    let operation_type = parse_operation(&operation_type)?;
+    match consent.get_consent_form_html(&operation_type).await {
+        Ok(html) => Ok(Html(html)),
+        Err(e) => {
+            tracing::error!("Failed to load consent form for {:?}: {}", operation_type, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Record a consent or refusal against the form version the user was shown
+pub async fn record_consent(
+    Extension(consent): Extension<ConsentManager>,
+    Extension(session): Extension<AuthenticatedSession>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<ConsentRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let form = consent.current_form(&request.operation_type).await.map_err(|e| {
+        tracing::error!("Failed to load consent form for {:?}: {}", request.operation_type, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if form.version != request.form_version {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let signature = signature(peer.ip(), &headers, consent.trusted_proxies());
+    let record = ConsentRecord {
+        user_id: session.user_id.clone(),
+        operation_type: request.operation_type,
+        consent_given: request.consent_given,
+        timestamp: chrono::Utc::now(),
+        ip_address: signature.ip_address,
+        user_agent: signature.user_agent,
+        consent_text: form.consent_text,
+        form_version: Some(form.version),
+        attorney_supervised: session.role == UserRole::Attorney,
+        disclaimer_acknowledged: request.disclaimer_acknowledged,
+    };
+    match consent.record_consent(record).await {
+        Ok(entry) => Ok(Json(json!({
+            "success": true,
+            "entry": entry
+        }))),
+        Err(e) => {
+            tracing::warn!("Consent from {} rejected: {}", session.user_id, e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+pub async fn withdraw_consent(
+    Extension(consent): Extension<ConsentManager>,
+    Extension(session): Extension<AuthenticatedSession>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<WithdrawRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let signature = signature(peer.ip(), &headers, consent.trusted_proxies());
+    match consent
+        .withdraw_consent(&session.user_id, &request.operation_type, request.reason, signature)
+        .await
+    {
+        Ok(entry) => Ok(Json(json!({
+            "success": true,
+            "entry": entry
+        }))),
+        Err(e) => {
+            tracing::warn!("Consent withdrawal by {} failed: {}", session.user_id, e);
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+pub async fn consent_status(
+    Extension(consent): Extension<ConsentManager>,
+    Extension(session): Extension<AuthenticatedSession>,
+    Path(operation_type): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let operation_type = parse_operation(&operation_type)?;
+    let status = consent.check_consent(&session.user_id, &operation_type).await;
+    Ok(Json(json!({
+        "success": true,
+        "operation_type": operation_type,
+        "status": status
+    })))
+}
+
+// Consent report for the caller, or for anyone when an attorney or administrator asks
+pub async fn consent_report(
+    Extension(consent): Extension<ConsentManager>,
+    Extension(session): Extension<AuthenticatedSession>,
+    Query(params): Query<ReportQuery>,
+) -> Result<Response, StatusCode> {
+    let user_id = params.user_id.unwrap_or_else(|| session.user_id.clone());
+    if user_id != session.user_id && !matches!(session.role, UserRole::Attorney | UserRole::Administrator) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let format = params.format.unwrap_or(ExportFormat::JSON);
+    let content_type = match format {
+        ExportFormat::JSON => "application/json",
+        ExportFormat::CSV => "text/csv",
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    match consent.export_consent_report(&user_id, &format).await {
+        Ok(body) => {
+            if user_id != session.user_id {
+                tracing::info!("Consent report for {} exported by {}", user_id, session.user_id);
+            }
+            Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+        }
+        Err(e) => {
+            tracing::error!("Failed to export consent report for {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn parse_operation(name: &str) -> Result<LegalOperationType, StatusCode> {
+    serde_json::from_value(Value::String(name.to_string())).map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Client address and browser; the address is the connecting peer unless that
+/// peer is one of our trusted proxies
+fn signature(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> ConsentSignature {
+    let header_value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+
+    // Forwarded headers are client-controlled unless a proxy we run set them;
+    // behind one, the client is the last hop that is not another trusted proxy
+    let mut client = peer;
+    if trusted_proxies.contains(&peer) {
+        let forwarded = header_value("x-forwarded-for").map(|value| {
+            value
+                .rsplit(',')
+                .map(|hop| hop.trim().parse::<IpAddr>())
+                .take_while(Result::is_ok)
+                .flatten()
+                .find(|hop| !trusted_proxies.contains(hop))
+        });
+        client = match forwarded {
+            Some(hop) => hop.unwrap_or(client),
+            None => header_value("x-real-ip").and_then(|value| value.parse().ok()).unwrap_or(client),
+        };
+    }
+
+    ConsentSignature {
+        ip_address: Some(client.to_string()),
+        user_agent: header_value(header::USER_AGENT.as_str()).map(str::to_string),
+    }
+}
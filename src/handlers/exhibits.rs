@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::Row;
+use std::collections::BTreeMap;
+
+use crate::db::DbPool;
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::legal::pii::{detector, PiiKind, PiiMode, Pseudonymizer};
+use crate::legal::retention::{purge_exhibit, RecordKind, RetentionScheduler};
+
+// Delete an exhibit with its embeddings, transcript segments and index entries,
+// unless its matter is under a legal hold
+pub async fn delete_exhibit(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Extension(retention): Extension<RetentionScheduler>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    // Exhibits on matters the caller is kept out of look like missing ones
+    let case_id: Option<Option<i64>> = sqlx::query_scalar("SELECT case_id FROM exhibits WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load exhibit {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    match case_id {
+        Some(case_id) if user.matters.allows(case_id) => {}
+        _ => return Err(StatusCode::NOT_FOUND),
+    }
+
+    // Deleting material under a legal hold would be spoliation
+    match retention.hold_for(&RecordKind::Exhibit, &id.to_string()).await {
+        Ok(None) => {}
+        Ok(Some(hold)) => {
+            tracing::warn!("Exhibit {} deletion by {} refused: legal hold {}", id, user.user_id, hold.hold_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            tracing::error!("Failed to check legal holds for exhibit {}: {}", id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match purge_exhibit(&pool, id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to delete exhibit {}: {}", id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    tracing::warn!("Exhibit {} deleted by {}", id, user.user_id);
+
+    Ok(Json(json!({
+        "success": true,
+        "id": id
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `redact` (default) or `pseudonymize`
+    #[serde(default)]
+    pub mode: PiiMode,
+}
+
+// Export an exhibit's text with personal data removed. Pseudonyms are
+// consistent within one export but the mapping is not kept, and minors'
+// names are replaced in either mode.
+pub async fn export_exhibit(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let row = sqlx::query(&format!(
+        "SELECT exhibit_label, document_name, description, category, ai_extracted_text
+         FROM exhibits WHERE id = ? AND {}",
+        user.matters.sql_filter("case_id")
+    ))
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load exhibit {} for export: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let segments: Vec<String> = sqlx::query_scalar(
+        "SELECT text FROM transcript_segments WHERE exhibit_id = ? ORDER BY segment_index",
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load transcript for exhibit {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Protected together so a minor named in one field is removed from all
+    let fields = ["exhibit_label", "document_name", "description", "ai_extracted_text"];
+    let mut texts: Vec<String> = fields
+        .iter()
+        .map(|field| row.get::<Option<String>, _>(*field).unwrap_or_default())
+        .collect();
+    texts.extend(segments);
+    let mut protected = detector()
+        .protect_all(&texts, params.mode, &mut Pseudonymizer::new())
+        .into_iter();
+
+    let mut counts: BTreeMap<PiiKind, usize> = BTreeMap::new();
+    let mut next = || {
+        let text = protected.next().unwrap_or_default();
+        for (kind, count) in text.counts {
+            *counts.entry(kind).or_insert(0) += count;
+        }
+        text.text
+    };
+    let exhibit = json!({
+        "id": id,
+        "exhibit_label": next(),
+        "document_name": next(),
+        "description": next(),
+        "category": row.get::<Option<String>, _>("category"),
+        "extracted_text": next(),
+        "transcript": (fields.len()..texts.len()).map(|_| next()).collect::<Vec<_>>(),
+    });
+
+    let removed: usize = counts.values().sum();
+    tracing::info!("Exhibit {} exported by {} with {} PII spans removed", id, user.user_id, removed);
+
+    Ok(Json(json!({
+        "success": true,
+        "mode": params.mode,
+        "exhibit": exhibit,
+        "pii_removed": counts.iter().map(|(kind, count)| (kind.label(), *count)).collect::<BTreeMap<_, _>>(),
+    })))
+}
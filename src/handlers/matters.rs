@@ -0,0 +1,302 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::db::DbPool;
+use crate::legal::access_control::{AccessController, UserRole};
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::legal::conflict_check::{ConflictChecker, ConflictDecision};
+use crate::models::requests::CreateCaseRequest;
+
+#[derive(Debug, Deserialize)]
+pub struct OpenMatterRequest {
+    pub docket_number: String,
+    pub court: String,
+    #[serde(flatten)]
+    pub case: CreateCaseRequest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatterMemberRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWallRequest {
+    pub name: String,
+    pub reason: String,
+    pub members: Vec<String>,
+    pub case_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConflictReviewRequest {
+    pub decision: ConflictDecision,
+    pub notes: Option<String>,
+}
+
+// Screen a proposed matter's parties against existing matters and correspondents
+pub async fn conflict_check(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(mut request): Json<CreateCaseRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    request.sanitize();
+    if request.validate().is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match ConflictChecker::new(pool).check(&request, &user.user_id).await {
+        Ok(report) => {
+            let report = report.visible_to(&user.matters);
+            Ok(Json(json!({
+                "success": true,
+                "report": report,
+                "text": report.to_text()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Conflict check failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Open a matter and record its parties for later conflict checks
+pub async fn open_matter(
+    State(pool): State<DbPool>,
+    Json(mut payload): Json<OpenMatterRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    payload.case.sanitize();
+    if payload.case.validate().is_err()
+        || payload.docket_number.trim().is_empty()
+        || payload.court.trim().is_empty()
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match ConflictChecker::new(pool)
+        .open_matter(&payload.case, &payload.docket_number, &payload.court)
+        .await
+    {
+        Ok(case_id) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to open matter '{}': {}", payload.case.title, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn get_conflict_report(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(report_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    match ConflictChecker::new(pool).get_report(&report_id).await {
+        Ok(Some(report)) => {
+            let report = report.visible_to(&user.matters);
+            Ok(Json(json!({
+                "success": true,
+                "report": report,
+                "text": report.to_text()
+            })))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load conflict report {}: {}", report_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Only attorneys who can see every hit may clear, waive or decline a conflict
+pub async fn review_conflict_report(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(report_id): Path<String>,
+    Json(payload): Json<ConflictReviewRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    if user.role != UserRole::Attorney {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let checker = ConflictChecker::new(pool);
+    match checker.get_report(&report_id).await {
+        Ok(Some(report)) if report.visible_to(&user.matters).withheld_hits > 0 => {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load conflict report {}: {}", report_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match checker
+        .review(&report_id, &user.user_id, payload.decision, payload.notes)
+        .await
+    {
+        Ok(report) => Ok(Json(json!({
+            "success": true,
+            "report": report
+        }))),
+        Err(e) => {
+            tracing::warn!("Conflict review of {} rejected: {}", report_id, e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+pub async fn matter_parties(
+    State(pool): State<DbPool>,
+    Path(case_id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    match ConflictChecker::new(pool).parties(case_id).await {
+        Ok(parties) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id,
+            "parties": parties
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load parties for case {}: {}", case_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// A matter's access list and the walls screening anyone from it
+pub async fn matter_access(
+    Extension(access): Extension<AccessController>,
+    Path(case_id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    let matters = access.matters();
+    let loaded = async {
+        Ok::<_, String>((
+            matters.is_restricted(case_id).await?,
+            matters.matter_team(case_id).await?,
+            matters.walls_for_matter(case_id).await?,
+        ))
+    }
+    .await;
+
+    match loaded {
+        Ok((restricted, team, walls)) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id,
+            "restricted": restricted,
+            "team": team,
+            "walls": walls
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load access for case {}: {}", case_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Put a user on a matter's access list; the first grant closes the matter to everyone else
+pub async fn grant_matter_access(
+    Extension(access): Extension<AccessController>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(case_id): Path<i64>,
+    Json(request): Json<MatterMemberRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.matters().grant_matter_access(case_id, &request.user_id, &user.user_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id,
+            "user_id": request.user_id
+        }))),
+        Err(e) => {
+            tracing::warn!("{} could not add {} to case {}: {}", user.user_id, request.user_id, case_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+pub async fn revoke_matter_access(
+    Extension(access): Extension<AccessController>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path((case_id, member)): Path<(i64, String)>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.matters().revoke_matter_access(case_id, &member, &user.user_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id,
+            "user_id": member
+        }))),
+        Err(e) => {
+            tracing::warn!("{} could not remove {} from case {}: {}", user.user_id, member, case_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+// Screen users from matters they must not work on
+pub async fn create_wall(
+    Extension(access): Extension<AccessController>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(request): Json<CreateWallRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match access
+        .matters()
+        .create_wall(&request.name, &request.reason, &user.user_id, &request.members, &request.case_ids)
+        .await
+    {
+        Ok(wall) => Ok(Json(json!({
+            "success": true,
+            "wall": wall
+        }))),
+        Err(e) => {
+            tracing::warn!("{} could not create ethical wall '{}': {}", user.user_id, request.name, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+pub async fn add_wall_member(
+    Extension(access): Extension<AccessController>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(wall_id): Path<String>,
+    Json(request): Json<MatterMemberRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.matters().add_wall_member(&wall_id, &request.user_id, &user.user_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "wall_id": wall_id,
+            "user_id": request.user_id
+        }))),
+        Err(e) => {
+            tracing::warn!("{} could not add {} to ethical wall {}: {}", user.user_id, request.user_id, wall_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+// Stop screening a wall's members; matter access lists are not restored
+pub async fn lift_wall(
+    Extension(access): Extension<AccessController>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(wall_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.matters().lift_wall(&wall_id, &user.user_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "wall_id": wall_id
+        }))),
+        Err(e) => {
+            tracing::warn!("{} could not lift ethical wall {}: {}", user.user_id, wall_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{extract::Path, http::StatusCode, response::Json, Extension};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::legal::access_control::AccessController;
+use crate::legal::access_middleware::AuthenticatedSession;
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+// Start enrolling an authenticator app; the URI is rendered as a QR code
+pub async fn begin_enrollment(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().begin_enrollment(&session.user_id, &session.token).await {
+        Ok(enrollment) => Ok(Json(json!({
+            "success": true,
+            "secret": enrollment.secret,
+            "provisioning_uri": enrollment.provisioning_uri
+        }))),
+        Err(e) => {
+            tracing::warn!("MFA enrollment for {} refused: {}", session.user_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+// Confirm the authenticator with its first code; recovery codes are shown once
+pub async fn confirm_enrollment(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+    Json(request): Json<MfaCodeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().confirm_enrollment(&session.user_id, &session.token, &request.code).await {
+        Ok(recovery_codes) => Ok(Json(json!({
+            "success": true,
+            "recovery_codes": recovery_codes
+        }))),
+        Err(e) => {
+            tracing::warn!("MFA confirmation for {} failed: {}", session.user_id, e);
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+// Verify this session, or step it up, with a TOTP or recovery code
+pub async fn verify(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+    Json(request): Json<MfaCodeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().verify(&session.user_id, &session.token, &request.code).await {
+        Ok(method) => Ok(Json(json!({
+            "success": true,
+            "method": method
+        }))),
+        Err(e) => {
+            tracing::warn!("MFA verification for {} failed: {}", session.user_id, e);
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+pub async fn status(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().status(&session.user_id, &session.token).await {
+        Ok(status) => Ok(Json(json!({
+            "success": true,
+            "status": status
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load MFA status for {}: {}", session.user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Replace the recovery codes; needs a recent verification
+pub async fn regenerate_recovery_codes(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().regenerate_recovery_codes(&session.user_id, &session.token).await {
+        Ok(recovery_codes) => Ok(Json(json!({
+            "success": true,
+            "recovery_codes": recovery_codes
+        }))),
+        Err(e) => {
+            tracing::warn!("Recovery code regeneration for {} refused: {}", session.user_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+// Remove another user's authenticator so they can enroll again
+pub async fn reset(
+    Extension(access): Extension<AccessController>,
+    Extension(session): Extension<AuthenticatedSession>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    match access.mfa().reset(&user_id, &session.user_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "user_id": user_id
+        }))),
+        Err(e) => {
+            tracing::warn!("MFA reset of {} by {} refused: {}", user_id, session.user_id, e);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
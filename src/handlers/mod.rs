@@ -1,11 +1,23 @@
+pub mod audit;
+pub mod consent;
+pub mod exhibits;
 pub mod legal_analysis;
+pub mod matters;
+pub mod mfa;
 pub mod powerpoint_automation;
+pub mod retention;
+pub mod risk;
+pub mod search;
+pub mod secure;
+pub mod sso;
+pub mod timeline;
+pub mod tone;
 
 use axum::{
     extract::{Query, State},
     http::StatusCode,
     response::{Html, Json},
-    Json as AxumJson,
+    Extension, Json as AxumJson,
 };
 
 use serde::Deserialize;
@@ -16,9 +28,13 @@ use std::collections::HashMap;
 // Re-export models
 use crate::ai::{
     core_engine::{AdvancedPromptRequest, AiCoreEngine, InputType},
-    AiConfig,
+    risk_model::{CaseHistory, LogisticRiskModel},
+    transcription::store_transcript,
+    AiConfig, AiError,
 };
 use crate::db::DbPool;
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::legal::ethical_walls::MatterScope;
 use crate::models::*;
 
 // Health check endpoint
@@ -38,17 +54,25 @@ pub async fn dashboard() -> Html<String> {
     Html(html)
 }
 
-// Dashboard data API endpoint
-pub async fn dashboard_data(State(pool): State<DbPool>) -> Result<Json<Value>, StatusCode> {
+// Dashboard data API endpoint, limited to the caller's matters
+pub async fn dashboard_data(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, StatusCode> {
+    let in_scope = user.matters.sql_filter("case_id");
+
     // Get basic stats
-    let stats_query = sqlx::query(
+    let stats_sql = format!(
         "SELECT 
             COUNT(*) as total_incidents,
             COALESCE(SUM(duration_hours), 0.0) as total_hours,
             COALESCE(AVG(duration_hours), 0.0) as avg_duration,
             COUNT(CASE WHEN denied_date LIKE '2024-06%' THEN 1 END) as this_month
-         FROM placement_denials",
+         FROM placement_denials
+         WHERE {}",
+        in_scope
     );
+    let stats_query = sqlx::query(&stats_sql);
 
     let stats_row = stats_query
         .fetch_one(&pool)
@@ -63,14 +87,17 @@ pub async fn dashboard_data(State(pool): State<DbPool>) -> Result<Json<Value>, S
     });
 
     // Monthly trend
-    let monthly_query = sqlx::query(
+    let monthly_sql = format!(
         "SELECT 
             substr(denied_date, 1, 7) as month,
             COUNT(*) as count
          FROM placement_denials 
+         WHERE {}
          GROUP BY substr(denied_date, 1, 7)
          ORDER BY month",
+        in_scope
     );
+    let monthly_query = sqlx::query(&monthly_sql);
 
     let monthly_rows = monthly_query
         .fetch_all(&pool)
@@ -87,14 +114,17 @@ pub async fn dashboard_data(State(pool): State<DbPool>) -> Result<Json<Value>, S
         .collect();
 
     // Categories
-    let category_query = sqlx::query(
+    let category_sql = format!(
         "SELECT 
             violation_category as category,
             COUNT(*) as count
          FROM placement_denials 
+         WHERE {}
          GROUP BY violation_category
          ORDER BY count DESC",
+        in_scope
     );
+    let category_query = sqlx::query(&category_sql);
 
     let category_rows = category_query
         .fetch_all(&pool)
@@ -111,12 +141,15 @@ pub async fn dashboard_data(State(pool): State<DbPool>) -> Result<Json<Value>, S
         .collect();
 
     // Recent incidents
-    let recent_query = sqlx::query(
+    let recent_sql = format!(
         "SELECT denied_date, denial_reason, duration_hours
          FROM placement_denials 
+         WHERE {}
          ORDER BY denied_date DESC 
          LIMIT 10",
+        in_scope
     );
+    let recent_query = sqlx::query(&recent_sql);
 
     let recent_rows = recent_query
         .fetch_all(&pool)
@@ -144,6 +177,7 @@ pub async fn dashboard_data(State(pool): State<DbPool>) -> Result<Json<Value>, S
 // Advanced AI prompt endpoint with multi-modal capabilities
 pub async fn ai_prompt(
     State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
     Json(payload): Json<Value>,
 ) -> Result<Json<Value>, StatusCode> {
     let prompt = payload["prompt"].as_str().unwrap_or("").to_string();
@@ -152,22 +186,26 @@ pub async fn ai_prompt(
     let style_preference = payload["style"].as_str().map(|s| s.to_string());
 
     // Initialize AI Core Engine
-    let ai_config = AiConfig::default();
-    let ai_engine = AiCoreEngine::new(ai_config);
+    let ai_engine = build_ai_engine(&pool).await;
 
     // Gather current dashboard context
     let mut context = std::collections::HashMap::new();
 
     // Add dashboard statistics to context
-    if let Ok(stats) = get_quick_stats(&pool).await {
+    if let Ok(stats) = get_quick_stats(&pool, &user.matters).await {
         context.insert("current_stats".to_string(), stats);
     }
 
     // Add recent incidents to context
-    if let Ok(recent_data) = get_recent_dashboard_data(&pool).await {
+    if let Ok(recent_data) = get_recent_dashboard_data(&pool, &user.matters).await {
         context.insert("recent_data".to_string(), recent_data);
     }
 
+    // Add engineered risk features for model-backed risk alerts
+    if let Ok(risk_features) = get_recent_risk_features(&pool, &user.matters).await {
+        context.insert("risk_features".to_string(), risk_features);
+    }
+
     // Determine input type
     let parsed_input_type = match input_type {
         "voice" => InputType::Voice,
@@ -212,28 +250,34 @@ pub async fn ai_prompt(
         Err(e) => {
             // Fallback to simple responses if AI engine fails
             tracing::warn!("AI engine failed, falling back to simple responses: {}", e);
-            let fallback_response = generate_fallback_response(&prompt, &pool).await?;
+            let fallback_response = generate_fallback_response(&prompt, &pool, &user.matters).await?;
             Ok(Json(fallback_response))
         }
     }
 }
 
 // Real-time AI monitoring endpoint
-pub async fn ai_monitor(State(pool): State<DbPool>) -> Result<Json<Value>, StatusCode> {
-    let ai_config = AiConfig::default();
-    let ai_engine = AiCoreEngine::new(ai_config);
+pub async fn ai_monitor(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, StatusCode> {
+    let ai_engine = build_ai_engine(&pool).await;
 
     // Gather current dashboard context for monitoring
     let mut context = std::collections::HashMap::new();
 
-    if let Ok(stats) = get_quick_stats(&pool).await {
+    if let Ok(stats) = get_quick_stats(&pool, &user.matters).await {
         context.insert("current_stats".to_string(), stats);
     }
 
-    if let Ok(recent_data) = get_recent_dashboard_data(&pool).await {
+    if let Ok(recent_data) = get_recent_dashboard_data(&pool, &user.matters).await {
         context.insert("recent_data".to_string(), recent_data);
     }
 
+    if let Ok(risk_features) = get_recent_risk_features(&pool, &user.matters).await {
+        context.insert("risk_features".to_string(), risk_features);
+    }
+
     // Get proactive suggestions from AI
     match ai_engine.monitor_and_assist(&context).await {
         Ok(suggestions) => Ok(Json(json!({
@@ -256,15 +300,65 @@ pub async fn ai_monitor(State(pool): State<DbPool>) -> Result<Json<Value>, Statu
 // Voice processing endpoint
 pub async fn ai_voice(
     State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(params): Query<VoiceQuery>,
     body: axum::body::Bytes,
 ) -> Result<Json<Value>, StatusCode> {
-    let ai_config = AiConfig::default();
-    let ai_engine = AiCoreEngine::new(ai_config);
+    if !user.matters.allows(params.case_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let ai_engine = build_ai_engine(&pool).await;
+
+    let transcript = match ai_engine.transcribe(&body).await {
+        Ok(transcript) => transcript,
+        Err(AiError::InvalidInput(e)) => {
+            tracing::warn!("Rejected voice upload: {}", e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Err(e) => {
+            tracing::error!("Voice transcription failed: {}", e);
+            return Ok(Json(json!({
+                "success": false,
+                "error": format!("Voice transcription failed: {}", e)
+            })));
+        }
+    };
+
+    // Keep the recording and its transcript as evidence, linked to each other
+    let stored = match store_transcript(
+        &pool,
+        &body,
+        &transcript,
+        params.sender.as_deref(),
+        params.case_id,
+        "data/audio",
+    )
+    .await
+    {
+        Ok(stored) => Some(stored),
+        Err(e) => {
+            tracing::warn!("Failed to store transcript: {}", e);
+            None
+        }
+    };
 
-    match ai_engine.process_voice_input(&body).await {
+    // Recorded calls are only stored; dictated commands also get an AI response
+    if params.store_only.unwrap_or(false) {
+        return Ok(Json(json!({
+            "success": true,
+            "transcription": transcript.text,
+            "segments": transcript.segments,
+            "stored": stored
+        })));
+    }
+
+    match ai_engine.process_voice_transcript(&transcript).await {
         Ok(ai_response) => Ok(Json(json!({
             "success": true,
-            "transcription": "Audio processed",
+            "transcription": transcript.text,
+            "segments": transcript.segments,
+            "stored": stored,
             "response": ai_response.primary_response,
             "confidence": ai_response.confidence,
             "suggested_actions": ai_response.suggested_actions
@@ -273,20 +367,63 @@ pub async fn ai_voice(
             tracing::error!("Voice processing failed: {}", e);
             Ok(Json(json!({
                 "success": false,
+                "transcription": transcript.text,
+                "stored": stored,
                 "error": "Voice processing not available"
             })))
         }
     }
 }
 
-async fn get_quick_stats(pool: &DbPool) -> Result<Value, StatusCode> {
-    let stats_query = sqlx::query(
+#[derive(Debug, Deserialize)]
+pub struct VoiceQuery {
+    pub sender: Option<String>,
+    /// Matter the recording belongs to
+    pub case_id: Option<i64>,
+    pub store_only: Option<bool>,
+}
+
+// Build the AI engine, attaching the latest trained risk model when one exists
+async fn build_ai_engine(pool: &DbPool) -> AiCoreEngine {
+    let ai_engine = AiCoreEngine::new(AiConfig::default());
+
+    match LogisticRiskModel::load_latest(pool).await {
+        Ok(Some(model)) => ai_engine.with_risk_model(model),
+        Ok(None) => ai_engine,
+        Err(e) => {
+            tracing::warn!("Failed to load risk model: {}", e);
+            ai_engine
+        }
+    }
+}
+
+async fn get_recent_risk_features(pool: &DbPool, scope: &MatterScope) -> Result<Value, StatusCode> {
+    let history = CaseHistory::load(pool, scope)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let features: Vec<_> = history
+        .denials
+        .iter()
+        .rev()
+        .take(20)
+        .map(|denial| history.features_for(denial))
+        .collect();
+
+    serde_json::to_value(features).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_quick_stats(pool: &DbPool, scope: &MatterScope) -> Result<Value, StatusCode> {
+    let stats_sql = format!(
         "SELECT 
             COUNT(*) as total_incidents,
             COALESCE(SUM(duration_hours), 0.0) as total_hours,
             COALESCE(AVG(duration_hours), 0.0) as avg_duration
-         FROM placement_denials",
+         FROM placement_denials
+         WHERE {}",
+        scope.sql_filter("case_id")
     );
+    let stats_query = sqlx::query(&stats_sql);
 
     let stats_row = stats_query
         .fetch_one(pool)
@@ -300,13 +437,16 @@ async fn get_quick_stats(pool: &DbPool) -> Result<Value, StatusCode> {
     }))
 }
 
-async fn get_recent_dashboard_data(pool: &DbPool) -> Result<Value, StatusCode> {
-    let recent_query = sqlx::query(
+async fn get_recent_dashboard_data(pool: &DbPool, scope: &MatterScope) -> Result<Value, StatusCode> {
+    let recent_sql = format!(
         "SELECT denied_date, denial_reason, duration_hours, violation_category
          FROM placement_denials 
+         WHERE {}
          ORDER BY denied_date DESC 
          LIMIT 20",
+        scope.sql_filter("case_id")
     );
+    let recent_query = sqlx::query(&recent_sql);
 
     let recent_rows = recent_query
         .fetch_all(pool)
@@ -556,7 +696,11 @@ fn calculate_diff(content1: &str, content2: &str) -> Vec<Value> {
     diff_lines
 }
 
-async fn generate_fallback_response(prompt: &str, pool: &DbPool) -> Result<Value, StatusCode> {
+async fn generate_fallback_response(
+    prompt: &str,
+    pool: &DbPool,
+    scope: &MatterScope,
+) -> Result<Value, StatusCode> {
     // Fallback to simple keyword-based responses when AI engine is unavailable
     let response = match prompt.to_lowercase().as_str() {
         p if p.contains("statistics") || p.contains("stats") => {
@@ -564,7 +708,7 @@ async fn generate_fallback_response(prompt: &str, pool: &DbPool) -> Result<Value
                 "success": true,
                 "action": "show_stats",
                 "message": "Here's a summary of your key statistics",
-                "data": get_quick_stats(pool).await?,
+                "data": get_quick_stats(pool, scope).await?,
                 "fallback": true
             })
         }
@@ -0,0 +1,231 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::import_wizard::classifier::classify_file;
+use crate::import_wizard::metadata::FileMetadata;
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::legal::retention::{RecordKind, RetentionDecision, RetentionScheduler};
+use crate::legal::DataClassification;
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleFileRequest {
+    pub file_path: String,
+}
+
+/// The matter and the start of the retention period come from the exhibit itself
+#[derive(Debug, Deserialize)]
+pub struct ScheduleRecordRequest {
+    pub record_kind: RecordKind,
+    pub record_id: String,
+    pub data_classification: DataClassification,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    #[serde(flatten)]
+    pub decision: RetentionDecision,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HoldRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CertificateQuery {
+    pub case_id: Option<i64>,
+}
+
+// Schedule an imported file with the retention its classification calls for.
+// Only files imported for a matter can be scheduled, and anything else is a
+// plain 404 so the route can't be used to probe the filesystem.
+pub async fn schedule_file(
+    Extension(retention): Extension<RetentionScheduler>,
+    Json(request): Json<ScheduleFileRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let file = match retention.matter_file(&request.file_path).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to look up {}: {}", request.file_path, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let metadata = FileMetadata::from_file(&file.record_id).await.map_err(|e| {
+        tracing::warn!("Cannot schedule {}: {}", file.record_id, e);
+        StatusCode::NOT_FOUND
+    })?;
+    let classification = classify_file(&file.record_id, &metadata);
+
+    match retention.schedule_file(&file, &classification).await {
+        Ok(item) => Ok(Json(json!({
+            "success": true,
+            "classification": classification,
+            "item": item
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to schedule {}: {}", file.record_id, e);
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+// Schedule an exhibit; files go through /api/retention/files
+pub async fn schedule_record(
+    Extension(retention): Extension<RetentionScheduler>,
+    Json(request): Json<ScheduleRecordRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let exhibit_id = match request.record_kind {
+        RecordKind::Exhibit => request.record_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        RecordKind::File => return Err(StatusCode::BAD_REQUEST),
+    };
+    let exhibit = match retention.matter_exhibit(exhibit_id).await {
+        Ok(Some(exhibit)) => exhibit,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to look up exhibit {}: {}", exhibit_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match retention.schedule_record(&exhibit, request.data_classification).await {
+        Ok(item) => Ok(Json(json!({
+            "success": true,
+            "item": item
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to schedule {}: {}", request.record_id, e);
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+// Items waiting for an attorney to approve or extend before disposal
+pub async fn review_queue(
+    Extension(retention): Extension<RetentionScheduler>,
+) -> Result<Json<Value>, StatusCode> {
+    if let Err(e) = retention.queue_due_for_review(Utc::now()).await {
+        tracing::warn!("Failed to refresh the retention review queue: {}", e);
+    }
+    match retention.review_queue().await {
+        Ok(items) => Ok(Json(json!({
+            "success": true,
+            "items": items
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load the retention review queue: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn review_item(
+    Extension(retention): Extension<RetentionScheduler>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+    Json(request): Json<ReviewRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.decide(id, &user.user_id, request.decision, request.notes).await {
+        Ok(item) => Ok(Json(json!({
+            "success": true,
+            "item": item
+        }))),
+        Err(e) => {
+            tracing::warn!("Retention review of {} by {} rejected: {}", id, user.user_id, e);
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+// Destroy approved items that are past their disposition date
+pub async fn dispose_due(
+    Extension(retention): Extension<RetentionScheduler>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.dispose_due(Utc::now(), &user.user_id).await {
+        Ok(certificates) => Ok(Json(json!({
+            "success": true,
+            "certificates": certificates
+        }))),
+        Err(e) => {
+            tracing::error!("Retention disposal run by {} failed: {}", user.user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn certificates(
+    Extension(retention): Extension<RetentionScheduler>,
+    Query(params): Query<CertificateQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.certificates(params.case_id).await {
+        Ok(certificates) => Ok(Json(json!({
+            "success": true,
+            "certificates": certificates
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load disposition certificates: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn matter_holds(
+    Extension(retention): Extension<RetentionScheduler>,
+    Path(case_id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.active_holds(Some(case_id)).await {
+        Ok(holds) => Ok(Json(json!({
+            "success": true,
+            "case_id": case_id,
+            "holds": holds
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load legal holds for case {}: {}", case_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn place_hold(
+    Extension(retention): Extension<RetentionScheduler>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(case_id): Path<i64>,
+    Json(request): Json<HoldRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.place_hold(case_id, &request.reason, &user.user_id).await {
+        Ok(hold) => Ok(Json(json!({
+            "success": true,
+            "hold": hold
+        }))),
+        Err(e) => {
+            tracing::warn!("Legal hold on case {} by {} rejected: {}", case_id, user.user_id, e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+pub async fn release_hold(
+    Extension(retention): Extension<RetentionScheduler>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(hold_id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    match retention.release_hold(&hold_id, &user.user_id).await {
+        Ok(hold) => Ok(Json(json!({
+            "success": true,
+            "hold": hold
+        }))),
+        Err(e) => {
+            tracing::warn!("Release of legal hold {} by {} failed: {}", hold_id, user.user_id, e);
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+}
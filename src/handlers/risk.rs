@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::ai::risk_model::{score_all_denials, CaseHistory, LogisticRiskModel, TrainingConfig};
+use crate::ai::AiError;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::legal::access_middleware::AuthenticatedUser;
+
+#[derive(Debug, Deserialize)]
+pub struct RecordOutcomeRequest {
+    pub placement_denial_id: i64,
+    pub outcome: bool,
+    pub labeled_by: Option<String>,
+    pub notes: Option<String>,
+}
+
+// Record a labelled outcome used to train the risk model
+pub async fn record_outcome(
+    State(pool): State<DbPool>,
+    Json(payload): Json<RecordOutcomeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let result = sqlx::query(
+        "INSERT INTO risk_outcomes (placement_denial_id, outcome, labeled_by, notes)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(payload.placement_denial_id)
+    .bind(payload.outcome)
+    .bind(&payload.labeled_by)
+    .bind(&payload.notes)
+    .execute(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": result.last_insert_rowid()
+    })))
+}
+
+// Train a new model from all labelled outcomes
+pub async fn train_model(
+    State(pool): State<DbPool>,
+    config: Option<Json<TrainingConfig>>,
+) -> Result<Json<Value>, AppError> {
+    let config = config.map(|Json(c)| c).unwrap_or_default();
+
+    let model = LogisticRiskModel::train_from_database(&pool, &config)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Risk model training failed: {}", e);
+            match e {
+                AiError::InvalidInput(message) => AppError::validation("risk_outcomes", message),
+                other => AppError::AiProcessing {
+                    message: other.to_string(),
+                },
+            }
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "version": model.version,
+        "metrics": model.metrics,
+        "feature_names": model.feature_names,
+        "weights": model.weights
+    })))
+}
+
+// Score the caller's denials now with the latest model
+pub async fn score_denials(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, StatusCode> {
+    let model = LogisticRiskModel::load_latest(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::CONFLICT)?;
+
+    let scored = score_all_denials(&pool, &model, &user.matters)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "model_version": model.version,
+        "denials_scored": scored
+    })))
+}
+
+// Explain the risk score of a single denial, from the history the caller may see
+pub async fn explain_denial(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    let model = LogisticRiskModel::load_latest(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::CONFLICT)?;
+
+    let history = CaseHistory::load(&pool, &user.matters)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let denial = history
+        .denials
+        .iter()
+        .find(|d| d.id == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let explanation = model
+        .explain(&history.features_for(denial))
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "explanation": explanation
+    })))
+}
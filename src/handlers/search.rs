@@ -0,0 +1,53 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::db::DbPool;
+use crate::legal::access_middleware::AuthenticatedUser;
+use crate::search::FullTextIndex;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+// Full-text search with phrase ("..."), prefix (term*) and fuzzy (term~) clauses
+pub async fn search(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(params): Query<SearchQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let index = FullTextIndex::new(pool);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+
+    match index.search(&params.q, limit, &user.matters).await {
+        Ok(results) => Ok(Json(json!({
+            "success": true,
+            "results": results
+        }))),
+        Err(e) => {
+            tracing::error!("Search failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Bring the index up to date now instead of waiting for the background sync
+pub async fn reindex(State(pool): State<DbPool>) -> Result<Json<Value>, StatusCode> {
+    match FullTextIndex::new(pool).sync().await {
+        Ok(summary) => Ok(Json(json!({
+            "success": true,
+            "summary": summary
+        }))),
+        Err(e) => {
+            tracing::error!("Search index sync failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
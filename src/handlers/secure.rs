@@ -0,0 +1,180 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::crypto_db::{CryptoDatabase, DatabaseError, QueryBuilder};
+use crate::legal::access_middleware::AuthenticatedUser;
+
+/// Table in the encrypted store holding sensitive communications
+const COMMUNICATIONS_TABLE: &str = "communications";
+
+/// A communication kept encrypted at rest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecureCommunication {
+    pub communication_date: String,
+    pub sender: Option<String>,
+    pub recipient: Option<String>,
+    pub medium: Option<String>,
+    pub subject: Option<String>,
+    pub message_content: Option<String>,
+    #[serde(default)]
+    pub related_to_placement: bool,
+    /// Matter the communication is filed under
+    #[serde(default)]
+    pub case_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecureCommunicationParams {
+    pub sender: Option<String>,
+    pub recipient: Option<String>,
+    pub medium: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+fn status_for(error: DatabaseError) -> StatusCode {
+    match error {
+        DatabaseError::NotFound(_) => StatusCode::NOT_FOUND,
+        DatabaseError::Validation(_) | DatabaseError::Query(_) => StatusCode::BAD_REQUEST,
+        DatabaseError::Conflict(_) => StatusCode::CONFLICT,
+        e => {
+            tracing::error!("Encrypted store error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Load a communication, treating one on a matter the caller is kept out of as missing
+async fn visible_communication(
+    secure: &CryptoDatabase,
+    user: &AuthenticatedUser,
+    id: &Uuid,
+) -> Result<SecureCommunication, StatusCode> {
+    let communication: SecureCommunication = secure.get(COMMUNICATIONS_TABLE, id).await.map_err(status_for)?;
+    if !user.matters.allows(communication.case_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(communication)
+}
+
+/// Answers the secure routes when the encrypted store failed to open at startup
+pub async fn unavailable() -> StatusCode {
+    StatusCode::SERVICE_UNAVAILABLE
+}
+
+// Store a communication in the encrypted store
+pub async fn create_communication(
+    Extension(secure): Extension<Arc<CryptoDatabase>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(communication): Json<SecureCommunication>,
+) -> Result<Json<Value>, StatusCode> {
+    if !user.matters.allows(communication.case_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let id = secure
+        .insert(COMMUNICATIONS_TABLE, &communication)
+        .await
+        .map_err(status_for)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": id
+    })))
+}
+
+// Decrypt a single communication
+pub async fn get_communication(
+    Extension(secure): Extension<Arc<CryptoDatabase>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let communication = visible_communication(&secure, &user, &id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": id,
+        "communication": communication
+    })))
+}
+
+// Replace a communication, bumping its record version
+pub async fn update_communication(
+    Extension(secure): Extension<Arc<CryptoDatabase>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(communication): Json<SecureCommunication>,
+) -> Result<Json<Value>, StatusCode> {
+    visible_communication(&secure, &user, &id).await?;
+    if !user.matters.allows(communication.case_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    secure
+        .update(COMMUNICATIONS_TABLE, &id, &communication)
+        .await
+        .map_err(status_for)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": id
+    })))
+}
+
+pub async fn delete_communication(
+    Extension(secure): Extension<Arc<CryptoDatabase>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    visible_communication(&secure, &user, &id).await?;
+    secure
+        .delete(COMMUNICATIONS_TABLE, &id)
+        .await
+        .map_err(status_for)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "id": id
+    })))
+}
+
+// Exact-match search over communications; sender and recipient are matched by blind token.
+// Communications on matters the caller is kept out of are left out of the page.
+pub async fn list_communications(
+    Extension(secure): Extension<Arc<CryptoDatabase>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(params): Query<SecureCommunicationParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut query = QueryBuilder::new(COMMUNICATIONS_TABLE)
+        .limit(params.limit.unwrap_or(50).min(500))
+        .offset(params.offset.unwrap_or(0));
+    for (field, value) in [
+        ("sender", params.sender),
+        ("recipient", params.recipient),
+        ("medium", params.medium),
+    ] {
+        if let Some(value) = value {
+            query = query.where_eq(field, json!(value));
+        }
+    }
+
+    let results: Vec<(Uuid, SecureCommunication)> =
+        secure.query_with_ids(&query).await.map_err(status_for)?;
+    let communications: Vec<Value> = results
+        .into_iter()
+        .filter(|(_, communication)| user.matters.allows(communication.case_id))
+        .map(|(id, communication)| json!({ "id": id, "communication": communication }))
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "count": communications.len(),
+        "communications": communications
+    })))
+}
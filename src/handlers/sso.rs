@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Redirect},
+    routing::get,
+    Extension, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::auth::{AuthError, AuthManager, LOGIN_TIMEOUT_MINUTES};
+use crate::legal::access_control::AccessController;
+
+/// Cookie holding the `state` of the login this browser started
+const LOGIN_STATE_COOKIE: &str = "moodbridge_login_state";
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Single sign-on routes; they sit outside `/api` so the access middleware
+/// lets them through without a session
+pub fn router<S>(auth: Arc<AuthManager>, access: AccessController) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/auth/:provider/login", get(login))
+        .route("/auth/:provider/callback", get(callback))
+        .layer(Extension(auth))
+        .layer(Extension(access))
+}
+
+// Send the browser to the identity provider, remembering which login it started
+pub async fn login(
+    Extension(auth): Extension<Arc<AuthManager>>,
+    Path(provider): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    match auth.begin_login(&provider).await {
+        Ok((url, state)) => Ok(([(header::SET_COOKIE, state_cookie(&provider, &state, LOGIN_TIMEOUT_MINUTES * 60))], Redirect::to(&url))),
+        Err(AuthError::ProviderNotConfigured(_)) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to start {} sign-in: {}", provider, e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+// Finish the sign-in and open an app session for the linked user
+pub async fn callback(
+    Extension(auth): Extension<Arc<AuthManager>>,
+    Extension(access): Extension<AccessController>,
+    Path(provider): Path<String>,
+    Query(params): Query<CallbackQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    if let Some(error) = params.error {
+        tracing::warn!(
+            "{} sign-in failed at the provider: {} {}",
+            provider,
+            error,
+            params.error_description.unwrap_or_default()
+        );
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let (Some(code), Some(state)) = (params.code, params.state) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    // A callback opened in a browser that didn't start this login, e.g. one
+    // planted by an attacker to sign the victim into the attacker's account
+    if login_state(&headers).as_deref() != Some(state.as_str()) {
+        tracing::warn!("{} sign-in callback without its login cookie", provider);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let identity = auth.complete_login(&provider, &code, &state).await.map_err(|e| {
+        tracing::warn!("{} sign-in rejected: {}", provider, e);
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let claim = |name: &str| identity.metadata.get(name).and_then(Value::as_str).unwrap_or_default();
+    // A provider that doesn't say the email is verified hasn't verified it
+    let email_verified = identity.metadata.get("email_verified").and_then(Value::as_bool).unwrap_or(false);
+    let user = match access
+        .resolve_identity(claim("issuer"), claim("subject"), &identity.email, email_verified)
+        .await
+    {
+        Ok(Some(user)) if user.active => user,
+        Ok(_) => {
+            tracing::warn!("{} sign-in by {} has no active account", provider, identity.email);
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Err(e) => {
+            tracing::error!("Failed to resolve {} identity: {}", provider, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match access.create_auth_token(&user.user_id).await {
+        Ok(token) => Ok(([(header::SET_COOKIE, state_cookie(&provider, "", 0))], Json(json!({
+            "success": true,
+            "token": token,
+            "token_type": "Bearer",
+            "user_id": user.user_id,
+            "role": user.role,
+            "display_name": identity.display_name,
+            "provider": provider
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to open session for {}: {}", user.user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn state_cookie(provider: &str, state: &str, max_age_secs: i64) -> String {
+    format!(
+        "{}={}; Path=/auth/{}; Max-Age={}; HttpOnly; SameSite=Lax",
+        LOGIN_STATE_COOKIE, state, provider, max_age_secs
+    )
+}
+
+fn login_state(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(LOGIN_STATE_COOKIE)?.strip_prefix('='))
+        .map(str::to_string)
+}
@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::ai::llm::OpenAiService;
+use crate::ai::timeline_extraction::{
+    approve_candidate, extract_and_queue, list_candidates, reject_candidate, TimelineExtractor,
+};
+use crate::ai::AiConfig;
+use crate::db::DbPool;
+use crate::legal::access_middleware::AuthenticatedUser;
+
+#[derive(Debug, Deserialize)]
+pub struct ExtractTimelineRequest {
+    pub refine_with_llm: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CandidateQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    pub reviewer: String,
+}
+
+// Extract dated events from exhibits and communications into the review queue
+pub async fn extract_timeline(
+    State(pool): State<DbPool>,
+    Json(payload): Json<ExtractTimelineRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let extractor = TimelineExtractor::new();
+    let config = AiConfig::default();
+
+    let result = if payload.refine_with_llm.unwrap_or(false) && config.openai_api_key.is_some() {
+        let service = OpenAiService::new(config);
+        extract_and_queue(&pool, &extractor, Some(&service)).await
+    } else {
+        extract_and_queue(&pool, &extractor, None).await
+    };
+
+    match result {
+        Ok(summary) => Ok(Json(json!({
+            "success": true,
+            "summary": summary
+        }))),
+        Err(e) => {
+            tracing::error!("Timeline extraction failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List candidate events, pending by default
+pub async fn timeline_candidates(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(params): Query<CandidateQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let status = params.status.unwrap_or_else(|| "pending".to_string());
+    let status = if status == "all" { None } else { Some(status) };
+
+    let candidates = list_candidates(&pool, status.as_deref(), &user.matters)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "count": candidates.len(),
+        "candidates": candidates
+    })))
+}
+
+// Approve a candidate into the timeline
+pub async fn approve_timeline_candidate(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+    Json(payload): Json<ReviewRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let event_id = approve_candidate(&pool, id, &payload.reviewer, &user.matters)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "timeline_event_id": event_id
+    })))
+}
+
+// Reject a candidate
+pub async fn reject_timeline_candidate(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+    Json(payload): Json<ReviewRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    reject_candidate(&pool, id, &payload.reviewer, &user.matters)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true })))
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::ai::llm::OpenAiService;
+use crate::ai::sentiment::{
+    pre_denial_alerts, score_communications, tone_timelines, LexiconToneScorer,
+};
+use crate::ai::AiConfig;
+use crate::db::DbPool;
+use crate::legal::access_middleware::AuthenticatedUser;
+
+#[derive(Debug, Deserialize)]
+pub struct ScoreToneRequest {
+    pub refine_with_llm: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToneAlertParams {
+    pub window_days: Option<i64>,
+    pub min_increase: Option<f64>,
+}
+
+// Score new communications with the lexicon scorer, optionally refined by the LLM
+pub async fn score_tone(
+    State(pool): State<DbPool>,
+    Json(payload): Json<ScoreToneRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let scorer = LexiconToneScorer::new();
+    let config = AiConfig::default();
+
+    let result = if payload.refine_with_llm.unwrap_or(false) && config.openai_api_key.is_some() {
+        let service = OpenAiService::new(config);
+        score_communications(&pool, &scorer, Some(&service)).await
+    } else {
+        score_communications(&pool, &scorer, None).await
+    };
+
+    match result {
+        Ok(count) => Ok(Json(json!({
+            "success": true,
+            "communications_scored": count
+        }))),
+        Err(e) => {
+            tracing::error!("Tone scoring failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Sender-by-sender tone timelines for the caller's matters
+pub async fn tone_timeline(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, StatusCode> {
+    let timelines = tone_timelines(&pool, &user.matters)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "senders": timelines
+    })))
+}
+
+// Alerts for tone escalation ahead of placement denials in the caller's matters
+pub async fn tone_alerts(
+    State(pool): State<DbPool>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(params): Query<ToneAlertParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let alerts = pre_denial_alerts(
+        &pool,
+        &user.matters,
+        params.window_days.unwrap_or(7),
+        params.min_increase.unwrap_or(0.25),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "alert_count": alerts.len(),
+        "alerts": alerts
+    })))
+}
@@ -3,6 +3,7 @@ use std::path::Path;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::error::AppError;
+use crate::legal::pii::{detector, PiiKind};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadata {
@@ -157,6 +158,8 @@ async fn get_content_preview(file_path: &str, mime_type: &Option<String>) -> Opt
 pub struct FileAnalysis {
     pub metadata: FileMetadata,
     pub contains_pii: bool,
+    /// Kinds of personal data located anywhere in the file
+    pub pii_kinds: Vec<PiiKind>,
     pub contains_financial_data: bool,
     pub contains_legal_terms: bool,
     pub language: Option<String>,
@@ -168,11 +171,18 @@ impl FileAnalysis {
     pub async fn analyze_file(file_path: &str) -> Result<Self, AppError> {
         let metadata = FileMetadata::from_file(file_path).await?;
         
+        // PII can sit anywhere, so scan the whole text rather than the preview
+        let content = metadata
+            .content_preview
+            .as_ref()
+            .map(|preview| fs::read_to_string(file_path).unwrap_or_else(|_| preview.clone()));
+        let pii_kinds = content.as_deref().map(detect_pii).unwrap_or_default();
+
         let (contains_pii, contains_financial_data, contains_legal_terms, language, word_count, line_count) = 
-            if let Some(preview) = &metadata.content_preview {
+            if let (Some(preview), Some(content)) = (&metadata.content_preview, &content) {
                 (
-                    detect_pii(preview),
-                    detect_financial_data(preview),
+                    !pii_kinds.is_empty(),
+                    detect_financial_data(content, &pii_kinds),
                     detect_legal_terms(preview),
                     detect_language(preview),
                     Some(count_words(preview)),
@@ -185,6 +195,7 @@ impl FileAnalysis {
         Ok(FileAnalysis {
             metadata,
             contains_pii,
+            pii_kinds,
             contains_financial_data,
             contains_legal_terms,
             language,
@@ -194,20 +205,22 @@ impl FileAnalysis {
     }
 }
 
-fn detect_pii(content: &str) -> bool {
-    let content_lower = content.to_lowercase();
-    
-    // Common PII patterns
-    let pii_patterns = [
-        "social security", "ssn", "date of birth", "dob", "driver's license",
-        "passport", "credit card", "bank account", "routing number",
-        "personal identification", "national id", "tax id", "ein"
-    ];
-    
-    pii_patterns.iter().any(|pattern| content_lower.contains(pattern))
+fn detect_pii(content: &str) -> Vec<PiiKind> {
+    let mut kinds: Vec<PiiKind> = detector().detect(content).into_iter().map(|m| m.kind).collect();
+    kinds.sort();
+    kinds.dedup();
+    kinds
 }
 
-fn detect_financial_data(content: &str) -> bool {
+fn detect_financial_data(content: &str, pii_kinds: &[PiiKind]) -> bool {
+    // A validated account or routing number is financial data whatever the wording
+    if pii_kinds
+        .iter()
+        .any(|kind| matches!(kind, PiiKind::AccountNumber | PiiKind::RoutingNumber))
+    {
+        return true;
+    }
+
     let content_lower = content.to_lowercase();
     
     let financial_patterns = [
@@ -1,12 +1,30 @@
 /// Access Control Module for MoodBridge_Rust
-/// 
+///
 /// This module provides role-based access control for legal operations,
 /// ensuring only authorized users can perform sensitive legal functions.
+/// Users, role permissions, temporary grants and sessions are kept in SQLite.
 
+use crate::db::DbPool;
+use crate::auth::kms::KmsProvider;
+use crate::legal::ethical_walls::MatterAccess;
+use crate::legal::mfa::{Mfa, SecretStore};
 use crate::legal::LegalOperationType;
+use base64::Engine as _;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::Row;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 use chrono::{DateTime, Utc};
+use tokio::sync::OnceCell;
+
+/// How long a session token stays valid
+const DEFAULT_SESSION_TTL_HOURS: i64 = 8;
+
+const ACCESS_SCHEMA: &str = include_str!("../../data/access_control.sql");
 
 /// User role definitions
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -53,28 +71,126 @@ pub struct User {
     pub active: bool,
 }
 
+/// A permission granted for a limited time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemporaryGrant {
+    pub grant_id: String,
+    pub user_id: String,
+    pub operation_type: LegalOperationType,
+    pub granted_by: String,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
 /// Access control manager
+///
+/// Cloning is cheap; clones share the same database.
 #[derive(Debug, Clone)]
 pub struct AccessController {
-    users: HashMap<String, User>,
-    role_permissions: HashMap<UserRole, HashSet<LegalOperationType>>,
+    pool: DbPool,
+    schema: Arc<OnceCell<()>>,
+    session_ttl: chrono::Duration,
+    mfa_required: bool,
+    secrets: Option<SecretStore>,
+}
+
+impl Default for AccessController {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AccessController {
+    /// Controller over a private in-memory database, for tests and tools
     pub fn new() -> Self {
-        let mut controller = Self {
-            users: HashMap::new(),
-            role_permissions: HashMap::new(),
-        };
-        
-        controller.initialize_default_permissions();
-        controller
+        // A single connection that never closes keeps the in-memory database alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_lazy("sqlite::memory:")
+            .expect("in-memory SQLite URL is valid");
+        Self::with_pool(pool)
+    }
+
+    /// Controller persisting to `pool`, creating its tables if needed
+    pub async fn open(pool: DbPool) -> Result<Self, String> {
+        let controller = Self::with_pool(pool);
+        controller.ready().await?;
+        Ok(controller)
+    }
+
+    fn with_pool(pool: DbPool) -> Self {
+        Self {
+            pool,
+            schema: Arc::new(OnceCell::new()),
+            session_ttl: chrono::Duration::hours(DEFAULT_SESSION_TTL_HOURS),
+            mfa_required: false,
+            secrets: None,
+        }
+    }
+
+    pub fn with_session_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Require every session to pass multi-factor authentication
+    /// (`SecurityConfig::require_mfa`)
+    pub fn with_mfa_required(mut self, required: bool) -> Self {
+        self.mfa_required = required;
+        self
+    }
+
+    /// Key store holding TOTP secrets; without one users can't enroll in MFA
+    pub fn with_secret_store(mut self, kms: Arc<dyn KmsProvider>) -> Self {
+        self.secrets = Some(SecretStore::new(kms));
+        self
     }
 
-    /// Initialize default role-based permissions
-    fn initialize_default_permissions(&mut self) {
+    pub fn mfa_required(&self) -> bool {
+        self.mfa_required
+    }
+
+    /// TOTP enrollment, verification and step-up for these users
+    pub fn mfa(&self) -> Mfa {
+        Mfa::new(self.clone(), self.secrets.clone())
+    }
+
+    /// Matter access lists and ethical walls kept alongside these users
+    pub fn matters(&self) -> MatterAccess {
+        MatterAccess::new(self.clone())
+    }
+
+    pub(crate) fn pool(&self) -> &DbPool {
+        &self.pool
+    }
+
+    pub(crate) async fn ready(&self) -> Result<(), String> {
+        self.schema
+            .get_or_try_init(|| async {
+                sqlx::query(ACCESS_SCHEMA)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| format!("Failed to create access control tables: {}", e))?;
+                self.initialize_default_permissions().await
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Seed default role-based permissions for operation types the table has
+    /// never seen: every type on first start, and types added in later releases
+    async fn initialize_default_permissions(&self) -> Result<(), String> {
+        let known: HashSet<String> = sqlx::query_scalar("SELECT DISTINCT operation_type FROM access_role_permissions")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .collect();
+
         // Attorney permissions - full access to all operations
-        let attorney_permissions = [
+        let attorney_permissions = vec![
             LegalOperationType::AILegalAdvice,
             LegalOperationType::VoiceRecording,
             LegalOperationType::DocumentModification,
@@ -84,47 +200,99 @@ impl AccessController {
             LegalOperationType::PresentationGeneration,
             LegalOperationType::SemanticSearch,
             LegalOperationType::CollaborationMetrics,
-        ].iter().cloned().collect();
+            LegalOperationType::AuditLogExport,
+            LegalOperationType::ExhibitDeletion,
+            LegalOperationType::RecordDisposition,
+        ];
 
         // Paralegal permissions - supervised access
-        let paralegal_permissions = [
+        let paralegal_permissions = vec![
             LegalOperationType::DocumentIntelligence,
             LegalOperationType::PresentationGeneration,
             LegalOperationType::SemanticSearch,
             LegalOperationType::CollaborationMetrics,
             LegalOperationType::TimelineAnalysis,
-        ].iter().cloned().collect();
+        ];
 
         // Legal Assistant permissions - limited access
-        let assistant_permissions = [
+        let assistant_permissions = vec![
             LegalOperationType::DocumentIntelligence,
             LegalOperationType::PresentationGeneration,
             LegalOperationType::SemanticSearch,
-        ].iter().cloned().collect();
+        ];
 
         // Administrator permissions - system functions only
-        let admin_permissions = [
-            LegalOperationType::CollaborationMetrics,
-        ].iter().cloned().collect();
+        let admin_permissions = vec![LegalOperationType::CollaborationMetrics, LegalOperationType::AuditLogExport];
 
         // User permissions - very limited
-        let user_permissions = [
-            LegalOperationType::SemanticSearch,
-        ].iter().cloned().collect();
+        let user_permissions = vec![LegalOperationType::SemanticSearch];
 
         // Guest permissions - none
-        let guest_permissions = HashSet::new();
+        for (role, permissions) in [
+            (UserRole::Attorney, attorney_permissions),
+            (UserRole::Paralegal, paralegal_permissions),
+            (UserRole::LegalAssistant, assistant_permissions),
+            (UserRole::Administrator, admin_permissions),
+            (UserRole::User, user_permissions),
+        ] {
+            for operation_type in permissions {
+                if known.contains(&enum_key(&operation_type)) {
+                    continue;
+                }
+                sqlx::query("INSERT OR IGNORE INTO access_role_permissions (role, operation_type) VALUES (?, ?)")
+                    .bind(enum_key(&role))
+                    .bind(enum_key(&operation_type))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+            }
+        }
+        Ok(())
+    }
 
-        self.role_permissions.insert(UserRole::Attorney, attorney_permissions);
-        self.role_permissions.insert(UserRole::Paralegal, paralegal_permissions);
-        self.role_permissions.insert(UserRole::LegalAssistant, assistant_permissions);
-        self.role_permissions.insert(UserRole::Administrator, admin_permissions);
-        self.role_permissions.insert(UserRole::User, user_permissions);
-        self.role_permissions.insert(UserRole::Guest, guest_permissions);
+    /// Replace the operations a role may perform
+    pub async fn set_role_permissions(
+        &self,
+        role: &UserRole,
+        operations: &[LegalOperationType],
+    ) -> Result<(), String> {
+        self.ready().await?;
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query("DELETE FROM access_role_permissions WHERE role = ?")
+            .bind(enum_key(role))
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        for operation_type in operations {
+            sqlx::query("INSERT INTO access_role_permissions (role, operation_type) VALUES (?, ?)")
+                .bind(enum_key(role))
+                .bind(enum_key(operation_type))
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    /// Operations granted to each role
+    pub async fn role_permissions(&self) -> Result<HashMap<UserRole, HashSet<LegalOperationType>>, String> {
+        self.ready().await?;
+        let rows = sqlx::query("SELECT role, operation_type FROM access_role_permissions")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        let mut permissions: HashMap<UserRole, HashSet<LegalOperationType>> = HashMap::new();
+        for row in rows {
+            let role = parse_enum(&row.get::<String, _>("role"))?;
+            let operation_type = parse_enum(&row.get::<String, _>("operation_type"))?;
+            permissions.entry(role).or_default().insert(operation_type);
+        }
+        Ok(permissions)
     }
 
     /// Add or update a user
-    pub async fn add_user(&mut self, user: User) -> Result<(), String> {
+    pub async fn add_user(&self, user: User) -> Result<(), String> {
         // Validate user data
         if user.email.is_empty() {
             return Err("Email cannot be empty".to_string());
@@ -134,83 +302,136 @@ impl AccessController {
             return Err("Attorneys must have a bar number".to_string());
         }
 
-        self.users.insert(user.user_id.clone(), user);
+        self.ready().await?;
+        let permissions = serde_json::to_string(&user.permissions).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO access_users (user_id, email, role, bar_number, jurisdiction, supervisor_id, permissions, created_at, last_login, active)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                email = excluded.email, role = excluded.role, bar_number = excluded.bar_number,
+                jurisdiction = excluded.jurisdiction, supervisor_id = excluded.supervisor_id,
+                permissions = excluded.permissions, active = excluded.active",
+        )
+        .bind(&user.user_id)
+        .bind(&user.email)
+        .bind(enum_key(&user.role))
+        .bind(&user.bar_number)
+        .bind(&user.jurisdiction)
+        .bind(&user.supervisor_id)
+        .bind(permissions)
+        .bind(user.created_at)
+        .bind(user.last_login)
+        .bind(user.active)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
         tracing::info!("User added to access control system");
         Ok(())
     }
 
     /// Check if a user has permission for an operation
+    ///
+    /// Storage errors deny access.
     pub async fn has_permission(&self, user_id: &str, operation_type: &LegalOperationType) -> bool {
-        if let Some(user) = self.users.get(user_id) {
-            if !user.active {
-                return false;
+        match self.check_permission(user_id, operation_type).await {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                tracing::error!("Permission check failed for {}: {}", user_id, e);
+                false
             }
+        }
+    }
 
-            // Check role-based permissions
-            if let Some(role_permissions) = self.role_permissions.get(&user.role) {
-                if role_permissions.contains(operation_type) {
-                    return true;
-                }
-            }
+    async fn check_permission(&self, user_id: &str, operation_type: &LegalOperationType) -> Result<bool, String> {
+        let user = match self.get_user(user_id).await? {
+            Some(user) if user.active => user,
+            _ => return Ok(false),
+        };
 
-            // Check individual permissions
-            let operation_key = format!("{:?}", operation_type);
-            return user.permissions.contains(&operation_key);
+        // Check role-based permissions
+        let operation_key = enum_key(operation_type);
+        let by_role: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM access_role_permissions WHERE role = ? AND operation_type = ?",
+        )
+        .bind(enum_key(&user.role))
+        .bind(&operation_key)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+        if by_role > 0 {
+            return Ok(true);
         }
 
-        false
+        // Check individual permissions
+        if user.permissions.contains(&format!("{:?}", operation_type)) {
+            return Ok(true);
+        }
+
+        // Temporary grants lapse on their own once expires_at passes
+        let by_grant: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM access_grants
+             WHERE user_id = ? AND operation_type = ? AND revoked_at IS NULL AND expires_at > ?",
+        )
+        .bind(user_id)
+        .bind(&operation_key)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(by_grant > 0)
     }
 
     /// Get permission level for a user and operation
     pub async fn get_permission_level(&self, user_id: &str, operation_type: &LegalOperationType) -> PermissionLevel {
-        if let Some(user) = self.users.get(user_id) {
-            if !user.active {
-                return PermissionLevel::Denied;
-            }
+        let user = match self.get_user(user_id).await {
+            Ok(Some(user)) => user,
+            _ => return PermissionLevel::Denied,
+        };
+        if !user.active {
+            return PermissionLevel::Denied;
+        }
+
+        match (&user.role, operation_type) {
+            // Attorneys have full access
+            (UserRole::Attorney, _) => PermissionLevel::Full,
 
-            match (&user.role, operation_type) {
-                // Attorneys have full access
-                (UserRole::Attorney, _) => PermissionLevel::Full,
-                
-                // Paralegals need supervision for sensitive operations
-                (UserRole::Paralegal, LegalOperationType::AILegalAdvice) => PermissionLevel::Supervised,
-                (UserRole::Paralegal, LegalOperationType::DocumentModification) => PermissionLevel::Supervised,
-                (UserRole::Paralegal, LegalOperationType::ClientDataProcessing) => PermissionLevel::Supervised,
-                (UserRole::Paralegal, _) => {
-                    if self.has_permission(user_id, operation_type).await {
-                        PermissionLevel::Full
-                    } else {
-                        PermissionLevel::Denied
-                    }
+            // Paralegals need supervision for sensitive operations
+            (UserRole::Paralegal, LegalOperationType::AILegalAdvice) => PermissionLevel::Supervised,
+            (UserRole::Paralegal, LegalOperationType::DocumentModification) => PermissionLevel::Supervised,
+            (UserRole::Paralegal, LegalOperationType::ClientDataProcessing) => PermissionLevel::Supervised,
+            (UserRole::Paralegal, _) => {
+                if self.has_permission(user_id, operation_type).await {
+                    PermissionLevel::Full
+                } else {
+                    PermissionLevel::Denied
                 }
-                
-                // Legal assistants have limited access
-                (UserRole::LegalAssistant, _) => {
-                    if self.has_permission(user_id, operation_type).await {
-                        PermissionLevel::ReadOnly
-                    } else {
-                        PermissionLevel::Denied
-                    }
+            }
+
+            // Legal assistants have limited access
+            (UserRole::LegalAssistant, _) => {
+                if self.has_permission(user_id, operation_type).await {
+                    PermissionLevel::ReadOnly
+                } else {
+                    PermissionLevel::Denied
                 }
-                
-                // Others have minimal access
-                _ => {
-                    if self.has_permission(user_id, operation_type).await {
-                        PermissionLevel::ReadOnly
-                    } else {
-                        PermissionLevel::Denied
-                    }
+            }
+
+            // Others have minimal access
+            _ => {
+                if self.has_permission(user_id, operation_type).await {
+                    PermissionLevel::ReadOnly
+                } else {
+                    PermissionLevel::Denied
                 }
             }
-        } else {
-            PermissionLevel::Denied
         }
     }
 
     /// Check if user requires supervision for an operation
     pub async fn requires_supervision(&self, user_id: &str, operation_type: &LegalOperationType) -> bool {
-        if let Some(user) = self.users.get(user_id) {
-            match user.role {
+        match self.get_user(user_id).await {
+            Ok(Some(user)) => match user.role {
                 UserRole::Attorney => false, // Attorneys don't need supervision
                 UserRole::Paralegal => {
                     matches!(operation_type,
@@ -219,26 +440,22 @@ impl AccessController {
                         LegalOperationType::ClientDataProcessing
                     )
                 }
-                _ => true, // All other roles need supervision for sensitive operations
-            }
-        } else {
-            true // Unknown users need supervision
+                // All other roles need supervision for sensitive operations
+                _ => is_sensitive(operation_type),
+            },
+            _ => true, // Unknown users need supervision
         }
     }
 
     /// Get supervising attorney for a user
-    pub async fn get_supervisor(&self, user_id: &str) -> Option<&User> {
-        if let Some(user) = self.users.get(user_id) {
-            if let Some(supervisor_id) = &user.supervisor_id {
-                return self.users.get(supervisor_id);
-            }
-        }
-        None
+    pub async fn get_supervisor(&self, user_id: &str) -> Option<User> {
+        let supervisor_id = self.get_user(user_id).await.ok()??.supervisor_id?;
+        self.get_user(&supervisor_id).await.ok()?
     }
 
     /// Validate attorney credentials
     pub async fn validate_attorney(&self, user_id: &str) -> Result<bool, String> {
-        if let Some(user) = self.users.get(user_id) {
+        if let Some(user) = self.get_user(user_id).await? {
             if user.role != UserRole::Attorney {
                 return Ok(false);
             }
@@ -261,21 +478,25 @@ impl AccessController {
     }
 
     /// Generate access control report for audit purposes
-    pub async fn generate_access_report(&self) -> String {
+    pub async fn generate_access_report(&self) -> Result<String, String> {
+        let users = self.list_users().await?;
+        let role_permissions = self.role_permissions().await?;
+        let grants = self.active_grants().await?;
+
         let mut report = String::from("=== ACCESS CONTROL AUDIT REPORT ===\n\n");
-        
+
         report.push_str(&format!("Report Generated: {}\n\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-        
+
         // User summary
         report.push_str("USER SUMMARY:\n");
-        for role in [UserRole::Attorney, UserRole::Paralegal, UserRole::LegalAssistant, 
+        for role in [UserRole::Attorney, UserRole::Paralegal, UserRole::LegalAssistant,
                      UserRole::Administrator, UserRole::User, UserRole::Guest] {
-            let count = self.users.values().filter(|u| u.role == role).count();
+            let count = users.iter().filter(|u| u.role == role).count();
             report.push_str(&format!("  {:?}: {} users\n", role, count));
         }
-        
+
         report.push_str("\nUSER DETAILS:\n");
-        for user in self.users.values() {
+        for user in &users {
             report.push_str(&format!(
                 "  {} ({}): Role={:?}, Active={}, Bar#={:?}, Jurisdiction={:?}\n",
                 user.user_id,
@@ -286,79 +507,214 @@ impl AccessController {
                 user.jurisdiction
             ));
         }
-        
+
         report.push_str("\nROLE PERMISSIONS:\n");
-        for (role, permissions) in &self.role_permissions {
+        for (role, permissions) in &role_permissions {
             report.push_str(&format!("  {:?}:\n", role));
             for permission in permissions {
                 report.push_str(&format!("    - {:?}\n", permission));
             }
         }
-        
-        report
+
+        report.push_str("\nACTIVE TEMPORARY GRANTS:\n");
+        for grant in &grants {
+            report.push_str(&format!(
+                "  {} -> {:?}, granted by {}, expires {}\n",
+                grant.user_id,
+                grant.operation_type,
+                grant.granted_by,
+                grant.expires_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+        }
+
+        Ok(report)
     }
 
-    /// Create authentication token (simplified for demo)
+    /// Create a session token for an active user
+    ///
+    /// The token is random; only its SHA-256 hash is stored.
     pub async fn create_auth_token(&self, user_id: &str) -> Result<String, String> {
-        if let Some(user) = self.users.get(user_id) {
-            if !user.active {
-                return Err("User account is inactive".to_string());
-            }
-            
-            // In a real implementation, this would create a proper JWT or similar token
-            let token = format!("auth_{}_{}", user_id, Utc::now().timestamp());
-            Ok(token)
-        } else {
-            Err("User not found".to_string())
+        match self.get_user(user_id).await? {
+            Some(user) if !user.active => return Err("User account is inactive".to_string()),
+            Some(_) => {}
+            None => return Err("User not found".to_string()),
         }
+
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| "System random number generator failed".to_string())?;
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+
+        let now = Utc::now();
+        sqlx::query("INSERT INTO access_sessions (token_hash, user_id, created_at, expires_at) VALUES (?, ?, ?, ?)")
+            .bind(token_hash(&token))
+            .bind(user_id)
+            .bind(now)
+            .bind(now + self.session_ttl)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        self.update_last_login(user_id).await?;
+        Ok(token)
     }
 
-    /// Validate authentication token (simplified for demo)
+    /// Validate a session token, returning its user
     pub async fn validate_auth_token(&self, token: &str) -> Result<String, String> {
-        // In a real implementation, this would properly validate a JWT or similar token
-        if token.starts_with("auth_") {
-            let parts: Vec<&str> = token.split('_').collect();
-            if parts.len() >= 2 {
-                let user_id = parts[1];
-                if self.users.contains_key(user_id) {
-                    return Ok(user_id.to_string());
-                }
-            }
-        }
-        Err("Invalid token".to_string())
+        self.ready().await?;
+        let user_id: Option<String> = sqlx::query_scalar(
+            "SELECT s.user_id FROM access_sessions s JOIN access_users u ON u.user_id = s.user_id
+             WHERE s.token_hash = ? AND s.revoked_at IS NULL AND s.expires_at > ? AND u.active = 1",
+        )
+        .bind(token_hash(token))
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?;
+        user_id.ok_or_else(|| "Invalid token".to_string())
     }
 
-    /// Grant temporary permission to a user
+    /// End a session before it expires
+    pub async fn revoke_auth_token(&self, token: &str) -> Result<(), String> {
+        self.ready().await?;
+        sqlx::query("UPDATE access_sessions SET revoked_at = ? WHERE token_hash = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(token_hash(token))
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Grant a permission to a user until `duration` has passed
     pub async fn grant_temporary_permission(
-        &mut self,
+        &self,
         user_id: &str,
         operation_type: LegalOperationType,
         granted_by: &str,
-    ) -> Result<(), String> {
+        duration: chrono::Duration,
+    ) -> Result<TemporaryGrant, String> {
         // Verify the granter has permission to grant
         if !self.has_permission(granted_by, &operation_type).await {
             return Err("Insufficient privileges to grant permission".to_string());
         }
+        if duration <= chrono::Duration::zero() {
+            return Err("Grant duration must be positive".to_string());
+        }
+        if self.get_user(user_id).await?.is_none() {
+            return Err("User not found".to_string());
+        }
 
-        if let Some(user) = self.users.get_mut(user_id) {
-            let operation_key = format!("{:?}", operation_type);
-            user.permissions.insert(operation_key);
-            tracing::info!("Temporary permission granted to user {} by {}", user_id, granted_by);
-            Ok(())
-        } else {
-            Err("User not found".to_string())
+        let now = Utc::now();
+        let grant = TemporaryGrant {
+            grant_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            operation_type,
+            granted_by: granted_by.to_string(),
+            granted_at: now,
+            expires_at: now + duration,
+        };
+        sqlx::query(
+            "INSERT INTO access_grants (grant_id, user_id, operation_type, granted_by, granted_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&grant.grant_id)
+        .bind(&grant.user_id)
+        .bind(enum_key(&grant.operation_type))
+        .bind(&grant.granted_by)
+        .bind(grant.granted_at)
+        .bind(grant.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        tracing::info!(
+            "Temporary permission {:?} granted to user {} by {} until {}",
+            grant.operation_type, user_id, granted_by, grant.expires_at
+        );
+        Ok(grant)
+    }
+
+    /// Grants that are neither revoked nor expired
+    pub async fn active_grants(&self) -> Result<Vec<TemporaryGrant>, String> {
+        self.ready().await?;
+        let rows = sqlx::query(
+            "SELECT grant_id, user_id, operation_type, granted_by, granted_at, expires_at FROM access_grants
+             WHERE revoked_at IS NULL AND expires_at > ? ORDER BY expires_at",
+        )
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TemporaryGrant {
+                    grant_id: row.get("grant_id"),
+                    user_id: row.get("user_id"),
+                    operation_type: parse_enum(&row.get::<String, _>("operation_type"))?,
+                    granted_by: row.get("granted_by"),
+                    granted_at: row.get("granted_at"),
+                    expires_at: row.get("expires_at"),
+                })
+            })
+            .collect()
+    }
+
+    /// Record grants whose time has run out and drop dead sessions
+    ///
+    /// Expired grants already stop counting in `has_permission`; this marks them so
+    /// the expiry shows up in the log and in the table.
+    pub async fn expire_grants(&self) -> Result<usize, String> {
+        self.ready().await?;
+        let now = Utc::now();
+        let expired = sqlx::query(
+            "SELECT user_id, operation_type FROM access_grants
+             WHERE revoked_at IS NULL AND expired_at IS NULL AND expires_at <= ?",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        sqlx::query(
+            "UPDATE access_grants SET expired_at = ?
+             WHERE revoked_at IS NULL AND expired_at IS NULL AND expires_at <= ?",
+        )
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        for row in &expired {
+            tracing::info!(
+                "Temporary permission {} expired for user {}",
+                row.get::<String, _>("operation_type"),
+                row.get::<String, _>("user_id")
+            );
         }
+
+        sqlx::query("DELETE FROM access_sessions WHERE expires_at <= ? OR revoked_at IS NOT NULL")
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        sqlx::query("DELETE FROM access_mfa_sessions WHERE token_hash NOT IN (SELECT token_hash FROM access_sessions)")
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(expired.len())
     }
 
-    /// Revoke permission from a user
+    /// Revoke permission from a user, including any temporary grants for it
     pub async fn revoke_permission(
-        &mut self,
+        &self,
         user_id: &str,
         operation_type: LegalOperationType,
         revoked_by: &str,
     ) -> Result<(), String> {
         // Verify the revoker has admin privileges
-        if let Some(revoker) = self.users.get(revoked_by) {
+        if let Some(revoker) = self.get_user(revoked_by).await? {
             if !matches!(revoker.role, UserRole::Attorney | UserRole::Administrator) {
                 return Err("Insufficient privileges to revoke permission".to_string());
             }
@@ -366,32 +722,136 @@ impl AccessController {
             return Err("Revoker not found".to_string());
         }
 
-        if let Some(user) = self.users.get_mut(user_id) {
-            let operation_key = format!("{:?}", operation_type);
-            user.permissions.remove(&operation_key);
-            tracing::info!("Permission revoked from user {} by {}", user_id, revoked_by);
-            Ok(())
-        } else {
-            Err("User not found".to_string())
-        }
+        let mut user = self.get_user(user_id).await?.ok_or_else(|| "User not found".to_string())?;
+        user.permissions.remove(&format!("{:?}", operation_type));
+        let permissions = serde_json::to_string(&user.permissions).map_err(|e| e.to_string())?;
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query("UPDATE access_users SET permissions = ? WHERE user_id = ?")
+            .bind(permissions)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        sqlx::query(
+            "UPDATE access_grants SET revoked_at = ?, revoked_by = ?
+             WHERE user_id = ? AND operation_type = ? AND revoked_at IS NULL",
+        )
+        .bind(Utc::now())
+        .bind(revoked_by)
+        .bind(user_id)
+        .bind(enum_key(&operation_type))
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::info!("Permission revoked from user {} by {}", user_id, revoked_by);
+        Ok(())
     }
 
     /// Get user by ID
-    pub fn get_user(&self, user_id: &str) -> Option<&User> {
-        self.users.get(user_id)
+    pub async fn get_user(&self, user_id: &str) -> Result<Option<User>, String> {
+        self.ready().await?;
+        let row = sqlx::query("SELECT * FROM access_users WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        row.map(|row| user_from_row(&row)).transpose()
     }
 
-    /// Update user last login
-    pub async fn update_last_login(&mut self, user_id: &str) {
-        if let Some(user) = self.users.get_mut(user_id) {
-            user.last_login = Some(Utc::now());
+    /// Find the active user with this email address, if exactly one has it
+    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, String> {
+        self.ready().await?;
+        let rows = sqlx::query("SELECT * FROM access_users WHERE lower(email) = lower(?) AND active = 1")
+            .bind(email.trim())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+        match rows.as_slice() {
+            [row] => user_from_row(row).map(Some),
+            _ => Ok(None),
         }
     }
 
+    /// Link a single sign-on identity (issuer and subject) to a user
+    pub async fn link_identity(&self, issuer: &str, subject: &str, user_id: &str) -> Result<(), String> {
+        if self.get_user(user_id).await?.is_none() {
+            return Err("User not found".to_string());
+        }
+        sqlx::query("INSERT OR REPLACE INTO access_identities (issuer, subject, user_id, linked_at) VALUES (?, ?, ?, ?)")
+            .bind(issuer)
+            .bind(subject)
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        tracing::info!("Linked identity {} from {} to user {}", subject, issuer, user_id);
+        Ok(())
+    }
+
+    /// The user a single sign-on identity belongs to
+    ///
+    /// Identities are matched on issuer and subject. The first sign-in falls back
+    /// to the email address, only if the provider says it is verified, and links
+    /// the identity so later sign-ins don't depend on the email. Identities from
+    /// providers that don't vouch for email have to be linked with `link_identity`.
+    pub async fn resolve_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+        email: &str,
+        email_verified: bool,
+    ) -> Result<Option<User>, String> {
+        self.ready().await?;
+        let linked: Option<String> =
+            sqlx::query_scalar("SELECT user_id FROM access_identities WHERE issuer = ? AND subject = ?")
+                .bind(issuer)
+                .bind(subject)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(db_error)?;
+        if let Some(user_id) = linked {
+            return self.get_user(&user_id).await;
+        }
+
+        if email.is_empty() || !email_verified {
+            return Ok(None);
+        }
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(None);
+        };
+        self.link_identity(issuer, subject, &user.user_id).await?;
+        Ok(Some(user))
+    }
+
+    pub async fn list_users(&self) -> Result<Vec<User>, String> {
+        self.ready().await?;
+        let rows = sqlx::query("SELECT * FROM access_users ORDER BY user_id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+        rows.iter().map(user_from_row).collect()
+    }
+
+    /// Update user last login
+    pub async fn update_last_login(&self, user_id: &str) -> Result<(), String> {
+        self.ready().await?;
+        sqlx::query("UPDATE access_users SET last_login = ? WHERE user_id = ?")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
     /// Deactivate user account
-    pub async fn deactivate_user(&mut self, user_id: &str, deactivated_by: &str) -> Result<(), String> {
+    pub async fn deactivate_user(&self, user_id: &str, deactivated_by: &str) -> Result<(), String> {
         // Verify deactivator has admin privileges
-        if let Some(deactivator) = self.users.get(deactivated_by) {
+        if let Some(deactivator) = self.get_user(deactivated_by).await? {
             if !matches!(deactivator.role, UserRole::Attorney | UserRole::Administrator) {
                 return Err("Insufficient privileges to deactivate user".to_string());
             }
@@ -399,12 +859,187 @@ impl AccessController {
             return Err("Deactivator not found".to_string());
         }
 
-        if let Some(user) = self.users.get_mut(user_id) {
-            user.active = false;
-            tracing::warn!("User {} deactivated by {}", user_id, deactivated_by);
-            Ok(())
-        } else {
-            Err("User not found".to_string())
+        let updated = sqlx::query("UPDATE access_users SET active = 0 WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        if updated.rows_affected() == 0 {
+            return Err("User not found".to_string());
+        }
+
+        tracing::warn!("User {} deactivated by {}", user_id, deactivated_by);
+        Ok(())
+    }
+}
+
+/// Periodically record expired grants and clear dead sessions
+pub fn spawn_grant_expiry(controller: AccessController, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = controller.expire_grants().await {
+                tracing::warn!("Failed to expire temporary grants: {}", e);
+            }
+        }
+    });
+}
+
+/// Operations that need attorney oversight for anyone but an attorney
+fn is_sensitive(operation_type: &LegalOperationType) -> bool {
+    matches!(operation_type,
+        LegalOperationType::AILegalAdvice |
+        LegalOperationType::VoiceRecording |
+        LegalOperationType::DocumentModification |
+        LegalOperationType::ClientDataProcessing |
+        LegalOperationType::ExhibitDeletion |
+        LegalOperationType::RecordDisposition
+    )
+}
+
+fn user_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<User, String> {
+    Ok(User {
+        user_id: row.get("user_id"),
+        email: row.get("email"),
+        role: parse_enum(&row.get::<String, _>("role"))?,
+        bar_number: row.get("bar_number"),
+        jurisdiction: row.get("jurisdiction"),
+        supervisor_id: row.get("supervisor_id"),
+        permissions: serde_json::from_str(&row.get::<String, _>("permissions")).map_err(|e| e.to_string())?,
+        created_at: row.get("created_at"),
+        last_login: row.get("last_login"),
+        active: row.get("active"),
+    })
+}
+
+/// Serde name of a unit enum variant, as stored in the database
+pub(crate) fn enum_key<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(key)) => key,
+        _ => format!("{:?}", serde_json::to_value(value).ok()),
+    }
+}
+
+fn parse_enum<T: serde::de::DeserializeOwned>(key: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(key.to_string()))
+        .map_err(|e| format!("Unknown value '{}' in access control tables: {}", key, e))
+}
+
+pub(crate) fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub(crate) fn db_error(e: sqlx::Error) -> String {
+    format!("Access control database error: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(user_id: &str, role: UserRole, supervisor_id: Option<&str>) -> User {
+        User {
+            user_id: user_id.to_string(),
+            email: format!("{}@firm.example", user_id),
+            bar_number: (role == UserRole::Attorney).then(|| "12345".to_string()),
+            jurisdiction: Some("WA".to_string()),
+            role,
+            supervisor_id: supervisor_id.map(str::to_string),
+            permissions: HashSet::new(),
+            created_at: Utc::now(),
+            last_login: None,
+            active: true,
         }
     }
+
+    async fn file_pool(dir: &tempfile::TempDir) -> DbPool {
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("access.db").display());
+        sqlx::SqlitePool::connect(&url).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_users_and_grants_survive_restart() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let controller = AccessController::open(file_pool(&dir).await).await.unwrap();
+        controller.add_user(user("atty", UserRole::Attorney, None)).await.unwrap();
+        controller.add_user(user("para", UserRole::Paralegal, Some("atty"))).await.unwrap();
+        controller
+            .grant_temporary_permission("para", LegalOperationType::AILegalAdvice, "atty", chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let token = controller.create_auth_token("para").await.unwrap();
+
+        let reopened = AccessController::open(file_pool(&dir).await).await.unwrap();
+        assert!(reopened.has_permission("para", &LegalOperationType::AILegalAdvice).await);
+        assert_eq!(reopened.validate_auth_token(&token).await.unwrap(), "para");
+        assert_eq!(reopened.get_supervisor("para").await.unwrap().user_id, "atty");
+        assert!(reopened.validate_auth_token("auth_para_1700000000").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_temporary_grants_expire_and_can_be_revoked() {
+        let controller = AccessController::new();
+        controller.add_user(user("atty", UserRole::Attorney, None)).await.unwrap();
+        controller.add_user(user("para", UserRole::Paralegal, Some("atty"))).await.unwrap();
+        let op = LegalOperationType::DocumentModification;
+        assert!(!controller.has_permission("para", &op).await);
+
+        controller
+            .grant_temporary_permission("para", op.clone(), "atty", chrono::Duration::milliseconds(50))
+            .await
+            .unwrap();
+        assert!(controller.has_permission("para", &op).await);
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(!controller.has_permission("para", &op).await);
+        assert_eq!(controller.expire_grants().await.unwrap(), 1);
+
+        controller
+            .grant_temporary_permission("para", op.clone(), "atty", chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        controller.revoke_permission("para", op.clone(), "atty").await.unwrap();
+        assert!(!controller.has_permission("para", &op).await);
+
+        // Paralegals can't hand out what they don't have
+        assert!(controller
+            .grant_temporary_permission("para", LegalOperationType::AILegalAdvice, "para", chrono::Duration::hours(1))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_end_on_revocation_and_deactivation() {
+        let controller = AccessController::new().with_session_ttl(chrono::Duration::hours(1));
+        controller.add_user(user("atty", UserRole::Attorney, None)).await.unwrap();
+        controller.add_user(user("asst", UserRole::LegalAssistant, Some("atty"))).await.unwrap();
+
+        let first = controller.create_auth_token("asst").await.unwrap();
+        let second = controller.create_auth_token("asst").await.unwrap();
+        assert_ne!(first, second);
+
+        controller.revoke_auth_token(&first).await.unwrap();
+        assert!(controller.validate_auth_token(&first).await.is_err());
+        assert!(controller.validate_auth_token(&second).await.is_ok());
+
+        controller.deactivate_user("asst", "atty").await.unwrap();
+        assert!(controller.validate_auth_token(&second).await.is_err());
+        assert!(controller.create_auth_token("asst").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_verified_email_links_an_identity() {
+        let controller = AccessController::new();
+        controller.add_user(user("atty", UserRole::Attorney, None)).await.unwrap();
+        let issuer = "https://idp.example";
+
+        assert!(controller.resolve_identity(issuer, "sub-1", "atty@firm.example", false).await.unwrap().is_none());
+        let linked = controller.resolve_identity(issuer, "sub-2", "atty@firm.example", true).await.unwrap();
+        assert_eq!(linked.unwrap().user_id, "atty");
+
+        // Once linked, the subject is enough
+        let again = controller.resolve_identity(issuer, "sub-2", "", false).await.unwrap();
+        assert_eq!(again.unwrap().user_id, "atty");
+        assert!(controller.resolve_identity(issuer, "sub-1", "atty@firm.example", false).await.unwrap().is_none());
+    }
 }
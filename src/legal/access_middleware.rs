@@ -0,0 +1,344 @@
+//! Route-level enforcement of legal access control
+//!
+//! Every `/api` route is mapped to the `LegalOperationType` it performs. Callers
+//! authenticate with `Authorization: Bearer <token>` from
+//! `AccessController::create_auth_token`; the middleware then checks
+//! `has_permission` and, where `requires_supervision` says so, a second token from
+//! the caller's supervising attorney in `X-Supervisor-Token`. Routes with a
+//! `:case_id` segment are also limited by the matter's access list and ethical
+//! walls, and every other route gets the caller's `MatterScope` to filter what
+//! it returns. Unmapped `/api` routes are refused so a new handler can't ship without
+//! a classification.
+//!
+//! Multi-factor authentication is checked last: with `require_mfa` every session
+//! must have been verified, and step-up operations such as audit log exports
+//! need a verification from the last few minutes. Refusals carry an `mfa` field
+//! (`enroll`, `verify` or `step_up`) telling the client what to ask for. The
+//! `/api/mfa` routes themselves only need a valid session; self-service routes
+//! such as `/api/consent` need a valid session that has passed MFA if required.
+
+use super::access_control::{AccessController, UserRole};
+use super::ethical_walls::MatterScope;
+use super::mfa::MfaChallenge;
+use super::LegalOperationType;
+use axum::{
+    extract::{MatchedPath, State},
+    http::{header, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+/// Header carrying the supervising attorney's session token
+pub const SUPERVISOR_TOKEN_HEADER: &str = "x-supervisor-token";
+
+/// Routes anyone may call
+const PUBLIC_ROUTES: &[&str] = &["/api/health"];
+
+/// Route prefixes where callers act on their own records rather than a matter
+const SELF_SERVICE_ROUTES: &[&str] = &["/api/consent"];
+
+/// The caller, inserted into request extensions once access is granted
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: String,
+    pub role: UserRole,
+    pub operation_type: LegalOperationType,
+    /// Attorney who co-signed a supervised request
+    pub supervised_by: Option<String>,
+    /// Matters the caller is kept out of
+    pub matters: MatterScope,
+}
+
+/// The caller of a session-only route, with the bearer token it presented
+#[derive(Debug, Clone)]
+pub struct AuthenticatedSession {
+    pub user_id: String,
+    pub role: UserRole,
+    pub token: String,
+}
+
+/// What a route needs before its handler runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteAccess {
+    Public,
+    /// Any valid session, whatever its MFA state
+    Session,
+    /// Any valid session that has passed MFA when it is required
+    SelfService,
+    Operation(LegalOperationType),
+    Unmapped,
+}
+
+/// Classify a request by method and matched route pattern
+pub fn route_access(method: &Method, route: &str) -> RouteAccess {
+    use LegalOperationType::*;
+
+    if PUBLIC_ROUTES.contains(&route) || !route.starts_with("/api/") {
+        return RouteAccess::Public;
+    }
+    if route.starts_with("/api/mfa/") {
+        return RouteAccess::Session;
+    }
+    if SELF_SERVICE_ROUTES
+        .iter()
+        .any(|prefix| route.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+    {
+        return RouteAccess::SelfService;
+    }
+
+    let operation = match (method.as_str(), route) {
+        ("POST", "/api/ai/prompt") => AILegalAdvice,
+        ("POST", "/api/ai/voice") => VoiceRecording,
+        (_, "/api/ai/risk/outcomes")
+        | (_, "/api/ai/risk/train")
+        | (_, "/api/ai/risk/score")
+        | (_, "/api/ai/risk/:id/explain") => ClientDataProcessing,
+        (_, "/api/communications/tone/score")
+        | (_, "/api/communications/tone/timeline")
+        | (_, "/api/communications/tone/alerts") => DocumentIntelligence,
+        ("POST", "/api/timeline/extract") | ("GET", "/api/timeline/candidates") => TimelineAnalysis,
+        ("POST", "/api/timeline/candidates/:id/approve")
+        | ("POST", "/api/timeline/candidates/:id/reject") => DocumentModification,
+        (_, "/api/search") | (_, "/api/search/reindex") => SemanticSearch,
+        (_, "/api/secure/communications") | (_, "/api/secure/communications/:id") => ClientDataProcessing,
+        ("GET", "/api/data/diff") => DocumentIntelligence,
+        ("POST", "/api/data/commit") => DocumentModification,
+        ("GET", "/api/dashboard") => CollaborationMetrics,
+        ("POST", "/api/matters")
+        | ("POST", "/api/matters/conflict-check")
+        | ("GET", "/api/matters/conflicts/:report_id")
+        | ("POST", "/api/matters/conflicts/:report_id/review")
+        | ("GET", "/api/matters/:case_id/parties")
+        | ("GET", "/api/matters/:case_id/access")
+        | ("POST", "/api/matters/:case_id/access")
+        | ("DELETE", "/api/matters/:case_id/access/:user_id")
+        | ("POST", "/api/walls")
+        | ("POST", "/api/walls/:wall_id/members")
+        | ("POST", "/api/walls/:wall_id/lift") => ClientDataProcessing,
+        ("GET", "/api/audit/export") => AuditLogExport,
+        ("DELETE", "/api/exhibits/:id") => ExhibitDeletion,
+        ("GET", "/api/exhibits/:id/export") => ClientDataProcessing,
+        (_, "/api/retention/files")
+        | (_, "/api/retention/records")
+        | ("GET", "/api/retention/reviews")
+        | ("POST", "/api/retention/reviews/:id")
+        | ("POST", "/api/retention/dispose")
+        | ("GET", "/api/retention/certificates")
+        | ("POST", "/api/retention/holds/:hold_id/release")
+        | ("GET", "/api/matters/:case_id/holds")
+        | ("POST", "/api/matters/:case_id/holds") => RecordDisposition,
+        _ => return RouteAccess::Unmapped,
+    };
+    RouteAccess::Operation(operation)
+}
+
+/// Axum middleware enforcing `route_access`; install with
+/// `route_layer(middleware::from_fn_with_state(controller, require_access))`
+pub async fn require_access<B>(
+    State(controller): State<AccessController>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = match request.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().to_string(),
+        None => request.uri().path().to_string(),
+    };
+
+    let access = route_access(request.method(), &route);
+    if access == RouteAccess::Public {
+        return next.run(request).await;
+    }
+    if access == RouteAccess::Unmapped {
+        tracing::warn!("Refusing {} {}: route has no legal operation mapping", request.method(), route);
+        return deny(StatusCode::FORBIDDEN, "Route is not available");
+    }
+
+    let Some(token) = bearer_token(&request).map(str::to_string) else {
+        return deny(StatusCode::UNAUTHORIZED, "Missing bearer token");
+    };
+    let Ok(user_id) = controller.validate_auth_token(&token).await else {
+        return deny(StatusCode::UNAUTHORIZED, "Invalid or expired token");
+    };
+    let user = match controller.get_user(&user_id).await {
+        Ok(Some(user)) => user,
+        _ => return deny(StatusCode::UNAUTHORIZED, "Invalid or expired token"),
+    };
+
+    let RouteAccess::Operation(operation_type) = access else {
+        if access == RouteAccess::SelfService {
+            match controller.mfa().session_challenge(&user_id, &token).await {
+                Ok(None) => {}
+                Ok(Some(challenge)) => return deny_mfa(challenge, None),
+                Err(e) => {
+                    tracing::error!("Failed to check MFA for {}: {}", user_id, e);
+                    return deny(StatusCode::INTERNAL_SERVER_ERROR, "Unable to check multi-factor authentication");
+                }
+            }
+        }
+        request.extensions_mut().insert(AuthenticatedSession { user_id, role: user.role, token });
+        return next.run(request).await;
+    };
+
+    if !controller.has_permission(&user_id, &operation_type).await {
+        tracing::warn!("User {} denied {:?} on {} {}", user_id, operation_type, request.method(), route);
+        return deny(StatusCode::FORBIDDEN, &format!("Not permitted to perform {:?}", operation_type));
+    }
+
+    if let Some(case_id) = path_param(&route, request.uri().path(), "case_id") {
+        let allowed = match case_id.parse::<i64>() {
+            Ok(case_id) => controller.matters().can_access_matter(&user_id, case_id).await,
+            Err(_) => false,
+        };
+        if !allowed {
+            tracing::warn!("User {} denied access to case {} on {}", user_id, case_id, route);
+            return deny(StatusCode::FORBIDDEN, "Not permitted to access this matter");
+        }
+    }
+
+    let matters = match controller.matters().scope(&user_id).await {
+        Ok(matters) => matters,
+        Err(e) => {
+            tracing::error!("Failed to load matter access for {}: {}", user_id, e);
+            return deny(StatusCode::INTERNAL_SERVER_ERROR, "Unable to check matter access");
+        }
+    };
+
+    let mut supervised_by = None;
+    if controller.requires_supervision(&user_id, &operation_type).await {
+        match supervising_attorney(&controller, &request, &user_id).await {
+            Some(attorney_id) => supervised_by = Some(attorney_id),
+            None => {
+                tracing::warn!("User {} attempted {:?} without attorney supervision", user_id, operation_type);
+                return deny(
+                    StatusCode::FORBIDDEN,
+                    &format!("{:?} requires a token from the supervising attorney", operation_type),
+                );
+            }
+        }
+    }
+
+    match controller.mfa().challenge(&user_id, &token, &operation_type).await {
+        Ok(None) => {}
+        Ok(Some(challenge)) => {
+            tracing::info!("User {} needs MFA ({:?}) for {:?}", user_id, challenge, operation_type);
+            return deny_mfa(challenge, Some(&operation_type));
+        }
+        Err(e) => {
+            tracing::error!("Failed to check MFA for {}: {}", user_id, e);
+            return deny(StatusCode::INTERNAL_SERVER_ERROR, "Unable to check multi-factor authentication");
+        }
+    }
+
+    request.extensions_mut().insert(AuthenticatedUser {
+        user_id,
+        role: user.role,
+        operation_type,
+        supervised_by,
+        matters,
+    });
+    next.run(request).await
+}
+
+/// The supervisor's user ID, if the supervisor token belongs to the caller's
+/// supervising attorney
+async fn supervising_attorney<B>(
+    controller: &AccessController,
+    request: &Request<B>,
+    user_id: &str,
+) -> Option<String> {
+    let token = request.headers().get(SUPERVISOR_TOKEN_HEADER)?.to_str().ok()?;
+    let supervisor_id = controller.validate_auth_token(token).await.ok()?;
+    let supervisor = controller.get_supervisor(user_id).await?;
+    (supervisor.user_id == supervisor_id && supervisor.role == UserRole::Attorney && supervisor.active)
+        .then_some(supervisor_id)
+}
+
+/// Value of the `:name` segment of `route` in the request path
+fn path_param<'a>(route: &str, path: &'a str, name: &str) -> Option<&'a str> {
+    let position = route.split('/').position(|segment| segment.strip_prefix(':') == Some(name))?;
+    path.split('/').nth(position)
+}
+
+fn bearer_token<B>(request: &Request<B>) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn deny(status: StatusCode, error: &str) -> Response {
+    (status, Json(json!({ "success": false, "error": error }))).into_response()
+}
+
+fn deny_mfa(challenge: MfaChallenge, operation_type: Option<&LegalOperationType>) -> Response {
+    let error = match (challenge, operation_type) {
+        (MfaChallenge::Enroll, _) => "Multi-factor authentication enrollment is required".to_string(),
+        (MfaChallenge::StepUp, Some(operation_type)) => {
+            format!("{:?} requires a fresh multi-factor verification", operation_type)
+        }
+        _ => "Multi-factor verification is required".to_string(),
+    };
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({ "success": false, "error": error, "mfa": challenge })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attorney_only_routes_map_to_sensitive_operations() {
+        assert_eq!(
+            route_access(&Method::POST, "/api/ai/prompt"),
+            RouteAccess::Operation(LegalOperationType::AILegalAdvice)
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/api/data/commit"),
+            RouteAccess::Operation(LegalOperationType::DocumentModification)
+        );
+        assert_eq!(
+            route_access(&Method::DELETE, "/api/secure/communications/:id"),
+            RouteAccess::Operation(LegalOperationType::ClientDataProcessing)
+        );
+        assert_eq!(
+            route_access(&Method::DELETE, "/api/exhibits/:id"),
+            RouteAccess::Operation(LegalOperationType::ExhibitDeletion)
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/api/exhibits/:id/export"),
+            RouteAccess::Operation(LegalOperationType::ClientDataProcessing)
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/api/matters/:case_id/holds"),
+            RouteAccess::Operation(LegalOperationType::RecordDisposition)
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/api/walls/:wall_id/lift"),
+            RouteAccess::Operation(LegalOperationType::ClientDataProcessing)
+        );
+        assert_eq!(route_access(&Method::POST, "/api/mfa/verify"), RouteAccess::Session);
+        assert_eq!(route_access(&Method::POST, "/api/consent"), RouteAccess::SelfService);
+        assert_eq!(route_access(&Method::GET, "/api/consent/report"), RouteAccess::SelfService);
+        assert_eq!(route_access(&Method::GET, "/api/consentless"), RouteAccess::Unmapped);
+        assert_eq!(route_access(&Method::GET, "/api/health"), RouteAccess::Public);
+        assert_eq!(route_access(&Method::GET, "/index.html"), RouteAccess::Public);
+        assert_eq!(route_access(&Method::DELETE, "/api/data/commit"), RouteAccess::Unmapped);
+        assert_eq!(route_access(&Method::GET, "/api/new-feature"), RouteAccess::Unmapped);
+    }
+
+    #[test]
+    fn test_path_param_reads_case_id_from_request_path() {
+        let route = "/api/matters/:case_id/parties";
+        assert_eq!(path_param(route, "/api/matters/42/parties", "case_id"), Some("42"));
+        assert_eq!(path_param("/api/matters/conflicts/:report_id", "/api/matters/conflicts/abc", "case_id"), None);
+    }
+}
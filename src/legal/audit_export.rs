@@ -0,0 +1,832 @@
+//! Audit Log Export Formats for MoodBridge_Rust
+//!
+//! Exports are produced one entry at a time so large date ranges can be streamed.
+//! Every format carries the result of verifying the hash chain and ends with a
+//! summary holding a SHA-256 digest of the exported entries.
+
+use crate::legal::audit_log::ExportFormat;
+use crate::legal::audit_store::AuditVerification;
+use crate::legal::AuditLogEntry;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Namespace of XML exports, described by `AUDIT_LOG_XSD`
+pub const AUDIT_LOG_XML_NAMESPACE: &str = "urn:moodbridge:audit-log:1";
+
+/// XML Schema for XML exports
+pub const AUDIT_LOG_XSD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           xmlns="urn:moodbridge:audit-log:1"
+           targetNamespace="urn:moodbridge:audit-log:1"
+           elementFormDefault="qualified">
+  <xs:element name="audit_log">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="verification" type="VerificationType"/>
+        <xs:element name="entries">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="entry" type="EntryType" minOccurs="0" maxOccurs="unbounded"/>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+        <xs:element name="summary" type="SummaryType"/>
+      </xs:sequence>
+      <xs:attribute name="generated_at" type="xs:dateTime" use="required"/>
+      <xs:attribute name="start_date" type="xs:dateTime"/>
+      <xs:attribute name="end_date" type="xs:dateTime"/>
+    </xs:complexType>
+  </xs:element>
+  <xs:complexType name="VerificationType">
+    <xs:sequence>
+      <xs:element name="issue" type="xs:string" minOccurs="0" maxOccurs="unbounded"/>
+    </xs:sequence>
+    <xs:attribute name="status" use="required">
+      <xs:simpleType>
+        <xs:restriction base="xs:string">
+          <xs:enumeration value="intact"/>
+          <xs:enumeration value="tampered"/>
+          <xs:enumeration value="not_verified"/>
+        </xs:restriction>
+      </xs:simpleType>
+    </xs:attribute>
+    <xs:attribute name="entries_checked" type="xs:nonNegativeInteger"/>
+    <xs:attribute name="segments_checked" type="xs:nonNegativeInteger"/>
+    <xs:attribute name="checkpoints_checked" type="xs:nonNegativeInteger"/>
+    <xs:attribute name="last_sequence" type="xs:nonNegativeInteger"/>
+    <xs:attribute name="last_hash" type="xs:string"/>
+  </xs:complexType>
+  <xs:complexType name="EntryType">
+    <xs:sequence>
+      <xs:element name="id" type="xs:string"/>
+      <xs:element name="timestamp" type="xs:dateTime"/>
+      <xs:element name="user_id" type="xs:string"/>
+      <xs:element name="operation_type" type="xs:string"/>
+      <xs:element name="compliance_status" type="xs:string"/>
+      <xs:element name="attorney_review_required" type="xs:boolean"/>
+      <xs:element name="data_classification" type="xs:string"/>
+      <xs:element name="details">
+        <xs:complexType>
+          <xs:sequence>
+            <xs:element name="detail" minOccurs="0" maxOccurs="unbounded">
+              <xs:complexType>
+                <xs:simpleContent>
+                  <xs:extension base="xs:string">
+                    <xs:attribute name="key" type="xs:string" use="required"/>
+                  </xs:extension>
+                </xs:simpleContent>
+              </xs:complexType>
+            </xs:element>
+          </xs:sequence>
+        </xs:complexType>
+      </xs:element>
+    </xs:sequence>
+    <xs:attribute name="sequence" type="xs:positiveInteger"/>
+    <xs:attribute name="hash" type="xs:string"/>
+  </xs:complexType>
+  <xs:complexType name="SummaryType">
+    <xs:attribute name="entry_count" type="xs:nonNegativeInteger" use="required"/>
+    <xs:attribute name="first_sequence" type="xs:positiveInteger"/>
+    <xs:attribute name="last_sequence" type="xs:positiveInteger"/>
+    <xs:attribute name="content_digest" type="xs:string" use="required"/>
+  </xs:complexType>
+</xs:schema>
+"#;
+
+/// What an export covers, written before its entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportHeader {
+    pub generated_at: DateTime<Utc>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    /// `None` when the logger has no persistent store to verify
+    pub verification: Option<AuditVerification>,
+}
+
+impl ExportHeader {
+    fn chain_status(&self) -> &'static str {
+        match &self.verification {
+            Some(verification) if verification.is_intact() => "intact",
+            Some(_) => "tampered",
+            None => "not_verified",
+        }
+    }
+}
+
+/// Totals written after an export's entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub entry_count: usize,
+    pub first_sequence: Option<u64>,
+    pub last_sequence: Option<u64>,
+    /// SHA-256 over the exported entries in order
+    pub content_digest: String,
+}
+
+/// Formats an export incrementally; each call returns the bytes to write next
+pub struct AuditExporter {
+    header: ExportHeader,
+    format: FormatState,
+    digest: Sha256,
+    entry_count: usize,
+    first_sequence: Option<u64>,
+    last_sequence: Option<u64>,
+}
+
+enum FormatState {
+    Json,
+    Csv,
+    Xml,
+    Pdf(Box<PdfWriter>),
+}
+
+impl AuditExporter {
+    pub fn new(format: &ExportFormat, header: ExportHeader) -> Self {
+        let format = match format {
+            ExportFormat::JSON => FormatState::Json,
+            ExportFormat::CSV => FormatState::Csv,
+            ExportFormat::XML => FormatState::Xml,
+            ExportFormat::PDF => FormatState::Pdf(Box::new(PdfWriter::new(&header))),
+        };
+        Self {
+            header,
+            format,
+            digest: Sha256::new(),
+            entry_count: 0,
+            first_sequence: None,
+            last_sequence: None,
+        }
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entry_count
+    }
+
+    pub fn begin(&mut self) -> Result<Vec<u8>, String> {
+        let header = &self.header;
+        match &mut self.format {
+            FormatState::Json => {
+                let verification = serde_json::to_string(&header.verification)
+                    .map_err(|e| format!("JSON export failed: {}", e))?;
+                Ok(format!(
+                    "{{\"generated_at\":\"{}\",\"start_date\":{},\"end_date\":{},\"chain_status\":\"{}\",\"verification\":{},\"entries\":[",
+                    header.generated_at.to_rfc3339(),
+                    json_date(header.start_date),
+                    json_date(header.end_date),
+                    header.chain_status(),
+                    verification
+                )
+                .into_bytes())
+            }
+            FormatState::Csv => Ok(csv_record(&[
+                "Sequence",
+                "Timestamp",
+                "User ID",
+                "Operation Type",
+                "Compliance Status",
+                "Attorney Review Required",
+                "Data Classification",
+                "Entry ID",
+                "Entry Hash",
+                "Details",
+                "Chain Verification",
+            ])
+            .into_bytes()),
+            FormatState::Xml => Ok(xml_begin(header).into_bytes()),
+            FormatState::Pdf(pdf) => Ok(pdf.begin()),
+        }
+    }
+
+    /// Format one entry, with its chain position when it came from the audit store
+    pub fn entry(
+        &mut self,
+        sequence: Option<u64>,
+        hash: Option<&str>,
+        entry: &AuditLogEntry,
+    ) -> Result<Vec<u8>, String> {
+        // Object keys come out sorted through Value, so the digest is reproducible
+        let canonical = serde_json::to_value(entry)
+            .and_then(|value| serde_json::to_vec(&value))
+            .map_err(|e| format!("Export failed: {}", e))?;
+        self.digest.update(sequence.unwrap_or(0).to_be_bytes());
+        self.digest.update(hash.unwrap_or("").as_bytes());
+        self.digest.update(&canonical);
+
+        let first = self.entry_count == 0;
+        self.entry_count += 1;
+        if self.first_sequence.is_none() {
+            self.first_sequence = sequence;
+        }
+        if sequence.is_some() {
+            self.last_sequence = sequence;
+        }
+
+        let chain_status = self.header.chain_status();
+        match &mut self.format {
+            FormatState::Json => {
+                let row = serde_json::json!({
+                    "sequence": sequence,
+                    "hash": hash,
+                    "entry": entry,
+                });
+                let separator = if first { "\n" } else { ",\n" };
+                Ok(format!("{}{}", separator, row).into_bytes())
+            }
+            FormatState::Csv => Ok(csv_record(&[
+                &sequence.map(|s| s.to_string()).unwrap_or_default(),
+                &entry.timestamp.to_rfc3339(),
+                &entry.user_id,
+                &format!("{:?}", entry.operation_type),
+                &format!("{:?}", entry.compliance_status),
+                &entry.attorney_review_required.to_string(),
+                &format!("{:?}", entry.data_processed),
+                &entry.entry_id,
+                hash.unwrap_or(""),
+                &details_json(entry),
+                chain_status,
+            ])
+            .into_bytes()),
+            FormatState::Xml => Ok(xml_entry(sequence, hash, entry).into_bytes()),
+            FormatState::Pdf(pdf) => Ok(pdf.entry(sequence, hash, entry)),
+        }
+    }
+
+    pub fn finish(self) -> Result<(Vec<u8>, ExportSummary), String> {
+        let summary = ExportSummary {
+            entry_count: self.entry_count,
+            first_sequence: self.first_sequence,
+            last_sequence: self.last_sequence,
+            content_digest: hex::encode(self.digest.finalize()),
+        };
+
+        let bytes = match self.format {
+            FormatState::Json => {
+                let summary_json = serde_json::to_string(&summary)
+                    .map_err(|e| format!("JSON export failed: {}", e))?;
+                format!("\n],\"summary\":{}}}\n", summary_json).into_bytes()
+            }
+            // CSV has no trailer; each row carries the verification result
+            FormatState::Csv => Vec::new(),
+            FormatState::Xml => xml_finish(&summary).into_bytes(),
+            FormatState::Pdf(mut pdf) => pdf.finish(&self.header, &summary),
+        };
+        Ok((bytes, summary))
+    }
+}
+
+fn json_date(date: Option<DateTime<Utc>>) -> String {
+    date.map(|d| format!("\"{}\"", d.to_rfc3339()))
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Operation details as JSON with sorted keys
+fn details_json(entry: &AuditLogEntry) -> String {
+    serde_json::to_value(&entry.operation_details)
+        .map(|value| value.to_string())
+        .unwrap_or_default()
+}
+
+/// One RFC 4180 record, CRLF-terminated
+pub(crate) fn csv_record(fields: &[&str]) -> String {
+    let mut record = fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",");
+    record.push_str("\r\n");
+    record
+}
+
+/// Quote a field when it holds a comma, quote or line break, doubling inner quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escape text for XML, dropping characters XML 1.0 can't carry
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn xml_begin(header: &ExportHeader) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<audit_log xmlns=\"{}\" generated_at=\"{}\"",
+        AUDIT_LOG_XML_NAMESPACE,
+        header.generated_at.to_rfc3339()
+    ));
+    if let Some(start_date) = header.start_date {
+        xml.push_str(&format!(" start_date=\"{}\"", start_date.to_rfc3339()));
+    }
+    if let Some(end_date) = header.end_date {
+        xml.push_str(&format!(" end_date=\"{}\"", end_date.to_rfc3339()));
+    }
+    xml.push_str(">\n");
+
+    xml.push_str(&format!("  <verification status=\"{}\"", header.chain_status()));
+    match &header.verification {
+        Some(verification) => {
+            xml.push_str(&format!(
+                " entries_checked=\"{}\" segments_checked=\"{}\" checkpoints_checked=\"{}\" last_sequence=\"{}\" last_hash=\"{}\"",
+                verification.entries_checked,
+                verification.segments_checked,
+                verification.checkpoints_checked,
+                verification.last_sequence,
+                xml_escape(&verification.last_hash)
+            ));
+            if verification.issues.is_empty() {
+                xml.push_str("/>\n");
+            } else {
+                xml.push_str(">\n");
+                for issue in &verification.issues {
+                    xml.push_str(&format!("    <issue>{}</issue>\n", xml_escape(&issue.to_string())));
+                }
+                xml.push_str("  </verification>\n");
+            }
+        }
+        None => xml.push_str("/>\n"),
+    }
+
+    xml.push_str("  <entries>\n");
+    xml
+}
+
+fn xml_entry(sequence: Option<u64>, hash: Option<&str>, entry: &AuditLogEntry) -> String {
+    let mut xml = String::from("    <entry");
+    if let Some(sequence) = sequence {
+        xml.push_str(&format!(" sequence=\"{}\"", sequence));
+    }
+    if let Some(hash) = hash {
+        xml.push_str(&format!(" hash=\"{}\"", xml_escape(hash)));
+    }
+    xml.push_str(">\n");
+    xml.push_str(&format!("      <id>{}</id>\n", xml_escape(&entry.entry_id)));
+    xml.push_str(&format!("      <timestamp>{}</timestamp>\n", entry.timestamp.to_rfc3339()));
+    xml.push_str(&format!("      <user_id>{}</user_id>\n", xml_escape(&entry.user_id)));
+    xml.push_str(&format!("      <operation_type>{:?}</operation_type>\n", entry.operation_type));
+    xml.push_str(&format!("      <compliance_status>{:?}</compliance_status>\n", entry.compliance_status));
+    xml.push_str(&format!("      <attorney_review_required>{}</attorney_review_required>\n", entry.attorney_review_required));
+    xml.push_str(&format!("      <data_classification>{:?}</data_classification>\n", entry.data_processed));
+
+    let mut details: Vec<_> = entry.operation_details.iter().collect();
+    details.sort_by(|a, b| a.0.cmp(b.0));
+    if details.is_empty() {
+        xml.push_str("      <details/>\n");
+    } else {
+        xml.push_str("      <details>\n");
+        for (key, value) in details {
+            xml.push_str(&format!(
+                "        <detail key=\"{}\">{}</detail>\n",
+                xml_escape(key),
+                xml_escape(&value.to_string())
+            ));
+        }
+        xml.push_str("      </details>\n");
+    }
+    xml.push_str("    </entry>\n");
+    xml
+}
+
+fn xml_finish(summary: &ExportSummary) -> String {
+    let mut xml = String::from("  </entries>\n");
+    xml.push_str(&format!("  <summary entry_count=\"{}\"", summary.entry_count));
+    if let Some(first_sequence) = summary.first_sequence {
+        xml.push_str(&format!(" first_sequence=\"{}\"", first_sequence));
+    }
+    if let Some(last_sequence) = summary.last_sequence {
+        xml.push_str(&format!(" last_sequence=\"{}\"", last_sequence));
+    }
+    xml.push_str(&format!(" content_digest=\"{}\"/>\n", summary.content_digest));
+    xml.push_str("</audit_log>\n");
+    xml
+}
+
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+const BODY_FONT_SIZE: f32 = 8.0;
+const LINE_HEIGHT: f32 = 10.0;
+const BODY_TOP: f32 = PAGE_HEIGHT - MARGIN - 36.0;
+const BODY_BOTTOM: f32 = MARGIN + 24.0;
+/// Courier is 0.6 em wide, so this many characters fit between the margins
+const MAX_LINE_CHARS: usize = 104;
+
+const CATALOG_ID: u32 = 1;
+const PAGES_ID: u32 = 2;
+const FONT_REGULAR_ID: u32 = 3;
+const FONT_BOLD_ID: u32 = 4;
+const FONT_MONO_ID: u32 = 5;
+
+/// Minimal PDF 1.4 writer that emits each page as soon as it is full
+///
+/// Byte offsets are tracked as objects go out, so the cross-reference table can be
+/// written at the end without holding earlier pages.
+struct PdfWriter {
+    offset: usize,
+    object_offsets: Vec<(u32, usize)>,
+    next_object: u32,
+    page_ids: Vec<u32>,
+    lines: Vec<String>,
+    subtitle: String,
+}
+
+impl PdfWriter {
+    fn new(header: &ExportHeader) -> Self {
+        let range = match (header.start_date, header.end_date) {
+            (None, None) => "all entries".to_string(),
+            (start, end) => format!(
+                "{} to {}",
+                start.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "beginning".to_string()),
+                end.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "present".to_string())
+            ),
+        };
+        Self {
+            offset: 0,
+            object_offsets: Vec::new(),
+            next_object: FONT_MONO_ID + 1,
+            page_ids: Vec::new(),
+            lines: Vec::new(),
+            subtitle: format!(
+                "Generated {} | Range: {} | Hash chain: {}",
+                header.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                range,
+                header.chain_status().replace('_', " ").to_uppercase()
+            ),
+        }
+    }
+
+    fn lines_per_page() -> usize {
+        ((BODY_TOP - BODY_BOTTOM) / LINE_HEIGHT) as usize
+    }
+
+    fn begin(&mut self) -> Vec<u8> {
+        let mut out = self.raw(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        for (id, base_font) in [
+            (FONT_REGULAR_ID, "Helvetica"),
+            (FONT_BOLD_ID, "Helvetica-Bold"),
+            (FONT_MONO_ID, "Courier"),
+        ] {
+            let font = format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                base_font
+            );
+            out.extend(self.object(id, font.as_bytes()));
+        }
+        out
+    }
+
+    fn entry(&mut self, sequence: Option<u64>, hash: Option<&str>, entry: &AuditLogEntry) -> Vec<u8> {
+        let mut block = vec![format!(
+            "#{:<7} {}  {}  {:?}",
+            sequence.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            entry.user_id,
+            entry.operation_type
+        )];
+        block.push(format!(
+            "         status {:?} | data {:?} | attorney review {}",
+            entry.compliance_status,
+            entry.data_processed,
+            if entry.attorney_review_required { "required" } else { "not required" }
+        ));
+        block.push(format!("         entry {}", entry.entry_id));
+        if let Some(hash) = hash {
+            block.push(format!("         hash  {}", hash));
+        }
+        if !entry.operation_details.is_empty() {
+            block.push(format!("         details {}", details_json(entry)));
+        }
+        block.push(String::new());
+
+        let wrapped: Vec<String> = block.iter().flat_map(|line| wrap(line, "         ")).collect();
+        self.push_block(wrapped)
+    }
+
+    fn finish(&mut self, header: &ExportHeader, summary: &ExportSummary) -> Vec<u8> {
+        let mut block = vec!["HASH SUMMARY".to_string(), String::new()];
+        block.push(format!("Entries exported:    {}", summary.entry_count));
+        if let (Some(first), Some(last)) = (summary.first_sequence, summary.last_sequence) {
+            block.push(format!("Sequence range:      #{} to #{}", first, last));
+        }
+        block.push(format!("Export digest:       {}", summary.content_digest));
+        block.push(String::new());
+        match &header.verification {
+            Some(verification) => {
+                block.push(format!(
+                    "Chain verification:  {}",
+                    if verification.is_intact() { "INTACT" } else { "TAMPERED" }
+                ));
+                block.push(format!("Verified at:         {}", verification.verified_at.to_rfc3339()));
+                block.push(format!(
+                    "Checked:             {} entries, {} sealed segments, {} signed checkpoints",
+                    verification.entries_checked, verification.segments_checked, verification.checkpoints_checked
+                ));
+                block.push(format!("Chain head:          #{}", verification.last_sequence));
+                block.push(format!("                     {}", verification.last_hash));
+                for issue in &verification.issues {
+                    block.push(format!("  ! {}", issue));
+                }
+            }
+            None => block.push("Chain verification:  NOT VERIFIED (no persistent audit store)".to_string()),
+        }
+
+        let wrapped: Vec<String> = block.iter().flat_map(|line| wrap(line, "    ")).collect();
+        let mut out = self.push_block(wrapped);
+        out.extend(self.flush_page());
+
+        let kids = self
+            .page_ids
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let pages = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, self.page_ids.len());
+        out.extend(self.object(PAGES_ID, pages.as_bytes()));
+        let catalog = format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID);
+        out.extend(self.object(CATALOG_ID, catalog.as_bytes()));
+        let info_id = self.allocate();
+        let info = format!(
+            "<< /Title ({}) /Producer (MoodBridge) /CreationDate (D:{}Z) >>",
+            pdf_string("Legal Audit Log Export"),
+            header.generated_at.format("%Y%m%d%H%M%S")
+        );
+        out.extend(self.object(info_id, info.as_bytes()));
+
+        let xref_offset = self.offset;
+        let mut offsets = self.object_offsets.clone();
+        offsets.sort_by_key(|(id, _)| *id);
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for (_, offset) in &offsets {
+            xref.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            CATALOG_ID,
+            info_id,
+            xref_offset
+        ));
+        out.extend(self.raw(xref.as_bytes()));
+        out
+    }
+
+    /// Add lines, starting a new page first when they wouldn't fit on this one
+    fn push_block(&mut self, block: Vec<String>) -> Vec<u8> {
+        let mut out = Vec::new();
+        let per_page = Self::lines_per_page();
+        if !self.lines.is_empty() && self.lines.len() + block.len() > per_page {
+            out.extend(self.flush_page());
+        }
+        for line in block {
+            if self.lines.len() >= per_page {
+                out.extend(self.flush_page());
+            }
+            self.lines.push(line);
+        }
+        out
+    }
+
+    fn flush_page(&mut self) -> Vec<u8> {
+        if self.lines.is_empty() && !self.page_ids.is_empty() {
+            return Vec::new();
+        }
+        let page_number = self.page_ids.len() + 1;
+
+        let mut content = Vec::new();
+        content.extend(text_op("F2", 12.0, MARGIN, PAGE_HEIGHT - MARGIN, "MoodBridge Legal Audit Log"));
+        content.extend(text_op("F1", 8.0, MARGIN, PAGE_HEIGHT - MARGIN - 14.0, &self.subtitle));
+        content.extend(rule(PAGE_HEIGHT - MARGIN - 22.0));
+        for (index, line) in self.lines.drain(..).enumerate() {
+            let y = BODY_TOP - index as f32 * LINE_HEIGHT;
+            content.extend(text_op("F3", BODY_FONT_SIZE, MARGIN, y, &line));
+        }
+        content.extend(rule(MARGIN + 12.0));
+        content.extend(text_op(
+            "F1",
+            7.0,
+            MARGIN,
+            MARGIN,
+            "Confidential audit record. Entries are SHA-256 hash-chained; see the hash summary on the last page.",
+        ));
+        let page_label = format!("Page {}", page_number);
+        let label_x = PAGE_WIDTH - MARGIN - page_label.len() as f32 * 0.6 * 7.0;
+        content.extend(text_op("F3", 7.0, label_x, MARGIN, &page_label));
+
+        let content_id = self.allocate();
+        let page_id = self.allocate();
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(&content);
+        stream.extend(b"\nendstream");
+        let mut out = self.object(content_id, &stream);
+
+        let page = format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources << /Font << /F1 {} 0 R /F2 {} 0 R /F3 {} 0 R >> >> >>",
+            PAGES_ID, PAGE_WIDTH, PAGE_HEIGHT, content_id, FONT_REGULAR_ID, FONT_BOLD_ID, FONT_MONO_ID
+        );
+        out.extend(self.object(page_id, page.as_bytes()));
+        self.page_ids.push(page_id);
+        out
+    }
+
+    fn allocate(&mut self) -> u32 {
+        let id = self.next_object;
+        self.next_object += 1;
+        id
+    }
+
+    fn object(&mut self, id: u32, body: &[u8]) -> Vec<u8> {
+        self.object_offsets.push((id, self.offset));
+        let mut out = format!("{} 0 obj\n", id).into_bytes();
+        out.extend(body);
+        out.extend(b"\nendobj\n");
+        self.offset += out.len();
+        out
+    }
+
+    fn raw(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.offset += bytes.len();
+        bytes.to_vec()
+    }
+}
+
+/// Split a line into pieces of at most `MAX_LINE_CHARS`, indenting continuations
+fn wrap(line: &str, indent: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= MAX_LINE_CHARS {
+        return vec![line.to_string()];
+    }
+    let mut lines = vec![chars[..MAX_LINE_CHARS].iter().collect::<String>()];
+    let width = MAX_LINE_CHARS - indent.len();
+    for chunk in chars[MAX_LINE_CHARS..].chunks(width) {
+        lines.push(format!("{}{}", indent, chunk.iter().collect::<String>()));
+    }
+    lines
+}
+
+fn text_op(font: &str, size: f32, x: f32, y: f32, text: &str) -> Vec<u8> {
+    let mut op = format!("BT /{} {} Tf {:.1} {:.1} Td (", font, size, x, y).into_bytes();
+    op.extend(pdf_string(text).bytes());
+    op.extend(b") Tj ET\n");
+    op
+}
+
+fn rule(y: f32) -> Vec<u8> {
+    format!("0.5 w {:.1} {:.1} m {:.1} {:.1} l S\n", MARGIN, y, PAGE_WIDTH - MARGIN, y).into_bytes()
+}
+
+/// Escape text for a PDF literal string; characters outside Latin-1 become `?`
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            '\u{A0}'..='\u{FF}' => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legal::audit_log::{AuditLogger, AuditSearchCriteria};
+    use crate::legal::{ComplianceStatus, DataClassification, LegalOperationType};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn entry(user_id: &str, timestamp: DateTime<Utc>) -> AuditLogEntry {
+        AuditLogEntry {
+            entry_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            operation_type: LegalOperationType::DocumentModification,
+            timestamp,
+            operation_details: HashMap::from([
+                ("note".to_string(), serde_json::json!("Smith, \"J\" <minor>")),
+            ]),
+            compliance_status: ComplianceStatus::Compliant,
+            attorney_review_required: false,
+            data_processed: DataClassification::Confidential,
+        }
+    }
+
+    fn criteria() -> AuditSearchCriteria {
+        AuditSearchCriteria {
+            user_id: None,
+            operation_type: None,
+            start_date: None,
+            end_date: None,
+            compliance_status: None,
+            data_classification: None,
+            limit: None,
+        }
+    }
+
+    async fn logger_with_entries(dir: &TempDir, count: usize) -> AuditLogger {
+        let logger = AuditLogger::open(dir.path(), KEY).await.unwrap();
+        let start = Utc::now() - chrono::Duration::days(30);
+        for i in 0..count {
+            let timestamp = start + chrono::Duration::hours(i as i64);
+            logger.log_operation(&entry(&format!("user-{}", i), timestamp)).await.unwrap();
+        }
+        logger
+    }
+
+    #[tokio::test]
+    async fn test_pdf_export_is_paginated_with_valid_xref() {
+        let dir = TempDir::new().unwrap();
+        let logger = logger_with_entries(&dir, 80).await;
+        logger.archive_old_logs(Utc::now() - chrono::Duration::days(29)).await.unwrap();
+
+        let pdf = logger.export_audit_log(&criteria(), ExportFormat::PDF).await.unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        assert!(text.contains("HASH SUMMARY"));
+        assert!(text.contains("Hash chain: INTACT"));
+        assert!(text.contains("(Page 2)"));
+
+        // Every xref offset must land on the start of its object
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = String::from_utf8(pdf[startxref..].to_vec()).unwrap();
+        assert!(xref.starts_with("xref\n"));
+        for (id, line) in xref.lines().skip(3).take_while(|l| l.ends_with(" n ")).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", id + 1).as_bytes()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exports_carry_verification_and_filter_by_date() {
+        let dir = TempDir::new().unwrap();
+        let logger = logger_with_entries(&dir, 10).await;
+        logger.archive_old_logs(Utc::now() - chrono::Duration::days(29)).await.unwrap();
+
+        let mut range = criteria();
+        range.start_date = Some(Utc::now() - chrono::Duration::days(30) + chrono::Duration::minutes(90));
+        range.end_date = Some(Utc::now() - chrono::Duration::days(30) + chrono::Duration::minutes(330));
+
+        let mut csv = Vec::new();
+        let summary = logger.export_audit_log_to(&range, &ExportFormat::CSV, &mut csv).await.unwrap();
+        assert_eq!(summary.entry_count, 4);
+        assert_eq!((summary.first_sequence, summary.last_sequence), (Some(3), Some(6)));
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.matches("\r\n").count(), 5);
+        assert!(csv.contains("\"{\"\"note\"\":\"\"Smith, \\\"\"J\\\"\" <minor>\"\"}\",intact\r\n"));
+
+        let xml = logger.export_audit_log(&range, ExportFormat::XML).await.unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.contains("<verification status=\"intact\" entries_checked=\"10\""));
+        assert!(xml.contains("&lt;minor&gt;"));
+        assert_eq!(xml.matches("<entry ").count(), 4);
+        assert!(xml.contains(&format!("content_digest=\"{}\"", summary.content_digest)));
+
+        let json = logger.export_audit_log(&range, ExportFormat::JSON).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["chain_status"], "intact");
+        assert_eq!(json["entries"].as_array().unwrap().len(), 4);
+        assert_eq!(json["summary"]["content_digest"], summary.content_digest.as_str());
+    }
+
+    #[test]
+    fn test_csv_fields_are_quoted_per_rfc_4180() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("Smith, Jane"), "\"Smith, Jane\"");
+        assert_eq!(csv_field("said \"no\""), "\"said \"\"no\"\"\"");
+        assert_eq!(csv_field("line one\nline two"), "\"line one\nline two\"");
+        assert_eq!(csv_record(&["a", "b,c"]), "a,\"b,c\"\r\n");
+    }
+
+    #[test]
+    fn test_xml_and_pdf_escaping() {
+        assert_eq!(xml_escape("<a & 'b'>\u{1}"), "&lt;a &amp; &apos;b&apos;&gt;");
+        assert_eq!(pdf_string("(x) \\ é ✓"), "\\(x\\) \\\\ \\351 ?");
+    }
+
+    #[test]
+    fn test_wrap_keeps_lines_within_page_width() {
+        let line = "x".repeat(250);
+        let wrapped = wrap(&line, "    ");
+        assert!(wrapped.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS));
+        assert_eq!(wrapped.concat().replace(' ', ""), line);
+    }
+}
@@ -5,9 +5,15 @@
 /// and documentation of system activities.
 
 use crate::legal::{AuditLogEntry, LegalOperationType, ComplianceStatus, DataClassification};
+use crate::legal::audit_export::{AuditExporter, ExportHeader, ExportSummary};
+use crate::legal::audit_store::{AuditStore, AuditVerification, ChainedAuditEntry};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 use chrono::{DateTime, Utc};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::RwLock;
 
 /// Audit log search criteria
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +27,18 @@ pub struct AuditSearchCriteria {
     pub limit: Option<usize>,
 }
 
+impl AuditSearchCriteria {
+    /// Whether `entry` passes every filter that is set; `limit` is not applied
+    pub fn matches(&self, entry: &AuditLogEntry) -> bool {
+        self.user_id.as_ref().is_none_or(|user_id| entry.user_id == *user_id)
+            && self.operation_type.as_ref().is_none_or(|operation_type| entry.operation_type == *operation_type)
+            && self.start_date.is_none_or(|start_date| entry.timestamp >= start_date)
+            && self.end_date.is_none_or(|end_date| entry.timestamp <= end_date)
+            && self.compliance_status.as_ref().is_none_or(|status| entry.compliance_status == *status)
+            && self.data_classification.as_ref().is_none_or(|classification| entry.data_processed == *classification)
+    }
+}
+
 /// Audit log statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditStatistics {
@@ -43,28 +61,64 @@ pub enum ExportFormat {
 }
 
 /// Audit logger for legal operations
+///
+/// With an `AuditStore` every entry is persisted to a hash chain before it is
+/// accepted; the in-memory entries are a cache for searches and reports.
 #[derive(Debug, Clone)]
 pub struct AuditLogger {
-    log_entries: Vec<AuditLogEntry>,
+    log_entries: Arc<RwLock<Vec<AuditLogEntry>>>,
     max_entries: usize,
+    store: Option<Arc<AuditStore>>,
+}
+
+impl Default for AuditLogger {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AuditLogger {
+    /// In-memory logger, for tests and tools that don't need a durable trail
     pub fn new() -> Self {
         Self {
-            log_entries: Vec::new(),
+            log_entries: Arc::new(RwLock::new(Vec::new())),
             max_entries: 100_000, // Keep last 100,000 entries in memory
+            store: None,
         }
     }
 
+    /// Logger persisting to the hash-chained store in `dir`
+    pub async fn open(dir: impl AsRef<Path>, signing_key: &[u8]) -> Result<Self, String> {
+        let store = AuditStore::open(dir, signing_key).await?;
+        Self::new().with_store(Arc::new(store)).await
+    }
+
+    /// Persist entries to `store`, loading its unarchived entries
+    pub async fn with_store(mut self, store: Arc<AuditStore>) -> Result<Self, String> {
+        self.load_active(&store).await?;
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    pub fn store(&self) -> Option<&Arc<AuditStore>> {
+        self.store.as_ref()
+    }
+
     /// Log a legal operation
-    pub async fn log_operation(&mut self, entry: &AuditLogEntry) {
-        self.log_entries.push(entry.clone());
+    ///
+    /// Fails if the entry could not be persisted, so callers can refuse the operation.
+    pub async fn log_operation(&self, entry: &AuditLogEntry) -> Result<(), String> {
+        if let Some(store) = &self.store {
+            store.append(entry).await?;
+        }
+
+        let mut log_entries = self.log_entries.write().await;
+        log_entries.push(entry.clone());
         
-        // Maintain maximum entries limit
-        if self.log_entries.len() > self.max_entries {
+        // Maintain maximum entries limit; persisted entries stay in the store
+        if log_entries.len() > self.max_entries {
             // Remove oldest entries but keep important ones
-            self.log_entries.retain(|e| {
+            log_entries.retain(|e| {
                 // Always keep entries that required attorney review
                 if e.attorney_review_required {
                     return true;
@@ -87,56 +141,16 @@ impl AuditLogger {
             entry.operation_type,
             entry.compliance_status
         );
+        Ok(())
     }
 
     /// Search audit log entries
-    pub async fn search_entries(&self, criteria: &AuditSearchCriteria) -> Vec<&AuditLogEntry> {
-        let mut results: Vec<&AuditLogEntry> = self.log_entries
+    pub async fn search_entries(&self, criteria: &AuditSearchCriteria) -> Vec<AuditLogEntry> {
+        let log_entries = self.log_entries.read().await;
+        let mut results: Vec<AuditLogEntry> = log_entries
             .iter()
-            .filter(|entry| {
-                // Filter by user ID
-                if let Some(ref user_id) = criteria.user_id {
-                    if entry.user_id != *user_id {
-                        return false;
-                    }
-                }
-
-                // Filter by operation type
-                if let Some(ref operation_type) = criteria.operation_type {
-                    if entry.operation_type != *operation_type {
-                        return false;
-                    }
-                }
-
-                // Filter by date range
-                if let Some(start_date) = criteria.start_date {
-                    if entry.timestamp < start_date {
-                        return false;
-                    }
-                }
-
-                if let Some(end_date) = criteria.end_date {
-                    if entry.timestamp > end_date {
-                        return false;
-                    }
-                }
-
-                // Filter by compliance status
-                if let Some(ref compliance_status) = criteria.compliance_status {
-                    if entry.compliance_status != *compliance_status {
-                        return false;
-                    }
-                }
-
-                // Filter by data classification
-                if let Some(ref data_classification) = criteria.data_classification {
-                    if entry.data_processed != *data_classification {
-                        return false;
-                    }
-                }
-
-                true
-            })
+            .filter(|entry| criteria.matches(entry))
+            .cloned()
             .collect();
 
         // Sort by timestamp (newest first)
@@ -152,8 +166,9 @@ impl AuditLogger {
 
     /// Get audit statistics
     pub async fn get_statistics(&self) -> AuditStatistics {
+        let log_entries = self.log_entries.read().await;
         let mut stats = AuditStatistics {
-            total_entries: self.log_entries.len(),
+            total_entries: log_entries.len(),
             entries_by_operation: HashMap::new(),
             entries_by_user: HashMap::new(),
             entries_by_compliance_status: HashMap::new(),
@@ -164,7 +179,7 @@ impl AuditLogger {
 
         let recent_threshold = Utc::now() - chrono::Duration::hours(24);
 
-        for entry in &self.log_entries {
+        for entry in log_entries.iter() {
             // Count by operation type
             let operation_key = format!("{:?}", entry.operation_type);
             *stats.entries_by_operation.entry(operation_key).or_insert(0) += 1;
@@ -199,108 +214,66 @@ impl AuditLogger {
     }
 
     /// Export audit log
-    pub async fn export_audit_log(&self, criteria: &AuditSearchCriteria, format: ExportFormat) -> Result<String, String> {
-        let entries = self.search_entries(criteria).await;
+    pub async fn export_audit_log(&self, criteria: &AuditSearchCriteria, format: ExportFormat) -> Result<Vec<u8>, String> {
+        let mut output = Vec::new();
+        self.export_audit_log_to(criteria, &format, &mut output).await?;
+        Ok(output)
+    }
 
-        match format {
-            ExportFormat::JSON => {
-                serde_json::to_string_pretty(&entries)
-                    .map_err(|e| format!("JSON export failed: {}", e))
-            }
-            ExportFormat::CSV => {
-                self.export_to_csv(&entries).await
-            }
-            ExportFormat::PDF => {
-                self.export_to_pdf(&entries).await
+    /// Stream an export to `writer`, oldest entry first
+    ///
+    /// With a store the chain is verified first and the export reads archived
+    /// segments one at a time, so large date ranges never sit in memory at once.
+    /// Without one the in-memory entries are exported and marked as not verified.
+    pub async fn export_audit_log_to<W>(
+        &self,
+        criteria: &AuditSearchCriteria,
+        format: &ExportFormat,
+        writer: &mut W,
+    ) -> Result<ExportSummary, String>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let verification = match &self.store {
+            Some(store) => Some(store.verify().await?),
+            None => None,
+        };
+        let mut exporter = AuditExporter::new(format, ExportHeader {
+            generated_at: Utc::now(),
+            start_date: criteria.start_date,
+            end_date: criteria.end_date,
+            verification,
+        });
+        let limit = criteria.limit.unwrap_or(usize::MAX);
+        write_export(writer, exporter.begin()?).await?;
+
+        match &self.store {
+            Some(store) => {
+                for seal in store.segments().await? {
+                    if exporter.entry_count() >= limit {
+                        break;
+                    }
+                    let segment = store.read_segment(&seal).await?;
+                    export_chained(&mut exporter, writer, criteria, limit, segment).await?;
+                }
+                if exporter.entry_count() < limit {
+                    let active = store.active_entries().await?;
+                    export_chained(&mut exporter, writer, criteria, limit, active).await?;
+                }
             }
-            ExportFormat::XML => {
-                self.export_to_xml(&entries).await
+            None => {
+                let mut entries = self.search_entries(&AuditSearchCriteria { limit: None, ..criteria.clone() }).await;
+                entries.reverse();
+                for entry in entries.iter().take(limit) {
+                    write_export(writer, exporter.entry(None, None, entry)?).await?;
+                }
             }
         }
-    }
-
-    /// Export to CSV format
-    async fn export_to_csv(&self, entries: &[&AuditLogEntry]) -> Result<String, String> {
-        let mut csv = String::from("Timestamp,User ID,Operation Type,Compliance Status,Attorney Review Required,Data Classification,Entry ID\n");
-
-        for entry in entries {
-            csv.push_str(&format!(
-                "{},{},{:?},{:?},{},{:?},{}\n",
-                entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
-                entry.user_id,
-                entry.operation_type,
-                entry.compliance_status,
-                entry.attorney_review_required,
-                entry.data_processed,
-                entry.entry_id
-            ));
-        }
-
-        Ok(csv)
-    }
-
-    /// Export to PDF format (simplified for demo)
-    async fn export_to_pdf(&self, entries: &[&AuditLogEntry]) -> Result<String, String> {
-        let mut pdf_content = String::from("AUDIT LOG REPORT\n");
-        pdf_content.push_str(&format!("Generated: {}\n\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-        
-        for entry in entries {
-            pdf_content.push_str(&format!(
-                "Entry ID: {}\n",
-                entry.entry_id
-            ));
-            pdf_content.push_str(&format!(
-                "Timestamp: {}\n",
-                entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
-            ));
-            pdf_content.push_str(&format!(
-                "User: {}\n",
-                entry.user_id
-            ));
-            pdf_content.push_str(&format!(
-                "Operation: {:?}\n",
-                entry.operation_type
-            ));
-            pdf_content.push_str(&format!(
-                "Compliance Status: {:?}\n",
-                entry.compliance_status
-            ));
-            pdf_content.push_str(&format!(
-                "Attorney Review Required: {}\n",
-                entry.attorney_review_required
-            ));
-            pdf_content.push_str(&format!(
-                "Data Classification: {:?}\n",
-                entry.data_processed
-            ));
-            pdf_content.push_str("---\n\n");
-        }
-
-        Ok(pdf_content)
-    }
-
-    /// Export to XML format
-    async fn export_to_xml(&self, entries: &[&AuditLogEntry]) -> Result<String, String> {
-        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<audit_log>\n");
-        xml.push_str(&format!("  <generated_at>{}</generated_at>\n", Utc::now().to_rfc3339()));
-        xml.push_str("  <entries>\n");
-
-        for entry in entries {
-            xml.push_str("    <entry>\n");
-            xml.push_str(&format!("      <id>{}</id>\n", entry.entry_id));
-            xml.push_str(&format!("      <timestamp>{}</timestamp>\n", entry.timestamp.to_rfc3339()));
-            xml.push_str(&format!("      <user_id>{}</user_id>\n", entry.user_id));
-            xml.push_str(&format!("      <operation_type>{:?}</operation_type>\n", entry.operation_type));
-            xml.push_str(&format!("      <compliance_status>{:?}</compliance_status>\n", entry.compliance_status));
-            xml.push_str(&format!("      <attorney_review_required>{}</attorney_review_required>\n", entry.attorney_review_required));
-            xml.push_str(&format!("      <data_classification>{:?}</data_classification>\n", entry.data_processed));
-            xml.push_str("    </entry>\n");
-        }
 
-        xml.push_str("  </entries>\n");
-        xml.push_str("</audit_log>\n");
-
-        Ok(xml)
+        let (trailer, summary) = exporter.finish()?;
+        write_export(writer, trailer).await?;
+        writer.flush().await.map_err(|e| format!("Export write failed: {}", e))?;
+        Ok(summary)
     }
 
     /// Generate compliance report
@@ -403,56 +376,81 @@ impl AuditLogger {
         report
     }
 
-    /// Archive old audit logs
-    pub async fn archive_old_logs(&mut self, archive_before: DateTime<Utc>) -> usize {
-        let initial_count = self.log_entries.len();
-        
-        self.log_entries.retain(|entry| {
-            // Keep entries newer than archive date
-            if entry.timestamp > archive_before {
-                return true;
-            }
-            
-            // Always keep privileged data operations regardless of age
-            if matches!(entry.data_processed, 
-                DataClassification::AttorneyClientPrivileged | 
-                DataClassification::WorkProduct
-            ) {
-                return true;
-            }
-            
-            // Always keep entries that required attorney review
-            if entry.attorney_review_required {
-                return true;
-            }
-            
-            false
-        });
+    /// Move entries logged at or before `archive_before` into sealed segments
+    ///
+    /// Archived entries leave the in-memory cache but stay in the store, where
+    /// `verify` still checks them. Nothing is deleted.
+    pub async fn archive_old_logs(&self, archive_before: DateTime<Utc>) -> Result<usize, String> {
+        let store = self.store.as_ref()
+            .ok_or_else(|| "Archiving requires a persistent audit store".to_string())?;
+
+        let archived_count = store.archive_before(archive_before).await?;
+        self.load_active(store).await?;
 
-        let archived_count = initial_count - self.log_entries.len();
         tracing::info!("Archived {} old audit log entries", archived_count);
-        
-        archived_count
+        Ok(archived_count)
+    }
+
+    /// Check the persisted trail for modified, missing or truncated entries
+    pub async fn verify(&self) -> Result<AuditVerification, String> {
+        let store = self.store.as_ref()
+            .ok_or_else(|| "Verification requires a persistent audit store".to_string())?;
+        store.verify().await
+    }
+
+    /// Replace the in-memory entries with the newest unarchived ones in `store`
+    async fn load_active(&self, store: &AuditStore) -> Result<(), String> {
+        let active = store.active_entries().await?;
+        let skip = active.len().saturating_sub(self.max_entries);
+        *self.log_entries.write().await =
+            active.into_iter().skip(skip).map(|chained| chained.entry).collect();
+        Ok(())
     }
 
     /// Get entries requiring attorney review
-    pub async fn get_pending_attorney_reviews(&self) -> Vec<&AuditLogEntry> {
+    pub async fn get_pending_attorney_reviews(&self) -> Vec<AuditLogEntry> {
         self.log_entries
+            .read()
+            .await
             .iter()
             .filter(|entry| {
                 entry.attorney_review_required && 
                 matches!(entry.compliance_status, ComplianceStatus::RequiresAttorneyReview)
             })
+            .cloned()
             .collect()
     }
+}
 
-    /// Clear all audit logs (administrative function)
-    pub async fn clear_all_logs(&mut self, cleared_by: &str) -> Result<usize, String> {
-        let count = self.log_entries.len();
-        self.log_entries.clear();
-        
-        tracing::warn!("All audit logs cleared by user: {}", cleared_by);
-        
-        Ok(count)
+async fn export_chained<W>(
+    exporter: &mut AuditExporter,
+    writer: &mut W,
+    criteria: &AuditSearchCriteria,
+    limit: usize,
+    entries: Vec<ChainedAuditEntry>,
+) -> Result<(), String>
+where
+    W: AsyncWrite + Unpin,
+{
+    for chained in entries.iter().filter(|chained| criteria.matches(&chained.entry)) {
+        if exporter.entry_count() >= limit {
+            break;
+        }
+        let bytes = exporter.entry(Some(chained.sequence), Some(&chained.hash), &chained.entry)?;
+        write_export(writer, bytes).await?;
+    }
+    Ok(())
+}
+
+async fn write_export<W>(writer: &mut W, bytes: Vec<u8>) -> Result<(), String>
+where
+    W: AsyncWrite + Unpin,
+{
+    if bytes.is_empty() {
+        return Ok(());
     }
+    writer
+        .write_all(&bytes)
+        .await
+        .map_err(|e| format!("Export write failed: {}", e))
 }
@@ -0,0 +1,820 @@
+//! Tamper-Evident Audit Store for MoodBridge_Rust
+//!
+//! Audit entries are appended to disk with the hash of the entry before them, so
+//! editing or removing any entry breaks the chain. Signed checkpoints pin the chain
+//! head at intervals, which also exposes entries cut from the end. Archiving moves
+//! the oldest entries into signed, read-only segments; nothing is ever deleted.
+//!
+//! The one exception is a final line left incomplete by a crash mid-append. It was
+//! never acknowledged to the caller, so opening the store cuts it off and records
+//! the repair, which `verify` keeps reporting. Damage anywhere else stops the open.
+
+use crate::legal::AuditLogEntry;
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// `prev_hash` of the first entry in the chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const ACTIVE_FILE: &str = "active.jsonl";
+const CHECKPOINT_FILE: &str = "checkpoints.jsonl";
+const SEGMENT_DIR: &str = "segments";
+const MANIFEST_FILE: &str = "manifest.jsonl";
+const RECOVERY_FILE: &str = "recoveries.jsonl";
+
+/// Environment variables naming the audit directory and the hex-encoded signing key
+pub const AUDIT_DIR_ENV: &str = "AUDIT_LOG_DIR";
+pub const AUDIT_KEY_ENV: &str = "AUDIT_SIGNING_KEY";
+
+/// Audit directory from `AUDIT_LOG_DIR`, defaulting to `data/audit`
+pub fn audit_dir_from_env() -> PathBuf {
+    std::env::var(AUDIT_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/audit"))
+}
+
+/// Signing key from the hex in `AUDIT_SIGNING_KEY`
+pub fn signing_key_from_env() -> Result<Vec<u8>, String> {
+    let hex_key = std::env::var(AUDIT_KEY_ENV)
+        .map_err(|_| format!("{} is not set", AUDIT_KEY_ENV))?;
+    hex::decode(hex_key.trim()).map_err(|e| format!("{} is not valid hex: {}", AUDIT_KEY_ENV, e))
+}
+
+/// An audit entry linked to the one before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainedAuditEntry {
+    /// Position in the chain, starting at 1
+    pub sequence: u64,
+    pub prev_hash: String,
+    pub hash: String,
+    pub entry: AuditLogEntry,
+}
+
+/// Signed record of the chain head at some sequence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    pub sequence: u64,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+/// Signed description of an archived, read-only segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentSeal {
+    pub file_name: String,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub first_prev_hash: String,
+    pub last_hash: String,
+    pub entry_count: usize,
+    pub sealed_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+/// Problem found while verifying the audit trail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, thiserror::Error)]
+pub enum ChainIssue {
+    #[error("entry {sequence} was modified")]
+    Modified { sequence: u64 },
+    #[error("entries {expected} to {found} are missing")]
+    Missing { expected: u64, found: u64 },
+    #[error("entry {sequence} does not link to the entry before it")]
+    BrokenLink { sequence: u64 },
+    #[error("entry {sequence} appears more than once")]
+    Duplicate { sequence: u64 },
+    #[error("checkpoint at {sequence} has an invalid signature")]
+    BadCheckpointSignature { sequence: u64 },
+    #[error("checkpoint at {sequence} does not match the stored entry")]
+    CheckpointMismatch { sequence: u64 },
+    #[error("chain ends at {last_sequence} but was checkpointed at {checkpoint_sequence}")]
+    Truncated { checkpoint_sequence: u64, last_sequence: u64 },
+    #[error("segment {file_name} has an invalid seal")]
+    BadSegmentSeal { file_name: String },
+    #[error("segment {file_name} does not match its seal")]
+    SegmentMismatch { file_name: String },
+    #[error("{file_name} line {line} is unreadable: {reason}")]
+    Unreadable { file_name: String, line: usize, reason: String },
+    #[error("{file_name} line {line} was cut short by a crash; {bytes} bytes were removed")]
+    TornWrite { file_name: String, line: usize, bytes: usize },
+}
+
+/// Outcome of `AuditStore::verify`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub verified_at: DateTime<Utc>,
+    pub entries_checked: usize,
+    pub segments_checked: usize,
+    pub checkpoints_checked: usize,
+    pub last_sequence: u64,
+    pub last_hash: String,
+    pub issues: Vec<ChainIssue>,
+}
+
+impl AuditVerification {
+    pub fn is_intact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Head of the chain, guarded so appends are serialized
+#[derive(Debug)]
+struct ChainHead {
+    last_sequence: u64,
+    last_hash: String,
+    since_checkpoint: u64,
+}
+
+/// Append-only, hash-chained audit store in a directory
+#[derive(Debug)]
+pub struct AuditStore {
+    dir: PathBuf,
+    signing_key: hmac::Key,
+    checkpoint_interval: u64,
+    head: Mutex<ChainHead>,
+}
+
+impl AuditStore {
+    /// Open or create a store in `dir`, signing checkpoints and seals with `signing_key`
+    pub async fn open(dir: impl AsRef<Path>, signing_key: &[u8]) -> Result<Self, String> {
+        if signing_key.len() < 32 {
+            return Err("Audit signing key must be at least 32 bytes".to_string());
+        }
+
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(SEGMENT_DIR))
+            .await
+            .map_err(|e| format!("Failed to create audit directory: {}", e))?;
+
+        let store = Self {
+            dir,
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, signing_key),
+            checkpoint_interval: 1000,
+            head: Mutex::new(ChainHead {
+                last_sequence: 0,
+                last_hash: GENESIS_HASH.to_string(),
+                since_checkpoint: 0,
+            }),
+        };
+        store.recover().await?;
+        Ok(store)
+    }
+
+    /// Write a checkpoint after every `interval` entries
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append an entry to the chain, returning it with its sequence and hashes
+    pub async fn append(&self, entry: &AuditLogEntry) -> Result<ChainedAuditEntry, String> {
+        let mut head = self.head.lock().await;
+
+        let sequence = head.last_sequence + 1;
+        let hash = entry_hash(sequence, &head.last_hash, entry)?;
+        let chained = ChainedAuditEntry {
+            sequence,
+            prev_hash: head.last_hash.clone(),
+            hash,
+            entry: entry.clone(),
+        };
+
+        append_line(&self.dir.join(ACTIVE_FILE), &chained).await?;
+        head.last_sequence = sequence;
+        head.last_hash = chained.hash.clone();
+        head.since_checkpoint += 1;
+
+        if head.since_checkpoint >= self.checkpoint_interval {
+            self.write_checkpoint(&mut head).await?;
+        }
+        Ok(chained)
+    }
+
+    /// Sign the current chain head
+    pub async fn checkpoint(&self) -> Result<Option<AuditCheckpoint>, String> {
+        let mut head = self.head.lock().await;
+        if head.last_sequence == 0 {
+            return Ok(None);
+        }
+        self.write_checkpoint(&mut head).await.map(Some)
+    }
+
+    /// Entries not yet archived, oldest first
+    pub async fn active_entries(&self) -> Result<Vec<ChainedAuditEntry>, String> {
+        let _head = self.head.lock().await;
+        let (entries, problems) = read_lines(&self.dir.join(ACTIVE_FILE)).await?;
+        match problems.into_iter().next() {
+            Some(issue) => Err(issue.to_string()),
+            None => Ok(entries),
+        }
+    }
+
+    /// Seals of archived segments, oldest first
+    pub async fn segments(&self) -> Result<Vec<SegmentSeal>, String> {
+        let (mut seals, problems) =
+            read_lines::<SegmentSeal>(&self.dir.join(SEGMENT_DIR).join(MANIFEST_FILE)).await?;
+        if let Some(issue) = problems.into_iter().next() {
+            return Err(issue.to_string());
+        }
+        seals.sort_by_key(|seal| seal.first_sequence);
+        Ok(seals)
+    }
+
+    /// Entries of an archived segment
+    pub async fn read_segment(&self, seal: &SegmentSeal) -> Result<Vec<ChainedAuditEntry>, String> {
+        let (entries, problems) = read_lines(&self.segment_path(&seal.file_name)?).await?;
+        match problems.into_iter().next() {
+            Some(issue) => Err(issue.to_string()),
+            None => Ok(entries),
+        }
+    }
+
+    /// Move active entries logged at or before `before` into a sealed segment
+    ///
+    /// Only the oldest run of entries moves, so the chain stays in order across
+    /// segments. Returns how many entries were archived.
+    pub async fn archive_before(&self, before: DateTime<Utc>) -> Result<usize, String> {
+        let mut head = self.head.lock().await;
+
+        let active_path = self.dir.join(ACTIVE_FILE);
+        let (entries, problems) = read_lines::<ChainedAuditEntry>(&active_path).await?;
+        if let Some(issue) = problems.into_iter().next() {
+            return Err(format!("Refusing to archive a damaged audit log: {}", issue));
+        }
+
+        let count = entries
+            .iter()
+            .take_while(|chained| chained.entry.timestamp <= before)
+            .count();
+        if count == 0 {
+            return Ok(0);
+        }
+        let (archived, remaining) = entries.split_at(count);
+        let first = &archived[0];
+        let last = &archived[count - 1];
+
+        let file_name = format!("{:020}-{:020}.jsonl", first.sequence, last.sequence);
+        let segment_path = self.segment_path(&file_name)?;
+        write_lines_atomic(&segment_path, archived).await?;
+        make_read_only(&segment_path).await?;
+
+        let mut seal = SegmentSeal {
+            file_name,
+            first_sequence: first.sequence,
+            last_sequence: last.sequence,
+            first_prev_hash: first.prev_hash.clone(),
+            last_hash: last.hash.clone(),
+            entry_count: count,
+            sealed_at: Utc::now(),
+            signature: String::new(),
+        };
+        seal.signature = self.sign(&seal_message(&seal));
+        append_line(&self.dir.join(SEGMENT_DIR).join(MANIFEST_FILE), &seal).await?;
+
+        // A crash before this rewrite leaves sealed entries in the active file too;
+        // `recover` drops them on the next open
+        write_lines_atomic(&active_path, remaining).await?;
+        self.write_checkpoint(&mut head).await?;
+
+        tracing::info!(
+            "Sealed audit entries {} to {} into {}",
+            seal.first_sequence,
+            seal.last_sequence,
+            seal.file_name
+        );
+        Ok(count)
+    }
+
+    /// Check every segment, entry and checkpoint, reporting tampering instead of failing
+    pub async fn verify(&self) -> Result<AuditVerification, String> {
+        let _head = self.head.lock().await;
+
+        let (mut issues, problems) = read_lines::<ChainIssue>(&self.dir.join(RECOVERY_FILE)).await?;
+        issues.extend(problems);
+        let mut walker = ChainWalker::default();
+
+        let (seals, problems) =
+            read_lines::<SegmentSeal>(&self.dir.join(SEGMENT_DIR).join(MANIFEST_FILE)).await?;
+        issues.extend(problems);
+        let mut seals = seals;
+        seals.sort_by_key(|seal| seal.first_sequence);
+
+        for seal in &seals {
+            if !self.verify_signature(&seal_message(seal), &seal.signature) {
+                issues.push(ChainIssue::BadSegmentSeal { file_name: seal.file_name.clone() });
+            }
+            let (entries, problems) = match self.segment_path(&seal.file_name) {
+                Ok(path) => read_lines::<ChainedAuditEntry>(&path).await?,
+                Err(_) => (Vec::new(), Vec::new()),
+            };
+            issues.extend(problems);
+
+            let matches_seal = entries.len() == seal.entry_count
+                && entries.first().is_some_and(|first| {
+                    first.sequence == seal.first_sequence && first.prev_hash == seal.first_prev_hash
+                })
+                && entries.last().is_some_and(|last| {
+                    last.sequence == seal.last_sequence && last.hash == seal.last_hash
+                });
+            if !matches_seal {
+                issues.push(ChainIssue::SegmentMismatch { file_name: seal.file_name.clone() });
+            }
+            for chained in &entries {
+                walker.check(chained, &mut issues)?;
+            }
+        }
+
+        let (entries, problems) = read_lines::<ChainedAuditEntry>(&self.dir.join(ACTIVE_FILE)).await?;
+        issues.extend(problems);
+        for chained in &entries {
+            walker.check(chained, &mut issues)?;
+        }
+
+        let (checkpoints, problems) =
+            read_lines::<AuditCheckpoint>(&self.dir.join(CHECKPOINT_FILE)).await?;
+        issues.extend(problems);
+        for checkpoint in &checkpoints {
+            if !self.verify_signature(&checkpoint_message(checkpoint), &checkpoint.signature) {
+                issues.push(ChainIssue::BadCheckpointSignature { sequence: checkpoint.sequence });
+            } else if checkpoint.sequence > walker.last_sequence {
+                issues.push(ChainIssue::Truncated {
+                    checkpoint_sequence: checkpoint.sequence,
+                    last_sequence: walker.last_sequence,
+                });
+            } else if walker.hashes.get(&checkpoint.sequence) != Some(&checkpoint.hash) {
+                issues.push(ChainIssue::CheckpointMismatch { sequence: checkpoint.sequence });
+            }
+        }
+
+        issues.dedup();
+        Ok(AuditVerification {
+            verified_at: Utc::now(),
+            entries_checked: walker.hashes.len(),
+            segments_checked: seals.len(),
+            checkpoints_checked: checkpoints.len(),
+            last_sequence: walker.last_sequence,
+            last_hash: walker.last_hash,
+            issues,
+        })
+    }
+
+    /// Find the chain head and finish an append or archive interrupted by a crash
+    async fn recover(&self) -> Result<(), String> {
+        let mut head = self.head.lock().await;
+
+        for path in [
+            self.dir.join(SEGMENT_DIR).join(MANIFEST_FILE),
+            self.dir.join(ACTIVE_FILE),
+            self.dir.join(CHECKPOINT_FILE),
+        ] {
+            if let Some(issue) = cut_torn_tail(&path).await? {
+                tracing::warn!("Repaired audit log: {}", issue);
+                append_line(&self.dir.join(RECOVERY_FILE), &issue).await?;
+            }
+        }
+
+        let sealed_through = self.segments().await?.last().map(|seal| {
+            (seal.last_sequence, seal.last_hash.clone())
+        });
+
+        let active_path = self.dir.join(ACTIVE_FILE);
+        let (mut entries, problems) = read_lines::<ChainedAuditEntry>(&active_path).await?;
+        if let Some(issue) = problems.into_iter().next() {
+            return Err(format!("Audit log is damaged: {}", issue));
+        }
+
+        if let Some((sealed_sequence, _)) = &sealed_through {
+            let before = entries.len();
+            entries.retain(|chained| chained.sequence > *sealed_sequence);
+            if entries.len() != before {
+                write_lines_atomic(&active_path, &entries).await?;
+            }
+        }
+
+        let (last_sequence, last_hash) = match (entries.last(), sealed_through) {
+            (Some(last), _) => (last.sequence, last.hash.clone()),
+            (None, Some(sealed)) => sealed,
+            (None, None) => (0, GENESIS_HASH.to_string()),
+        };
+
+        let (checkpoints, _) = read_lines::<AuditCheckpoint>(&self.dir.join(CHECKPOINT_FILE)).await?;
+        let checkpointed = checkpoints.iter().map(|c| c.sequence).max().unwrap_or(0);
+
+        head.last_sequence = last_sequence;
+        head.last_hash = last_hash;
+        head.since_checkpoint = last_sequence.saturating_sub(checkpointed);
+        Ok(())
+    }
+
+    async fn write_checkpoint(&self, head: &mut ChainHead) -> Result<AuditCheckpoint, String> {
+        let mut checkpoint = AuditCheckpoint {
+            sequence: head.last_sequence,
+            hash: head.last_hash.clone(),
+            created_at: Utc::now(),
+            signature: String::new(),
+        };
+        checkpoint.signature = self.sign(&checkpoint_message(&checkpoint));
+        append_line(&self.dir.join(CHECKPOINT_FILE), &checkpoint).await?;
+        head.since_checkpoint = 0;
+        Ok(checkpoint)
+    }
+
+    /// Path of a segment, rejecting names that would leave the segment directory
+    fn segment_path(&self, file_name: &str) -> Result<PathBuf, String> {
+        if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+            return Err(format!("Invalid segment name: {}", file_name));
+        }
+        Ok(self.dir.join(SEGMENT_DIR).join(file_name))
+    }
+
+    fn sign(&self, message: &str) -> String {
+        hex::encode(hmac::sign(&self.signing_key, message.as_bytes()).as_ref())
+    }
+
+    fn verify_signature(&self, message: &str, signature: &str) -> bool {
+        hex::decode(signature)
+            .map(|tag| hmac::verify(&self.signing_key, message.as_bytes(), &tag).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Running state while walking the chain from its first entry
+struct ChainWalker {
+    last_sequence: u64,
+    last_hash: String,
+    hashes: HashMap<u64, String>,
+}
+
+impl Default for ChainWalker {
+    fn default() -> Self {
+        Self {
+            last_sequence: 0,
+            last_hash: GENESIS_HASH.to_string(),
+            hashes: HashMap::new(),
+        }
+    }
+}
+
+impl ChainWalker {
+    fn check(&mut self, chained: &ChainedAuditEntry, issues: &mut Vec<ChainIssue>) -> Result<(), String> {
+        if chained.sequence <= self.last_sequence {
+            issues.push(ChainIssue::Duplicate { sequence: chained.sequence });
+            return Ok(());
+        }
+        if chained.sequence > self.last_sequence + 1 {
+            issues.push(ChainIssue::Missing {
+                expected: self.last_sequence + 1,
+                found: chained.sequence,
+            });
+        } else if chained.prev_hash != self.last_hash {
+            issues.push(ChainIssue::BrokenLink { sequence: chained.sequence });
+        }
+        if entry_hash(chained.sequence, &chained.prev_hash, &chained.entry)? != chained.hash {
+            issues.push(ChainIssue::Modified { sequence: chained.sequence });
+        }
+
+        self.last_sequence = chained.sequence;
+        self.last_hash = chained.hash.clone();
+        self.hashes.insert(chained.sequence, chained.hash.clone());
+        Ok(())
+    }
+}
+
+/// SHA-256 over the sequence, the previous hash and the entry with its keys sorted
+fn entry_hash(sequence: u64, prev_hash: &str, entry: &AuditLogEntry) -> Result<String, String> {
+    // Going through Value sorts object keys, so the hash survives a round trip to disk
+    let canonical = serde_json::to_value(entry)
+        .and_then(|value| serde_json::to_vec(&value))
+        .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(sequence.to_be_bytes());
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(&canonical);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn checkpoint_message(checkpoint: &AuditCheckpoint) -> String {
+    format!(
+        "checkpoint|{}|{}|{}",
+        checkpoint.sequence,
+        checkpoint.hash,
+        checkpoint.created_at.to_rfc3339()
+    )
+}
+
+fn seal_message(seal: &SegmentSeal) -> String {
+    format!(
+        "segment|{}|{}|{}|{}|{}|{}|{}",
+        seal.file_name,
+        seal.first_sequence,
+        seal.last_sequence,
+        seal.first_prev_hash,
+        seal.last_hash,
+        seal.entry_count,
+        seal.sealed_at.to_rfc3339()
+    )
+}
+
+/// Append one JSON line and flush it to disk
+async fn append_line<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let mut line = serde_json::to_vec(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    line.push(b'\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.write_all(&line)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    file.sync_data()
+        .await
+        .map_err(|e| format!("Failed to sync {}: {}", path.display(), e))
+}
+
+/// Remove a final line that `append_line` didn't finish
+///
+/// A complete line only lacking its newline gets the newline instead, so the
+/// next append starts on a line of its own.
+async fn cut_torn_tail(path: &Path) -> Result<Option<ChainIssue>, String> {
+    let contents = match fs::read(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    if contents.last().is_none_or(|byte| *byte == b'\n') {
+        return Ok(None);
+    }
+
+    let line_start = contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |at| at + 1);
+    let tail = &contents[line_start..];
+    let complete = serde_json::from_slice::<serde_json::Value>(tail).is_ok();
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .append(complete)
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    if complete {
+        let mut file = file;
+        file.write_all(b"\n")
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        file.sync_data()
+            .await
+            .map_err(|e| format!("Failed to sync {}: {}", path.display(), e))?;
+        return Ok(None);
+    }
+    file.set_len(line_start as u64)
+        .await
+        .map_err(|e| format!("Failed to truncate {}: {}", path.display(), e))?;
+    file.sync_data()
+        .await
+        .map_err(|e| format!("Failed to sync {}: {}", path.display(), e))?;
+
+    Ok(Some(ChainIssue::TornWrite {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        line: contents[..line_start].iter().filter(|byte| **byte == b'\n').count() + 1,
+        bytes: tail.len(),
+    }))
+}
+
+/// Replace a file's lines through a temporary file and rename
+async fn write_lines_atomic<T: Serialize>(path: &Path, values: &[T]) -> Result<(), String> {
+    let mut contents = Vec::new();
+    for value in values {
+        serde_json::to_writer(&mut contents, value).map_err(|e| format!("Failed to serialize: {}", e))?;
+        contents.push(b'\n');
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", tmp_path.display(), e))?;
+    file.write_all(&contents)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| format!("Failed to sync {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path)
+        .await
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+async fn make_read_only(path: &Path) -> Result<(), String> {
+    let mut permissions = fs::metadata(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)
+        .await
+        .map_err(|e| format!("Failed to protect {}: {}", path.display(), e))
+}
+
+/// Parse a JSON-lines file, collecting unreadable lines as issues; a missing file is empty
+async fn read_lines<T>(path: &Path) -> Result<(Vec<T>, Vec<ChainIssue>), String>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let contents = match fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), Vec::new())),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    let mut values = Vec::new();
+    let mut issues = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(value) => values.push(value),
+            Err(e) => issues.push(ChainIssue::Unreadable {
+                file_name: file_name.clone(),
+                line: index + 1,
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok((values, issues))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legal::{ComplianceStatus, DataClassification, LegalOperationType};
+    use tempfile::TempDir;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn entry(user_id: &str, timestamp: DateTime<Utc>) -> AuditLogEntry {
+        AuditLogEntry {
+            entry_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            operation_type: LegalOperationType::DocumentModification,
+            timestamp,
+            operation_details: HashMap::from([
+                ("exhibit".to_string(), serde_json::json!(12)),
+                ("action".to_string(), serde_json::json!("redact")),
+            ]),
+            compliance_status: ComplianceStatus::Compliant,
+            attorney_review_required: false,
+            data_processed: DataClassification::Confidential,
+        }
+    }
+
+    async fn rewrite_active(dir: &Path, edit: impl FnOnce(&mut Vec<ChainedAuditEntry>)) {
+        let path = dir.join(ACTIVE_FILE);
+        let (mut entries, _) = read_lines::<ChainedAuditEntry>(&path).await.unwrap();
+        edit(&mut entries);
+        write_lines_atomic(&path, &entries).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_chain_detects_modification_and_deletion() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        for n in 0..5 {
+            store.append(&entry(&format!("user{}", n), Utc::now())).await.unwrap();
+        }
+        assert!(store.verify().await.unwrap().is_intact());
+
+        rewrite_active(temp_dir.path(), |entries| entries[1].entry.user_id = "someone_else".to_string()).await;
+        let report = store.verify().await.unwrap();
+        assert_eq!(report.issues, vec![ChainIssue::Modified { sequence: 2 }]);
+
+        rewrite_active(temp_dir.path(), |entries| {
+            entries[1].entry.user_id = "user1".to_string();
+            entries.remove(3);
+        })
+        .await;
+        let report = store.verify().await.unwrap();
+        assert_eq!(report.issues, vec![ChainIssue::Missing { expected: 4, found: 5 }]);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_detects_truncation() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AuditStore::open(temp_dir.path(), KEY)
+            .await
+            .unwrap()
+            .with_checkpoint_interval(3);
+        for n in 0..4 {
+            store.append(&entry(&format!("user{}", n), Utc::now())).await.unwrap();
+        }
+
+        // Dropping the tail leaves a valid chain, but the signed checkpoint remembers it
+        rewrite_active(temp_dir.path(), |entries| entries.truncate(2)).await;
+        let report = store.verify().await.unwrap();
+        assert_eq!(
+            report.issues,
+            vec![ChainIssue::Truncated { checkpoint_sequence: 3, last_sequence: 2 }]
+        );
+
+        // Checkpoints can't be forged without the signing key
+        let forged = AuditStore::open(temp_dir.path(), b"another key that is long enough!!").await.unwrap();
+        let report = forged.verify().await.unwrap();
+        assert_eq!(report.issues, vec![ChainIssue::BadCheckpointSignature { sequence: 3 }]);
+    }
+
+    #[tokio::test]
+    async fn test_archive_seals_segments_and_chain_continues() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        let old = Utc::now() - chrono::Duration::days(400);
+        for n in 0..3 {
+            store.append(&entry(&format!("old{}", n), old)).await.unwrap();
+        }
+        store.append(&entry("recent", Utc::now())).await.unwrap();
+
+        let archived = store.archive_before(Utc::now() - chrono::Duration::days(365)).await.unwrap();
+        assert_eq!(archived, 3);
+        assert_eq!(store.active_entries().await.unwrap().len(), 1);
+
+        let segments = store.segments().await.unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(store.read_segment(&segments[0]).await.unwrap().len(), 3);
+
+        // Reopening picks up the chain after the sealed segment
+        drop(store);
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        let appended = store.append(&entry("after_restart", Utc::now())).await.unwrap();
+        assert_eq!(appended.sequence, 5);
+
+        let report = store.verify().await.unwrap();
+        assert!(report.is_intact(), "{:?}", report.issues);
+        assert_eq!(report.entries_checked, 5);
+        assert_eq!(report.segments_checked, 1);
+    }
+
+    #[tokio::test]
+    async fn test_torn_final_line_is_cut_and_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        for n in 0..3 {
+            store.append(&entry(&format!("user{}", n), Utc::now())).await.unwrap();
+        }
+        drop(store);
+
+        // A crash part way through the fourth append
+        let active = temp_dir.path().join(ACTIVE_FILE);
+        let mut file = fs::OpenOptions::new().append(true).open(&active).await.unwrap();
+        file.write_all(br#"{"sequence":4,"prev_hash":"ab"#).await.unwrap();
+        drop(file);
+
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        assert_eq!(store.active_entries().await.unwrap().len(), 3);
+        assert_eq!(store.append(&entry("after_crash", Utc::now())).await.unwrap().sequence, 4);
+
+        let report = store.verify().await.unwrap();
+        assert_eq!(
+            report.issues,
+            vec![ChainIssue::TornWrite { file_name: ACTIVE_FILE.to_string(), line: 4, bytes: 29 }]
+        );
+        assert_eq!(report.entries_checked, 4);
+    }
+
+    #[tokio::test]
+    async fn test_damage_before_the_last_line_stops_the_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AuditStore::open(temp_dir.path(), KEY).await.unwrap();
+        for n in 0..3 {
+            store.append(&entry(&format!("user{}", n), Utc::now())).await.unwrap();
+        }
+        drop(store);
+
+        let active = temp_dir.path().join(ACTIVE_FILE);
+        let contents = fs::read_to_string(&active).await.unwrap();
+        let mut lines: Vec<&str> = contents.lines().collect();
+        lines[1] = "{\"sequence\":2,";
+        fs::write(&active, lines.join("\n") + "\n").await.unwrap();
+
+        let error = AuditStore::open(temp_dir.path(), KEY).await.unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,242 @@
+//! Legal compliance enforcement for AI and document routes
+//!
+//! `LegalComplianceLayer` runs `LegalComplianceEngine::check_compliance` before
+//! the handler. The request is classified with `route_access`, the data it
+//! touches is classified from the route alone (never from anything the client
+//! sends), the caller comes from the access middleware's `AuthenticatedUser`,
+//! and the engine writes an audit entry for every decision. Blocked requests
+//! get a JSON body listing the consent form and disclaimer that are missing,
+//! so a client can show them and retry. Install with `route_layer` on the
+//! routes it covers, added before the access middleware so that runs first.
+
+use super::access_control::enum_key;
+use super::access_middleware::{route_access, AuthenticatedUser, RouteAccess};
+use super::{
+    ComplianceResult, ComplianceStatus, DataClassification, LegalComplianceEngine, LegalOperationType,
+    AUDIT_LOG_UNAVAILABLE,
+};
+use axum::{
+    extract::MatchedPath,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Tower layer applying `LegalComplianceEngine` checks to every request
+#[derive(Clone)]
+pub struct LegalComplianceLayer {
+    engine: Arc<LegalComplianceEngine>,
+}
+
+impl LegalComplianceLayer {
+    pub fn new(engine: Arc<LegalComplianceEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+impl<S> Layer<S> for LegalComplianceLayer {
+    type Service = LegalCompliance<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LegalCompliance {
+            inner,
+            engine: self.engine.clone(),
+        }
+    }
+}
+
+/// Service produced by `LegalComplianceLayer`
+#[derive(Clone)]
+pub struct LegalCompliance<S> {
+    inner: S,
+    engine: Arc<LegalComplianceEngine>,
+}
+
+impl<S, B> Service<Request<B>> for LegalCompliance<S>
+where
+    S: Service<Request<B>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        // The clone may not be ready; keep the one that was polled
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let engine = self.engine.clone();
+
+        Box::pin(async move {
+            let route = match request.extensions().get::<MatchedPath>() {
+                Some(path) => path.as_str().to_string(),
+                None => request.uri().path().to_string(),
+            };
+            let RouteAccess::Operation(operation_type) = route_access(request.method(), &route) else {
+                tracing::warn!("Refusing {} {}: route has no legal operation mapping", request.method(), route);
+                return Ok(blocked(StatusCode::FORBIDDEN, "Route is not available", json!(null)));
+            };
+            let Some(user) = request.extensions().get::<AuthenticatedUser>().cloned() else {
+                return Ok(blocked(StatusCode::UNAUTHORIZED, "Missing bearer token", json!(null)));
+            };
+
+            let data_classification = route_classification(&route, &operation_type);
+
+            let mut context = HashMap::new();
+            context.insert("method".to_string(), json!(request.method().as_str()));
+            context.insert("route".to_string(), json!(route));
+            context.insert("path".to_string(), json!(request.uri().path()));
+            context.insert("supervised_by".to_string(), json!(user.supervised_by));
+
+            let result = engine
+                .check_compliance(&user.user_id, operation_type.clone(), data_classification, context)
+                .await;
+            if !result.allowed {
+                tracing::warn!("User {} blocked from {:?}: {:?}", user.user_id, operation_type, result.status);
+                return Ok(compliance_refusal(&engine, &user.user_id, &operation_type, &result).await);
+            }
+            if result.attorney_review_required {
+                tracing::info!("{:?} by {} requires attorney review", operation_type, user.user_id);
+            }
+
+            request.extensions_mut().insert(result);
+            inner.call(request).await
+        })
+    }
+}
+
+/// The most sensitive data a route can return or change
+fn route_classification(route: &str, operation_type: &LegalOperationType) -> DataClassification {
+    if route.starts_with("/api/secure/communications") {
+        return DataClassification::AttorneyClientPrivileged;
+    }
+    if route == "/api/audit/export"
+        || route.starts_with("/api/exhibits/")
+        || route.starts_with("/api/retention/")
+        || route.starts_with("/api/search")
+        || route.ends_with("/holds")
+    {
+        return DataClassification::Confidential;
+    }
+    default_classification(operation_type)
+}
+
+/// What an operation's data is assumed to be when its route isn't listed
+fn default_classification(operation_type: &LegalOperationType) -> DataClassification {
+    match operation_type {
+        LegalOperationType::VoiceRecording => DataClassification::PersonalIdentifiableInformation,
+        LegalOperationType::AILegalAdvice | LegalOperationType::ClientDataProcessing => DataClassification::Confidential,
+        LegalOperationType::DocumentModification
+        | LegalOperationType::DocumentIntelligence
+        | LegalOperationType::TimelineAnalysis => DataClassification::WorkProduct,
+        _ => DataClassification::InternalUse,
+    }
+}
+
+/// Response naming what the caller still needs before the operation can run
+async fn compliance_refusal(
+    engine: &LegalComplianceEngine,
+    user_id: &str,
+    operation_type: &LegalOperationType,
+    result: &ComplianceResult,
+) -> Response {
+    let mut missing = Vec::new();
+    if result.status == ComplianceStatus::RequiresConsent {
+        let consent = engine.consent_manager();
+        let status = consent.check_consent(user_id, operation_type).await;
+        match consent.current_form(operation_type).await {
+            Ok(form) => {
+                missing.push(json!({
+                    "kind": "consent",
+                    "form_version": form.version,
+                    "form_url": format!("/api/consent/forms/{}", enum_key(operation_type)),
+                    "consented_form_version": status.form_version,
+                    "requires_renewal": status.requires_renewal
+                }));
+                missing.push(json!({
+                    "kind": "disclaimer",
+                    "disclaimer_hash": form.disclaimer_hash,
+                    "text": form.disclaimer_text
+                }));
+            }
+            Err(e) => tracing::error!("Failed to load consent form for {:?}: {}", operation_type, e),
+        }
+    }
+
+    let (status, error) = match result.status {
+        ComplianceStatus::RequiresConsent => (
+            StatusCode::PRECONDITION_REQUIRED,
+            format!("{:?} requires consent to the current form", operation_type),
+        ),
+        _ if result.additional_requirements.iter().any(|r| r == AUDIT_LOG_UNAVAILABLE) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The operation can't be audited right now".to_string(),
+        ),
+        _ => (StatusCode::FORBIDDEN, format!("Not permitted to perform {:?}", operation_type)),
+    };
+
+    blocked(
+        status,
+        &error,
+        json!({
+            "operation_type": operation_type,
+            "status": result.status,
+            "missing": missing,
+            "additional_requirements": result.additional_requirements
+        }),
+    )
+}
+
+fn blocked(status: StatusCode, error: &str, compliance: Value) -> Response {
+    (status, Json(json!({ "success": false, "error": error, "compliance": compliance }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_classification_treats_recordings_as_personal_data() {
+        assert_eq!(
+            default_classification(&LegalOperationType::VoiceRecording),
+            DataClassification::PersonalIdentifiableInformation
+        );
+        assert_eq!(default_classification(&LegalOperationType::TimelineAnalysis), DataClassification::WorkProduct);
+    }
+
+    #[test]
+    fn test_classification_comes_from_the_route() {
+        assert_eq!(
+            route_classification("/api/secure/communications/:id", &LegalOperationType::ClientDataProcessing),
+            DataClassification::AttorneyClientPrivileged
+        );
+        assert_eq!(
+            route_classification("/api/audit/export", &LegalOperationType::AuditLogExport),
+            DataClassification::Confidential
+        );
+        assert_eq!(
+            route_classification("/api/matters/:case_id/holds", &LegalOperationType::RecordDisposition),
+            DataClassification::Confidential
+        );
+        // Search hits carry snippets of exhibits and communications
+        assert_eq!(
+            route_classification("/api/search", &LegalOperationType::SemanticSearch),
+            DataClassification::Confidential
+        );
+        assert_eq!(
+            route_classification("/api/ai/voice", &LegalOperationType::VoiceRecording),
+            DataClassification::PersonalIdentifiableInformation
+        );
+    }
+}
@@ -0,0 +1,834 @@
+//! Conflict-of-interest screening for MoodBridge_Rust
+//!
+//! Before a matter is opened, every party on the `CreateCaseRequest` is compared
+//! against parties to existing matters (including clients) and the senders of
+//! stored communications. Names are matched fuzzily so that "Jon Smith" and
+//! "John Smith, Jr." or "Acme Corp." and "ACME Corporation" are caught. Each
+//! check produces a report that an attorney must clear, waive or decline.
+//!
+//! Hits on matters the reader is walled off from are withheld from the copy of
+//! the report they see, and only a reader who sees every hit may review it.
+
+use crate::db::DbPool;
+use crate::legal::ethical_walls::MatterScope;
+use crate::models::requests::{CaseParty, CreateCaseRequest, PartyRole};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::OnceCell;
+
+/// Names at least this similar are reported
+pub const MATCH_THRESHOLD: f64 = 0.88;
+
+const CONFLICT_SCHEMA: &str = include_str!("../../data/conflicts.sql");
+
+/// Words that don't distinguish one party from another
+const NAME_NOISE: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "hon", "jr", "sr", "ii", "iii", "esq", "the", "inc", "llc", "llp",
+    "ltd", "corp", "corporation", "co", "company", "pc", "pllc", "pa",
+];
+
+/// How seriously a match should be taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ConflictSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// Where an existing name came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConflictSource {
+    MatterParty {
+        case_id: i64,
+        docket_number: Option<String>,
+        case_title: Option<String>,
+        role: PartyRole,
+    },
+    CommunicationSender {
+        communication_id: i64,
+        communication_date: String,
+        #[serde(default)]
+        case_id: Option<i64>,
+    },
+}
+
+impl ConflictSource {
+    /// The matter the known name was found under, if any
+    pub fn case_id(&self) -> Option<i64> {
+        match self {
+            Self::MatterParty { case_id, .. } => Some(*case_id),
+            Self::CommunicationSender { case_id, .. } => *case_id,
+        }
+    }
+}
+
+/// A new party whose name resembles one the firm already knows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictHit {
+    pub party: CaseParty,
+    pub matched_name: String,
+    pub similarity: f64,
+    pub source: ConflictSource,
+    pub severity: ConflictSeverity,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictReportStatus {
+    /// Nothing matched; kept as a record that the check ran
+    NoConflicts,
+    PendingReview,
+    Cleared,
+    /// Conflict accepted with informed client consent
+    Waived,
+    /// The firm won't take the matter
+    Declined,
+}
+
+/// An attorney's decision on a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictDecision {
+    Cleared,
+    Waived,
+    Declined,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictReport {
+    pub report_id: String,
+    pub case_title: String,
+    pub requested_by: String,
+    pub created_at: DateTime<Utc>,
+    pub parties_checked: Vec<CaseParty>,
+    pub hits: Vec<ConflictHit>,
+    pub status: ConflictReportStatus,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_notes: Option<String>,
+    /// Hits left out of this copy because the reader is screened from their matters
+    #[serde(default)]
+    pub withheld_hits: usize,
+}
+
+impl ConflictReport {
+    /// The report as a reader with `scope` may see it
+    pub fn visible_to(&self, scope: &MatterScope) -> ConflictReport {
+        let mut report = self.clone();
+        report.hits.retain(|hit| scope.allows(hit.source.case_id()));
+        report.withheld_hits += self.hits.len() - report.hits.len();
+        report
+    }
+
+    pub fn highest_severity(&self) -> Option<ConflictSeverity> {
+        self.hits.iter().map(|hit| hit.severity).max()
+    }
+
+    /// Plain-text report for the reviewing attorney
+    pub fn to_text(&self) -> String {
+        let mut report = String::from("=== CONFLICT OF INTEREST REPORT ===\n\n");
+        report.push_str(&format!("Report ID: {}\n", self.report_id));
+        report.push_str(&format!("Proposed Matter: {}\n", self.case_title));
+        report.push_str(&format!("Requested By: {}\n", self.requested_by));
+        report.push_str(&format!("Generated: {}\n", self.created_at.format("%Y-%m-%d %H:%M:%S UTC")));
+        report.push_str(&format!("Status: {:?}\n\n", self.status));
+
+        report.push_str("PARTIES CHECKED:\n");
+        for party in &self.parties_checked {
+            report.push_str(&format!("  {} ({:?})\n", party.name, party.role));
+        }
+
+        report.push_str(&format!("\nPOTENTIAL CONFLICTS: {}\n", self.hits.len()));
+        for hit in &self.hits {
+            let source = match &hit.source {
+                ConflictSource::MatterParty { case_id, docket_number, case_title, role } => format!(
+                    "{:?} in case {} {}{}",
+                    role,
+                    case_id,
+                    docket_number.as_deref().unwrap_or(""),
+                    case_title.as_deref().map(|t| format!(" ({})", t)).unwrap_or_default()
+                ),
+                ConflictSource::CommunicationSender { communication_id, communication_date, .. } => {
+                    format!("sender of communication {} on {}", communication_id, communication_date)
+                }
+            };
+            report.push_str(&format!(
+                "  [{:?}] {} ({:?}) ~ \"{}\" {:.0}% - {}\n      {}\n",
+                hit.severity,
+                hit.party.name,
+                hit.party.role,
+                hit.matched_name,
+                hit.similarity * 100.0,
+                source,
+                hit.reason
+            ));
+        }
+        if self.withheld_hits > 0 {
+            report.push_str(&format!(
+                "  {} further potential conflicts on screened matters withheld\n",
+                self.withheld_hits
+            ));
+        }
+
+        if let Some(reviewer) = &self.reviewed_by {
+            report.push_str(&format!("\nREVIEWED BY: {}\n", reviewer));
+            if let Some(notes) = &self.review_notes {
+                report.push_str(&format!("Notes: {}\n", notes));
+            }
+        }
+        report
+    }
+}
+
+/// A name the firm already knows, with where it came from
+struct KnownName {
+    name: String,
+    normalized: String,
+    source: ConflictSource,
+}
+
+/// Runs conflict checks and keeps the parties and reports they rely on
+#[derive(Debug, Clone)]
+pub struct ConflictChecker {
+    pool: DbPool,
+    schema: Arc<OnceCell<()>>,
+    threshold: f64,
+}
+
+impl ConflictChecker {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            schema: Arc::new(OnceCell::new()),
+            threshold: MATCH_THRESHOLD,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    async fn ready(&self) -> Result<(), String> {
+        self.schema
+            .get_or_try_init(|| async {
+                sqlx::query(CONFLICT_SCHEMA)
+                    .execute(&self.pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("Failed to create conflict check tables: {}", e))
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Open a matter and record its parties in one transaction
+    pub async fn open_matter(
+        &self,
+        request: &CreateCaseRequest,
+        docket_number: &str,
+        court: &str,
+    ) -> Result<i64, String> {
+        self.ready().await?;
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        let case_id = sqlx::query(
+            "INSERT INTO case_info (docket_number, case_title, court, status) VALUES (?, ?, ?, 'Active')",
+        )
+        .bind(docket_number.trim())
+        .bind(&request.title)
+        .bind(court.trim())
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?
+        .last_insert_rowid();
+        for party in request.all_parties() {
+            insert_party(&mut tx, case_id, &party).await?;
+        }
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::info!("Opened case {} ({}) with its parties", case_id, request.title);
+        Ok(case_id)
+    }
+
+    /// Record the parties of an existing matter so later checks see them
+    pub async fn record_parties(&self, case_id: i64, request: &CreateCaseRequest) -> Result<(), String> {
+        self.ready().await?;
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        for party in request.all_parties() {
+            insert_party(&mut tx, case_id, &party).await?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    pub async fn add_party(&self, case_id: i64, party: &CaseParty) -> Result<(), String> {
+        self.ready().await?;
+        let mut conn = self.pool.acquire().await.map_err(db_error)?;
+        insert_party(&mut conn, case_id, party).await
+    }
+
+    /// Give matters opened before party tracking the parties named in their
+    /// "A v. B" caption, as related parties since the firm's side isn't recorded
+    ///
+    /// Returns the number of matters filled in.
+    pub async fn backfill_parties(&self) -> Result<usize, String> {
+        self.ready().await?;
+        let captions: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, case_title FROM case_info
+             WHERE NOT EXISTS (SELECT 1 FROM matter_parties p WHERE p.case_id = case_info.id)",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        let mut filled = 0;
+        for (case_id, title) in captions {
+            let names = caption_parties(&title);
+            if names.is_empty() {
+                continue;
+            }
+            for name in names {
+                insert_party(&mut tx, case_id, &CaseParty { name, role: PartyRole::RelatedParty }).await?;
+            }
+            filled += 1;
+        }
+        tx.commit().await.map_err(db_error)?;
+
+        if filled > 0 {
+            tracing::info!("Recorded caption parties for {} existing matters", filled);
+        }
+        Ok(filled)
+    }
+
+    pub async fn parties(&self, case_id: i64) -> Result<Vec<CaseParty>, String> {
+        self.ready().await?;
+        let rows = sqlx::query("SELECT name, role FROM matter_parties WHERE case_id = ? ORDER BY id")
+            .bind(case_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+        rows.iter()
+            .map(|row| {
+                Ok(CaseParty {
+                    name: row.get("name"),
+                    role: parse_role(&row.get::<String, _>("role"))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Screen a proposed matter and store the report for review
+    pub async fn check(&self, request: &CreateCaseRequest, requested_by: &str) -> Result<ConflictReport, String> {
+        let parties = request.all_parties();
+        let known = self.known_names().await?;
+
+        let mut hits = Vec::new();
+        for party in &parties {
+            let normalized = normalize_name(&party.name);
+            if normalized.is_empty() {
+                continue;
+            }
+            for candidate in &known {
+                let similarity = name_similarity(&normalized, &candidate.normalized);
+                if similarity < self.threshold {
+                    continue;
+                }
+                let (severity, reason) = assess(party.role, &candidate.source);
+                hits.push(ConflictHit {
+                    party: party.clone(),
+                    matched_name: candidate.name.clone(),
+                    similarity,
+                    source: candidate.source.clone(),
+                    severity,
+                    reason: reason.to_string(),
+                });
+            }
+        }
+        hits.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(b.similarity.total_cmp(&a.similarity))
+        });
+
+        let report = ConflictReport {
+            report_id: uuid::Uuid::new_v4().to_string(),
+            case_title: request.title.clone(),
+            requested_by: requested_by.to_string(),
+            created_at: Utc::now(),
+            parties_checked: parties,
+            status: if hits.is_empty() {
+                ConflictReportStatus::NoConflicts
+            } else {
+                ConflictReportStatus::PendingReview
+            },
+            hits,
+            reviewed_by: None,
+            reviewed_at: None,
+            review_notes: None,
+            withheld_hits: 0,
+        };
+
+        sqlx::query(
+            "INSERT INTO conflict_reports (report_id, case_title, requested_by, status, report, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&report.report_id)
+        .bind(&report.case_title)
+        .bind(&report.requested_by)
+        .bind(format!("{:?}", report.status))
+        .bind(serde_json::to_string(&report).map_err(|e| e.to_string())?)
+        .bind(report.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if !report.hits.is_empty() {
+            tracing::warn!(
+                "Conflict check for '{}' found {} potential conflicts (highest {:?})",
+                report.case_title,
+                report.hits.len(),
+                report.highest_severity()
+            );
+        }
+        Ok(report)
+    }
+
+    pub async fn get_report(&self, report_id: &str) -> Result<Option<ConflictReport>, String> {
+        self.ready().await?;
+        let report: Option<String> = sqlx::query_scalar("SELECT report FROM conflict_reports WHERE report_id = ?")
+            .bind(report_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        report
+            .map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// Reports still waiting for an attorney, oldest first
+    pub async fn pending_reports(&self) -> Result<Vec<ConflictReport>, String> {
+        self.ready().await?;
+        let reports: Vec<String> = sqlx::query_scalar(
+            "SELECT report FROM conflict_reports WHERE status = 'PendingReview' ORDER BY created_at",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        reports
+            .iter()
+            .map(|json| serde_json::from_str(json).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    /// Record an attorney's decision; waivers must say how consent was obtained
+    pub async fn review(
+        &self,
+        report_id: &str,
+        reviewed_by: &str,
+        decision: ConflictDecision,
+        notes: Option<String>,
+    ) -> Result<ConflictReport, String> {
+        let mut report = self
+            .get_report(report_id)
+            .await?
+            .ok_or_else(|| "Conflict report not found".to_string())?;
+        if report.status != ConflictReportStatus::PendingReview {
+            return Err(format!("Conflict report is {:?}, not pending review", report.status));
+        }
+        if decision == ConflictDecision::Waived && notes.as_deref().is_none_or(|n| n.trim().is_empty()) {
+            return Err("A waiver must record the client's informed consent".to_string());
+        }
+
+        report.status = match decision {
+            ConflictDecision::Cleared => ConflictReportStatus::Cleared,
+            ConflictDecision::Waived => ConflictReportStatus::Waived,
+            ConflictDecision::Declined => ConflictReportStatus::Declined,
+        };
+        report.reviewed_by = Some(reviewed_by.to_string());
+        report.reviewed_at = Some(Utc::now());
+        report.review_notes = notes;
+
+        sqlx::query("UPDATE conflict_reports SET status = ?, report = ?, reviewed_at = ? WHERE report_id = ?")
+            .bind(format!("{:?}", report.status))
+            .bind(serde_json::to_string(&report).map_err(|e| e.to_string())?)
+            .bind(report.reviewed_at)
+            .bind(report_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        tracing::info!("Conflict report {} marked {:?} by {}", report_id, report.status, reviewed_by);
+        Ok(report)
+    }
+
+    /// Parties to existing matters and communication senders
+    async fn known_names(&self) -> Result<Vec<KnownName>, String> {
+        self.ready().await?;
+        let mut known = Vec::new();
+
+        let parties = sqlx::query(
+            "SELECT p.case_id, p.name, p.role, c.docket_number, c.case_title
+             FROM matter_parties p LEFT JOIN case_info c ON c.id = p.case_id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        for row in parties {
+            let name: String = row.get("name");
+            known.push(KnownName {
+                normalized: normalize_name(&name),
+                source: ConflictSource::MatterParty {
+                    case_id: row.get("case_id"),
+                    docket_number: row.get("docket_number"),
+                    case_title: row.get("case_title"),
+                    role: parse_role(&row.get::<String, _>("role"))?,
+                },
+                name,
+            });
+        }
+
+        // One entry per distinct sender and matter is enough to flag prior contact
+        let senders = sqlx::query(
+            "SELECT MIN(id) AS id, MIN(communication_date) AS communication_date, sender, case_id
+             FROM communications WHERE sender IS NOT NULL AND TRIM(sender) != ''
+             GROUP BY sender, case_id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        for row in senders {
+            let name: String = row.get("sender");
+            known.push(KnownName {
+                normalized: normalize_name(&name),
+                source: ConflictSource::CommunicationSender {
+                    communication_id: row.get("id"),
+                    communication_date: row.get("communication_date"),
+                    case_id: row.get("case_id"),
+                },
+                name,
+            });
+        }
+
+        known.retain(|k| !k.normalized.is_empty());
+        Ok(known)
+    }
+}
+
+/// How a match between a new party and a known name bears on the firm's duties
+fn assess(new_role: PartyRole, source: &ConflictSource) -> (ConflictSeverity, &'static str) {
+    use PartyRole::*;
+
+    let existing_role = match source {
+        ConflictSource::MatterParty { role, .. } => *role,
+        ConflictSource::CommunicationSender { .. } => {
+            return (ConflictSeverity::Medium, "Prior communications with this party are on file");
+        }
+    };
+
+    match (new_role, existing_role) {
+        (Client, AdverseParty) => (ConflictSeverity::High, "Proposed client is adverse to the firm in another matter"),
+        (AdverseParty, Client) => (ConflictSeverity::High, "Proposed matter is adverse to a current or former client"),
+        (OpposingCounsel, Client) | (Client, OpposingCounsel) => {
+            (ConflictSeverity::Medium, "Party has acted both as counsel against the firm and as its client")
+        }
+        (_, RelatedParty) | (RelatedParty, _) => (ConflictSeverity::Medium, "Related party in another matter"),
+        (Client, Client) => (ConflictSeverity::Low, "Existing client of the firm"),
+        (AdverseParty, AdverseParty) => (ConflictSeverity::Low, "Also adverse in another matter"),
+        _ => (ConflictSeverity::Low, "Appears in another matter"),
+    }
+}
+
+/// Lowercase words of a name without punctuation, titles or entity suffixes
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['\'', '\u{2019}'], "")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !NAME_NOISE.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Similarity in [0, 1] between two normalized names
+///
+/// Each word of the shorter name is paired with its closest word in the longer
+/// one (Jaro-Winkler), so word order and middle names don't matter. A lone
+/// surname matching a full name is discounted.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a_words: Vec<&str> = a.split_whitespace().collect();
+    let b_words: Vec<&str> = b.split_whitespace().collect();
+    let (shorter, longer) = if a_words.len() <= b_words.len() {
+        (a_words, b_words)
+    } else {
+        (b_words, a_words)
+    };
+    if shorter.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = shorter
+        .iter()
+        .map(|word| {
+            longer
+                .iter()
+                .map(|other| jaro_winkler(word, other))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    let mut score = total / shorter.len() as f64;
+    if shorter.len() == 1 && longer.len() > 1 {
+        score *= 0.85;
+    }
+    score
+}
+
+/// Jaro-Winkler similarity with the standard 0.1 prefix scale
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0usize;
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let a_seq = a.iter().zip(&a_matched).filter(|(_, m)| **m).map(|(c, _)| c);
+    let b_seq = b.iter().zip(&b_matched).filter(|(_, m)| **m).map(|(c, _)| c);
+    let transpositions = a_seq.zip(b_seq).filter(|(x, y)| x != y).count() / 2;
+
+    let m = matches as f64;
+    let jaro = (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0;
+    let prefix = a.iter().zip(&b).take(4).take_while(|(x, y)| x == y).count();
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+async fn insert_party(
+    conn: &mut sqlx::SqliteConnection,
+    case_id: i64,
+    party: &CaseParty,
+) -> Result<(), String> {
+    sqlx::query("INSERT INTO matter_parties (case_id, name, role, created_at) VALUES (?, ?, ?, ?)")
+        .bind(case_id)
+        .bind(party.name.trim())
+        .bind(format!("{:?}", party.role))
+        .bind(Utc::now())
+        .execute(conn)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// The names either side of the "v." in a case caption
+fn caption_parties(title: &str) -> Vec<String> {
+    let lowered = title.to_ascii_lowercase();
+    let split = [" v. ", " vs. ", " vs ", " v "]
+        .iter()
+        .find_map(|sep| lowered.find(sep).map(|at| (at, sep.len())));
+    let Some((at, len)) = split else {
+        return Vec::new();
+    };
+    [&title[..at], &title[at + len..]]
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !normalize_name(name).is_empty())
+        .collect()
+}
+
+fn parse_role(role: &str) -> Result<PartyRole, String> {
+    serde_json::from_value(serde_json::Value::String(role.to_string()))
+        .map_err(|_| format!("Unknown party role '{}'", role))
+}
+
+fn db_error(e: sqlx::Error) -> String {
+    format!("Conflict check database error: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::requests::CasePriority;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn pool() -> DbPool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE case_info (id INTEGER PRIMARY KEY, docket_number TEXT, case_title TEXT, court TEXT, status TEXT);
+             CREATE TABLE communications (id INTEGER PRIMARY KEY, communication_date TEXT NOT NULL, sender TEXT, case_id INTEGER);
+             INSERT INTO case_info (id, docket_number, case_title, court) VALUES (1, '23-3-01234-5', 'Smith v. Acme', 'King County');
+             INSERT INTO communications (communication_date, sender) VALUES ('2024-02-01', 'Rebecca Turner'), ('2024-02-03', 'Rebecca Turner');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    fn request(client: &str, parties: &[(&str, PartyRole)]) -> CreateCaseRequest {
+        CreateCaseRequest {
+            title: "New matter".to_string(),
+            description: None,
+            case_type: "family_law".to_string(),
+            priority: CasePriority::Medium,
+            client_name: Some(client.to_string()),
+            client_email: None,
+            due_date: None,
+            tags: None,
+            metadata: None,
+            parties: Some(
+                parties
+                    .iter()
+                    .map(|(name, role)| CaseParty { name: name.to_string(), role: *role })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_name_matching_tolerates_spelling_order_and_suffixes() {
+        let sim = |a: &str, b: &str| name_similarity(&normalize_name(a), &normalize_name(b));
+        assert_eq!(sim("Acme Corp.", "ACME Corporation"), 1.0);
+        assert_eq!(sim("Smith, John", "John Smith Jr."), 1.0);
+        assert!(sim("Jon Smith", "John Smith") >= MATCH_THRESHOLD);
+        assert!(sim("Katherine O'Neil", "Catherine ONeil") >= MATCH_THRESHOLD);
+        assert!(sim("Jane Smith", "John Smith") < MATCH_THRESHOLD);
+        assert!(sim("Smith", "John Smith") < MATCH_THRESHOLD);
+        assert!(sim("Acme Corp", "Apex Corp") < MATCH_THRESHOLD);
+        assert!((jaro_winkler("martha", "marhta") - 0.961).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_representing_the_adverse_party_is_flagged_high() {
+        let checker = ConflictChecker::new(pool().await);
+        checker
+            .record_parties(1, &request("John Smith", &[("Acme Corporation", PartyRole::AdverseParty)]))
+            .await
+            .unwrap();
+
+        let report = checker
+            .check(&request("ACME Corp.", &[("Jon Smith", PartyRole::AdverseParty)]), "atty")
+            .await
+            .unwrap();
+        assert_eq!(report.status, ConflictReportStatus::PendingReview);
+        assert_eq!(report.hits.len(), 2);
+        assert!(report.hits.iter().all(|hit| hit.severity == ConflictSeverity::High));
+        match &report.hits[0].source {
+            ConflictSource::MatterParty { docket_number, .. } => {
+                assert_eq!(docket_number.as_deref(), Some("23-3-01234-5"))
+            }
+            other => panic!("unexpected source {:?}", other),
+        }
+        assert!(report.to_text().contains("Smith v. Acme"));
+        assert_eq!(checker.pending_reports().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sender_matches_and_review_workflow() {
+        let checker = ConflictChecker::new(pool().await);
+
+        let clean = checker.check(&request("Maria Lopez", &[]), "atty").await.unwrap();
+        assert_eq!(clean.status, ConflictReportStatus::NoConflicts);
+
+        let report = checker
+            .check(&request("Maria Lopez", &[("Rebeca Turner", PartyRole::Witness)]), "atty")
+            .await
+            .unwrap();
+        assert_eq!(report.hits.len(), 1);
+        assert_eq!(report.hits[0].severity, ConflictSeverity::Medium);
+        assert!(matches!(report.hits[0].source, ConflictSource::CommunicationSender { communication_id: 1, .. }));
+
+        assert!(checker.review(&report.report_id, "atty", ConflictDecision::Waived, None).await.is_err());
+        let reviewed = checker
+            .review(&report.report_id, "atty", ConflictDecision::Waived, Some("Written consent 2024-03-01".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(reviewed.status, ConflictReportStatus::Waived);
+        assert_eq!(
+            checker.get_report(&report.report_id).await.unwrap().unwrap().reviewed_by.as_deref(),
+            Some("atty")
+        );
+        assert!(checker.review(&report.report_id, "atty", ConflictDecision::Cleared, None).await.is_err());
+        assert!(checker.pending_reports().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_opened_and_existing_matters_are_screened() {
+        let checker = ConflictChecker::new(pool().await);
+        assert_eq!(checker.backfill_parties().await.unwrap(), 1);
+        assert_eq!(checker.backfill_parties().await.unwrap(), 0);
+        let names: Vec<String> = checker.parties(1).await.unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["Smith", "Acme"]);
+
+        let case_id = checker
+            .open_matter(&request("Dana Whitfield", &[("Northgate Holdings", PartyRole::AdverseParty)]), "24-2-00077-1", "King County")
+            .await
+            .unwrap();
+        assert_eq!(checker.parties(case_id).await.unwrap().len(), 2);
+
+        let report = checker
+            .check(&request("Northgate Holdings LLC", &[]), "atty")
+            .await
+            .unwrap();
+        assert_eq!(report.hits.len(), 1);
+        assert_eq!(report.hits[0].severity, ConflictSeverity::High);
+    }
+
+    #[tokio::test]
+    async fn test_hits_on_screened_matters_are_withheld() {
+        let checker = ConflictChecker::new(pool().await);
+        checker
+            .record_parties(1, &request("John Smith", &[("Acme Corporation", PartyRole::AdverseParty)]))
+            .await
+            .unwrap();
+        let report = checker
+            .check(&request("Maria Lopez", &[("Acme Corp", PartyRole::AdverseParty), ("Rebecca Turner", PartyRole::Witness)]), "atty")
+            .await
+            .unwrap();
+        assert_eq!(report.hits.len(), 2);
+
+        let matters = crate::legal::access_control::AccessController::open(checker.pool.clone())
+            .await
+            .unwrap()
+            .matters();
+        // Matter 1 is limited to the partner
+        sqlx::query(
+            "INSERT INTO matter_restrictions (case_id, restricted_by, restricted_at) VALUES (1, 'partner', '2024-01-01');
+             INSERT INTO matter_access (case_id, user_id, granted_by, granted_at) VALUES (1, 'partner', 'partner', '2024-01-01');",
+        )
+        .execute(&checker.pool)
+        .await
+        .unwrap();
+        let screened = report.visible_to(&matters.scope("lateral").await.unwrap());
+        assert_eq!(screened.withheld_hits, 1);
+        assert!(matches!(screened.hits[0].source, ConflictSource::CommunicationSender { case_id: None, .. }));
+        assert!(screened.to_text().contains("1 further potential conflicts"));
+        assert_eq!(report.visible_to(&matters.scope("partner").await.unwrap()).withheld_hits, 0);
+    }
+}
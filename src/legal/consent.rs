@@ -1,12 +1,44 @@
 /// Consent Management Module for MoodBridge_Rust
-/// 
+///
 /// This module handles user consent for legally sensitive operations,
 /// ensuring proper documentation and compliance with legal requirements.
-
+///
+/// Consents live in an append-only ledger: every grant, refusal and withdrawal
+/// is a new row naming the form version and disclaimer hash the user saw, with
+/// the IP address and user agent it came from. Forms are versioned by content,
+/// so a release that changes a disclaimer or consent text publishes a new
+/// version and earlier consents stop counting until the user agrees again.
+
+use crate::db::DbPool;
+use crate::legal::access_control::enum_key;
+use crate::legal::audit_export::{csv_record, xml_escape};
+use crate::legal::audit_log::ExportFormat;
+use crate::legal::disclaimers;
 use crate::legal::{ConsentRecord, LegalOperationType};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::Row;
+use std::net::IpAddr;
+use std::sync::Arc;
 use chrono::{DateTime, Utc, Duration};
+use tokio::sync::OnceCell;
+
+const CONSENT_SCHEMA: &str = include_str!("../../data/consent.sql");
+
+/// Comma-separated addresses of the reverse proxies in front of the server
+pub const TRUSTED_PROXIES_ENV: &str = "TRUSTED_PROXIES";
+
+const CONSENT_FORM_TEMPLATE: &str = include_str!("../../templates/consent_form.html");
+
+/// Statements the user ticks before consenting; part of every form version
+const ACKNOWLEDGEMENTS: &[&str] = &[
+    "I have read and understand the legal disclaimer above",
+    "I understand the legal risks and limitations of this technology",
+    "I acknowledge this system does not provide legal advice",
+    "I agree to consult with a qualified attorney for legal matters",
+    "I accept full responsibility for the consequences of using this system",
+];
 
 /// Consent status for a specific operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,105 +48,510 @@ pub struct ConsentStatus {
     pub expires_at: Option<DateTime<Utc>>,
     pub attorney_supervised: bool,
     pub requires_renewal: bool,
+    /// Form version the latest ledger entry refers to
+    pub form_version: Option<u32>,
+}
+
+/// One published version of the consent form for an operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentForm {
+    pub operation_type: LegalOperationType,
+    pub version: u32,
+    pub consent_text: String,
+    pub disclaimer_text: String,
+    pub acknowledgements: Vec<String>,
+    /// SHA-256 of the disclaimer text
+    pub disclaimer_hash: String,
+    /// SHA-256 over everything the user is shown
+    pub form_hash: String,
+    pub published_at: DateTime<Utc>,
+}
+
+impl ConsentForm {
+    /// The form as it ships in this release, not yet versioned
+    fn current(operation_type: &LegalOperationType) -> Self {
+        let consent_text = default_consent_text(operation_type).to_string();
+        let disclaimer_text = disclaimers::for_operation(operation_type).to_string();
+        let acknowledgements: Vec<String> = ACKNOWLEDGEMENTS.iter().map(|a| a.to_string()).collect();
+        let content = serde_json::to_vec(&(enum_key(operation_type), &consent_text, &disclaimer_text, &acknowledgements))
+            .expect("form content serializes");
+
+        Self {
+            operation_type: operation_type.clone(),
+            version: 0,
+            disclaimer_hash: sha256_hex(disclaimer_text.as_bytes()),
+            form_hash: sha256_hex(&content),
+            consent_text,
+            disclaimer_text,
+            acknowledgements,
+            published_at: Utc::now(),
+        }
+    }
+
+    /// Render the form; the page posts its version back with the consent
+    pub fn to_html(&self) -> String {
+        let operation_type = enum_key(&self.operation_type);
+        let supervision_notice = if ConsentManager::requires_attorney_supervision(&self.operation_type) {
+            r#"<div class="attorney-supervision">
+            <h4>⚖️ ATTORNEY SUPERVISION REQUIRED</h4>
+            <p>This operation requires attorney supervision. Ensure that a qualified attorney is overseeing this process and will review all outputs before use.</p>
+        </div>"#
+        } else {
+            ""
+        };
+        let acknowledgements: String = self
+            .acknowledgements
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                format!(
+                    r#"            <div class="checkbox-group">
+                <label>
+                    <input type="checkbox" id="ack-{}" required>
+                    {}
+                </label>
+            </div>
+"#,
+                    i,
+                    xml_escape(text)
+                )
+            })
+            .collect();
+
+        CONSENT_FORM_TEMPLATE
+            .replace("{{operation_name}}", operation_name(&self.operation_type))
+            .replace("{{operation_type}}", &operation_type)
+            .replace("{{form_version}}", &self.version.to_string())
+            .replace("{{form_hash}}", &self.form_hash)
+            .replace("{{supervision_notice}}", supervision_notice)
+            .replace("{{acknowledgements}}", &acknowledgements)
+            .replace("{{consent_text}}", &xml_escape(&self.consent_text))
+            .replace("{{disclaimer_text}}", &xml_escape(&self.disclaimer_text))
+    }
+}
+
+/// Proxies listed in `TRUSTED_PROXIES`; none when it is unset
+pub fn trusted_proxies_from_env() -> Result<Vec<IpAddr>, String> {
+    let Ok(list) = std::env::var(TRUSTED_PROXIES_ENV) else {
+        return Ok(Vec::new());
+    };
+    list.split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            address
+                .parse()
+                .map_err(|e| format!("{} entry {:?} is not an IP address: {}", TRUSTED_PROXIES_ENV, address, e))
+        })
+        .collect()
+}
+
+/// What a ledger entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentAction {
+    Granted,
+    Declined,
+    Withdrawn,
+}
+
+/// Where a consent or withdrawal was submitted from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsentSignature {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// One row of the consent ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentLedgerEntry {
+    pub id: i64,
+    pub user_id: String,
+    pub operation_type: LegalOperationType,
+    pub action: ConsentAction,
+    pub form_version: u32,
+    pub form_hash: String,
+    pub disclaimer_hash: String,
+    pub attorney_supervised: bool,
+    pub disclaimer_acknowledged: bool,
+    pub signature: ConsentSignature,
+    /// Reason given for a withdrawal
+    pub reason: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Current consent for one operation in a user's report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentReportLine {
+    pub operation_type: LegalOperationType,
+    pub current_form_version: u32,
+    pub status: ConsentStatus,
+}
+
+/// Everything a user has agreed to, refused and withdrawn, with the forms involved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentReport {
+    pub user_id: String,
+    pub generated_at: DateTime<Utc>,
+    pub consents: Vec<ConsentReportLine>,
+    pub history: Vec<ConsentLedgerEntry>,
+    pub forms: Vec<ConsentForm>,
 }
 
 /// Consent manager for tracking and validating user consent
 #[derive(Debug, Clone)]
 pub struct ConsentManager {
-    consent_records: HashMap<String, HashMap<String, ConsentRecord>>,
+    pool: DbPool,
+    schema: Arc<OnceCell<()>>,
     consent_expiry_hours: u64,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+}
+
+impl Default for ConsentManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConsentManager {
+    /// Ledger in a private in-memory database, for tests and tools
     pub fn new() -> Self {
+        // A single connection that never closes keeps the in-memory database alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_lazy("sqlite::memory:")
+            .expect("in-memory SQLite URL is valid");
+        Self::with_pool(pool)
+    }
+
+    /// Ledger persisting to `pool`, creating its tables if needed
+    pub async fn open(pool: DbPool) -> Result<Self, String> {
+        let manager = Self::with_pool(pool);
+        manager.ready().await?;
+        Ok(manager)
+    }
+
+    fn with_pool(pool: DbPool) -> Self {
         Self {
-            consent_records: HashMap::new(),
+            pool,
+            schema: Arc::new(OnceCell::new()),
             consent_expiry_hours: 24, // Consent expires after 24 hours by default
+            trusted_proxies: Arc::new(Vec::new()),
         }
     }
 
-    /// Record user consent for a specific operation
-    pub async fn record_consent(&mut self, consent_record: ConsentRecord) -> Result<(), String> {
-        let user_id = consent_record.user_id.clone();
-        let operation_key = format!("{:?}", consent_record.operation_type);
+    /// Reverse proxies whose forwarded-for headers are believed when signing
+    pub fn with_trusted_proxies(mut self, proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = Arc::new(proxies);
+        self
+    }
+
+    pub fn trusted_proxies(&self) -> &[IpAddr] {
+        &self.trusted_proxies
+    }
+
+    async fn ready(&self) -> Result<(), String> {
+        self.schema
+            .get_or_try_init(|| async {
+                sqlx::query(CONSENT_SCHEMA)
+                    .execute(&self.pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("Failed to create consent tables: {}", e))
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// The form users must agree to now, publishing a new version if this
+    /// release changed its text
+    pub async fn current_form(&self, operation_type: &LegalOperationType) -> Result<ConsentForm, String> {
+        self.ready().await?;
+        let mut form = ConsentForm::current(operation_type);
+        let key = enum_key(operation_type);
+
+        let latest = sqlx::query(
+            "SELECT * FROM consent_forms WHERE operation_type = ? ORDER BY version DESC LIMIT 1",
+        )
+        .bind(&key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        .map(|row| form_from_row(&row))
+        .transpose()?;
+
+        match latest {
+            Some(latest) if latest.form_hash == form.form_hash => Ok(latest),
+            latest => {
+                form.version = latest.map_or(1, |latest| latest.version + 1);
+                sqlx::query(
+                    "INSERT OR IGNORE INTO consent_forms (operation_type, version, consent_text, disclaimer_text,
+                        acknowledgements, disclaimer_hash, form_hash, published_at)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&key)
+                .bind(form.version as i64)
+                .bind(&form.consent_text)
+                .bind(&form.disclaimer_text)
+                .bind(serde_json::to_string(&form.acknowledgements).map_err(|e| e.to_string())?)
+                .bind(&form.disclaimer_hash)
+                .bind(&form.form_hash)
+                .bind(form.published_at)
+                .execute(&self.pool)
+                .await
+                .map_err(db_error)?;
+                tracing::info!("Published consent form version {} for {}", form.version, key);
+                Ok(form)
+            }
+        }
+    }
 
+    /// A published form version, for showing what a user agreed to in the past
+    pub async fn get_form(&self, operation_type: &LegalOperationType, version: u32) -> Result<Option<ConsentForm>, String> {
+        self.ready().await?;
+        sqlx::query("SELECT * FROM consent_forms WHERE operation_type = ? AND version = ?")
+            .bind(enum_key(operation_type))
+            .bind(version as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?
+            .map(|row| form_from_row(&row))
+            .transpose()
+    }
+
+    /// Record user consent for a specific operation
+    ///
+    /// The consent text must be the current form's, and `form_version`, when
+    /// set, the version the user was shown; otherwise the form changed under
+    /// them and they have to review it again. The ledger is stamped with the
+    /// server's clock rather than `timestamp`.
+    pub async fn record_consent(&self, consent_record: ConsentRecord) -> Result<ConsentLedgerEntry, String> {
         // Validate consent record
         if consent_record.consent_text.is_empty() {
             return Err("Consent text cannot be empty".to_string());
         }
 
-        // Store the consent record
-        self.consent_records
-            .entry(user_id)
-            .or_insert_with(HashMap::new)
-            .insert(operation_key, consent_record);
+        let form = self.current_form(&consent_record.operation_type).await?;
+        if consent_record.form_version.is_some_and(|version| version != form.version) {
+            return Err(format!(
+                "Consent form version {} is out of date; review version {}",
+                consent_record.form_version.unwrap_or_default(),
+                form.version
+            ));
+        }
+        if consent_record.consent_text != form.consent_text {
+            return Err("Consent text does not match the current consent form".to_string());
+        }
+        if consent_record.consent_given && !consent_record.disclaimer_acknowledged {
+            return Err("The disclaimer must be acknowledged before consenting".to_string());
+        }
 
-        tracing::info!("Consent recorded for user and operation");
-        Ok(())
+        let action = if consent_record.consent_given { ConsentAction::Granted } else { ConsentAction::Declined };
+        let entry = self
+            .append(
+                &consent_record.user_id,
+                &form,
+                action,
+                consent_record.attorney_supervised,
+                consent_record.disclaimer_acknowledged,
+                ConsentSignature {
+                    ip_address: consent_record.ip_address,
+                    user_agent: consent_record.user_agent,
+                },
+                None,
+            )
+            .await?;
+
+        tracing::info!("Consent {:?} recorded for user and operation", action);
+        Ok(entry)
     }
 
     /// Check if user has valid consent for an operation
+    ///
+    /// Fails closed: a ledger that can't be read means no consent.
     pub async fn check_consent(&self, user_id: &str, operation_type: &LegalOperationType) -> ConsentStatus {
-        let operation_key = format!("{:?}", operation_type);
-        
-        if let Some(user_consents) = self.consent_records.get(user_id) {
-            if let Some(consent_record) = user_consents.get(&operation_key) {
-                // Check if consent has expired
-                let expiry_time = consent_record.timestamp + Duration::hours(self.consent_expiry_hours as i64);
-                let now = Utc::now();
-
-                if now > expiry_time {
-                    return ConsentStatus {
-                        consent_given: false,
-                        timestamp: Some(consent_record.timestamp),
-                        expires_at: Some(expiry_time),
-                        attorney_supervised: consent_record.attorney_supervised,
-                        requires_renewal: true,
-                    };
-                }
-
-                return ConsentStatus {
-                    consent_given: consent_record.consent_given,
-                    timestamp: Some(consent_record.timestamp),
-                    expires_at: Some(expiry_time),
-                    attorney_supervised: consent_record.attorney_supervised,
-                    requires_renewal: false,
-                };
+        match self.consent_status(user_id, operation_type).await {
+            Ok((status, _)) => status,
+            Err(e) => {
+                tracing::error!("Failed to read consent ledger: {}", e);
+                no_consent(None)
             }
         }
+    }
 
-        // No consent found
-        ConsentStatus {
+    async fn consent_status(
+        &self,
+        user_id: &str,
+        operation_type: &LegalOperationType,
+    ) -> Result<(ConsentStatus, u32), String> {
+        let form = self.current_form(operation_type).await?;
+        let Some(latest) = self.latest_entry(user_id, operation_type).await? else {
+            return Ok((no_consent(None), form.version));
+        };
+
+        let expiry_time = latest.recorded_at + Duration::hours(self.consent_expiry_hours as i64);
+        let mut status = ConsentStatus {
             consent_given: false,
-            timestamp: None,
+            timestamp: Some(latest.recorded_at),
             expires_at: None,
-            attorney_supervised: false,
+            attorney_supervised: latest.attorney_supervised,
             requires_renewal: false,
+            form_version: Some(latest.form_version),
+        };
+        if latest.action == ConsentAction::Granted {
+            status.expires_at = Some(expiry_time);
+            // A new form version or an expired consent needs the user to agree again
+            status.requires_renewal = latest.form_version != form.version || Utc::now() > expiry_time;
+            status.consent_given = !status.requires_renewal;
         }
+        Ok((status, form.version))
+    }
+
+    /// Withdraw consent; the grant stays in the ledger with the withdrawal after it
+    pub async fn withdraw_consent(
+        &self,
+        user_id: &str,
+        operation_type: &LegalOperationType,
+        reason: Option<String>,
+        signature: ConsentSignature,
+    ) -> Result<ConsentLedgerEntry, String> {
+        let latest = self.latest_entry(user_id, operation_type).await?;
+        let Some(latest) = latest.filter(|entry| entry.action == ConsentAction::Granted) else {
+            return Err("No consent record found to revoke".to_string());
+        };
+        let form = self
+            .get_form(operation_type, latest.form_version)
+            .await?
+            .ok_or_else(|| "Consent form for the withdrawn consent is missing".to_string())?;
+
+        let entry = self
+            .append(user_id, &form, ConsentAction::Withdrawn, latest.attorney_supervised, latest.disclaimer_acknowledged, signature, reason)
+            .await?;
+        tracing::info!("Consent revoked for user and operation");
+        Ok(entry)
     }
 
     /// Revoke consent for a specific operation
-    pub async fn revoke_consent(&mut self, user_id: &str, operation_type: &LegalOperationType) -> Result<(), String> {
-        let operation_key = format!("{:?}", operation_type);
-        
-        if let Some(user_consents) = self.consent_records.get_mut(user_id) {
-            if let Some(consent_record) = user_consents.get_mut(&operation_key) {
-                consent_record.consent_given = false;
-                consent_record.timestamp = Utc::now();
-                tracing::info!("Consent revoked for user and operation");
-                return Ok(());
+    pub async fn revoke_consent(&self, user_id: &str, operation_type: &LegalOperationType) -> Result<(), String> {
+        self.withdraw_consent(user_id, operation_type, None, ConsentSignature::default())
+            .await
+            .map(|_| ())
+    }
+
+    /// Get the consents a user currently holds, one per operation
+    pub async fn get_user_consents(&self, user_id: &str) -> Vec<ConsentRecord> {
+        let history = match self.consent_history(user_id).await {
+            Ok(history) => history,
+            Err(e) => {
+                tracing::error!("Failed to read consent ledger: {}", e);
+                return Vec::new();
             }
+        };
+
+        let mut records = Vec::new();
+        for operation_type in operations_in(&history) {
+            let Some(latest) = history.iter().rev().find(|entry| entry.operation_type == operation_type) else {
+                continue;
+            };
+            if latest.action != ConsentAction::Granted {
+                continue;
+            }
+            let consent_text = match self.get_form(&operation_type, latest.form_version).await {
+                Ok(Some(form)) => form.consent_text,
+                _ => continue,
+            };
+            records.push(ConsentRecord {
+                user_id: latest.user_id.clone(),
+                operation_type,
+                consent_given: true,
+                timestamp: latest.recorded_at,
+                ip_address: latest.signature.ip_address.clone(),
+                user_agent: latest.signature.user_agent.clone(),
+                consent_text,
+                form_version: Some(latest.form_version),
+                attorney_supervised: latest.attorney_supervised,
+                disclaimer_acknowledged: latest.disclaimer_acknowledged,
+            });
         }
+        records
+    }
 
-        Err("No consent record found to revoke".to_string())
+    /// Every ledger entry for a user, oldest first
+    pub async fn consent_history(&self, user_id: &str) -> Result<Vec<ConsentLedgerEntry>, String> {
+        self.ready().await?;
+        sqlx::query("SELECT * FROM consent_ledger WHERE user_id = ? ORDER BY id")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(ledger_entry_from_row)
+            .collect()
     }
 
-    /// Get all consent records for a user
-    pub async fn get_user_consents(&self, user_id: &str) -> Vec<ConsentRecord> {
-        if let Some(user_consents) = self.consent_records.get(user_id) {
-            user_consents.values().cloned().collect()
-        } else {
-            Vec::new()
+    /// Current consents, full history and the form versions it refers to
+    pub async fn consent_report(&self, user_id: &str) -> Result<ConsentReport, String> {
+        let history = self.consent_history(user_id).await?;
+
+        let mut consents = Vec::new();
+        for operation_type in operations_in(&history) {
+            let (status, current_form_version) = self.consent_status(user_id, &operation_type).await?;
+            consents.push(ConsentReportLine { operation_type, current_form_version, status });
+        }
+
+        let mut forms: Vec<ConsentForm> = Vec::new();
+        for entry in &history {
+            let seen = forms
+                .iter()
+                .any(|form| form.operation_type == entry.operation_type && form.version == entry.form_version);
+            if !seen {
+                if let Some(form) = self.get_form(&entry.operation_type, entry.form_version).await? {
+                    forms.push(form);
+                }
+            }
+        }
+
+        Ok(ConsentReport {
+            user_id: user_id.to_string(),
+            generated_at: Utc::now(),
+            consents,
+            history,
+            forms,
+        })
+    }
+
+    /// Export a user's consent report as JSON (full report) or CSV (ledger rows)
+    pub async fn export_consent_report(&self, user_id: &str, format: &ExportFormat) -> Result<Vec<u8>, String> {
+        let report = self.consent_report(user_id).await?;
+        match format {
+            ExportFormat::JSON => serde_json::to_vec_pretty(&report).map_err(|e| e.to_string()),
+            ExportFormat::CSV => {
+                let mut csv = csv_record(&[
+                    "id", "recorded_at", "operation_type", "action", "form_version", "form_hash",
+                    "disclaimer_hash", "attorney_supervised", "disclaimer_acknowledged", "ip_address",
+                    "user_agent", "reason",
+                ]);
+                for entry in &report.history {
+                    csv.push_str(&csv_record(&[
+                        &entry.id.to_string(),
+                        &entry.recorded_at.to_rfc3339(),
+                        &enum_key(&entry.operation_type),
+                        &enum_key(&entry.action),
+                        &entry.form_version.to_string(),
+                        &entry.form_hash,
+                        &entry.disclaimer_hash,
+                        &entry.attorney_supervised.to_string(),
+                        &entry.disclaimer_acknowledged.to_string(),
+                        entry.signature.ip_address.as_deref().unwrap_or_default(),
+                        entry.signature.user_agent.as_deref().unwrap_or_default(),
+                        entry.reason.as_deref().unwrap_or_default(),
+                    ]));
+                }
+                Ok(csv.into_bytes())
+            }
+            other => Err(format!("{:?} is not supported for consent reports", other)),
         }
     }
 
@@ -130,6 +567,9 @@ impl ConsentManager {
             LegalOperationType::PresentationGeneration => true,
             LegalOperationType::SemanticSearch => true,
             LegalOperationType::CollaborationMetrics => true,
+            LegalOperationType::AuditLogExport => false,
+            LegalOperationType::ExhibitDeletion => true,
+            LegalOperationType::RecordDisposition => false,
         }
     }
 
@@ -144,257 +584,269 @@ impl ConsentManager {
         }
     }
 
-    /// Get consent form HTML for an operation
-    pub fn get_consent_form_html(&self, operation_type: &LegalOperationType, disclaimer_text: &str) -> String {
-        let operation_name = match operation_type {
-            LegalOperationType::AILegalAdvice => "AI Legal Assistance",
-            LegalOperationType::VoiceRecording => "Voice Recording",
-            LegalOperationType::DocumentModification => "Document Modification",
-            LegalOperationType::ClientDataProcessing => "Client Data Processing",
-            LegalOperationType::TimelineAnalysis => "Timeline Analysis",
-            LegalOperationType::DocumentIntelligence => "Document Intelligence",
-            LegalOperationType::PresentationGeneration => "Presentation Generation",
-            LegalOperationType::SemanticSearch => "Semantic Search",
-            LegalOperationType::CollaborationMetrics => "Collaboration Metrics",
-        };
+    /// Get consent form HTML for the current version of an operation's form
+    pub async fn get_consent_form_html(&self, operation_type: &LegalOperationType) -> Result<String, String> {
+        Ok(self.current_form(operation_type).await?.to_html())
+    }
 
-        let attorney_supervision_required = Self::requires_attorney_supervision(operation_type);
-
-        format!(r#"
-<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Legal Consent Required - {}</title>
-    <style>
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-            max-width: 800px;
-            margin: 0 auto;
-            padding: 20px;
-            background: #f5f5f5;
-        }}
-        .consent-container {{
-            background: white;
-            border-radius: 10px;
-            padding: 30px;
-            box-shadow: 0 4px 12px rgba(0,0,0,0.1);
-            border-left: 5px solid #dc3545;
-        }}
-        .warning-header {{
-            background: #dc3545;
-            color: white;
-            padding: 15px;
-            margin: -30px -30px 20px -30px;
-            border-radius: 10px 10px 0 0;
-            text-align: center;
-        }}
-        .disclaimer {{
-            background: #fff3cd;
-            border: 1px solid #ffeaa7;
-            border-radius: 5px;
-            padding: 20px;
-            margin: 20px 0;
-            white-space: pre-line;
-            font-size: 14px;
-            line-height: 1.6;
-            max-height: 300px;
-            overflow-y: auto;
-        }}
-        .consent-form {{
-            margin-top: 30px;
-            padding-top: 20px;
-            border-top: 2px solid #eee;
-        }}
-        .checkbox-group {{
-            margin: 15px 0;
-            padding: 15px;
-            background: #f8f9fa;
-            border-radius: 5px;
-        }}
-        .checkbox-group input[type="checkbox"] {{
-            margin-right: 10px;
-            transform: scale(1.2);
-        }}
-        .checkbox-group label {{
-            font-weight: 500;
-            cursor: pointer;
-        }}
-        .attorney-supervision {{
-            background: #e7f3ff;
-            border: 2px solid #0056b3;
-            border-radius: 5px;
-            padding: 15px;
-            margin: 20px 0;
-        }}
-        .buttons {{
-            text-align: center;
-            margin-top: 30px;
-        }}
-        .btn {{
-            padding: 12px 30px;
-            margin: 0 10px;
-            border: none;
-            border-radius: 5px;
-            font-size: 16px;
-            cursor: pointer;
-            font-weight: 500;
-        }}
-        .btn-danger {{
-            background: #dc3545;
-            color: white;
-        }}
-        .btn-success {{
-            background: #28a745;
-            color: white;
-        }}
-        .btn:disabled {{
-            background: #6c757d;
-            cursor: not-allowed;
-        }}
-        .timestamp {{
-            font-size: 12px;
-            color: #666;
-            text-align: center;
-            margin-top: 20px;
-        }}
-    </style>
-</head>
-<body>
-    <div class="consent-container">
-        <div class="warning-header">
-            <h2>⚖️ LEGAL CONSENT REQUIRED ⚖️</h2>
-            <p>Operation: {}</p>
-        </div>
-
-        <h3>Legal Disclaimer and Warning</h3>
-        <div class="disclaimer">{}</div>
-
-        {}
-
-        <div class="consent-form">
-            <h3>Consent Requirements</h3>
-            
-            <div class="checkbox-group">
-                <label>
-                    <input type="checkbox" id="understand-disclaimer" required>
-                    I have read and understand the legal disclaimer above
-                </label>
-            </div>
+    /// Set custom consent expiry time
+    pub fn set_consent_expiry_hours(&mut self, hours: u64) {
+        self.consent_expiry_hours = hours;
+    }
 
-            <div class="checkbox-group">
-                <label>
-                    <input type="checkbox" id="accept-risks" required>
-                    I understand the legal risks and limitations of this technology
-                </label>
-            </div>
+    async fn latest_entry(
+        &self,
+        user_id: &str,
+        operation_type: &LegalOperationType,
+    ) -> Result<Option<ConsentLedgerEntry>, String> {
+        self.ready().await?;
+        sqlx::query("SELECT * FROM consent_ledger WHERE user_id = ? AND operation_type = ? ORDER BY id DESC LIMIT 1")
+            .bind(user_id)
+            .bind(enum_key(operation_type))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?
+            .as_ref()
+            .map(ledger_entry_from_row)
+            .transpose()
+    }
 
-            <div class="checkbox-group">
-                <label>
-                    <input type="checkbox" id="not-legal-advice" required>
-                    I acknowledge this system does not provide legal advice
-                </label>
-            </div>
+    #[allow(clippy::too_many_arguments)]
+    async fn append(
+        &self,
+        user_id: &str,
+        form: &ConsentForm,
+        action: ConsentAction,
+        attorney_supervised: bool,
+        disclaimer_acknowledged: bool,
+        signature: ConsentSignature,
+        reason: Option<String>,
+    ) -> Result<ConsentLedgerEntry, String> {
+        let recorded_at = Utc::now();
+        let id = sqlx::query(
+            "INSERT INTO consent_ledger (user_id, operation_type, action, form_version, form_hash, disclaimer_hash,
+                attorney_supervised, disclaimer_acknowledged, ip_address, user_agent, reason, recorded_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(enum_key(&form.operation_type))
+        .bind(enum_key(&action))
+        .bind(form.version as i64)
+        .bind(&form.form_hash)
+        .bind(&form.disclaimer_hash)
+        .bind(attorney_supervised)
+        .bind(disclaimer_acknowledged)
+        .bind(&signature.ip_address)
+        .bind(&signature.user_agent)
+        .bind(&reason)
+        .bind(recorded_at)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?
+        .last_insert_rowid();
+
+        Ok(ConsentLedgerEntry {
+            id,
+            user_id: user_id.to_string(),
+            operation_type: form.operation_type.clone(),
+            action,
+            form_version: form.version,
+            form_hash: form.form_hash.clone(),
+            disclaimer_hash: form.disclaimer_hash.clone(),
+            attorney_supervised,
+            disclaimer_acknowledged,
+            signature,
+            reason,
+            recorded_at,
+        })
+    }
+}
 
-            <div class="checkbox-group">
-                <label>
-                    <input type="checkbox" id="attorney-consultation" required>
-                    I agree to consult with a qualified attorney for legal matters
-                </label>
-            </div>
+/// Consent text for an operation, as shown on its form
+pub fn default_consent_text(operation_type: &LegalOperationType) -> &'static str {
+    match operation_type {
+        LegalOperationType::AILegalAdvice => {
+            "I understand that this AI system does not provide legal advice and that I should consult with a qualified attorney for legal matters. I acknowledge that this system is for informational purposes only."
+        }
+        LegalOperationType::VoiceRecording => {
+            "I consent to the recording and processing of my voice for the purpose of this legal technology system. I understand that recordings may be stored and analyzed by AI systems."
+        }
+        LegalOperationType::DocumentModification => {
+            "I understand that modifying legal documents through this system may have legal consequences. I acknowledge that proper legal review and chain of custody procedures should be followed."
+        }
+        _ => {
+            "I acknowledge that I am using this legal technology system at my own discretion and understand the potential legal implications."
+        }
+    }
+}
 
-            <div class="checkbox-group">
-                <label>
-                    <input type="checkbox" id="accept-responsibility" required>
-                    I accept full responsibility for the consequences of using this system
-                </label>
-            </div>
+fn operation_name(operation_type: &LegalOperationType) -> &'static str {
+    match operation_type {
+        LegalOperationType::AILegalAdvice => "AI Legal Assistance",
+        LegalOperationType::VoiceRecording => "Voice Recording",
+        LegalOperationType::DocumentModification => "Document Modification",
+        LegalOperationType::ClientDataProcessing => "Client Data Processing",
+        LegalOperationType::TimelineAnalysis => "Timeline Analysis",
+        LegalOperationType::DocumentIntelligence => "Document Intelligence",
+        LegalOperationType::PresentationGeneration => "Presentation Generation",
+        LegalOperationType::SemanticSearch => "Semantic Search",
+        LegalOperationType::CollaborationMetrics => "Collaboration Metrics",
+        LegalOperationType::AuditLogExport => "Audit Log Export",
+        LegalOperationType::ExhibitDeletion => "Exhibit Deletion",
+        LegalOperationType::RecordDisposition => "Record Disposition",
+    }
+}
 
-            <div class="buttons">
-                <button type="button" class="btn btn-danger" onclick="window.history.back()">
-                    Decline - Go Back
-                </button>
-                <button type="button" class="btn btn-success" id="consent-button" disabled onclick="submitConsent()">
-                    I Consent - Proceed
-                </button>
-            </div>
-        </div>
-
-        <div class="timestamp">
-            Consent recorded: <span id="timestamp"></span>
-        </div>
-    </div>
-
-    <script>
-        // Enable consent button only when all checkboxes are checked
-        const checkboxes = document.querySelectorAll('input[type="checkbox"]');
-        const consentButton = document.getElementById('consent-button');
-
-        checkboxes.forEach(checkbox => {{
-            checkbox.addEventListener('change', updateConsentButton);
-        }});
-
-        function updateConsentButton() {{
-            const allChecked = Array.from(checkboxes).every(cb => cb.checked);
-            consentButton.disabled = !allChecked;
-        }}
-
-        function submitConsent() {{
-            if (Array.from(checkboxes).every(cb => cb.checked)) {{
-                document.getElementById('timestamp').textContent = new Date().toISOString();
-                
-                // In a real implementation, this would POST to a consent endpoint
-                const consentData = {{
-                    operation_type: '{}',
-                    consent_given: true,
-                    timestamp: new Date().toISOString(),
-                    ip_address: null, // Would be filled by server
-                    attorney_supervised: {},
-                    disclaimer_acknowledged: true
-                }};
-
-                // Show success message and redirect
-                alert('Consent recorded successfully. You may now proceed with the operation.');
-                
-                // Redirect to the original operation
-                window.location.href = '/api/consent/recorded?operation={}';
-            }}
-        }}
-    </script>
-</body>
-</html>"#,
-            operation_name,
-            operation_name,
-            disclaimer_text,
-            if attorney_supervision_required {
-                r#"<div class="attorney-supervision">
-                    <h4>⚖️ ATTORNEY SUPERVISION REQUIRED</h4>
-                    <p>This operation requires attorney supervision. Ensure that a qualified attorney is overseeing this process and will review all outputs before use.</p>
-                </div>"#
-            } else {
-                ""
-            },
-            format!("{:?}", operation_type),
-            attorney_supervision_required.to_string(),
-            format!("{:?}", operation_type)
-        )
+fn no_consent(form_version: Option<u32>) -> ConsentStatus {
+    ConsentStatus {
+        consent_given: false,
+        timestamp: None,
+        expires_at: None,
+        attorney_supervised: false,
+        requires_renewal: false,
+        form_version,
     }
+}
 
-    /// Set custom consent expiry time
-    pub fn set_consent_expiry_hours(&mut self, hours: u64) {
-        self.consent_expiry_hours = hours;
+/// Operations appearing in `history`, in the order first seen
+fn operations_in(history: &[ConsentLedgerEntry]) -> Vec<LegalOperationType> {
+    let mut operations: Vec<LegalOperationType> = Vec::new();
+    for entry in history {
+        if !operations.contains(&entry.operation_type) {
+            operations.push(entry.operation_type.clone());
+        }
     }
+    operations
+}
 
-    /// Clean up expired consent records
-    pub async fn cleanup_expired_consents(&mut self) {
-        let now = Utc::now();
-        let expiry_duration = Duration::hours(self.consent_expiry_hours as i64);
+fn form_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ConsentForm, String> {
+    Ok(ConsentForm {
+        operation_type: parse_key(&row.get::<String, _>("operation_type"))?,
+        version: row.get::<i64, _>("version") as u32,
+        consent_text: row.get("consent_text"),
+        disclaimer_text: row.get("disclaimer_text"),
+        acknowledgements: serde_json::from_str(&row.get::<String, _>("acknowledgements")).map_err(|e| e.to_string())?,
+        disclaimer_hash: row.get("disclaimer_hash"),
+        form_hash: row.get("form_hash"),
+        published_at: row.get("published_at"),
+    })
+}
 
-        for user_consents in self.consent_records.values_mut() {
-            user_consents.retain(|_, consent| {
-                consent.timestamp + expiry_duration > now
-            });
+fn ledger_entry_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ConsentLedgerEntry, String> {
+    Ok(ConsentLedgerEntry {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        operation_type: parse_key(&row.get::<String, _>("operation_type"))?,
+        action: parse_key(&row.get::<String, _>("action"))?,
+        form_version: row.get::<i64, _>("form_version") as u32,
+        form_hash: row.get("form_hash"),
+        disclaimer_hash: row.get("disclaimer_hash"),
+        attorney_supervised: row.get("attorney_supervised"),
+        disclaimer_acknowledged: row.get("disclaimer_acknowledged"),
+        signature: ConsentSignature {
+            ip_address: row.get("ip_address"),
+            user_agent: row.get("user_agent"),
+        },
+        reason: row.get("reason"),
+        recorded_at: row.get("recorded_at"),
+    })
+}
+
+fn parse_key<T: serde::de::DeserializeOwned>(key: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(key.to_string()))
+        .map_err(|e| format!("Unknown value '{}' in consent ledger: {}", key, e))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn db_error(e: sqlx::Error) -> String {
+    format!("Consent ledger database error: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consent(user_id: &str, operation_type: LegalOperationType, form_version: Option<u32>) -> ConsentRecord {
+        ConsentRecord {
+            user_id: user_id.to_string(),
+            consent_text: default_consent_text(&operation_type).to_string(),
+            operation_type,
+            consent_given: true,
+            timestamp: Utc::now(),
+            ip_address: Some("203.0.113.7".to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            form_version,
+            attorney_supervised: true,
+            disclaimer_acknowledged: true,
         }
     }
+
+    #[tokio::test]
+    async fn test_consent_is_versioned_and_withdrawal_is_kept_in_history() {
+        let manager = ConsentManager::new();
+        let form = manager.current_form(&LegalOperationType::AILegalAdvice).await.unwrap();
+        assert_eq!(form.version, 1);
+        assert_eq!(manager.current_form(&LegalOperationType::AILegalAdvice).await.unwrap().form_hash, form.form_hash);
+
+        assert!(manager.record_consent(consent("client", LegalOperationType::AILegalAdvice, Some(2))).await.is_err());
+        let entry = manager
+            .record_consent(consent("client", LegalOperationType::AILegalAdvice, Some(1)))
+            .await
+            .unwrap();
+        assert_eq!(entry.disclaimer_hash, sha256_hex(disclaimers::AI_LEGAL_ADVICE_DISCLAIMER.as_bytes()));
+        assert!(manager.check_consent("client", &LegalOperationType::AILegalAdvice).await.consent_given);
+
+        manager
+            .withdraw_consent(
+                "client",
+                &LegalOperationType::AILegalAdvice,
+                Some("Changed my mind".to_string()),
+                ConsentSignature::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!manager.check_consent("client", &LegalOperationType::AILegalAdvice).await.consent_given);
+
+        let history = manager.consent_history("client").await.unwrap();
+        let actions: Vec<_> = history.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec![ConsentAction::Granted, ConsentAction::Withdrawn]);
+        assert_eq!(history[0].signature.ip_address.as_deref(), Some("203.0.113.7"));
+        assert!(manager.get_user_consents("client").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_changed_form_requires_consent_again() {
+        let manager = ConsentManager::new();
+        manager
+            .record_consent(consent("client", LegalOperationType::VoiceRecording, None))
+            .await
+            .unwrap();
+
+        // Simulate the consent having been given to an earlier release's form
+        sqlx::query("UPDATE consent_forms SET form_hash = 'older', version = 1")
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+        let status = manager.check_consent("client", &LegalOperationType::VoiceRecording).await;
+        assert!(!status.consent_given && status.requires_renewal);
+        assert_eq!(status.form_version, Some(1));
+        assert_eq!(manager.current_form(&LegalOperationType::VoiceRecording).await.unwrap().version, 2);
+
+        manager
+            .record_consent(consent("client", LegalOperationType::VoiceRecording, Some(2)))
+            .await
+            .unwrap();
+        assert!(manager.check_consent("client", &LegalOperationType::VoiceRecording).await.consent_given);
+
+        let csv = manager.export_consent_report("client", &ExportFormat::CSV).await.unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.contains("VoiceRecording,granted,2,"));
+        let report = manager.consent_report("client").await.unwrap();
+        assert_eq!(report.forms.len(), 2);
+        assert!(report.forms[1].to_html().contains("Form version 2"));
+    }
 }
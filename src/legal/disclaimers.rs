@@ -3,6 +3,7 @@
 /// This module contains comprehensive legal disclaimers and warnings for all
 /// potentially sensitive legal operations within the platform.
 
+use crate::legal::LegalOperationType;
 use serde::{Deserialize, Serialize};
 
 /// General legal disclaimer for the platform
@@ -280,6 +281,21 @@ By using AI timeline analysis, you acknowledge:
 Do not rely solely on AI-generated timelines for legal strategy or evidence.
 "#;
 
+/// Disclaimer shown on the consent form for an operation
+pub fn for_operation(operation_type: &LegalOperationType) -> &'static str {
+    match operation_type {
+        LegalOperationType::AILegalAdvice => AI_LEGAL_ADVICE_DISCLAIMER,
+        LegalOperationType::VoiceRecording => VOICE_RECORDING_DISCLAIMER,
+        LegalOperationType::DocumentModification => DOCUMENT_MODIFICATION_DISCLAIMER,
+        LegalOperationType::ClientDataProcessing => CLIENT_DATA_DISCLAIMER,
+        LegalOperationType::TimelineAnalysis => TIMELINE_ANALYSIS_DISCLAIMER,
+        LegalOperationType::PresentationGeneration => PRESENTATION_GENERATION_DISCLAIMER,
+        LegalOperationType::SemanticSearch => SEMANTIC_SEARCH_DISCLAIMER,
+        LegalOperationType::CollaborationMetrics => COLLABORATION_METRICS_DISCLAIMER,
+        _ => GENERAL_DISCLAIMER,
+    }
+}
+
 /// Helper struct for managing disclaimers
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DisclaimerManager {
@@ -0,0 +1,578 @@
+//! Matter-scoped access for MoodBridge_Rust
+//!
+//! Role permissions say what a user may do; this module says on which matters.
+//! A matter with an access list is limited to the users on it, and stays
+//! restricted even if everyone is later taken off the list. An ethical wall
+//! screens a group of users from one or more matters, e.g. a lateral hire who
+//! represented the adverse party, and overrides any access list.
+//!
+//! Routes with a `:case_id` are checked by the access middleware. Everything
+//! else that can return records from several matters filters them through the
+//! caller's `MatterScope`.
+
+use crate::legal::access_control::{db_error, AccessController, UserRole};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::BTreeSet;
+use chrono::{DateTime, Utc};
+
+/// The matter of the exhibit or communication named by a row's `source_type`
+/// and `source_id` columns, for tables derived from those records
+pub const SOURCE_MATTER_SQL: &str = "(CASE source_type \
+     WHEN 'exhibit' THEN (SELECT e.case_id FROM exhibits e WHERE e.id = source_id) \
+     WHEN 'communication' THEN (SELECT c.case_id FROM communications c WHERE c.id = source_id) END)";
+
+/// The matters one user is kept out of
+///
+/// Records filed under no matter aren't covered by access lists or walls and
+/// stay visible.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatterScope {
+    denied: BTreeSet<i64>,
+}
+
+impl MatterScope {
+    /// A scope that denies nothing, for tools and tests acting for the firm
+    pub fn unrestricted() -> Self {
+        Self::default()
+    }
+
+    pub fn allows(&self, case_id: Option<i64>) -> bool {
+        case_id.is_none_or(|case_id| !self.denied.contains(&case_id))
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.denied.is_empty()
+    }
+
+    /// SQL condition keeping rows whose matter, given by `expression`, is allowed
+    pub fn sql_filter(&self, expression: &str) -> String {
+        if self.denied.is_empty() {
+            return "1 = 1".to_string();
+        }
+        // Only integers go into the statement
+        let denied = self.denied.iter().map(i64::to_string).collect::<Vec<_>>().join(", ");
+        format!("({expr} IS NULL OR {expr} NOT IN ({denied}))", expr = expression)
+    }
+}
+
+/// A group of users screened from a set of matters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EthicalWall {
+    pub wall_id: String,
+    pub name: String,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub members: Vec<String>,
+    pub case_ids: Vec<i64>,
+    pub lifted_at: Option<DateTime<Utc>>,
+}
+
+/// Matter access lists and ethical walls, stored with the access control tables
+#[derive(Debug, Clone)]
+pub struct MatterAccess {
+    access: AccessController,
+}
+
+impl MatterAccess {
+    pub fn new(access: AccessController) -> Self {
+        Self { access }
+    }
+
+    /// Whether `user_id` may work on `case_id`
+    ///
+    /// Storage errors deny access.
+    pub async fn can_access_matter(&self, user_id: &str, case_id: i64) -> bool {
+        match self.check_matter_access(user_id, case_id).await {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                tracing::error!("Matter access check failed for {} on case {}: {}", user_id, case_id, e);
+                false
+            }
+        }
+    }
+
+    async fn check_matter_access(&self, user_id: &str, case_id: i64) -> Result<bool, String> {
+        if self.is_screened(user_id, case_id).await? {
+            return Ok(false);
+        }
+        if !self.is_restricted(case_id).await? {
+            return Ok(true);
+        }
+
+        let team = self.matter_team(case_id).await?;
+        Ok(team.iter().any(|member| member == user_id))
+    }
+
+    /// Whether `case_id` has ever had an access list
+    pub async fn is_restricted(&self, case_id: i64) -> Result<bool, String> {
+        self.access.ready().await?;
+        let restricted: Option<i64> = sqlx::query_scalar("SELECT case_id FROM matter_restrictions WHERE case_id = ?")
+            .bind(case_id)
+            .fetch_optional(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        Ok(restricted.is_some())
+    }
+
+    /// Every matter `user_id` is screened from or left off the access list of
+    pub async fn scope(&self, user_id: &str) -> Result<MatterScope, String> {
+        self.access.ready().await?;
+        let denied: Vec<i64> = sqlx::query_scalar(
+            "SELECT r.case_id FROM matter_restrictions r
+             WHERE NOT EXISTS (SELECT 1 FROM matter_access a WHERE a.case_id = r.case_id AND a.user_id = ?)
+             UNION
+             SELECT m.case_id FROM ethical_walls w
+             JOIN ethical_wall_members u ON u.wall_id = w.wall_id
+             JOIN ethical_wall_matters m ON m.wall_id = w.wall_id
+             WHERE w.lifted_at IS NULL AND u.user_id = ?",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(self.access.pool())
+        .await
+        .map_err(db_error)?;
+        Ok(MatterScope { denied: denied.into_iter().collect() })
+    }
+
+    /// Whether an active ethical wall keeps `user_id` away from `case_id`
+    pub async fn is_screened(&self, user_id: &str, case_id: i64) -> Result<bool, String> {
+        self.access.ready().await?;
+        let walls: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM ethical_walls w
+             JOIN ethical_wall_members u ON u.wall_id = w.wall_id
+             JOIN ethical_wall_matters m ON m.wall_id = w.wall_id
+             WHERE w.lifted_at IS NULL AND u.user_id = ? AND m.case_id = ?",
+        )
+        .bind(user_id)
+        .bind(case_id)
+        .fetch_one(self.access.pool())
+        .await
+        .map_err(db_error)?;
+        Ok(walls > 0)
+    }
+
+    /// Users on a matter's access list
+    pub async fn matter_team(&self, case_id: i64) -> Result<Vec<String>, String> {
+        self.access.ready().await?;
+        sqlx::query_scalar("SELECT user_id FROM matter_access WHERE case_id = ? ORDER BY user_id")
+            .bind(case_id)
+            .fetch_all(self.access.pool())
+            .await
+            .map_err(db_error)
+    }
+
+    /// Put a user on a matter's access list
+    pub async fn grant_matter_access(&self, case_id: i64, user_id: &str, granted_by: &str) -> Result<(), String> {
+        self.require_attorney_on_matter(granted_by, case_id).await?;
+        if self.access.get_user(user_id).await?.is_none() {
+            return Err("User not found".to_string());
+        }
+        if self.is_screened(user_id, case_id).await? {
+            return Err(format!("User {} is screened from case {} by an ethical wall", user_id, case_id));
+        }
+
+        let mut tx = self.access.pool().begin().await.map_err(db_error)?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO matter_restrictions (case_id, restricted_by, restricted_at) VALUES (?, ?, ?)",
+        )
+        .bind(case_id)
+        .bind(granted_by)
+        .bind(Utc::now())
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO matter_access (case_id, user_id, granted_by, granted_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(case_id)
+        .bind(user_id)
+        .bind(granted_by)
+        .bind(Utc::now())
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::info!("User {} added to case {} by {}", user_id, case_id, granted_by);
+        Ok(())
+    }
+
+    /// Take a user off a matter's access list
+    pub async fn revoke_matter_access(&self, case_id: i64, user_id: &str, revoked_by: &str) -> Result<(), String> {
+        self.require_attorney_on_matter(revoked_by, case_id).await?;
+        sqlx::query("DELETE FROM matter_access WHERE case_id = ? AND user_id = ?")
+            .bind(case_id)
+            .bind(user_id)
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+
+        tracing::info!("User {} removed from case {} by {}", user_id, case_id, revoked_by);
+        Ok(())
+    }
+
+    /// Screen `members` from `case_ids`, removing them from those matters' access lists
+    pub async fn create_wall(
+        &self,
+        name: &str,
+        reason: &str,
+        created_by: &str,
+        members: &[String],
+        case_ids: &[i64],
+    ) -> Result<EthicalWall, String> {
+        self.require_attorney(created_by).await?;
+        for case_id in case_ids {
+            self.require_attorney_on_matter(created_by, *case_id).await?;
+        }
+        if reason.trim().is_empty() {
+            return Err("An ethical wall must record its reason".to_string());
+        }
+        if members.is_empty() || case_ids.is_empty() {
+            return Err("An ethical wall needs at least one member and one matter".to_string());
+        }
+        if members.iter().any(|member| member == created_by) {
+            return Err("Attorneys cannot screen themselves; ask another attorney".to_string());
+        }
+
+        let wall = EthicalWall {
+            wall_id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            reason: reason.to_string(),
+            created_by: created_by.to_string(),
+            created_at: Utc::now(),
+            members: members.to_vec(),
+            case_ids: case_ids.to_vec(),
+            lifted_at: None,
+        };
+
+        let mut tx = self.access.pool().begin().await.map_err(db_error)?;
+        sqlx::query(
+            "INSERT INTO ethical_walls (wall_id, name, reason, created_by, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&wall.wall_id)
+        .bind(&wall.name)
+        .bind(&wall.reason)
+        .bind(&wall.created_by)
+        .bind(wall.created_at)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        for member in members {
+            sqlx::query("INSERT OR IGNORE INTO ethical_wall_members (wall_id, user_id) VALUES (?, ?)")
+                .bind(&wall.wall_id)
+                .bind(member)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        for case_id in case_ids {
+            sqlx::query("INSERT OR IGNORE INTO ethical_wall_matters (wall_id, case_id) VALUES (?, ?)")
+                .bind(&wall.wall_id)
+                .bind(case_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+            for member in members {
+                sqlx::query("DELETE FROM matter_access WHERE case_id = ? AND user_id = ?")
+                    .bind(case_id)
+                    .bind(member)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::warn!(
+            "Ethical wall '{}' screens {:?} from cases {:?} (created by {})",
+            wall.name, wall.members, wall.case_ids, created_by
+        );
+        Ok(wall)
+    }
+
+    /// Add a user to an existing wall
+    pub async fn add_wall_member(&self, wall_id: &str, user_id: &str, added_by: &str) -> Result<(), String> {
+        let wall = self.get_wall(wall_id).await?.ok_or_else(|| "Ethical wall not found".to_string())?;
+        self.require_wall_manager(added_by, &wall).await?;
+        if wall.lifted_at.is_some() {
+            return Err("Ethical wall has been lifted".to_string());
+        }
+        if user_id == added_by {
+            return Err("Attorneys cannot screen themselves; ask another attorney".to_string());
+        }
+
+        let mut tx = self.access.pool().begin().await.map_err(db_error)?;
+        sqlx::query("INSERT OR IGNORE INTO ethical_wall_members (wall_id, user_id) VALUES (?, ?)")
+            .bind(wall_id)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        for case_id in &wall.case_ids {
+            sqlx::query("DELETE FROM matter_access WHERE case_id = ? AND user_id = ?")
+                .bind(case_id)
+                .bind(user_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::warn!("User {} added to ethical wall '{}' by {}", user_id, wall.name, added_by);
+        Ok(())
+    }
+
+    /// Stop screening a wall's members; the wall stays on record
+    pub async fn lift_wall(&self, wall_id: &str, lifted_by: &str) -> Result<(), String> {
+        let wall = self.get_wall(wall_id).await?.ok_or_else(|| "Ethical wall not found".to_string())?;
+        self.require_wall_manager(lifted_by, &wall).await?;
+        let updated = sqlx::query(
+            "UPDATE ethical_walls SET lifted_at = ?, lifted_by = ? WHERE wall_id = ? AND lifted_at IS NULL",
+        )
+        .bind(Utc::now())
+        .bind(lifted_by)
+        .bind(wall_id)
+        .execute(self.access.pool())
+        .await
+        .map_err(db_error)?;
+        if updated.rows_affected() == 0 {
+            return Err("Ethical wall not found or already lifted".to_string());
+        }
+
+        tracing::warn!("Ethical wall {} lifted by {}", wall_id, lifted_by);
+        Ok(())
+    }
+
+    pub async fn get_wall(&self, wall_id: &str) -> Result<Option<EthicalWall>, String> {
+        self.access.ready().await?;
+        let row = sqlx::query("SELECT * FROM ethical_walls WHERE wall_id = ?")
+            .bind(wall_id)
+            .fetch_optional(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        match row {
+            Some(row) => Ok(Some(self.wall_from_row(&row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Active walls screening anyone from `case_id`
+    pub async fn walls_for_matter(&self, case_id: i64) -> Result<Vec<EthicalWall>, String> {
+        self.access.ready().await?;
+        let rows = sqlx::query(
+            "SELECT w.* FROM ethical_walls w JOIN ethical_wall_matters m ON m.wall_id = w.wall_id
+             WHERE m.case_id = ? AND w.lifted_at IS NULL ORDER BY w.created_at",
+        )
+        .bind(case_id)
+        .fetch_all(self.access.pool())
+        .await
+        .map_err(db_error)?;
+
+        let mut walls = Vec::with_capacity(rows.len());
+        for row in &rows {
+            walls.push(self.wall_from_row(row).await?);
+        }
+        Ok(walls)
+    }
+
+    async fn wall_from_row(&self, row: &sqlx::sqlite::SqliteRow) -> Result<EthicalWall, String> {
+        let wall_id: String = row.get("wall_id");
+        let members = sqlx::query_scalar("SELECT user_id FROM ethical_wall_members WHERE wall_id = ? ORDER BY user_id")
+            .bind(&wall_id)
+            .fetch_all(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        let case_ids = sqlx::query_scalar("SELECT case_id FROM ethical_wall_matters WHERE wall_id = ? ORDER BY case_id")
+            .bind(&wall_id)
+            .fetch_all(self.access.pool())
+            .await
+            .map_err(db_error)?;
+
+        Ok(EthicalWall {
+            wall_id,
+            name: row.get("name"),
+            reason: row.get("reason"),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            members,
+            case_ids,
+            lifted_at: row.get("lifted_at"),
+        })
+    }
+
+    async fn require_attorney(&self, user_id: &str) -> Result<(), String> {
+        match self.access.get_user(user_id).await? {
+            Some(user) if user.active && user.role == UserRole::Attorney => Ok(()),
+            Some(_) => Err("Only attorneys can manage matter access and ethical walls".to_string()),
+            None => Err("User not found".to_string()),
+        }
+    }
+
+    async fn require_attorney_on_matter(&self, user_id: &str, case_id: i64) -> Result<(), String> {
+        self.require_attorney(user_id).await?;
+        if !self.can_access_matter(user_id, case_id).await {
+            return Err(format!("User {} has no access to case {}", user_id, case_id));
+        }
+        Ok(())
+    }
+
+    /// An attorney outside `wall` with access to every matter it covers
+    async fn require_wall_manager(&self, user_id: &str, wall: &EthicalWall) -> Result<(), String> {
+        self.require_attorney(user_id).await?;
+        if wall.members.iter().any(|member| member == user_id) {
+            return Err("Attorneys cannot change a wall that screens them".to_string());
+        }
+        for case_id in &wall.case_ids {
+            self.require_attorney_on_matter(user_id, *case_id).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legal::access_control::User;
+    use std::collections::HashSet;
+
+    async fn firm() -> AccessController {
+        let access = AccessController::new();
+        for (user_id, role) in [
+            ("partner", UserRole::Attorney),
+            ("lateral", UserRole::Attorney),
+            ("para", UserRole::Paralegal),
+        ] {
+            access
+                .add_user(User {
+                    user_id: user_id.to_string(),
+                    email: format!("{}@firm.example", user_id),
+                    bar_number: (role == UserRole::Attorney).then(|| "12345".to_string()),
+                    role,
+                    jurisdiction: Some("WA".to_string()),
+                    supervisor_id: None,
+                    permissions: HashSet::new(),
+                    created_at: Utc::now(),
+                    last_login: None,
+                    active: true,
+                })
+                .await
+                .unwrap();
+        }
+        access
+    }
+
+    #[tokio::test]
+    async fn test_access_list_limits_matter_to_its_team() {
+        let matters = firm().await.matters();
+        assert!(matters.can_access_matter("para", 7).await);
+
+        matters.grant_matter_access(7, "partner", "partner").await.unwrap();
+        matters.grant_matter_access(7, "para", "partner").await.unwrap();
+        assert_eq!(matters.matter_team(7).await.unwrap(), vec!["para", "partner"]);
+        assert!(!matters.can_access_matter("lateral", 7).await);
+
+        // Off the team, so can't add themselves either
+        assert!(matters.grant_matter_access(7, "lateral", "lateral").await.is_err());
+        assert!(matters.grant_matter_access(7, "para", "para").await.is_err());
+
+        matters.revoke_matter_access(7, "para", "partner").await.unwrap();
+        assert!(!matters.can_access_matter("para", 7).await);
+    }
+
+    #[tokio::test]
+    async fn test_emptied_access_list_keeps_matter_closed() {
+        let access = firm().await;
+        let matters = access.matters();
+        matters.grant_matter_access(9, "partner", "partner").await.unwrap();
+        // Revoking yourself is the only way to empty the list
+        matters.revoke_matter_access(9, "partner", "partner").await.unwrap();
+
+        assert!(matters.matter_team(9).await.unwrap().is_empty());
+        for user_id in ["partner", "lateral", "para"] {
+            assert!(!matters.can_access_matter(user_id, 9).await);
+        }
+        assert!(!matters.scope("para").await.unwrap().allows(Some(9)));
+    }
+
+    #[tokio::test]
+    async fn test_scope_denies_walled_and_restricted_matters() {
+        let matters = firm().await.matters();
+        matters.grant_matter_access(1, "partner", "partner").await.unwrap();
+        matters
+            .create_wall("Screen", "Prior representation", "partner", &["lateral".to_string()], &[2])
+            .await
+            .unwrap();
+
+        let scope = matters.scope("lateral").await.unwrap();
+        assert!(!scope.allows(Some(1)) && !scope.allows(Some(2)));
+        assert!(scope.allows(Some(3)) && scope.allows(None));
+        assert_eq!(scope.sql_filter("case_id"), "(case_id IS NULL OR case_id NOT IN (1, 2))");
+
+        let partner = matters.scope("partner").await.unwrap();
+        assert!(partner.is_unrestricted());
+        assert_eq!(partner.sql_filter("case_id"), "1 = 1");
+    }
+
+    #[tokio::test]
+    async fn test_ethical_wall_overrides_access_until_lifted() {
+        let matters = firm().await.matters();
+        matters.grant_matter_access(3, "partner", "partner").await.unwrap();
+        matters.grant_matter_access(3, "lateral", "partner").await.unwrap();
+
+        let wall = matters
+            .create_wall(
+                "Doe v. Roe screen",
+                "Represented Roe at prior firm",
+                "partner",
+                &["lateral".to_string()],
+                &[3, 4],
+            )
+            .await
+            .unwrap();
+        assert!(!matters.can_access_matter("lateral", 3).await);
+        assert!(!matters.can_access_matter("lateral", 4).await);
+        assert!(matters.can_access_matter("lateral", 5).await);
+        assert!(matters.grant_matter_access(3, "lateral", "partner").await.is_err());
+        assert_eq!(matters.walls_for_matter(4).await.unwrap()[0].members, vec!["lateral"]);
+
+        matters.add_wall_member(&wall.wall_id, "para", "partner").await.unwrap();
+        assert!(!matters.can_access_matter("para", 4).await);
+        assert!(matters.lift_wall(&wall.wall_id, "para").await.is_err());
+
+        matters.lift_wall(&wall.wall_id, "partner").await.unwrap();
+        assert!(matters.can_access_matter("para", 4).await);
+        // The wall dropped the lateral hire from the team; lifting it doesn't restore access
+        assert!(!matters.can_access_matter("lateral", 3).await);
+    }
+
+    #[tokio::test]
+    async fn test_wall_changes_need_an_unscreened_attorney_on_its_matters() {
+        let matters = firm().await.matters();
+        let wall = matters
+            .create_wall("Screen", "Prior representation", "partner", &["lateral".to_string()], &[6])
+            .await
+            .unwrap();
+
+        // The screened attorney can't lift the wall or pad it with others
+        assert!(matters.lift_wall(&wall.wall_id, "lateral").await.is_err());
+        assert!(matters.add_wall_member(&wall.wall_id, "para", "lateral").await.is_err());
+        assert!(!matters.can_access_matter("lateral", 6).await);
+        assert!(matters.can_access_matter("para", 6).await);
+
+        // Nor can an attorney left off a matter's access list
+        matters.grant_matter_access(8, "partner", "partner").await.unwrap();
+        let restricted = matters
+            .create_wall("Second screen", "Conflict", "partner", &["para".to_string()], &[8])
+            .await
+            .unwrap();
+        assert!(matters.lift_wall(&restricted.wall_id, "lateral").await.is_err());
+        assert!(matters
+            .create_wall("Third screen", "Conflict", "lateral", &["para".to_string()], &[8])
+            .await
+            .is_err());
+
+        matters.lift_wall(&restricted.wall_id, "partner").await.unwrap();
+    }
+}
@@ -0,0 +1,700 @@
+//! Multi-factor Authentication Module for MoodBridge_Rust
+//!
+//! Users enroll an authenticator app with an RFC 6238 TOTP secret, kept in the
+//! KMS rather than the access database. A session is marked verified when its
+//! owner enters a current code or a one-time recovery code. With
+//! `SecurityConfig::require_mfa` every session must be verified before it can
+//! use the API; operations that can't be undone or that release the audit
+//! trail additionally need a verification from the last few minutes (step-up).
+
+use crate::auth::kms::KmsProvider;
+use crate::legal::access_control::{db_error, token_hash, AccessController, UserRole};
+use crate::legal::LegalOperationType;
+use chrono::{DateTime, Duration, Utc};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+use std::sync::Arc;
+
+/// Digits in a TOTP code
+pub const TOTP_DIGITS: u32 = 6;
+
+/// Seconds each TOTP code is valid for
+pub const TOTP_PERIOD_SECONDS: i64 = 30;
+
+/// Time steps either side of now that are accepted, for clocks that drift
+pub const TOTP_SKEW_STEPS: i64 = 1;
+
+/// Recovery codes issued at enrollment
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// How long a verification satisfies step-up for sensitive operations
+pub const STEP_UP_WINDOW_MINUTES: i64 = 5;
+
+/// Failed codes allowed before verification is locked
+const MAX_FAILED_ATTEMPTS: i64 = 5;
+const LOCKOUT_MINUTES: i64 = 15;
+
+/// Issuer shown in authenticator apps
+const TOTP_ISSUER: &str = "MoodBridge";
+
+/// 160-bit secrets, the HMAC-SHA1 block the RFC recommends
+const SECRET_BYTES: usize = 20;
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Operations that always need a recent verification, whatever `require_mfa` says
+pub fn requires_step_up(operation_type: &LegalOperationType) -> bool {
+    matches!(operation_type,
+        LegalOperationType::AuditLogExport |
+        LegalOperationType::ExhibitDeletion
+    )
+}
+
+/// KMS holding TOTP secrets
+#[derive(Clone)]
+pub struct SecretStore(Arc<dyn KmsProvider>);
+
+impl SecretStore {
+    pub fn new(kms: Arc<dyn KmsProvider>) -> Self {
+        Self(kms)
+    }
+}
+
+impl std::fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretStore")
+    }
+}
+
+/// A new TOTP secret, shown to the user once
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaEnrollment {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub provisioning_uri: String,
+}
+
+/// How a session was verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MfaMethod {
+    Totp,
+    RecoveryCode,
+}
+
+/// What a session still has to do before an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MfaChallenge {
+    /// The user has no confirmed authenticator yet
+    Enroll,
+    /// The session hasn't been verified
+    Verify,
+    /// The session's verification is too old for this operation
+    StepUp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaStatus {
+    pub required: bool,
+    pub enrolled: bool,
+    pub enrollment_pending: bool,
+    pub session_verified_at: Option<DateTime<Utc>>,
+    pub step_up_valid_until: Option<DateTime<Utc>>,
+    pub recovery_codes_remaining: i64,
+}
+
+/// TOTP enrollment and verification for the users of an `AccessController`
+#[derive(Debug, Clone)]
+pub struct Mfa {
+    access: AccessController,
+    secrets: Option<SecretStore>,
+}
+
+impl Mfa {
+    pub(crate) fn new(access: AccessController, secrets: Option<SecretStore>) -> Self {
+        Self { access, secrets }
+    }
+
+    fn secrets(&self) -> Result<&Arc<dyn KmsProvider>, String> {
+        self.secrets
+            .as_ref()
+            .map(|store| &store.0)
+            .ok_or_else(|| "MFA is unavailable: no key store is configured".to_string())
+    }
+
+    /// Start enrolling an authenticator; nothing changes until it is confirmed
+    ///
+    /// Replacing an existing authenticator needs a session verified in the
+    /// step-up window, e.g. with a recovery code after losing a phone.
+    pub async fn begin_enrollment(&self, user_id: &str, token: &str) -> Result<MfaEnrollment, String> {
+        let user = self.access.get_user(user_id).await?.ok_or_else(|| "User not found".to_string())?;
+        if self.is_enrolled(user_id).await? && !self.stepped_up(token).await? {
+            return Err("Verify with a current code before replacing your authenticator".to_string());
+        }
+
+        let mut secret = [0u8; SECRET_BYTES];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| "System random number generator failed".to_string())?;
+        let secret = base32_encode(&secret);
+        self.secrets()?
+            .encrypt_and_store(&pending_secret_key(user_id), &secret)
+            .await
+            .map_err(|e| format!("Failed to store MFA secret: {}", e))?;
+
+        sqlx::query("INSERT OR IGNORE INTO access_mfa (user_id, enrolled_at) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+
+        let label = format!("{}:{}", TOTP_ISSUER, user.email);
+        let provisioning_uri = format!(
+            "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            urlencoding::encode(&label),
+            secret,
+            urlencoding::encode(TOTP_ISSUER),
+            TOTP_DIGITS,
+            TOTP_PERIOD_SECONDS
+        );
+        Ok(MfaEnrollment { secret, provisioning_uri })
+    }
+
+    /// Confirm a pending enrollment with a code from the new authenticator
+    ///
+    /// Returns fresh recovery codes; any earlier ones stop working. Only their
+    /// hashes are kept, so this is the one time they can be shown.
+    pub async fn confirm_enrollment(&self, user_id: &str, token: &str, code: &str) -> Result<Vec<String>, String> {
+        self.check_lockout(user_id).await?;
+        let pending = self
+            .secrets()?
+            .retrieve_and_decrypt(&pending_secret_key(user_id))
+            .await
+            .map_err(|_| "No enrollment is pending".to_string())?;
+        let secret = base32_decode(&pending).ok_or_else(|| "Stored MFA secret is corrupt".to_string())?;
+
+        let Some(step) = matching_step(&secret, code, Utc::now().timestamp(), i64::MIN) else {
+            self.record_failure(user_id).await?;
+            return Err("Invalid code".to_string());
+        };
+
+        let kms = self.secrets()?;
+        kms.encrypt_and_store(&secret_key(user_id), &pending)
+            .await
+            .map_err(|e| format!("Failed to store MFA secret: {}", e))?;
+        if let Err(e) = kms.delete_key(&pending_secret_key(user_id)).await {
+            tracing::warn!("Failed to remove pending MFA secret for {}: {}", user_id, e);
+        }
+
+        sqlx::query(
+            "UPDATE access_mfa SET confirmed_at = ?, last_step = ?, failed_attempts = 0, locked_until = NULL
+             WHERE user_id = ?",
+        )
+        .bind(Utc::now())
+        .bind(step)
+        .bind(user_id)
+        .execute(self.access.pool())
+        .await
+        .map_err(db_error)?;
+
+        let codes = self.replace_recovery_codes(user_id).await?;
+        self.mark_verified(user_id, token).await?;
+        tracing::info!("User {} enrolled an authenticator", user_id);
+        Ok(codes)
+    }
+
+    /// Verify a session with a TOTP code or an unused recovery code
+    ///
+    /// Each TOTP time step is accepted once, so an observed code can't be replayed.
+    pub async fn verify(&self, user_id: &str, token: &str, code: &str) -> Result<MfaMethod, String> {
+        if !self.is_enrolled(user_id).await? {
+            return Err("No authenticator is enrolled".to_string());
+        }
+        self.check_lockout(user_id).await?;
+
+        let method = if is_recovery_code(code) {
+            let used = sqlx::query(
+                "UPDATE access_recovery_codes SET used_at = ?
+                 WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+            )
+            .bind(Utc::now())
+            .bind(user_id)
+            .bind(recovery_code_hash(code))
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+            (used.rows_affected() == 1).then_some(MfaMethod::RecoveryCode)
+        } else {
+            self.verify_totp(user_id, code).await?.then_some(MfaMethod::Totp)
+        };
+
+        let Some(method) = method else {
+            self.record_failure(user_id).await?;
+            return Err("Invalid code".to_string());
+        };
+        sqlx::query("UPDATE access_mfa SET failed_attempts = 0 WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        self.mark_verified(user_id, token).await?;
+        if method == MfaMethod::RecoveryCode {
+            tracing::warn!("User {} signed in with a recovery code", user_id);
+        }
+        Ok(method)
+    }
+
+    async fn verify_totp(&self, user_id: &str, code: &str) -> Result<bool, String> {
+        let stored = self
+            .secrets()?
+            .retrieve_and_decrypt(&secret_key(user_id))
+            .await
+            .map_err(|e| format!("Failed to load MFA secret: {}", e))?;
+        let secret = base32_decode(&stored).ok_or_else(|| "Stored MFA secret is corrupt".to_string())?;
+
+        let last_step: i64 = sqlx::query_scalar("SELECT last_step FROM access_mfa WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        let Some(step) = matching_step(&secret, code, Utc::now().timestamp(), last_step) else {
+            return Ok(false);
+        };
+
+        // Conditional so two concurrent requests can't both spend the same step
+        let updated = sqlx::query("UPDATE access_mfa SET last_step = ? WHERE user_id = ? AND last_step < ?")
+            .bind(step)
+            .bind(user_id)
+            .bind(step)
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        Ok(updated.rows_affected() == 1)
+    }
+
+    /// Issue new recovery codes; needs a session in the step-up window
+    pub async fn regenerate_recovery_codes(&self, user_id: &str, token: &str) -> Result<Vec<String>, String> {
+        if !self.is_enrolled(user_id).await? {
+            return Err("No authenticator is enrolled".to_string());
+        }
+        if !self.stepped_up(token).await? {
+            return Err("Verify with a current code before generating recovery codes".to_string());
+        }
+        self.replace_recovery_codes(user_id).await
+    }
+
+    /// Remove a user's authenticator so they can enroll again, e.g. after losing
+    /// both their phone and recovery codes; only another attorney or an
+    /// administrator can do this
+    pub async fn reset(&self, user_id: &str, reset_by: &str) -> Result<(), String> {
+        let actor = self.access.get_user(reset_by).await?.ok_or_else(|| "Resetting user not found".to_string())?;
+        if actor.user_id == user_id || !matches!(actor.role, UserRole::Attorney | UserRole::Administrator) {
+            return Err("Insufficient privileges to reset MFA".to_string());
+        }
+
+        let kms = self.secrets()?;
+        for key in [secret_key(user_id), pending_secret_key(user_id)] {
+            if kms.key_exists(&key).await.unwrap_or(false) {
+                kms.delete_key(&key).await.map_err(|e| format!("Failed to delete MFA secret: {}", e))?;
+            }
+        }
+
+        let mut tx = self.access.pool().begin().await.map_err(db_error)?;
+        for table in ["access_mfa", "access_recovery_codes", "access_mfa_sessions"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = ?", table))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::warn!("MFA for user {} reset by {}", user_id, reset_by);
+        Ok(())
+    }
+
+    pub async fn is_enrolled(&self, user_id: &str) -> Result<bool, String> {
+        self.access.ready().await?;
+        let confirmed: Option<Option<DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT confirmed_at FROM access_mfa WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(self.access.pool())
+                .await
+                .map_err(db_error)?;
+        Ok(matches!(confirmed, Some(Some(_))))
+    }
+
+    /// When the session behind `token` was last verified
+    pub async fn session_verified_at(&self, token: &str) -> Result<Option<DateTime<Utc>>, String> {
+        self.access.ready().await?;
+        sqlx::query_scalar("SELECT verified_at FROM access_mfa_sessions WHERE token_hash = ?")
+            .bind(token_hash(token))
+            .fetch_optional(self.access.pool())
+            .await
+            .map_err(db_error)
+    }
+
+    pub async fn status(&self, user_id: &str, token: &str) -> Result<MfaStatus, String> {
+        let enrolled = self.is_enrolled(user_id).await?;
+        let enrollment_pending = match &self.secrets {
+            Some(store) => store.0.key_exists(&pending_secret_key(user_id)).await.unwrap_or(false),
+            None => false,
+        };
+        let session_verified_at = self.session_verified_at(token).await?;
+        let step_up_valid_until = session_verified_at
+            .map(|verified_at| verified_at + Duration::minutes(STEP_UP_WINDOW_MINUTES))
+            .filter(|until| *until > Utc::now());
+        let recovery_codes_remaining: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM access_recovery_codes WHERE user_id = ? AND used_at IS NULL")
+                .bind(user_id)
+                .fetch_one(self.access.pool())
+                .await
+                .map_err(db_error)?;
+
+        Ok(MfaStatus {
+            required: self.access.mfa_required(),
+            enrolled,
+            enrollment_pending,
+            session_verified_at,
+            step_up_valid_until,
+            recovery_codes_remaining,
+        })
+    }
+
+    /// What the session must do before `operation_type`, or `None` if nothing
+    pub async fn challenge(
+        &self,
+        user_id: &str,
+        token: &str,
+        operation_type: &LegalOperationType,
+    ) -> Result<Option<MfaChallenge>, String> {
+        self.pending_challenge(user_id, token, requires_step_up(operation_type)).await
+    }
+
+    /// What the session must do before using routes outside any operation,
+    /// such as the user's own consent records
+    pub async fn session_challenge(&self, user_id: &str, token: &str) -> Result<Option<MfaChallenge>, String> {
+        self.pending_challenge(user_id, token, false).await
+    }
+
+    async fn pending_challenge(&self, user_id: &str, token: &str, step_up: bool) -> Result<Option<MfaChallenge>, String> {
+        if !step_up && !self.access.mfa_required() {
+            return Ok(None);
+        }
+
+        let Some(verified_at) = self.session_verified_at(token).await? else {
+            return Ok(Some(if self.is_enrolled(user_id).await? {
+                MfaChallenge::Verify
+            } else {
+                MfaChallenge::Enroll
+            }));
+        };
+        if step_up && Utc::now() - verified_at > Duration::minutes(STEP_UP_WINDOW_MINUTES) {
+            return Ok(Some(MfaChallenge::StepUp));
+        }
+        Ok(None)
+    }
+
+    async fn stepped_up(&self, token: &str) -> Result<bool, String> {
+        Ok(self
+            .session_verified_at(token)
+            .await?
+            .is_some_and(|verified_at| Utc::now() - verified_at <= Duration::minutes(STEP_UP_WINDOW_MINUTES)))
+    }
+
+    async fn mark_verified(&self, user_id: &str, token: &str) -> Result<(), String> {
+        sqlx::query("INSERT OR REPLACE INTO access_mfa_sessions (token_hash, user_id, verified_at) VALUES (?, ?, ?)")
+            .bind(token_hash(token))
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(self.access.pool())
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(&self, user_id: &str) -> Result<Vec<String>, String> {
+        let codes = (0..RECOVERY_CODE_COUNT).map(|_| new_recovery_code()).collect::<Result<Vec<_>, _>>()?;
+
+        let mut tx = self.access.pool().begin().await.map_err(db_error)?;
+        sqlx::query("DELETE FROM access_recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for code in &codes {
+            sqlx::query("INSERT INTO access_recovery_codes (user_id, code_hash, created_at) VALUES (?, ?, ?)")
+                .bind(user_id)
+                .bind(recovery_code_hash(code))
+                .bind(Utc::now())
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)?;
+        Ok(codes)
+    }
+
+    async fn check_lockout(&self, user_id: &str) -> Result<(), String> {
+        self.access.ready().await?;
+        let locked_until: Option<Option<DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT locked_until FROM access_mfa WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(self.access.pool())
+                .await
+                .map_err(db_error)?;
+        match locked_until.flatten() {
+            Some(until) if until > Utc::now() => Err(format!(
+                "Too many invalid codes; try again after {}",
+                until.format("%H:%M UTC")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    async fn record_failure(&self, user_id: &str) -> Result<(), String> {
+        let row = sqlx::query(
+            "UPDATE access_mfa SET failed_attempts = failed_attempts + 1 WHERE user_id = ? RETURNING failed_attempts",
+        )
+        .bind(user_id)
+        .fetch_optional(self.access.pool())
+        .await
+        .map_err(db_error)?;
+
+        if row.is_some_and(|row| row.get::<i64, _>("failed_attempts") >= MAX_FAILED_ATTEMPTS) {
+            sqlx::query("UPDATE access_mfa SET failed_attempts = 0, locked_until = ? WHERE user_id = ?")
+                .bind(Utc::now() + Duration::minutes(LOCKOUT_MINUTES))
+                .bind(user_id)
+                .execute(self.access.pool())
+                .await
+                .map_err(db_error)?;
+            tracing::warn!("MFA locked for user {} after {} invalid codes", user_id, MAX_FAILED_ATTEMPTS);
+        }
+        Ok(())
+    }
+}
+
+/// RFC 4226 HOTP value for `counter`
+fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let digest = hmac::sign(&key, &counter.to_be_bytes());
+    let digest = digest.as_ref();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    binary % 10u32.pow(digits)
+}
+
+/// RFC 6238 TOTP code for `unix_time`
+pub fn totp_code(secret: &[u8], unix_time: i64) -> String {
+    let step = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+    format!("{:0width$}", hotp(secret, step as u64, TOTP_DIGITS), width = TOTP_DIGITS as usize)
+}
+
+/// The time step `code` belongs to, within the skew window and after `last_step`
+fn matching_step(secret: &[u8], code: &str, unix_time: i64, last_step: i64) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let current = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step > last_step && *step >= 0)
+        .find(|step| {
+            let expected = format!("{:0width$}", hotp(secret, *step as u64, TOTP_DIGITS), width = TOTP_DIGITS as usize);
+            constant_time_eq(expected.as_bytes(), code.as_bytes())
+        })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn new_recovery_code() -> Result<String, String> {
+    let mut bytes = [0u8; RECOVERY_CODE_LENGTH];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "System random number generator failed".to_string())?;
+    let code: String = bytes.iter().map(|b| BASE32_ALPHABET[(b & 0x1f) as usize] as char).collect();
+    Ok(format!("{}-{}", &code[..5], &code[5..]).to_lowercase())
+}
+
+/// Recovery codes are letters and digits; TOTP codes are digits only
+fn is_recovery_code(input: &str) -> bool {
+    let normalized = normalize_recovery_code(input);
+    normalized.len() == RECOVERY_CODE_LENGTH && !normalized.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn normalize_recovery_code(input: &str) -> String {
+    input.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase()
+}
+
+fn recovery_code_hash(code: &str) -> String {
+    hex::encode(Sha256::digest(normalize_recovery_code(code).as_bytes()))
+}
+
+fn secret_key(user_id: &str) -> String {
+    format!("mfa:totp:{}", user_id)
+}
+
+fn pending_secret_key(user_id: &str) -> String {
+    format!("mfa:totp-pending:{}", user_id)
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    output
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET.iter().position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::kms::{KdfParams, KeystoreSecret, LocalKmsProvider};
+    use crate::legal::access_control::User;
+    use std::collections::HashSet;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rfc_6238_sha1_vectors() {
+        let secret = b"12345678901234567890";
+        for (time, expected) in [(59, 94287082), (1111111109, 7081804), (1234567890, 89005924), (2000000000, 69279037)] {
+            assert_eq!(hotp(secret, (time / 30) as u64, 8), expected);
+        }
+        assert_eq!(totp_code(secret, 59), "287082");
+        assert_eq!(base32_decode(&base32_encode(secret)).unwrap(), secret);
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_codes_within_skew_are_accepted_once() {
+        let secret = b"12345678901234567890";
+        let now = 1_700_000_000;
+        let previous = totp_code(secret, now - 30);
+        let step = now / 30;
+
+        assert_eq!(matching_step(secret, &previous, now, 0), Some(step - 1));
+        assert_eq!(matching_step(secret, &previous, now, step - 1), None);
+        assert_eq!(matching_step(secret, &totp_code(secret, now - 90), now, 0), None);
+        assert!(is_recovery_code("abcde-2345f") && !is_recovery_code("123456"));
+    }
+
+    async fn enrolled_controller() -> (AccessController, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let kdf = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let kms = LocalKmsProvider::open_with_kdf(dir.path().to_path_buf(), KeystoreSecret::passphrase("test"), kdf)
+            .await
+            .unwrap();
+        let access = AccessController::new().with_secret_store(Arc::new(kms));
+        access
+            .add_user(User {
+                user_id: "atty".to_string(),
+                email: "atty@firm.example".to_string(),
+                role: UserRole::Attorney,
+                bar_number: Some("WSBA-1".to_string()),
+                jurisdiction: Some("WA".to_string()),
+                supervisor_id: None,
+                permissions: HashSet::new(),
+                created_at: Utc::now(),
+                last_login: None,
+                active: true,
+            })
+            .await
+            .unwrap();
+        (access, dir)
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_recovery_codes_and_lockout() {
+        let (access, _dir) = enrolled_controller().await;
+        let mfa = access.mfa();
+        let token = access.create_auth_token("atty").await.unwrap();
+
+        let enrollment = mfa.begin_enrollment("atty", &token).await.unwrap();
+        assert!(enrollment.provisioning_uri.starts_with("otpauth://totp/MoodBridge%3Aatty%40firm.example?secret="));
+        assert!(!mfa.is_enrolled("atty").await.unwrap());
+
+        let secret = base32_decode(&enrollment.secret).unwrap();
+        let codes = mfa
+            .confirm_enrollment("atty", &token, &totp_code(&secret, Utc::now().timestamp()))
+            .await
+            .unwrap();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(mfa.is_enrolled("atty").await.unwrap());
+
+        // A new session verifies with a recovery code, which then can't be reused
+        let second = access.create_auth_token("atty").await.unwrap();
+        assert_eq!(mfa.challenge("atty", &second, &LegalOperationType::AuditLogExport).await.unwrap(), Some(MfaChallenge::Verify));
+        assert_eq!(mfa.verify("atty", &second, &codes[0].to_uppercase()).await.unwrap(), MfaMethod::RecoveryCode);
+        assert_eq!(mfa.challenge("atty", &second, &LegalOperationType::AuditLogExport).await.unwrap(), None);
+        assert!(mfa.verify("atty", &second, &codes[0]).await.is_err());
+
+        // Repeated bad codes lock verification, even for a good recovery code
+        let third = access.create_auth_token("atty").await.unwrap();
+        for _ in 0..MAX_FAILED_ATTEMPTS - 1 {
+            assert!(mfa.verify("atty", &third, "000000").await.is_err());
+        }
+        let error = mfa.verify("atty", &third, &codes[1]).await;
+        assert!(error.is_err() && mfa.verify("atty", &third, &codes[2]).await.unwrap_err().starts_with("Too many"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_verification_needs_step_up_only_for_sensitive_operations() {
+        let (access, _dir) = enrolled_controller().await;
+        let access = access.with_mfa_required(true);
+        let mfa = access.mfa();
+        let token = access.create_auth_token("atty").await.unwrap();
+        let secret = base32_decode(&mfa.begin_enrollment("atty", &token).await.unwrap().secret).unwrap();
+        mfa.confirm_enrollment("atty", &token, &totp_code(&secret, Utc::now().timestamp()))
+            .await
+            .unwrap();
+
+        sqlx::query("UPDATE access_mfa_sessions SET verified_at = ?")
+            .bind(Utc::now() - Duration::minutes(STEP_UP_WINDOW_MINUTES + 1))
+            .execute(access.pool())
+            .await
+            .unwrap();
+        assert_eq!(mfa.challenge("atty", &token, &LegalOperationType::SemanticSearch).await.unwrap(), None);
+        assert_eq!(
+            mfa.challenge("atty", &token, &LegalOperationType::ExhibitDeletion).await.unwrap(),
+            Some(MfaChallenge::StepUp)
+        );
+        assert!(mfa.regenerate_recovery_codes("atty", &token).await.is_err());
+        assert!(mfa.begin_enrollment("atty", &token).await.is_err());
+    }
+}
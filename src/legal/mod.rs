@@ -14,11 +14,20 @@ use chrono::{DateTime, Utc};
 pub mod disclaimers;
 pub mod consent;
 pub mod access_control;
+pub mod access_middleware;
 pub mod audit_log;
+pub mod audit_export;
+pub mod audit_store;
 pub mod compliance_check;
+pub mod compliance_layer;
+pub mod conflict_check;
+pub mod ethical_walls;
+pub mod mfa;
+pub mod pii;
+pub mod retention;
 
 /// Legal compliance status for operations
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ComplianceStatus {
     Compliant,
     RequiresConsent,
@@ -28,7 +37,7 @@ pub enum ComplianceStatus {
 }
 
 /// Types of legal operations that require special handling
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LegalOperationType {
     AILegalAdvice,
     VoiceRecording,
@@ -39,6 +48,9 @@ pub enum LegalOperationType {
     PresentationGeneration,
     SemanticSearch,
     CollaborationMetrics,
+    AuditLogExport,
+    ExhibitDeletion,
+    RecordDisposition,
 }
 
 /// User consent record
@@ -49,7 +61,10 @@ pub struct ConsentRecord {
     pub consent_given: bool,
     pub timestamp: DateTime<Utc>,
     pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
     pub consent_text: String,
+    /// Consent form version the user was shown, if known
+    pub form_version: Option<u32>,
     pub attorney_supervised: bool,
     pub disclaimer_acknowledged: bool,
 }
@@ -68,7 +83,7 @@ pub struct AuditLogEntry {
 }
 
 /// Classification of data being processed
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DataClassification {
     PublicData,
     InternalUse,
@@ -91,6 +106,9 @@ pub struct ComplianceResult {
     pub additional_requirements: Vec<String>,
 }
 
+/// Requirement reported when an operation is refused because it can't be audited
+pub const AUDIT_LOG_UNAVAILABLE: &str = "Audit log unavailable";
+
 /// Main legal compliance checker
 pub struct LegalComplianceEngine {
     consent_manager: consent::ConsentManager,
@@ -107,13 +125,78 @@ impl LegalComplianceEngine {
         }
     }
 
+    /// Use `audit_logger`, e.g. one backed by a persistent `AuditStore`
+    pub fn with_audit_logger(mut self, audit_logger: audit_log::AuditLogger) -> Self {
+        self.audit_logger = audit_logger;
+        self
+    }
+
+    /// Use `consent_manager`, e.g. one whose ledger is in the application database
+    pub fn with_consent_manager(mut self, consent_manager: consent::ConsentManager) -> Self {
+        self.consent_manager = consent_manager;
+        self
+    }
+
+    /// Use `access_controller`, e.g. one opened on the application database
+    pub fn with_access_controller(mut self, access_controller: access_control::AccessController) -> Self {
+        self.access_controller = access_controller;
+        self
+    }
+
+    /// Consent ledger consulted by `check_compliance`
+    pub fn consent_manager(&self) -> &consent::ConsentManager {
+        &self.consent_manager
+    }
+
     /// Check if an operation is legally compliant
+    ///
+    /// Every decision, allowed or not, is written to the audit log. An allowed
+    /// operation that can't be audited is refused.
     pub async fn check_compliance(
         &self,
         user_id: &str,
         operation_type: LegalOperationType,
         data_classification: DataClassification,
-        context: HashMap<String, serde_json::Value>,
+        mut context: HashMap<String, serde_json::Value>,
+    ) -> ComplianceResult {
+        let mut result = self.evaluate(user_id, &operation_type, &data_classification).await;
+
+        context.insert("allowed".to_string(), serde_json::Value::Bool(result.allowed));
+        if !result.allowed {
+            context.insert("denied_because".to_string(), serde_json::json!(result.additional_requirements));
+        }
+        let audit_entry = AuditLogEntry {
+            entry_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            operation_type,
+            timestamp: Utc::now(),
+            operation_details: context,
+            compliance_status: result.status.clone(),
+            attorney_review_required: result.attorney_review_required,
+            data_processed: data_classification,
+        };
+
+        // Log the operation; an operation that can't be audited doesn't go ahead
+        if let Err(e) = self.audit_logger.log_operation(&audit_entry).await {
+            tracing::error!("Failed to record audit log entry: {}", e);
+            if result.allowed {
+                result.allowed = false;
+                result.status = ComplianceStatus::Prohibited;
+                result.additional_requirements.push(AUDIT_LOG_UNAVAILABLE.to_string());
+            }
+            return result;
+        }
+        result.audit_log_entry = Some(audit_entry);
+
+        result
+    }
+
+    /// Access, disclaimer and consent requirements for an operation
+    async fn evaluate(
+        &self,
+        user_id: &str,
+        operation_type: &LegalOperationType,
+        data_classification: &DataClassification,
     ) -> ComplianceResult {
         let mut result = ComplianceResult {
             allowed: false,
@@ -126,7 +209,7 @@ impl LegalComplianceEngine {
         };
 
         // Check access permissions
-        if !self.access_controller.has_permission(user_id, &operation_type).await {
+        if !self.access_controller.has_permission(user_id, operation_type).await {
             result.status = ComplianceStatus::Prohibited;
             result.additional_requirements.push("Insufficient access permissions".to_string());
             return result;
@@ -177,15 +260,15 @@ impl LegalComplianceEngine {
                 }
             }
             _ => {
-                result.required_disclaimers.push(disclaimers::GENERAL_DISCLAIMER.to_string());
-                result.consent_required = true;
+                result.required_disclaimers.push(disclaimers::for_operation(operation_type).to_string());
+                result.consent_required = consent::ConsentManager::requires_consent(operation_type);
             }
         }
 
         // Check consent status
         if result.consent_required {
             let consent_status = self.consent_manager
-                .check_consent(user_id, &operation_type)
+                .check_consent(user_id, operation_type)
                 .await;
             
             if !consent_status.consent_given {
@@ -202,66 +285,33 @@ impl LegalComplianceEngine {
         } else {
             ComplianceStatus::Compliant
         };
-
-        // Create audit log entry
-        let audit_entry = AuditLogEntry {
-            entry_id: uuid::Uuid::new_v4().to_string(),
-            user_id: user_id.to_string(),
-            operation_type: operation_type.clone(),
-            timestamp: Utc::now(),
-            operation_details: context,
-            compliance_status: result.status.clone(),
-            attorney_review_required: result.attorney_review_required,
-            data_processed: data_classification,
-        };
-
-        // Log the operation
-        self.audit_logger.log_operation(&audit_entry).await;
-        result.audit_log_entry = Some(audit_entry);
-
         result
     }
 
-    /// Record user consent for an operation
+    /// Record user consent for an operation against its current consent form
     pub async fn record_consent(
-        &mut self,
+        &self,
         user_id: &str,
         operation_type: LegalOperationType,
         consent_given: bool,
-        ip_address: Option<String>,
+        signature: consent::ConsentSignature,
         attorney_supervised: bool,
-    ) -> Result<(), String> {
+    ) -> Result<consent::ConsentLedgerEntry, String> {
         let consent_record = ConsentRecord {
             user_id: user_id.to_string(),
-            operation_type: operation_type.clone(),
+            consent_text: consent::default_consent_text(&operation_type).to_string(),
+            operation_type,
             consent_given,
             timestamp: Utc::now(),
-            ip_address,
-            consent_text: self.get_consent_text(&operation_type),
+            ip_address: signature.ip_address,
+            user_agent: signature.user_agent,
+            form_version: None,
             attorney_supervised,
             disclaimer_acknowledged: true,
         };
 
         self.consent_manager.record_consent(consent_record).await
     }
-
-    /// Get appropriate consent text for an operation
-    fn get_consent_text(&self, operation_type: &LegalOperationType) -> String {
-        match operation_type {
-            LegalOperationType::AILegalAdvice => {
-                "I understand that this AI system does not provide legal advice and that I should consult with a qualified attorney for legal matters. I acknowledge that this system is for informational purposes only.".to_string()
-            }
-            LegalOperationType::VoiceRecording => {
-                "I consent to the recording and processing of my voice for the purpose of this legal technology system. I understand that recordings may be stored and analyzed by AI systems.".to_string()
-            }
-            LegalOperationType::DocumentModification => {
-                "I understand that modifying legal documents through this system may have legal consequences. I acknowledge that proper legal review and chain of custody procedures should be followed.".to_string()
-            }
-            _ => {
-                "I acknowledge that I am using this legal technology system at my own discretion and understand the potential legal implications.".to_string()
-            }
-        }
-    }
 }
 
 /// Helper function to create compliance wrapper for operations
@@ -0,0 +1,534 @@
+//! PII Detection and Redaction Module for MoodBridge_Rust
+//!
+//! Finds and locates personal data in free text: social security numbers,
+//! card and bank account numbers, routing numbers, phone numbers, email
+//! addresses, dates of birth, street addresses and the names of minors.
+//! Numbers are validated (SSN area rules, Luhn, ABA checksums) so case numbers
+//! and exhibit references aren't flagged.
+//!
+//! Two ways to remove what is found:
+//! - redaction replaces each span with a fixed marker and can't be undone;
+//! - pseudonymisation swaps each value for a numbered token and keeps the
+//!   mapping in a `Pseudonymizer`, so a reply that uses the tokens can be
+//!   turned back into the original values locally.
+//!
+//! Minors' names are never restored: family-court material must not identify
+//! children, so their tokens stay in place even when re-identifying.
+
+use crate::ai::timeline_extraction::TimelineExtractor;
+use chrono::{Datelike, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Kinds of personal data the detector looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Ssn,
+    AccountNumber,
+    RoutingNumber,
+    PhoneNumber,
+    Email,
+    DateOfBirth,
+    MinorName,
+    Address,
+}
+
+impl PiiKind {
+    /// Label used in redaction markers and pseudonym tokens
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ssn => "SSN",
+            Self::AccountNumber => "ACCOUNT",
+            Self::RoutingNumber => "ROUTING",
+            Self::PhoneNumber => "PHONE",
+            Self::Email => "EMAIL",
+            Self::DateOfBirth => "DOB",
+            Self::MinorName => "MINOR",
+            Self::Address => "ADDRESS",
+        }
+    }
+}
+
+/// How detected PII is removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiMode {
+    /// Irreversible `[REDACTED SSN]` markers
+    #[default]
+    Redact,
+    /// Numbered tokens that a `Pseudonymizer` can map back
+    Pseudonymize,
+}
+
+/// A located piece of personal data; `start` and `end` are byte offsets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub start: usize,
+    pub end: usize,
+    pub value: String,
+}
+
+/// Text with PII removed, and what was removed where (without the values)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactedText {
+    pub text: String,
+    pub counts: BTreeMap<PiiKind, usize>,
+}
+
+impl RedactedText {
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+/// Words that follow a cue like "child" without being a name
+const NOT_NAMES: &[&str] = &[
+    "The", "A", "An", "This", "That", "His", "Her", "Their", "Our", "My", "Your", "Support", "Custody",
+    "Protective", "Services", "Service", "Welfare", "Care", "Advocate", "Abuse", "Protection", "Court",
+    "Visitation", "Placement", "Of", "In", "And", "Or", "Is", "Was", "Has", "Had", "Will", "Shall",
+    "Family", "Guardian", "Ad", "Litem", "Parenting", "Plan", "Order", "Judge", "County", "State",
+];
+
+/// Regex-based PII detector
+pub struct PiiDetector {
+    ssn: Regex,
+    card: Regex,
+    bank_account: Regex,
+    routing: Regex,
+    phone: Regex,
+    email: Regex,
+    address: Regex,
+    po_box: Regex,
+    minor_cue: Regex,
+    name_with_age: Regex,
+    name_before: Regex,
+    dates: TimelineExtractor,
+}
+
+impl Default for PiiDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PiiDetector {
+    pub fn new() -> Self {
+        const NAME: &str = r"[A-Z][a-z]+(?:[-'][A-Z]?[a-z]+)?";
+        Self {
+            ssn: Regex::new(r"(?i)(\bssn|social security(?: number| no\.?)?)?[\s:#]*\b(\d{3})([- ]?)(\d{2})([- ]?)(\d{4})\b").unwrap(),
+            card: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap(),
+            bank_account: Regex::new(
+                r"(?i)\b(?:account|acct|a/c)\.?\s*(?:number|num|no\.?|#)?\s*(?:ending in\s*)?[:#]?\s*(\d(?:[ -]?\d){3,16})\b",
+            )
+            .unwrap(),
+            routing: Regex::new(r"(?i)\b(?:routing|aba|rtn)\s*(?:transit\s*)?(?:number|num|no\.?|#)?\s*[:#]?\s*(\d{9})\b").unwrap(),
+            phone: Regex::new(r"(?:\+?1[\s.-]?)?(?:\(([2-9]\d{2})\)\s?|\b([2-9]\d{2})[\s.-])([2-9]\d{2})[\s.-](\d{4})\b").unwrap(),
+            email: Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b").unwrap(),
+            address: Regex::new(
+                r"\b\d{1,6}\s+(?:[NSEW]\.?\s+)?(?:[A-Z0-9][A-Za-z0-9'-]*\s+){0,4}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Court|Ct|Way|Place|Pl|Terrace|Ter|Circle|Cir|Parkway|Pkwy|Highway|Hwy|Loop|Trail|Trl)\b\.?(?:,?\s*(?:Apt|Apartment|Unit|Suite|Ste|#)\.?\s*[A-Za-z0-9-]+)?(?:,\s*[A-Z][A-Za-z .'-]*,\s*[A-Z]{2}\s+\d{5}(?:-\d{4})?)?",
+            )
+            .unwrap(),
+            po_box: Regex::new(r"(?i)\bP\.?\s*O\.?\s*Box\s+\d+\b").unwrap(),
+            minor_cue: Regex::new(&format!(
+                r"(?i:\b(?:minor child(?:ren)?|minor|child(?:ren)?|son|daughter|stepson|stepdaughter|grandson|granddaughter|juvenile|infant)\b)[\s,:]+({n}(?:\s+(?:[A-Z]\.|{n})){{0,2}})",
+                n = NAME
+            ))
+            .unwrap(),
+            name_with_age: Regex::new(&format!(
+                r"\b({n}(?:\s+(?:[A-Z]\.|{n})){{0,2}})\s*(?:\(\s*(?:[Aa]ge[d]?\s*)?(\d{{1,2}})(?:\s*(?:years?\s*old|y/?o|yrs?))?\s*\)|,\s*(?:[Aa]ge[d]?\s+(\d{{1,2}})|(\d{{1,2}})(?:\s*years?\s*old|\s*y/?o|-year-old)))",
+                n = NAME
+            ))
+            .unwrap(),
+            name_before: Regex::new(&format!(r"({n}(?:\s+(?:[A-Z]\.|{n})){{0,2}})\s*[,(]?\s*$", n = NAME)).unwrap(),
+            dates: TimelineExtractor::new(),
+        }
+    }
+
+    /// Every PII span in `text`, in order and without overlaps
+    pub fn detect(&self, text: &str) -> Vec<PiiMatch> {
+        self.detect_with(text, &[])
+    }
+
+    /// As `detect`, also treating `known_minors` as minors' names
+    fn detect_with(&self, text: &str, known_minors: &[String]) -> Vec<PiiMatch> {
+        let mut found = Vec::new();
+        let mut push = |kind, start, end| found.push((kind, start, end));
+
+        for caps in self.ssn.captures_iter(text) {
+            let (area, group, serial) = (&caps[2], &caps[4], &caps[6]);
+            let separated = !caps[3].is_empty() && caps[3] == caps[5];
+            // Nine bare digits are only an SSN when the text says so
+            if (separated || caps.get(1).is_some()) && ssn_valid(area, group, serial) {
+                push(PiiKind::Ssn, caps.get(2).unwrap().start(), caps.get(6).unwrap().end());
+            }
+        }
+        for m in self.card.find_iter(text) {
+            let digits: String = m.as_str().chars().filter(char::is_ascii_digit).collect();
+            if (13..=19).contains(&digits.len()) && luhn_valid(&digits) {
+                push(PiiKind::AccountNumber, m.start(), m.end());
+            }
+        }
+        for caps in self.bank_account.captures_iter(text) {
+            let m = caps.get(1).unwrap();
+            push(PiiKind::AccountNumber, m.start(), m.end());
+        }
+        for caps in self.routing.captures_iter(text) {
+            let m = caps.get(1).unwrap();
+            if aba_valid(m.as_str()) {
+                push(PiiKind::RoutingNumber, m.start(), m.end());
+            }
+        }
+        for m in self.phone.find_iter(text) {
+            push(PiiKind::PhoneNumber, m.start(), m.end());
+        }
+        for m in self.email.find_iter(text) {
+            push(PiiKind::Email, m.start(), m.end());
+        }
+        for m in self.address.find_iter(text).chain(self.po_box.find_iter(text)) {
+            push(PiiKind::Address, m.start(), m.end());
+        }
+
+        // Dates of birth, and the children they belong to
+        let mut minors: Vec<String> = known_minors.to_vec();
+        for (date, start, end) in self.dates.find_dates(text) {
+            let before = &text[floor_char_boundary(text, start.saturating_sub(40))..start];
+            let lower = before.to_lowercase();
+            let Some(cue) = ["date of birth", "d.o.b.", "dob", "birthdate", "birth date", "born"]
+                .iter()
+                .filter_map(|cue| lower.rfind(cue))
+                .max()
+            else {
+                continue;
+            };
+            // The cue must lead up to the date, not belong to an earlier sentence
+            let lead_in = lower[cue..].trim_start_matches("d.o.b.");
+            if lead_in.contains(['.', ';']) {
+                continue;
+            }
+            push(PiiKind::DateOfBirth, start, end);
+
+            let age = Utc::now().year() - date.year();
+            if age < 18 {
+                if let Some(caps) = self.name_before.captures(&before[..cue]) {
+                    minors.push(caps[1].to_string());
+                }
+            }
+        }
+        for caps in self.minor_cue.captures_iter(text) {
+            minors.push(caps[1].to_string());
+        }
+        for caps in self.name_with_age.captures_iter(text) {
+            let age = caps.get(2).or(caps.get(3)).or(caps.get(4)).and_then(|m| m.as_str().parse::<u32>().ok());
+            if age.is_some_and(|age| age < 18) {
+                minors.push(caps[1].to_string());
+            }
+        }
+        for name in minor_names(&minors) {
+            let pattern = Regex::new(&format!(r"\b{}\b", regex::escape(&name))).unwrap();
+            for m in pattern.find_iter(text) {
+                push(PiiKind::MinorName, m.start(), m.end());
+            }
+        }
+
+        // Earliest first; overlapping spans are merged into their union so no
+        // part of either is left behind, under the kind of the longest one
+        found.sort_by_key(|(_, start, end)| (*start, std::cmp::Reverse(*end)));
+        let mut merged: Vec<(PiiKind, usize, usize, usize)> = Vec::new();
+        for (kind, start, end) in found {
+            if let Some((last_kind, _, last_end, longest)) = merged.last_mut() {
+                if start < *last_end {
+                    if end - start > *longest {
+                        *last_kind = kind;
+                        *longest = end - start;
+                    }
+                    *last_end = (*last_end).max(end);
+                    continue;
+                }
+            }
+            merged.push((kind, start, end, end - start));
+        }
+        merged
+            .into_iter()
+            .map(|(kind, start, end, _)| PiiMatch { kind, start, end, value: text[start..end].to_string() })
+            .collect()
+    }
+
+    pub fn contains_pii(&self, text: &str) -> bool {
+        !self.detect(text).is_empty()
+    }
+
+    /// Replace every PII span with a `[REDACTED KIND]` marker
+    pub fn redact(&self, text: &str) -> RedactedText {
+        remove(text, self.detect(text), PiiMode::Redact, &mut Pseudonymizer::new())
+    }
+
+    /// Replace every PII span with a token from `pseudonyms`, reusing the
+    /// token for values seen before
+    pub fn pseudonymize(&self, text: &str, pseudonyms: &mut Pseudonymizer) -> RedactedText {
+        remove(text, self.detect(text), PiiMode::Pseudonymize, pseudonyms)
+    }
+
+    /// Remove PII from `text` the way `mode` says
+    pub fn protect(&self, text: &str, mode: PiiMode, pseudonyms: &mut Pseudonymizer) -> RedactedText {
+        remove(text, self.detect(text), mode, pseudonyms)
+    }
+
+    /// Remove PII from texts that belong together, such as the fields of one
+    /// exhibit or the messages of one prompt. A child identified as a minor in
+    /// any of them is removed from all of them.
+    pub fn protect_all<S: AsRef<str>>(
+        &self,
+        texts: &[S],
+        mode: PiiMode,
+        pseudonyms: &mut Pseudonymizer,
+    ) -> Vec<RedactedText> {
+        let mut minors: Vec<String> = texts
+            .iter()
+            .flat_map(|text| self.detect(text.as_ref()))
+            .filter(|m| m.kind == PiiKind::MinorName)
+            .map(|m| m.value)
+            .collect();
+        minors.sort();
+        minors.dedup();
+
+        texts
+            .iter()
+            .map(|text| {
+                let text = text.as_ref();
+                remove(text, self.detect_with(text, &minors), mode, pseudonyms)
+            })
+            .collect()
+    }
+}
+
+fn remove(text: &str, matches: Vec<PiiMatch>, mode: PiiMode, pseudonyms: &mut Pseudonymizer) -> RedactedText {
+    let counts = count_kinds(&matches);
+    let text = replace_spans(text, &matches, |m| match mode {
+        PiiMode::Redact => format!("[REDACTED {}]", m.kind.label()),
+        PiiMode::Pseudonymize => pseudonyms.token_for(m.kind, &m.value),
+    });
+    RedactedText { text, counts }
+}
+
+/// Detector shared by callers that don't need their own
+pub fn detector() -> &'static PiiDetector {
+    static DETECTOR: OnceLock<PiiDetector> = OnceLock::new();
+    DETECTOR.get_or_init(PiiDetector::new)
+}
+
+/// Token mapping for one pseudonymised exchange
+///
+/// Keep it only as long as the replies it must re-identify; it holds the
+/// original values.
+#[derive(Debug, Default)]
+pub struct Pseudonymizer {
+    tokens: HashMap<(PiiKind, String), String>,
+    originals: HashMap<String, String>,
+    counters: HashMap<PiiKind, usize>,
+}
+
+impl Pseudonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn token_for(&mut self, kind: PiiKind, value: &str) -> String {
+        if let Some(token) = self.tokens.get(&(kind, value.to_string())) {
+            return token.clone();
+        }
+        let counter = self.counters.entry(kind).or_insert(0);
+        *counter += 1;
+        let token = format!("[{}_{}]", kind.label(), counter);
+        self.tokens.insert((kind, value.to_string()), token.clone());
+        if kind != PiiKind::MinorName {
+            self.originals.insert(token.clone(), value.to_string());
+        }
+        token
+    }
+
+    /// Put the original values back in place of their tokens; minors' names
+    /// stay pseudonymised
+    pub fn reidentify(&self, text: &str) -> String {
+        let mut restored = text.to_string();
+        for (token, original) in &self.originals {
+            restored = restored.replace(token, original);
+        }
+        restored
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+/// Luhn check used by payment card numbers
+pub fn luhn_valid(digits: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        let Some(mut d) = c.to_digit(10) else {
+            return false;
+        };
+        if i % 2 == 1 {
+            d *= 2;
+            if d > 9 {
+                d -= 9;
+            }
+        }
+        sum += d;
+    }
+    !digits.is_empty() && sum % 10 == 0
+}
+
+/// ABA routing number checksum (weights 3, 7, 1)
+pub fn aba_valid(digits: &str) -> bool {
+    let values: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    if values.len() != 9 || digits.len() != 9 {
+        return false;
+    }
+    let sum: u32 = values.iter().zip([3, 7, 1].iter().cycle()).map(|(d, w)| d * w).sum();
+    sum.is_multiple_of(10)
+}
+
+/// SSA rules: no 000, 666 or 9xx area, no 00 group, no 0000 serial
+pub fn ssn_valid(area: &str, group: &str, serial: &str) -> bool {
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+/// Full names plus each first name long enough to be distinctive
+fn minor_names(found: &[String]) -> Vec<String> {
+    let mut names = HashSet::new();
+    for name in found {
+        let words: Vec<&str> = name.split_whitespace().filter(|w| !NOT_NAMES.contains(w)).collect();
+        if words.is_empty() || words.len() != name.split_whitespace().count() {
+            continue;
+        }
+        names.insert(words.join(" "));
+        if words[0].len() >= 3 {
+            names.insert(words[0].to_string());
+        }
+    }
+    let mut names: Vec<String> = names.into_iter().collect();
+    // Longest first so a full name isn't split by its first name
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names
+}
+
+fn count_kinds(matches: &[PiiMatch]) -> BTreeMap<PiiKind, usize> {
+    let mut counts = BTreeMap::new();
+    for m in matches {
+        *counts.entry(m.kind).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn replace_spans(text: &str, matches: &[PiiMatch], mut replacement: impl FnMut(&PiiMatch) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in matches {
+        out.push_str(&text[last..m.start]);
+        out.push_str(&replacement(m));
+        last = m.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<PiiKind> {
+        detector().detect(text).into_iter().map(|m| m.kind).collect()
+    }
+
+    #[test]
+    fn test_numbers_are_validated() {
+        assert_eq!(kinds("SSN 123-45-6789 on file"), vec![PiiKind::Ssn]);
+        assert!(kinds("Case no. 666-12-3456").is_empty());
+        assert!(kinds("Docket 123456789").is_empty());
+        assert_eq!(kinds("Card 4111 1111 1111 1111 was charged"), vec![PiiKind::AccountNumber]);
+        assert!(kinds("Card 4111 1111 1111 1112 was charged").is_empty());
+        assert_eq!(kinds("Routing number 021000021"), vec![PiiKind::RoutingNumber]);
+        assert!(kinds("Routing number 021000022").is_empty());
+        assert_eq!(kinds("Paid from account no. 0012-3344-55"), vec![PiiKind::AccountNumber]);
+    }
+
+    #[test]
+    fn test_contact_details_are_located() {
+        let text = "Call (503) 555-0142 or email jane.doe@example.com; she lives at 1420 NW Lovejoy St, Apt 3, Portland, OR 97209.";
+        let matches = detector().detect(text);
+        let kinds: Vec<PiiKind> = matches.iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, vec![PiiKind::PhoneNumber, PiiKind::Email, PiiKind::Address]);
+        assert_eq!(matches[1].value, "jane.doe@example.com");
+        assert_eq!(&text[matches[2].start..matches[2].end], "1420 NW Lovejoy St, Apt 3, Portland, OR 97209");
+    }
+
+    #[test]
+    fn test_overlapping_spans_are_redacted_together() {
+        let text = "Reach me at (503) 555-0142.jd@example.com today";
+        let matches = detector().detect(text);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].kind, PiiKind::Email);
+        assert_eq!(matches[0].value, "(503) 555-0142.jd@example.com");
+
+        let redacted = detector().redact(text).text;
+        assert!(!redacted.contains("503") && !redacted.contains("example.com"));
+    }
+
+    #[test]
+    fn test_minors_are_found_by_cue_age_and_date_of_birth() {
+        let birth_year = Utc::now().year() - 9;
+        let text = format!(
+            "The minor child, Emma Rose Hart, lives with Mother. Liam Hart (age 6) attends school. \
+             Noah Hart, DOB 03/14/{}, was present. Emma and Liam were interviewed. \
+             Mother, age 41, testified.",
+            birth_year
+        );
+        let redacted = detector().redact(&text);
+
+        assert!(!redacted.text.contains("Emma") && !redacted.text.contains("Liam") && !redacted.text.contains("Noah"));
+        assert!(redacted.text.contains("Mother, age 41"));
+        assert_eq!(redacted.counts[&PiiKind::MinorName], 5);
+        assert_eq!(redacted.counts[&PiiKind::DateOfBirth], 1);
+    }
+
+    #[test]
+    fn test_pseudonyms_are_consistent_and_reversible_except_for_minors() {
+        let mut pseudonyms = Pseudonymizer::new();
+        let text = "Mother's email is mom@example.com. The child Ava Cole emailed mom@example.com.";
+        let protected = detector().pseudonymize(text, &mut pseudonyms);
+
+        assert_eq!(protected.text, "Mother's email is [EMAIL_1]. The child [MINOR_1] emailed [EMAIL_1].");
+        assert_eq!(
+            pseudonyms.reidentify("Reply to [EMAIL_1] about [MINOR_1]"),
+            "Reply to mom@example.com about [MINOR_1]"
+        );
+    }
+
+    #[test]
+    fn test_minors_named_in_one_text_are_removed_from_all() {
+        let texts = ["Declaration regarding the minor child, Ava Cole.", "Ava told the evaluator about school."];
+        let protected = detector().protect_all(&texts, PiiMode::Redact, &mut Pseudonymizer::new());
+
+        assert_eq!(protected[1].text, "[REDACTED MINOR] told the evaluator about school.");
+        assert_eq!(detector().redact(texts[1]).text, texts[1]);
+    }
+
+    #[test]
+    fn test_ordinary_legal_text_is_untouched() {
+        let text = "On 3/14/2024 the court continued the hearing to 10:30 a.m. in Courtroom 4B under RCW 26.09.191.";
+        assert!(kinds(text).is_empty());
+        assert_eq!(detector().redact(text).text, text);
+    }
+}
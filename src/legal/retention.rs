@@ -0,0 +1,1038 @@
+//! Data Retention Module for MoodBridge_Rust
+//!
+//! Tracks when every imported file and stored record is due for disposition,
+//! using the retention period `import_wizard::classify_file` assigns or one
+//! derived from its `DataClassification`. Nothing is destroyed on the date
+//! alone: due items go to an attorney review queue, and only approved items
+//! are disposed of, each with a certificate recording what was destroyed, who
+//! approved it and a hash of the content.
+//!
+//! A legal hold on a matter suspends all of this for the matter's items.
+//! Placing a hold sends queued and approved items back to the schedule, and
+//! disposal re-checks holds immediately before anything is deleted.
+//!
+//! Requests only name what to schedule. The matter and the start of the
+//! retention period come from what the server recorded when the file was
+//! imported or the exhibit created, and files must lie under the import root.
+
+use crate::db::DbPool;
+use crate::import_wizard::classifier::{FileClassification, SensitivityLevel};
+use crate::import_wizard::config::ImportWizardConfig;
+use crate::legal::access_control::enum_key;
+use crate::legal::audit_log::AuditLogger;
+use crate::legal::{AuditLogEntry, ComplianceStatus, DataClassification, LegalOperationType};
+use crate::search::remove_indexed;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+const RETENTION_SCHEMA: &str = include_str!("../../data/retention.sql");
+
+/// Retention for files the classifier gives no period, e.g. ordinary media
+pub const DEFAULT_RETENTION_DAYS: u32 = 1095; // 3 years
+
+/// How far ahead of the disposition date items are sent for review
+pub const REVIEW_LEAD_DAYS: i64 = 30;
+
+/// What a scheduled item refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    /// A file on disk; `record_id` is its path
+    File,
+    /// A row in `exhibits` with its embeddings, transcript and index entries
+    Exhibit,
+}
+
+/// Where an item is in the retention lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionStatus {
+    Scheduled,
+    PendingReview,
+    Approved,
+    Destroyed,
+}
+
+/// An attorney's answer to a queued item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "decision")]
+pub enum RetentionDecision {
+    /// Destroy once the disposition date has passed
+    Approve,
+    /// Keep for `days` more and review again then
+    Extend { days: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionItem {
+    pub id: i64,
+    pub record_kind: RecordKind,
+    pub record_id: String,
+    pub case_id: Option<i64>,
+    pub data_classification: DataClassification,
+    pub retention_days: u32,
+    pub retain_from: DateTime<Utc>,
+    pub disposition_date: DateTime<Utc>,
+    pub status: RetentionStatus,
+    pub queued_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_notes: Option<String>,
+}
+
+/// What the server recorded about a file or exhibit belonging to a matter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatterLink {
+    pub record_kind: RecordKind,
+    /// Canonical path for files, row id for exhibits
+    pub record_id: String,
+    pub case_id: i64,
+    /// When the file was imported or the exhibit created; retention runs from here
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalHold {
+    pub hold_id: String,
+    pub case_id: i64,
+    pub reason: String,
+    pub placed_by: String,
+    pub placed_at: DateTime<Utc>,
+    pub released_by: Option<String>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+/// Record that an item was destroyed, and on whose authority
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispositionCertificate {
+    pub certificate_id: String,
+    pub schedule_id: i64,
+    pub record_kind: RecordKind,
+    pub record_id: String,
+    pub case_id: Option<i64>,
+    pub data_classification: DataClassification,
+    pub retention_days: u32,
+    pub disposition_date: DateTime<Utc>,
+    pub approved_by: String,
+    pub approved_at: DateTime<Utc>,
+    pub destroyed_by: String,
+    pub destroyed_at: DateTime<Utc>,
+    pub method: String,
+    /// SHA-256 of the destroyed content, when it could be read first
+    pub content_hash: Option<String>,
+    /// SHA-256 over every other field, to detect later edits
+    pub certificate_hash: String,
+}
+
+impl DispositionCertificate {
+    fn compute_hash(&self) -> String {
+        let mut unsigned = self.clone();
+        unsigned.certificate_hash = String::new();
+        let canonical = serde_json::to_vec(&unsigned).unwrap_or_default();
+        hex::encode(Sha256::digest(&canonical))
+    }
+
+    /// Whether the certificate still matches its hash
+    pub fn verify(&self) -> bool {
+        self.compute_hash() == self.certificate_hash
+    }
+}
+
+/// Retention period for a stored record of the given classification
+pub fn retention_days_for(data_classification: &DataClassification) -> u32 {
+    match data_classification {
+        DataClassification::AttorneyClientPrivileged
+        | DataClassification::WorkProduct
+        | DataClassification::Confidential => 2555, // 7 years
+        DataClassification::HealthInformation => 2190, // 6 years
+        DataClassification::PersonalIdentifiableInformation | DataClassification::InternalUse => DEFAULT_RETENTION_DAYS,
+        DataClassification::PublicData => 365,
+    }
+}
+
+/// `DataClassification` corresponding to a classifier sensitivity level
+pub fn classification_for(sensitivity: &SensitivityLevel) -> DataClassification {
+    match sensitivity {
+        SensitivityLevel::Public => DataClassification::PublicData,
+        SensitivityLevel::Internal => DataClassification::InternalUse,
+        SensitivityLevel::Confidential => DataClassification::Confidential,
+        SensitivityLevel::HighlyConfidential | SensitivityLevel::TopSecret => DataClassification::AttorneyClientPrivileged,
+    }
+}
+
+/// Retention schedule, legal holds and disposition certificates
+#[derive(Clone)]
+pub struct RetentionScheduler {
+    pool: DbPool,
+    schema: Arc<OnceCell<()>>,
+    audit_logger: Option<AuditLogger>,
+    import_root: PathBuf,
+}
+
+impl Default for RetentionScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetentionScheduler {
+    /// Scheduler kept in memory, for tests and tools
+    pub fn new() -> Self {
+        // A single connection that never closes keeps the in-memory database alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_lazy("sqlite::memory:")
+            .expect("in-memory SQLite URL is valid");
+        Self::with_pool(pool)
+    }
+
+    /// Scheduler on the application database, creating its tables if needed
+    ///
+    /// Exhibits are disposed of from the same database.
+    pub async fn open(pool: DbPool) -> Result<Self, String> {
+        let scheduler = Self::with_pool(pool);
+        scheduler.ready().await?;
+        Ok(scheduler)
+    }
+
+    fn with_pool(pool: DbPool) -> Self {
+        Self {
+            pool,
+            schema: Arc::new(OnceCell::new()),
+            audit_logger: None,
+            import_root: ImportWizardConfig::default().storage_paths.base_path,
+        }
+    }
+
+    /// Only files under `import_root` can be scheduled or destroyed
+    pub fn with_import_root(mut self, import_root: impl Into<PathBuf>) -> Self {
+        self.import_root = import_root.into();
+        self
+    }
+
+    /// Record every disposal in `audit_logger`
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    async fn ready(&self) -> Result<(), String> {
+        self.schema
+            .get_or_try_init(|| async {
+                sqlx::query(RETENTION_SCHEMA)
+                    .execute(&self.pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("Failed to create retention tables: {}", e))
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Schedule an imported file using its classification
+    pub async fn schedule_file(
+        &self,
+        file: &MatterLink,
+        classification: &FileClassification,
+    ) -> Result<RetentionItem, String> {
+        let retention_days = classification.retention_period_days.unwrap_or(DEFAULT_RETENTION_DAYS);
+        let data_classification = classification_for(&classification.sensitivity);
+        self.schedule(file, data_classification, retention_days).await
+    }
+
+    /// Schedule a stored record using the period for its classification
+    pub async fn schedule_record(
+        &self,
+        record: &MatterLink,
+        data_classification: DataClassification,
+    ) -> Result<RetentionItem, String> {
+        let retention_days = retention_days_for(&data_classification);
+        self.schedule(record, data_classification, retention_days).await
+    }
+
+    /// Add or reschedule an item; a rescheduled item starts over and needs a
+    /// fresh review
+    async fn schedule(
+        &self,
+        link: &MatterLink,
+        data_classification: DataClassification,
+        retention_days: u32,
+    ) -> Result<RetentionItem, String> {
+        self.ready().await?;
+        let (record_kind, record_id) = (&link.record_kind, link.record_id.as_str());
+        if let Some(existing) = self.find(record_kind, record_id).await? {
+            if existing.status == RetentionStatus::Destroyed {
+                return Err(format!("{:?} {} has already been destroyed", record_kind, record_id));
+            }
+        }
+
+        let disposition_date = link.linked_at + Duration::days(retention_days as i64);
+        sqlx::query(
+            "INSERT INTO retention_schedule \
+             (record_kind, record_id, case_id, data_classification, retention_days, retain_from, disposition_date, status) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT (record_kind, record_id) DO UPDATE SET \
+             case_id = excluded.case_id, data_classification = excluded.data_classification, \
+             retention_days = excluded.retention_days, retain_from = excluded.retain_from, \
+             disposition_date = excluded.disposition_date, status = excluded.status, \
+             queued_at = NULL, reviewed_by = NULL, reviewed_at = NULL, review_notes = NULL",
+        )
+        .bind(enum_key(record_kind))
+        .bind(record_id)
+        .bind(link.case_id)
+        .bind(enum_key(&data_classification))
+        .bind(retention_days as i64)
+        .bind(link.linked_at)
+        .bind(disposition_date)
+        .bind(enum_key(&RetentionStatus::Scheduled))
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        self.find(record_kind, record_id)
+            .await?
+            .ok_or_else(|| format!("{:?} {} was not scheduled", record_kind, record_id))
+    }
+
+    /// Record that the file at `file_path` was imported for a matter
+    pub async fn link_file(
+        &self,
+        file_path: impl AsRef<Path>,
+        case_id: i64,
+        imported_at: DateTime<Utc>,
+    ) -> Result<MatterLink, String> {
+        self.ready().await?;
+        let path = self
+            .confined(file_path.as_ref())
+            .await
+            .ok_or_else(|| format!("{} is not a file under the import root", file_path.as_ref().display()))?;
+        sqlx::query(
+            "INSERT INTO matter_files (file_path, case_id, imported_at) VALUES (?, ?, ?) \
+             ON CONFLICT (file_path) DO UPDATE SET case_id = excluded.case_id, imported_at = excluded.imported_at",
+        )
+        .bind(&path)
+        .bind(case_id)
+        .bind(imported_at)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(MatterLink {
+            record_kind: RecordKind::File,
+            record_id: path,
+            case_id,
+            linked_at: imported_at,
+        })
+    }
+
+    /// The matter an imported file belongs to
+    ///
+    /// `None` alike for paths outside the import root, missing files and files
+    /// never linked to a matter, so callers can't probe the filesystem.
+    pub async fn matter_file(&self, file_path: &str) -> Result<Option<MatterLink>, String> {
+        self.ready().await?;
+        let Some(path) = self.confined(Path::new(file_path)).await else {
+            return Ok(None);
+        };
+        let row = sqlx::query("SELECT case_id, imported_at FROM matter_files WHERE file_path = ?")
+            .bind(&path)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(row.map(|row| MatterLink {
+            record_kind: RecordKind::File,
+            record_id: path,
+            case_id: row.get("case_id"),
+            linked_at: row.get("imported_at"),
+        }))
+    }
+
+    /// The matter an exhibit belongs to, or `None` if it has none or doesn't exist
+    pub async fn matter_exhibit(&self, exhibit_id: i64) -> Result<Option<MatterLink>, String> {
+        let row = sqlx::query("SELECT case_id, created_at FROM exhibits WHERE id = ? AND case_id IS NOT NULL")
+            .bind(exhibit_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(row.map(|row| MatterLink {
+            record_kind: RecordKind::Exhibit,
+            record_id: exhibit_id.to_string(),
+            case_id: row.get("case_id"),
+            linked_at: row.get::<chrono::NaiveDateTime, _>("created_at").and_utc(),
+        }))
+    }
+
+    /// Canonical form of `path` if it is a regular file under the import root
+    ///
+    /// Canonicalizing first means `..` segments and symlinks can't lead outside it.
+    async fn confined(&self, path: &Path) -> Option<String> {
+        let root = tokio::fs::canonicalize(&self.import_root).await.ok()?;
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+        let is_file = tokio::fs::metadata(&path).await.ok()?.is_file();
+        (is_file && path.starts_with(&root)).then(|| path.to_str().map(str::to_string))?
+    }
+
+    /// The schedule entry for a record, if it has one
+    pub async fn find(&self, record_kind: &RecordKind, record_id: &str) -> Result<Option<RetentionItem>, String> {
+        self.ready().await?;
+        let row = sqlx::query("SELECT * FROM retention_schedule WHERE record_kind = ? AND record_id = ?")
+            .bind(enum_key(record_kind))
+            .bind(record_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        row.as_ref().map(item_from_row).transpose()
+    }
+
+    pub async fn get_item(&self, item_id: i64) -> Result<Option<RetentionItem>, String> {
+        self.ready().await?;
+        let row = sqlx::query("SELECT * FROM retention_schedule WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        row.as_ref().map(item_from_row).transpose()
+    }
+
+    /// Suspend disposition of everything in a matter
+    pub async fn place_hold(&self, case_id: i64, reason: &str, placed_by: &str) -> Result<LegalHold, String> {
+        self.ready().await?;
+        if reason.trim().is_empty() {
+            return Err("A legal hold needs a reason".to_string());
+        }
+        let hold = LegalHold {
+            hold_id: uuid::Uuid::new_v4().to_string(),
+            case_id,
+            reason: reason.to_string(),
+            placed_by: placed_by.to_string(),
+            placed_at: Utc::now(),
+            released_by: None,
+            released_at: None,
+        };
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query("INSERT INTO legal_holds (hold_id, case_id, reason, placed_by, placed_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(&hold.hold_id)
+            .bind(case_id)
+            .bind(&hold.reason)
+            .bind(&hold.placed_by)
+            .bind(hold.placed_at)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        // Reviews and approvals given before the hold no longer stand
+        let reset = sqlx::query(
+            "UPDATE retention_schedule SET status = ?, queued_at = NULL, reviewed_by = NULL, reviewed_at = NULL \
+             WHERE case_id = ? AND status IN (?, ?)",
+        )
+        .bind(enum_key(&RetentionStatus::Scheduled))
+        .bind(case_id)
+        .bind(enum_key(&RetentionStatus::PendingReview))
+        .bind(enum_key(&RetentionStatus::Approved))
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        tracing::warn!(
+            "Legal hold {} placed on case {} by {}; {} queued items returned to the schedule",
+            hold.hold_id,
+            case_id,
+            placed_by,
+            reset.rows_affected()
+        );
+        Ok(hold)
+    }
+
+    /// Lift a hold; the matter's items resume their schedule
+    pub async fn release_hold(&self, hold_id: &str, released_by: &str) -> Result<LegalHold, String> {
+        self.ready().await?;
+        let released = sqlx::query(
+            "UPDATE legal_holds SET released_by = ?, released_at = ? WHERE hold_id = ? AND released_at IS NULL",
+        )
+        .bind(released_by)
+        .bind(Utc::now())
+        .bind(hold_id)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        if released.rows_affected() == 0 {
+            return Err(format!("No active legal hold {}", hold_id));
+        }
+
+        let row = sqlx::query("SELECT * FROM legal_holds WHERE hold_id = ?")
+            .bind(hold_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(db_error)?;
+        let hold = hold_from_row(&row);
+        tracing::warn!("Legal hold {} on case {} released by {}", hold_id, hold.case_id, released_by);
+        Ok(hold)
+    }
+
+    /// Active holds, for one matter or all of them
+    pub async fn active_holds(&self, case_id: Option<i64>) -> Result<Vec<LegalHold>, String> {
+        self.ready().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM legal_holds WHERE released_at IS NULL AND (? IS NULL OR case_id = ?) ORDER BY placed_at",
+        )
+        .bind(case_id)
+        .bind(case_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(rows.iter().map(hold_from_row).collect())
+    }
+
+    /// The active hold preventing a record's destruction, if any
+    pub async fn hold_for(&self, record_kind: &RecordKind, record_id: &str) -> Result<Option<LegalHold>, String> {
+        let mut case_id = self.find(record_kind, record_id).await?.and_then(|item| item.case_id);
+        // Exhibits that were never scheduled are still held through their matter
+        if let (None, RecordKind::Exhibit, Ok(exhibit_id)) = (case_id, record_kind, record_id.parse()) {
+            case_id = self.matter_exhibit(exhibit_id).await?.map(|exhibit| exhibit.case_id);
+        }
+        let Some(case_id) = case_id else {
+            return Ok(None);
+        };
+        Ok(self.active_holds(Some(case_id)).await?.into_iter().next())
+    }
+
+    /// Send items coming due by `now + REVIEW_LEAD_DAYS` to the review queue,
+    /// skipping matters on hold
+    pub async fn queue_due_for_review(&self, now: DateTime<Utc>) -> Result<Vec<RetentionItem>, String> {
+        self.ready().await?;
+        let rows = sqlx::query(
+            "UPDATE retention_schedule SET status = ?, queued_at = ? \
+             WHERE status = ? AND disposition_date <= ? \
+             AND (case_id IS NULL OR case_id NOT IN (SELECT case_id FROM legal_holds WHERE released_at IS NULL)) \
+             RETURNING *",
+        )
+        .bind(enum_key(&RetentionStatus::PendingReview))
+        .bind(now)
+        .bind(enum_key(&RetentionStatus::Scheduled))
+        .bind(now + Duration::days(REVIEW_LEAD_DAYS))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        let queued = rows.iter().map(item_from_row).collect::<Result<Vec<_>, _>>()?;
+        if !queued.is_empty() {
+            tracing::info!("{} items sent to the retention review queue", queued.len());
+        }
+        Ok(queued)
+    }
+
+    /// Items waiting for an attorney, soonest disposition first
+    pub async fn review_queue(&self) -> Result<Vec<RetentionItem>, String> {
+        self.items_with_status(&RetentionStatus::PendingReview).await
+    }
+
+    /// Items approved for destruction that haven't been disposed of yet
+    pub async fn approved_items(&self) -> Result<Vec<RetentionItem>, String> {
+        self.items_with_status(&RetentionStatus::Approved).await
+    }
+
+    async fn items_with_status(&self, status: &RetentionStatus) -> Result<Vec<RetentionItem>, String> {
+        self.ready().await?;
+        let rows = sqlx::query("SELECT * FROM retention_schedule WHERE status = ? ORDER BY disposition_date, id")
+            .bind(enum_key(status))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+        rows.iter().map(item_from_row).collect()
+    }
+
+    /// Record an attorney's decision on a queued item
+    ///
+    /// Callers must make sure `reviewer_id` is an attorney; the HTTP routes
+    /// do so through the `RecordDisposition` permission.
+    pub async fn decide(
+        &self,
+        item_id: i64,
+        reviewer_id: &str,
+        decision: RetentionDecision,
+        notes: Option<String>,
+    ) -> Result<RetentionItem, String> {
+        let item = self
+            .get_item(item_id)
+            .await?
+            .ok_or_else(|| format!("No retention item {}", item_id))?;
+        if item.status != RetentionStatus::PendingReview {
+            return Err(format!("Retention item {} is not awaiting review", item_id));
+        }
+        if let Some(case_id) = item.case_id {
+            if !self.active_holds(Some(case_id)).await?.is_empty() {
+                return Err(format!("Case {} is under a legal hold", case_id));
+            }
+        }
+
+        let (status, disposition_date) = match decision {
+            RetentionDecision::Approve => (RetentionStatus::Approved, item.disposition_date),
+            RetentionDecision::Extend { days } => (
+                RetentionStatus::Scheduled,
+                item.disposition_date.max(Utc::now()) + Duration::days(days as i64),
+            ),
+        };
+        sqlx::query(
+            "UPDATE retention_schedule SET status = ?, disposition_date = ?, reviewed_by = ?, reviewed_at = ?, \
+             review_notes = ? WHERE id = ?",
+        )
+        .bind(enum_key(&status))
+        .bind(disposition_date)
+        .bind(reviewer_id)
+        .bind(Utc::now())
+        .bind(notes)
+        .bind(item_id)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        tracing::info!("Retention item {} marked {:?} by {}", item_id, status, reviewer_id);
+
+        self.get_item(item_id)
+            .await?
+            .ok_or_else(|| format!("No retention item {}", item_id))
+    }
+
+    /// Destroy approved items whose disposition date has passed and issue a
+    /// certificate for each
+    ///
+    /// Items that fail are logged and left approved for the next run.
+    pub async fn dispose_due(&self, now: DateTime<Utc>, destroyed_by: &str) -> Result<Vec<DispositionCertificate>, String> {
+        let mut certificates = Vec::new();
+        for item in self.approved_items().await? {
+            if item.disposition_date > now {
+                continue;
+            }
+            match self.dispose(&item, destroyed_by).await {
+                Ok(Some(certificate)) => certificates.push(certificate),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to dispose of retention item {}: {}", item.id, e),
+            }
+        }
+        Ok(certificates)
+    }
+
+    async fn dispose(&self, item: &RetentionItem, destroyed_by: &str) -> Result<Option<DispositionCertificate>, String> {
+        // A hold may have been placed since the item was approved
+        if let Some(case_id) = item.case_id {
+            if !self.active_holds(Some(case_id)).await?.is_empty() {
+                tracing::warn!("Retention item {} skipped: case {} is under a legal hold", item.id, case_id);
+                return Ok(None);
+            }
+        }
+        let (Some(approved_by), Some(approved_at)) = (item.reviewed_by.clone(), item.reviewed_at) else {
+            return Err(format!("Retention item {} has no recorded approval", item.id));
+        };
+
+        let (method, content_hash) = match item.record_kind {
+            RecordKind::File => {
+                // The path is checked again in case the file was replaced by a link since
+                if self.confined(Path::new(&item.record_id)).await.is_none()
+                    && tokio::fs::symlink_metadata(&item.record_id).await.is_ok()
+                {
+                    return Err(format!("{} is outside the import root", item.record_id));
+                }
+                let content_hash = match tokio::fs::read(&item.record_id).await {
+                    Ok(bytes) => Some(hex::encode(Sha256::digest(&bytes))),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(format!("Failed to read {}: {}", item.record_id, e)),
+                };
+                if content_hash.is_some() {
+                    tokio::fs::remove_file(&item.record_id)
+                        .await
+                        .map_err(|e| format!("Failed to delete {}: {}", item.record_id, e))?;
+                }
+                let method = if content_hash.is_some() { "File deleted" } else { "File already absent" };
+                (method, content_hash)
+            }
+            RecordKind::Exhibit => {
+                let id: i64 = item
+                    .record_id
+                    .parse()
+                    .map_err(|_| format!("Invalid exhibit id {}", item.record_id))?;
+                let content_hash: Option<String> = sqlx::query_scalar("SELECT hash_sha256 FROM exhibits WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db_error)?
+                    .flatten();
+                let deleted = purge_exhibit(&self.pool, id).await?;
+                let method = if deleted { "Exhibit and derived data deleted" } else { "Exhibit already absent" };
+                (method, content_hash)
+            }
+        };
+
+        let mut certificate = DispositionCertificate {
+            certificate_id: uuid::Uuid::new_v4().to_string(),
+            schedule_id: item.id,
+            record_kind: item.record_kind.clone(),
+            record_id: item.record_id.clone(),
+            case_id: item.case_id,
+            data_classification: item.data_classification.clone(),
+            retention_days: item.retention_days,
+            disposition_date: item.disposition_date,
+            approved_by,
+            approved_at,
+            destroyed_by: destroyed_by.to_string(),
+            destroyed_at: Utc::now(),
+            method: method.to_string(),
+            content_hash,
+            certificate_hash: String::new(),
+        };
+        certificate.certificate_hash = certificate.compute_hash();
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(
+            "INSERT INTO disposition_certificates \
+             (certificate_id, schedule_id, record_kind, record_id, case_id, data_classification, retention_days, \
+             disposition_date, approved_by, approved_at, destroyed_by, destroyed_at, method, content_hash, certificate_hash) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&certificate.certificate_id)
+        .bind(certificate.schedule_id)
+        .bind(enum_key(&certificate.record_kind))
+        .bind(&certificate.record_id)
+        .bind(certificate.case_id)
+        .bind(enum_key(&certificate.data_classification))
+        .bind(certificate.retention_days as i64)
+        .bind(certificate.disposition_date)
+        .bind(&certificate.approved_by)
+        .bind(certificate.approved_at)
+        .bind(&certificate.destroyed_by)
+        .bind(certificate.destroyed_at)
+        .bind(&certificate.method)
+        .bind(&certificate.content_hash)
+        .bind(&certificate.certificate_hash)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+        sqlx::query("UPDATE retention_schedule SET status = ? WHERE id = ?")
+            .bind(enum_key(&RetentionStatus::Destroyed))
+            .bind(item.id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        if let Some(audit) = &self.audit_logger {
+            let mut details = HashMap::new();
+            details.insert("certificate_id".to_string(), serde_json::json!(certificate.certificate_id));
+            details.insert("record_kind".to_string(), serde_json::json!(certificate.record_kind));
+            details.insert("record_id".to_string(), serde_json::json!(certificate.record_id));
+            details.insert("approved_by".to_string(), serde_json::json!(certificate.approved_by));
+            let entry = AuditLogEntry {
+                entry_id: uuid::Uuid::new_v4().to_string(),
+                user_id: destroyed_by.to_string(),
+                operation_type: LegalOperationType::RecordDisposition,
+                timestamp: certificate.destroyed_at,
+                operation_details: details,
+                compliance_status: ComplianceStatus::Compliant,
+                attorney_review_required: false,
+                data_processed: certificate.data_classification.clone(),
+            };
+            if let Err(e) = audit.log_operation(&entry).await {
+                tracing::error!("Failed to audit disposition {}: {}", certificate.certificate_id, e);
+            }
+        }
+        tracing::warn!(
+            "{:?} {} destroyed under retention schedule, certificate {}",
+            certificate.record_kind,
+            certificate.record_id,
+            certificate.certificate_id
+        );
+        Ok(Some(certificate))
+    }
+
+    /// Disposition certificates, for one matter or all of them, newest first
+    pub async fn certificates(&self, case_id: Option<i64>) -> Result<Vec<DispositionCertificate>, String> {
+        self.ready().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM disposition_certificates WHERE (? IS NULL OR case_id = ?) ORDER BY destroyed_at DESC",
+        )
+        .bind(case_id)
+        .bind(case_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        rows.iter().map(certificate_from_row).collect()
+    }
+}
+
+/// Periodically queue items coming due and dispose of approved ones
+pub fn spawn_retention_sweep(scheduler: RetentionScheduler, interval: std::time::Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let now = Utc::now();
+            if let Err(e) = scheduler.queue_due_for_review(now).await {
+                tracing::warn!("Failed to queue retention reviews: {}", e);
+            }
+            if let Err(e) = scheduler.dispose_due(now, "retention-scheduler").await {
+                tracing::warn!("Failed to run retention dispositions: {}", e);
+            }
+        }
+    });
+}
+
+/// Delete an exhibit with its embeddings, transcript and search entries;
+/// false if there was no such exhibit
+///
+/// A voice recording also loses the transcript communication `store_transcript`
+/// filed for it and, unless another exhibit shares it, its audio file. The file
+/// goes before the commit, so a failed delete leaves the exhibit in place.
+pub async fn purge_exhibit(pool: &DbPool, id: i64) -> Result<bool, String> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+
+    let recording: Option<(Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT file_path, ai_content_type FROM exhibits WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut tx)
+            .await
+            .map_err(db_error)?;
+    let transcripts: Vec<i64> = sqlx::query_scalar(
+        "SELECT communication_id FROM transcript_segments
+         WHERE exhibit_id = ? AND communication_id IS NOT NULL
+         UNION
+         SELECT id FROM communications
+         WHERE medium = 'voice_transcript' AND subject = 'Transcript of exhibit ' || ?",
+    )
+    .bind(id)
+    .bind(id)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    for query in [
+        "DELETE FROM document_vectors WHERE document_id = ?",
+        "DELETE FROM transcript_segments WHERE exhibit_id = ?",
+    ] {
+        sqlx::query(query).bind(id).execute(&mut tx).await.map_err(db_error)?;
+    }
+    for communication_id in &transcripts {
+        for query in [
+            "DELETE FROM communication_tone_scores WHERE communication_id = ?",
+            "DELETE FROM communications WHERE id = ? AND medium = 'voice_transcript'",
+        ] {
+            sqlx::query(query).bind(communication_id).execute(&mut tx).await.map_err(db_error)?;
+        }
+        remove_indexed(&mut tx, "communication", *communication_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    remove_indexed(&mut tx, "exhibit", id).await.map_err(|e| e.to_string())?;
+    let result = sqlx::query("DELETE FROM exhibits WHERE id = ?")
+        .bind(id)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+
+    // Recordings are stored under their hash, so identical uploads share a file
+    if let Some((Some(file_path), Some(content_type))) = recording {
+        let shared: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM exhibits WHERE file_path = ?")
+            .bind(&file_path)
+            .fetch_one(&mut tx)
+            .await
+            .map_err(db_error)?;
+        if content_type == "transcript" && shared == 0 {
+            match tokio::fs::remove_file(&file_path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to delete {}: {}", file_path, e)),
+            }
+        }
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn item_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<RetentionItem, String> {
+    Ok(RetentionItem {
+        id: row.get("id"),
+        record_kind: parse_key(&row.get::<String, _>("record_kind"))?,
+        record_id: row.get("record_id"),
+        case_id: row.get("case_id"),
+        data_classification: parse_key(&row.get::<String, _>("data_classification"))?,
+        retention_days: row.get::<i64, _>("retention_days") as u32,
+        retain_from: row.get("retain_from"),
+        disposition_date: row.get("disposition_date"),
+        status: parse_key(&row.get::<String, _>("status"))?,
+        queued_at: row.get("queued_at"),
+        reviewed_by: row.get("reviewed_by"),
+        reviewed_at: row.get("reviewed_at"),
+        review_notes: row.get("review_notes"),
+    })
+}
+
+fn hold_from_row(row: &sqlx::sqlite::SqliteRow) -> LegalHold {
+    LegalHold {
+        hold_id: row.get("hold_id"),
+        case_id: row.get("case_id"),
+        reason: row.get("reason"),
+        placed_by: row.get("placed_by"),
+        placed_at: row.get("placed_at"),
+        released_by: row.get("released_by"),
+        released_at: row.get("released_at"),
+    }
+}
+
+fn certificate_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<DispositionCertificate, String> {
+    Ok(DispositionCertificate {
+        certificate_id: row.get("certificate_id"),
+        schedule_id: row.get("schedule_id"),
+        record_kind: parse_key(&row.get::<String, _>("record_kind"))?,
+        record_id: row.get("record_id"),
+        case_id: row.get("case_id"),
+        data_classification: parse_key(&row.get::<String, _>("data_classification"))?,
+        retention_days: row.get::<i64, _>("retention_days") as u32,
+        disposition_date: row.get("disposition_date"),
+        approved_by: row.get("approved_by"),
+        approved_at: row.get("approved_at"),
+        destroyed_by: row.get("destroyed_by"),
+        destroyed_at: row.get("destroyed_at"),
+        method: row.get("method"),
+        content_hash: row.get("content_hash"),
+        certificate_hash: row.get("certificate_hash"),
+    })
+}
+
+fn parse_key<T: serde::de::DeserializeOwned>(key: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(key.to_string()))
+        .map_err(|e| format!("Unknown value '{}' in retention tables: {}", key, e))
+}
+
+fn db_error(e: sqlx::Error) -> String {
+    format!("Retention database error: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import_wizard::classifier::FileCategory;
+
+    fn legal_document() -> FileClassification {
+        FileClassification {
+            category: FileCategory::LegalDocument,
+            sensitivity: SensitivityLevel::HighlyConfidential,
+            requires_encryption: true,
+            retention_period_days: Some(2555),
+            access_level: 9,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_schedule_follows_classifier_retention() {
+        let scheduler = RetentionScheduler::new();
+        let retain_from = Utc::now();
+        let file = MatterLink {
+            record_kind: RecordKind::File,
+            record_id: "/matters/12/custody-order.pdf".to_string(),
+            case_id: 12,
+            linked_at: retain_from,
+        };
+        let item = scheduler.schedule_file(&file, &legal_document()).await.unwrap();
+
+        assert_eq!(item.retention_days, 2555);
+        assert_eq!(item.disposition_date, retain_from + Duration::days(2555));
+        assert_eq!(item.data_classification, DataClassification::AttorneyClientPrivileged);
+        assert_eq!(item.status, RetentionStatus::Scheduled);
+    }
+
+    #[tokio::test]
+    async fn test_hold_keeps_items_out_of_review_and_disposal() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = RetentionScheduler::new().with_import_root(dir.path());
+        let path = dir.path().join("old-notes.txt");
+        std::fs::write(&path, b"notes").unwrap();
+
+        let file = scheduler.link_file(&path, 7, Utc::now() - Duration::days(4000)).await.unwrap();
+        let path = file.record_id.clone();
+        let item = scheduler.schedule_record(&file, DataClassification::InternalUse).await.unwrap();
+        assert_eq!(scheduler.queue_due_for_review(Utc::now()).await.unwrap().len(), 1);
+        scheduler.decide(item.id, "atty", RetentionDecision::Approve, None).await.unwrap();
+
+        // The hold undoes the approval and nothing is destroyed
+        let hold = scheduler.place_hold(7, "Pending appeal", "atty").await.unwrap();
+        assert!(scheduler.dispose_due(Utc::now(), "atty").await.unwrap().is_empty());
+        assert!(scheduler.queue_due_for_review(Utc::now()).await.unwrap().is_empty());
+        assert!(std::path::Path::new(&path).exists());
+        assert_eq!(scheduler.hold_for(&RecordKind::File, &path).await.unwrap().unwrap().hold_id, hold.hold_id);
+
+        // Released, it needs a fresh review before it goes
+        scheduler.release_hold(&hold.hold_id, "atty").await.unwrap();
+        assert_eq!(scheduler.queue_due_for_review(Utc::now()).await.unwrap().len(), 1);
+        scheduler.decide(item.id, "atty", RetentionDecision::Approve, Some("Matter closed".to_string())).await.unwrap();
+        let certificates = scheduler.dispose_due(Utc::now(), "records").await.unwrap();
+
+        assert_eq!(certificates.len(), 1);
+        assert!(!std::path::Path::new(&path).exists());
+        let certificate = &certificates[0];
+        assert!(certificate.verify());
+        assert_eq!(certificate.approved_by, "atty");
+        assert_eq!(certificate.content_hash.as_deref(), Some(hex::encode(Sha256::digest(b"notes")).as_str()));
+        assert_eq!(scheduler.certificates(Some(7)).await.unwrap()[0].certificate_hash, certificate.certificate_hash);
+    }
+
+    #[tokio::test]
+    async fn test_extension_moves_disposition_date_and_requeues_later() {
+        let scheduler = RetentionScheduler::new();
+        let exhibit = MatterLink {
+            record_kind: RecordKind::Exhibit,
+            record_id: "3".to_string(),
+            case_id: 4,
+            linked_at: Utc::now() - Duration::days(400),
+        };
+        let item = scheduler.schedule_record(&exhibit, DataClassification::PublicData).await.unwrap();
+        scheduler.queue_due_for_review(Utc::now()).await.unwrap();
+
+        let extended = scheduler
+            .decide(item.id, "atty", RetentionDecision::Extend { days: 180 }, None)
+            .await
+            .unwrap();
+        assert_eq!(extended.status, RetentionStatus::Scheduled);
+        assert!(extended.disposition_date > Utc::now() + Duration::days(179));
+        assert!(scheduler.queue_due_for_review(Utc::now()).await.unwrap().is_empty());
+        assert!(scheduler.decide(item.id, "atty", RetentionDecision::Approve, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_linked_files_under_the_import_root_resolve() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let scheduler = RetentionScheduler::new().with_import_root(root.path());
+
+        let imported = root.path().join("order.pdf");
+        let unlinked = root.path().join("draft.pdf");
+        let foreign = outside.path().join("passwd");
+        for path in [&imported, &unlinked, &foreign] {
+            std::fs::write(path, b"content").unwrap();
+        }
+
+        let imported_at = Utc::now() - Duration::days(90);
+        let link = scheduler.link_file(&imported, 12, imported_at).await.unwrap();
+        assert!(scheduler.link_file(&foreign, 12, imported_at).await.is_err());
+
+        let found = scheduler.matter_file(imported.to_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!((found.case_id, found.linked_at), (12, imported_at));
+        assert_eq!(found.record_id, link.record_id);
+
+        // A `..` path that resolves to the linked file still finds it
+        let dotted = root.path().join("sub").join("..").join("order.pdf");
+        std::fs::create_dir(root.path().join("sub")).unwrap();
+        assert!(scheduler.matter_file(dotted.to_str().unwrap()).await.unwrap().is_some());
+
+        let escape = root.path().join("..").join(outside.path().file_name().unwrap()).join("passwd");
+        for path in [unlinked.clone(), foreign, escape, root.path().join("missing.pdf")] {
+            assert!(scheduler.matter_file(path.to_str().unwrap()).await.unwrap().is_none());
+        }
+    }
+}
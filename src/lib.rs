@@ -1,12 +1,17 @@
 pub mod ai;
+pub mod app;
+pub mod auth;
 pub mod bicycle;
 pub mod config;
+pub mod crypto_db;
 pub mod db;
 pub mod demo_app;
 pub mod error;
 pub mod handlers;
+pub mod legal;
 pub mod models;
 pub mod nonprofit;
+pub mod search;
 
 pub mod game_creator;
 pub mod image_portal;
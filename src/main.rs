@@ -1,16 +1,23 @@
 #![allow(unused_imports, unused_variables)]
 
 use crate::{
+    app,
+    auth::AuthManager,
     config::AppConfig,
-    db::{create_pool, run_migrations, seed_sample_data},
+    crypto_db::CryptoDatabase,
+    db::{create_pool, create_secure_store, run_migrations, seed_sample_data},
     error::AppError,
+    legal::access_control::{spawn_grant_expiry, AccessController},
+    legal::audit_log::AuditLogger,
+    legal::consent::{trusted_proxies_from_env, ConsentManager},
+    legal::conflict_check::ConflictChecker,
+    legal::retention::{spawn_retention_sweep, RetentionScheduler},
+    legal::audit_store::{audit_dir_from_env, signing_key_from_env},
 };
-use axum::routing::{delete, post, put};
-use axum::{routing::get, Router};
+use axum::Router;
 use sqlx::{Pool, Sqlite};
-use std::{env, net::SocketAddr, time::Duration};
+use std::{env, net::SocketAddr, sync::Arc, time::Duration};
 use tower::ServiceBuilder;
-use tower_http::services::ServeDir;
 use tower_http::{
     cors::{Any, CorsLayer},
     limit::RequestBodyLimitLayer,
@@ -74,9 +81,100 @@ async fn startup() -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("✅ Sample data seeded");
     }
 
+    // Encrypted-at-rest store for sensitive tables, kept in the same database
+    tracing::info!("🔐 Opening encrypted record store...");
+    // Without it (e.g. no key store passphrase) only the secure routes are disabled
+    let secure = match create_secure_store(&pool).await {
+        Ok(secure) => {
+            tracing::info!("✅ Encrypted store ready");
+            Some(secure)
+        }
+        Err(e) => {
+            tracing::error!("❌ Encrypted store unavailable, secure communications disabled: {}", e);
+            None
+        }
+    };
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        tracing::warn!("⚠️  Using default configuration (non-critical): {}", e);
+        AppConfig::default()
+    });
+
+    // Users, roles and temporary grants for route-level access control
+    tracing::info!("🛡️  Opening access control...");
+    let mut access = AccessController::open(pool.clone())
+        .await
+        .map_err(|e| format!("Failed to open access control: {}", e))?
+        .with_mfa_required(config.security.require_mfa);
+    // TOTP secrets live in the KMS; without it nobody can enroll, which only
+    // matters if MFA is required
+    match crate::auth::kms::create_kms_from_env().await {
+        Ok(kms) => access = access.with_secret_store(Arc::from(kms)),
+        Err(e) if config.security.require_mfa => {
+            return Err(format!("MFA is required but the key store is unavailable: {}", e).into());
+        }
+        Err(e) => tracing::warn!("⚠️  MFA enrollment disabled, key store unavailable (non-critical): {}", e),
+    }
+    spawn_grant_expiry(access.clone(), Duration::from_secs(60));
+    tracing::info!("✅ Access control ready");
+
+    // Single sign-on providers from the environment (non-critical)
+    tracing::info!("🔑 Configuring single sign-on...");
+    let auth = match crate::auth::from_env().await {
+        Ok(manager) if manager.get_providers().is_empty() => {
+            tracing::info!("   No sign-on providers configured");
+            None
+        }
+        Ok(manager) => {
+            tracing::info!("✅ Sign-on providers: {}", manager.get_providers().join(", "));
+            Some(Arc::new(manager))
+        }
+        Err(e) => {
+            tracing::warn!("⚠️  Single sign-on disabled (non-critical): {}", e);
+            None
+        }
+    };
+
+    // Ledger of what each user consented to, against which form version
+    let consent = ConsentManager::open(pool.clone())
+        .await
+        .map_err(|e| format!("Failed to open consent ledger: {}", e))?
+        .with_trusted_proxies(trusted_proxies_from_env()?);
+
+    // Hash-chained audit trail; in memory only when no signing key is set
+    tracing::info!("📜 Opening audit log...");
+    let audit = match signing_key_from_env() {
+        Ok(key) => AuditLogger::open(audit_dir_from_env(), &key)
+            .await
+            .map_err(|e| format!("Failed to open audit log: {}", e))?,
+        Err(e) => {
+            tracing::warn!("⚠️  Audit log kept in memory only (non-critical): {}", e);
+            AuditLogger::new()
+        }
+    };
+    tracing::info!("✅ Audit log ready");
+
+    // Matters opened before party tracking get the parties from their caption
+    if let Err(e) = ConflictChecker::new(pool.clone()).backfill_parties().await {
+        tracing::warn!("⚠️  Could not backfill matter parties (non-critical): {}", e);
+    }
+
+    // Disposition dates, legal holds and the attorney review queue
+    let retention = RetentionScheduler::open(pool.clone())
+        .await
+        .map_err(|e| format!("Failed to open retention schedule: {}", e))?
+        .with_audit_logger(audit.clone());
+    spawn_retention_sweep(retention.clone(), Duration::from_secs(3600));
+
+    // Nightly batch scoring of placement denials with the latest risk model
+    crate::ai::risk_model::spawn_nightly_scoring(pool.clone(), 2);
+
+    // Keep the full-text search index in step with exhibits and communications
+    crate::search::spawn_background_sync(pool.clone(), Duration::from_secs(300));
+
     // Step 5: Build application routes
     tracing::info!("🛠️  Building application routes...");
-    let app = create_app(pool.clone()).await;
+    let app = create_app(pool.clone(), secure, access, consent, audit, retention, auth).await;
     tracing::info!("✅ Routes configured");
 
     // Step 6: Start server
@@ -96,7 +194,7 @@ async fn startup() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .map_err(|e| format!("Failed to bind to address: {}", e))?;
 
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
         .map_err(|e| format!("Server error: {}", e))?;
@@ -106,17 +204,17 @@ async fn startup() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 // Create the Axum application with all routes
-pub async fn create_app(pool: Pool<Sqlite>) -> Router {
-    Router::new()
-        .route("/api/health", get(handlers::health_check))
-        .route("/api/dashboard", get(handlers::dashboard_data))
-        .route("/api/ai/prompt", post(handlers::ai_prompt))
-        .route("/api/ai/voice", post(handlers::ai_voice))
-        .route("/api/data/diff", get(handlers::diff_data))
-        .route("/api/data/commit", post(handlers::commit_changes))
-        .nest_service("/", ServeDir::new("frontend/dist"))
-        .fallback(handlers::handle_fallback)
-        .with_state(pool)
+async fn create_app(
+    pool: Pool<Sqlite>,
+    secure: Option<Arc<CryptoDatabase>>,
+    access: AccessController,
+    consent: ConsentManager,
+    audit: AuditLogger,
+    retention: RetentionScheduler,
+    auth: Option<Arc<AuthManager>>,
+) -> Router {
+    app::create_app(pool, secure, access, consent, audit, retention, auth)
+        .await
         .layer(TraceLayer::new_for_http())
         .layer(CorsLayer::permissive())
         .layer(TimeoutLayer::new(Duration::from_secs(30)))
@@ -161,6 +161,9 @@ pub struct CreateCaseRequest {
 
     pub tags: Option<Vec<String>>,
     pub metadata: Option<serde_json::Value>,
+
+    /// Everyone else involved, screened for conflicts before the matter is opened
+    pub parties: Option<Vec<CaseParty>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -171,7 +174,35 @@ pub enum CasePriority {
     Critical,
 }
 
+/// A person or organisation involved in a matter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseParty {
+    pub name: String,
+    pub role: PartyRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PartyRole {
+    Client,
+    AdverseParty,
+    OpposingCounsel,
+    RelatedParty,
+    Witness,
+}
+
 impl CreateCaseRequest {
+    /// The client followed by every other named party
+    pub fn all_parties(&self) -> Vec<CaseParty> {
+        let client = self.client_name.iter().map(|name| CaseParty {
+            name: name.clone(),
+            role: PartyRole::Client,
+        });
+        client
+            .chain(self.parties.iter().flatten().cloned())
+            .filter(|party| !party.name.trim().is_empty())
+            .collect()
+    }
+
     pub fn sanitize(&mut self) {
         self.title = strip_html(self.title.trim());
 
@@ -192,6 +223,12 @@ impl CreateCaseRequest {
         if let Some(ref mut tags) = self.tags {
             *tags = tags.iter().map(|tag| strip_html(tag.trim())).collect();
         }
+
+        if let Some(ref mut parties) = self.parties {
+            for party in parties.iter_mut() {
+                party.name = strip_html(party.name.trim());
+            }
+        }
     }
 }
 
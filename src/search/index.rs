@@ -0,0 +1,314 @@
+// Text analysis, query parsing and scoring for the persistent full-text index
+
+use std::collections::HashSet;
+
+/// A normalised term with its token position and byte span in the source text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub term: String,
+    pub position: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Split text into lowercase alphanumeric tokens, keeping byte offsets for highlighting
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, ch) in text.char_indices() {
+        if ch.is_alphanumeric() {
+            if start.is_none() {
+                start = Some(i);
+            }
+        } else if let Some(s) = start.take() {
+            push_token(&mut tokens, text, s, i);
+        }
+    }
+    if let Some(s) = start {
+        push_token(&mut tokens, text, s, text.len());
+    }
+
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<Token>, text: &str, start: usize, end: usize) {
+    tokens.push(Token {
+        term: text[start..end].to_lowercase(),
+        position: tokens.len() as u32,
+        start,
+        end,
+    });
+}
+
+/// One clause of a search query; clauses are combined with AND
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryClause {
+    Term(String),
+    Prefix(String),
+    Fuzzy { term: String, max_edits: usize },
+    Phrase(Vec<String>),
+}
+
+/// Parse a query such as `"foster care" placem* visitaton~ judge`
+///
+/// Quoted text is a phrase, a trailing `*` is a prefix and a trailing `~` (optionally
+/// followed by an edit distance) is a fuzzy term. Everything else is an exact term.
+pub fn parse_query(input: &str) -> Vec<QueryClause> {
+    let mut clauses = Vec::new();
+    let mut rest = input.trim();
+
+    while !rest.is_empty() {
+        if let Some(after_quote) = rest.strip_prefix('"') {
+            let (phrase, remainder) = match after_quote.find('"') {
+                Some(end) => (&after_quote[..end], &after_quote[end + 1..]),
+                None => (after_quote, ""),
+            };
+            let terms: Vec<String> = tokenize(phrase).into_iter().map(|t| t.term).collect();
+            match terms.len() {
+                0 => {}
+                1 => clauses.push(QueryClause::Term(terms[0].clone())),
+                _ => clauses.push(QueryClause::Phrase(terms)),
+            }
+            rest = remainder.trim_start();
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        rest = rest[end..].trim_start();
+
+        if let Some(stem) = word.strip_suffix('*') {
+            if let Some(token) = tokenize(stem).into_iter().next() {
+                clauses.push(QueryClause::Prefix(token.term));
+            }
+        } else if let Some((stem, edits)) = word.split_once('~') {
+            if let Some(token) = tokenize(stem).into_iter().next() {
+                let max_edits = edits
+                    .parse()
+                    .unwrap_or_else(|_| default_edits(&token.term))
+                    .min(3);
+                clauses.push(QueryClause::Fuzzy {
+                    term: token.term,
+                    max_edits,
+                });
+            }
+        } else {
+            // Words like "foster-care" tokenise to several terms and behave as a phrase
+            let terms: Vec<String> = tokenize(word).into_iter().map(|t| t.term).collect();
+            match terms.len() {
+                0 => {}
+                1 => clauses.push(QueryClause::Term(terms[0].clone())),
+                _ => clauses.push(QueryClause::Phrase(terms)),
+            }
+        }
+    }
+
+    clauses
+}
+
+fn default_edits(term: &str) -> usize {
+    if term.chars().count() < 6 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, or `None` once it exceeds `max`
+pub fn levenshtein_within(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut row_min = current[0];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            row_min = row_min.min(current[j + 1]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+/// Start positions at which every term of a phrase occurs consecutively
+///
+/// `positions[i]` holds the sorted positions of the i-th phrase term in one document.
+pub fn phrase_starts(positions: &[Vec<u32>]) -> Vec<u32> {
+    let Some(first) = positions.first() else {
+        return Vec::new();
+    };
+
+    first
+        .iter()
+        .copied()
+        .filter(|&start| {
+            positions
+                .iter()
+                .enumerate()
+                .skip(1)
+                .all(|(offset, list)| list.binary_search(&(start + offset as u32)).is_ok())
+        })
+        .collect()
+}
+
+/// Okapi BM25 contribution of one term in one document
+pub fn bm25(term_freq: u32, doc_freq: u32, doc_len: u32, avg_doc_len: f64, total_docs: u32) -> f64 {
+    const K1: f64 = 1.2;
+    const B: f64 = 0.75;
+
+    let n = total_docs.max(1) as f64;
+    let df = doc_freq.max(1) as f64;
+    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+    let tf = term_freq as f64;
+    let length_norm = 1.0 - B + B * doc_len as f64 / avg_doc_len.max(1.0);
+
+    idf * tf * (K1 + 1.0) / (tf + K1 * length_norm)
+}
+
+/// Encode sorted token positions for storage in a postings row
+pub fn encode_positions(positions: &[u32]) -> String {
+    positions
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn decode_positions(encoded: &str) -> Vec<u32> {
+    encoded
+        .split_whitespace()
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+/// Build an HTML-safe snippet around the densest cluster of hits, wrapping hits in `<mark>`
+pub fn build_snippet(text: &str, tokens: &[Token], hits: &HashSet<u32>, window: usize) -> String {
+    if tokens.is_empty() {
+        return String::new();
+    }
+    let window = window.max(1).min(tokens.len());
+
+    // Slide a fixed-size token window and keep the one covering the most hits
+    let is_hit = |t: &Token| hits.contains(&t.position);
+    let mut count = tokens[..window].iter().filter(|t| is_hit(t)).count();
+    let (mut best_start, mut best_count) = (0, count);
+    for start in 1..=tokens.len() - window {
+        count -= usize::from(is_hit(&tokens[start - 1]));
+        count += usize::from(is_hit(&tokens[start + window - 1]));
+        if count > best_count {
+            best_start = start;
+            best_count = count;
+        }
+    }
+
+    let visible = &tokens[best_start..best_start + window];
+    let mut snippet = String::new();
+    if best_start > 0 {
+        snippet.push('…');
+    }
+
+    let mut cursor = visible[0].start;
+    for token in visible {
+        snippet.push_str(&escape_html(&text[cursor..token.start]));
+        if is_hit(token) {
+            snippet.push_str("<mark>");
+            snippet.push_str(&escape_html(&text[token.start..token.end]));
+            snippet.push_str("</mark>");
+        } else {
+            snippet.push_str(&escape_html(&text[token.start..token.end]));
+        }
+        cursor = token.end;
+    }
+
+    if best_start + window < tokens.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(char::is_whitespace, " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_clauses() {
+        let clauses = parse_query(r#""Foster Care" placem* visitaton~ judge~1 foster-care"#);
+        assert_eq!(
+            clauses,
+            vec![
+                QueryClause::Phrase(vec!["foster".into(), "care".into()]),
+                QueryClause::Prefix("placem".into()),
+                QueryClause::Fuzzy {
+                    term: "visitaton".into(),
+                    max_edits: 2
+                },
+                QueryClause::Fuzzy {
+                    term: "judge".into(),
+                    max_edits: 1
+                },
+                QueryClause::Phrase(vec!["foster".into(), "care".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_levenshtein_within() {
+        assert_eq!(levenshtein_within("visitation", "visitaton", 2), Some(1));
+        assert_eq!(levenshtein_within("custody", "custodian", 1), None);
+        assert_eq!(levenshtein_within("judge", "judge", 0), Some(0));
+    }
+
+    #[test]
+    fn test_phrase_starts() {
+        let tokens = tokenize("the foster care agency denied foster care placement");
+        let positions_of = |term: &str| -> Vec<u32> {
+            tokens
+                .iter()
+                .filter(|t| t.term == term)
+                .map(|t| t.position)
+                .collect()
+        };
+        let starts = phrase_starts(&[positions_of("foster"), positions_of("care")]);
+        assert_eq!(starts, vec![1, 5]);
+        assert!(phrase_starts(&[positions_of("care"), positions_of("foster")]).is_empty());
+    }
+
+    #[test]
+    fn test_snippet_highlights_and_escapes() {
+        let text =
+            "Intro text. The agency <staff> denied the placement request on Monday. Closing.";
+        let tokens = tokenize(text);
+        let hits: HashSet<u32> = tokens
+            .iter()
+            .filter(|t| t.term == "denied" || t.term == "placement")
+            .map(|t| t.position)
+            .collect();
+        let snippet = build_snippet(text, &tokens, &hits, 6);
+        assert!(snippet.contains("<mark>denied</mark> the <mark>placement</mark>"));
+        assert!(snippet.contains("&lt;staff&gt;"));
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+    }
+}
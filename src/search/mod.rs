@@ -0,0 +1,759 @@
+// Persistent, incrementally updated full-text search over exhibits and communications
+//
+// Replaces the per-call suffix tree rebuilds of `algorithms::suffix_tree::StringMatcher`
+// with a positional inverted index stored in SQLite. Triggers log changed source rows and
+// a sync re-indexes only those whose content hash changed, so ranking never touches the
+// source tables. The index keeps titles, hashes and postings but no document text;
+// snippets are cut from the source rows of the hits being returned.
+
+pub mod index;
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use tokio::time::sleep;
+
+use crate::db::DbPool;
+use crate::error::AppResult;
+use crate::legal::ethical_walls::{MatterScope, SOURCE_MATTER_SQL};
+use index::{
+    bm25, build_snippet, decode_positions, encode_positions, levenshtein_within, parse_query,
+    phrase_starts, tokenize, QueryClause,
+};
+
+/// Maximum dictionary terms a single prefix or fuzzy clause expands to
+const MAX_EXPANSIONS: usize = 50;
+/// Tokens shown in each highlighted snippet
+const SNIPPET_TOKENS: usize = 30;
+
+/// A source record to be indexed
+#[derive(Debug, Clone)]
+pub struct SearchDocument {
+    pub source_type: String,
+    pub source_id: i64,
+    pub title: String,
+    pub body: String,
+}
+
+impl SearchDocument {
+    /// Text that is tokenised, and that snippets are cut from
+    fn content(&self) -> String {
+        format!("{}\n\n{}", self.title, self.body)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SyncSummary {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub source_type: String,
+    pub source_id: i64,
+    pub title: String,
+    pub score: f64,
+    pub matched_terms: Vec<String>,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub total_hits: usize,
+    pub hits: Vec<SearchHit>,
+}
+
+struct Posting {
+    term_freq: u32,
+    doc_len: u32,
+    positions: Vec<u32>,
+}
+
+/// Documents matched by one clause with their score and highlighted positions
+type ClauseMatches = HashMap<i64, (f64, HashSet<u32>, HashSet<String>)>;
+
+pub struct FullTextIndex {
+    pool: DbPool,
+}
+
+impl FullTextIndex {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Bring the index up to date with exhibits and communications
+    ///
+    /// Only rows logged in `search_changes` since the stored watermark are read. The
+    /// first sync has no watermark and indexes every row, since the log triggers
+    /// may be newer than the data.
+    pub async fn sync(&self) -> AppResult<SyncSummary> {
+        let watermark: Option<i64> =
+            sqlx::query_scalar("SELECT last_change_seq FROM search_sync_state WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?;
+        let latest: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM search_changes")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let summary = match watermark {
+            None => self.full_sync().await?,
+            Some(last) if last >= latest => return Ok(SyncSummary::default()),
+            Some(last) => self.apply_changes(last, latest).await?,
+        };
+
+        // Changes logged while this sync ran sit above `latest` and are picked up next time
+        sqlx::query(
+            "INSERT INTO search_sync_state (id, last_change_seq) VALUES (1, ?)
+             ON CONFLICT(id) DO UPDATE SET last_change_seq = excluded.last_change_seq",
+        )
+        .bind(latest)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("DELETE FROM search_changes WHERE seq <= ?")
+            .bind(latest)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(summary)
+    }
+
+    async fn full_sync(&self) -> AppResult<SyncSummary> {
+        let sources = load_sources(&self.pool).await?;
+        let existing = self.indexed_hashes().await?;
+
+        let mut summary = SyncSummary::default();
+        let mut seen = HashSet::new();
+
+        for doc in &sources {
+            let key = (doc.source_type.clone(), doc.source_id);
+            self.refresh(doc, existing.get(&key), &mut summary).await?;
+            seen.insert(key);
+        }
+
+        for (source_type, source_id) in existing.keys() {
+            if !seen.contains(&(source_type.clone(), *source_id)) {
+                self.remove_document(source_type, *source_id).await?;
+                summary.removed += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Re-read only the rows logged with a sequence number in `(after, up_to]`
+    async fn apply_changes(&self, after: i64, up_to: i64) -> AppResult<SyncSummary> {
+        let changed = sqlx::query_as::<_, (String, i64)>(
+            "SELECT DISTINCT source_type, source_id FROM search_changes WHERE seq > ? AND seq <= ?",
+        )
+        .bind(after)
+        .bind(up_to)
+        .fetch_all(&self.pool)
+        .await?;
+        let existing = self.indexed_hashes().await?;
+
+        let mut summary = SyncSummary::default();
+        for (source_type, source_id) in changed {
+            let key = (source_type, source_id);
+            match load_source(&self.pool, &key.0, key.1).await? {
+                Some(doc) => self.refresh(&doc, existing.get(&key), &mut summary).await?,
+                None if existing.contains_key(&key) => {
+                    self.remove_document(&key.0, key.1).await?;
+                    summary.removed += 1;
+                }
+                None => {}
+            }
+        }
+
+        Ok(summary)
+    }
+
+    async fn indexed_hashes(&self) -> AppResult<HashMap<(String, i64), String>> {
+        Ok(sqlx::query_as::<_, (String, i64, String)>(
+            "SELECT source_type, source_id, content_hash FROM search_documents",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(source_type, source_id, hash)| ((source_type, source_id), hash))
+        .collect())
+    }
+
+    /// Re-index `doc` unless its content hash matches the indexed one
+    async fn refresh(
+        &self,
+        doc: &SearchDocument,
+        indexed_hash: Option<&String>,
+        summary: &mut SyncSummary,
+    ) -> AppResult<()> {
+        if indexed_hash == Some(&content_hash(&doc.content())) {
+            summary.unchanged += 1;
+        } else {
+            self.index_document(doc).await?;
+            summary.indexed += 1;
+        }
+        Ok(())
+    }
+
+    /// Index or re-index a single document
+    pub async fn index_document(&self, doc: &SearchDocument) -> AppResult<()> {
+        let content = doc.content();
+        let tokens = tokenize(&content);
+
+        let mut postings: HashMap<&str, Vec<u32>> = HashMap::new();
+        for token in &tokens {
+            postings
+                .entry(&token.term)
+                .or_default()
+                .push(token.position);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO search_documents
+             (source_type, source_id, title, content, content_hash, token_count, indexed_at)
+             VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(source_type, source_id) DO UPDATE SET
+               title = excluded.title,
+               content = excluded.content,
+               content_hash = excluded.content_hash,
+               token_count = excluded.token_count,
+               indexed_at = excluded.indexed_at",
+        )
+        .bind(&doc.source_type)
+        .bind(doc.source_id)
+        .bind(&doc.title)
+        // No copy of the text is kept; snippets are built from the source row
+        .bind("")
+        .bind(content_hash(&content))
+        .bind(tokens.len() as i64)
+        .execute(&mut tx)
+        .await?;
+
+        let doc_id: i64 = sqlx::query_scalar(
+            "SELECT id FROM search_documents WHERE source_type = ? AND source_id = ?",
+        )
+        .bind(&doc.source_type)
+        .bind(doc.source_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        clear_postings(&mut tx, doc_id).await?;
+
+        for (term, positions) in &postings {
+            sqlx::query(
+                "INSERT INTO search_postings (term, document_id, term_freq, positions)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(term)
+            .bind(doc_id)
+            .bind(positions.len() as i64)
+            .bind(encode_positions(positions))
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query(
+                "INSERT INTO search_terms (term, doc_freq) VALUES (?, 1)
+                 ON CONFLICT(term) DO UPDATE SET doc_freq = doc_freq + 1",
+            )
+            .bind(term)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn remove_document(&self, source_type: &str, source_id: i64) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+        remove_indexed(&mut tx, source_type, source_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Run a query; all clauses must match and results are ranked by BM25
+    ///
+    /// Documents from matters outside `scope` are dropped before ranking, so
+    /// they don't show up in `total_hits` either.
+    pub async fn search(&self, query: &str, limit: usize, scope: &MatterScope) -> AppResult<SearchResults> {
+        let clauses = parse_query(query);
+        let mut results = SearchResults {
+            query: query.to_string(),
+            total_hits: 0,
+            hits: Vec::new(),
+        };
+        if clauses.is_empty() {
+            return Ok(results);
+        }
+
+        let (total_docs, avg_len): (i64, Option<f64>) =
+            sqlx::query_as("SELECT COUNT(*), AVG(token_count) FROM search_documents")
+                .fetch_one(&self.pool)
+                .await?;
+        let total_docs = total_docs as u32;
+        let avg_len = avg_len.unwrap_or(1.0);
+
+        let score = |posting: &Posting, doc_freq: u32| {
+            bm25(
+                posting.term_freq,
+                doc_freq,
+                posting.doc_len,
+                avg_len,
+                total_docs,
+            )
+        };
+
+        let mut combined: Option<ClauseMatches> = None;
+
+        for clause in &clauses {
+            let mut matches = ClauseMatches::new();
+
+            match clause {
+                QueryClause::Phrase(terms) => {
+                    let mut per_term = Vec::new();
+                    for term in terms {
+                        per_term.push(self.postings_for(term).await?);
+                    }
+                    let Some((_, first)) = per_term.first() else {
+                        continue;
+                    };
+                    for doc_id in first.keys() {
+                        let lists: Option<Vec<&Posting>> =
+                            per_term.iter().map(|(_, p)| p.get(doc_id)).collect();
+                        let Some(lists) = lists else { continue };
+                        let positions: Vec<Vec<u32>> =
+                            lists.iter().map(|p| p.positions.clone()).collect();
+                        let starts = phrase_starts(&positions);
+                        if starts.is_empty() {
+                            continue;
+                        }
+
+                        // Phrase matches outrank the same words scattered through a document
+                        let doc_score: f64 = per_term
+                            .iter()
+                            .zip(&lists)
+                            .map(|((df, _), posting)| score(posting, *df))
+                            .sum::<f64>()
+                            * 1.5;
+                        let hits = starts
+                            .iter()
+                            .flat_map(|&s| (0..terms.len() as u32).map(move |o| s + o))
+                            .collect();
+                        matches
+                            .insert(*doc_id, (doc_score, hits, HashSet::from([terms.join(" ")])));
+                    }
+                }
+                _ => {
+                    for (term, weight) in self.expand_clause(clause).await? {
+                        let (doc_freq, postings) = self.postings_for(&term).await?;
+                        for (doc_id, posting) in postings {
+                            let entry = matches.entry(doc_id).or_default();
+                            entry.0 += weight * score(&posting, doc_freq);
+                            entry.1.extend(posting.positions);
+                            entry.2.insert(term.clone());
+                        }
+                    }
+                }
+            }
+
+            combined = Some(match combined {
+                None => matches,
+                Some(previous) => previous
+                    .into_iter()
+                    .filter_map(|(doc_id, (s, mut hits, mut terms))| {
+                        let (other_score, other_hits, other_terms) = matches.remove(&doc_id)?;
+                        hits.extend(other_hits);
+                        terms.extend(other_terms);
+                        Some((doc_id, (s + other_score, hits, terms)))
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut ranked: Vec<_> = combined.unwrap_or_default().into_iter().collect();
+        if !scope.is_unrestricted() {
+            let hidden: HashSet<i64> = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT id FROM search_documents WHERE NOT {}",
+                scope.sql_filter(SOURCE_MATTER_SQL)
+            ))
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .collect();
+            ranked.retain(|(doc_id, _)| !hidden.contains(doc_id));
+        }
+        ranked.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0).then(a.0.cmp(&b.0)));
+        results.total_hits = ranked.len();
+
+        for (doc_id, (doc_score, hits, terms)) in ranked.into_iter().take(limit) {
+            let row = sqlx::query(
+                "SELECT source_type, source_id, title, content_hash FROM search_documents WHERE id = ?",
+            )
+            .bind(doc_id)
+            .fetch_one(&self.pool)
+            .await?;
+            let source_type: String = row.get("source_type");
+            let source_id: i64 = row.get("source_id");
+
+            // Hit positions only line up with the text that was indexed; a row
+            // changed since the last sync gets no snippet until it is re-indexed
+            let snippet = match load_source(&self.pool, &source_type, source_id).await? {
+                Some(doc) if content_hash(&doc.content()) == row.get::<String, _>("content_hash") => {
+                    let content = doc.content();
+                    build_snippet(&content, &tokenize(&content), &hits, SNIPPET_TOKENS)
+                }
+                _ => String::new(),
+            };
+
+            let mut matched_terms: Vec<String> = terms.into_iter().collect();
+            matched_terms.sort();
+
+            results.hits.push(SearchHit {
+                source_type,
+                source_id,
+                title: row.get("title"),
+                score: doc_score,
+                matched_terms,
+                snippet,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Dictionary terms a clause stands for, with a weight relative to an exact match
+    async fn expand_clause(&self, clause: &QueryClause) -> AppResult<Vec<(String, f64)>> {
+        match clause {
+            QueryClause::Term(term) => Ok(vec![(term.clone(), 1.0)]),
+            QueryClause::Prefix(prefix) => {
+                let terms: Vec<String> = sqlx::query_scalar(
+                    "SELECT term FROM search_terms WHERE term >= ? AND term < ?
+                     ORDER BY doc_freq DESC LIMIT ?",
+                )
+                .bind(prefix)
+                .bind(format!("{}\u{10FFFF}", prefix))
+                .bind(MAX_EXPANSIONS as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                Ok(terms
+                    .into_iter()
+                    .map(|t| {
+                        let weight = if &t == prefix { 1.0 } else { 0.9 };
+                        (t, weight)
+                    })
+                    .collect())
+            }
+            QueryClause::Fuzzy { term, max_edits } => {
+                let len = term.chars().count();
+                let candidates: Vec<String> = sqlx::query_scalar(
+                    "SELECT term FROM search_terms WHERE length(term) BETWEEN ? AND ?",
+                )
+                .bind(len.saturating_sub(*max_edits) as i64)
+                .bind((len + max_edits) as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+                let mut expanded: Vec<(String, usize)> = candidates
+                    .into_iter()
+                    .filter_map(|c| levenshtein_within(term, &c, *max_edits).map(|d| (c, d)))
+                    .collect();
+                expanded.sort_by_key(|(_, distance)| *distance);
+                expanded.truncate(MAX_EXPANSIONS);
+
+                Ok(expanded
+                    .into_iter()
+                    .map(|(t, distance)| (t, 1.0 / (1.0 + distance as f64)))
+                    .collect())
+            }
+            QueryClause::Phrase(_) => Ok(Vec::new()),
+        }
+    }
+
+    async fn postings_for(&self, term: &str) -> AppResult<(u32, HashMap<i64, Posting>)> {
+        let rows = sqlx::query_as::<_, (i64, i64, String, i64)>(
+            "SELECT p.document_id, p.term_freq, p.positions, d.token_count
+             FROM search_postings p
+             JOIN search_documents d ON d.id = p.document_id
+             WHERE p.term = ?",
+        )
+        .bind(term)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let postings: HashMap<i64, Posting> = rows
+            .into_iter()
+            .map(|(doc_id, term_freq, positions, doc_len)| {
+                (
+                    doc_id,
+                    Posting {
+                        term_freq: term_freq as u32,
+                        doc_len: doc_len as u32,
+                        positions: decode_positions(&positions),
+                    },
+                )
+            })
+            .collect();
+        Ok((postings.len() as u32, postings))
+    }
+}
+
+/// Keep the index fresh by syncing on a fixed interval
+pub fn spawn_background_sync(pool: DbPool, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let index = FullTextIndex::new(pool);
+        loop {
+            match index.sync().await {
+                Ok(summary) if summary.indexed + summary.removed > 0 => tracing::info!(
+                    "Search index updated: {} indexed, {} removed",
+                    summary.indexed,
+                    summary.removed
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::error!("Search index sync failed: {}", e),
+            }
+            sleep(interval).await;
+        }
+    })
+}
+
+const EXHIBIT_SOURCE_SQL: &str =
+    "SELECT id, exhibit_label, document_name, description, ai_extracted_text FROM exhibits";
+const COMMUNICATION_SOURCE_SQL: &str =
+    "SELECT id, communication_date, sender, subject, message_content FROM communications";
+
+async fn load_sources(pool: &DbPool) -> AppResult<Vec<SearchDocument>> {
+    let exhibits = sqlx::query(EXHIBIT_SOURCE_SQL).fetch_all(pool).await?;
+    let communications = sqlx::query(COMMUNICATION_SOURCE_SQL).fetch_all(pool).await?;
+
+    Ok(exhibits
+        .iter()
+        .map(exhibit_document)
+        .chain(communications.iter().map(communication_document))
+        .collect())
+}
+
+/// The current version of one source row, or `None` once it has been deleted
+async fn load_source(
+    pool: &DbPool,
+    source_type: &str,
+    source_id: i64,
+) -> AppResult<Option<SearchDocument>> {
+    let (sql, to_document): (&str, fn(&SqliteRow) -> SearchDocument) = match source_type {
+        "exhibit" => (EXHIBIT_SOURCE_SQL, exhibit_document),
+        "communication" => (COMMUNICATION_SOURCE_SQL, communication_document),
+        _ => return Ok(None),
+    };
+    let row = sqlx::query(&format!("{} WHERE id = ?", sql))
+        .bind(source_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(to_document))
+}
+
+fn exhibit_document(row: &SqliteRow) -> SearchDocument {
+    let label: Option<String> = row.get("exhibit_label");
+    let name: String = row.get("document_name");
+    let body = [
+        row.get::<Option<String>, _>("description"),
+        row.get::<Option<String>, _>("ai_extracted_text"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n\n");
+
+    SearchDocument {
+        source_type: "exhibit".to_string(),
+        source_id: row.get("id"),
+        title: match label {
+            Some(label) => format!("{}: {}", label, name),
+            None => name,
+        },
+        body,
+    }
+}
+
+fn communication_document(row: &SqliteRow) -> SearchDocument {
+    let date: String = row.get("communication_date");
+    let sender: Option<String> = row.get("sender");
+    let subject: Option<String> = row.get("subject");
+
+    SearchDocument {
+        source_type: "communication".to_string(),
+        source_id: row.get("id"),
+        title: format!(
+            "{} {} — {}",
+            date,
+            sender.unwrap_or_default(),
+            subject.unwrap_or_default()
+        ),
+        body: row
+            .get::<Option<String>, _>("message_content")
+            .unwrap_or_default(),
+    }
+}
+
+/// Drop a source record from the index inside the caller's transaction
+pub async fn remove_indexed(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    source_type: &str,
+    source_id: i64,
+) -> AppResult<()> {
+    let doc_id: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM search_documents WHERE source_type = ? AND source_id = ?",
+    )
+    .bind(source_type)
+    .bind(source_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(doc_id) = doc_id {
+        clear_postings(tx, doc_id).await?;
+        sqlx::query("DELETE FROM search_documents WHERE id = ?")
+            .bind(doc_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn clear_postings(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    doc_id: i64,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE search_terms SET doc_freq = doc_freq - 1
+         WHERE term IN (SELECT term FROM search_postings WHERE document_id = ?)",
+    )
+    .bind(doc_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM search_terms WHERE doc_freq <= 0")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM search_postings WHERE document_id = ?")
+        .bind(doc_id)
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
+
+fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legal::access_control::AccessController;
+
+    async fn add_communication(pool: &DbPool, subject: &str, content: &str, case_id: Option<i64>) -> i64 {
+        sqlx::query(
+            "INSERT INTO communications (communication_date, sender, subject, message_content, case_id)
+             VALUES ('2024-05-01', 'Parent A', ?, ?, ?)",
+        )
+        .bind(subject)
+        .bind(content)
+        .bind(case_id)
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_rowid()
+    }
+
+    fn sources(results: &SearchResults) -> Vec<(String, i64)> {
+        results.hits.iter().map(|h| (h.source_type.clone(), h.source_id)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sync_reads_only_changed_rows() {
+        let pool = crate::db::create_pool("sqlite::memory:").await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let index = FullTextIndex::new(pool.clone());
+        let all = MatterScope::unrestricted();
+
+        let pickup = add_communication(&pool, "Pickup", "Pickup moved to the school gate", None).await;
+        sqlx::query("INSERT INTO exhibits (exhibit_label, document_name, description) VALUES ('A', 'Calendar.pdf', 'School gate schedule')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let first = index.sync().await.unwrap();
+        assert_eq!((first.indexed, first.unchanged, first.removed), (2, 0, 0));
+        // Nothing changed, so nothing is read
+        let idle = index.sync().await.unwrap();
+        assert_eq!((idle.indexed, idle.unchanged), (0, 0));
+
+        let stored: String = sqlx::query_scalar("SELECT content FROM search_documents WHERE source_type = 'communication'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(stored.is_empty());
+
+        let results = index.search("\"school gate\"", 10, &all).await.unwrap();
+        assert_eq!(results.total_hits, 2);
+        assert!(results.hits.iter().any(|h| h.snippet.contains("<mark>school</mark> <mark>gate</mark>")));
+        // Snippets keep the source's case and punctuation
+        assert!(results.hits.iter().any(|h| h.snippet.contains("A: Calendar.pdf") && h.snippet.contains("<mark>School</mark>")));
+
+        // One edit and one delete: the untouched exhibit is never re-read
+        sqlx::query("UPDATE communications SET message_content = 'Pickup moved to the library' WHERE id = ?")
+            .bind(pickup)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let dinner = add_communication(&pool, "Dinner", "Dinner on Sunday", None).await;
+        sqlx::query("DELETE FROM communications WHERE id = ?")
+            .bind(dinner)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let second = index.sync().await.unwrap();
+        assert_eq!((second.indexed, second.unchanged, second.removed), (1, 0, 0));
+
+        let results = index.search("gate", 10, &all).await.unwrap();
+        assert_eq!(sources(&results), vec![("exhibit".to_string(), 1)]);
+        assert_eq!(index.search("library", 10, &all).await.unwrap().total_hits, 1);
+        assert_eq!(index.search("dinner", 10, &all).await.unwrap().total_hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_drops_documents_outside_scope() {
+        let pool = crate::db::create_pool("sqlite::memory:").await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+        let case_id = sqlx::query("INSERT INTO case_info (docket_number, case_title, court) VALUES ('24-2-00001-1', 'Smith v. Jones', 'King County')")
+            .execute(&pool)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+        let restricted = add_communication(&pool, "Settlement", "Settlement offer attached", Some(case_id)).await;
+        let unfiled = add_communication(&pool, "Settlement", "Settlement call on Monday", None).await;
+        let index = FullTextIndex::new(pool.clone());
+        index.sync().await.unwrap();
+
+        let matters = AccessController::open(pool.clone()).await.unwrap().matters();
+        // The matter is limited to the partner
+        sqlx::query(
+            "INSERT INTO matter_restrictions (case_id, restricted_by, restricted_at) VALUES (?1, 'partner', '2024-01-01');
+             INSERT INTO matter_access (case_id, user_id, granted_by, granted_at) VALUES (?1, 'partner', 'partner', '2024-01-01');",
+        )
+        .bind(case_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let partner = index.search("settlement", 10, &matters.scope("partner").await.unwrap()).await.unwrap();
+        assert!(sources(&partner).contains(&("communication".to_string(), restricted)));
+        assert_eq!(partner.total_hits, 2);
+        let lateral = index.search("settlement", 10, &matters.scope("lateral").await.unwrap()).await.unwrap();
+        assert_eq!(lateral.total_hits, 1);
+        assert_eq!(sources(&lateral), vec![("communication".to_string(), unfiled)]);
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::Utc;
+use moodbridge_rust::legal::access_control::{User, UserRole};
+use moodbridge_rust::legal::access_middleware::SUPERVISOR_TOKEN_HEADER;
+use moodbridge_rust::legal::audit_log::AuditSearchCriteria;
+use moodbridge_rust::legal::LegalOperationType;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+/// The application with an attorney and the paralegal they supervise, and their tokens
+async fn firm() -> (common::TestApp, String, String) {
+    let app = common::TestApp::new().await;
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    app.access.add_user(User { supervisor_id: Some("atty".to_string()), ..common::user("para", UserRole::Paralegal) }).await.unwrap();
+    let attorney = app.access.create_auth_token("atty").await.unwrap();
+    let paralegal = app.access.create_auth_token("para").await.unwrap();
+    (app, attorney, paralegal)
+}
+
+#[tokio::test]
+async fn test_paralegal_cannot_run_attorney_only_operations() {
+    let (app, attorney, paralegal) = firm().await;
+    common::consent_to(&app.consent, "atty", LegalOperationType::AILegalAdvice).await;
+    common::consent_to(&app.consent, "para", LegalOperationType::SemanticSearch).await;
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+    let prompt = json!({ "prompt": "Summarise the parenting plan", "input_type": "text" });
+
+    let denied = client
+        .post(format!("{}/api/ai/prompt", base))
+        .bearer_auth(&paralegal)
+        .header(SUPERVISOR_TOKEN_HEADER, &attorney)
+        .json(&prompt)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    let body: Value = denied.json().await.unwrap();
+    assert_eq!(body["success"], false);
+
+    let allowed = client.post(format!("{}/api/ai/prompt", base)).bearer_auth(&attorney).json(&prompt).send().await.unwrap();
+    assert_eq!(allowed.status(), StatusCode::OK);
+
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&paralegal).send().await.unwrap();
+    assert_eq!(search.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_requests_need_a_valid_token_except_public_routes() {
+    let (app, attorney, _) = firm().await;
+    app.access.revoke_auth_token(&attorney).await.unwrap();
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+
+    let health = client.get(format!("{}/api/health", base)).send().await.unwrap();
+    assert_eq!(health.status(), StatusCode::OK);
+
+    let missing = client.get(format!("{}/api/search?q=visit", base)).send().await.unwrap();
+    assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+
+    let forged = client
+        .get(format!("{}/api/search?q=visit", base))
+        .bearer_auth(format!("auth_atty_{}", Utc::now().timestamp()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+    let revoked = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&attorney).send().await.unwrap();
+    assert_eq!(revoked.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_granted_paralegal_needs_supervising_attorney() {
+    let (app, attorney, paralegal) = firm().await;
+    app.access
+        .grant_temporary_permission("para", LegalOperationType::DocumentModification, "atty", chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    common::consent_to(&app.consent, "para", LegalOperationType::DocumentModification).await;
+    let (controller, audit) = (app.access.clone(), app.audit.clone());
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+    let dir = tempfile::tempdir().unwrap();
+    let change = json!({ "content": "Revised paragraph 4", "target_file": dir.path().join("affidavit.md") });
+
+    let unsupervised = client.post(format!("{}/api/data/commit", base)).bearer_auth(&paralegal).json(&change).send().await.unwrap();
+    assert_eq!(unsupervised.status(), StatusCode::FORBIDDEN);
+
+    // A paralegal's own token doesn't count as supervision
+    let self_signed = client
+        .post(format!("{}/api/data/commit", base))
+        .bearer_auth(&paralegal)
+        .header(SUPERVISOR_TOKEN_HEADER, &paralegal)
+        .json(&change)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(self_signed.status(), StatusCode::FORBIDDEN);
+
+    let supervised = client
+        .post(format!("{}/api/data/commit", base))
+        .bearer_auth(&paralegal)
+        .header(SUPERVISOR_TOKEN_HEADER, &attorney)
+        .json(&change)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(supervised.status(), StatusCode::OK);
+    let criteria = AuditSearchCriteria {
+        user_id: Some("para".to_string()),
+        operation_type: Some(LegalOperationType::DocumentModification),
+        start_date: None,
+        end_date: None,
+        compliance_status: None,
+        data_classification: None,
+        limit: None,
+    };
+    let entries = audit.search_entries(&criteria).await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].operation_details["supervised_by"], "atty");
+
+    controller.revoke_permission("para", LegalOperationType::DocumentModification, "atty").await.unwrap();
+    let revoked = client
+        .post(format!("{}/api/data/commit", base))
+        .bearer_auth(&paralegal)
+        .header(SUPERVISOR_TOKEN_HEADER, &attorney)
+        .json(&change)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revoked.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_ethical_wall_blocks_matter_routes_for_screened_users() {
+    let (app, attorney, _) = firm().await;
+    app.access.add_user(common::attorney("lateral")).await.unwrap();
+    let lateral = app.access.create_auth_token("lateral").await.unwrap();
+    app.access
+        .matters()
+        .create_wall("Roe screen", "Represented Roe at prior firm", "atty", &["lateral".to_string()], &[12])
+        .await
+        .unwrap();
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+
+    let screened = client.get(format!("{}/api/matters/12/parties", base)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(screened.status(), StatusCode::FORBIDDEN);
+
+    let other_matter = client.get(format!("{}/api/matters/13/parties", base)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(other_matter.status(), StatusCode::OK);
+
+    let unscreened = client.get(format!("{}/api/matters/12/parties", base)).bearer_auth(&attorney).send().await.unwrap();
+    assert_eq!(unscreened.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_attorneys_manage_access_lists_and_walls_over_http() {
+    let (app, attorney, _) = firm().await;
+    app.access.add_user(common::attorney("lateral")).await.unwrap();
+    let lateral = app.access.create_auth_token("lateral").await.unwrap();
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+
+    let granted = client
+        .post(format!("{}/api/matters/12/access", base))
+        .bearer_auth(&attorney)
+        .json(&json!({ "user_id": "atty" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(granted.status(), StatusCode::OK);
+    let listed: Value = client
+        .get(format!("{}/api/matters/12/access", base))
+        .bearer_auth(&attorney)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!((listed["restricted"].clone(), listed["team"].clone()), (json!(true), json!(["atty"])));
+    let off_list = client.get(format!("{}/api/matters/12/access", base)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(off_list.status(), StatusCode::FORBIDDEN);
+
+    let wall: Value = client
+        .post(format!("{}/api/walls", base))
+        .bearer_auth(&attorney)
+        .json(&json!({ "name": "Roe screen", "reason": "Prior representation", "members": ["lateral"], "case_ids": [13] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let wall_id = wall["wall"]["wall_id"].as_str().unwrap();
+    let screened = client.get(format!("{}/api/matters/13/parties", base)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(screened.status(), StatusCode::FORBIDDEN);
+
+    // The screened attorney can't take their own wall down
+    let self_lift = client.post(format!("{}/api/walls/{}/lift", base, wall_id)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(self_lift.status(), StatusCode::FORBIDDEN);
+
+    let lifted = client.post(format!("{}/api/walls/{}/lift", base, wall_id)).bearer_auth(&attorney).send().await.unwrap();
+    assert_eq!(lifted.status(), StatusCode::OK);
+    let unscreened = client.get(format!("{}/api/matters/13/parties", base)).bearer_auth(&lateral).send().await.unwrap();
+    assert_eq!(unscreened.status(), StatusCode::OK);
+
+    let revoked = client.delete(format!("{}/api/matters/12/access/atty", base)).bearer_auth(&attorney).send().await.unwrap();
+    assert_eq!(revoked.status(), StatusCode::OK);
+}
@@ -1,12 +1,15 @@
 use axum::extract::{Json as AxumJson, Query, State};
+use axum::Extension;
 use moodbridge_rust::db;
 use moodbridge_rust::handlers::{
-    ai_prompt, ai_voice, dashboard_data, diff_data, health_check, DiffQuery,
+    ai_prompt, ai_voice, dashboard_data, diff_data, health_check, DiffQuery, VoiceQuery,
 };
 use moodbridge_rust::models::requests::*;
 use serde_json::json;
 use validator::Validate;
 
+mod common;
+
 #[tokio::test]
 async fn test_health_check() {
     let response = health_check().await.unwrap();
@@ -18,7 +21,9 @@ async fn test_health_check() {
 async fn test_dashboard_data() {
     let pool = db::create_pool("sqlite::memory:").await.unwrap();
     db::run_migrations(&pool).await.unwrap();
-    let response = dashboard_data(State(pool)).await.unwrap();
+    let response = dashboard_data(State(pool), Extension(common::authenticated_attorney()))
+        .await
+        .unwrap();
     // Dashboard data returns Json<Value>, so we just check it's successful
     assert!(response.0.is_object());
 }
@@ -28,7 +33,13 @@ async fn test_ai_prompt() {
     let pool = db::create_pool("sqlite::memory:").await.unwrap();
     db::run_migrations(&pool).await.unwrap();
     let payload = json!({ "prompt": "Explain the legal term", "input_type": "text" });
-    let response = ai_prompt(State(pool), AxumJson(payload)).await.unwrap();
+    let response = ai_prompt(
+        State(pool),
+        Extension(common::authenticated_attorney()),
+        AxumJson(payload),
+    )
+    .await
+    .unwrap();
     // AI prompt returns Json<Value>, so we just check it's successful
     assert!(response.0.is_object());
 }
@@ -38,9 +49,10 @@ async fn test_ai_voice() {
     let pool = db::create_pool("sqlite::memory:").await.unwrap();
     db::run_migrations(&pool).await.unwrap();
     let body = axum::body::Bytes::from_static(b"test audio data");
-    let response = ai_voice(State(pool), body).await.unwrap();
-    // AI voice returns Json<Value>, so we just check it's successful
-    assert!(response.0.is_object());
+    let query = Query(VoiceQuery { sender: None, case_id: None, store_only: None });
+    let response = ai_voice(State(pool), Extension(common::authenticated_attorney()), query, body).await;
+    // Bytes that cannot be decoded as audio are a bad request
+    assert_eq!(response.unwrap_err(), axum::http::StatusCode::BAD_REQUEST);
 }
 
 #[tokio::test]
@@ -0,0 +1,125 @@
+//! Helpers shared by the integration tests
+
+// Each test binary compiles this module and uses only part of it
+#![allow(dead_code)]
+
+use axum::Router;
+use chrono::Utc;
+use moodbridge_rust::app::create_app;
+use moodbridge_rust::auth::AuthManager;
+use moodbridge_rust::db::{self, DbPool};
+use moodbridge_rust::legal::access_control::{AccessController, User, UserRole};
+use moodbridge_rust::legal::access_middleware::AuthenticatedUser;
+use moodbridge_rust::legal::audit_log::AuditLogger;
+use moodbridge_rust::legal::consent::ConsentManager;
+use moodbridge_rust::legal::ethical_walls::MatterScope;
+use moodbridge_rust::legal::retention::RetentionScheduler;
+use moodbridge_rust::legal::{ConsentRecord, LegalOperationType};
+use std::collections::HashSet;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+
+/// What `create_app` is built from, over a fresh in-memory database. Tests
+/// replace the parts they need to set up differently before serving it.
+pub struct TestApp {
+    pub pool: DbPool,
+    pub access: AccessController,
+    pub consent: ConsentManager,
+    pub audit: AuditLogger,
+    pub retention: RetentionScheduler,
+    pub auth: Option<Arc<AuthManager>>,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        let pool = db::create_pool("sqlite::memory:").await.unwrap();
+        db::run_migrations(&pool).await.unwrap();
+        Self {
+            access: AccessController::open(pool.clone()).await.unwrap(),
+            consent: ConsentManager::open(pool.clone()).await.unwrap(),
+            audit: AuditLogger::new(),
+            retention: RetentionScheduler::open(pool.clone()).await.unwrap(),
+            auth: None,
+            pool,
+        }
+    }
+
+    /// Serve the application's router and return its base URL
+    pub async fn serve(self) -> String {
+        self.serve_on(TcpListener::bind("127.0.0.1:0").unwrap()).await
+    }
+
+    pub async fn serve_on(self, listener: TcpListener) -> String {
+        let app = create_app(self.pool, None, self.access, self.consent, self.audit, self.retention, self.auth).await;
+        serve_on(listener, app)
+    }
+}
+
+/// Serve `app` on a free local port and return its base URL
+pub fn serve(app: Router) -> String {
+    serve_on(TcpListener::bind("127.0.0.1:0").unwrap(), app)
+}
+
+/// Serve `app` on a listener bound earlier, for apps that need their own URL to build
+pub fn serve_on(listener: TcpListener, app: Router) -> String {
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// An active firm user; attorneys get a bar number
+pub fn user(user_id: &str, role: UserRole) -> User {
+    User {
+        user_id: user_id.to_string(),
+        email: format!("{}@firm.example", user_id),
+        bar_number: (role == UserRole::Attorney).then(|| "WSBA-1".to_string()),
+        jurisdiction: Some("WA".to_string()),
+        role,
+        supervisor_id: None,
+        permissions: HashSet::new(),
+        created_at: Utc::now(),
+        last_login: None,
+        active: true,
+    }
+}
+
+pub fn attorney(user_id: &str) -> User {
+    user(user_id, UserRole::Attorney)
+}
+
+/// What the access middleware hands a handler for an attorney with no matter restrictions
+pub fn authenticated_attorney() -> AuthenticatedUser {
+    AuthenticatedUser {
+        user_id: "attorney".to_string(),
+        role: UserRole::Attorney,
+        operation_type: LegalOperationType::CollaborationMetrics,
+        supervised_by: None,
+        matters: MatterScope::unrestricted(),
+    }
+}
+
+/// Record `user_id`'s consent to the current form for `operation_type`
+pub async fn consent_to(consent: &ConsentManager, user_id: &str, operation_type: LegalOperationType) {
+    let form = consent.current_form(&operation_type).await.unwrap();
+    consent
+        .record_consent(ConsentRecord {
+            user_id: user_id.to_string(),
+            operation_type,
+            consent_given: true,
+            timestamp: Utc::now(),
+            ip_address: None,
+            user_agent: None,
+            consent_text: form.consent_text,
+            form_version: Some(form.version),
+            attorney_supervised: true,
+            disclaimer_acknowledged: true,
+        })
+        .await
+        .unwrap();
+}
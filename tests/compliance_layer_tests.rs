@@ -0,0 +1,60 @@
+use moodbridge_rust::legal::audit_log::AuditSearchCriteria;
+use moodbridge_rust::legal::{ComplianceStatus, LegalOperationType};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+#[tokio::test]
+async fn test_prompt_requires_consent_and_every_decision_is_audited() {
+    let app = common::TestApp::new().await;
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    let token = app.access.create_auth_token("atty").await.unwrap();
+    let (consent, audit) = (app.consent.clone(), app.audit.clone());
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    let blocked = http
+        .post(format!("{}/api/ai/prompt", base))
+        .bearer_auth(&token)
+        .json(&json!({ "prompt": "Summarise the parenting plan", "input_type": "text" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(blocked.status(), StatusCode::PRECONDITION_REQUIRED);
+    let body: Value = blocked.json().await.unwrap();
+    assert_eq!(body["compliance"]["operation_type"], "AILegalAdvice");
+    let missing = body["compliance"]["missing"].as_array().unwrap();
+    assert_eq!(missing[0]["kind"], "consent");
+    assert_eq!(missing[0]["form_version"], 1);
+    assert_eq!(missing[0]["form_url"], "/api/consent/forms/AILegalAdvice");
+    assert_eq!(missing[1]["kind"], "disclaimer");
+    assert!(!missing[1]["text"].as_str().unwrap().is_empty());
+
+    common::consent_to(&consent, "atty", LegalOperationType::AILegalAdvice).await;
+
+    let allowed = http
+        .post(format!("{}/api/ai/prompt", base))
+        .bearer_auth(&token)
+        .json(&json!({ "prompt": "Summarise the parenting plan", "input_type": "text" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(allowed.status(), StatusCode::OK);
+
+    let criteria = AuditSearchCriteria {
+        user_id: Some("atty".to_string()),
+        operation_type: Some(LegalOperationType::AILegalAdvice),
+        start_date: None,
+        end_date: None,
+        compliance_status: None,
+        data_classification: None,
+        limit: None,
+    };
+    let entries = audit.search_entries(&criteria).await;
+    assert_eq!(entries.len(), 2);
+    let allowed_flags: Vec<&Value> = entries.iter().map(|entry| &entry.operation_details["allowed"]).collect();
+    assert!(allowed_flags.contains(&&json!(true)) && allowed_flags.contains(&&json!(false)));
+    assert!(entries.iter().any(|entry| entry.compliance_status == ComplianceStatus::RequiresAttorneyReview));
+    assert!(entries.iter().all(|entry| entry.operation_details["route"] == "/api/ai/prompt"));
+}
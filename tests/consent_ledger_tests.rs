@@ -0,0 +1,116 @@
+use moodbridge_rust::legal::access_control::UserRole;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+#[tokio::test]
+async fn test_consent_ledger_records_signature_withdrawal_and_exports_report() {
+    let app = common::TestApp::new().await;
+    app.access.add_user(common::user("client", UserRole::User)).await.unwrap();
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    let client_token = app.access.create_auth_token("client").await.unwrap();
+    let attorney_token = app.access.create_auth_token("atty").await.unwrap();
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    let form = http
+        .get(format!("{}/api/consent/forms/VoiceRecording", base))
+        .bearer_auth(&client_token)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(form.contains("Form version 1") && form.contains("form_version: 1"));
+
+    let stale = http
+        .post(format!("{}/api/consent", base))
+        .bearer_auth(&client_token)
+        .json(&json!({ "operation_type": "VoiceRecording", "form_version": 0, "consent_given": true, "disclaimer_acknowledged": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), StatusCode::CONFLICT);
+
+    let granted = http
+        .post(format!("{}/api/consent", base))
+        .bearer_auth(&client_token)
+        .header("x-forwarded-for", "198.51.100.4, 10.0.0.1")
+        .header("user-agent", "ConsentTest/1.0")
+        .json(&json!({ "operation_type": "VoiceRecording", "form_version": 1, "consent_given": true, "disclaimer_acknowledged": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(granted.status(), StatusCode::OK);
+
+    let status: Value = http
+        .get(format!("{}/api/consent/status/VoiceRecording", base))
+        .bearer_auth(&client_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"]["consent_given"], true);
+
+    let withdrawn = http
+        .post(format!("{}/api/consent/withdraw", base))
+        .bearer_auth(&client_token)
+        .json(&json!({ "operation_type": "VoiceRecording", "reason": "No more recordings" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(withdrawn.status(), StatusCode::OK);
+
+    // Clients only see their own report; attorneys can pull anyone's
+    let forbidden = http
+        .get(format!("{}/api/consent/report?user_id=atty", base))
+        .bearer_auth(&client_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+
+    let csv = http
+        .get(format!("{}/api/consent/report?user_id=client&format=CSV", base))
+        .bearer_auth(&attorney_token)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 3);
+    // Nothing trusted sits in front of the server, so the forwarded header is ignored
+    assert!(rows[1].contains(",granted,1,") && rows[1].contains("127.0.0.1") && rows[1].contains("ConsentTest/1.0"));
+    assert!(!csv.contains("198.51.100.4"));
+    assert!(rows[2].contains(",withdrawn,1,") && rows[2].ends_with("No more recordings"));
+}
+
+#[tokio::test]
+async fn test_signature_takes_the_client_address_from_a_trusted_proxy() {
+    let mut app = common::TestApp::new().await;
+    app.access.add_user(common::user("client", UserRole::User)).await.unwrap();
+    let token = app.access.create_auth_token("client").await.unwrap();
+    app.consent = app.consent.with_trusted_proxies(vec!["127.0.0.1".parse().unwrap()]);
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    // The client can prepend whatever it likes; the proxy appends the address it saw
+    let granted = http
+        .post(format!("{}/api/consent", base))
+        .bearer_auth(&token)
+        .header("x-forwarded-for", "203.0.113.9, 198.51.100.4")
+        .json(&json!({ "operation_type": "VoiceRecording", "form_version": 1, "consent_given": true, "disclaimer_acknowledged": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(granted.status(), StatusCode::OK);
+
+    let entry: Value = granted.json().await.unwrap();
+    assert_eq!(entry["entry"]["signature"]["ip_address"], "198.51.100.4");
+}
@@ -17,6 +17,22 @@ async fn test_database_migrations() {
     assert!(result.is_ok());
 }
 
+#[tokio::test]
+async fn test_database_migrations_rerun_and_upgrade_older_tables() {
+    let pool = create_pool("sqlite::memory:").await.unwrap();
+    run_migrations(&pool).await.unwrap();
+    run_migrations(&pool).await.unwrap();
+
+    // A database from before matters were tracked gets the column on the next start
+    sqlx::query("ALTER TABLE exhibits DROP COLUMN case_id").execute(&pool).await.unwrap();
+    run_migrations(&pool).await.unwrap();
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('exhibits')")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(columns.contains(&"case_id".to_string()));
+}
+
 #[tokio::test]
 async fn test_sample_data_seeding() {
     let pool = create_pool("sqlite::memory:").await.unwrap();
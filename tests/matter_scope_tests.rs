@@ -0,0 +1,198 @@
+use chrono::Utc;
+use moodbridge_rust::ai::risk_model::{LogisticRiskModel, TrainingMetrics, FEATURE_NAMES};
+use moodbridge_rust::db::DbPool;
+use moodbridge_rust::legal::LegalOperationType;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+/// Denial, and messages before it, on each of two matters
+struct Firm {
+    pool: DbPool,
+    open_denial: i64,
+    walled_denial: i64,
+}
+
+async fn add_matter(pool: &DbPool, docket: &str) -> i64 {
+    sqlx::query("INSERT INTO case_info (docket_number, case_title, court) VALUES (?, ?, 'King County')")
+        .bind(docket)
+        .bind(format!("Matter {}", docket))
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_rowid()
+}
+
+/// A calm message 60 days out and a hostile one 2 days before a denial on `case_id`
+async fn add_history(pool: &DbPool, case_id: i64, sender: &str, reason: &str) -> i64 {
+    for (date, hostility) in [("2024-01-01", 0.1), ("2024-02-28", 0.9)] {
+        let communication_id = sqlx::query(
+            "INSERT INTO communications (communication_date, sender, medium, message_content, case_id)
+             VALUES (?, ?, 'email', 'message', ?)",
+        )
+        .bind(date)
+        .bind(sender)
+        .bind(case_id)
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        sqlx::query(
+            "INSERT INTO communication_tone_scores (communication_id, sentiment, hostility, escalation)
+             VALUES (?, 0.0, ?, ?)",
+        )
+        .bind(communication_id)
+        .bind(hostility)
+        .bind(hostility)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    sqlx::query(
+        "INSERT INTO placement_denials (denied_date, duration_hours, denial_reason, violation_category, case_id)
+         VALUES ('2024-03-01', 8.0, ?, 'schedule', ?)",
+    )
+    .bind(reason)
+    .bind(case_id)
+    .execute(pool)
+    .await
+    .unwrap()
+    .last_insert_rowid()
+}
+
+/// Served application with the lateral hire screened from the second matter;
+/// returns its URL and the tokens of the attorney and the lateral hire
+async fn firm() -> (Firm, String, String, String) {
+    let app = common::TestApp::new().await;
+    let pool = app.pool.clone();
+
+    let open_matter = add_matter(&pool, "24-2-00012-1").await;
+    let walled_matter = add_matter(&pool, "24-2-00013-1").await;
+    let open_denial = add_history(&pool, open_matter, "open-sender", "Open matter denial").await;
+    let walled_denial = add_history(&pool, walled_matter, "walled-sender", "Walled matter denial").await;
+    save_model(&pool).await;
+
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    app.access.add_user(common::attorney("lateral")).await.unwrap();
+    app.access
+        .matters()
+        .create_wall("Roe screen", "Represented Roe at prior firm", "atty", &["lateral".to_string()], &[walled_matter])
+        .await
+        .unwrap();
+    for user_id in ["atty", "lateral"] {
+        for operation_type in [
+            LegalOperationType::AILegalAdvice,
+            LegalOperationType::ClientDataProcessing,
+            LegalOperationType::DocumentIntelligence,
+        ] {
+            common::consent_to(&app.consent, user_id, operation_type).await;
+        }
+    }
+    let attorney = app.access.create_auth_token("atty").await.unwrap();
+    let lateral = app.access.create_auth_token("lateral").await.unwrap();
+
+    let base = app.serve().await;
+    (Firm { pool, open_denial, walled_denial }, base, attorney, lateral)
+}
+
+async fn save_model(pool: &DbPool) {
+    let features = FEATURE_NAMES.len();
+    LogisticRiskModel {
+        version: "test".to_string(),
+        feature_names: FEATURE_NAMES.iter().map(|name| name.to_string()).collect(),
+        weights: vec![0.1; features],
+        bias: 0.0,
+        feature_means: vec![0.0; features],
+        feature_stds: vec![1.0; features],
+        metrics: TrainingMetrics { examples: 10, positive_rate: 0.5, log_loss: 0.5, accuracy: 0.8 },
+        trained_at: Utc::now(),
+    }
+    .save(pool)
+    .await
+    .unwrap();
+}
+
+async fn get(base: &str, path: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new().get(format!("{}{}", base, path)).bearer_auth(token).send().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_score_denials_leaves_walled_matters_alone() {
+    let (firm, base, _, lateral) = firm().await;
+
+    let response: Value = reqwest::Client::new()
+        .post(format!("{}/api/ai/risk/score", base))
+        .bearer_auth(&lateral)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["denials_scored"], 1);
+
+    let analysed: Vec<i64> = sqlx::query_scalar("SELECT id FROM placement_denials WHERE ai_analysis IS NOT NULL")
+        .fetch_all(&firm.pool)
+        .await
+        .unwrap();
+    assert_eq!(analysed, vec![firm.open_denial]);
+}
+
+#[tokio::test]
+async fn test_explain_denial_hides_walled_denials() {
+    let (firm, base, _, lateral) = firm().await;
+
+    let walled = get(&base, &format!("/api/ai/risk/{}/explain", firm.walled_denial), &lateral).await;
+    assert_eq!(walled.status(), StatusCode::NOT_FOUND);
+
+    let open: Value = get(&base, &format!("/api/ai/risk/{}/explain", firm.open_denial), &lateral)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(open["explanation"]["denial_id"], firm.open_denial);
+}
+
+#[tokio::test]
+async fn test_tone_timeline_excludes_walled_senders() {
+    let (_, base, _, lateral) = firm().await;
+
+    let body = get(&base, "/api/communications/tone/timeline", &lateral).await.text().await.unwrap();
+    assert!(body.contains("open-sender"));
+    assert!(!body.contains("walled-sender"));
+}
+
+#[tokio::test]
+async fn test_tone_alerts_exclude_walled_denials() {
+    let (_, base, attorney, lateral) = firm().await;
+    let path = "/api/communications/tone/alerts?window_days=7&min_increase=0.3";
+
+    let everyone = get(&base, path, &attorney).await.text().await.unwrap();
+    assert!(everyone.contains("walled-sender"));
+
+    let body = get(&base, path, &lateral).await.text().await.unwrap();
+    assert!(body.contains("open-sender"));
+    assert!(!body.contains("walled-sender"));
+}
+
+#[tokio::test]
+async fn test_ai_prompt_context_excludes_walled_matters() {
+    let (_, base, _, lateral) = firm().await;
+
+    let response: Value = reqwest::Client::new()
+        .post(format!("{}/api/ai/prompt", base))
+        .bearer_auth(&lateral)
+        .json(&json!({ "prompt": "Show me the stats", "input_type": "text" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(!response.to_string().contains("Walled matter denial"));
+    if response["fallback"] == true {
+        assert_eq!(response["data"]["total_incidents"], 1);
+    }
+}
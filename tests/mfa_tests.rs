@@ -0,0 +1,152 @@
+use chrono::Utc;
+use moodbridge_rust::auth::kms::{KdfParams, KeystoreSecret, LocalKmsProvider};
+use moodbridge_rust::legal::mfa::totp_code;
+use moodbridge_rust::legal::LegalOperationType;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+mod common;
+
+/// The application with MFA secrets in a local KMS, an attorney who has
+/// consented to search and exhibit deletion, and exhibit 7 to delete
+async fn firm(require_mfa: bool) -> (common::TestApp, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let kdf = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+    let kms = LocalKmsProvider::open_with_kdf(dir.path().to_path_buf(), KeystoreSecret::passphrase("test"), kdf)
+        .await
+        .unwrap();
+    let mut app = common::TestApp::new().await;
+    app.access = app.access.with_mfa_required(require_mfa).with_secret_store(Arc::new(kms));
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    common::consent_to(&app.consent, "atty", LegalOperationType::SemanticSearch).await;
+    common::consent_to(&app.consent, "atty", LegalOperationType::ExhibitDeletion).await;
+    sqlx::query("INSERT INTO exhibits (id, document_name, case_id) VALUES (7, 'Visit log', 1)")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    (app, dir)
+}
+
+/// Enroll through the API and return the recovery codes
+async fn enroll(client: &reqwest::Client, base: &str, token: &str) -> Vec<String> {
+    let enrollment: Value = client
+        .post(format!("{}/api/mfa/enroll", base))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let uri = enrollment["provisioning_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/") && uri.contains("issuer=MoodBridge"));
+
+    let secret = base32_secret(enrollment["secret"].as_str().unwrap());
+    let confirmed = client
+        .post(format!("{}/api/mfa/enroll/confirm", base))
+        .bearer_auth(token)
+        .json(&json!({ "code": totp_code(&secret, Utc::now().timestamp()) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(confirmed.status(), StatusCode::OK);
+    let body: Value = confirmed.json().await.unwrap();
+    body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect()
+}
+
+fn base32_secret(encoded: &str) -> Vec<u8> {
+    const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let (mut buffer, mut bits, mut output) = (0u32, 0, Vec::new());
+    for c in encoded.chars() {
+        buffer = (buffer << 5) | ALPHABET.find(c).unwrap() as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    output
+}
+
+#[tokio::test]
+async fn test_sensitive_operations_need_step_up_even_when_mfa_is_optional() {
+    let (app, _dir) = firm(false).await;
+    let token = app.access.create_auth_token("atty").await.unwrap();
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(search.status(), StatusCode::OK);
+
+    let export = client.get(format!("{}/api/audit/export", base)).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(export.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = export.json().await.unwrap();
+    assert_eq!(body["mfa"], "enroll");
+
+    enroll(&client, &base, &token).await;
+    let export = client.get(format!("{}/api/audit/export", base)).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(export.status(), StatusCode::OK);
+    let deletion = client.delete(format!("{}/api/exhibits/7", base)).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(deletion.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_required_mfa_blocks_unverified_sessions_until_they_verify() {
+    let (app, _dir) = firm(true).await;
+    let first = app.access.create_auth_token("atty").await.unwrap();
+    let second = app.access.create_auth_token("atty").await.unwrap();
+    let base = app.serve().await;
+    let client = reqwest::Client::new();
+
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&first).send().await.unwrap();
+    assert_eq!(search.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(search.json::<Value>().await.unwrap()["mfa"], "enroll");
+
+    let recovery_codes = enroll(&client, &base, &first).await;
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&first).send().await.unwrap();
+    assert_eq!(search.status(), StatusCode::OK);
+
+    // Another session of the same user still has to verify
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&second).send().await.unwrap();
+    assert_eq!(search.json::<Value>().await.unwrap()["mfa"], "verify");
+
+    let wrong = client
+        .post(format!("{}/api/mfa/verify", base))
+        .bearer_auth(&second)
+        .json(&json!({ "code": "000000" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+
+    let verified = client
+        .post(format!("{}/api/mfa/verify", base))
+        .bearer_auth(&second)
+        .json(&json!({ "code": recovery_codes[0] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(verified.json::<Value>().await.unwrap()["method"], "RecoveryCode");
+
+    let status: Value = client
+        .get(format!("{}/api/mfa/status", base))
+        .bearer_auth(&second)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"]["enrolled"], true);
+    assert_eq!(status["status"]["recovery_codes_remaining"], 9);
+
+    let search = client.get(format!("{}/api/search?q=visit", base)).bearer_auth(&second).send().await.unwrap();
+    assert_eq!(search.status(), StatusCode::OK);
+}
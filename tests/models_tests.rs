@@ -203,6 +203,10 @@ async fn test_create_case_request_validation() {
         due_date: Some(Utc::now() + chrono::Duration::days(30)),
         tags: Some(vec!["urgent".to_string(), "family".to_string()]),
         metadata: None,
+        parties: Some(vec![CaseParty {
+            name: "Jane Doe".to_string(),
+            role: PartyRole::AdverseParty,
+        }]),
     };
 
     assert!(request.validate().is_ok());
@@ -0,0 +1,234 @@
+use base64::Engine as _;
+use moodbridge_rust::auth::kms::{KdfParams, KeystoreSecret, LocalKmsProvider};
+use moodbridge_rust::auth::mock_idp::{MockIdp, MockIdpFault, MockUser};
+use moodbridge_rust::auth::oidc::{OidcConfig, OidcProvider};
+use moodbridge_rust::auth::{AuthConfigBuilder, AuthManager, AuthProvider, AuthProviderKind, AuthorizationRequest};
+use moodbridge_rust::legal::access_control::{AccessController, User};
+use reqwest::{header, redirect, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+mod common;
+
+const CLIENT_ID: &str = "moodbridge-dashboard";
+
+/// 2048-bit RSA test key (PKCS#8 DER), only ever used to sign mock ID tokens
+const TEST_RSA_PKCS8: &[&str] = &[
+    "MIIEvQIBADANBgkqhkiG9w0BAQEFAASCBKcwggSjAgEAAoIBAQCa7xJ63s/+6ZXqWNqhXawuaZYGzH93CsLfJ8YUedor4HvgrPhq",
+    "ajuUjOZ/4fRxBLmgHgNlYsMfPrzpaBrmu35igfZXCwbwfxD6PjfIzi6kgNG6gJbxPjvEywZENyrKVGVfJnNBPUD7CXcIyEYuLLD5",
+    "VYZYaHnXWkF2MynZaXplpeNAXK1yGkTw/n/1+EhINiN2giZXnxbjNmE2Fsl/mLjUas2hTKoIwY8ncZytmAjq80Wf4+AS4qcR660v",
+    "aTtUDrltqJhi+jI9PzZ4bmQlorVO5NxWpp5qfXFAMreEd1j4HRax63Dcb0We34NFjMavlKdxe0GI/Q5lJrKgvimwbNF5AgMBAAEC",
+    "ggEABRYDx+mMT6iMma+uxqUP78qayJCaPxbI+KEiPx4UZ8QT4PdgpMKlv83Dy88nsnWWn99YWnHMaLhLJIjcuDYPsy1jwC3+HXM4",
+    "ZBSaiXKeRJtvdxU9T0BNHzvQcfkTJCby2HWnSy0W9t6zzAOiDSY9rYOtmDWeyU9ezoHltevA3zkOnVQ1xXt7PipLNuzrgRFG1lWH",
+    "nRtBeJoYDrM/j8jC6q8wxoLnYfFY5BmQnVtpmDBYahsW/NfPu7HFUtUu+qjhFAypaYAaU7DXwTUy9xeQem4r3Gk/3p9JnPguMbFM",
+    "jmrV0yRx9VmIak8q4PLdANhSK3PPVOVWFT15qYhKso42QQKBgQDZcnSNTTsFBdBjM4AiAQbpc10meKgGVH05ZG+chBVPd7cOZBah",
+    "Yrnwvkcw5xCDormJnPBSPYnFJrjEn3Qyh2ht+QzAYkw0LJR9e1B3nY5oovRRccWUFjiQ121i5/2s7qT4834y16emUyUj8e59tpiu",
+    "lxTQQhH22BKgfBG9LKkbQQKBgQC2Z0A+STlrisp4LiCykrZH6A8+p1YSZ2r4/VyYU2V188B4IZkbz1Z8m6dtlaZFeX79xrXYPf8M",
+    "Tz4IPzgYRbpFsVswraKavqzIH+nE7nhGi0mtw6bdGNuN13lwDevzYLCI9bY3z4+W3bVhy/s5jZGa2GjRfRmXXvYgNp4HLhXAOQKB",
+    "gQC3xf+zHD5xNES2UmRMWMnaq2Fijj6TevyKcNPTV94a13aidvyZULMNQAMy1VYjqndcUoLZPqEdOmiV5J1zxvT/XKjmPy1gOv0U",
+    "QA/1x/b/gBk0bA8r5MvRvf/4w1Clwtsye7eAiLpr24FcK7AAFz5a79zrVauRD3ROpgzn8MLLAQKBgA4Q/FVRVEpE08Rc2kyrH79/",
+    "190fDnYw34EAGOnuVH66I3egL6yDWsoVPt4mkkH9UpvDfTaF4WtTmRcNuQe2KN1DBiNE4KtKVQzB2UdElKXsC+yIkA+w4Q2ZNlY7",
+    "3JxSMor9V+YJ7arWwiwejOstTbNBSSQOn7CatCRb0ef/19GRAoGAPK53MHVU1KFZHwFCpF3d2AqD33cK8Ovy//dDzVDBuPl4xSOV",
+    "kDSnNSlnhwB6Ij9WuH0n/KUUf4oTbugJ2WrpSr8J+2Vu4taUcRXKMngSxWS2ePEb/hfYRiVYB4SNKy3OzYLHTIddmeDVnawmjwI6",
+    "6d9rTVaNd771QspJ9rWKYJ8=",
+];
+
+struct Firm {
+    base: String,
+    auth: Arc<AuthManager>,
+    client: reqwest::Client,
+    _kms_dir: TempDir,
+}
+
+/// The application with sign-in through `idp`
+async fn start_app(idp: &MockIdp, access: AccessController) -> Firm {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    let kms_dir = TempDir::new().unwrap();
+    let kdf = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+    let kms = LocalKmsProvider::open_with_kdf(kms_dir.path().to_path_buf(), KeystoreSecret::passphrase("test"), kdf)
+        .await
+        .unwrap();
+    let config = OidcConfig::new(idp.issuer(), idp.client_id())
+        .with_jwks_refresh_interval(std::time::Duration::ZERO);
+    let auth = Arc::new(
+        AuthConfigBuilder::new(Box::new(kms))
+            .with_redirect_base(&base)
+            .with_oidc("firm", config)
+            .build(),
+    );
+
+    let app = common::TestApp { access, auth: Some(auth.clone()), ..common::TestApp::new().await };
+    app.serve_on(listener).await;
+
+    let client = reqwest::Client::builder().redirect(redirect::Policy::none()).build().unwrap();
+    Firm { base, auth, client, _kms_dir: kms_dir }
+}
+
+fn idp_with_user(idp: MockIdp) -> MockIdp {
+    idp.add_user(MockUser {
+        sub: "00u-jane".to_string(),
+        email: "Jane.Doe@firm.example".to_string(),
+        name: "Jane Doe".to_string(),
+    });
+    idp.sign_in_as(Some("00u-jane"));
+    idp
+}
+
+async fn access_with_jane() -> AccessController {
+    let access = AccessController::new();
+    access.add_user(User { email: "jane.doe@firm.example".to_string(), ..common::attorney("jdoe") }).await.unwrap();
+    access
+}
+
+fn location(response: &reqwest::Response) -> String {
+    assert!(response.status().is_redirection(), "expected a redirect, got {}", response.status());
+    response.headers()[header::LOCATION].to_str().unwrap().to_string()
+}
+
+/// Where the IdP sends the browser back to, and the login cookie the browser holds
+struct Callback {
+    url: String,
+    cookie: String,
+}
+
+/// Follow login → IdP → back to our callback, without opening the callback yet
+async fn sign_in(firm: &Firm) -> Callback {
+    let login = firm.client.get(format!("{}/auth/firm/login", firm.base)).send().await.unwrap();
+    let set_cookie = login.headers()[header::SET_COOKIE].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+    let authorize = firm.client.get(location(&login)).send().await.unwrap();
+    Callback { url: location(&authorize), cookie }
+}
+
+/// Open the callback in the browser that started the login
+async fn finish(firm: &Firm, callback: &Callback) -> reqwest::Response {
+    firm.client.get(&callback.url).header(header::COOKIE, &callback.cookie).send().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_sign_in_opens_a_session_and_keeps_tokens_in_the_kms() {
+    let idp = idp_with_user(MockIdp::start(CLIENT_ID).await.unwrap());
+    let firm = start_app(&idp, access_with_jane().await).await;
+
+    let callback = sign_in(&firm).await;
+    assert!(callback.url.starts_with(&format!("{}/auth/firm/callback?code=", firm.base)));
+
+    // Another browser can't finish a login it didn't start
+    let elsewhere = firm.client.get(&callback.url).send().await.unwrap();
+    assert_eq!(elsewhere.status(), StatusCode::UNAUTHORIZED);
+
+    let response = finish(&firm, &callback).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!((body["user_id"].clone(), body["role"].clone()), (json!("jdoe"), json!("Attorney")));
+
+    let token = body["token"].as_str().unwrap();
+    let me: Value = firm
+        .client
+        .get(format!("{}/api/consent/report", firm.base))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(me["user_id"], "jdoe");
+
+    let stored = firm
+        .auth
+        .retrieve_credentials("00u-jane", AuthProviderKind::Custom("oidc".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(stored.email, "Jane.Doe@firm.example");
+    assert!(stored.refresh_token.is_some());
+
+    // The same callback can't be replayed
+    let replay = finish(&firm, &callback).await;
+    assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_tampered_id_tokens_are_rejected() {
+    let idp = idp_with_user(MockIdp::start(CLIENT_ID).await.unwrap());
+    let firm = start_app(&idp, access_with_jane().await).await;
+
+    for fault in [
+        MockIdpFault::WrongNonce,
+        MockIdpFault::WrongAudience,
+        MockIdpFault::WrongIssuer,
+        MockIdpFault::Expired,
+        MockIdpFault::BadSignature,
+    ] {
+        idp.inject_fault(Some(fault));
+        let callback = sign_in(&firm).await;
+        let response = finish(&firm, &callback).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?} was accepted", fault);
+    }
+
+    idp.inject_fault(None);
+    let callback = sign_in(&firm).await;
+    assert_eq!(finish(&firm, &callback).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_forged_state_and_unknown_users_are_refused() {
+    let idp = idp_with_user(MockIdp::start(CLIENT_ID).await.unwrap());
+    idp.add_user(MockUser {
+        sub: "00u-stranger".to_string(),
+        email: "stranger@elsewhere.example".to_string(),
+        name: "Stranger".to_string(),
+    });
+    let firm = start_app(&idp, access_with_jane().await).await;
+
+    let callback = sign_in(&firm).await;
+    let forged = Callback { url: callback.url.replace("state=", "state=x"), cookie: callback.cookie.replace('=', "=x") };
+    assert_eq!(finish(&firm, &forged).await.status(), StatusCode::UNAUTHORIZED);
+
+    idp.sign_in_as(Some("00u-stranger"));
+    let callback = sign_in(&firm).await;
+    assert_eq!(finish(&firm, &callback).await.status(), StatusCode::FORBIDDEN);
+
+    idp.sign_in_as(None);
+    let callback = sign_in(&firm).await;
+    assert!(callback.url.contains("error=login_required"));
+    assert_eq!(finish(&firm, &callback).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_code_exchange_requires_the_matching_pkce_verifier() {
+    let idp = idp_with_user(MockIdp::start(CLIENT_ID).await.unwrap());
+    let provider = OidcProvider::new(
+        OidcConfig::new(idp.issuer(), CLIENT_ID).with_redirect_uri("http://127.0.0.1/callback".to_string()),
+    );
+    let client = reqwest::Client::builder().redirect(redirect::Policy::none()).build().unwrap();
+
+    let request = AuthorizationRequest::new().unwrap();
+    let authorize = client.get(provider.get_auth_url(&request).await.unwrap()).send().await.unwrap();
+    let callback = location(&authorize);
+    let code = callback.split("code=").nth(1).unwrap().split('&').next().unwrap();
+
+    let mut wrong_verifier = request.clone();
+    wrong_verifier.pkce_verifier = AuthorizationRequest::new().unwrap().pkce_verifier;
+    assert!(provider.exchange_code(code, &wrong_verifier).await.is_err());
+    // A failed exchange burns the code
+    assert!(provider.exchange_code(code, &request).await.is_err());
+}
+
+#[tokio::test]
+async fn test_rs256_tokens_and_key_rotation() {
+    let der = base64::engine::general_purpose::STANDARD.decode(TEST_RSA_PKCS8.concat()).unwrap();
+    let idp = idp_with_user(MockIdp::start_with_rsa(CLIENT_ID, &der).await.unwrap());
+    let firm = start_app(&idp, access_with_jane().await).await;
+
+    let callback = sign_in(&firm).await;
+    assert_eq!(finish(&firm, &callback).await.status(), StatusCode::OK);
+
+    // The client has the old JWKS cached and must refetch for the new key ID
+    idp.rotate_key().unwrap();
+    let callback = sign_in(&firm).await;
+    assert_eq!(finish(&firm, &callback).await.status(), StatusCode::OK);
+}
@@ -1,10 +1,13 @@
 use axum::extract::State;
+use axum::Extension;
 use moodbridge_rust::db;
 use moodbridge_rust::handlers::*;
 use moodbridge_rust::models::PlacementDenial;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
 
+mod common;
+
 #[tokio::test]
 async fn test_database_connection_pool_performance() {
     let pool = db::create_pool("sqlite::memory:").await.unwrap();
@@ -40,7 +43,7 @@ async fn test_api_response_time() {
     db::seed_sample_data(&pool).await.unwrap();
 
     let start = Instant::now();
-    let result = dashboard_data(State(pool)).await;
+    let result = dashboard_data(State(pool), Extension(common::authenticated_attorney())).await;
     let duration = start.elapsed();
 
     assert!(result.is_ok());
@@ -81,7 +84,7 @@ async fn test_concurrent_api_requests() {
         .map(|_| {
             let pool = pool.clone();
             tokio::spawn(async move {
-                let result = dashboard_data(State(pool)).await;
+                let result = dashboard_data(State(pool), Extension(common::authenticated_attorney())).await;
                 assert!(result.is_ok());
             })
         })
@@ -103,7 +106,7 @@ async fn test_memory_usage_stability() {
 
     // Perform many operations to test for memory leaks
     for i in 0..100 {
-        let result = dashboard_data(State(pool.clone())).await;
+        let result = dashboard_data(State(pool.clone()), Extension(common::authenticated_attorney())).await;
         assert!(result.is_ok());
 
         // Every 10 iterations, force garbage collection (if available)
@@ -152,7 +155,7 @@ async fn test_large_dataset_performance() {
 
     // Test query performance on large dataset
     let query_start = Instant::now();
-    let result = dashboard_data(State(pool)).await;
+    let result = dashboard_data(State(pool), Extension(common::authenticated_attorney())).await;
     let query_duration = query_start.elapsed();
 
     assert!(result.is_ok());
@@ -166,7 +169,7 @@ async fn test_timeout_handling() {
     db::run_migrations(&pool).await.unwrap();
 
     // Test that operations complete within reasonable timeouts
-    let result = timeout(Duration::from_secs(30), dashboard_data(State(pool))).await;
+    let result = timeout(Duration::from_secs(30), dashboard_data(State(pool), Extension(common::authenticated_attorney()))).await;
 
     assert!(result.is_ok(), "Operation should complete within timeout");
     assert!(result.unwrap().is_ok(), "Operation should succeed");
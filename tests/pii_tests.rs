@@ -0,0 +1,118 @@
+use axum::{routing::post, Json, Router};
+use moodbridge_rust::ai::llm::OpenAiService;
+use moodbridge_rust::ai::{AiConfig, AiService};
+use moodbridge_rust::legal::LegalOperationType;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+// Records what reaches the model and answers by echoing the tokens it was given
+fn mock_openai(seen: Arc<Mutex<Vec<String>>>) -> String {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(move |Json(request): Json<Value>| {
+            let seen = seen.clone();
+            async move {
+                let user = request["messages"][1]["content"].as_str().unwrap_or_default().to_string();
+                seen.lock().unwrap().push(user.clone());
+                let tokens: Vec<&str> = user
+                    .split(['[', ']'])
+                    .skip(1)
+                    .step_by(2)
+                    .collect();
+                Json(json!({
+                    "choices": [{ "message": { "role": "assistant", "content": format!("Mentions [{}]", tokens.join("] and [")) } }]
+                }))
+            }
+        }),
+    );
+    common::serve(app)
+}
+
+#[tokio::test]
+async fn test_prompts_are_pseudonymised_and_replies_reidentified() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let service = OpenAiService::new(AiConfig {
+        openai_api_key: Some("test-key".to_string()),
+        openai_base_url: mock_openai(seen.clone()),
+        ..AiConfig::default()
+    });
+
+    let reply = service
+        .analyze_document(
+            "Father (SSN 123-45-6789, dad@example.com) asked that the minor child, Ava Cole, stay with him.",
+            "declaration",
+        )
+        .await
+        .unwrap();
+
+    let sent = seen.lock().unwrap()[0].clone();
+    assert!(!sent.contains("123-45-6789") && !sent.contains("dad@example.com") && !sent.contains("Ava"));
+    assert!(sent.contains("[SSN_1]") && sent.contains("[EMAIL_1]"));
+
+    let analysis = reply.data.unwrap()["analysis"].as_str().unwrap().to_string();
+    assert!(analysis.contains("123-45-6789") && analysis.contains("dad@example.com"));
+    // Minors' names are never restored
+    assert!(analysis.contains("[MINOR_1]") && !analysis.contains("Ava"));
+}
+
+#[tokio::test]
+async fn test_exhibit_export_redacts_personal_data() {
+    let app = common::TestApp::new().await;
+    for statement in [
+        "INSERT INTO exhibits (id, exhibit_label, document_name, description, category, ai_extracted_text, case_id)
+         VALUES (7, 'R-7', 'School records', 'Report cards for the minor child, Ava Cole', 'education',
+         'Ava Cole, DOB 01/02/2019. Parent contact: (503) 555-0142, mom@example.com', 1)",
+        "INSERT INTO communications (id, communication_date, sender, medium, message_content, case_id)
+         VALUES (1, '2024-03-01', 'Parent A', 'voice_transcript', 'Recorded call', 1)",
+        "INSERT INTO transcript_segments (exhibit_id, communication_id, segment_index, start_ms, end_ms, text)
+         VALUES (7, 1, 0, 0, 4000, 'Ava said her SSN card, 123-45-6789, is at Mom''s.')",
+    ] {
+        sqlx::query(statement).execute(&app.pool).await.unwrap();
+    }
+
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    let token = app.access.create_auth_token("atty").await.unwrap();
+    common::consent_to(&app.consent, "atty", LegalOperationType::ClientDataProcessing).await;
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    let exported: Value = http
+        .get(format!("{}/api/exhibits/7/export", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let body = exported["exhibit"].to_string();
+    for value in ["Ava", "Cole", "555-0142", "mom@example.com", "123-45-6789", "01/02/2019"] {
+        assert!(!body.contains(value), "{} leaked into {}", value, body);
+    }
+    assert_eq!(exported["mode"], "redact");
+    assert_eq!(exported["exhibit"]["category"], "education");
+    assert_eq!(exported["pii_removed"]["SSN"], 1);
+
+    let pseudonymised: Value = http
+        .get(format!("{}/api/exhibits/7/export?mode=pseudonymize", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(pseudonymised["exhibit"]["transcript"][0].as_str().unwrap().contains("[SSN_1]"));
+    assert!(!pseudonymised["exhibit"].to_string().contains("Ava"));
+
+    let missing = http
+        .get(format!("{}/api/exhibits/8/export", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,201 @@
+use chrono::{Duration, Utc};
+use moodbridge_rust::ai::sentiment::{score_communications, LexiconToneScorer};
+use moodbridge_rust::ai::transcription::{store_transcript, Transcript, TranscriptSegment};
+use moodbridge_rust::db;
+use moodbridge_rust::legal::access_control::UserRole;
+use moodbridge_rust::legal::retention::purge_exhibit;
+use moodbridge_rust::legal::ethical_walls::MatterScope;
+use moodbridge_rust::search::FullTextIndex;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+#[tokio::test]
+async fn test_file_is_destroyed_only_after_hold_release_and_attorney_approval() {
+    let mut app = common::TestApp::new().await;
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    app.access.add_user(common::user("para", UserRole::Paralegal)).await.unwrap();
+    let token = app.access.create_auth_token("atty").await.unwrap();
+    let paralegal_token = app.access.create_auth_token("para").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("visitation-notes.txt");
+    std::fs::write(&path, "Visit notes from 2015").unwrap();
+    app.retention = app.retention.with_import_root(dir.path());
+    app.retention.link_file(&path, 12, Utc::now() - Duration::days(4000)).await.unwrap();
+
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    let scheduled: Value = http
+        .post(format!("{}/api/retention/files", base))
+        .bearer_auth(&token)
+        .json(&json!({ "file_path": path }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let item_id = scheduled["item"]["id"].as_i64().unwrap();
+    assert_eq!(scheduled["item"]["status"], "scheduled");
+
+    let hold: Value = http
+        .post(format!("{}/api/matters/12/holds", base))
+        .bearer_auth(&token)
+        .json(&json!({ "reason": "Appeal filed" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let hold_id = hold["hold"]["hold_id"].as_str().unwrap().to_string();
+
+    // Held matters stay out of the queue
+    let queue: Value = http
+        .get(format!("{}/api/retention/reviews", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(queue["items"].as_array().unwrap().is_empty());
+
+    let released = http
+        .post(format!("{}/api/retention/holds/{}/release", base, hold_id))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(released.status(), StatusCode::OK);
+
+    let queue: Value = http
+        .get(format!("{}/api/retention/reviews", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(queue["items"][0]["id"], item_id);
+
+    // Only attorneys review dispositions
+    let paralegal = http
+        .get(format!("{}/api/retention/reviews", base))
+        .bearer_auth(&paralegal_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(paralegal.status(), StatusCode::FORBIDDEN);
+
+    let approved = http
+        .post(format!("{}/api/retention/reviews/{}", base, item_id))
+        .bearer_auth(&token)
+        .json(&json!({ "decision": "approve", "notes": "Matter closed in 2016" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(approved.status(), StatusCode::OK);
+    assert!(path.exists());
+
+    let disposed: Value = http
+        .post(format!("{}/api/retention/dispose", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(!path.exists());
+    assert_eq!(disposed["certificates"][0]["approved_by"], "atty");
+    assert_eq!(disposed["certificates"][0]["method"], "File deleted");
+
+    let certificates: Value = http
+        .get(format!("{}/api/retention/certificates?case_id=12", base))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(certificates["certificates"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_only_files_imported_for_a_matter_can_be_scheduled() {
+    let mut app = common::TestApp::new().await;
+    app.access.add_user(common::attorney("atty")).await.unwrap();
+    let token = app.access.create_auth_token("atty").await.unwrap();
+
+    let root = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let unlinked = root.path().join("draft.txt");
+    let foreign = outside.path().join("server.key");
+    std::fs::write(&unlinked, "Draft").unwrap();
+    std::fs::write(&foreign, "Key").unwrap();
+    app.retention = app.retention.with_import_root(root.path());
+    let base = app.serve().await;
+    let http = reqwest::Client::new();
+
+    // Outside the root, missing and unlinked all look the same
+    let escape = root.path().join("..").join(outside.path().file_name().unwrap()).join("server.key");
+    for path in [foreign.clone(), escape, root.path().join("missing.txt"), unlinked] {
+        let response = http
+            .post(format!("{}/api/retention/files", base))
+            .bearer_auth(&token)
+            .json(&json!({ "file_path": path }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    assert!(foreign.exists());
+}
+
+#[tokio::test]
+async fn test_purging_a_recording_takes_its_transcript_audio_and_index_entries() {
+    let pool = db::create_pool("sqlite::memory:").await.unwrap();
+    db::run_migrations(&pool).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let transcript = Transcript {
+        text: "You will never see the children again".to_string(),
+        segments: vec![TranscriptSegment {
+            start_ms: 0,
+            end_ms: 2_000,
+            text: "You will never see the children again".to_string(),
+        }],
+        duration_ms: 2_000,
+        backend: "test".to_string(),
+        language: None,
+    };
+    let stored = store_transcript(&pool, b"voicemail", &transcript, Some("Parent B"), Some(1), dir.path().to_str().unwrap())
+        .await
+        .unwrap();
+    score_communications(&pool, &LexiconToneScorer::new(), None).await.unwrap();
+    let index = FullTextIndex::new(pool.clone());
+    index.sync().await.unwrap();
+    assert_eq!(index.search("children", 10, &MatterScope::unrestricted()).await.unwrap().total_hits, 2);
+
+    assert!(purge_exhibit(&pool, stored.exhibit_id).await.unwrap());
+
+    let communications: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM communications WHERE id = ?")
+        .bind(stored.communication_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let tone_scores: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM communication_tone_scores WHERE communication_id = ?")
+        .bind(stored.communication_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!((communications, tone_scores), (0, 0));
+    assert!(!std::path::Path::new(&stored.audio_path).exists());
+    // Gone from the index without waiting for the next sync
+    assert_eq!(index.search("children", 10, &MatterScope::unrestricted()).await.unwrap().total_hits, 0);
+}
@@ -0,0 +1,305 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use moodbridge_rust::auth::kms::{KmsError, KmsProvider};
+use moodbridge_rust::auth::vault::{HashiCorpVaultProvider, VaultAuth, VaultConfig};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Just enough of Vault's KV v2, Transit, token and AppRole APIs
+#[derive(Default)]
+struct MockVault {
+    kv: BTreeMap<String, Vec<Map<String, Value>>>,
+    tokens: HashSet<String>,
+    lease_seconds: u64,
+    logins: usize,
+    renewals: usize,
+    namespaces: Vec<Option<String>>,
+}
+
+type Shared = Arc<Mutex<MockVault>>;
+
+fn vault_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "errors": [message] }))).into_response()
+}
+
+async fn handle(
+    State(vault): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut vault = vault.lock().unwrap();
+    let path = uri.path().trim_start_matches("/v1/").to_string();
+    let query = uri.query().unwrap_or("").to_string();
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    vault.namespaces.push(
+        headers
+            .get("X-Vault-Namespace")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    );
+
+    if path == "auth/approle/login" {
+        if body["role_id"] != "role" || body["secret_id"] != "secret" {
+            return vault_error(StatusCode::BAD_REQUEST, "invalid role or secret ID");
+        }
+        vault.logins += 1;
+        let token = format!("approle-token-{}", vault.logins);
+        vault.tokens.insert(token.clone());
+        let lease = vault.lease_seconds;
+        return Json(json!({
+            "auth": { "client_token": token, "lease_duration": lease, "renewable": true }
+        }))
+        .into_response();
+    }
+
+    let token = headers
+        .get("X-Vault-Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !vault.tokens.contains(&token) {
+        return vault_error(StatusCode::FORBIDDEN, "permission denied");
+    }
+
+    match (method.as_str(), path.as_str()) {
+        ("GET", "auth/token/lookup-self") => {
+            Json(json!({ "data": { "ttl": 0, "renewable": false } })).into_response()
+        }
+        ("POST", "auth/token/renew-self") => {
+            vault.renewals += 1;
+            Json(json!({
+                "auth": { "client_token": token, "lease_duration": 3600, "renewable": true }
+            }))
+            .into_response()
+        }
+        ("GET", p) if p.starts_with("secret/data/") => {
+            let key = &p["secret/data/".len()..];
+            let versions = match vault.kv.get(key) {
+                Some(versions) => versions,
+                None => return vault_error(StatusCode::NOT_FOUND, ""),
+            };
+            let version = query
+                .strip_prefix("version=")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(versions.len());
+            match versions.get(version.wrapping_sub(1)) {
+                Some(data) => Json(json!({
+                    "data": {
+                        "data": data,
+                        "metadata": { "version": version, "created_time": "2024-01-01T00:00:00Z" }
+                    }
+                }))
+                .into_response(),
+                None => vault_error(StatusCode::NOT_FOUND, ""),
+            }
+        }
+        ("POST", p) if p.starts_with("secret/data/") => {
+            let key = p["secret/data/".len()..].to_string();
+            let current = vault.kv.get(&key).map(Vec::len).unwrap_or(0) as u64;
+            if let Some(cas) = body["options"]["cas"].as_u64() {
+                if cas != current {
+                    return vault_error(
+                        StatusCode::BAD_REQUEST,
+                        "check-and-set parameter did not match the current version",
+                    );
+                }
+            }
+            let data = body["data"].as_object().cloned().unwrap_or_default();
+            let versions = vault.kv.entry(key).or_default();
+            versions.push(data);
+            Json(json!({ "data": { "version": versions.len() } })).into_response()
+        }
+        ("GET", p) if p.starts_with("secret/metadata/") && query == "list=true" => {
+            let dir = &p["secret/metadata/".len()..];
+            let mut children: Vec<String> = vault
+                .kv
+                .keys()
+                .filter_map(|key| key.strip_prefix(dir))
+                .map(|rest| match rest.find('/') {
+                    Some(index) => rest[..=index].to_string(),
+                    None => rest.to_string(),
+                })
+                .collect();
+            children.dedup();
+            if children.is_empty() {
+                return vault_error(StatusCode::NOT_FOUND, "");
+            }
+            Json(json!({ "data": { "keys": children } })).into_response()
+        }
+        ("DELETE", p) if p.starts_with("secret/metadata/") => {
+            vault.kv.remove(&p["secret/metadata/".len()..]);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        ("POST", "transit/encrypt/moodbridge") => {
+            let plaintext = body["plaintext"].as_str().unwrap_or("");
+            Json(json!({ "data": { "ciphertext": format!("vault:v1:{}", plaintext) } })).into_response()
+        }
+        ("POST", "transit/decrypt/moodbridge") => {
+            let ciphertext = body["ciphertext"].as_str().unwrap_or("");
+            match ciphertext.strip_prefix("vault:v1:") {
+                Some(plaintext) => Json(json!({ "data": { "plaintext": plaintext } })).into_response(),
+                None => vault_error(StatusCode::BAD_REQUEST, "invalid ciphertext"),
+            }
+        }
+        ("POST", "transit/datakey/plaintext/moodbridge") => Json(json!({
+            "data": {
+                "plaintext": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+                "ciphertext": "vault:v1:wrapped-data-key"
+            }
+        }))
+        .into_response(),
+        _ => vault_error(StatusCode::NOT_FOUND, "no handler for route"),
+    }
+}
+
+async fn start_mock(vault: MockVault) -> (String, Shared) {
+    let shared = Arc::new(Mutex::new(vault));
+    let app = Router::new().fallback(handle).with_state(shared.clone());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service())
+            .await
+            .unwrap();
+    });
+    (format!("http://{}", addr), shared)
+}
+
+fn approle() -> VaultAuth {
+    VaultAuth::AppRole {
+        role_id: "role".to_string(),
+        secret_id: "secret".to_string(),
+        mount: "approle".to_string(),
+    }
+}
+
+fn root_token_mock() -> MockVault {
+    MockVault {
+        tokens: HashSet::from(["root".to_string()]),
+        ..MockVault::default()
+    }
+}
+
+#[tokio::test]
+async fn test_kv_versioned_reads_and_check_and_set() {
+    let (addr, _) = start_mock(root_token_mock()).await;
+    let vault = HashiCorpVaultProvider::new(VaultConfig::new(addr, VaultAuth::Token("root".to_string()))).unwrap();
+
+    let mut data = Map::new();
+    data.insert("value".to_string(), json!("first"));
+    assert_eq!(vault.write_secret("app/config", data.clone(), Some(0)).await.unwrap(), 1);
+
+    data.insert("value".to_string(), json!("second"));
+    assert_eq!(vault.write_secret("app/config", data.clone(), Some(1)).await.unwrap(), 2);
+
+    // A writer holding the stale version loses
+    let conflict = vault.write_secret("app/config", data, Some(1)).await;
+    assert!(matches!(conflict, Err(KmsError::VersionConflict(_))));
+
+    let latest = vault.read_secret("app/config", None).await.unwrap();
+    assert_eq!((latest.version, latest.data["value"].clone()), (2, json!("second")));
+    let first = vault.read_secret("app/config", Some(1)).await.unwrap();
+    assert_eq!(first.data["value"], json!("first"));
+
+    assert!(matches!(vault.read_secret("missing", None).await, Err(KmsError::KeyNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_approle_login_uses_namespace_and_relogs_after_revocation() {
+    let (addr, mock) = start_mock(MockVault {
+        lease_seconds: 3600,
+        ..MockVault::default()
+    })
+    .await;
+    let config = VaultConfig::new(addr, approle()).with_namespace("legal/prod");
+    let vault = HashiCorpVaultProvider::new(config).unwrap();
+
+    vault.encrypt_and_store("oauth/token", "abc").await.unwrap();
+    assert_eq!(mock.lock().unwrap().logins, 1);
+
+    // Vault forgets the token, e.g. after a restart of a dev server
+    mock.lock().unwrap().tokens.clear();
+    assert_eq!(vault.retrieve_and_decrypt("oauth/token").await.unwrap(), "abc");
+
+    let mock = mock.lock().unwrap();
+    assert_eq!(mock.logins, 2);
+    assert!(mock.namespaces.iter().all(|ns| ns.as_deref() == Some("legal/prod")));
+}
+
+#[tokio::test]
+async fn test_token_is_renewed_before_its_lease_expires() {
+    let (addr, mock) = start_mock(MockVault {
+        lease_seconds: 1,
+        ..MockVault::default()
+    })
+    .await;
+    let vault = HashiCorpVaultProvider::new(VaultConfig::new(addr, approle())).unwrap();
+
+    vault.encrypt_and_store("k", "v").await.unwrap();
+    assert_eq!(mock.lock().unwrap().renewals, 0);
+
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    assert!(vault.key_exists("k").await.unwrap());
+
+    let mock = mock.lock().unwrap();
+    assert_eq!((mock.logins, mock.renewals), (1, 1));
+}
+
+#[tokio::test]
+async fn test_transit_encrypts_values_before_they_reach_kv() {
+    let (addr, mock) = start_mock(root_token_mock()).await;
+    let config = VaultConfig::new(addr, VaultAuth::Token("root".to_string())).with_transit("transit", "moodbridge");
+    let vault = HashiCorpVaultProvider::new(config).unwrap();
+
+    vault.encrypt_and_store("crypto_db/master_key_v1", "key-material").await.unwrap();
+    vault.encrypt_and_store("crypto_db/nested/index_key", "index").await.unwrap();
+    vault.encrypt_and_store("oauth/other", "x").await.unwrap();
+
+    let stored = mock.lock().unwrap().kv["crypto_db/master_key_v1"][0].clone();
+    assert!(stored.get("value").is_none());
+    assert!(stored["ciphertext"].as_str().unwrap().starts_with("vault:v1:"));
+
+    assert_eq!(vault.retrieve_and_decrypt("crypto_db/master_key_v1").await.unwrap(), "key-material");
+    assert_eq!(
+        vault.list_keys("crypto_db/").await.unwrap(),
+        vec!["crypto_db/master_key_v1", "crypto_db/nested/index_key"]
+    );
+
+    let data_key = vault.generate_data_key().await.unwrap();
+    assert_eq!(data_key.plaintext.len(), 32);
+
+    vault.delete_key("oauth/other").await.unwrap();
+    assert!(!vault.key_exists("oauth/other").await.unwrap());
+}
+
+/// Run with a dev server: `vault server -dev -dev-root-token-id=root`, then
+/// `VAULT_ADDR=http://127.0.0.1:8200 VAULT_TOKEN=root cargo test -- --ignored`
+#[tokio::test]
+#[ignore]
+async fn test_against_dev_mode_vault() {
+    let config = VaultConfig::from_env().unwrap().expect("VAULT_ADDR must be set");
+    let vault = HashiCorpVaultProvider::new(config).unwrap();
+
+    let key = format!("moodbridge-test/{}", uuid::Uuid::new_v4());
+    vault.encrypt_and_store(&key, "dev-secret").await.unwrap();
+    assert_eq!(vault.retrieve_and_decrypt(&key).await.unwrap(), "dev-secret");
+
+    let mut data = Map::new();
+    data.insert("value".to_string(), json!("next"));
+    assert!(matches!(vault.write_secret(&key, data.clone(), Some(0)).await, Err(KmsError::VersionConflict(_))));
+    assert_eq!(vault.write_secret(&key, data, Some(1)).await.unwrap(), 2);
+
+    vault.delete_key(&key).await.unwrap();
+    assert!(!vault.key_exists(&key).await.unwrap());
+}